@@ -0,0 +1,158 @@
+//! The build cache, which keeps the dependencies of a project built between invocations of the compiler
+//!
+//! A dependency is stored in `target/cache/` under a fingerprint of everything it was built from: the versions of the
+//! compiler and of the cache format, the `flux.toml` of the project it belongs to, the path and text of every source
+//! file in its `src` directory, and the fingerprints of the packages it depends on. Since the ids of packages are
+//! written into the items and bodies that refer to them, the id the dependency was built with is part of its
+//! fingerprint too.
+//!
+//! Only dependencies that built without errors are stored, so loading one never reports anything, not even warnings.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bincode::Options;
+use flux_hir::Package;
+use flux_id::id;
+use flux_mir::PackageMir;
+use flux_util::{words::with_interner, FileId, Interner};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use crate::{cfg::CFG_FILE_NAME, source_files};
+
+/// Where the build cache is kept in a project
+pub(crate) const CACHE_DIR: &str = "target/cache";
+
+/// Cache entries are versioned, so that ones written by a compiler that encoded or lowered packages differently are
+/// built again rather than loaded
+///
+/// This has to be bumped whenever a change to the compiler changes what it would store for the same sources, like a
+/// change to the layout of the HIR or MIR, or to how they are lowered. The version of the `flux` crate is part of the
+/// fingerprint too, but isn't enough on its own, since it doesn't change between the commits that make such changes.
+const CACHE_VERSION: u32 = 2;
+
+/// Everything the compiler needs of a package that it would otherwise have to build
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedPackage {
+    pub package: Package,
+    pub mir: PackageMir,
+    /// The files of every module in the package, starting with its entry
+    pub files: Vec<FileId>,
+}
+
+pub(crate) struct Cache {
+    dir: PathBuf,
+    interner: &'static Interner,
+    /// The entries that were loaded or stored
+    used: Vec<PathBuf>,
+}
+
+impl Cache {
+    pub fn new(project_root: &Path, interner: &'static Interner) -> Self {
+        Self {
+            dir: project_root.join(CACHE_DIR),
+            interner,
+            used: vec![],
+        }
+    }
+
+    /// Load a package from the cache, if it was stored with the same fingerprint
+    ///
+    /// Entries that can't be read are treated as missing, and built again.
+    pub fn load(&mut self, name: &str, fingerprint: u64) -> Option<CachedPackage> {
+        let path = self.entry_path(name, fingerprint);
+        let bytes = fs::read(&path).ok()?;
+        match with_interner(self.interner, || bincode_options().deserialize(&bytes)) {
+            Ok(cached) => {
+                info!(package =? name, "loaded package from the build cache");
+                self.used.push(path);
+                Some(cached)
+            }
+            _ => {
+                warn!(path =? path, "ignoring malformed build cache entry");
+                None
+            }
+        }
+    }
+
+    /// Store a package in the cache
+    ///
+    /// A cache that can't be written to only means packages are built again next time, so failures are not reported.
+    pub fn store(&mut self, name: &str, fingerprint: u64, cached: &CachedPackage) {
+        let path = self.entry_path(name, fingerprint);
+        let bytes = match with_interner(self.interner, || bincode_options().serialize(cached)) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!(package =? name, err =? err, "could not serialize package for the build cache");
+                return;
+            }
+        };
+        if let Err(err) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, bytes)) {
+            warn!(path =? path, err =? err, "could not write build cache entry");
+            return;
+        }
+        info!(package =? name, "stored package in the build cache");
+        self.used.push(path);
+    }
+
+    /// Remove the entries that weren't loaded or stored, which were built from sources that have since changed
+    pub fn remove_unused(self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        for stale in entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| !self.used.contains(path))
+        {
+            let _ = fs::remove_file(stale);
+        }
+    }
+
+    fn entry_path(&self, name: &str, fingerprint: u64) -> PathBuf {
+        self.dir.join(format!("{name}-{fingerprint:016x}.bin"))
+    }
+}
+
+/// Entries are written with variable-length integers, and an entry with anything left over after the package is
+/// treated as malformed
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new().reject_trailing_bytes()
+}
+
+/// Fingerprint a package from the compiler and cache versions, its sources, its project's `flux.toml`, and the fingerprints of its
+/// dependencies
+pub(crate) fn fingerprint(
+    project_root: &Path,
+    package_root: &Path,
+    package_id: id::Pkg,
+    dependencies: impl Iterator<Item = u64>,
+) -> u64 {
+    let mut hasher = Sha256::new();
+    // Every variable-length part is preceded by its length, so that neighbouring parts can't run into each other
+    let mut write = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    write(env!("CARGO_PKG_VERSION").as_bytes());
+    write(&CACHE_VERSION.to_le_bytes());
+    write(&fs::read(project_root.join(CFG_FILE_NAME)).unwrap_or_default());
+    write(&Into::<u32>::into(package_id).to_le_bytes());
+    for dependency in dependencies {
+        write(&dependency.to_le_bytes());
+    }
+
+    let mut files = vec![];
+    source_files(&package_root.join("src"), &mut files);
+    files.sort();
+    for file in files {
+        write(file.as_os_str().as_encoded_bytes());
+        write(&fs::read(&file).unwrap_or_default());
+    }
+    let digest = hasher.finalize();
+    u64::from_le_bytes(digest[..8].try_into().unwrap())
+}
@@ -1,15 +1,43 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, ops::Range};
 
+use flux_diagnostics::{closest_name, Diagnostic, Lint, LintLevel, ToDiagnostic};
+use flux_util::{FileId, InFile, Span};
+use semver::VersionReq;
 use serde::Deserialize;
-use toml::Value;
+use value::{Key, Value, ValueKind};
+
+use crate::{
+    diagnostics::ConfigError,
+    git::{GitReference, GitSource},
+};
+
+mod value;
 
 pub const CFG_FILE_NAME: &'static str = "flux.toml";
 
+/// The name of the user config, which is in the flux home directory
+pub const USER_CFG_FILE_NAME: &str = "config.toml";
+
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct Config {
     pub packages: Vec<Package>,
+    /// Whether the packages are those of a `[workspace]`, each of which is in a directory named after it
+    pub workspace: bool,
     pub build: Build,
     pub dependencies: Dependencies,
+    #[serde(skip)]
+    pub lints: Lints,
+    /// The registry that dependencies are fetched from, relative to the project root
+    #[serde(skip)]
+    pub registry: Option<String>,
+}
+
+/// The settings of a user that apply to every project they build
+#[derive(Debug, Default, Clone)]
+pub struct UserConfig {
+    /// The registry that dependencies are fetched from, unless a project has one of its own, relative to the flux home
+    /// directory
+    pub registry: Option<String>,
 }
 
 #[derive(Deserialize, Debug, Default, Clone)]
@@ -52,7 +80,8 @@ impl Default for BuildType {
 
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct Dependencies {
-    pub map: HashMap<String, Dependency>,
+    /// Ordered by name, so that dependencies are built in the same order, and given the same ids, every time
+    pub map: BTreeMap<String, Dependency>,
 }
 
 impl Dependencies {
@@ -61,109 +90,597 @@ impl Dependencies {
     }
 }
 
+/// A dependency is either a project at a `path`, a project in a `git` repository, or a package from the registry with
+/// a `version` that matches its requirement
+///
+/// A path or git dependency can also have a `version` requirement, which the package it points at has to match.
 #[derive(Deserialize, Debug, Default, Clone)]
 pub struct Dependency {
     pub path: Option<String>,
+    #[serde(skip)]
+    pub git: Option<GitSource>,
+    #[serde(skip)]
+    pub version: Option<VersionReq>,
+}
+
+/// The levels lints are reported at, by the name of the lint
+///
+/// Names are checked once the config has been parsed, so that an unknown lint can be reported like any other error.
+#[derive(Debug, Default, Clone)]
+pub struct Lints {
+    pub map: BTreeMap<String, LintLevel>,
+}
+
+impl Lints {
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &LintLevel)> {
+        self.map.iter()
+    }
+}
+
+/// Parse the contents of a `flux.toml`
+///
+/// Like a source file, a config is parsed as far as it can be, along with everything that is wrong with it. Keys that
+/// aren't known are only warned about, since they are ignored either way.
+pub fn parse_cfg(content: &str, file_id: FileId) -> (Config, Vec<Diagnostic>) {
+    let mut parser = CfgParser {
+        file_id,
+        diagnostics: vec![],
+    };
+    let cfg = parser.parse(content);
+    (cfg, parser.diagnostics)
+}
+
+/// Parse the contents of a user config, which is checked the same way as a `flux.toml`
+pub fn parse_user_cfg(content: &str, file_id: FileId) -> (UserConfig, Vec<Diagnostic>) {
+    let mut parser = CfgParser {
+        file_id,
+        diagnostics: vec![],
+    };
+    let cfg = parser.parse_user(content);
+    (cfg, parser.diagnostics)
 }
 
-pub fn parse_cfg(content: &str) -> Config {
-    let value: Value = toml::from_str(&content).expect("could not parse config file");
-
-    let mut cfg = Config::default();
-
-    if let Some(package_settings) = value.get("package") {
-        let mut pkg = Package::default();
-
-        let name = package_settings
-            .get("name")
-            .unwrap_or_else(|| panic!("missing name field in package settings"))
-            .as_str()
-            .expect("expected name type to be a string");
-        let version = package_settings
-            .get("version")
-            .unwrap_or_else(|| panic!("missing version field in package settings"))
-            .as_str()
-            .expect("expected version type to be a string");
-
-        pkg.name = name.to_string();
-        pkg.version = version.to_string();
-        cfg.packages = vec![pkg];
-    } else if let Some(workspace_settings) = value.get("workspace") {
-        if !cfg.packages.is_empty() {
-            panic!("cannot define package settings and workspace settings in the same flux.toml (they are mutually exclusive)");
-        }
-
-        let packages = workspace_settings.get("packages").map_or_else(
-            || panic!("expected packages list in workspace settings"),
-            |packages| {
-                let packages: Vec<_> = packages
-                    .as_array()
-                    .expect("expected packages setting to be an array")
-                    .iter()
-                    .map(|package| {
-                        let mut pkg = Package::default();
-                        pkg.name = package
-                            .as_str()
-                            .expect("expected package to be a string")
-                            .to_string();
-                        pkg
-                    })
-                    .collect();
-                packages
-            },
+struct CfgParser {
+    file_id: FileId,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl CfgParser {
+    fn parse(&mut self, content: &str) -> Config {
+        let mut cfg = Config::default();
+        let Some(root) = self.root(content) else {
+            return cfg;
+        };
+        self.check_keys(
+            &root,
+            &[
+                "package",
+                "workspace",
+                "build",
+                "dependencies",
+                "lints",
+                "registry",
+            ],
+            &format!("`{CFG_FILE_NAME}`"),
         );
 
-        cfg.packages = packages;
-    }
-    if let Some(build_settings) = value.get("build") {
-        if let Some(opt_level) = build_settings.get("opt-level") {
-            let level = match opt_level
-                .as_integer()
-                .expect("expected opt-level to be integer")
-            {
-                0 => OptLevel::None,
-                1 => OptLevel::Low,
-                2 => OptLevel::Medium,
-                3 => OptLevel::High,
-                l => panic!("invalid opt-level `{l}`"),
+        match (get(&root, "package"), get(&root, "workspace")) {
+            (Some((package_key, _)), Some((workspace_key, _))) => self.conflicting_keys(
+                package_key,
+                workspace_key,
+                "a project is either a single package, or a workspace of packages",
+            ),
+            (Some((key, package)), None) => {
+                cfg.packages = self.package(key, package).into_iter().collect()
+            }
+            (None, Some((key, workspace))) => {
+                cfg.packages = self.workspace(key, workspace);
+                cfg.workspace = true;
+            }
+            (None, None) => self.report(ConfigError::MissingConfigKey {
+                table: (),
+                table_file_span: self.file_span(None),
+                missing: "a `[package]` or `[workspace]` table".to_string(),
+            }),
+        }
+        if let Some((key, build)) = get(&root, "build") {
+            cfg.build = self.build(key, build);
+        }
+        if let Some((key, dependencies)) = get(&root, "dependencies") {
+            cfg.dependencies = self.dependencies(key, dependencies);
+        }
+        if let Some((key, lints)) = get(&root, "lints") {
+            cfg.lints = self.lints(key, lints);
+        }
+        if let Some((key, registry)) = get(&root, "registry") {
+            cfg.registry = self.registry(key, registry);
+        }
+        cfg
+    }
+
+    fn parse_user(&mut self, content: &str) -> UserConfig {
+        let mut cfg = UserConfig::default();
+        let Some(root) = self.root(content) else {
+            return cfg;
+        };
+        self.check_keys(&root, &["registry"], &format!("`{USER_CFG_FILE_NAME}`"));
+        if let Some((key, registry)) = get(&root, "registry") {
+            cfg.registry = self.registry(key, registry);
+        }
+        cfg
+    }
+
+    /// The keys of the file, unless it isn't valid toml
+    fn root(&mut self, content: &str) -> Option<Vec<(Key, Value)>> {
+        let root = match value::parse(content) {
+            Ok(root) => root,
+            Err(err) => {
+                self.report(ConfigError::CouldNotParseConfigFile {
+                    problem: err.message().to_string(),
+                    problem_file_span: self.file_span(err.span()),
+                });
+                return None;
+            }
+        };
+        match root.kind {
+            ValueKind::Table(root) => Some(root),
+            _ => None,
+        }
+    }
+
+    fn package(&mut self, key: &Key, value: &Value) -> Option<Package> {
+        let table = self.table(value)?;
+        self.check_keys(table, &["name", "version"], "`[package]`");
+        let name = self
+            .required(key, table, "name")
+            .and_then(|name| self.string(name));
+        let version = self
+            .required(key, table, "version")
+            .and_then(|version| self.version(version));
+        Some(Package {
+            name: name?.to_string(),
+            version: version?.to_string(),
+        })
+    }
+
+    fn workspace(&mut self, key: &Key, value: &Value) -> Vec<Package> {
+        let Some(table) = self.table(value) else {
+            return vec![];
+        };
+        self.check_keys(table, &["packages"], "`[workspace]`");
+        let Some(packages) = self
+            .required(key, table, "packages")
+            .and_then(|packages| self.array(packages))
+        else {
+            return vec![];
+        };
+        packages
+            .iter()
+            .filter_map(|package| self.string(package))
+            .map(|name| Package {
+                name: name.to_string(),
+                ..Package::default()
+            })
+            .collect()
+    }
+
+    fn build(&mut self, _key: &Key, value: &Value) -> Build {
+        let mut build = Build::default();
+        let Some(table) = self.table(value) else {
+            return build;
+        };
+        self.check_keys(table, &["opt-level", "type"], "`[build]`");
+        if let Some((_, opt_level)) = get(table, "opt-level") {
+            let level = match self.integer(opt_level) {
+                Some(0) => Some(OptLevel::None),
+                Some(1) => Some(OptLevel::Low),
+                Some(2) => Some(OptLevel::Medium),
+                Some(3) => Some(OptLevel::High),
+                Some(level) => {
+                    self.invalid_value(opt_level, "0, 1, 2 or 3", format!("`{level}`"));
+                    None
+                }
+                None => None,
             };
-            cfg.build.opt_level = level;
-        }
-        if let Some(build_type) = build_settings.get("type") {
-            let ty = match build_type
-                .as_str()
-                .expect("expected build type to be string")
-            {
-                "debug" => BuildType::Debug,
-                "release" => BuildType::Release,
-                t => panic!("invalid build type `{t}`"),
+            build.opt_level = level.unwrap_or_default();
+        }
+        if let Some((_, build_type)) = get(table, "type") {
+            let ty = match self.string(build_type) {
+                Some("debug") => Some(BuildType::Debug),
+                Some("release") => Some(BuildType::Release),
+                Some(ty) => {
+                    self.invalid_value(
+                        build_type,
+                        "`\"debug\"` or `\"release\"`",
+                        format!("`\"{ty}\"`"),
+                    );
+                    None
+                }
+                None => None,
             };
-            cfg.build.ty = ty;
+            build.ty = ty.unwrap_or_default();
         }
+        build
     }
-    if let Some(dependencies) = value.get("dependencies") {
-        let table = dependencies.as_table().unwrap();
-        let dependencies_map = table
+
+    fn dependencies(&mut self, _key: &Key, value: &Value) -> Dependencies {
+        let Some(table) = self.table(value) else {
+            return Dependencies::default();
+        };
+        let map = table
             .iter()
-            .map(|(key, value)| {
-                let dependency = value.as_table().unwrap();
-                let path = dependency
-                    .get("path")
-                    .expect("dependency must have path property")
-                    .as_str()
-                    .expect("depdendency path must be string");
-                (
-                    key.clone(),
-                    Dependency {
-                        path: Some(path.to_string()),
-                    },
-                )
+            .filter_map(|(key, dependency)| {
+                // `name = "1.2"` is short for `name = { version = "1.2" }`
+                if let ValueKind::String(_) = &dependency.kind {
+                    let version = self.version_req(dependency)?;
+                    return Some((
+                        key.name.clone(),
+                        Dependency {
+                            path: None,
+                            git: None,
+                            version: Some(version),
+                        },
+                    ));
+                }
+                let dependency_table = self.table(dependency)?;
+                self.check_keys(
+                    dependency_table,
+                    &["path", "git", "branch", "tag", "rev", "version"],
+                    &format!("`[dependencies.{}]`", key.name),
+                );
+                let path = get(dependency_table, "path");
+                let git = get(dependency_table, "git");
+                let version = get(dependency_table, "version");
+                if path.is_none() && git.is_none() && version.is_none() {
+                    self.report(ConfigError::MissingConfigKey {
+                        table: (),
+                        table_file_span: self.file_span(key.span.clone()),
+                        missing: "`path`, `git` or `version`".to_string(),
+                    });
+                    return None;
+                }
+                if let (Some((path_key, _)), Some((git_key, _))) = (path, git) {
+                    self.conflicting_keys(
+                        path_key,
+                        git_key,
+                        "a dependency is either at a path, or in a git repository",
+                    );
+                    return None;
+                }
+                let path = match path {
+                    Some((_, path)) => Some(self.string(path)?.to_string()),
+                    None => None,
+                };
+                let git = self.git_source(key, dependency_table)?;
+                let version = match version {
+                    Some((_, version)) => Some(self.version_req(version)?),
+                    None => None,
+                };
+                Some((key.name.clone(), Dependency { path, git, version }))
             })
             .collect();
-        cfg.dependencies = Dependencies {
-            map: dependencies_map,
+        Dependencies { map }
+    }
+
+    /// The repository of a dependency, and what to check out of it, if it has one
+    ///
+    /// Returns `None` if what to check out is wrong, once that has been reported, and `Some(None)` if the dependency
+    /// isn't in a repository.
+    fn git_source(
+        &mut self,
+        key: &Key,
+        dependency_table: &[(Key, Value)],
+    ) -> Option<Option<GitSource>> {
+        let references: Vec<_> = ["branch", "tag", "rev"]
+            .into_iter()
+            .filter_map(|name| get(dependency_table, name))
+            .collect();
+        if let [first, second, ..] = references[..] {
+            self.conflicting_keys(
+                &first.0,
+                &second.0,
+                "a git dependency is checked out at one branch, tag or revision",
+            );
+            return None;
+        }
+        let Some((_, url)) = get(dependency_table, "git") else {
+            if !references.is_empty() {
+                self.report(ConfigError::MissingConfigKey {
+                    table: (),
+                    table_file_span: self.file_span(key.span.clone()),
+                    missing: "`git`".to_string(),
+                });
+                return None;
+            }
+            return Some(None);
+        };
+        let url = self.string(url)?.to_string();
+        let reference = match references.first() {
+            None => GitReference::DefaultBranch,
+            Some((reference_key, reference)) => {
+                let reference = self.string(reference)?.to_string();
+                match reference_key.name.as_str() {
+                    "branch" => GitReference::Branch(reference),
+                    "tag" => GitReference::Tag(reference),
+                    _ => GitReference::Rev(reference),
+                }
+            }
         };
+        Some(Some(GitSource { url, reference }))
     }
 
-    cfg
+    fn registry(&mut self, key: &Key, value: &Value) -> Option<String> {
+        let table = self.table(value)?;
+        self.check_keys(table, &["path"], "`[registry]`");
+        let path = self.required(key, table, "path")?;
+        self.string(path).map(str::to_string)
+    }
+
+    fn lints(&mut self, _key: &Key, value: &Value) -> Lints {
+        let Some(table) = self.table(value) else {
+            return Lints::default();
+        };
+        let map = table
+            .iter()
+            .filter_map(|(key, level)| {
+                if Lint::from_name(&key.name).is_none() {
+                    self.report(ConfigError::UnknownLint {
+                        lint: key.name.clone(),
+                        lint_file_span: self.file_span(key.span.clone()),
+                        candidate: closest_name(
+                            &key.name,
+                            Lint::ALL.iter().map(|lint| lint.name()),
+                        )
+                        .map(str::to_string),
+                    });
+                    return None;
+                }
+                let name = self.string(level)?;
+                let Some(level_value) = LintLevel::from_name(name) else {
+                    self.invalid_value(
+                        level,
+                        "`\"allow\"`, `\"warn\"` or `\"deny\"`",
+                        format!("`\"{name}\"`"),
+                    );
+                    return None;
+                };
+                Some((key.name.clone(), level_value))
+            })
+            .collect();
+        Lints { map }
+    }
+
+    /// Warn about the keys of a table that aren't any of the `known` ones
+    fn check_keys(&mut self, table: &[(Key, Value)], known: &[&str], table_name: &str) {
+        for (key, _) in table {
+            if known.contains(&key.name.as_str()) {
+                continue;
+            }
+            self.report(ConfigError::UnknownConfigKey {
+                key: key.name.clone(),
+                key_file_span: self.file_span(key.span.clone()),
+                table: table_name.to_string(),
+                candidate: closest_name(&key.name, known.iter().copied()).map(str::to_string),
+            });
+        }
+    }
+
+    /// The value of a key that the table under `table_key` must have
+    fn required<'a>(
+        &mut self,
+        table_key: &Key,
+        table: &'a [(Key, Value)],
+        key: &str,
+    ) -> Option<&'a Value> {
+        let value = get(table, key).map(|(_, value)| value);
+        if value.is_none() {
+            self.report(ConfigError::MissingConfigKey {
+                table: (),
+                table_file_span: self.file_span(table_key.span.clone()),
+                missing: format!("`{key}`"),
+            });
+        }
+        value
+    }
+
+    fn table<'a>(&mut self, value: &'a Value) -> Option<&'a [(Key, Value)]> {
+        match &value.kind {
+            ValueKind::Table(table) => Some(table),
+            kind => {
+                self.invalid_value(value, "a table", kind.description().to_string());
+                None
+            }
+        }
+    }
+
+    fn array<'a>(&mut self, value: &'a Value) -> Option<&'a [Value]> {
+        match &value.kind {
+            ValueKind::Array(values) => Some(values),
+            kind => {
+                self.invalid_value(value, "an array", kind.description().to_string());
+                None
+            }
+        }
+    }
+
+    fn string<'a>(&mut self, value: &'a Value) -> Option<&'a str> {
+        match &value.kind {
+            ValueKind::String(string) => Some(string),
+            kind => {
+                self.invalid_value(value, "a string", kind.description().to_string());
+                None
+            }
+        }
+    }
+
+    fn integer(&mut self, value: &Value) -> Option<i64> {
+        match &value.kind {
+            ValueKind::Integer(integer) => Some(*integer),
+            kind => {
+                self.invalid_value(value, "an integer", kind.description().to_string());
+                None
+            }
+        }
+    }
+
+    /// A semantic version, like `0.1.0`
+    fn version<'a>(&mut self, value: &'a Value) -> Option<&'a str> {
+        let version = self.string(value)?;
+        match semver::Version::parse(version) {
+            Ok(_) => Some(version),
+            Err(err) => {
+                self.invalid_value(
+                    value,
+                    "a semantic version like `\"0.1.0\"`",
+                    format!("`\"{version}\"`: {err}"),
+                );
+                None
+            }
+        }
+    }
+
+    /// A requirement on the version of a dependency, like `1.2` or `>=1.2, <1.5`
+    fn version_req(&mut self, value: &Value) -> Option<VersionReq> {
+        let requirement = self.string(value)?;
+        match VersionReq::parse(requirement) {
+            Ok(requirement) => Some(requirement),
+            Err(err) => {
+                self.invalid_value(
+                    value,
+                    "a version requirement like `\"1.2\"`",
+                    format!("`\"{requirement}\"`: {err}"),
+                );
+                None
+            }
+        }
+    }
+
+    fn invalid_value(&mut self, value: &Value, expected: &str, found: String) {
+        self.report(ConfigError::InvalidConfigValue {
+            value: (),
+            value_file_span: self.file_span(value.span.clone()),
+            expected: expected.to_string(),
+            found,
+        });
+    }
+
+    fn conflicting_keys(&mut self, first: &Key, second: &Key, reason: &str) {
+        self.report(ConfigError::ConflictingConfigKeys {
+            first: first.name.clone(),
+            first_file_span: self.file_span(first.span.clone()),
+            second: second.name.clone(),
+            second_file_span: self.file_span(second.span.clone()),
+            reason: reason.to_string(),
+        });
+    }
+
+    fn report(&mut self, error: ConfigError) {
+        self.diagnostics.push(error.to_diagnostic());
+    }
+
+    /// Values without a span, like implicit tables, are pointed at the start of the file
+    fn file_span(&self, span: Option<Range<usize>>) -> InFile<Span> {
+        let span = span.unwrap_or(0..0);
+        InFile::new(Span::new(span.start as u32..span.end as u32), self.file_id)
+    }
+}
+
+fn get<'a>(table: &'a [(Key, Value)], key: &str) -> Option<&'a (Key, Value)> {
+    table.iter().find(|(table_key, _)| table_key.name == key)
+}
+
+#[cfg(test)]
+mod tests {
+    use flux_diagnostics::DiagnosticCode;
+    use flux_util::FileId;
+
+    use super::*;
+
+    fn parse(content: &str) -> (Config, Vec<Diagnostic>) {
+        parse_cfg(content, FileId::poisoned())
+    }
+
+    /// The codes of the diagnostics, along with the text their spans point at
+    fn problems<'a>(
+        content: &'a str,
+        diagnostics: &[Diagnostic],
+    ) -> Vec<(DiagnosticCode, &'a str)> {
+        diagnostics
+            .iter()
+            .map(|diagnostic| {
+                let range = diagnostic.offset.span.range;
+                let text = &content[usize::from(range.start())..usize::from(range.end())];
+                (diagnostic.code, text)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_config() {
+        let content = "[package]\nname = \"app\"\nversion = \"1.2.0-beta.1\"\n\n[build]\nopt-level = 3\ntype = \"release\"\n\n[dependencies]\njson = \"1.2\"\nshapes = { git = \"https://example.com/shapes.git\", branch = \"main\" }\n\n[dependencies.std]\npath = \"../std\"\n\n[lints]\nunused_locals = \"deny\"\n";
+        let (cfg, diagnostics) = parse(content);
+        assert!(diagnostics.is_empty(), "{diagnostics:?}");
+        assert!(!cfg.workspace);
+        assert_eq!(cfg.packages[0].name, "app");
+        assert!(matches!(cfg.build.opt_level, OptLevel::High));
+        assert!(matches!(cfg.build.ty, BuildType::Release));
+        assert_eq!(cfg.dependencies.map["std"].path.as_deref(), Some("../std"));
+        assert_eq!(
+            cfg.dependencies.map["shapes"].git,
+            Some(GitSource {
+                url: "https://example.com/shapes.git".to_string(),
+                reference: GitReference::Branch("main".to_string()),
+            })
+        );
+        let json = cfg.dependencies.map["json"].version.as_ref().unwrap();
+        assert!(json.matches(&semver::Version::new(1, 4, 0)));
+        assert_eq!(cfg.lints.map.len(), 1);
+    }
+
+    #[test]
+    fn problems_point_at_their_keys_and_values() {
+        let content = "[package]\nname = \"app\"\nverison = \"1.0.0\"\ncreated = 1979-05-27\n\n[build]\nopt-level = 7\n";
+        let (_, diagnostics) = parse(content);
+        assert_eq!(
+            problems(content, &diagnostics),
+            [
+                (DiagnosticCode::UnknownConfigKey, "verison"),
+                (DiagnosticCode::UnknownConfigKey, "created"),
+                (DiagnosticCode::MissingConfigKey, "package"),
+                (DiagnosticCode::InvalidConfigValue, "7"),
+            ]
+        );
+        assert_eq!(diagnostics[0].suggestions()[0].replacement, "version");
+        assert!(!diagnostics[0].is_error());
+
+        let content = "[package]\nname = \"app\"\nversion = \"1.0\"\n\n[dependencies]\njson = \"~>1\"\nstd = {}\n";
+        let (_, diagnostics) = parse(content);
+        assert_eq!(
+            problems(content, &diagnostics),
+            [
+                (DiagnosticCode::InvalidConfigValue, "\"1.0\""),
+                (DiagnosticCode::InvalidConfigValue, "\"~>1\""),
+                (DiagnosticCode::MissingConfigKey, "std"),
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].labels[0].inner.inner,
+            "expected a semantic version like `\"0.1.0\"`, found `\"1.0\"`: unexpected end of input while parsing minor version number"
+        );
+
+        let content = "[package]\nname = \"app\"\nversion = \"1.0.0\"\n\n[dependencies]\na = { path = \"../a\", git = \"../a.git\" }\nb = { git = \"../b.git\", tag = \"v1\", rev = \"abc\" }\nc = { branch = \"main\" }\n";
+        let (_, diagnostics) = parse(content);
+        assert_eq!(
+            problems(content, &diagnostics),
+            [
+                (DiagnosticCode::ConflictingConfigKeys, "git"),
+                (DiagnosticCode::ConflictingConfigKeys, "rev"),
+                (DiagnosticCode::MissingConfigKey, "c"),
+            ]
+        );
+
+        let content = "[package]\nname = \"app\nversion = \"1.0.0\"\n";
+        let (_, diagnostics) = parse(content);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::CouldNotParseConfigFile);
+    }
 }
@@ -0,0 +1,108 @@
+//! TOML values along with where they are in the file they were read from
+//!
+//! `toml::Value` forgets its spans, so the config is read into these instead, for diagnostics to point at. They are
+//! taken from a `toml_edit` document, which keeps them, and the values it has no span for, like the implicit
+//! `dependencies` table of `[dependencies.std]`, go without one.
+
+use std::ops::Range;
+
+use toml_edit::{ImDocument, Item, TableLike, TomlError};
+
+#[derive(Debug)]
+pub(super) struct Value {
+    pub span: Option<Range<usize>>,
+    pub kind: ValueKind,
+}
+
+#[derive(Debug)]
+pub(super) enum ValueKind {
+    String(String),
+    Integer(i64),
+    Float,
+    Boolean,
+    Datetime,
+    Array(Vec<Value>),
+    Table(Vec<(Key, Value)>),
+}
+
+#[derive(Debug)]
+pub(super) struct Key {
+    pub name: String,
+    pub span: Option<Range<usize>>,
+}
+
+impl ValueKind {
+    /// What kind of value this is, as it is described in diagnostics
+    pub fn description(&self) -> &'static str {
+        match self {
+            ValueKind::String(_) => "a string",
+            ValueKind::Integer(_) => "an integer",
+            ValueKind::Float => "a float",
+            ValueKind::Boolean => "a boolean",
+            ValueKind::Datetime => "a datetime",
+            ValueKind::Array(_) => "an array",
+            ValueKind::Table(_) => "a table",
+        }
+    }
+}
+
+/// Parse a whole file, whose root is always a table
+pub(super) fn parse(content: &str) -> Result<Value, TomlError> {
+    let document = ImDocument::parse(content)?;
+    Ok(Value {
+        span: None,
+        kind: table(document.as_table()),
+    })
+}
+
+fn table(table: &dyn TableLike) -> ValueKind {
+    let entries = table
+        .iter()
+        .filter_map(|(name, _)| {
+            let (key, item) = table.get_key_value(name)?;
+            let key = Key {
+                name: name.to_string(),
+                span: key.span(),
+            };
+            Some((key, self::item(item)?))
+        })
+        .collect();
+    ValueKind::Table(entries)
+}
+
+fn item(item: &Item) -> Option<Value> {
+    let kind = match item {
+        Item::None => return None,
+        Item::Value(value) => return Some(self::value(value)),
+        Item::Table(table) => self::table(table),
+        Item::ArrayOfTables(tables) => ValueKind::Array(
+            tables
+                .iter()
+                .map(|table| Value {
+                    span: table.span(),
+                    kind: self::table(table),
+                })
+                .collect(),
+        ),
+    };
+    Some(Value {
+        span: item.span(),
+        kind,
+    })
+}
+
+fn value(value: &toml_edit::Value) -> Value {
+    let kind = match value {
+        toml_edit::Value::String(string) => ValueKind::String(string.value().clone()),
+        toml_edit::Value::Integer(integer) => ValueKind::Integer(*integer.value()),
+        toml_edit::Value::Float(_) => ValueKind::Float,
+        toml_edit::Value::Boolean(_) => ValueKind::Boolean,
+        toml_edit::Value::Datetime(_) => ValueKind::Datetime,
+        toml_edit::Value::Array(array) => ValueKind::Array(array.iter().map(self::value).collect()),
+        toml_edit::Value::InlineTable(table) => self::table(table),
+    };
+    Value {
+        span: value.span(),
+        kind,
+    }
+}
@@ -1,5 +1,28 @@
 pub(super) mod build;
-pub mod run {}
+pub(super) mod clean;
+pub(super) mod explain;
+pub(super) mod fix;
+pub(super) mod fmt;
+pub(super) mod init;
+pub(super) mod new;
+pub(super) mod run;
+pub(super) mod tree;
+pub(super) mod vendor;
 pub mod lsp {}
 pub mod test {}
-pub mod new {}
+
+use std::path::PathBuf;
+
+use flux_diagnostics::ice;
+
+/// The root of the project a command works on, which is the current directory unless it was given a path to one
+fn project_root(root_path: Option<PathBuf>) -> PathBuf {
+    root_path.unwrap_or_else(|| {
+        std::env::current_dir().unwrap_or_else(|err| {
+            ice(format!(
+                "could not determine project root path, make sure you have the proper permissions for this directory: {:?}",
+                err
+            ))
+        })
+    })
+}
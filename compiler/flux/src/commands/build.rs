@@ -1,9 +1,13 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use flux_diagnostics::ice;
+use clap::builder::RangedU64ValueParser;
 use lasso::ThreadedRodeo;
 
-use crate::{driver::Driver, get_config, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+use crate::{
+    driver::{default_jobs, Driver},
+    lints::LintArgs,
+    ExitStatus, INTERNER, PRE_INTERNED_VALUES,
+};
 
 #[derive(clap::Args, Debug)]
 pub struct Args {
@@ -32,29 +36,884 @@ pub struct Args {
     #[arg(long)]
     debug_bodies: bool,
 
+    /// Debug the MIR
+    ///
+    /// Defaults to false.
+    /// If true prints the control flow graph of every function
+    #[arg(long)]
+    debug_mir: bool,
+
     /// Prints the stack trace on panic
     #[arg(long)]
     stack_trace: bool,
+
+    /// What to generate for packages with a `main` function
+    ///
+    /// Defaults to an executable.
+    /// `c` writes a C11 translation unit for every package instead, which can be built with any C compiler
+    #[arg(long, value_enum, default_value_t = Emit::Exe)]
+    emit: Emit,
+
+    /// The platform to compile for
+    ///
+    /// Defaults to the host machine.
+    /// `wasm32` writes a WebAssembly module exporting the public functions in the root module of every package
+    #[arg(long, value_enum, conflicts_with = "emit")]
+    target: Option<Target>,
+
+    /// How diagnostics are reported
+    ///
+    /// Defaults to text for people to read on stderr.
+    /// `json` writes a JSON object per diagnostic to stdout, and `sarif` a SARIF 2.1.0 log once the build is done
+    #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+
+    /// Fail rather than change `flux.lock`
+    ///
+    /// Defaults to false.
+    /// If true, the versions of dependencies pinned in the lockfile are used as they are, or the build fails
+    #[arg(long)]
+    locked: bool,
+
+    /// How many threads to parse and type check on
+    ///
+    /// Defaults to one for every core.
+    /// The output is the same however many there are
+    #[arg(short, long, default_value_t = default_jobs(), value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    jobs: usize,
+
+    #[command(flatten)]
+    lints: LintArgs,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Emit {
+    Exe,
+    C,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Wasm32,
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    Human,
+    Json,
+    Sarif,
+}
+
+impl From<MessageFormat> for flux_diagnostics::MessageFormat {
+    fn from(message_format: MessageFormat) -> Self {
+        match message_format {
+            MessageFormat::Human => Self::Human,
+            MessageFormat::Json => Self::Json,
+            MessageFormat::Sarif => Self::Sarif,
+        }
+    }
 }
 
 pub fn build(args: Args) -> ExitStatus {
-    let project_root = args.root_path.unwrap_or(std::env::current_dir().unwrap_or_else(|err| ice(&format!("could not determine project root path, make sure you have the proper permissions for this directory: {:?}", err))));
+    let project_root = super::project_root(args.root_path);
     tracing::info!(project_root =? project_root, "executing build command");
 
-    let flux_config = match get_config(&project_root) {
-        Ok(cfg) => cfg,
-        Err(diagnostic) => {
-            diagnostic.report();
-            return ExitStatus::Failure;
-        }
-    };
     let compilation_config = flux_hir::Config {
         debug_cst: args.debug_cst,
         debug_item_tree: args.debug_item_tree,
         debug_bodies: args.debug_bodies,
+        debug_mir: args.debug_mir,
     };
     let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
-    let mut driver = Driver::new(compilation_config, interner);
+    let mut driver = Driver::new(compilation_config, args.message_format.into(), interner)
+        .with_locked(args.locked)
+        .with_jobs(args.jobs);
+
+    let status = build_project(
+        &mut driver,
+        &project_root,
+        &args.lints,
+        args.target,
+        args.emit,
+    );
+    driver.finish_reporting();
+    status
+}
+
+fn build_project(
+    driver: &mut Driver,
+    project_root: &Path,
+    lints: &LintArgs,
+    target: Option<Target>,
+    emit: Emit,
+) -> ExitStatus {
+    let Some(flux_config) = driver.load_config(project_root, false) else {
+        driver.report_diagnostics();
+        return ExitStatus::Failure;
+    };
+
+    let lint_levels = match lints.levels(&flux_config) {
+        Ok(lint_levels) => lint_levels,
+        Err(err) => {
+            driver.report_io_error(err);
+            return ExitStatus::Failure;
+        }
+    };
+
+    let (built_packages, status) =
+        driver.build_project(project_root.to_path_buf(), &flux_config, &lint_levels);
+    driver.report_diagnostics();
+    if status == ExitStatus::Failure {
+        return status;
+    }
+    // Like the packages of a workspace, executables all share the target directory of the project
+    for package_id in built_packages {
+        let status = match (target, emit) {
+            (Some(Target::Wasm32), _) => {
+                driver.emit_wasm_module(package_id, project_root, &flux_config.build)
+            }
+            (None, Emit::Exe) => {
+                driver.build_executable(package_id, project_root, &flux_config.build)
+            }
+            (None, Emit::C) => driver.emit_c_source(package_id, project_root, &flux_config.build),
+        };
+        if status == ExitStatus::Failure {
+            return ExitStatus::Failure;
+        }
+    }
+    ExitStatus::Success
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf, process::Command};
+
+    use flux_diagnostics::{DiagnosticCode, LintLevels, MessageFormat};
+    use lasso::ThreadedRodeo;
+    use wasmi::{Engine, Linker, Module, Store};
+
+    use crate::{driver::Driver, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+    const SRC: &str = r#"
+struct Rect {
+	w u32,
+	h u32,
+}
+
+apply to Rect {
+	fn area(this This) -> u32 {
+		this.w * this.h
+	}
+}
+
+fn scale(r Rect, k u32) -> Rect {
+	Rect { w: r.w * k, h: r.h * k }
+}
+
+fn read<T>(p T*, i u64) -> T {
+	@flux.intrinsics.ptr_read(p, i)
+}
+
+fn sum(buf u64*, n u64, i u64, acc u64) -> u64 {
+	if i == n {
+		acc
+	} else {
+		sum(buf, n, i + 1, acc + read(buf, i))
+	}
+}
+
+pub fn area(w u32, h u32) -> u32 {
+	let r = scale(Rect { w: w, h: h }, 2);
+	r.area()
+}
+
+pub fn total(n u64) -> u64 {
+	let buf = @flux.intrinsics.malloc(64) as u64*;
+	@flux.intrinsics.ptr_read(buf, 0) = n;
+	@flux.intrinsics.ptr_read(buf, 1) = 5;
+	@flux.intrinsics.ptr_read(buf, 2) = 6;
+	let copy = @flux.intrinsics.malloc(64) as u64*;
+	@flux.intrinsics.memcpy(copy, buf);
+	@flux.intrinsics.free(buf);
+	let reused = @flux.intrinsics.malloc(8) as u64*;
+	@flux.intrinsics.ptr_read(reused, 0) = 100;
+	let s = sum(copy, 3, 0, 0);
+	@flux.intrinsics.free(copy);
+	@flux.intrinsics.free(reused);
+	s
+}
+
+pub fn wrapping(x u8) -> u8 {
+	x + 10
+}
+"#;
+
+    /// Write a project depending on the standard library to a temporary directory
+    fn new_project(name: &str, src: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("flux-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("flux.toml"),
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n[dependencies]\nstd = {{ path = \"{}\" }}\n",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/../../std")
+            ),
+        )
+        .unwrap();
+        fs::write(root.join("src/main.flx"), src).unwrap();
+        root
+    }
+
+    /// Build `src` as the project `name` and run its `main` function, which has to be public so that it is exported to
+    /// wasm, on every backend: the interpreter, a native executable, C compiled with `cc -std=c11 -Wall -Werror` and
+    /// wasm
+    ///
+    /// Returns what `main` returned on each, truncated to the exit status of a process, or `None` if it stopped with a
+    /// runtime error.
+    fn run_on_every_backend(name: &str, src: &str) -> [(&'static str, Option<u8>); 4] {
+        let root = new_project(name, src);
+        let root_path = root.to_str().unwrap();
+        let out_dir = root.join("target/debug");
+
+        let interpreted = interpret(&root).ok().map(|val| val as u8);
+
+        let run = |path: &std::path::Path| {
+            let output = Command::new(path).output().unwrap();
+            output.status.code().map(|code| code as u8)
+        };
+        let build = |flags: &[&str]| {
+            let args = ["flux", "build", "--root-path", root_path];
+            crate::run_with_args(args.iter().chain(flags))
+        };
+
+        assert_eq!(build(&[]), ExitStatus::Success);
+        let native = run(&out_dir.join(name));
+
+        assert_eq!(build(&["--emit", "c"]), ExitStatus::Success);
+        let units: Vec<_> = fs::read_dir(out_dir.join("c").join(name))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let executable = out_dir.join(format!("{name}-c"));
+        let cc = Command::new("cc")
+            .args(["-std=c11", "-Wall", "-Werror", "-o"])
+            .arg(&executable)
+            .args(&units)
+            .output()
+            .unwrap();
+        assert!(
+            cc.status.success(),
+            "{}",
+            String::from_utf8_lossy(&cc.stderr)
+        );
+        let c = run(&executable);
+
+        assert_eq!(build(&["--target", "wasm32"]), ExitStatus::Success);
+        let binary = fs::read(out_dir.join(format!("wasm32/{name}.wasm"))).unwrap();
+        let engine = Engine::default();
+        let module = Module::new(&engine, &binary[..]).unwrap();
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        let main = instance.get_typed_func::<(), i64>(&store, "main").unwrap();
+        let wasm = main.call(&mut store, ()).ok().map(|val| val as u8);
+
+        let _ = fs::remove_dir_all(&root);
+        [
+            ("interpreter", interpreted),
+            ("native", native),
+            ("c", c),
+            ("wasm", wasm),
+        ]
+    }
+
+    /// Build the project at `root` and interpret it, returning what `main` returned or the code of the runtime error
+    fn interpret(root: &std::path::Path) -> Result<i128, DiagnosticCode> {
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let config = driver.load_config(root, false).unwrap();
+        let (built_packages, status) =
+            driver.build_project(root.to_path_buf(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        assert!(!driver.has_errors(), "{}", driver.rendered_diagnostics());
+        driver.run_value(built_packages[0])
+    }
+
+    /// The codes of everything reported for building the project at `root`
+    fn reported_codes(root: &std::path::Path) -> Vec<DiagnosticCode> {
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let config = driver.load_config(root, false).unwrap();
+        driver.build_project(root.to_path_buf(), &config, &LintLevels::default());
+        driver.diagnostic_codes()
+    }
+
+    fn assert_on_every_backend(name: &str, src: &str, expected: Option<u8>) {
+        for (backend, result) in run_on_every_backend(name, src) {
+            assert_eq!(result, expected, "`{name}` on the {backend} backend");
+        }
+    }
+
+    #[test]
+    fn tuple_patterns_match_on_every_backend() {
+        let src = "fn pick(a u64, b u64) -> u64 {
+\tmatch (a, b) {
+\t\t(1, x) => x,
+\t\t(x, 2) => x * 10,
+\t\t(_, _) => 7,
+\t}
+}
+
+pub fn main() -> u64 {
+\tpick(1, 5) + pick(3, 2) + pick(4, 4)
+}
+";
+        assert_on_every_backend("tuples", src, Some(42));
+    }
+
+    /// Structs, enums with payloads and generic functions, which C gets as structs, tagged unions and one function per
+    /// instantiation
+    #[test]
+    fn structs_enums_and_generics_run_on_every_backend() {
+        let src = "struct Rect {
+\tw u32,
+\th u32,
+}
+
+enum Shape {
+\tSquare -> u32,
+\tRect -> Rect,
+\tEmpty,
+}
+
+fn area(shape Shape) -> u32 {
+\tmatch shape {
+\t\tShape::Square(side) => side * side,
+\t\tShape::Rect(rect) => rect.w * rect.h,
+\t\tShape::Empty => 0,
+\t}
+}
+
+fn or<T>(opt std::Option<T>, default T) -> T {
+\tmatch opt {
+\t\tstd::Option::Some(val) => val,
+\t\tstd::Option::None => default,
+\t}
+}
+
+pub fn main() -> u64 {
+\tlet rect = Rect { w: 2, h: 5 };
+\tlet total = area(Shape::Square(3)) + area(Shape::Rect(rect)) + area(Shape::Empty);
+\tlet missing u32 = or(std::Option::None, 1);
+\tlet present = or(std::Option::Some(true), false);
+\tmatch present {
+\t\ttrue => (total + missing) as u64,
+\t\tfalse => 0,
+\t}
+}
+";
+        assert_on_every_backend("adts", src, Some(20));
+    }
+
+    /// `while`, `loop` with a value, and labeled `for` loops over a range, with `continue` and `break` in each
+    #[test]
+    fn loops_run_on_every_backend() {
+        let src = "fn count(n u64) -> u64 {
+\tlet total u64 = 0;
+\tlet i u64 = 0;
+\tlet counting = true;
+\twhile counting {
+\t\ti = i + 1;
+\t\tif i == n {
+\t\t\tcounting = false;
+\t\t}
+\t\tif i == 3 {
+\t\t\tcontinue;
+\t\t}
+\t\ttotal = total + i;
+\t}
+\ttotal
+}
+
+fn root_of(square u64) -> u64 {
+\tlet i u64 = 0;
+\tloop {
+\t\ti = i + 1;
+\t\tif i * i == square {
+\t\t\tbreak i;
+\t\t}
+\t}
+}
+
+fn pairs(n u64) -> u64 {
+\tlet found u64 = 0;
+\t'outer: for a in std::iter::range(0, n) {
+\t\tfor b in std::iter::range(0, n) {
+\t\t\tif b == a {
+\t\t\t\tcontinue 'outer;
+\t\t\t}
+\t\t\tif a + b == 7 {
+\t\t\t\tbreak 'outer;
+\t\t\t}
+\t\t\tfound = found + 1;
+\t\t}
+\t}
+\tfound
+}
+
+pub fn main() -> u64 {
+\tcount(5) + root_of(49) * 10 + pairs(10)
+}
+";
+        assert_on_every_backend("loops", src, Some(91));
+    }
+
+    /// `break` and `continue` jump to the blocks after and at the start of the loop they name
+    #[test]
+    fn labeled_loops_lower_to_jumps_in_mir() {
+        let src = "fn count(n u64) -> u64 {
+\tlet i u64 = 0;
+\t'outer: loop {
+\t\tloop {
+\t\t\tif i == n {
+\t\t\t\tbreak 'outer;
+\t\t\t}
+\t\t\ti = i + 1;
+\t\t\tcontinue 'outer;
+\t\t}
+\t}
+\ti
+}
+
+fn main() -> u64 {
+\tcount(3)
+}
+";
+        let root = new_project("mir", src);
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let config = driver.load_config(&root, false).unwrap();
+        let (built_packages, status) =
+            driver.build_project(root.clone(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        let mir = driver.mir_text(built_packages[0]);
+        let count = &mir[mir.find("fn count").unwrap()..];
+        let count = &count[..count.find("\n}\n").unwrap() + 2];
+        let _ = fs::remove_dir_all(&root);
+
+        // `bb1` is the start of the outer loop, and `bb2` is what comes after it
+        for block in [
+            "bb1: {\n        goto -> bb3;\n    }",
+            "bb2: {\n        _0 = copy _2;\n        return;\n    }",
+            "_3 = ();\n        goto -> bb2;",
+            "_2 = copy _10;\n        _9 = ();\n        goto -> bb1;",
+        ] {
+            assert!(count.contains(block), "{count}");
+        }
+    }
+
+    #[test]
+    fn paren_tuple_and_index_expressions_run_on_every_backend() {
+        let src = "fn second(pair (u64, u64)) -> u64 {
+\tmatch pair {
+\t\t(_, b) => b,
+\t}
+}
+
+fn read(p u64*, i u64) -> u64 {
+\tp[i]
+}
+
+pub fn main() -> u64 {
+\tlet p = @flux.intrinsics.malloc(16) as u64*;
+\t@flux.intrinsics.ptr_read(p, 0) = 4;
+\t@flux.intrinsics.ptr_read(p, 1) = 3;
+\tlet x u64 = 5;
+\t(second((read(p, 1), (x))) + p[0]) * 2
+}
+";
+        assert_on_every_backend("exprs", src, Some(18));
+    }
+
+    /// Addresses, floats and strings are typed, but none of the backends can compile them yet
+    ///
+    /// A mismatch is reported for both of the types involved, so only which codes are reported is checked.
+    #[test]
+    fn expressions_are_typed() {
+        let cases = [
+            (
+                "let half u64 = 0.5;\n\thalf",
+                vec![DiagnosticCode::TypeMismatch],
+            ),
+            (
+                "let name u64 = \"flux\";\n\tname",
+                vec![DiagnosticCode::TypeMismatch],
+            ),
+            (
+                "let x u64 = 1;\n\tlet addr u64 = &x;\n\taddr",
+                vec![DiagnosticCode::TypeMismatch],
+            ),
+            (
+                "let pair (u64, u64) = (1, true);\n\tmatch pair {\n\t\t(a, _) => a,\n\t}",
+                vec![DiagnosticCode::TypeMismatch],
+            ),
+            (
+                "let count u64 = 3;\n\tlet idx u64 = 0;\n\tcount[idx]",
+                vec![DiagnosticCode::NotIndexable],
+            ),
+            (
+                "let x u64 = 1;\n\tmatch (&x, 0.5, \"flux\") {\n\t\t(_, _, _) => x,\n\t}",
+                vec![DiagnosticCode::UnsupportedExpr],
+            ),
+        ];
+        let root = new_project("typed-exprs", "");
+        for (body, codes) in cases {
+            let src = format!("pub fn main() -> u64 {{\n\t{body}\n}}\n");
+            fs::write(root.join("src/main.flx"), &src).unwrap();
+            let mut reported = reported_codes(&root);
+            reported.dedup();
+            assert_eq!(reported, codes, "{src}");
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    /// Overflow is a runtime error in the interpreter and in debug builds of native code, while C and wasm wrap around
+    #[test]
+    fn integer_overflow_is_checked_in_debug_builds() {
+        let src = "fn mul(a u64, b u64) -> u64 {\n\ta * b\n}\n\npub fn main() -> u64 {\n\tlet big = mul(4294967296, 4294967295);\n\tmul(big, big) + 3\n}\n";
+        let results = run_on_every_backend("overflow", src);
+        assert_eq!(
+            results,
+            [
+                ("interpreter", None),
+                ("native", None),
+                ("c", Some(3)),
+                ("wasm", Some(3))
+            ]
+        );
+
+        let src = "fn add(a u8, b u8) -> u8 {\n\ta + b\n}\n\npub fn main() -> u64 {\n\tadd(200, 100) as u64\n}\n";
+        let results = run_on_every_backend("narrow-overflow", src);
+        assert_eq!(
+            results,
+            [
+                ("interpreter", None),
+                ("native", None),
+                ("c", Some(44)),
+                ("wasm", Some(44))
+            ]
+        );
+    }
+
+    #[test]
+    fn interpreted_allocations_are_sized_in_bytes() {
+        let src = "fn alloc<T>(size u64) -> T* {
+\t@flux.intrinsics.malloc(size) as T*
+}
+
+fn write<T>(p T*, i u64, val T) {
+\t@flux.intrinsics.ptr_read(p, i) = val;
+}
+
+fn main() -> u64 {
+\tlet p u32* = alloc(12);
+\twrite(p, 2, 7);
+\twrite(p, 3, 8);
+\t0
+}
+";
+        let root = new_project("bytes", src);
+        assert_eq!(interpret(&root), Err(DiagnosticCode::OutOfBoundsAccess));
+
+        fs::write(
+            root.join("src/main.flx"),
+            src.replace("write(p, 3, 8)", "write(p, 1, 5)")
+                .replace(
+                    "\t0\n}",
+                    "\tlet a = @flux.intrinsics.ptr_read(p, 2) as u64;\n\tlet b = @flux.intrinsics.ptr_read(p, 1) as u64;\n\ta * 10 + b\n}",
+                ),
+        )
+        .unwrap();
+        assert_eq!(interpret(&root), Ok(75));
+
+        fs::write(
+            root.join("src/main.flx"),
+            "fn main() -> u64 {\n\tlet p = @flux.intrinsics.malloc(18446744073709551615) as u8*;\n\t0\n}\n",
+        )
+        .unwrap();
+        assert_eq!(interpret(&root), Err(DiagnosticCode::AllocationTooLarge));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn interpreter_reports_runtime_errors() {
+        let cases = [
+            (
+                "fn main() -> u64 {\n\tlet zero u64 = 0;\n\tlet p = zero as u64*;\n\t@flux.intrinsics.ptr_read(p, 0)\n}\n",
+                DiagnosticCode::NullPointerDereference,
+            ),
+            (
+                "fn main() -> u64 {\n\tlet p = @flux.intrinsics.malloc(8) as u64*;\n\t@flux.intrinsics.ptr_read(p, 1)\n}\n",
+                DiagnosticCode::OutOfBoundsAccess,
+            ),
+            (
+                "fn main() -> u64 {\n\tlet p = @flux.intrinsics.malloc(8) as u64*;\n\t@flux.intrinsics.ptr_read(p, 0) = 1;\n\t@flux.intrinsics.free(p);\n\t@flux.intrinsics.ptr_read(p, 0)\n}\n",
+                DiagnosticCode::UseAfterFree,
+            ),
+            (
+                "fn main() -> u64 {\n\tlet p = @flux.intrinsics.malloc(8) as u64*;\n\t@flux.intrinsics.free(p);\n\t@flux.intrinsics.free(p);\n\t0\n}\n",
+                DiagnosticCode::InvalidFree,
+            ),
+            (
+                "fn main() -> u64 {\n\tlet p = @flux.intrinsics.malloc(8) as u64*;\n\t@flux.intrinsics.ptr_read(p, 0)\n}\n",
+                DiagnosticCode::UninitializedRead,
+            ),
+            (
+                "fn add(a u8, b u8) -> u8 {\n\ta + b\n}\n\nfn main() -> u64 {\n\tadd(200, 100) as u64\n}\n",
+                DiagnosticCode::IntegerOverflow,
+            ),
+        ];
+        let root = new_project("runtime-errors", "");
+        for (src, code) in cases {
+            fs::write(root.join("src/main.flx"), src).unwrap();
+            assert_eq!(interpret(&root), Err(code), "{src}");
+        }
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn memcpy_copies_the_requested_size_on_every_backend() {
+        let src = "pub fn main() -> u64 {
+\tlet src = @flux.intrinsics.malloc(16) as u64*;
+\t@flux.intrinsics.ptr_read(src, 0) = 1;
+\t@flux.intrinsics.ptr_read(src, 1) = 2;
+\tlet dst = @flux.intrinsics.malloc(16) as u64*;
+\tlet next = @flux.intrinsics.malloc(8) as u64*;
+\t@flux.intrinsics.ptr_read(next, 0) = 30;
+\t@flux.intrinsics.memcpy(dst, src);
+\t@flux.intrinsics.free(src);
+\t@flux.intrinsics.ptr_read(dst, 0) * 10 + @flux.intrinsics.ptr_read(dst, 1) + @flux.intrinsics.ptr_read(next, 0)
+}
+";
+        assert_on_every_backend("memcpy", src, Some(42));
+    }
+
+    #[test]
+    fn allocations_too_large_fail_on_every_backend() {
+        let src = "pub fn main() -> u64 {
+\tlet p = @flux.intrinsics.malloc(18446744073709551615) as u8*;
+\t@flux.intrinsics.free(p);
+\t0
+}
+";
+        assert_on_every_backend("huge_alloc", src, None);
+    }
+
+    #[test]
+    fn wasm32_exports_public_functions() {
+        let root = new_project("wasm", SRC);
+        let status = crate::run_with_args([
+            "flux",
+            "build",
+            "--root-path",
+            root.to_str().unwrap(),
+            "--target",
+            "wasm32",
+        ]);
+        assert_eq!(status, ExitStatus::Success);
+
+        let out_dir = root.join("target/debug/wasm32");
+        assert!(out_dir.join("wasm.wat").exists());
+        let binary = fs::read(out_dir.join("wasm.wasm")).unwrap();
+
+        let engine = Engine::default();
+        let module = Module::new(&engine, &binary[..]).unwrap();
+        let mut store = Store::new(&engine, ());
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        assert!(instance.get_memory(&store, "memory").is_some());
+
+        let area = instance
+            .get_typed_func::<(i32, i32), i32>(&store, "area")
+            .unwrap();
+        assert_eq!(area.call(&mut store, (3, 4)).unwrap(), 48);
+        let total = instance
+            .get_typed_func::<i64, i64>(&store, "total")
+            .unwrap();
+        assert_eq!(total.call(&mut store, 4).unwrap(), 15);
+        // The stack and the free list are left as they were found, so exports can be called repeatedly
+        assert_eq!(total.call(&mut store, 7).unwrap(), 18);
+        let wrapping = instance
+            .get_typed_func::<i32, i32>(&store, "wrapping")
+            .unwrap();
+        assert_eq!(wrapping.call(&mut store, 250).unwrap(), 4);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn dependencies_are_loaded_from_the_cache() {
+        let root = new_project(
+            "cached",
+            "fn add(a u64, b u64) -> u64 {\n\ta + b\n}\n\nfn main() -> u64 {\n\tadd(2, 3)\n}\n",
+        );
+        let root_path = root.to_str().unwrap();
+        let build = ["flux", "build", "--root-path", root_path, "--emit", "c"];
+        let read_units = || {
+            ["cached", "std"].map(|unit| {
+                fs::read_to_string(root.join(format!("target/debug/c/cached/{unit}.c"))).unwrap()
+            })
+        };
+
+        assert_eq!(crate::run_with_args(build), ExitStatus::Success);
+        let built = read_units();
+        let entries: Vec<_> = fs::read_dir(root.join("target/cache"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        assert!(entries.len() == 1 && entries[0].starts_with("std-"));
+
+        assert_eq!(crate::run_with_args(build), ExitStatus::Success);
+        assert_eq!(read_units(), built);
+
+        // The units being the same doesn't mean std wasn't built again, so check what the driver loaded
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let config = driver.load_config(&root, false).unwrap();
+        let (_, status) = driver.build_project(root.clone(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(driver.prebuilt_packages(), ["std"]);
+
+        let clean = ["flux", "clean", "--root-path", root_path];
+        assert_eq!(crate::run_with_args(clean), ExitStatus::Success);
+        assert!(!root.join("target").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn denied_lints_fail_the_build() {
+        let root = new_project("lints", "fn main() -> u64 {\n\tlet x u64 = 1;\n\t2\n}\n");
+        let root_path = root.to_str().unwrap();
+        let build = |flags: &[&str]| {
+            let args = ["flux", "build", "--root-path", root_path, "--emit", "c"];
+            crate::run_with_args(args.iter().chain(flags))
+        };
+
+        assert_eq!(build(&[]), ExitStatus::Success);
+        assert_eq!(build(&["-D", "unused_locals"]), ExitStatus::Failure);
+        assert_eq!(build(&["--deny-warnings"]), ExitStatus::Failure);
+        assert_eq!(
+            build(&["--deny-warnings", "-A", "unused_locals"]),
+            ExitStatus::Success
+        );
+        assert_eq!(build(&["-W", "unknown"]), ExitStatus::Failure);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    /// The code of what went wrong translating `src` to C, if anything did
+    fn emit_c_error(name: &str, src: &str) -> Option<DiagnosticCode> {
+        let root = new_project(name, src);
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let config = driver.load_config(&root, false).unwrap();
+        let (built_packages, status) =
+            driver.build_project(root.clone(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        let code = driver.emit_c_error(built_packages[0]);
+        let _ = fs::remove_dir_all(&root);
+        code
+    }
+
+    #[test]
+    fn unbounded_instantiation_is_an_error() {
+        let src = "fn grow<T>(x T) -> u64 {\n\tlet p = @flux.intrinsics.malloc(8) as T*;\n\tgrow(p)\n}\n\nfn main() -> u64 {\n\tlet x = @flux.intrinsics.malloc(8) as u64*;\n\tgrow(x)\n}\n";
+        assert_eq!(
+            emit_c_error("recursion", src),
+            Some(DiagnosticCode::InstantiationRecursionLimit)
+        );
+
+        let root = new_project("recursion", src);
+        let status = crate::run_with_args([
+            "flux",
+            "build",
+            "--root-path",
+            root.to_str().unwrap(),
+            "--emit",
+            "c",
+        ]);
+        assert_eq!(status, ExitStatus::Failure);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn long_call_chains_are_not_unbounded_instantiation() {
+        let mut src = "fn link100() -> u64 {\n\t1\n}\n".to_string();
+        for idx in (1..100).rev() {
+            src.push_str(&format!(
+                "\nfn link{idx}() -> u64 {{\n\tlink{}() + 1\n}}\n",
+                idx + 1
+            ));
+        }
+        src.push_str("\npub fn main() -> u64 {\n\tlink1()\n}\n");
+        assert_eq!(emit_c_error("chain", &src), None);
+        assert_on_every_backend("chain", &src, Some(100));
+
+        // Generic functions that each instantiate the next, none of them twice
+        let mut src = "fn link100<T>(x T) -> u64 {\n\t1\n}\n".to_string();
+        for idx in (1..100).rev() {
+            src.push_str(&format!(
+                "\nfn link{idx}<T>(x T) -> u64 {{\n\tlink{}(x) + 1\n}}\n",
+                idx + 1
+            ));
+        }
+        src.push_str("\npub fn main() -> u64 {\n\tlet x u64 = 0;\n\tlink1(x)\n}\n");
+        assert_eq!(emit_c_error("generic-chain", &src), None);
+        assert_on_every_backend("generic-chain", &src, Some(100));
+    }
+
+    #[test]
+    fn output_does_not_depend_on_the_number_of_jobs() {
+        let root = new_project(
+            "jobs",
+            &format!("{SRC}\nfn main() -> u64 {{\n\ttotal(4)\n}}\n"),
+        );
+        let root_path = root.to_str().unwrap();
+        let build = |jobs: &str| {
+            let args = ["flux", "build", "--root-path", root_path, "--emit", "c"];
+            let status = crate::run_with_args(args.iter().chain(&["-j", jobs]));
+            let unit = fs::read_to_string(root.join("target/debug/c/jobs/jobs.c")).unwrap();
+            let _ = fs::remove_dir_all(root.join("target"));
+            (status, unit)
+        };
+        let (status, unit) = build("1");
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(build("4"), (status, unit));
+
+        let src = "fn first() -> u64 {\n\tmissing()\n}\n\nfn second() -> u32 {\n\tlet x u64 = 1;\n\tx\n}\n\nfn main() -> u64 {\n\tfirst() + second()\n}\n";
+        fs::write(root.join("src/main.flx"), src).unwrap();
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let diagnostics = |jobs: usize| {
+            let mut driver =
+                Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+                    .with_jobs(jobs);
+            let config = driver.load_config(&root, false).unwrap();
+            let (_, status) = driver.build_project(root.clone(), &config, &LintLevels::default());
+            assert_eq!(status, ExitStatus::Success);
+            assert!(driver.has_errors());
+            driver.rendered_diagnostics()
+        };
+        assert_eq!(diagnostics(1), diagnostics(4));
 
-    driver.build_project(project_root, &flux_config).1
+        let _ = fs::remove_dir_all(&root);
+    }
 }
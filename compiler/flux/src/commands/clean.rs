@@ -0,0 +1,33 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use crate::{diagnostics::DriverError, ExitStatus};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+}
+
+/// Remove the target directory of a project, including the build cache, so that everything is built again next time
+pub fn clean(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing clean command");
+
+    let target_dir = project_root.join("target");
+    match fs::remove_dir_all(&target_dir) {
+        Ok(()) => ExitStatus::Success,
+        Err(err) if err.kind() == ErrorKind::NotFound => ExitStatus::Success,
+        Err(err) => {
+            DriverError::Clean {
+                path: target_dir.display().to_string(),
+                err: err.to_string(),
+            }
+            .to_io_error()
+            .report();
+            ExitStatus::Failure
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use flux_diagnostics::DiagnosticCode;
+
+use crate::{diagnostics::DriverError, ExitStatus};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// The code to explain, as it is reported, like `E0016`
+    code: String,
+}
+
+pub fn explain(args: Args) -> ExitStatus {
+    match DiagnosticCode::from_name(&args.code) {
+        Some(code) => {
+            print!("{}", code.explanation());
+            ExitStatus::Success
+        }
+        None => {
+            DriverError::UnknownDiagnosticCode { code: args.code }
+                .to_io_error()
+                .report();
+            ExitStatus::Failure
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use flux_diagnostics::{DiagnosticCode, Example, ExampleStage, LintLevels, MessageFormat};
+    use lasso::ThreadedRodeo;
+
+    use crate::{driver::Driver, INTERNER, PRE_INTERNED_VALUES};
+
+    /// The codes of everything reported for an example, built in the project at `root`
+    ///
+    /// An example is only run or translated to C if building it reported nothing.
+    fn reported_codes(root: &Path, example: &Example) -> Vec<DiagnosticCode> {
+        let src = root.join("src");
+        let _ = fs::remove_dir_all(&src);
+        fs::create_dir_all(&src).unwrap();
+        for (name, content) in &example.files {
+            fs::write(src.join(name), content).unwrap();
+        }
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let flux_config = driver.load_config(root, false).unwrap();
+        let (built_packages, _) =
+            driver.build_project(root.to_path_buf(), &flux_config, &LintLevels::default());
+        let mut codes = driver.diagnostic_codes();
+        if codes.is_empty() {
+            let package_id = *built_packages.last().unwrap();
+            codes.extend(match example.stage {
+                ExampleStage::Build => None,
+                ExampleStage::Run => driver.run_error(package_id),
+                ExampleStage::EmitC => driver.emit_c_error(package_id),
+            });
+        }
+        codes
+    }
+
+    #[test]
+    fn examples_report_their_code() {
+        let root = std::env::temp_dir().join(format!("flux-explain-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(
+            root.join("flux.toml"),
+            format!(
+                "[package]\nname = \"example\"\nversion = \"0.1.0\"\n\n[dependencies]\nstd = {{ path = \"{}\" }}\n",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/../../std")
+            ),
+        )
+        .unwrap();
+
+        // Every example is checked before failing, so that they can all be fixed at once
+        let mut failures = vec![];
+        for code in DiagnosticCode::ALL {
+            for (idx, example) in code.examples().unwrap().iter().enumerate() {
+                let codes = reported_codes(&root, example);
+                // An example reports its code and nothing else, however many times it is reported
+                let as_expected = match example.code {
+                    Some(code) => {
+                        !codes.is_empty() && codes.iter().all(|reported| *reported == code)
+                    }
+                    None => codes.is_empty(),
+                };
+                if !as_expected {
+                    failures.push(format!(
+                        "example {} of {code} reported {}",
+                        idx + 1,
+                        codes
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+            }
+        }
+        let _ = fs::remove_dir_all(&root);
+        assert!(failures.is_empty(), "{}", failures.join("\n"));
+    }
+}
@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use flux_diagnostics::{fmt::Plural, Applicability, MessageFormat};
+use lasso::ThreadedRodeo;
+
+use crate::{driver::Driver, lints::LintArgs, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+/// Applying a suggestion can uncover more, so the project is rebuilt after every round of them, up to this many times
+const MAX_ROUNDS: usize = 4;
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    /// Also apply suggestions that might not be what was meant
+    ///
+    /// Defaults to false.
+    /// If true, misspelled names are replaced with the closest one in scope, and extra arguments are removed
+    #[arg(long)]
+    maybe_incorrect: bool,
+
+    #[command(flatten)]
+    lints: LintArgs,
+}
+
+pub fn fix(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing fix command");
+
+    let applicability = if args.maybe_incorrect {
+        Applicability::MaybeIncorrect
+    } else {
+        Applicability::MachineApplicable
+    };
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+
+    let mut round = 0;
+    loop {
+        let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        // The config is read again every round, since that is when any problems with it are reported
+        let Some(flux_config) = driver.load_config(&project_root, false) else {
+            driver.report_diagnostics();
+            return ExitStatus::Failure;
+        };
+        let lint_levels = match args.lints.levels(&flux_config) {
+            Ok(lint_levels) => lint_levels,
+            Err(err) => {
+                driver.report_io_error(err);
+                return ExitStatus::Failure;
+            }
+        };
+        let (_, status) = driver.build_project(project_root.clone(), &flux_config, &lint_levels);
+        if status == ExitStatus::Failure {
+            return status;
+        }
+
+        let fixed_files = match driver.apply_suggestions(applicability) {
+            Ok(fixed_files) => fixed_files,
+            Err(err) => {
+                driver.report_io_error(err);
+                return ExitStatus::Failure;
+            }
+        };
+        round += 1;
+        if fixed_files.is_empty() || round == MAX_ROUNDS {
+            // Whatever is left has to be fixed by hand
+            driver.report_diagnostics();
+            return if driver.has_errors() {
+                ExitStatus::Failure
+            } else {
+                ExitStatus::Success
+            };
+        }
+        for (path, num_applied) in fixed_files {
+            println!(
+                "fixed `{path}` ({num_applied} suggestion{})",
+                num_applied.plural("s")
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::ExitStatus;
+
+    #[test]
+    fn suggestions_are_applied() {
+        let root = std::env::temp_dir().join(format!("flux-fix-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("flux.toml"),
+            format!(
+                "[package]\nname = \"fix\"\nversion = \"0.1.0\"\n\n[dependencies]\nstd = {{ path = \"{}\" }}\n",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/../../std")
+            ),
+        )
+        .unwrap();
+        let main = root.join("src/main.flx");
+        fs::write(
+            &main,
+            "fn total(count u64) -> u64 {\n\t(count + 1) as u64\n}\n\nfn main() -> u64 {\n\tlet count u64 = 2;\n\tcuont + total(count)\n}\n",
+        )
+        .unwrap();
+        let root_path = root.to_str().unwrap();
+
+        // Only the cast is sure to be unnecessary, so the misspelled local is left alone
+        let status = crate::run_with_args(["flux", "fix", "--root-path", root_path]);
+        assert_eq!(status, ExitStatus::Failure);
+        assert_eq!(
+            fs::read_to_string(&main).unwrap(),
+            "fn total(count u64) -> u64 {\n\t(count + 1)\n}\n\nfn main() -> u64 {\n\tlet count u64 = 2;\n\tcuont + total(count)\n}\n"
+        );
+
+        let status =
+            crate::run_with_args(["flux", "fix", "--root-path", root_path, "--maybe-incorrect"]);
+        assert_eq!(status, ExitStatus::Success);
+        assert_eq!(
+            fs::read_to_string(&main).unwrap(),
+            "fn total(count u64) -> u64 {\n\t(count + 1)\n}\n\nfn main() -> u64 {\n\tlet count u64 = 2;\n\tcount + total(count)\n}\n"
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use flux_diagnostics::{Diagnostic, SourceCache};
+use flux_fmt::FormatError;
+use lasso::ThreadedRodeo;
+
+use crate::{
+    diagnostics::DriverError, get_config, source_files, ExitStatus, INTERNER, PRE_INTERNED_VALUES,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    /// Check that files are formatted without changing them
+    ///
+    /// Lists the files that are not formatted, and fails if there are any
+    #[arg(long)]
+    check: bool,
+
+    /// Files to format
+    ///
+    /// Defaults to every source file in the packages of the project
+    files: Vec<PathBuf>,
+}
+
+pub fn fmt(args: Args) -> ExitStatus {
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+    let mut source_cache = SourceCache::new(interner);
+    let files = if args.files.is_empty() {
+        let project_root = super::project_root(args.root_path);
+        match project_files(&project_root, &mut source_cache) {
+            Ok(files) => files,
+            Err(status) => return status,
+        }
+    } else {
+        args.files
+    };
+    tracing::info!(files = files.len(), "executing fmt command");
+
+    let mut status = ExitStatus::Success;
+    for path in files {
+        let path = path.to_str().unwrap().to_string();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) => {
+                DriverError::ReadSourceFile {
+                    path,
+                    err: err.to_string(),
+                }
+                .to_io_error()
+                .report();
+                status = ExitStatus::Failure;
+                continue;
+            }
+        };
+        let file_id = source_cache.add_input_file(&path, content.clone());
+        match flux_fmt::format(&content, file_id, interner) {
+            Ok(formatted) if formatted == content => {}
+            Ok(_) if args.check => {
+                println!("`{path}` is not formatted");
+                status = ExitStatus::Failure;
+            }
+            Ok(formatted) => {
+                if let Err(err) = fs::write(&path, formatted) {
+                    DriverError::WriteOutputFile {
+                        path,
+                        err: err.to_string(),
+                    }
+                    .to_io_error()
+                    .report();
+                    status = ExitStatus::Failure;
+                }
+            }
+            Err(FormatError::Syntax(diagnostics)) => {
+                source_cache.report_diagnostics(diagnostics.iter());
+                status = ExitStatus::Failure;
+            }
+            Err(FormatError::ChangedMeaning) => {
+                DriverError::Format { path }.to_io_error().report();
+                status = ExitStatus::Failure;
+            }
+        }
+    }
+    status
+}
+
+/// The source files of every package in the project, leaving out its dependencies
+///
+/// Only where the packages are is needed from the config, so it is left to `flux build` to warn about anything else.
+fn project_files(
+    project_root: &Path,
+    source_cache: &mut SourceCache,
+) -> Result<Vec<PathBuf>, ExitStatus> {
+    let (flux_config, diagnostics) = match get_config(project_root, source_cache) {
+        Ok(loaded) => loaded,
+        Err(diagnostic) => {
+            diagnostic.report();
+            return Err(ExitStatus::Failure);
+        }
+    };
+    if diagnostics.iter().any(Diagnostic::is_error) {
+        source_cache.report_diagnostics(diagnostics.iter());
+        return Err(ExitStatus::Failure);
+    }
+    let single_package_project = !flux_config.workspace;
+    let mut files = vec![];
+    for package in &flux_config.packages {
+        let package_root = if single_package_project {
+            project_root.to_path_buf()
+        } else {
+            project_root.join(&package.name)
+        };
+        source_files(&package_root.join("src"), &mut files);
+    }
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use crate::ExitStatus;
+
+    #[test]
+    fn check_fails_until_formatted() {
+        let root = std::env::temp_dir().join(format!("flux-fmt-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("flux.toml"),
+            "[package]\nname = \"fmt\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/main.flx"), "fn main()->s32{0}").unwrap();
+        let root_path = root.to_str().unwrap();
+
+        let check = ["flux", "fmt", "--root-path", root_path, "--check"];
+        assert_eq!(crate::run_with_args(check), ExitStatus::Failure);
+        assert_eq!(
+            crate::run_with_args(["flux", "fmt", "--root-path", root_path]),
+            ExitStatus::Success
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("src/main.flx")).unwrap(),
+            "fn main() -> s32 {\n\t0\n}\n"
+        );
+        assert_eq!(crate::run_with_args(check), ExitStatus::Success);
+        let _ = fs::remove_dir_all(&root);
+    }
+}
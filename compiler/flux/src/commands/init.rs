@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use crate::{
+    commands::new::{create_project, LayoutArgs},
+    ExitStatus,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+}
+
+/// Make a project of an existing directory, keeping whatever source files it already has
+pub fn init(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing init command");
+
+    match create_project(&project_root, &args.layout) {
+        Ok(()) => ExitStatus::Success,
+        Err(err) => {
+            err.report();
+            ExitStatus::Failure
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use flux_diagnostics::IOError;
+
+use crate::{cfg::CFG_FILE_NAME, diagnostics::DriverError, ExitStatus};
+
+const STARTER_MAIN: &str = "fn main() -> u64 {\n\t0\n}\n";
+
+/// How the project is laid out, shared with `flux init`
+#[derive(clap::Args, Debug)]
+pub struct LayoutArgs {
+    /// Name of the package
+    ///
+    /// Defaults to the name of the project directory
+    #[arg(long)]
+    name: Option<String>,
+
+    /// Create a workspace, with the package in a directory of its own
+    ///
+    /// Defaults to false.
+    /// If true, more packages can be added to the `packages` of the workspace, each in a directory named after it
+    #[arg(long)]
+    workspace: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to the directory to create the project in, which must not exist yet
+    path: PathBuf,
+
+    #[command(flatten)]
+    layout: LayoutArgs,
+}
+
+pub fn new(args: Args) -> ExitStatus {
+    tracing::info!(path =? args.path, "executing new command");
+
+    if args.path.exists() {
+        DriverError::ProjectExists {
+            path: args.path.display().to_string(),
+        }
+        .to_io_error()
+        .report();
+        return ExitStatus::Failure;
+    }
+    match create_project(&args.path, &args.layout) {
+        Ok(()) => ExitStatus::Success,
+        Err(err) => {
+            err.report();
+            ExitStatus::Failure
+        }
+    }
+}
+
+/// Lay out a project in `project_root`, keeping any of its files that already exist
+///
+/// A single package project has its `src` directory next to `flux.toml`, while a workspace has the package in a
+/// directory named after it, which is where `flux build` looks for each of them.
+pub(super) fn create_project(project_root: &Path, layout: &LayoutArgs) -> Result<(), IOError> {
+    let name = match &layout.name {
+        Some(name) => name.clone(),
+        None => project_root
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+    };
+    validate_package_name(&name)?;
+
+    let cfg_path = project_root.join(CFG_FILE_NAME);
+    if cfg_path.exists() {
+        return Err(DriverError::ProjectExists {
+            path: cfg_path.display().to_string(),
+        }
+        .to_io_error());
+    }
+
+    let (cfg, package_root) = if layout.workspace {
+        (
+            format!("[workspace]\npackages = [\"{name}\"]\n"),
+            project_root.join(&name),
+        )
+    } else {
+        (
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n"),
+            project_root.to_path_buf(),
+        )
+    };
+    let src = package_root.join("src");
+    fs::create_dir_all(&src).map_err(|err| create_project_error(&src, err))?;
+    write(&cfg_path, &cfg)?;
+    let main = src.join("main.flx");
+    if !main.exists() {
+        write(&main, STARTER_MAIN)?;
+    }
+
+    let gitignore = project_root.join(".gitignore");
+    let ignored = fs::read_to_string(&gitignore).unwrap_or_default();
+    let ignores_target = ignored
+        .lines()
+        .any(|line| matches!(line.trim(), "target" | "target/" | "/target" | "/target/"));
+    if !ignores_target {
+        let separator = if ignored.is_empty() || ignored.ends_with('\n') {
+            ""
+        } else {
+            "\n"
+        };
+        write(&gitignore, &format!("{ignored}{separator}target/\n"))?;
+    }
+
+    if layout.workspace {
+        println!("created workspace with package `{name}`");
+    } else {
+        println!("created package `{name}`");
+    }
+    Ok(())
+}
+
+/// Package names are used as the first segment of paths into the package, so they have to be identifiers
+fn validate_package_name(name: &str) -> Result<(), IOError> {
+    if flux_parser::is_ident(name) {
+        return Ok(());
+    }
+    let reason = if name.is_empty() {
+        "it is empty"
+    } else if !name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        "it does not start with a letter"
+    } else if name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        "it is a keyword"
+    } else {
+        "it contains characters other than letters, digits and `_`"
+    };
+    Err(DriverError::InvalidPackageName {
+        name: name.to_string(),
+        reason: reason.to_string(),
+    }
+    .to_io_error())
+}
+
+fn write(path: &Path, content: &str) -> Result<(), IOError> {
+    fs::write(path, content).map_err(|err| create_project_error(path, err))
+}
+
+fn create_project_error(path: &Path, err: std::io::Error) -> IOError {
+    DriverError::CreateProject {
+        path: path.display().to_string(),
+        err: err.to_string(),
+    }
+    .to_io_error()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use flux_diagnostics::{LintLevels, MessageFormat};
+    use lasso::ThreadedRodeo;
+
+    use crate::{driver::Driver, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+    #[test]
+    fn new_projects_build() {
+        let dir = std::env::temp_dir().join(format!("flux-new-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        for (name, workspace) in [("single", false), ("multi", true)] {
+            let root = dir.join(name);
+            let root_path = root.to_str().unwrap();
+            let mut args = vec!["flux", "new", root_path];
+            if workspace {
+                args.push("--workspace");
+            }
+            assert_eq!(crate::run_with_args(args.clone()), ExitStatus::Success);
+            assert_eq!(
+                fs::read_to_string(root.join(".gitignore")).unwrap(),
+                "target/\n"
+            );
+            let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+            let mut driver =
+                Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+            let flux_config = driver.load_config(&root, false).unwrap();
+            let (built_packages, _) =
+                driver.build_project(root.clone(), &flux_config, &LintLevels::default());
+            assert_eq!(built_packages.len(), 1, "`{name}` was not built");
+            assert!(driver.diagnostic_codes().is_empty());
+            assert_eq!(crate::run_with_args(args), ExitStatus::Failure);
+        }
+
+        let root = dir.join("existing");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join(".gitignore"), "*.o").unwrap();
+        let root_path = root.to_str().unwrap();
+        let init = ["flux", "init", "--root-path", root_path];
+        assert_eq!(crate::run_with_args(init), ExitStatus::Success);
+        assert_eq!(
+            fs::read_to_string(root.join(".gitignore")).unwrap(),
+            "*.o\ntarget/\n"
+        );
+        assert_eq!(crate::run_with_args(init), ExitStatus::Failure);
+
+        let root = dir.join("invalid");
+        let root_path = root.to_str().unwrap();
+        for name in ["fn", "2d", "my-app"] {
+            let status =
+                crate::run_with_args(["flux", "init", "--root-path", root_path, "--name", name]);
+            assert_eq!(status, ExitStatus::Failure, "`{name}` was accepted");
+        }
+        assert!(!root.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
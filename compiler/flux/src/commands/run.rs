@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+
+use clap::builder::RangedU64ValueParser;
+use flux_diagnostics::MessageFormat;
+use lasso::ThreadedRodeo;
+
+use crate::{
+    driver::{default_jobs, Driver},
+    lints::LintArgs,
+    ExitStatus, INTERNER, PRE_INTERNED_VALUES,
+};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    /// Debug the CST
+    ///
+    /// Defaults to false.
+    /// If true prints the CST
+    #[arg(long)]
+    debug_cst: bool,
+
+    /// Debug the Item Tree
+    ///
+    /// Defaults to false.
+    #[arg(long)]
+    debug_item_tree: bool,
+
+    /// Debug the Item Tree with Expression Bodies
+    ///
+    /// Defaults to false.
+    #[arg(long)]
+    debug_bodies: bool,
+
+    /// Debug the MIR
+    ///
+    /// Defaults to false.
+    /// If true prints the control flow graph of every function
+    #[arg(long)]
+    debug_mir: bool,
+
+    /// Prints the stack trace on panic
+    #[arg(long)]
+    stack_trace: bool,
+
+    /// Fail rather than change `flux.lock`
+    ///
+    /// Defaults to false.
+    /// If true, the versions of dependencies pinned in the lockfile are used as they are, or the build fails
+    #[arg(long)]
+    locked: bool,
+
+    /// How many threads to parse and type check on
+    ///
+    /// Defaults to one for every core.
+    /// The output is the same however many there are
+    #[arg(short, long, default_value_t = default_jobs(), value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    jobs: usize,
+
+    #[command(flatten)]
+    lints: LintArgs,
+}
+
+pub fn run(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing run command");
+
+    let compilation_config = flux_hir::Config {
+        debug_cst: args.debug_cst,
+        debug_item_tree: args.debug_item_tree,
+        debug_bodies: args.debug_bodies,
+        debug_mir: args.debug_mir,
+    };
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+    let mut driver = Driver::new(compilation_config, MessageFormat::Human, interner)
+        .with_locked(args.locked)
+        .with_jobs(args.jobs);
+    let Some(flux_config) = driver.load_config(&project_root, false) else {
+        driver.report_diagnostics();
+        return ExitStatus::Failure;
+    };
+
+    let lint_levels = match args.lints.levels(&flux_config) {
+        Ok(lint_levels) => lint_levels,
+        Err(err) => {
+            driver.report_io_error(err);
+            return ExitStatus::Failure;
+        }
+    };
+
+    let (built_packages, status) = driver.build_project(project_root, &flux_config, &lint_levels);
+    driver.report_diagnostics();
+    if status == ExitStatus::Failure {
+        return status;
+    }
+    match built_packages.last() {
+        Some(package_id) => driver.run_package(*package_id),
+        None => ExitStatus::Failure,
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+
+use flux_diagnostics::MessageFormat;
+use lasso::ThreadedRodeo;
+
+use crate::{driver::Driver, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    /// Fail rather than change `flux.lock`
+    ///
+    /// Defaults to false.
+    /// If true, the versions of dependencies pinned in the lockfile are used as they are, or the command fails
+    #[arg(long)]
+    locked: bool,
+}
+
+/// Print the packages a project depends on, and what each of them depends on in turn
+pub fn tree(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing tree command");
+
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+    let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+        .with_locked(args.locked);
+    let status = print_tree(&mut driver, &project_root);
+    driver.report_diagnostics();
+    driver.finish_reporting();
+    status
+}
+
+fn print_tree(driver: &mut Driver, project_root: &Path) -> ExitStatus {
+    let Some(flux_config) = driver.load_config(project_root, false) else {
+        return ExitStatus::Failure;
+    };
+    let graph = driver
+        .fetch_dependencies(project_root, &flux_config)
+        .and_then(|_| driver.package_graph(project_root, &flux_config));
+    match graph {
+        Ok(graph) => {
+            print!("{}", graph.render());
+            ExitStatus::Success
+        }
+        Err(err) => {
+            driver.report_io_error(err);
+            ExitStatus::Failure
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::path::{Path, PathBuf};
+
+use flux_diagnostics::MessageFormat;
+use lasso::ThreadedRodeo;
+
+use crate::{driver::Driver, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+#[derive(clap::Args, Debug)]
+pub struct Args {
+    /// Path to root directory of the flux project
+    ///
+    /// Defaults to current directory
+    #[arg(long)]
+    root_path: Option<PathBuf>,
+
+    /// Path to the directory to copy the dependencies into, relative to the project root
+    ///
+    /// Whatever registry was vendored there before is replaced
+    #[arg(default_value = "vendor")]
+    path: PathBuf,
+}
+
+/// Copy every package the project depends on from the registry, and every commit it depends on from git repositories,
+/// into a registry of its own in the project, so that it can be built without either
+pub fn vendor(args: Args) -> ExitStatus {
+    let project_root = super::project_root(args.root_path);
+    tracing::info!(project_root =? project_root, "executing vendor command");
+
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+    let mut driver = Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+    let status = vendor_project(&mut driver, &project_root, &args.path);
+    driver.report_diagnostics();
+    driver.finish_reporting();
+    status
+}
+
+fn vendor_project(driver: &mut Driver, project_root: &Path, path: &Path) -> ExitStatus {
+    let Some(flux_config) = driver.load_config(project_root, false) else {
+        return ExitStatus::Failure;
+    };
+    let registry = driver.registry(project_root, &flux_config);
+    let dest = project_root.join(path);
+    let vendored = driver
+        .resolve_dependencies(project_root, &flux_config, &registry)
+        .and_then(|resolution| {
+            registry.vendor(&resolution, &dest)?;
+            Ok(resolution.packages.len() + resolution.git.len())
+        });
+    let num_packages = match vendored {
+        Ok(num_packages) => num_packages,
+        Err(err) => {
+            driver.report_io_error(err);
+            return ExitStatus::Failure;
+        }
+    };
+
+    println!("vendored {num_packages} packages into `{}`", dest.display());
+    if registry.root() != dest {
+        println!(
+            "to build from them, add this to `flux.toml`:\n\n[registry]\npath = \"{}\"",
+            path.display()
+        );
+    }
+    ExitStatus::Success
+}
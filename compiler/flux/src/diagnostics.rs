@@ -1,9 +1,104 @@
-use flux_diagnostics::{DiagnosticCode, IOError};
+use flux_diagnostics::{DiagnosticCode, IOError, Lint};
+use flux_proc_macros::diagnostic;
+
+use crate::lockfile::LOCKFILE_NAME;
 
 #[derive(Debug, Clone)]
 pub enum DriverError {
-    ReadConfigFile { candidate: String },
-    ReadEntryFile { package: String, candidate: String },
+    ReadConfigFile {
+        candidate: String,
+    },
+    ReadEntryFile {
+        package: String,
+        candidate: String,
+    },
+    NoMainFunction {
+        package: String,
+    },
+    WriteOutputFile {
+        path: String,
+        err: String,
+    },
+    Link {
+        package: String,
+        output: String,
+    },
+    ReadSourceFile {
+        path: String,
+        err: String,
+    },
+    Format {
+        path: String,
+    },
+    Clean {
+        path: String,
+        err: String,
+    },
+    UnknownLint {
+        name: String,
+    },
+    UnknownDiagnosticCode {
+        code: String,
+    },
+    InvalidPackageName {
+        name: String,
+        reason: String,
+    },
+    ProjectExists {
+        path: String,
+    },
+    CreateProject {
+        path: String,
+        err: String,
+    },
+    UnresolvableDependency {
+        name: String,
+        requirements: Vec<String>,
+        versions: Vec<String>,
+        registry: String,
+    },
+    DependencyVersionMismatch {
+        name: String,
+        requirement: String,
+        version: String,
+        path: String,
+    },
+    DependencyChecksumMismatch {
+        name: String,
+        version: String,
+    },
+    LockfileOutOfDate {
+        path: String,
+    },
+    ReadLockfile {
+        path: String,
+        err: String,
+    },
+    ReadRegistry {
+        path: String,
+        err: String,
+    },
+    FetchDependency {
+        name: String,
+        version: String,
+        err: String,
+    },
+    CorruptDependency {
+        name: String,
+        version: String,
+        path: String,
+    },
+    FetchGitDependency {
+        url: String,
+        err: String,
+    },
+    MissingGitRevision {
+        url: String,
+        reference: String,
+    },
+    DependencyCycle {
+        cycle: Vec<String>,
+    },
 }
 
 impl DriverError {
@@ -23,6 +118,263 @@ impl DriverError {
                     "create the file `{candidate}` or change its permissions if it already exists"
                 )],
             ),
+            DriverError::NoMainFunction { package } => IOError::new(
+                DiagnosticCode::NoMainFunction,
+                format!("could not find `main` function in package `{package}`"),
+                vec![format!(
+                    "add a `fn main()` to the root module of `{package}` to give it an entry point"
+                )],
+            ),
+            DriverError::WriteOutputFile { path, err } => IOError::new(
+                DiagnosticCode::CouldNotWriteOutputFile,
+                format!("could not write output file `{path}`"),
+                vec![err],
+            ),
+            DriverError::Link { package, output } => IOError::new(
+                DiagnosticCode::CouldNotLink,
+                format!("could not link package `{package}`"),
+                vec![output.trim_end().to_string()],
+            ),
+            DriverError::ReadSourceFile { path, err } => IOError::new(
+                DiagnosticCode::CouldNotReadSourceFile,
+                format!("could not read source file `{path}`"),
+                vec![err],
+            ),
+            DriverError::Format { path } => IOError::new(
+                DiagnosticCode::CouldNotFormat,
+                format!("could not format `{path}`"),
+                vec![
+                    "the formatted file would not mean the same thing as the original, so it was left as it is"
+                        .to_string(),
+                ],
+            ),
+            DriverError::Clean { path, err } => IOError::new(
+                DiagnosticCode::CouldNotClean,
+                format!("could not remove `{path}`"),
+                vec![err],
+            ),
+            DriverError::UnknownLint { name } => IOError::new(
+                DiagnosticCode::UnknownLint,
+                format!("unknown lint `{name}`"),
+                vec![format!(
+                    "the lints are {}",
+                    Lint::ALL
+                        .iter()
+                        .map(|lint| format!("`{lint}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )],
+            ),
+            DriverError::UnknownDiagnosticCode { code } => IOError::new(
+                DiagnosticCode::UnknownDiagnosticCode,
+                format!("unknown diagnostic code `{code}`"),
+                vec![format!(
+                    "codes are written like `E0016`, and go from `{}` to `{}`",
+                    DiagnosticCode::ALL[0],
+                    DiagnosticCode::ALL[DiagnosticCode::ALL.len() - 1]
+                )],
+            ),
+            DriverError::InvalidPackageName { name, reason } => IOError::new(
+                DiagnosticCode::InvalidPackageName,
+                format!("invalid package name `{name}`"),
+                vec![
+                    format!("package names are identifiers, but {reason}"),
+                    format!("choose a different name with `--name`"),
+                ],
+            ),
+            DriverError::ProjectExists { path } => IOError::new(
+                DiagnosticCode::CouldNotCreateProject,
+                format!("`{path}` already exists"),
+                vec![format!(
+                    "`flux new` creates a directory for the project, while `flux init` makes a project of a directory that has no `flux.toml`"
+                )],
+            ),
+            DriverError::CreateProject { path, err } => IOError::new(
+                DiagnosticCode::CouldNotCreateProject,
+                format!("could not create `{path}`"),
+                vec![err],
+            ),
+            DriverError::UnresolvableDependency {
+                name,
+                mut requirements,
+                versions,
+                registry,
+            } => {
+                requirements.push(if versions.is_empty() {
+                    format!("the registry at `{registry}` has no package named `{name}`")
+                } else {
+                    format!(
+                        "the registry at `{registry}` has {}",
+                        versions
+                            .iter()
+                            .map(|version| format!("`{version}`"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    )
+                });
+                IOError::new(
+                    DiagnosticCode::CouldNotResolveDependencies,
+                    format!("could not find a version of `{name}` that matches every requirement on it"),
+                    requirements,
+                )
+            }
+            DriverError::DependencyVersionMismatch {
+                name,
+                requirement,
+                version,
+                path,
+            } => IOError::new(
+                DiagnosticCode::CouldNotResolveDependencies,
+                format!("dependency `{name}` does not match `{requirement}`"),
+                vec![
+                    format!("the package at `{path}` is version `{version}`"),
+                    format!("change the `version` of the dependency, or leave it out to use the package as it is"),
+                ],
+            ),
+            DriverError::DependencyChecksumMismatch { name, version } => IOError::new(
+                DiagnosticCode::DependencyChecksumMismatch,
+                format!("`{name} {version}` has changed since it was pinned in `{LOCKFILE_NAME}`"),
+                vec![format!(
+                    "restore the package in the registry, or remove it from `{LOCKFILE_NAME}` to pin it as it is now"
+                )],
+            ),
+            DriverError::LockfileOutOfDate { path } => IOError::new(
+                DiagnosticCode::LockfileOutOfDate,
+                format!("`{path}` needs to be updated, but `--locked` was passed"),
+                vec![format!(
+                    "build without `--locked` to update the lockfile, and check in the change"
+                )],
+            ),
+            DriverError::ReadLockfile { path, err } => IOError::new(
+                DiagnosticCode::CouldNotReadLockfile,
+                format!("could not read lockfile `{path}`"),
+                vec![
+                    err,
+                    format!("remove it to have the dependencies of the project resolved again"),
+                ],
+            ),
+            DriverError::ReadRegistry { path, err } => IOError::new(
+                DiagnosticCode::CouldNotFetchDependency,
+                format!("could not read registry index `{path}`"),
+                vec![err],
+            ),
+            DriverError::FetchDependency { name, version, err } => IOError::new(
+                DiagnosticCode::CouldNotFetchDependency,
+                format!("could not fetch `{name} {version}`"),
+                vec![err],
+            ),
+            DriverError::CorruptDependency {
+                name,
+                version,
+                path,
+            } => IOError::new(
+                DiagnosticCode::DependencyChecksumMismatch,
+                format!("the archive of `{name} {version}` does not match the checksum in the index of its registry"),
+                vec![
+                    format!("`{path}` was changed or corrupted since it was added to the registry"),
+                ],
+            ),
+            DriverError::FetchGitDependency { url, err } => IOError::new(
+                DiagnosticCode::CouldNotFetchDependency,
+                format!("could not fetch git repository `{url}`"),
+                vec![err],
+            ),
+            DriverError::MissingGitRevision { url, reference } => IOError::new(
+                DiagnosticCode::MissingGitRevision,
+                format!("git repository `{url}` has no {reference}"),
+                vec![format!(
+                    "check the `branch`, `tag` or `rev` of the dependency against the repository"
+                )],
+            ),
+            DriverError::DependencyCycle { cycle } => {
+                let mut help: Vec<_> = cycle
+                    .windows(2)
+                    .map(|pair| format!("`{}` depends on `{}`", pair[0], pair[1]))
+                    .collect();
+                help.push(
+                    "a package can't depend on itself, so move what the packages share into a package of its own"
+                        .to_string(),
+                );
+                IOError::new(
+                    DiagnosticCode::DependencyCycle,
+                    format!("`{}` depends on itself", cycle[0]),
+                    help,
+                )
+            }
         }
     }
 }
+
+#[diagnostic]
+pub enum ConfigError {
+    #[error(
+        location = problem,
+        primary = "could not parse config file",
+        label at problem = "{problem}",
+    )]
+    CouldNotParseConfigFile {
+        #[filespanned]
+        problem: String,
+    },
+    #[error(
+        location = table,
+        primary = "missing config key",
+        label at table = "missing {missing}",
+    )]
+    MissingConfigKey {
+        #[filespanned]
+        table: (),
+        missing: String,
+    },
+    #[error(
+        location = second,
+        primary = "conflicting config keys",
+        label at second = "`{second}` cannot be used along with `{first}`",
+        label at first = "`{first}` is used here",
+        help = "{reason}",
+    )]
+    ConflictingConfigKeys {
+        #[filespanned]
+        first: String,
+        #[filespanned]
+        second: String,
+        reason: String,
+    },
+    #[error(
+        location = value,
+        primary = "invalid config value",
+        label at value = "expected {expected}, found {found}",
+    )]
+    InvalidConfigValue {
+        #[filespanned]
+        value: (),
+        expected: String,
+        found: String,
+    },
+    #[warning(
+        location = key,
+        primary = "unknown config key",
+        label at key = "unknown key `{key}` in {table}",
+        suggestion(maybe_incorrect) at key => candidate = "did you mean `{candidate}`?"
+    )]
+    UnknownConfigKey {
+        #[filespanned]
+        key: String,
+        table: String,
+        candidate: Option<String>,
+    },
+    #[error(
+        location = lint,
+        primary = "unknown lint",
+        label at lint = "unknown lint `{lint}`",
+        help = "the lints are {}" with (
+            Lint::ALL.iter().map(|lint| format!("`{lint}`")).collect::<Vec<_>>().join(", ")
+        ),
+        suggestion(maybe_incorrect) at lint => candidate = "did you mean `{candidate}`?"
+    )]
+    UnknownLint {
+        #[filespanned]
+        lint: String,
+        candidate: Option<String>,
+    },
+}
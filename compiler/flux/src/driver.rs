@@ -1,153 +1,860 @@
-use std::path::{Component, Path, PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
 
-use flux_diagnostics::{Diagnostic, IOError, SourceCache};
-use flux_hir::Package;
-use flux_id::{id, Map};
-use flux_util::Interner;
+use flux_db::Shared;
+use flux_diagnostics::{
+    applicable_suggestions, apply_suggestions, Applicability, Diagnostic, IOError, Lint,
+    LintLevels, MessageFormat, SourceCache,
+};
+use flux_hir::{
+    db::{
+        fn_keys, Context, Database, DefinePackage, FileText, LowerFnBody, PackageBodiesQuery,
+        PackageSource, ParseFile, Prebuilt, PrebuiltPackage, Project,
+    },
+    Package,
+};
+use flux_id::{
+    id::{self, WithPackage},
+    Map,
+};
+use flux_interp::Value;
+use flux_mir::PackageMir;
+use flux_util::{FileId, Interner};
 use tracing::info;
 
 use crate::{
-    cfg::{self, Config},
-    get_config, get_package_entry_file_path, ExitStatus,
+    cache::{self, Cache, CachedPackage},
+    cfg::{self, Config, UserConfig},
+    diagnostics::DriverError,
+    get_config, get_package_entry_file_path, get_user_config,
+    git::Git,
+    graph::PackageGraph,
+    lockfile::{Lockfile, LOCKFILE_NAME},
+    registry::{self, Registry},
+    resolver::{self, Resolution},
+    ExitStatus,
 };
 
 pub(crate) struct Driver {
     compilation_config: flux_hir::Config,
     interner: &'static Interner,
+    db: Database,
     source_cache: SourceCache,
     packages: Map<id::Pkg, Package>,
+    mir: HashMap<id::Pkg, PackageMir>,
+    /// What each package was built from, indexed by package id
+    fingerprints: Vec<u64>,
+    /// The source files of the packages in the project, leaving out its dependencies
+    project_files: Vec<FileId>,
     diagnostics: Vec<Diagnostic>,
+    /// Where the user config is, and where packages that are depended on by version are fetched to
+    flux_home: PathBuf,
+    /// Read the first time a registry is needed, and shared by every project that is resolved after that
+    user_config: Option<UserConfig>,
+    /// Whether the build fails rather than change the lockfile
+    locked: bool,
+    resolution: Resolution,
+    /// The configs of the projects depended on, or `None` if they could not be loaded
+    dependency_configs: HashMap<PathBuf, Option<Config>>,
 }
 
 impl Driver {
-    pub(crate) fn new(compilation_config: flux_hir::Config, interner: &'static Interner) -> Self {
+    pub(crate) fn new(
+        compilation_config: flux_hir::Config,
+        message_format: MessageFormat,
+        interner: &'static Interner,
+    ) -> Self {
+        let mut db = Database::new(Context {
+            interner,
+            config: compilation_config.clone(),
+        });
+        db.set_jobs(default_jobs());
         Self {
+            db,
             compilation_config,
             interner,
-            source_cache: SourceCache::new(interner),
+            source_cache: SourceCache::new(interner).with_message_format(message_format),
             packages: Map::new(),
+            mir: HashMap::new(),
+            fingerprints: vec![],
+            project_files: vec![],
             diagnostics: vec![],
+            flux_home: registry::flux_home(),
+            user_config: None,
+            locked: false,
+            resolution: Resolution::default(),
+            dependency_configs: HashMap::new(),
         }
     }
 
+    pub(crate) fn with_locked(mut self, locked: bool) -> Self {
+        self.locked = locked;
+        self
+    }
+
+    /// Parse and type check on up to `jobs` threads
+    pub(crate) fn with_jobs(mut self, jobs: usize) -> Self {
+        self.db.set_jobs(jobs);
+        self
+    }
+
     pub(crate) fn build_project(
         &mut self,
         project_root: PathBuf,
         flux_config: &Config,
+        lint_levels: &LintLevels,
     ) -> (Vec<id::Pkg>, ExitStatus) {
-        let single_package_project = flux_config.packages.len() == 1;
-
-        let mut all_dependencies = vec![];
-        let mut built_packages = vec![];
-        for package in &flux_config.packages {
-            let package_root = if single_package_project {
-                project_root.clone()
-            } else {
-                project_root.join(&package.name)
-            };
+        let graph = self
+            .fetch_dependencies(&project_root, flux_config)
+            .and_then(|_| self.package_graph(&project_root, flux_config));
+        let graph = match graph {
+            Ok(graph) => graph,
+            Err(err) => {
+                self.report_io_error(err);
+                return (vec![], ExitStatus::Failure);
+            }
+        };
+        let mut sources = vec![];
+        let built_packages = self.collect_packages(&graph, &mut sources);
+        let num_packages = sources.len();
+        let levels = levels(&sources);
+        self.db.set::<Project>((), sources.into());
 
-            if !flux_config.dependencies.map.is_empty() {
-                info!(package =? package.name, "building dependencies");
+        // Debugging output is printed while packages are built, so nothing is loaded from the cache when it is asked for
+        let mut cache =
+            (!self.prints_debug_output()).then(|| Cache::new(&project_root, self.interner));
+        let dependencies: Vec<_> = (0..num_packages as u32)
+            .map(id::Pkg::from)
+            .filter(|package_id| !built_packages.contains(package_id))
+            .collect();
+        let mut cached_mir = HashMap::new();
+        if let Some(cache) = &mut cache {
+            for package_id in &dependencies {
+                let name =
+                    self.db.input::<Project>(())[Into::<u32>::into(*package_id) as usize].name;
+                let fingerprint = self.fingerprint(*package_id);
+                if let Some(cached) = cache.load(self.interner.resolve(&name), fingerprint) {
+                    cached_mir.insert(*package_id, cached.mir);
+                    self.db.set::<Prebuilt>(
+                        *package_id,
+                        Some(Shared::new(PrebuiltPackage {
+                            package: cached.package,
+                            files: cached.files,
+                            fingerprint,
+                        })),
+                    );
+                }
             }
-            let (dependencies, errors) =
-                self.build_dependencies(&package_root, &flux_config.dependencies);
-            all_dependencies.extend(dependencies.iter().copied());
-            errors.into_iter().for_each(|err| err.report());
+        }
 
-            info!(package =? package.name, "building package definitions");
-            let package_id =
-                match self.build_package_definitions(&package.name, &resolve(&package_root)) {
-                    Ok(id) => id,
-                    Err(err) => {
-                        err.report();
-                        continue;
+        // Packages at the same level of the graph don't depend on each other, so the files they start from are parsed at
+        // once. Everything is still reported in the order of the packages, so the output doesn't depend on how many
+        // threads there are.
+        for level in &levels {
+            let entries: Vec<_> = level
+                .iter()
+                .filter(|package_id| self.db.input::<Prebuilt>(**package_id).is_none())
+                .map(|package_id| {
+                    self.db.input::<Project>(())[Into::<u32>::into(*package_id) as usize].entry
+                })
+                .collect();
+            self.db.get_all::<ParseFile>(&entries);
+            for package_id in level {
+                self.db.get::<DefinePackage>(*package_id);
+            }
+        }
+
+        let mut packages = Map::new();
+        let mut files = HashMap::new();
+        for package_id in (0..num_packages as u32).map(id::Pkg::from) {
+            let definitions = self.db.get::<DefinePackage>(package_id);
+            files.insert(package_id, definitions.files.clone());
+            if built_packages.contains(&package_id) {
+                self.project_files.extend(definitions.files.iter().copied());
+            }
+            info!(package =? self.interner.resolve(&definitions.package.name), "building package definitions");
+            for file_id in &definitions.files {
+                let content = self.db.input::<FileText>(*file_id).unwrap_or_default();
+                self.source_cache
+                    .add_input_file(file_id.as_str(self.interner), content.to_string());
+            }
+            self.extend_diagnostics(
+                &definitions.diagnostics,
+                built_packages.contains(&package_id),
+                lint_levels,
+            );
+            packages.insert(definitions.package.clone());
+        }
+
+        // Function bodies only depend on declarations, so every body of a level is lowered and type checked at once
+        for level in &levels {
+            let keys: Vec<_> = level
+                .iter()
+                .filter(|package_id| self.db.input::<Prebuilt>(**package_id).is_none())
+                .flat_map(|package_id| fn_keys(*package_id, packages.get(*package_id)))
+                .collect();
+            self.db.get_all::<LowerFnBody>(&keys);
+        }
+        for package_id in (0..num_packages as u32).map(id::Pkg::from) {
+            info!(package =? self.interner.resolve(&packages.get(package_id).name), "building package bodies");
+            let lowered = self.db.get::<PackageBodiesQuery>(package_id);
+            for debug in &lowered.debug {
+                let mut buf = Vec::new();
+                self.source_cache
+                    .write_diagnostics_to_buffer(std::slice::from_ref(debug), &mut buf);
+                println!("{}", String::from_utf8_lossy(&buf));
+            }
+            self.extend_diagnostics(
+                &lowered.diagnostics,
+                built_packages.contains(&package_id),
+                lint_levels,
+            );
+            packages
+                .get_mut(package_id)
+                .set_bodies(lowered.bodies.clone());
+        }
+        self.packages = packages;
+
+        // Bodies that failed to type check contain poisoned expressions, which have no meaning in mir
+        if !self.has_errors() {
+            for package_id in (0..num_packages as u32).map(id::Pkg::from) {
+                match cached_mir.remove(&package_id) {
+                    Some(mir) => {
+                        self.mir.insert(package_id, mir);
                     }
+                    None => self.build_package_mir(package_id),
+                }
+            }
+        }
+
+        if let Some(mut cache) = cache.filter(|_| !self.has_errors()) {
+            for package_id in dependencies {
+                if self.db.input::<Prebuilt>(package_id).is_some() {
+                    continue;
+                }
+                let package = self.packages.get(package_id);
+                let cached = CachedPackage {
+                    package: package.clone(),
+                    mir: self.mir.remove(&package_id).unwrap_or_default(),
+                    files: files.remove(&package_id).unwrap_or_default(),
                 };
-            built_packages.push(package_id);
-            self.packages
-                .get_mut(package_id)
-                .set_dependencies(dependencies);
+                cache.store(
+                    self.interner.resolve(&package.name),
+                    self.fingerprint(package_id),
+                    &cached,
+                );
+                self.mir.insert(package_id, cached.mir);
+            }
+            cache.remove_unused();
         }
 
-        let mut exprs = Map::new();
-        for (package_id, package) in self
-            .packages
-            .iter()
-            .filter(|(package_id, _)| !all_dependencies.contains(package_id))
-        {
-            info!(package =? self.interner.resolve(&package.name), "building package bodies");
-            flux_hir::build_package_bodies(
-                package_id,
-                &self.packages,
-                &mut exprs,
-                self.interner,
-                &mut self.diagnostics,
-                &self.source_cache,
-            );
+        (built_packages, ExitStatus::Success)
+    }
+
+    /// Read the config of the project at `project_root`, keeping what is wrong with it to be reported with the rest
+    ///
+    /// A dependency's config is not the project's to fix, so only its errors are kept. `None` is returned if there were
+    /// any, once they have been kept.
+    pub(crate) fn load_config(
+        &mut self,
+        project_root: &Path,
+        is_dependency: bool,
+    ) -> Option<Config> {
+        // Dependencies are loaded both to resolve versions and to be built, but their errors are only kept once
+        if is_dependency {
+            if let Some(config) = self.dependency_configs.get(&resolve(project_root)) {
+                return config.clone();
+            }
+        }
+        let config = match get_config(project_root, &mut self.source_cache) {
+            Ok((config, diagnostics)) => {
+                let has_errors = diagnostics.iter().any(Diagnostic::is_error);
+                self.diagnostics.extend(
+                    diagnostics
+                        .into_iter()
+                        .filter(|diagnostic| !is_dependency || diagnostic.is_error()),
+                );
+                (!has_errors).then_some(config)
+            }
+            Err(err) => {
+                self.report_io_error(err);
+                None
+            }
+        };
+        if is_dependency {
+            self.dependency_configs
+                .insert(resolve(project_root), config.clone());
+        }
+        config
+    }
+
+    /// The registry the project at `project_root` fetches its dependencies from
+    ///
+    /// The user config is read the first time this is called, keeping what is wrong with it to be reported with the
+    /// rest.
+    pub(crate) fn registry(&mut self, project_root: &Path, flux_config: &Config) -> Registry {
+        if self.user_config.is_none() {
+            let user_config = match get_user_config(&self.flux_home, &mut self.source_cache) {
+                Ok((user_config, diagnostics)) => {
+                    self.diagnostics.extend(diagnostics);
+                    user_config
+                }
+                Err(err) => {
+                    self.report_io_error(err);
+                    UserConfig::default()
+                }
+            };
+            self.user_config = Some(user_config);
+        }
+        Registry::for_project(
+            project_root,
+            flux_config,
+            self.user_config.as_ref().unwrap(),
+            &self.flux_home,
+        )
+    }
+
+    /// Resolve the dependencies of the project, and fetch the ones that haven't been yet
+    pub(crate) fn fetch_dependencies(
+        &mut self,
+        project_root: &Path,
+        flux_config: &Config,
+    ) -> Result<(), IOError> {
+        let registry = self.registry(project_root, flux_config);
+        let resolution = self.resolve_dependencies(project_root, flux_config, &registry)?;
+        for (name, package) in &resolution.packages {
+            registry.fetch(name, &package.version, &package.checksum)?;
         }
+        self.resolution = resolution;
+        Ok(())
+    }
+
+    /// The graph of the packages of the project and of every project it depends on, once they have been fetched
+    pub(crate) fn package_graph(
+        &mut self,
+        project_root: &Path,
+        flux_config: &Config,
+    ) -> Result<PackageGraph, IOError> {
+        // The configs of dependencies are loaded through the driver, which can't be borrowed along with its resolution
+        let resolution = std::mem::take(&mut self.resolution);
+        let graph = PackageGraph::build(project_root, flux_config, &resolution, &mut |path| {
+            self.load_config(path, true)
+        });
+        self.resolution = resolution;
+        graph
+    }
 
+    /// Pick the versions of the packages the project needs from the registry, and pin them in its lockfile
+    pub(crate) fn resolve_dependencies(
+        &mut self,
+        project_root: &Path,
+        flux_config: &Config,
+        registry: &Registry,
+    ) -> Result<Resolution, IOError> {
+        let lockfile = Lockfile::read(project_root)?;
+        let resolution = resolver::resolve(
+            project_root,
+            flux_config,
+            registry,
+            &Git::new(&self.flux_home),
+            lockfile.as_ref(),
+            &mut |path| self.load_config(path, true),
+        )?;
+
+        let resolved = resolution.lockfile();
+        // Projects without dependencies from the registry don't need a lockfile, so they aren't given one
+        let unchanged = match &lockfile {
+            Some(lockfile) => *lockfile == resolved,
+            None => resolved.packages.is_empty(),
+        };
+        if !unchanged {
+            if self.locked {
+                return Err(DriverError::LockfileOutOfDate {
+                    path: project_root.join(LOCKFILE_NAME).display().to_string(),
+                }
+                .to_io_error());
+            }
+            resolved.write(project_root)?;
+        }
+        Ok(resolution)
+    }
+
+    /// Report everything that was found while building the project
+    pub(crate) fn report_diagnostics(&self) {
         self.source_cache
             .report_diagnostics(self.diagnostics.iter());
+    }
 
-        (built_packages, ExitStatus::Success)
+    /// Apply the suggestions of everything found in the project that are at least as confident as `applicability`,
+    /// rewriting the files they are in
+    ///
+    /// Returns the path of every file that was changed, along with the number of suggestions applied to it.
+    pub(crate) fn apply_suggestions(
+        &self,
+        applicability: Applicability,
+    ) -> Result<Vec<(String, usize)>, IOError> {
+        let mut fixed_files = vec![];
+        for file_id in &self.project_files {
+            let suggestions = applicable_suggestions(&self.diagnostics, *file_id, applicability);
+            if suggestions.is_empty() {
+                continue;
+            }
+            let path = file_id.as_str(self.interner);
+            let content = self.source_cache.get_file_content(file_id);
+            let (fixed, num_applied) = apply_suggestions(&content, &suggestions);
+            std::fs::write(path, fixed).map_err(|err| {
+                DriverError::WriteOutputFile {
+                    path: path.to_string(),
+                    err: err.to_string(),
+                }
+                .to_io_error()
+            })?;
+            fixed_files.push((path.to_string(), num_applied));
+        }
+        Ok(fixed_files)
+    }
+
+    /// Whether anything reported so far should stop the project from being compiled, which warnings don't
+    pub(crate) fn has_errors(&self) -> bool {
+        self.diagnostics.iter().any(Diagnostic::is_error)
     }
 
-    fn build_dependencies(
+    /// Add the diagnostics of a package at the levels they are configured with
+    ///
+    /// Dependencies are not the project's to fix, so no lints are reported for them.
+    fn extend_diagnostics(
         &mut self,
+        diagnostics: &[Diagnostic],
+        is_built: bool,
+        lint_levels: &LintLevels,
+    ) {
+        let diagnostics = diagnostics.iter().cloned();
+        if is_built {
+            self.diagnostics
+                .extend(diagnostics.filter_map(|diagnostic| lint_levels.apply(diagnostic)));
+        } else {
+            self.diagnostics
+                .extend(diagnostics.filter(|diagnostic| Lint::of(diagnostic.code).is_none()));
+        }
+    }
+
+    pub(crate) fn report_io_error(&self, err: IOError) {
+        self.source_cache.report_io_error(err);
+    }
+
+    /// Write out anything that could only be written once everything has been reported, like a SARIF log
+    pub(crate) fn finish_reporting(&self) {
+        self.source_cache.finish_reporting();
+    }
+
+    /// Whether any debugging output was asked for
+    fn prints_debug_output(&self) -> bool {
+        let config = &self.compilation_config;
+        config.debug_cst || config.debug_item_tree || config.debug_bodies || config.debug_mir
+    }
+
+    fn fingerprint(&self, package_id: id::Pkg) -> u64 {
+        self.fingerprints[Into::<u32>::into(package_id) as usize]
+    }
+
+    fn build_package_mir(&mut self, package_id: id::Pkg) {
+        info!(package =? self.interner.resolve(&self.packages.get(package_id).name), "building package mir");
+        let mir = flux_mir::lower_package(
+            package_id,
+            &self.packages,
+            self.interner,
+            &mut self.diagnostics,
+        );
+        self.mir.insert(package_id, mir);
+        if self.compilation_config.debug_mir {
+            println!("{}", self.mir_text(package_id));
+        }
+    }
+
+    /// The mir of a package that has already been built, as `--debug-mir` prints it
+    pub(crate) fn mir_text(&self, package_id: id::Pkg) -> String {
+        self.mir[&package_id].to_string(package_id, &self.packages, self.interner)
+    }
+
+    /// Compile a package that has already been built into an executable at `target/<build type>/<package name>`
+    ///
+    /// Packages without a `main` function are libraries, and have nothing to compile on their own.
+    pub(crate) fn build_executable(
+        &self,
+        package_id: id::Pkg,
+        project_root: &Path,
+        build_cfg: &cfg::Build,
+    ) -> ExitStatus {
+        if self.has_errors() {
+            return ExitStatus::Failure;
+        }
+
+        let package = self.packages.get(package_id);
+        let name = self.interner.resolve(&package.name);
+        let main = match package.get_main_fn(self.interner) {
+            Some(main) => main,
+            None => {
+                info!(package =? name, "package has no `main` function, not building an executable");
+                return ExitStatus::Success;
+            }
+        };
+
+        info!(package =? name, "generating code");
+        let options = flux_codegen::Options {
+            opt_level: match build_cfg.opt_level {
+                cfg::OptLevel::None => flux_codegen::OptLevel::None,
+                cfg::OptLevel::Low | cfg::OptLevel::Medium => flux_codegen::OptLevel::Speed,
+                cfg::OptLevel::High => flux_codegen::OptLevel::SpeedAndSize,
+            },
+            debug_assertions: matches!(build_cfg.ty, cfg::BuildType::Debug),
+        };
+        let object = match flux_codegen::emit_object(
+            name,
+            main.in_pkg(package_id),
+            &self.packages,
+            &self.mir,
+            self.interner,
+            &options,
+        ) {
+            Ok(object) => object,
+            Err(diagnostic) => {
+                self.source_cache.report_diagnostic(&diagnostic);
+                return ExitStatus::Failure;
+            }
+        };
+
+        let out_dir = out_dir(project_root, build_cfg);
+        let object_path = out_dir.join(format!("{name}.o"));
+        if self.write_output_file(&object_path, &object) == ExitStatus::Failure {
+            return ExitStatus::Failure;
+        }
+
+        info!(package =? name, "linking");
+        let executable_path = out_dir.join(name);
+        if let Err(output) = flux_codegen::link(&object_path, &executable_path) {
+            self.report_io_error(
+                DriverError::Link {
+                    package: name.to_string(),
+                    output,
+                }
+                .to_io_error(),
+            );
+            return ExitStatus::Failure;
+        }
+        ExitStatus::Success
+    }
+
+    /// Translate a package that has already been built into C, writing a translation unit for it and each of its
+    /// dependencies to `target/<build type>/c/<package>/`
+    pub(crate) fn emit_c_source(
+        &self,
+        package_id: id::Pkg,
         project_root: &Path,
-        dependencies: &cfg::Dependencies,
-    ) -> (Vec<id::Pkg>, Vec<IOError>) {
-        let mut built_packages = vec![];
-        let mut errors = vec![];
-        for (_, dependency) in dependencies.map.iter() {
-            let path = dependency
-                .path
-                .as_ref()
-                .expect("no other type of dependency than local right now");
-            let path = resolve(&project_root.join(path));
-
-            let flux_config = match get_config(&path) {
-                Ok(cfg) => cfg,
+        build_cfg: &cfg::Build,
+    ) -> ExitStatus {
+        if self.has_errors() {
+            return ExitStatus::Failure;
+        }
+
+        let package = self.packages.get(package_id);
+        let name = self.interner.resolve(&package.name);
+        let main = match package.get_main_fn(self.interner) {
+            Some(main) => main,
+            None => {
+                info!(package =? name, "package has no `main` function, not generating c");
+                return ExitStatus::Success;
+            }
+        };
+
+        info!(package =? name, "generating c");
+        let units = match flux_codegen::emit_c(
+            main.in_pkg(package_id),
+            &self.packages,
+            &self.mir,
+            self.interner,
+        ) {
+            Ok(units) => units,
+            Err(diagnostic) => {
+                self.source_cache.report_diagnostic(&diagnostic);
+                return ExitStatus::Failure;
+            }
+        };
+
+        let out_dir = out_dir(project_root, build_cfg).join("c").join(name);
+        for unit in units {
+            let path = out_dir.join(format!("{}.c", unit.package));
+            if self.write_output_file(&path, unit.source.as_bytes()) == ExitStatus::Failure {
+                return ExitStatus::Failure;
+            }
+        }
+        ExitStatus::Success
+    }
+
+    /// Compile the public functions in the root module of a package into a WebAssembly module that exports them
+    ///
+    /// Generic functions cannot be exported, since there is no way to tell which types they should be compiled for.
+    pub(crate) fn emit_wasm_module(
+        &self,
+        package_id: id::Pkg,
+        project_root: &Path,
+        build_cfg: &cfg::Build,
+    ) -> ExitStatus {
+        if self.has_errors() {
+            return ExitStatus::Failure;
+        }
+
+        let package = self.packages.get(package_id);
+        let name = self.interner.resolve(&package.name);
+        let exports: Vec<_> = package
+            .public_fns()
+            .filter(|fn_id| {
+                let fn_decl = package.item_tree().functions.get(*fn_id);
+                fn_decl.generic_params.types.len() == 0
+            })
+            .map(|fn_id| fn_id.in_pkg(package_id))
+            .collect();
+        if exports.is_empty() {
+            info!(package =? name, "package has no public functions to export, not generating wasm");
+            return ExitStatus::Success;
+        }
+
+        info!(package =? name, "generating wasm");
+        let module =
+            match flux_codegen::emit_wasm(&exports, &self.packages, &self.mir, self.interner) {
+                Ok(module) => module,
                 Err(diagnostic) => {
-                    errors.push(diagnostic);
-                    continue;
+                    self.source_cache.report_diagnostic(&diagnostic);
+                    return ExitStatus::Failure;
                 }
             };
 
-            let (mut packages, _) = self.build_project(path, &flux_config);
-            built_packages.append(&mut packages);
+        let out_dir = out_dir(project_root, build_cfg).join("wasm32");
+        let wat_path = out_dir.join(format!("{name}.wat"));
+        if self.write_output_file(&wat_path, module.text.as_bytes()) == ExitStatus::Failure {
+            return ExitStatus::Failure;
         }
-        (built_packages, errors)
+        self.write_output_file(&out_dir.join(format!("{name}.wasm")), &module.binary)
     }
 
-    fn build_package_definitions(
-        &mut self,
-        name: &str,
-        package_root: &Path,
-    ) -> Result<id::Pkg, IOError> {
-        let (entry_path, content) = match get_package_entry_file_path(package_root, name) {
-            Ok(x) => x,
-            Err(err) => {
-                return Err(err);
+    /// Interpret the `main` function of a package that has already been built
+    pub(crate) fn run_package(&self, package_id: id::Pkg) -> ExitStatus {
+        if self.has_errors() {
+            return ExitStatus::Failure;
+        }
+
+        let package = self.packages.get(package_id);
+        let main = match package.get_main_fn(self.interner) {
+            Some(main) => main,
+            None => {
+                self.report_io_error(
+                    DriverError::NoMainFunction {
+                        package: self.interner.resolve(&package.name).to_string(),
+                    }
+                    .to_io_error(),
+                );
+                return ExitStatus::Failure;
             }
         };
 
-        let name = self.interner.get_or_intern(name);
-        let file_id = self
-            .source_cache
-            .add_input_file(&entry_path, content.clone());
+        info!(package =? self.interner.resolve(&package.name), "running package");
+        match flux_interp::run(main.in_pkg(package_id), &self.packages, self.interner) {
+            Ok(val) => {
+                let status = match val {
+                    Value::Int(int) => int.val,
+                    _ => 0,
+                };
+                println!("process exited with status {status}");
+                ExitStatus::Success
+            }
+            Err(diagnostic) => {
+                self.source_cache.report_diagnostic(&diagnostic);
+                ExitStatus::Failure
+            }
+        }
+    }
 
-        let package = flux_hir::build_package_definitions(
-            name,
-            file_id,
-            &content,
-            &mut self.source_cache,
+    fn write_output_file(&self, path: &Path, contents: &[u8]) -> ExitStatus {
+        let result = match path.parent() {
+            Some(dir) => std::fs::create_dir_all(dir),
+            None => Ok(()),
+        }
+        .and_then(|_| std::fs::write(path, contents));
+        if let Err(err) = result {
+            self.report_io_error(
+                DriverError::WriteOutputFile {
+                    path: path.display().to_string(),
+                    err: err.to_string(),
+                }
+                .to_io_error(),
+            );
+            return ExitStatus::Failure;
+        }
+        ExitStatus::Success
+    }
+
+    /// Read the entry file of every package in the graph, dependencies first
+    ///
+    /// Returns the ids of the packages of the project itself.
+    fn collect_packages(
+        &mut self,
+        graph: &PackageGraph,
+        sources: &mut Vec<PackageSource>,
+    ) -> Vec<id::Pkg> {
+        // Packages whose entry file can't be read are left out, along with what depends on them
+        let mut package_ids: Vec<Option<id::Pkg>> = vec![];
+        for package in &graph.packages {
+            let dependencies: Vec<_> = package
+                .dependencies
+                .iter()
+                .filter_map(|dependency| package_ids[*dependency])
+                .collect();
+            let (entry_path, content) =
+                match get_package_entry_file_path(&package.root, &package.name) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        self.report_io_error(err);
+                        package_ids.push(None);
+                        continue;
+                    }
+                };
+            let entry = FileId::new(self.interner.get_or_intern(&entry_path));
+            self.db.set::<FileText>(entry, Some(content.into()));
+            let package_id = id::Pkg::from(sources.len() as u32);
+            self.fingerprints.push(cache::fingerprint(
+                &package.project_root,
+                &package.root,
+                package_id,
+                dependencies
+                    .iter()
+                    .map(|dependency| self.fingerprint(*dependency)),
+            ));
+            sources.push(PackageSource {
+                name: self.interner.get_or_intern(&package.name),
+                entry,
+                dependencies,
+            });
+            package_ids.push(Some(package_id));
+        }
+        graph
+            .roots
+            .iter()
+            .filter_map(|root| package_ids[*root])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+impl Driver {
+    /// Use `flux_home` rather than the flux home directory of the user, so that tests don't share a cache with it
+    pub(crate) fn with_flux_home(mut self, flux_home: PathBuf) -> Self {
+        self.flux_home = flux_home;
+        self
+    }
+
+    /// The codes of everything reported while building the project
+    pub(crate) fn diagnostic_codes(&self) -> Vec<flux_diagnostics::DiagnosticCode> {
+        self.diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.code)
+            .collect()
+    }
+
+    /// The names of the packages that were loaded from the build cache rather than built
+    pub(crate) fn prebuilt_packages(&self) -> Vec<String> {
+        let project = self.db.input::<Project>(());
+        project
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| {
+                self.db
+                    .input::<Prebuilt>(id::Pkg::from(*idx as u32))
+                    .is_some()
+            })
+            .map(|(_, source)| self.interner.resolve(&source.name).to_string())
+            .collect()
+    }
+
+    /// Everything reported while building the project, as it would be printed
+    pub(crate) fn rendered_diagnostics(&self) -> String {
+        let mut buf = Vec::new();
+        self.source_cache
+            .write_diagnostics_to_buffer(&self.diagnostics, &mut buf);
+        String::from_utf8_lossy(&buf).into_owned()
+    }
+
+    /// What the `main` function of a package that has already been built returns when it is interpreted, or the code of
+    /// what went wrong running it
+    pub(crate) fn run_value(
+        &self,
+        package_id: id::Pkg,
+    ) -> Result<i128, flux_diagnostics::DiagnosticCode> {
+        let Some(main) = self.packages.get(package_id).get_main_fn(self.interner) else {
+            return Err(flux_diagnostics::DiagnosticCode::NoMainFunction);
+        };
+        match flux_interp::run(main.in_pkg(package_id), &self.packages, self.interner) {
+            Ok(Value::Int(int)) => Ok(int.val),
+            Ok(_) => Ok(0),
+            Err(diagnostic) => Err(diagnostic.code),
+        }
+    }
+
+    /// The code of what went wrong running a package that has already been built, if anything did
+    pub(crate) fn run_error(
+        &self,
+        package_id: id::Pkg,
+    ) -> Option<flux_diagnostics::DiagnosticCode> {
+        let Some(main) = self.packages.get(package_id).get_main_fn(self.interner) else {
+            return Some(flux_diagnostics::DiagnosticCode::NoMainFunction);
+        };
+        flux_interp::run(main.in_pkg(package_id), &self.packages, self.interner)
+            .err()
+            .map(|diagnostic| diagnostic.code)
+    }
+
+    /// The code of what went wrong translating a package that has already been built to C, if anything did
+    pub(crate) fn emit_c_error(
+        &self,
+        package_id: id::Pkg,
+    ) -> Option<flux_diagnostics::DiagnosticCode> {
+        let main = self.packages.get(package_id).get_main_fn(self.interner)?;
+        flux_codegen::emit_c(
+            main.in_pkg(package_id),
+            &self.packages,
+            &self.mir,
             self.interner,
-            &mut self.diagnostics,
-        );
-        let package_id = self.packages.insert(package);
-        Ok(package_id)
+        )
+        .err()
+        .map(|diagnostic| diagnostic.code)
+    }
+}
+
+/// How many threads to build on when it isn't given, which is one for every core
+pub(crate) fn default_jobs() -> usize {
+    std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+}
+
+/// The packages grouped into levels, where each package only depends on packages of earlier levels, starting with the
+/// ones that don't depend on anything
+fn levels(sources: &[PackageSource]) -> Vec<Vec<id::Pkg>> {
+    let mut levels: Vec<Vec<id::Pkg>> = vec![];
+    let mut package_levels: Vec<usize> = vec![];
+    for (idx, source) in sources.iter().enumerate() {
+        // Packages come after the ones they depend on
+        let level = source
+            .dependencies
+            .iter()
+            .map(|dependency| package_levels[Into::<u32>::into(*dependency) as usize] + 1)
+            .max()
+            .unwrap_or(0);
+        if level == levels.len() {
+            levels.push(vec![]);
+        }
+        levels[level].push(id::Pkg::from(idx as u32));
+        package_levels.push(level);
     }
+    levels
 }
 
 fn resolve(path: &Path) -> PathBuf {
@@ -161,3 +868,10 @@ fn resolve(path: &Path) -> PathBuf {
     }
     resolved
 }
+
+fn out_dir(project_root: &Path, build_cfg: &cfg::Build) -> PathBuf {
+    project_root.join("target").join(match build_cfg.ty {
+        cfg::BuildType::Debug => "debug",
+        cfg::BuildType::Release => "release",
+    })
+}
@@ -0,0 +1,225 @@
+//! Dependencies that are checked out of git repositories
+//!
+//! Every repository is fetched into a bare clone of its own, which only has to be fetched again when a reference moves
+//! or a commit it doesn't have is needed. Each commit that is depended on is then checked out into a directory of its
+//! own with `git archive`, which leaves out submodules and the `.git` directory, so that a checkout is nothing but the
+//! files of the package at that commit:
+//!
+//! ```text
+//! git/db/<name>-<url hash>
+//! git/checkouts/<name>-<url hash>/<commit>
+//! ```
+//!
+//! The `git` command is used rather than a library, so that repositories are fetched with the credentials and settings
+//! the user already has.
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use flux_diagnostics::IOError;
+use sha2::{Digest, Sha256};
+
+use crate::diagnostics::DriverError;
+
+/// A repository, along with what to check out of it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GitSource {
+    pub url: String,
+    pub reference: GitReference,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GitReference {
+    DefaultBranch,
+    Branch(String),
+    Tag(String),
+    /// A commit, or anything else `git rev-parse` understands
+    Rev(String),
+}
+
+/// How a source is written in the lockfile, before the `#` and the commit it was pinned to
+impl fmt::Display for GitSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "git+{}", self.url)?;
+        match &self.reference {
+            GitReference::DefaultBranch => Ok(()),
+            GitReference::Branch(branch) => write!(f, "?branch={branch}"),
+            GitReference::Tag(tag) => write!(f, "?tag={tag}"),
+            GitReference::Rev(rev) => write!(f, "?rev={rev}"),
+        }
+    }
+}
+
+impl fmt::Display for GitReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitReference::DefaultBranch => write!(f, "default branch"),
+            GitReference::Branch(branch) => write!(f, "branch `{branch}`"),
+            GitReference::Tag(tag) => write!(f, "tag `{tag}`"),
+            GitReference::Rev(rev) => write!(f, "revision `{rev}`"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Git {
+    /// Where repositories are fetched to
+    db: PathBuf,
+    /// Where the commits that are depended on are checked out
+    checkouts: PathBuf,
+}
+
+impl Git {
+    pub fn new(flux_home: &Path) -> Self {
+        Self {
+            db: flux_home.join("git").join("db"),
+            checkouts: flux_home.join("git").join("checkouts"),
+        }
+    }
+
+    /// The commit the reference of `source` is at, once the repository has been fetched
+    pub fn resolve(&self, source: &GitSource) -> Result<String, IOError> {
+        let db = self.fetch(source)?;
+        let rev = match &source.reference {
+            GitReference::DefaultBranch => "refs/flux/HEAD".to_string(),
+            GitReference::Branch(branch) => format!("refs/flux/heads/{branch}"),
+            GitReference::Tag(tag) => format!("refs/flux/tags/{tag}"),
+            GitReference::Rev(rev) => rev.clone(),
+        };
+        find_commit(&db, &rev).ok_or_else(|| {
+            DriverError::MissingGitRevision {
+                url: source.url.clone(),
+                reference: source.reference.to_string(),
+            }
+            .to_io_error()
+        })
+    }
+
+    /// Check out `commit` of the repository of `source`, unless it already has been, returning where it is
+    ///
+    /// The repository is only fetched if it doesn't have the commit, like when it was pinned by a lockfile on another
+    /// machine.
+    pub fn checkout(&self, source: &GitSource, commit: &str) -> Result<PathBuf, IOError> {
+        let checkout = checkout_path(&self.checkouts, &source.url, commit);
+        if checkout.exists() {
+            return Ok(checkout);
+        }
+        let mut db = self.db.join(repository_name(&source.url));
+        if find_commit(&db, commit).is_none() {
+            db = self.fetch(source)?;
+            if find_commit(&db, commit).is_none() {
+                return Err(DriverError::MissingGitRevision {
+                    url: source.url.clone(),
+                    reference: GitReference::Rev(commit.to_string()).to_string(),
+                }
+                .to_io_error());
+            }
+        }
+
+        let fetch_error = |err: String| {
+            DriverError::FetchGitDependency {
+                url: source.url.clone(),
+                err,
+            }
+            .to_io_error()
+        };
+        let archive = git(&db, &["archive", "--format=tar", commit]).map_err(fetch_error)?;
+        // A checkout is only moved into place once all of it has been written, so that one that was cut short isn't
+        // mistaken for a commit that was checked out
+        let partial = checkout.with_extension("partial");
+        let _ = fs::remove_dir_all(&partial);
+        tar::Archive::new(&archive[..])
+            .unpack(&partial)
+            .and_then(|_| fs::rename(&partial, &checkout))
+            .map_err(|err| fetch_error(err.to_string()))?;
+        Ok(checkout)
+    }
+
+    /// Fetch every branch and tag of the repository of `source` into its bare clone, returning where that is
+    ///
+    /// They are fetched into a namespace of their own, so that branches that were removed from the repository don't
+    /// linger as if they hadn't been.
+    fn fetch(&self, source: &GitSource) -> Result<PathBuf, IOError> {
+        let db = self.db.join(repository_name(&source.url));
+        let fetch_error = |err: String| {
+            DriverError::FetchGitDependency {
+                url: source.url.clone(),
+                err,
+            }
+            .to_io_error()
+        };
+        if !db.exists() {
+            fs::create_dir_all(&db).map_err(|err| fetch_error(err.to_string()))?;
+            git(&db, &["init", "--bare", "--quiet"]).map_err(fetch_error)?;
+        }
+        git(
+            &db,
+            &[
+                "fetch",
+                "--quiet",
+                "--force",
+                "--no-tags",
+                "--prune",
+                // A url starting with `-` would otherwise be taken as an option
+                "--",
+                &source.url,
+                "+HEAD:refs/flux/HEAD",
+                "+refs/heads/*:refs/flux/heads/*",
+                "+refs/tags/*:refs/flux/tags/*",
+            ],
+        )
+        .map_err(fetch_error)?;
+        Ok(db)
+    }
+}
+
+/// The full hash of the commit `rev` is in the repository at `db`, if it has it
+fn find_commit(db: &Path, rev: &str) -> Option<String> {
+    let output = git(
+        db,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{rev}^{{commit}}"),
+        ],
+    )
+    .ok()?;
+    Some(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// Where `commit` of the repository at `url` is checked out to in `dir`, which is laid out like the checkouts of
+/// [`Git`]
+pub fn checkout_path(dir: &Path, url: &str, commit: &str) -> PathBuf {
+    dir.join(repository_name(url)).join(commit)
+}
+
+/// The name of the directory a repository is fetched to, which is named after the repository so that it can be found
+/// by hand, and has a hash of its url so that repositories that share a name don't share a directory
+fn repository_name(url: &str) -> String {
+    let name = url
+        .trim_end_matches('/')
+        .rsplit(['/', '\\', ':'])
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(".git");
+    let hash = format!("{:x}", Sha256::digest(url.as_bytes()));
+    format!("{name}-{}", &hash[..16])
+}
+
+/// Run `git` in the repository at `db`, returning what it wrote to stdout
+fn git(db: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let output = Command::new("git")
+        .arg("--git-dir")
+        .arg(db)
+        .args(args)
+        .output()
+        .map_err(|err| format!("could not run `git`: {err}"))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(output.stdout)
+}
@@ -0,0 +1,331 @@
+//! The graph of the packages a project is built from
+//!
+//! Every project that is depended on is added once, by its canonical path, however many packages depend on it and
+//! however they refer to it, so that each of its packages is only built once. Packages come after the ones they depend
+//! on, which is the order they are built in.
+//!
+//! A project that depends on itself, directly or through others, could never be built, since each of its packages
+//! would have to be built before itself. That is reported along with the packages the cycle goes through.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use flux_diagnostics::IOError;
+use tracing::info;
+
+use crate::{cfg::Config, diagnostics::DriverError, resolver::Resolution};
+
+#[derive(Debug, Default)]
+pub struct PackageGraph {
+    /// Every package comes after the ones it depends on
+    pub packages: Vec<GraphPackage>,
+    /// The packages of the project itself, by their index in `packages`
+    pub roots: Vec<usize>,
+    /// The packages of every project that has been added, by the canonical path of the project
+    projects: HashMap<PathBuf, Vec<usize>>,
+}
+
+#[derive(Debug)]
+pub struct GraphPackage {
+    pub name: String,
+    pub version: String,
+    pub project_root: PathBuf,
+    /// The directory with the `src` directory of the package
+    pub root: PathBuf,
+    pub origin: Origin,
+    /// The packages this one depends on, by their index in the graph
+    pub dependencies: Vec<usize>,
+}
+
+/// Where the project of a package came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Origin {
+    /// The project that is being built
+    Project,
+    Path,
+    Registry,
+    /// A git repository, as its source is written in the lockfile
+    Git(String),
+}
+
+impl PackageGraph {
+    /// The graph of the project at `project_root`, along with every project it depends on
+    ///
+    /// Dependencies from the registry or from git are looked up in `resolution`, and left out if they aren't in it,
+    /// since that has already been reported. `load_config` reads the config of a project, reporting what is wrong with
+    /// it, and projects whose config can't be read are left out too.
+    pub fn build(
+        project_root: &Path,
+        config: &Config,
+        resolution: &Resolution,
+        load_config: &mut dyn FnMut(&Path) -> Option<Config>,
+    ) -> Result<Self, IOError> {
+        let mut builder = GraphBuilder {
+            graph: Self::default(),
+            resolution,
+            load_config,
+            visiting: vec![],
+        };
+        let roots = builder.add_project(project_root, config, Origin::Project)?;
+        Ok(Self {
+            roots,
+            ..builder.graph
+        })
+    }
+
+    /// The graph as a tree, with the project at the root, like `flux tree` prints it
+    ///
+    /// The dependencies of a package are only listed the first time it is printed, and it is marked with `(*)` after
+    /// that.
+    pub fn render(&self) -> String {
+        let mut tree = String::new();
+        let mut printed = HashSet::new();
+        for root in &self.roots {
+            self.render_package(*root, "", "", &mut printed, &mut tree);
+        }
+        tree
+    }
+
+    fn render_package(
+        &self,
+        idx: usize,
+        prefix: &str,
+        children_prefix: &str,
+        printed: &mut HashSet<usize>,
+        tree: &mut String,
+    ) {
+        let package = &self.packages[idx];
+        write!(tree, "{prefix}{}", package.name).unwrap();
+        if !package.version.is_empty() {
+            write!(tree, " {}", package.version).unwrap();
+        }
+        match &package.origin {
+            Origin::Project => {}
+            Origin::Path => write!(tree, " ({})", package.project_root.display()).unwrap(),
+            Origin::Registry => write!(tree, " (registry)").unwrap(),
+            Origin::Git(source) => {
+                // The whole commit is more than anyone reads
+                let source = match source.split_once('#') {
+                    Some((source, commit)) => {
+                        format!("{source}#{}", &commit[..commit.len().min(8)])
+                    }
+                    None => source.clone(),
+                };
+                write!(tree, " ({source})").unwrap()
+            }
+        }
+        if !printed.insert(idx) && !package.dependencies.is_empty() {
+            tree.push_str(" (*)\n");
+            return;
+        }
+        tree.push('\n');
+        for (i, dependency) in package.dependencies.iter().enumerate() {
+            let (branch, indent) = if i + 1 == package.dependencies.len() {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            self.render_package(
+                *dependency,
+                &format!("{children_prefix}{branch}"),
+                &format!("{children_prefix}{indent}"),
+                printed,
+                tree,
+            );
+        }
+    }
+}
+
+struct GraphBuilder<'a> {
+    graph: PackageGraph,
+    resolution: &'a Resolution,
+    load_config: &'a mut dyn FnMut(&Path) -> Option<Config>,
+    /// The packages whose dependencies are being added, along with the canonical path of their project, from the
+    /// project that is being built to the one that was reached last
+    visiting: Vec<(PathBuf, String)>,
+}
+
+impl GraphBuilder<'_> {
+    /// Add the packages of a project and of every project it depends on, returning the indices of its own
+    fn add_project(
+        &mut self,
+        project_root: &Path,
+        config: &Config,
+        origin: Origin,
+    ) -> Result<Vec<usize>, IOError> {
+        let key = fs::canonicalize(project_root).unwrap_or_else(|_| project_root.to_path_buf());
+        if let Some(packages) = self.graph.projects.get(&key) {
+            return Ok(packages.clone());
+        }
+
+        let mut packages = vec![];
+        for package in &config.packages {
+            let package_root = if config.workspace {
+                project_root.join(&package.name)
+            } else {
+                project_root.to_path_buf()
+            };
+            if !config.dependencies.map.is_empty() {
+                info!(package =? package.name, "collecting dependencies");
+            }
+
+            self.visiting.push((key.clone(), package.name.clone()));
+            let mut dependencies = vec![];
+            for (name, dependency) in config.dependencies.iter() {
+                let (path, dependency_origin) = match (&dependency.path, &dependency.git) {
+                    (Some(path), _) => (package_root.join(path), Origin::Path),
+                    (None, Some(source)) => {
+                        let Some(checkout) = self.resolution.git.get(&source.to_string()) else {
+                            continue;
+                        };
+                        let source = format!("{source}#{}", checkout.commit);
+                        (checkout.root.clone(), Origin::Git(source))
+                    }
+                    (None, None) => match self.resolution.get(name) {
+                        Some(resolved) => (resolved.root.clone(), Origin::Registry),
+                        None => continue,
+                    },
+                };
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                self.check_cycle(&path, name)?;
+                let Some(dependency_config) = (self.load_config)(&path) else {
+                    continue;
+                };
+                dependencies.extend(self.add_project(
+                    &path,
+                    &dependency_config,
+                    dependency_origin,
+                )?);
+            }
+            self.visiting.pop();
+
+            self.graph.packages.push(GraphPackage {
+                name: package.name.clone(),
+                version: package.version.clone(),
+                project_root: project_root.to_path_buf(),
+                root: package_root,
+                origin: origin.clone(),
+                dependencies,
+            });
+            packages.push(self.graph.packages.len() - 1);
+        }
+        self.graph.projects.insert(key, packages.clone());
+        Ok(packages)
+    }
+
+    /// A project whose packages are still having their dependencies added is one that depends on itself
+    fn check_cycle(&self, project_root: &Path, name: &str) -> Result<(), IOError> {
+        let Some(start) = self
+            .visiting
+            .iter()
+            .position(|(visiting, _)| visiting == project_root)
+        else {
+            return Ok(());
+        };
+        let mut cycle: Vec<_> = self.visiting[start..]
+            .iter()
+            .map(|(_, package)| package.clone())
+            .collect();
+        cycle.push(name.to_string());
+        Err(DriverError::DependencyCycle { cycle }.to_io_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use flux_diagnostics::{DiagnosticCode, LintLevels, MessageFormat};
+    use lasso::ThreadedRodeo;
+
+    use super::*;
+    use crate::{cfg::CFG_FILE_NAME, driver::Driver, ExitStatus, INTERNER, PRE_INTERNED_VALUES};
+
+    fn write_package(root: &Path, name: &str, dependencies: &str, src: &str) {
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join(CFG_FILE_NAME),
+            format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n[dependencies]\n{dependencies}"),
+        )
+        .unwrap();
+        fs::write(root.join("src/main.flx"), src).unwrap();
+    }
+
+    #[test]
+    fn shared_projects_are_added_once_and_cycles_are_reported() {
+        let dir = std::env::temp_dir().join(format!("flux-graph-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let one = "pub fn one() -> u64 {\n\t1\n}\n";
+        write_package(&dir.join("base"), "base", "", one);
+        write_package(
+            &dir.join("left"),
+            "left",
+            "base = { path = \"../base\" }\n",
+            one,
+        );
+        // The same project, however it is spelled
+        let dependencies =
+            "base = { path = \"../right/../base\" }\nleft = { path = \"../left\" }\n";
+        write_package(&dir.join("right"), "right", dependencies, one);
+        let root = dir.join("app");
+        let dependencies = "left = { path = \"../left\" }\nright = { path = \"../right\" }\n";
+        let src = "fn main() -> u64 {\n\tright::one()\n}\n";
+        write_package(&root, "app", dependencies, src);
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let driver = || Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner);
+        let mut built = driver();
+        let config = built.load_config(&root, false).unwrap();
+        let (built_packages, status) =
+            built.build_project(root.clone(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        assert!(built.diagnostic_codes().is_empty());
+        assert_eq!(built.run_error(built_packages[0]), None);
+
+        let graph = driver().package_graph(&root, &config).unwrap();
+        assert_eq!(graph.packages.len(), 4);
+        let dir = fs::canonicalize(&dir).unwrap();
+        let (left, right, base) = (dir.join("left"), dir.join("right"), dir.join("base"));
+        assert_eq!(
+            graph.render(),
+            format!(
+                "app 0.1.0
+├── left 0.1.0 ({left})
+│   └── base 0.1.0 ({base})
+└── right 0.1.0 ({right})
+    ├── base 0.1.0 ({base})
+    └── left 0.1.0 ({left}) (*)
+",
+                left = left.display(),
+                right = right.display(),
+                base = base.display(),
+            )
+        );
+
+        write_package(
+            &dir.join("base"),
+            "base",
+            "app = { path = \"../app\" }\n",
+            one,
+        );
+        let err = driver().package_graph(&root, &config).unwrap_err();
+        assert_eq!(err.code(), DiagnosticCode::DependencyCycle);
+        assert_eq!(err.message(), "`app` depends on itself");
+        assert_eq!(
+            err.help()[..3],
+            [
+                "`app` depends on `left`",
+                "`left` depends on `base`",
+                "`base` depends on `app`",
+            ]
+        );
+        let (_, status) = driver().build_project(root, &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Failure);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
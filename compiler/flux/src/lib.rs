@@ -1,16 +1,28 @@
-use std::{ffi::OsString, fs, path::Path, sync::OnceLock};
+use std::{
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use cfg::{Config, CFG_FILE_NAME};
+use cfg::{Config, UserConfig, CFG_FILE_NAME, USER_CFG_FILE_NAME};
 use clap::{Parser, Subcommand};
-use commands::build;
+use commands::{build, clean, explain, fix, fmt, init, new, run, tree, vendor};
 use diagnostics::DriverError;
-use flux_diagnostics::IOError;
+use flux_diagnostics::{Diagnostic, IOError, SourceCache};
 use flux_util::Interner;
 
-mod cfg;
+mod cache;
+pub mod cfg;
 mod commands;
 mod diagnostics;
 mod driver;
+pub mod git;
+pub mod graph;
+mod lints;
+pub mod lockfile;
+pub mod registry;
+pub mod resolver;
 
 static INTERNER: OnceLock<Interner> = OnceLock::new();
 
@@ -42,6 +54,42 @@ enum Command {
     ///
     /// Build a flux project without running it
     Build(build::Args),
+    /// Run
+    ///
+    /// Build and run a flux project
+    Run(run::Args),
+    /// Format
+    ///
+    /// Format the source files of a flux project in the canonical style
+    Fmt(fmt::Args),
+    /// Fix
+    ///
+    /// Apply the fixes suggested for the problems found in a flux project
+    Fix(fix::Args),
+    /// Clean
+    ///
+    /// Remove the build outputs and build cache of a flux project
+    Clean(clean::Args),
+    /// Explain
+    ///
+    /// Print the long-form explanation of a diagnostic code, like `E0016`
+    Explain(explain::Args),
+    /// New
+    ///
+    /// Create a flux project in a new directory
+    New(new::Args),
+    /// Init
+    ///
+    /// Make a flux project of an existing directory
+    Init(init::Args),
+    /// Vendor
+    ///
+    /// Copy the dependencies of a flux project from the registry into the project
+    Vendor(vendor::Args),
+    /// Tree
+    ///
+    /// Print the dependency graph of a flux project
+    Tree(tree::Args),
 }
 
 pub fn run_with_args<T, I>(args: I) -> ExitStatus
@@ -52,10 +100,26 @@ where
     let args = Args::parse_from(args);
     match args.command {
         Command::Build(args) => build::build(args),
+        Command::Run(args) => run::run(args),
+        Command::Fmt(args) => fmt::fmt(args),
+        Command::Fix(args) => fix::fix(args),
+        Command::Clean(args) => clean::clean(args),
+        Command::Explain(args) => explain::explain(args),
+        Command::New(args) => new::new(args),
+        Command::Init(args) => init::init(args),
+        Command::Vendor(args) => vendor::vendor(args),
+        Command::Tree(args) => tree::tree(args),
     }
 }
 
-pub fn get_config(project_root: &Path) -> Result<Config, IOError> {
+/// Read and parse the config of the project at `project_root`
+///
+/// The config file is added to `source_cache`, so that what is wrong with it can be reported like any other diagnostic.
+/// If any of those is an error, the config is only as much of it as could be made sense of.
+pub fn get_config(
+    project_root: &Path,
+    source_cache: &mut SourceCache,
+) -> Result<(Config, Vec<Diagnostic>), IOError> {
     let cfg_path = project_root.join(CFG_FILE_NAME);
     let content = fs::read_to_string(&cfg_path).map_err(|_error| {
         DriverError::ReadConfigFile {
@@ -63,7 +127,32 @@ pub fn get_config(project_root: &Path) -> Result<Config, IOError> {
         }
         .to_io_error()
     })?;
-    Ok(cfg::parse_cfg(&content))
+    let file_id = source_cache.add_input_file(cfg_path.to_str().unwrap(), content.clone());
+    Ok(cfg::parse_cfg(&content, file_id))
+}
+
+/// Read and parse the user config in `flux_home`, which is the default if there is none
+///
+/// Like the config of a project, the file is added to `source_cache`, so that what is wrong with it can be reported.
+pub fn get_user_config(
+    flux_home: &Path,
+    source_cache: &mut SourceCache,
+) -> Result<(UserConfig, Vec<Diagnostic>), IOError> {
+    let cfg_path = flux_home.join(USER_CFG_FILE_NAME);
+    let content = match fs::read_to_string(&cfg_path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Ok((UserConfig::default(), vec![]))
+        }
+        Err(_) => {
+            return Err(DriverError::ReadConfigFile {
+                candidate: cfg_path.to_str().unwrap().to_string(),
+            }
+            .to_io_error())
+        }
+    };
+    let file_id = source_cache.add_input_file(cfg_path.to_str().unwrap(), content.clone());
+    Ok(cfg::parse_user_cfg(&content, file_id))
 }
 
 pub fn get_package_entry_file_path(
@@ -81,3 +170,20 @@ pub fn get_package_entry_file_path(
         })
         .and_then(|content| Ok((file_path.to_str().unwrap().to_string(), content)))
 }
+
+/// Every source file in `dir` and the directories in it
+pub(crate) fn source_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+    {
+        if path.is_dir() {
+            source_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "flx") {
+            files.push(path);
+        }
+    }
+}
@@ -0,0 +1,52 @@
+use flux_diagnostics::{IOError, Lint, LintLevel, LintLevels};
+
+use crate::{cfg::Config, diagnostics::DriverError};
+
+/// Lint levels set on the command line, which take precedence over the `[lints]` table of `flux.toml`
+#[derive(clap::Args, Debug)]
+pub struct LintArgs {
+    /// Don't report the lint
+    #[arg(short = 'A', long = "allow", value_name = "LINT")]
+    allow: Vec<String>,
+
+    /// Report the lint as a warning
+    #[arg(short = 'W', long = "warn", value_name = "LINT")]
+    warn: Vec<String>,
+
+    /// Report the lint as an error
+    #[arg(short = 'D', long = "deny", value_name = "LINT")]
+    deny: Vec<String>,
+
+    /// Report every warning as an error, failing the build
+    #[arg(long)]
+    deny_warnings: bool,
+}
+
+impl LintArgs {
+    /// The level of every lint, from the config and then the command line
+    pub(crate) fn levels(&self, flux_config: &Config) -> Result<LintLevels, IOError> {
+        let mut levels = LintLevels::default();
+        let flags = [
+            (LintLevel::Allow, &self.allow),
+            (LintLevel::Warn, &self.warn),
+            (LintLevel::Deny, &self.deny),
+        ];
+        let from_config = flux_config.lints.iter().map(|(name, level)| (name, *level));
+        let from_flags = flags
+            .into_iter()
+            .flat_map(|(level, names)| names.iter().map(move |name| (name, level)));
+        for (name, level) in from_config.chain(from_flags) {
+            let lint = Lint::from_name(name).ok_or_else(|| {
+                DriverError::UnknownLint {
+                    name: name.to_string(),
+                }
+                .to_io_error()
+            })?;
+            levels.set(lint, level);
+        }
+        if self.deny_warnings {
+            levels.deny_warnings();
+        }
+        Ok(levels)
+    }
+}
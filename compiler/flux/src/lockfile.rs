@@ -0,0 +1,106 @@
+//! `flux.lock`, which pins the versions of the packages a project depends on from the registry, and the commits of the
+//! ones it depends on from git repositories
+//!
+//! A lockfile is checked in with the project, so that everyone who builds it uses the same versions of its dependencies
+//! until they are resolved again on purpose.
+
+use std::{fs, path::Path};
+
+use flux_diagnostics::IOError;
+use serde::{Deserialize, Serialize};
+
+use crate::{diagnostics::DriverError, git::GitSource};
+
+pub const LOCKFILE_NAME: &str = "flux.lock";
+
+/// Lockfiles are versioned, so that ones written by older versions of `flux` can be told apart
+const LOCKFILE_VERSION: u32 = 1;
+
+const HEADER: &str = "# This file is generated by flux, and is not meant to be edited by hand\n\n";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Lockfile {
+    version: u32,
+    /// Ordered by name, so that the lockfile only changes when the versions do
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    /// Where a package that isn't from the registry came from, like `git+<url>?branch=<branch>#<commit>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// The SHA-256 of the archive of the package in the registry, in hex
+    ///
+    /// Packages from git repositories are pinned by their commit instead, so they have none.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub checksum: String,
+    /// The packages this one depends on, as `name version`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub dependencies: Vec<String>,
+}
+
+impl Lockfile {
+    pub fn new(packages: Vec<LockedPackage>) -> Self {
+        Self {
+            version: LOCKFILE_VERSION,
+            packages,
+        }
+    }
+
+    /// The lockfile of the project at `project_root`, if it has one
+    pub fn read(project_root: &Path) -> Result<Option<Self>, IOError> {
+        let path = project_root.join(LOCKFILE_NAME);
+        let read_error = |err: String| {
+            DriverError::ReadLockfile {
+                path: path.display().to_string(),
+                err,
+            }
+            .to_io_error()
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(read_error(err.to_string())),
+        };
+        let lockfile: Self =
+            toml::from_str(&content).map_err(|err| read_error(err.message().to_string()))?;
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(read_error(format!(
+                "it is version {} of the lockfile format, but only version {LOCKFILE_VERSION} is supported",
+                lockfile.version
+            )));
+        }
+        Ok(Some(lockfile))
+    }
+
+    pub fn write(&self, project_root: &Path) -> Result<(), IOError> {
+        let path = project_root.join(LOCKFILE_NAME);
+        let content = toml::to_string(self).expect("lockfiles are always valid toml");
+        fs::write(&path, format!("{HEADER}{content}")).map_err(|err| {
+            DriverError::WriteOutputFile {
+                path: path.display().to_string(),
+                err: err.to_string(),
+            }
+            .to_io_error()
+        })
+    }
+
+    /// The package from the registry pinned by the name `name`, if there is one
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages
+            .iter()
+            .find(|package| package.name == name && package.source.is_none())
+    }
+
+    /// The commit the repository of `source` is pinned to, if it is
+    pub fn get_git(&self, source: &GitSource) -> Option<&str> {
+        let prefix = format!("{source}#");
+        self.packages
+            .iter()
+            .find_map(|package| package.source.as_deref()?.strip_prefix(&prefix))
+    }
+}
@@ -1,10 +1,24 @@
 use tracing::metadata::LevelFilter;
-use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::{
+    filter::Targets, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+};
 
 fn main() {
-    tracing_subscriber::fmt::fmt()
-        .with_span_events(FmtSpan::CLOSE)
-        .with_max_level(LevelFilter::TRACE)
+    tracing_subscriber::registry()
+        // Logs go to stderr, leaving stdout to diagnostics that are written for other tools to read
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_span_events(FmtSpan::CLOSE),
+        )
+        // Cranelift logs every pass it runs over every function
+        .with(
+            Targets::new()
+                .with_default(LevelFilter::TRACE)
+                .with_target("cranelift", LevelFilter::WARN),
+        )
         .init();
-    flux::run_with_args(std::env::args_os());
+    if flux::run_with_args(std::env::args_os()) == flux::ExitStatus::Failure {
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,333 @@
+//! Registries, which the packages that are depended on by version are fetched from
+//!
+//! A registry is a directory rather than a service, so that it can be mirrored anywhere a build can read from, like a
+//! network share, or the project itself with `flux vendor`. It has an index of the versions of every package, along
+//! with what each of them depends on, which is all that resolving versions needs, and an archive of every version,
+//! which is only read once it has been picked:
+//!
+//! ```text
+//! index/<name>.toml
+//! packages/<name>/<name>-<version>.tar.gz
+//! ```
+//!
+//! Fetched packages are unpacked into a cache that every project shares, once their archive has been checked against
+//! the checksum in the index.
+//!
+//! A registry made by `flux vendor` also has the checkouts of the git dependencies of the project, at the commits its
+//! lockfile pins them to, so that they are built from there rather than fetched:
+//!
+//! ```text
+//! git/<repository name>-<url hash>/<commit>/
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use flate2::read::GzDecoder;
+use flux_diagnostics::IOError;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    cfg::{Config, UserConfig},
+    diagnostics::DriverError,
+    git,
+    resolver::Resolution,
+};
+
+/// The environment variable that overrides the registry of the user config
+pub const REGISTRY_VAR: &str = "FLUX_REGISTRY";
+
+/// The environment variable that overrides where the flux home directory is
+pub const HOME_VAR: &str = "FLUX_HOME";
+
+/// The directory with the user config, the default registry and the cache of fetched packages
+///
+/// Defaults to `~/.flux`.
+pub fn flux_home() -> PathBuf {
+    match std::env::var_os(HOME_VAR) {
+        Some(path) => PathBuf::from(path),
+        None => std::env::var_os("HOME")
+            .map(PathBuf::from)
+            .unwrap_or_default()
+            .join(".flux"),
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Index {
+    #[serde(default, rename = "version")]
+    versions: Vec<IndexEntry>,
+}
+
+/// A version of a package, as it is listed in the index
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct IndexEntry {
+    pub version: Version,
+    /// The SHA-256 of the archive of the package, in hex
+    pub checksum: String,
+    /// What the package depends on, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, VersionReq>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Registry {
+    root: PathBuf,
+    /// Where fetched packages are unpacked
+    cache: PathBuf,
+}
+
+impl Registry {
+    /// The registry a project fetches its dependencies from
+    ///
+    /// That is the first of the `[registry]` of its config, the `FLUX_REGISTRY` environment variable, the `[registry]`
+    /// of the user config, and the `registry` directory in the flux home directory.
+    pub fn for_project(
+        project_root: &Path,
+        config: &Config,
+        user_config: &UserConfig,
+        flux_home: &Path,
+    ) -> Self {
+        let root = if let Some(path) = &config.registry {
+            project_root.join(path)
+        } else if let Some(path) = std::env::var_os(REGISTRY_VAR) {
+            PathBuf::from(path)
+        } else if let Some(path) = &user_config.registry {
+            flux_home.join(path)
+        } else {
+            flux_home.join("registry")
+        };
+        Self {
+            root,
+            cache: flux_home.join("cache"),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The versions of the package named `name`, which has none if it isn't in the index
+    pub fn versions(&self, name: &str) -> Result<Vec<IndexEntry>, IOError> {
+        let path = self.root.join("index").join(format!("{name}.toml"));
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(read_registry_error(&path, err.to_string())),
+        };
+        let index: Index = toml::from_str(&content)
+            .map_err(|err| read_registry_error(&path, err.message().to_string()))?;
+        Ok(index.versions)
+    }
+
+    /// Where a version of a package is unpacked to once it has been fetched
+    ///
+    /// The checksum is part of it, so that registries that disagree about what a version is don't share it.
+    pub fn unpacked_path(&self, name: &str, version: &Version, checksum: &str) -> PathBuf {
+        let checksum = &checksum[..checksum.len().min(16)];
+        self.cache.join(format!("{name}-{version}-{checksum}"))
+    }
+
+    /// Unpack a package that was resolved from this registry, unless it already has been
+    pub fn fetch(&self, name: &str, version: &Version, checksum: &str) -> Result<(), IOError> {
+        let unpacked = self.unpacked_path(name, version, checksum);
+        if unpacked.exists() {
+            return Ok(());
+        }
+        let archive = self.archive(name, version, checksum)?;
+        // A package is only moved into place once all of it has been unpacked, so that one that was cut short isn't
+        // mistaken for a package that was fetched
+        let partial = unpacked.with_file_name(format!(
+            "{}.partial",
+            unpacked.file_name().unwrap().to_string_lossy()
+        ));
+        let _ = fs::remove_dir_all(&partial);
+        tar::Archive::new(GzDecoder::new(&archive[..]))
+            .unpack(&partial)
+            .and_then(|_| fs::rename(&partial, &unpacked))
+            .map_err(|err| {
+                DriverError::FetchDependency {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    err: err.to_string(),
+                }
+                .to_io_error()
+            })
+    }
+
+    /// Where `commit` of the repository at `url` was vendored to, if it was
+    pub fn vendored_checkout(&self, url: &str, commit: &str) -> Option<PathBuf> {
+        let checkout = git::checkout_path(&self.root.join("git"), url, commit);
+        checkout.is_dir().then_some(checkout)
+    }
+
+    /// Copy the packages of a resolution, along with its git checkouts, into a registry of their own at `dest`,
+    /// replacing what was there before
+    pub fn vendor(&self, resolution: &Resolution, dest: &Path) -> Result<(), IOError> {
+        let mut vendored = vec![];
+        for (name, package) in &resolution.packages {
+            let entry = self
+                .versions(name)?
+                .into_iter()
+                .find(|entry| entry.version == package.version)
+                .ok_or_else(|| {
+                    DriverError::FetchDependency {
+                        name: name.to_string(),
+                        version: package.version.to_string(),
+                        err: "it is no longer in the index of its registry".to_string(),
+                    }
+                    .to_io_error()
+                })?;
+            let archive = self.archive(name, &entry.version, &entry.checksum)?;
+            vendored.push((name, entry, archive));
+        }
+        let mut checkouts = vec![];
+        for checkout in resolution.git.values() {
+            let files = read_files(&checkout.root)
+                .map_err(|err| read_registry_error(&checkout.root, err.to_string()))?;
+            let path = git::checkout_path(&dest.join("git"), &checkout.url, &checkout.commit);
+            checkouts.push((path, files));
+        }
+
+        // Everything is read before anything is removed, in case `dest` is this registry
+        for dir in ["index", "packages", "git"] {
+            let dir = dest.join(dir);
+            match fs::remove_dir_all(&dir) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                    return Err(write_error(&dir, err));
+                }
+                _ => {}
+            }
+        }
+        for (name, entry, archive) in vendored {
+            let path = archive_path(dest, name, &entry.version);
+            write(&path, &archive)?;
+            let index = Index {
+                versions: vec![entry],
+            };
+            let path = dest.join("index").join(format!("{name}.toml"));
+            write(&path, toml::to_string(&index).unwrap().as_bytes())?;
+        }
+        for (checkout, files) in checkouts {
+            for (path, contents) in files {
+                write(&checkout.join(path), &contents)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The archive of a version of a package, once it has been checked against its checksum
+    fn archive(&self, name: &str, version: &Version, checksum: &str) -> Result<Vec<u8>, IOError> {
+        let path = archive_path(&self.root, name, version);
+        let archive = fs::read(&path).map_err(|err| {
+            DriverError::FetchDependency {
+                name: name.to_string(),
+                version: version.to_string(),
+                err: format!("could not read `{}`: {err}", path.display()),
+            }
+            .to_io_error()
+        })?;
+        if sha256(&archive) != checksum {
+            return Err(DriverError::CorruptDependency {
+                name: name.to_string(),
+                version: version.to_string(),
+                path: path.display().to_string(),
+            }
+            .to_io_error());
+        }
+        Ok(archive)
+    }
+}
+
+fn archive_path(registry_root: &Path, name: &str, version: &Version) -> PathBuf {
+    registry_root
+        .join("packages")
+        .join(name)
+        .join(format!("{name}-{version}.tar.gz"))
+}
+
+/// Every file under `root`, by its path relative to `root`
+fn read_files(root: &Path) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut files = vec![];
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(root.join(&dir))? {
+            let entry = entry?;
+            let path = dir.join(entry.file_name());
+            if entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                files.push((path, fs::read(entry.path())?));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn write(path: &Path, contents: &[u8]) -> Result<(), IOError> {
+    match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+    .and_then(|_| fs::write(path, contents))
+    .map_err(|err| write_error(path, err))
+}
+
+fn write_error(path: &Path, err: io::Error) -> IOError {
+    DriverError::WriteOutputFile {
+        path: path.display().to_string(),
+        err: err.to_string(),
+    }
+    .to_io_error()
+}
+
+fn read_registry_error(path: &Path, err: String) -> IOError {
+    DriverError::ReadRegistry {
+        path: path.display().to_string(),
+        err,
+    }
+    .to_io_error()
+}
+
+/// Add the package at `package_root` to the registry at `registry_root`, the way publishing it would
+#[cfg(test)]
+pub(crate) fn publish(registry_root: &Path, package_root: &Path) {
+    use flate2::{write::GzEncoder, Compression};
+    use flux_util::FileId;
+
+    use crate::cfg::{parse_cfg, CFG_FILE_NAME};
+
+    let content = fs::read_to_string(package_root.join(CFG_FILE_NAME)).unwrap();
+    let (config, _) = parse_cfg(&content, FileId::poisoned());
+    let name = &config.packages[0].name;
+    let version = Version::parse(&config.packages[0].version).unwrap();
+
+    let mut builder = tar::Builder::new(GzEncoder::new(vec![], Compression::default()));
+    builder.append_dir_all(".", package_root).unwrap();
+    let archive = builder.into_inner().unwrap().finish().unwrap();
+    write(&archive_path(registry_root, name, &version), &archive).unwrap();
+
+    let index_path = registry_root.join("index").join(format!("{name}.toml"));
+    let mut index: Index = fs::read_to_string(&index_path)
+        .map(|content| toml::from_str(&content).unwrap())
+        .unwrap_or_default();
+    index.versions.push(IndexEntry {
+        version,
+        checksum: sha256(&archive),
+        dependencies: config
+            .dependencies
+            .iter()
+            .filter_map(|(name, dependency)| Some((name.clone(), dependency.version.clone()?)))
+            .collect(),
+    });
+    write(&index_path, toml::to_string(&index).unwrap().as_bytes()).unwrap();
+}
@@ -0,0 +1,856 @@
+//! Picking the version of every package a project depends on from the registry
+//!
+//! A project has a single version of each package from the registry, which has to match the requirements of everything
+//! that depends on it, so that a package two others depend on is only built once. The newest version that matches is
+//! tried first, and a choice is gone back on once it leaves no version of some other package that matches. Versions
+//! pinned by the lockfile are tried before any other, so that they only change when the requirements do.
+//!
+//! Only the index of the registry is read to resolve versions, so that nothing is fetched that isn't used. Git
+//! dependencies are checked out while resolving though, since they are depended on like projects at a path, whose
+//! requirements can only be known once their config has been read.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use flux_diagnostics::IOError;
+use semver::{Version, VersionReq};
+
+use crate::{
+    cfg::Config,
+    diagnostics::DriverError,
+    git::{Git, GitSource},
+    lockfile::{LockedPackage, Lockfile},
+    registry::{IndexEntry, Registry},
+};
+
+/// The version picked for every package from the registry, by name, and the commit every git repository was checked out
+/// at
+#[derive(Debug, Default)]
+pub struct Resolution {
+    pub packages: BTreeMap<String, ResolvedPackage>,
+    /// By the source of the repository, as it is written in the lockfile
+    pub git: BTreeMap<String, GitCheckout>,
+}
+
+#[derive(Debug)]
+pub struct ResolvedPackage {
+    pub version: Version,
+    /// Where the package is unpacked to once it has been fetched
+    pub root: PathBuf,
+    pub checksum: String,
+    /// The packages from the registry this one depends on, by name
+    pub dependencies: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct GitCheckout {
+    /// The name the repository is depended on by
+    pub name: String,
+    /// The version of the package in the repository, as its config has it
+    pub version: String,
+    pub url: String,
+    pub commit: String,
+    pub root: PathBuf,
+}
+
+impl Resolution {
+    pub fn get(&self, name: &str) -> Option<&ResolvedPackage> {
+        self.packages.get(name)
+    }
+
+    /// Where the repository of a git dependency was checked out
+    pub fn git_root(&self, source: &GitSource) -> Option<&Path> {
+        self.git
+            .get(&source.to_string())
+            .map(|checkout| checkout.root.as_path())
+    }
+
+    /// The lockfile that pins the versions that were picked, and the commits that were checked out
+    pub fn lockfile(&self) -> Lockfile {
+        let mut packages: Vec<_> = self
+            .packages
+            .iter()
+            .map(|(name, package)| LockedPackage {
+                name: name.clone(),
+                version: package.version.to_string(),
+                source: None,
+                checksum: package.checksum.clone(),
+                dependencies: package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| format!("{dependency} {}", self.packages[dependency].version))
+                    .collect(),
+            })
+            .chain(self.git.iter().map(|(source, checkout)| LockedPackage {
+                name: checkout.name.clone(),
+                version: checkout.version.clone(),
+                source: Some(format!("{source}#{}", checkout.commit)),
+                checksum: String::new(),
+                dependencies: vec![],
+            }))
+            .collect();
+        packages.sort_by(|a, b| (&a.name, &a.source).cmp(&(&b.name, &b.source)));
+        Lockfile::new(packages)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Requirement {
+    name: String,
+    requirement: VersionReq,
+    /// The package with the requirement, as it is described in diagnostics
+    required_by: String,
+}
+
+/// Pick the versions of the packages that the project at `project_root`, and the projects it depends on by path or
+/// from `git`, need from the `registry`
+///
+/// `load_config` reads the config of a project, reporting what is wrong with it. Projects whose config can't be read are
+/// left out.
+pub fn resolve(
+    project_root: &Path,
+    config: &Config,
+    registry: &Registry,
+    git: &Git,
+    lockfile: Option<&Lockfile>,
+    load_config: &mut dyn FnMut(&Path) -> Option<Config>,
+) -> Result<Resolution, IOError> {
+    let mut resolver = Resolver {
+        registry,
+        git,
+        lockfile,
+        load_config,
+        versions: HashMap::new(),
+        checkouts: BTreeMap::new(),
+    };
+    let mut requirements = vec![];
+    resolver.project_requirements(project_root, config, &mut requirements, &mut HashSet::new())?;
+    let mut selected = BTreeMap::new();
+    resolver.search(&mut requirements, &mut selected)?;
+
+    let mut resolution = Resolution {
+        git: std::mem::take(&mut resolver.checkouts),
+        ..Default::default()
+    };
+    for (name, version) in selected {
+        let entry = resolver.entry(&name, &version);
+        let locked = lockfile.and_then(|lockfile| lockfile.get(&name));
+        if let Some(locked) = locked.filter(|locked| locked.version == version.to_string()) {
+            if locked.checksum != entry.checksum {
+                return Err(DriverError::DependencyChecksumMismatch {
+                    name,
+                    version: version.to_string(),
+                }
+                .to_io_error());
+            }
+        }
+        let package = ResolvedPackage {
+            root: registry.unpacked_path(&name, &version, &entry.checksum),
+            checksum: entry.checksum.clone(),
+            dependencies: entry.dependencies.keys().cloned().collect(),
+            version,
+        };
+        resolution.packages.insert(name, package);
+    }
+    Ok(resolution)
+}
+
+struct Resolver<'a> {
+    registry: &'a Registry,
+    git: &'a Git,
+    lockfile: Option<&'a Lockfile>,
+    load_config: &'a mut dyn FnMut(&Path) -> Option<Config>,
+    /// The versions of each package in the index, newest first
+    versions: HashMap<String, Vec<IndexEntry>>,
+    /// The repositories that have been checked out, by their source
+    checkouts: BTreeMap<String, GitCheckout>,
+}
+
+impl Resolver<'_> {
+    /// Pick a version of every package that is required, but that hasn't been picked yet
+    ///
+    /// On failure, the error is about the last package that no version could be picked for.
+    fn search(
+        &mut self,
+        requirements: &mut Vec<Requirement>,
+        selected: &mut BTreeMap<String, Version>,
+    ) -> Result<(), IOError> {
+        let Some(name) = requirements
+            .iter()
+            .find(|requirement| !selected.contains_key(&requirement.name))
+            .map(|requirement| requirement.name.clone())
+        else {
+            return Ok(());
+        };
+
+        let mut candidates: Vec<_> = self
+            .versions(&name)?
+            .iter()
+            .map(|entry| entry.version.clone())
+            .filter(|version| {
+                requirements
+                    .iter()
+                    .filter(|requirement| requirement.name == name)
+                    .all(|requirement| requirement.requirement.matches(version))
+            })
+            .collect();
+        if let Some(locked) = self.locked_version(&name) {
+            if let Some(idx) = candidates.iter().position(|version| *version == locked) {
+                let locked = candidates.remove(idx);
+                candidates.insert(0, locked);
+            }
+        }
+
+        let mut error = self.conflict(&name, requirements);
+        for version in candidates {
+            let num_requirements = requirements.len();
+            let entry = self.entry(&name, &version);
+            let required_by = format!("`{name} {version}`");
+            let candidate_requirements: Vec<_> = entry
+                .dependencies
+                .iter()
+                .map(|(name, requirement)| Requirement {
+                    name: name.clone(),
+                    requirement: requirement.clone(),
+                    required_by: required_by.clone(),
+                })
+                .collect();
+            requirements.extend(candidate_requirements);
+            selected.insert(name.clone(), version);
+
+            // A package that was already picked is only checked against the new requirements on it here, since the
+            // search only goes on to packages that haven't been
+            let broken = requirements[num_requirements..].iter().find(|requirement| {
+                selected
+                    .get(&requirement.name)
+                    .is_some_and(|version| !requirement.requirement.matches(version))
+            });
+            let result = match broken {
+                Some(broken) => Err(self.conflict(&broken.name.clone(), requirements)),
+                None => self.search(requirements, selected),
+            };
+            match result {
+                Ok(()) => return Ok(()),
+                Err(err) => error = err,
+            }
+            requirements.truncate(num_requirements);
+            selected.remove(&name);
+        }
+        Err(error)
+    }
+
+    /// The requirements of the packages of a project, and of the projects it depends on by path
+    fn project_requirements(
+        &mut self,
+        project_root: &Path,
+        config: &Config,
+        requirements: &mut Vec<Requirement>,
+        visited: &mut HashSet<PathBuf>,
+    ) -> Result<(), IOError> {
+        if !visited.insert(project_root.to_path_buf()) {
+            return Ok(());
+        }
+        for package in &config.packages {
+            let package_root = if config.workspace {
+                project_root.join(&package.name)
+            } else {
+                project_root.to_path_buf()
+            };
+            let required_by = if package.version.is_empty() {
+                format!("`{}`", package.name)
+            } else {
+                format!("`{} {}`", package.name, package.version)
+            };
+            for (name, dependency) in config.dependencies.iter() {
+                let path = match (&dependency.path, &dependency.git) {
+                    (Some(path), _) => {
+                        let path = package_root.join(path);
+                        fs::canonicalize(&path).unwrap_or(path)
+                    }
+                    (None, Some(source)) => self.checkout(name, source)?,
+                    (None, None) => {
+                        if let Some(requirement) = &dependency.version {
+                            requirements.push(Requirement {
+                                name: name.clone(),
+                                requirement: requirement.clone(),
+                                required_by: required_by.clone(),
+                            });
+                        }
+                        continue;
+                    }
+                };
+                let Some(dependency_config) = (self.load_config)(&path) else {
+                    continue;
+                };
+                if let Some(source) = &dependency.git {
+                    let checkout = self.checkouts.get_mut(&source.to_string()).unwrap();
+                    checkout.version = dependency_config
+                        .packages
+                        .first()
+                        .map(|package| package.version.clone())
+                        .unwrap_or_default();
+                }
+                if let Some(requirement) = &dependency.version {
+                    check_path_version(name, requirement, &path, &dependency_config)?;
+                }
+                self.project_requirements(&path, &dependency_config, requirements, visited)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Check out the repository of a git dependency at the commit the lockfile pins it to, or else at the commit its
+    /// reference is at now
+    ///
+    /// A commit that was vendored into the registry is used from there, without going to the repository.
+    fn checkout(&mut self, name: &str, source: &GitSource) -> Result<PathBuf, IOError> {
+        let key = source.to_string();
+        if let Some(checkout) = self.checkouts.get(&key) {
+            return Ok(checkout.root.clone());
+        }
+        let commit = match self.lockfile.and_then(|lockfile| lockfile.get_git(source)) {
+            Some(commit) => commit.to_string(),
+            None => self.git.resolve(source)?,
+        };
+        let root = match self.registry.vendored_checkout(&source.url, &commit) {
+            Some(root) => root,
+            None => self.git.checkout(source, &commit)?,
+        };
+        self.checkouts.insert(
+            key,
+            GitCheckout {
+                name: name.to_string(),
+                version: String::new(),
+                url: source.url.clone(),
+                commit,
+                root: root.clone(),
+            },
+        );
+        Ok(root)
+    }
+
+    fn versions(&mut self, name: &str) -> Result<&[IndexEntry], IOError> {
+        if !self.versions.contains_key(name) {
+            let mut versions = self.registry.versions(name)?;
+            versions.sort_by(|a, b| b.version.cmp(&a.version));
+            self.versions.insert(name.to_string(), versions);
+        }
+        Ok(&self.versions[name])
+    }
+
+    /// The index entry of a version that was read while searching
+    fn entry(&self, name: &str, version: &Version) -> &IndexEntry {
+        self.versions[name]
+            .iter()
+            .find(|entry| entry.version == *version)
+            .unwrap()
+    }
+
+    fn locked_version(&self, name: &str) -> Option<Version> {
+        let locked = self.lockfile?.get(name)?;
+        Version::parse(&locked.version).ok()
+    }
+
+    /// The error for when no version of `name` matches every requirement on it
+    fn conflict(&self, name: &str, requirements: &[Requirement]) -> IOError {
+        DriverError::UnresolvableDependency {
+            name: name.to_string(),
+            requirements: requirements
+                .iter()
+                .filter(|requirement| requirement.name == name)
+                .map(|requirement| {
+                    format!(
+                        "`{}` is required by {}",
+                        requirement.requirement, requirement.required_by
+                    )
+                })
+                .collect(),
+            versions: self
+                .versions
+                .get(name)
+                .into_iter()
+                .flatten()
+                .map(|entry| entry.version.to_string())
+                .collect(),
+            registry: self.registry.root().display().to_string(),
+        }
+        .to_io_error()
+    }
+}
+
+/// A path dependency with a `version` is checked against the package at the path, rather than resolved
+fn check_path_version(
+    name: &str,
+    requirement: &VersionReq,
+    path: &Path,
+    config: &Config,
+) -> Result<(), IOError> {
+    for package in &config.packages {
+        let matches =
+            Version::parse(&package.version).is_ok_and(|version| requirement.matches(&version));
+        if !matches {
+            return Err(DriverError::DependencyVersionMismatch {
+                name: name.to_string(),
+                requirement: requirement.to_string(),
+                version: package.version.clone(),
+                path: path.display().to_string(),
+            }
+            .to_io_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use flux_diagnostics::{DiagnosticCode, LintLevels, MessageFormat, SourceCache};
+    use lasso::ThreadedRodeo;
+
+    use super::*;
+    use crate::{
+        cfg::CFG_FILE_NAME, driver::Driver, get_config, git::GitReference, lockfile::LOCKFILE_NAME,
+        registry, ExitStatus, INTERNER, PRE_INTERNED_VALUES,
+    };
+
+    /// Write a package to `root`, depending on `dependencies` as they would be written in its config
+    fn write_package(root: &Path, name: &str, version: &str, dependencies: &str, src: &str) {
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join(CFG_FILE_NAME),
+            format!("[package]\nname = \"{name}\"\nversion = \"{version}\"\n\n[dependencies]\n{dependencies}"),
+        )
+        .unwrap();
+        fs::write(root.join("src/main.flx"), src).unwrap();
+    }
+
+    /// Write a project depending on `dependencies`, which are fetched from the registry at `registry`
+    fn write_project(root: &Path, registry: &Path, dependencies: &str, src: &str) {
+        let dependencies = format!(
+            "{dependencies}\n[registry]\npath = \"{}\"\n",
+            registry.display()
+        );
+        write_package(root, "app", "0.1.0", &dependencies, src);
+    }
+
+    /// A registry where the newest `shapes` needs a `geometry` that `canvas` can't use
+    fn write_registry(registry: &Path) {
+        let _ = fs::remove_dir_all(registry);
+        let sources = registry.with_extension("sources");
+        for version in ["1.0.0", "1.1.0", "1.2.0", "1.3.0", "2.0.0"] {
+            let root = sources.join(format!("geometry-{version}"));
+            write_package(
+                &root,
+                "geometry",
+                version,
+                "",
+                "pub fn two() -> u64 {\n\t2\n}\n",
+            );
+            registry::publish(registry, &root);
+        }
+        for (version, requirement) in [("1.0.0", "1.1"), ("1.1.0", "2")] {
+            let root = sources.join(format!("shapes-{version}"));
+            let src = "pub fn two() -> u64 {\n\tgeometry::two()\n}\n";
+            write_package(
+                &root,
+                "shapes",
+                version,
+                &format!("geometry = \"{requirement}\"\n"),
+                src,
+            );
+            registry::publish(registry, &root);
+        }
+        let root = sources.join("canvas-1.0.0");
+        let dependencies = "geometry = \">=1.0, <1.3\"\n";
+        write_package(
+            &root,
+            "canvas",
+            "1.0.0",
+            dependencies,
+            "pub fn one() -> u64 {\n\t1\n}\n",
+        );
+        registry::publish(registry, &root);
+        let _ = fs::remove_dir_all(&sources);
+    }
+
+    fn resolve_in(
+        registry: &Path,
+        dependencies: &str,
+        lockfile: Option<&Lockfile>,
+    ) -> Result<Vec<(String, String)>, DiagnosticCode> {
+        let root = registry.with_extension("project");
+        write_project(&root, registry, dependencies, "");
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let mut source_cache = SourceCache::new(interner);
+        let (config, _) = get_config(&root, &mut source_cache).unwrap();
+        let registry = Registry::for_project(
+            &root,
+            &config,
+            &Default::default(),
+            &registry.with_extension("home"),
+        );
+        let git = Git::new(&root.with_extension("home"));
+        let resolution = resolve(&root, &config, &registry, &git, lockfile, &mut |path| {
+            get_config(path, &mut source_cache)
+                .ok()
+                .map(|(config, _)| config)
+        })
+        .map_err(|err| err.code())?;
+        Ok(resolution
+            .packages
+            .into_iter()
+            .map(|(name, package)| (name, package.version.to_string()))
+            .collect())
+    }
+
+    fn versions(versions: &[(&str, &str)]) -> Result<Vec<(String, String)>, DiagnosticCode> {
+        Ok(versions
+            .iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect())
+    }
+
+    #[test]
+    fn shared_dependencies_get_a_version_every_package_can_use() {
+        let registry = std::env::temp_dir().join(format!("flux-registry-{}", std::process::id()));
+        write_registry(&registry);
+
+        // `shapes 1.1.0` is the newest, but needs `geometry 2`, so it is given up on for `shapes 1.0.0`
+        assert_eq!(
+            resolve_in(&registry, "shapes = \"1\"\ncanvas = \"1\"\n", None),
+            versions(&[
+                ("canvas", "1.0.0"),
+                ("geometry", "1.2.0"),
+                ("shapes", "1.0.0")
+            ])
+        );
+        assert_eq!(
+            resolve_in(&registry, "shapes = \"1\"\n", None),
+            versions(&[("geometry", "2.0.0"), ("shapes", "1.1.0")])
+        );
+
+        let lockfile = Lockfile::new(vec![LockedPackage {
+            name: "geometry".to_string(),
+            version: "1.1.0".to_string(),
+            source: None,
+            checksum: String::new(),
+            dependencies: vec![],
+        }]);
+        assert_eq!(
+            resolve_in(&registry, "canvas = \"1\"\n", Some(&lockfile)),
+            Err(DiagnosticCode::DependencyChecksumMismatch)
+        );
+        let config = Config {
+            registry: Some(".".to_string()),
+            ..Default::default()
+        };
+        let index = Registry::for_project(&registry, &config, &Default::default(), &registry);
+        let lockfile = Lockfile::new(vec![LockedPackage {
+            checksum: index
+                .versions("geometry")
+                .unwrap()
+                .into_iter()
+                .find(|entry| entry.version.to_string() == "1.1.0")
+                .unwrap()
+                .checksum,
+            ..lockfile.packages[0].clone()
+        }]);
+        assert_eq!(
+            resolve_in(&registry, "canvas = \"1\"\n", Some(&lockfile)),
+            versions(&[("canvas", "1.0.0"), ("geometry", "1.1.0")])
+        );
+
+        for dependencies in [
+            "canvas = \"1\"\ngeometry = \"2\"\n",
+            "canvas = \"2\"\n",
+            "missing = \"1\"\n",
+        ] {
+            assert_eq!(
+                resolve_in(&registry, dependencies, None),
+                Err(DiagnosticCode::CouldNotResolveDependencies),
+                "{dependencies}"
+            );
+        }
+
+        let _ = fs::remove_dir_all(registry.with_extension("project"));
+        let _ = fs::remove_dir_all(&registry);
+    }
+
+    #[test]
+    fn lockfile_pins_what_is_built() {
+        let dir = std::env::temp_dir().join(format!("flux-lockfile-{}", std::process::id()));
+        let registry = dir.join("registry");
+        write_registry(&registry);
+        let root = dir.join("app");
+        let src = "fn main() -> u64 {\n\tshapes::two()\n}\n";
+        write_project(&root, &registry, "shapes = \"1\"\n", src);
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let driver = |locked: bool| {
+            Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+                .with_flux_home(dir.join("home"))
+                .with_locked(locked)
+        };
+        let resolve_locked = |root: &Path| {
+            let mut driver = driver(true);
+            let config = driver.load_config(root, false).unwrap();
+            let registry = driver.registry(root, &config);
+            driver
+                .resolve_dependencies(root, &config, &registry)
+                .map(|_| ())
+                .map_err(|err| err.code())
+        };
+
+        assert_eq!(
+            resolve_locked(&root),
+            Err(DiagnosticCode::LockfileOutOfDate)
+        );
+        let mut unlocked = driver(false);
+        let config = unlocked.load_config(&root, false).unwrap();
+        let (built_packages, status) =
+            unlocked.build_project(root.clone(), &config, &LintLevels::default());
+        assert_eq!(status, ExitStatus::Success);
+        assert!(unlocked.diagnostic_codes().is_empty());
+        assert_eq!(unlocked.run_error(built_packages[0]), None);
+        let lockfile = Lockfile::read(&root).unwrap().unwrap();
+        assert_eq!(
+            lockfile.get("shapes").unwrap().dependencies,
+            ["geometry 2.0.0"]
+        );
+        assert_eq!(resolve_locked(&root), Ok(()));
+
+        write_project(&root, &registry, "shapes = \"1\"\ncanvas = \"1\"\n", src);
+        assert_eq!(
+            resolve_locked(&root),
+            Err(DiagnosticCode::LockfileOutOfDate)
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vendored_dependencies_build_without_the_registry() {
+        let dir = std::env::temp_dir().join(format!("flux-vendor-{}", std::process::id()));
+        let registry = dir.join("registry");
+        write_registry(&registry);
+        let root = dir.join("app");
+        let src = "fn main() -> u64 {\n\tshapes::two()\n}\n";
+        write_project(&root, &registry, "shapes = \"1\"\n", src);
+
+        let root_path = root.to_str().unwrap();
+        let vendor = ["flux", "vendor", "--root-path", root_path];
+        assert_eq!(crate::run_with_args(vendor), ExitStatus::Success);
+        assert!(root.join("vendor/index/geometry.toml").exists());
+        assert!(!root.join("vendor/index/canvas.toml").exists());
+        let _ = fs::remove_dir_all(&registry);
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let build = |flux_home: &str| {
+            let mut driver =
+                Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+                    .with_flux_home(dir.join(flux_home))
+                    .with_locked(true);
+            let config = driver.load_config(&root, false).unwrap();
+            let (_, status) = driver.build_project(root.clone(), &config, &LintLevels::default());
+            status
+        };
+        write_project(&root, Path::new("vendor"), "shapes = \"1\"\n", src);
+        assert_eq!(build("home"), ExitStatus::Success);
+        // Vendoring from the registry that was vendored to keeps it as it is
+        assert_eq!(crate::run_with_args(vendor), ExitStatus::Success);
+        assert_eq!(build("home"), ExitStatus::Success);
+
+        let archive = root.join("vendor/packages/geometry/geometry-2.0.0.tar.gz");
+        fs::write(&archive, "not an archive").unwrap();
+        assert_eq!(build("other-home"), ExitStatus::Failure);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn vendored_git_dependencies_build_without_the_repository() {
+        let dir = std::env::temp_dir().join(format!("flux-vendor-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = dir.join("shapes");
+        write_package(
+            &repo,
+            "shapes",
+            "1.0.0",
+            "",
+            "pub fn two() -> u64 {\n\t2\n}\n",
+        );
+        git(&repo, &["init", "--quiet"]);
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "--quiet", "--message", "Add shapes"]);
+        let commit = git(&repo, &["rev-parse", "HEAD"]);
+
+        let root = dir.join("app");
+        let dependencies = format!("shapes = {{ git = \"file://{}\" }}\n", repo.display());
+        let src = "fn main() -> u64 {\n\tshapes::two()\n}\n";
+        write_project(&root, Path::new("vendor"), &dependencies, src);
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let driver = |flux_home: &str| {
+            Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+                .with_flux_home(dir.join(flux_home))
+                .with_locked(true)
+        };
+        let vendor = || {
+            let mut driver = driver("home").with_locked(false);
+            let config = driver.load_config(&root, false).unwrap();
+            let registry = driver.registry(&root, &config);
+            let resolution = driver
+                .resolve_dependencies(&root, &config, &registry)
+                .unwrap();
+            registry.vendor(&resolution, &root.join("vendor")).unwrap();
+        };
+        vendor();
+        let shapes = root.join("vendor/git").read_dir().unwrap().next().unwrap();
+        assert!(shapes
+            .unwrap()
+            .path()
+            .join(&commit)
+            .join("src/main.flx")
+            .exists());
+
+        // Neither the repository nor anything fetched from it is needed any more
+        fs::remove_dir_all(&repo).unwrap();
+        let build = |flux_home: &str| {
+            let mut driver = driver(flux_home);
+            let config = driver.load_config(&root, false).unwrap();
+            let (built_packages, status) =
+                driver.build_project(root.clone(), &config, &LintLevels::default());
+            assert_eq!(
+                status,
+                ExitStatus::Success,
+                "{}",
+                driver.rendered_diagnostics()
+            );
+            driver.run_value(built_packages[0])
+        };
+        assert_eq!(build("other-home"), Ok(2));
+        // Vendoring from the registry that was vendored to keeps it as it is
+        vendor();
+        assert_eq!(build("another-home"), Ok(2));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Run `git` in `dir`, as a user who has never configured it
+    fn git(dir: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=flux", "-c", "user.email=flux@localhost"])
+            .args([
+                "-c",
+                "init.defaultBranch=main",
+                "-c",
+                "commit.gpgsign=false",
+            ])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn git_dependencies_are_pinned_to_a_commit() {
+        let dir = std::env::temp_dir().join(format!("flux-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let repo = dir.join("shapes");
+        write_package(
+            &repo,
+            "shapes",
+            "1.0.0",
+            "",
+            "pub fn two() -> u64 {\n\t2\n}\n",
+        );
+        git(&repo, &["init", "--quiet"]);
+        git(&repo, &["add", "."]);
+        git(&repo, &["commit", "--quiet", "--message", "Add shapes"]);
+        git(&repo, &["tag", "v1"]);
+        let first = git(&repo, &["rev-parse", "HEAD"]);
+
+        let root = dir.join("app");
+        let url = format!("file://{}", repo.display());
+        let src = "fn main() -> u64 {\n\tshapes::two()\n}\n";
+        let depend_on = |reference: &str| {
+            let dependencies = format!("shapes = {{ git = \"{url}\"{reference} }}\n");
+            write_project(&root, &dir.join("registry"), &dependencies, src);
+        };
+
+        let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(PRE_INTERNED_VALUES));
+        let driver = || {
+            Driver::new(flux_hir::Config::release(), MessageFormat::Human, interner)
+                .with_flux_home(dir.join("home"))
+        };
+        let build = || {
+            let mut driver = driver();
+            let config = driver.load_config(&root, false).unwrap();
+            let (built_packages, status) =
+                driver.build_project(root.clone(), &config, &LintLevels::default());
+            assert_eq!(status, ExitStatus::Success);
+            assert_eq!(driver.run_error(built_packages[0]), None);
+            Lockfile::read(&root).unwrap().unwrap()
+        };
+        let resolve = || {
+            let mut driver = driver();
+            let config = driver.load_config(&root, false).unwrap();
+            let registry = driver.registry(&root, &config);
+            driver
+                .resolve_dependencies(&root, &config, &registry)
+                .map(|_| ())
+                .map_err(|err| err.code())
+        };
+
+        depend_on(", tag = \"v1\"");
+        let lockfile = build();
+        let shapes = &lockfile.packages[0];
+        assert_eq!(
+            (shapes.name.as_str(), shapes.version.as_str()),
+            ("shapes", "1.0.0")
+        );
+        assert_eq!(shapes.source, Some(format!("git+{url}?tag=v1#{first}")));
+
+        // Moving the tag doesn't change what is built until the lockfile is updated
+        fs::write(repo.join("src/main.flx"), "pub fn two() -> u64 {\n\t3\n}\n").unwrap();
+        git(
+            &repo,
+            &["commit", "--quiet", "--all", "--message", "Change shapes"],
+        );
+        git(&repo, &["tag", "--force", "v1"]);
+        assert_eq!(build(), lockfile);
+        fs::remove_file(root.join(LOCKFILE_NAME)).unwrap();
+        let second = git(&repo, &["rev-parse", "HEAD"]);
+        assert_eq!(
+            build().packages[0].source,
+            Some(format!("git+{url}?tag=v1#{second}"))
+        );
+
+        depend_on(&format!(", rev = \"{}\"", &first[..10]));
+        assert_eq!(
+            build().get_git(&GitSource {
+                url: url.clone(),
+                reference: GitReference::Rev(first[..10].to_string()),
+            }),
+            Some(first.as_str())
+        );
+        depend_on("");
+        assert_eq!(resolve(), Ok(()));
+        for reference in [", branch = \"missing\"", ", rev = \"0123456789\""] {
+            depend_on(reference);
+            assert_eq!(
+                resolve(),
+                Err(DiagnosticCode::MissingGitRevision),
+                "{reference}"
+            );
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
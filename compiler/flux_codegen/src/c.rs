@@ -0,0 +1,795 @@
+use std::collections::{HashMap, HashSet};
+
+use flux_diagnostics::{ice, Diagnostic};
+use flux_hir::def::expr::Op;
+use flux_id::id::{self, InPkg};
+use flux_mir::{
+    mir::{BasicBlock, Callee, Const, Operand, Place, Projection, Rvalue, Statement, Terminator},
+    Body,
+};
+use flux_typesystem::{ConcreteKind, FnSignature, TypeKind};
+use flux_util::{Path, Word};
+
+use crate::{
+    layout::{Field, IntKind, Layout, Repr, Scalar, Variant},
+    mono::MonoItems,
+    program::{Instance, Program},
+};
+
+const INCLUDES: &str = "\
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+";
+
+/// `@flux.intrinsics.memcpy` copies the whole source allocation, so allocations remember their size in front of
+/// the memory handed out to the program
+///
+/// A size too large to allocate along with that header aborts, as running out of memory does.
+const RUNTIME: &str = "\
+static inline void *flux_malloc(uint64_t size) {
+    if (size > SIZE_MAX - sizeof(uint64_t)) {
+        abort();
+    }
+    uint64_t *ptr = malloc(sizeof(uint64_t) + size);
+    if (!ptr) {
+        abort();
+    }
+    *ptr = size;
+    return ptr + 1;
+}
+
+static inline void flux_free(void *ptr) {
+    if (ptr) {
+        free((uint64_t *)ptr - 1);
+    }
+}
+
+static inline void flux_memcpy(void *dst, const void *src) {
+    memcpy(dst, src, ((const uint64_t *)src)[-1]);
+}
+";
+
+/// Generate one C11 translation unit for every package, containing the functions reachable from `main` that the
+/// package defines
+///
+/// Every unit declares all the types and functions of the program, so the units can be compiled separately and
+/// linked together. The unit of the package `main` belongs to also defines the C `main` function.
+pub(crate) fn emit_c(
+    main: InPkg<id::FnDecl>,
+    program: Program,
+    mono: &MonoItems,
+) -> Result<Vec<(id::Pkg, String)>, Diagnostic> {
+    let mut cx = CCtx {
+        program,
+        mono,
+        functions: HashMap::new(),
+        types: HashMap::new(),
+        forward_decls: String::new(),
+        type_defs: String::new(),
+        prototypes: String::new(),
+        definitions: HashMap::new(),
+    };
+    for instance in &mono.instances {
+        cx.declare_fn(instance.clone())?;
+    }
+    for instance in &mono.instances {
+        cx.define_fn(instance.clone())?;
+    }
+
+    let main_instance = Instance::new(main, vec![]);
+    let main_name = &cx.functions[&main_instance];
+
+    let main_ret = cx.program.normalize(
+        &cx.program.body(main).locals.get(Body::RETURN_PLACE).ty,
+        &main_instance,
+    );
+    let entry = match cx.program.layout_in(&main_ret, main)?.repr {
+        Repr::Scalar(_) => format!("int main(void) {{\n    return (int){main_name}();\n}}\n"),
+        _ => format!("int main(void) {{\n    {main_name}();\n    return 0;\n}}\n"),
+    };
+
+    let units = cx
+        .program
+        .packages
+        .iter()
+        .map(|(pkg_id, package)| {
+            let name = cx.program.interner.resolve(&package.name);
+            let mut source =
+                format!("/* Generated by flux from the package `{name}` */\n\n{INCLUDES}\n");
+            if !cx.forward_decls.is_empty() {
+                source.push_str(&cx.forward_decls);
+                source.push('\n');
+            }
+            // Every definition is already followed by a blank line
+            source.push_str(&cx.type_defs);
+            source.push_str(RUNTIME);
+            source.push('\n');
+            source.push_str(&cx.prototypes);
+            if let Some(definitions) = cx.definitions.get(&pkg_id) {
+                source.push('\n');
+                source.push_str(definitions);
+            }
+            if pkg_id == main.pkg_id {
+                source.push('\n');
+                source.push_str(&entry);
+            }
+            (pkg_id, source)
+        })
+        .collect();
+    Ok(units)
+}
+
+struct CCtx<'a> {
+    program: Program<'a>,
+    mono: &'a MonoItems,
+    /// The C names of the functions that have been declared
+    functions: HashMap<Instance, String>,
+    /// The C names of the structs, enums and function pointers that have been declared
+    types: HashMap<TypeKind, String>,
+    /// `typedef`s that let types be referred to before they are defined
+    forward_decls: String,
+    type_defs: String,
+    prototypes: String,
+    /// The function definitions of each package
+    definitions: HashMap<id::Pkg, String>,
+}
+
+impl<'a> CCtx<'a> {
+    /// The C type of a value, or `None` if it has no runtime representation
+    fn c_type(
+        &mut self,
+        ty: &TypeKind,
+        fn_id: InPkg<id::FnDecl>,
+    ) -> Result<Option<String>, Diagnostic> {
+        let layout = self.program.layout_in(ty, fn_id)?;
+        let c_type = match layout.repr {
+            Repr::Unit => return Ok(None),
+            Repr::Scalar(Scalar::Int(int)) => int_type(int),
+            Repr::Scalar(Scalar::Bool) => "bool".to_string(),
+            Repr::Scalar(Scalar::Ptr) => match ty {
+                TypeKind::Concrete(ConcreteKind::Ptr(to)) => {
+                    let to = self.c_type(&to.kind, fn_id)?;
+                    format!("{}*", to.as_deref().unwrap_or("void"))
+                }
+                TypeKind::Concrete(ConcreteKind::Fn(signature)) => {
+                    self.fn_ptr_type(ty, signature, fn_id)?
+                }
+                _ => ice("pointer sized value that is neither a pointer nor a function"),
+            },
+            Repr::Aggregate(fields) => self.struct_type(ty, &fields, fn_id)?,
+            Repr::Enum(variants) => self.enum_type(ty, &variants, fn_id)?,
+        };
+        Ok(Some(c_type))
+    }
+
+    /// Declare a type the first time it is used, returning its name
+    ///
+    /// Structs and enums are declared before their fields are, so that they can point to themselves.
+    fn declare_type(&mut self, ty: &TypeKind) -> Result<String, String> {
+        if let Some(name) = self.types.get(ty) {
+            return Err(name.clone());
+        }
+        let name = format!("flux_{}", self.program.mangle(ty));
+        self.types.insert(ty.clone(), name.clone());
+        self.forward_decls
+            .push_str(&format!("typedef struct {name} {name};\n"));
+        Ok(name)
+    }
+
+    fn struct_type(
+        &mut self,
+        ty: &TypeKind,
+        fields: &[Field],
+        fn_id: InPkg<id::FnDecl>,
+    ) -> Result<String, Diagnostic> {
+        let name = match self.declare_type(ty) {
+            Ok(name) => name,
+            Err(name) => return Ok(name),
+        };
+
+        let mut def = format!("struct {name} {{\n");
+        let mut empty = true;
+        for field in fields {
+            if let Some(c_type) = self.c_type(&field.ty, fn_id)? {
+                def.push_str(&format!("    {c_type} {};\n", self.ident(&field.name)));
+                empty = false;
+            }
+        }
+        if empty {
+            def.push_str("    /* C does not allow empty structs */\n    uint8_t unused;\n");
+        }
+        def.push_str("};\n\n");
+        self.type_defs.push_str(&def);
+        Ok(name)
+    }
+
+    /// Enums are tagged unions, with constants naming the tag of each variant
+    fn enum_type(
+        &mut self,
+        ty: &TypeKind,
+        variants: &[Variant],
+        fn_id: InPkg<id::FnDecl>,
+    ) -> Result<String, Diagnostic> {
+        let name = match self.declare_type(ty) {
+            Ok(name) => name,
+            Err(name) => return Ok(name),
+        };
+
+        let mut tags = vec![];
+        let mut payloads = String::new();
+        for (tag, variant) in variants.iter().enumerate() {
+            let variant_name = self.ident(&variant.name);
+            tags.push(format!("    {name}_{variant_name} = {tag},\n"));
+            if let Some((payload_ty, _)) = &variant.payload {
+                if let Some(c_type) = self.c_type(payload_ty, fn_id)? {
+                    payloads.push_str(&format!("        {c_type} {variant_name};\n"));
+                }
+            }
+        }
+
+        let mut def = format!(
+            "enum {{\n{}}};\n\nstruct {name} {{\n    uint32_t tag;\n",
+            tags.concat()
+        );
+        if !payloads.is_empty() {
+            def.push_str(&format!("    union {{\n{payloads}    }} payload;\n"));
+        }
+        def.push_str("};\n\n");
+        self.type_defs.push_str(&def);
+        Ok(name)
+    }
+
+    fn fn_ptr_type(
+        &mut self,
+        ty: &TypeKind,
+        signature: &FnSignature,
+        fn_id: InPkg<id::FnDecl>,
+    ) -> Result<String, Diagnostic> {
+        if let Some(name) = self.types.get(ty) {
+            return Ok(name.clone());
+        }
+        let ret = self.c_type(&signature.return_ty().kind, fn_id)?;
+        let mut params = vec![];
+        for param in signature.parameters() {
+            params.extend(self.c_type(&param.kind, fn_id)?);
+        }
+        let name = format!("flux_fn_{}", self.types.len());
+        self.forward_decls.push_str(&format!(
+            "typedef {} (*{name})({});\n",
+            ret.as_deref().unwrap_or("void"),
+            param_list(params),
+        ));
+        self.types.insert(ty.clone(), name.clone());
+        Ok(name)
+    }
+
+    /// Flux identifiers that are C keywords get an underscore appended, and the elements of tuples, which are named by
+    /// their index, get one prepended
+    fn ident(&self, name: &Word) -> String {
+        let name = self.program.interner.resolve(name);
+        if name.starts_with(|c: char| c.is_ascii_digit()) {
+            format!("_{name}")
+        } else if C_KEYWORDS.contains(&name) {
+            format!("{name}_")
+        } else {
+            name.to_string()
+        }
+    }
+
+    fn declare_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let fn_id = instance.fn_id;
+        let package = self
+            .program
+            .interner
+            .resolve(&self.program.packages.get(fn_id.pkg_id).name);
+        let package: String = package
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let mut name = format!(
+            "{package}_{}_{}",
+            self.program.fn_name(fn_id),
+            fn_id.inner.raw()
+        );
+        for arg in &instance.args {
+            name = format!("{name}_{}", self.program.mangle(arg));
+        }
+
+        let signature = self.fn_signature(&instance, &name)?;
+        self.prototypes.push_str(&format!("{signature};\n"));
+        self.functions.insert(instance, name);
+        Ok(())
+    }
+
+    fn fn_signature(&mut self, instance: &Instance, name: &str) -> Result<String, Diagnostic> {
+        let body = self.program.body(instance.fn_id);
+        let ty = |local| self.program.normalize(&body.locals.get(local).ty, instance);
+        let ret = ty(Body::RETURN_PLACE);
+        let params: Vec<_> = body.args().map(|local| (local, ty(local))).collect();
+
+        let ret = self.c_type(&ret, instance.fn_id)?;
+        let mut c_params = vec![];
+        for (local, ty) in params {
+            if let Some(c_type) = self.c_type(&ty, instance.fn_id)? {
+                c_params.push(format!("{c_type} _{}", local.raw()));
+            }
+        }
+        Ok(format!(
+            "{} {name}({})",
+            ret.as_deref().unwrap_or("void"),
+            param_list(c_params)
+        ))
+    }
+
+    fn define_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let name = self.functions[&instance].clone();
+        let signature = self.fn_signature(&instance, &name)?;
+        let pkg_id = instance.fn_id.pkg_id;
+        let definition = FnEmitter::new(self, instance)?.emit(signature)?;
+        let definitions = self.definitions.entry(pkg_id).or_default();
+        if !definitions.is_empty() {
+            definitions.push('\n');
+        }
+        definitions.push_str(&definition);
+        Ok(())
+    }
+}
+
+struct Typed {
+    /// The C expression, which is empty for values without a runtime representation
+    expr: String,
+    ty: TypeKind,
+    layout: Layout,
+}
+
+struct FnEmitter<'cx, 'a> {
+    cx: &'cx mut CCtx<'a>,
+    instance: Instance,
+    body: &'a Body,
+    /// The normalized type of each local
+    locals: Vec<(TypeKind, Layout)>,
+    out: String,
+}
+
+impl<'cx, 'a> FnEmitter<'cx, 'a> {
+    fn new(cx: &'cx mut CCtx<'a>, instance: Instance) -> Result<Self, Diagnostic> {
+        let body = cx.program.body(instance.fn_id);
+        let mut locals = Vec::with_capacity(body.locals.len());
+        for decl in body.locals.values() {
+            let ty = cx.program.normalize(&decl.ty, &instance);
+            let layout = cx.program.layout_in(&ty, instance.fn_id)?;
+            locals.push((ty, layout));
+        }
+        Ok(Self {
+            cx,
+            instance,
+            body,
+            locals,
+            out: String::new(),
+        })
+    }
+
+    fn emit(mut self, signature: String) -> Result<String, Diagnostic> {
+        self.out = format!("{signature} {{\n");
+
+        // Every local is marked as used, since the MIR keeps temporaries around that are only ever written to
+        let mut used = String::new();
+        for (local, (ty, _)) in self.locals.clone().iter().enumerate() {
+            if local != 0 && local <= self.body.arg_count {
+                continue;
+            }
+            if let Some(c_type) = self.cx.c_type(ty, self.instance.fn_id)? {
+                self.line(format!("{c_type} _{local};"));
+                used.push_str(&format!("    (void)_{local};\n"));
+            }
+        }
+        self.out.push_str(&used);
+
+        let jump_targets: HashSet<_> = self
+            .body
+            .blocks
+            .values()
+            .flat_map(|block| block.terminator().successors())
+            .collect();
+        for (block, data) in self.body.blocks.iter() {
+            if jump_targets.contains(&block) {
+                self.out.push_str(&format!("bb{}:\n", block.raw()));
+            }
+            for statement in &data.statements {
+                match statement {
+                    Statement::Assign(place, rvalue) => self.assign(place, rvalue)?,
+                }
+            }
+            self.terminator(block, data.terminator())?;
+        }
+        self.out.push_str("}\n");
+        Ok(self.out)
+    }
+
+    fn line(&mut self, line: impl AsRef<str>) {
+        self.out.push_str("    ");
+        self.out.push_str(line.as_ref());
+        self.out.push('\n');
+    }
+
+    fn layout_of(&self, ty: &TypeKind) -> Result<(TypeKind, Layout), Diagnostic> {
+        let ty = self.cx.program.normalize(ty, &self.instance);
+        let layout = self.cx.program.layout_in(&ty, self.instance.fn_id)?;
+        Ok((ty, layout))
+    }
+
+    fn c_type(&mut self, ty: &TypeKind) -> Result<Option<String>, Diagnostic> {
+        self.cx.c_type(ty, self.instance.fn_id)
+    }
+
+    fn place(&mut self, place: &Place) -> Result<Typed, Diagnostic> {
+        let (mut ty, mut layout) = self.locals[place.local.raw() as usize].clone();
+        let mut expr = format!("_{}", place.local.raw());
+        for projection in &place.projection {
+            match projection {
+                Projection::Field(name) => {
+                    let field = layout
+                        .field(name)
+                        .unwrap_or_else(|| ice("field projection on a type without that field"))
+                        .clone();
+                    expr = format!("{expr}.{}", self.cx.ident(name));
+                    ty = field.ty;
+                    layout = field.layout;
+                }
+                Projection::Index(idx) => {
+                    (ty, layout) = self.layout_of(pointee(&ty))?;
+                    expr = format!("{expr}[_{}]", idx.raw());
+                }
+                Projection::Downcast(variant, discriminant) => {
+                    (ty, layout) = layout
+                        .payload(*discriminant)
+                        .cloned()
+                        .unwrap_or_else(|| ice("downcast to a variant without a payload"));
+                    expr = format!("{expr}.payload.{}", self.cx.ident(variant));
+                }
+            }
+        }
+        if let Repr::Unit = layout.repr {
+            expr.clear();
+        }
+        Ok(Typed { expr, ty, layout })
+    }
+
+    fn operand(&mut self, operand: &Operand) -> Result<Typed, Diagnostic> {
+        match operand {
+            Operand::Copy(place) => self.place(place),
+            Operand::Const(Const::Int(val, ty)) => {
+                let (ty, layout) = self.layout_of(ty)?;
+                let int = match layout.repr {
+                    Repr::Scalar(Scalar::Int(int)) => int,
+                    _ => ice("integer constant with a non integer type"),
+                };
+                let mask = u64::MAX >> (64 - int.bits);
+                Ok(Typed {
+                    expr: format!("(({}){}ull)", int_type(int), val & mask),
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Bool(val)) => {
+                let bool_ty = TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    vec![self.cx.program.interner.get_or_intern_static("bool")],
+                    vec![],
+                )));
+                let (ty, layout) = self.layout_of(&bool_ty)?;
+                Ok(Typed {
+                    expr: val.to_string(),
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Fn(fn_id)) => {
+                let expr = self.cx.functions[&Instance::new(*fn_id, vec![])].clone();
+                let fn_decl = self.cx.program.fn_decl(*fn_id);
+                let ty = TypeKind::Concrete(ConcreteKind::Fn(FnSignature::new(
+                    fn_decl.params.iter().map(|param| param.ty.inner.clone()),
+                    fn_decl.return_ty.inner.clone(),
+                )));
+                let (ty, layout) = self.layout_of(&ty)?;
+                Ok(Typed { expr, ty, layout })
+            }
+        }
+    }
+
+    fn assign(&mut self, place: &Place, rvalue: &Rvalue) -> Result<(), Diagnostic> {
+        let dest = self.place(place)?;
+        if let Repr::Unit = dest.layout.repr {
+            return Ok(());
+        }
+
+        let val = match rvalue {
+            Rvalue::Use(operand) => self.operand(operand)?.expr,
+            Rvalue::BinOp(op, lhs, rhs) => {
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                self.bin_op(*op, &lhs, &rhs)?
+            }
+            Rvalue::Cast(operand, _) => {
+                let val = self.operand(operand)?;
+                let c_type = self
+                    .c_type(&dest.ty)?
+                    .unwrap_or_else(|| ice("cast to a type without a runtime representation"));
+                match (val.layout.scalar_kind(), dest.layout.scalar_kind()) {
+                    (Some(Scalar::Ptr), Some(Scalar::Int(_)))
+                    | (Some(Scalar::Int(_)), Some(Scalar::Ptr)) => {
+                        format!("({c_type})(uintptr_t){}", val.expr)
+                    }
+                    (Some(_), Some(_)) => format!("({c_type}){}", val.expr),
+                    _ => val.expr,
+                }
+            }
+            Rvalue::Struct(_, fields) => {
+                let c_type = self
+                    .c_type(&dest.ty)?
+                    .unwrap_or_else(|| ice("struct without a runtime representation"));
+                let mut inits = vec![];
+                for (name, operand) in fields {
+                    let val = self.operand(operand)?;
+                    if !val.expr.is_empty() {
+                        inits.push(format!(".{} = {}", self.cx.ident(name), val.expr));
+                    }
+                }
+                if inits.is_empty() {
+                    inits.push("0".to_string());
+                }
+                format!("({c_type}){{ {} }}", inits.join(", "))
+            }
+            Rvalue::Enum(variant, discriminant, payload) => {
+                let c_type = self
+                    .c_type(&dest.ty)?
+                    .unwrap_or_else(|| ice("enum without a runtime representation"));
+                let mut inits = vec![format!(".tag = {discriminant}")];
+                if let Some(operand) = payload {
+                    let val = self.operand(operand)?;
+                    if !val.expr.is_empty() {
+                        inits.push(format!(
+                            ".payload.{} = {}",
+                            self.cx.ident(variant),
+                            val.expr
+                        ));
+                    }
+                }
+                format!("({c_type}){{ {} }}", inits.join(", "))
+            }
+            Rvalue::Discriminant(place) => format!("{}.tag", self.place(place)?.expr),
+            Rvalue::Tuple(operands) if operands.is_empty() => return Ok(()),
+            Rvalue::Tuple(operands) => {
+                let c_type = self
+                    .c_type(&dest.ty)?
+                    .unwrap_or_else(|| ice("tuple without a runtime representation"));
+                let fields = match &dest.layout.repr {
+                    Repr::Aggregate(fields) => fields.clone(),
+                    _ => ice("tuple expression assigned to a place that is not a tuple"),
+                };
+                let mut inits = vec![];
+                for (field, operand) in fields.iter().zip(operands) {
+                    let val = self.operand(operand)?;
+                    if !val.expr.is_empty() {
+                        inits.push(format!(".{} = {}", self.cx.ident(&field.name), val.expr));
+                    }
+                }
+                if inits.is_empty() {
+                    inits.push("0".to_string());
+                }
+                format!("({c_type}){{ {} }}", inits.join(", "))
+            }
+        };
+        self.line(format!("{} = {val};", dest.expr));
+        Ok(())
+    }
+
+    /// Integer arithmetic wraps around like in release builds of native code, which C only guarantees for unsigned
+    /// integers at least as wide as an `int`
+    fn bin_op(&mut self, op: Op, lhs: &Typed, rhs: &Typed) -> Result<String, Diagnostic> {
+        let (l, r) = (&lhs.expr, &rhs.expr);
+        let val = match lhs.layout.scalar_kind() {
+            Some(Scalar::Int(int)) => {
+                let c_type = int_type(int);
+                match op {
+                    Op::Add | Op::Sub | Op::Mul => {
+                        let wrapping = int_type(IntKind {
+                            bits: int.bits.max(32),
+                            signed: false,
+                        });
+                        format!("({c_type})(({wrapping}){l} {op} ({wrapping}){r})")
+                    }
+                    Op::Div => format!("({c_type})({l} / ({c_type}){r})"),
+                    Op::CmpEq | Op::CmpNeq | Op::CmpGt | Op::CmpGte | Op::CmpLt | Op::CmpLte => {
+                        format!("{l} {op} ({c_type}){r}")
+                    }
+                    Op::Eq | Op::CmpAnd | Op::CmpOr => {
+                        ice(format!("unsupported binary operation `{op}` on integers"))
+                    }
+                }
+            }
+            Some(Scalar::Bool) => match op {
+                Op::CmpAnd | Op::CmpOr | Op::CmpEq | Op::CmpNeq => format!("{l} {op} {r}"),
+                _ => ice(format!("unsupported binary operation `{op}` on booleans")),
+            },
+            Some(Scalar::Ptr) => match op {
+                Op::CmpEq | Op::CmpNeq => {
+                    let c_type = self
+                        .c_type(&lhs.ty)?
+                        .unwrap_or_else(|| ice("pointer without a runtime representation"));
+                    format!("{l} {op} ({c_type}){r}")
+                }
+                _ => ice(format!("unsupported binary operation `{op}` on pointers")),
+            },
+            None => ice(format!("unsupported binary operation `{op}`")),
+        };
+        Ok(val)
+    }
+
+    fn terminator(&mut self, block: BasicBlock, terminator: &Terminator) -> Result<(), Diagnostic> {
+        match terminator {
+            Terminator::Goto(target) => self.line(format!("goto bb{};", target.raw())),
+            // A `switch` on a boolean is warned about, so every switch is a chain of comparisons
+            Terminator::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                let discr = self.operand(discr)?.expr;
+                for (val, target) in targets {
+                    self.line(format!("if ({discr} == {val}u) goto bb{};", target.raw()));
+                }
+                self.line(format!("goto bb{};", otherwise.raw()));
+            }
+            Terminator::Return => match self.locals[Body::RETURN_PLACE.raw() as usize].1.repr {
+                Repr::Unit => self.line("return;"),
+                _ => self.line("return _0;"),
+            },
+            Terminator::Call {
+                callee,
+                args,
+                dest,
+                target,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.operand(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let dest = self.place(dest)?;
+                let callee = match callee {
+                    Callee::Fn(Operand::Const(Const::Fn(_))) | Callee::Method(_) => {
+                        let callee = self.cx.mono.callee(&self.instance, block);
+                        Some(self.cx.functions[callee].clone())
+                    }
+                    Callee::Fn(operand) => Some(format!("({})", self.operand(operand)?.expr)),
+                    Callee::Intrinsic(name) => {
+                        self.intrinsic(name, &args, &dest)?;
+                        None
+                    }
+                };
+                if let Some(callee) = callee {
+                    let args: Vec<_> = args
+                        .into_iter()
+                        .map(|arg| arg.expr)
+                        .filter(|arg| !arg.is_empty())
+                        .collect();
+                    let call = format!("{callee}({})", args.join(", "));
+                    match dest.layout.repr {
+                        Repr::Unit => self.line(format!("{call};")),
+                        _ => self.line(format!("{} = {call};", dest.expr)),
+                    }
+                }
+                match target {
+                    Some(target) => self.line(format!("goto bb{};", target.raw())),
+                    None => self.line("abort();"),
+                }
+            }
+            Terminator::Unreachable => self.line("abort();"),
+        }
+        Ok(())
+    }
+
+    /// Intrinsics map either to a C operator or to a call into the C standard library
+    fn intrinsic(&mut self, name: &Word, args: &[Typed], dest: &Typed) -> Result<(), Diagnostic> {
+        let name = self.cx.program.interner.resolve(name);
+        let name = name
+            .strip_prefix("@flux.intrinsics.")
+            .unwrap_or_else(|| ice(format!("unknown intrinsic `{name}`")));
+        let val = match name {
+            "panic" => {
+                self.line("abort();");
+                return Ok(());
+            }
+            "free" => {
+                self.line(format!("flux_free({});", args[0].expr));
+                return Ok(());
+            }
+            "memcpy" => {
+                self.line(format!("flux_memcpy({}, {});", args[0].expr, args[1].expr));
+                return Ok(());
+            }
+            "malloc" => format!("flux_malloc({})", args[0].expr),
+            "ptr_read" => format!("{}[{}]", args[0].expr, args[1].expr),
+            _ => {
+                let op = match name.rsplit_once('_') {
+                    Some(("add", _)) => Op::Add,
+                    Some(("mul", _)) => Op::Mul,
+                    Some(("cmp_eq", _)) => Op::CmpEq,
+                    _ => ice(format!("unknown intrinsic `{name}`")),
+                };
+                self.bin_op(op, &args[0], &args[1])?
+            }
+        };
+        if !dest.expr.is_empty() {
+            self.line(format!("{} = {val};", dest.expr));
+        }
+        Ok(())
+    }
+}
+
+fn int_type(int: IntKind) -> String {
+    format!("{}int{}_t", if int.signed { "" } else { "u" }, int.bits)
+}
+
+fn param_list(params: Vec<String>) -> String {
+    if params.is_empty() {
+        "void".to_string()
+    } else {
+        params.join(", ")
+    }
+}
+
+fn pointee(ty: &TypeKind) -> &TypeKind {
+    match ty {
+        TypeKind::Concrete(ConcreteKind::Ptr(to)) => &to.kind,
+        _ => ice("indexed a value that is not a pointer"),
+    }
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "_Alignas",
+    "_Alignof",
+    "_Atomic",
+    "_Bool",
+    "_Complex",
+    "_Generic",
+    "_Imaginary",
+    "_Noreturn",
+    "_Static_assert",
+    "_Thread_local",
+];
@@ -0,0 +1,54 @@
+use flux_proc_macros::diagnostic;
+
+#[diagnostic]
+pub enum CodegenError {
+    #[error(
+        location = function,
+        primary = "could not infer generic arguments",
+        label at function = "`{name}` calls `{callee}`, but the type of its generic parameter `{param}` could not be determined",
+        help = "generic parameters have to appear in the parameter or return types of a function to be inferred",
+    )]
+    UninferredGenericArguments {
+        #[filespanned]
+        function: (),
+        name: String,
+        callee: String,
+        param: String,
+    },
+    #[error(
+        location = function,
+        primary = "cannot generate code for type",
+        label at function = "`{name}` uses the type `{ty}`, which has no native representation",
+    )]
+    UnsupportedType {
+        #[filespanned]
+        function: (),
+        name: String,
+        ty: String,
+    },
+    #[error(
+        location = function,
+        primary = "could not resolve method",
+        label at function = "`{name}` calls the method `{method}` on `{ty}`, but no application provides it",
+    )]
+    UnresolvedMethod {
+        #[filespanned]
+        function: (),
+        name: String,
+        method: String,
+        ty: String,
+    },
+    #[error(
+        location = function,
+        primary = "reached the recursion limit while instantiating generic functions",
+        label at function = "`{name}` instantiates `{instance}`, after instances of it instantiated it {limit} times",
+        help = "this usually means a generic function calls itself with a type that grows on every call",
+    )]
+    InstantiationRecursionLimit {
+        #[filespanned]
+        function: (),
+        name: String,
+        instance: String,
+        limit: usize,
+    },
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use flux_hir::def::GenericParams;
+use flux_typesystem::{ConcreteKind, Type, TypeKind};
+use flux_util::Word;
+
+use crate::program::Program;
+
+/// Target specific information needed to lay out types in memory
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LayoutCx {
+    pub(crate) ptr_bytes: u32,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Layout {
+    pub(crate) size: u32,
+    pub(crate) align: u32,
+    pub(crate) repr: Repr,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum Repr {
+    /// Types without any runtime representation, such as `()` and `!`
+    Unit,
+    Scalar(Scalar),
+    /// Structs, which always live in memory
+    Aggregate(Vec<Field>),
+    /// Enums, which live in memory as a `u32` tag followed by the payload of the variant the tag selects
+    Enum(Vec<Variant>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Scalar {
+    Int(IntKind),
+    Bool,
+    Ptr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct IntKind {
+    pub(crate) bits: u8,
+    pub(crate) signed: bool,
+}
+
+impl IntKind {
+    /// Integer literals whose type could not be narrowed down are `u64`s, like in the interpreter
+    const DEFAULT: Self = Self {
+        bits: 64,
+        signed: false,
+    };
+
+    /// The type of the tag that selects the variant of an enum
+    pub(crate) const TAG: Self = Self {
+        bits: 32,
+        signed: false,
+    };
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        let signed = match &name[..1] {
+            "s" => true,
+            "u" => false,
+            _ => return None,
+        };
+        let bits = match &name[1..] {
+            "64" => 64,
+            "32" => 32,
+            "16" => 16,
+            "8" => 8,
+            _ => return None,
+        };
+        Some(Self { bits, signed })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Field {
+    pub(crate) name: Word,
+    pub(crate) offset: u32,
+    pub(crate) ty: TypeKind,
+    pub(crate) layout: Layout,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Variant {
+    pub(crate) name: Word,
+    pub(crate) payload: Option<(TypeKind, Layout)>,
+}
+
+impl Layout {
+    const UNIT: Self = Self {
+        size: 0,
+        align: 1,
+        repr: Repr::Unit,
+    };
+
+    fn scalar(scalar: Scalar, size: u32) -> Self {
+        Self {
+            size,
+            align: size,
+            repr: Repr::Scalar(scalar),
+        }
+    }
+
+    pub(crate) fn scalar_kind(&self) -> Option<Scalar> {
+        match self.repr {
+            Repr::Scalar(scalar) => Some(scalar),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn in_memory(&self) -> bool {
+        matches!(self.repr, Repr::Aggregate(_) | Repr::Enum(_))
+    }
+
+    pub(crate) fn field(&self, name: &Word) -> Option<&Field> {
+        match &self.repr {
+            Repr::Aggregate(fields) => fields.iter().find(|field| field.name == *name),
+            _ => None,
+        }
+    }
+
+    /// The type and layout of the payload of the variant with the tag `discriminant`
+    pub(crate) fn payload(&self, discriminant: u32) -> Option<&(TypeKind, Layout)> {
+        match &self.repr {
+            Repr::Enum(variants) => variants.get(discriminant as usize)?.payload.as_ref(),
+            _ => None,
+        }
+    }
+
+    /// The offset of the payload of an enum, which is the same for every variant
+    pub(crate) fn payload_offset(&self) -> Option<u32> {
+        match &self.repr {
+            Repr::Enum(variants) => Some(payload_offset(
+                variants
+                    .iter()
+                    .filter_map(|variant| variant.payload.as_ref())
+                    .map(|(_, layout)| layout.align)
+                    .max()
+                    .unwrap_or(1),
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl LayoutCx {
+    /// Lay out a type that has already had `This` normalized away, returning `None` if it has no runtime
+    /// representation yet
+    pub(crate) fn layout_of(&self, ty: &TypeKind, program: &Program) -> Option<Layout> {
+        match ty {
+            TypeKind::Int => Some(self.int(IntKind::DEFAULT)),
+            TypeKind::Never => Some(Layout::UNIT),
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) if types.is_empty() => {
+                Some(Layout::UNIT)
+            }
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) => self.aggregate_layout(
+                types.iter().enumerate().map(|(idx, ty)| {
+                    (
+                        program.interner.get_or_intern(idx.to_string()),
+                        ty.kind.clone(),
+                    )
+                }),
+                program,
+            ),
+            TypeKind::Concrete(ConcreteKind::Ptr(_) | ConcreteKind::Fn(_)) => {
+                Some(Layout::scalar(Scalar::Ptr, self.ptr_bytes))
+            }
+            TypeKind::Concrete(ConcreteKind::Path(path)) => {
+                let name = path.last()?;
+                match program.interner.resolve(name) {
+                    "bool" => Some(Layout::scalar(Scalar::Bool, 1)),
+                    s => match IntKind::from_name(s) {
+                        Some(int) if path.len() == 1 => Some(self.int(int)),
+                        _ if program.get_enum(name).is_some() => {
+                            self.enum_layout(name, &path.args, program)
+                        }
+                        _ => self.struct_layout(name, &path.args, program),
+                    },
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn int(&self, int: IntKind) -> Layout {
+        Layout::scalar(Scalar::Int(int), int.bits as u32 / 8)
+    }
+
+    fn struct_layout(&self, name: &Word, args: &[Type], program: &Program) -> Option<Layout> {
+        let struct_decl = program.get_struct(name)?;
+        let generics = generic_args(&struct_decl.generic_params, args);
+        self.aggregate_layout(
+            struct_decl.fields.iter().map(|field| {
+                (
+                    field.name.inner,
+                    program.substitute(&field.ty.kind, None, &generics),
+                )
+            }),
+            program,
+        )
+    }
+
+    /// Fields are laid out in order, each aligned to its own alignment
+    ///
+    /// The elements of a tuple are laid out like the fields of a struct, named by their index.
+    fn aggregate_layout(
+        &self,
+        fields: impl Iterator<Item = (Word, TypeKind)>,
+        program: &Program,
+    ) -> Option<Layout> {
+        let mut size: u32 = 0;
+        let mut align = 1;
+        let mut laid_out = vec![];
+        for (name, ty) in fields {
+            let layout = self.layout_of(&ty, program)?;
+            let offset = size.next_multiple_of(layout.align);
+            size = offset + layout.size;
+            align = align.max(layout.align);
+            laid_out.push(Field {
+                name,
+                offset,
+                ty,
+                layout,
+            });
+        }
+
+        Some(Layout {
+            size: size.next_multiple_of(align),
+            align,
+            repr: Repr::Aggregate(laid_out),
+        })
+    }
+
+    /// Payloads all start at the same offset, after the tag, so the enum is as big as its largest payload
+    fn enum_layout(&self, name: &Word, args: &[Type], program: &Program) -> Option<Layout> {
+        let enum_decl = program.get_enum(name)?;
+        let generics = generic_args(&enum_decl.generic_params, args);
+
+        let tag = self.int(IntKind::TAG);
+        let mut payload_size = 0;
+        let mut payload_align = 1;
+        let mut variants = vec![];
+        for variant in enum_decl.variants.iter() {
+            let payload = match &variant.ty {
+                Some(ty) => {
+                    let ty = program.substitute(&ty.kind, None, &generics);
+                    let layout = self.layout_of(&ty, program)?;
+                    payload_size = payload_size.max(layout.size);
+                    payload_align = payload_align.max(layout.align);
+                    Some((ty, layout))
+                }
+                None => None,
+            };
+            variants.push(Variant {
+                name: variant.name.inner,
+                payload,
+            });
+        }
+
+        let payload_offset = payload_offset(payload_align);
+        let align = tag.align.max(payload_align);
+        Some(Layout {
+            size: (payload_offset + payload_size).next_multiple_of(align),
+            align,
+            repr: Repr::Enum(variants),
+        })
+    }
+}
+
+fn generic_args(generic_params: &GenericParams, args: &[Type]) -> HashMap<Word, TypeKind> {
+    generic_params
+        .types
+        .values()
+        .map(|name| name.inner)
+        .zip(args.iter().map(|arg| arg.kind.clone()))
+        .collect()
+}
+
+fn payload_offset(payload_align: u32) -> u32 {
+    (IntKind::TAG.bits as u32 / 8).next_multiple_of(payload_align)
+}
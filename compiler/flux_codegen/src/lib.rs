@@ -0,0 +1,132 @@
+use std::{collections::HashMap, path::Path, process::Command};
+
+use flux_diagnostics::{ice, Diagnostic};
+use flux_hir::Package;
+use flux_id::{
+    id::{self, InPkg},
+    Map,
+};
+use flux_mir::PackageMir;
+use flux_util::Interner;
+use layout::LayoutCx;
+use program::{Instance, Program};
+
+mod c;
+mod diagnostics;
+mod layout;
+mod mono;
+mod native;
+mod program;
+mod wasm;
+
+#[derive(Debug, Clone, Copy)]
+pub enum OptLevel {
+    None,
+    Speed,
+    SpeedAndSize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub opt_level: OptLevel,
+    /// Trap on integer overflow and verify the generated code
+    pub debug_assertions: bool,
+}
+
+/// C source code generated for a single package
+#[derive(Debug, Clone)]
+pub struct TranslationUnit {
+    pub package: String,
+    pub source: String,
+}
+
+/// A WebAssembly module in both the text and binary formats
+#[derive(Debug, Clone)]
+pub struct WasmModule {
+    pub text: String,
+    pub binary: Vec<u8>,
+}
+
+/// Compile every function reachable from `main` into an object file for the host machine
+///
+/// Generic functions are compiled once for every combination of types they are called with.
+pub fn emit_object(
+    name: &str,
+    main: InPkg<id::FnDecl>,
+    packages: &Map<id::Pkg, Package>,
+    mir: &HashMap<id::Pkg, PackageMir>,
+    interner: &'static Interner,
+    options: &Options,
+) -> Result<Vec<u8>, Diagnostic> {
+    let layout_cx = LayoutCx {
+        ptr_bytes: usize::BITS / 8,
+    };
+    let program = Program::new(packages, mir, interner, layout_cx);
+    let mono = mono::collect(&[Instance::new(main, vec![])], &program)?;
+    native::emit_object(name, main, program, &mono, options)
+}
+
+/// Translate every function reachable from `main` into C11, with one translation unit per package
+///
+/// The units only rely on the C standard library, so that programs can be built wherever there is a C compiler.
+pub fn emit_c(
+    main: InPkg<id::FnDecl>,
+    packages: &Map<id::Pkg, Package>,
+    mir: &HashMap<id::Pkg, PackageMir>,
+    interner: &'static Interner,
+) -> Result<Vec<TranslationUnit>, Diagnostic> {
+    let layout_cx = LayoutCx {
+        ptr_bytes: usize::BITS / 8,
+    };
+    let program = Program::new(packages, mir, interner, layout_cx);
+    let mono = mono::collect(&[Instance::new(main, vec![])], &program)?;
+    let units = c::emit_c(main, program, &mono)?;
+    Ok(units
+        .into_iter()
+        .map(|(pkg_id, source)| TranslationUnit {
+            package: interner.resolve(&packages.get(pkg_id).name).to_string(),
+            source,
+        })
+        .collect())
+}
+
+/// Compile `exports`, and every function reachable from them, into a WebAssembly module for `wasm32`
+///
+/// The module exports each function under its name, along with its linear memory. It does not import anything, so
+/// it can be run by any WebAssembly runtime.
+pub fn emit_wasm(
+    exports: &[InPkg<id::FnDecl>],
+    packages: &Map<id::Pkg, Package>,
+    mir: &HashMap<id::Pkg, PackageMir>,
+    interner: &'static Interner,
+) -> Result<WasmModule, Diagnostic> {
+    let layout_cx = LayoutCx { ptr_bytes: 4 };
+    let program = Program::new(packages, mir, interner, layout_cx);
+    let roots: Vec<_> = exports
+        .iter()
+        .map(|fn_id| Instance::new(*fn_id, vec![]))
+        .collect();
+    let mono = mono::collect(&roots, &program)?;
+    let text = wasm::emit_wat(exports, program, &mono)?;
+    let binary = wat::parse_str(&text)
+        .unwrap_or_else(|err| ice(format!("generated invalid WebAssembly: {err}")));
+    Ok(WasmModule { text, binary })
+}
+
+/// Link an object file into an executable with the system's C compiler, which also links in libc
+///
+/// The compiler can be overridden with the `CC` environment variable.
+pub fn link(object: &Path, output: &Path) -> Result<(), String> {
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let result = Command::new(&cc)
+        .arg(object)
+        .arg("-o")
+        .arg(output)
+        .output()
+        .map_err(|err| format!("could not run `{cc}`: {err}"))?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&result.stderr).into_owned())
+    }
+}
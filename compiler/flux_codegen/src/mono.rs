@@ -0,0 +1,264 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_id::id::{self, InPkg};
+use flux_mir::{
+    mir::{BasicBlock, Callee, Const, Operand, Place, Projection, Rvalue, Statement, Terminator},
+    Body,
+};
+use flux_typesystem::{ConcreteKind, FnSignature, TypeKind};
+use flux_util::Path;
+
+use crate::{
+    diagnostics::CodegenError,
+    program::{Instance, Program},
+};
+
+/// How many times instances of a function can instantiate it again before it is assumed to never end, as when a generic
+/// function calls itself with an ever growing type
+const RECURSION_LIMIT: usize = 64;
+
+/// The instances of functions that make up a program
+#[derive(Debug)]
+pub(crate) struct MonoItems {
+    /// Every instance reachable from the roots, in the order they were found
+    pub(crate) instances: Vec<Instance>,
+    /// The instance called by the terminator of each block that calls a function directly or calls a method
+    callees: HashMap<(Instance, BasicBlock), Instance>,
+}
+
+impl MonoItems {
+    /// The instance called at the end of `block` in `caller`
+    pub(crate) fn callee(&self, caller: &Instance, block: BasicBlock) -> &Instance {
+        self.callees
+            .get(&(caller.clone(), block))
+            .unwrap_or_else(|| ice("call was not visited by the monomorphization collector"))
+    }
+}
+
+/// Find every instance of a function reachable from `roots`, substituting the generic arguments of each caller
+/// into the calls it makes
+///
+/// Methods are resolved against the concrete type of their receiver, so each instance of a generic application
+/// calls the methods of the type it was instantiated with.
+pub(crate) fn collect(roots: &[Instance], program: &Program) -> Result<MonoItems, Diagnostic> {
+    let mut collector = Collector {
+        program,
+        seen: roots.iter().cloned().collect(),
+        queue: roots.iter().map(|root| (root.clone(), vec![])).collect(),
+        items: MonoItems {
+            instances: roots.to_vec(),
+            callees: HashMap::new(),
+        },
+    };
+    while let Some((instance, chain)) = collector.queue.pop_front() {
+        collector.visit(&instance, &chain)?;
+    }
+    Ok(collector.items)
+}
+
+struct Collector<'p, 'a> {
+    program: &'p Program<'a>,
+    seen: HashSet<Instance>,
+    /// Instances that have been found but not visited yet, along with the functions of the chain of generic instances
+    /// that instantiated them
+    queue: VecDeque<(Instance, Vec<InPkg<id::FnDecl>>)>,
+    items: MonoItems,
+}
+
+impl Collector<'_, '_> {
+    fn visit(
+        &mut self,
+        instance: &Instance,
+        chain: &[InPkg<id::FnDecl>],
+    ) -> Result<(), Diagnostic> {
+        let body = self.program.body(instance.fn_id);
+        for (block, data) in body.blocks.iter() {
+            for statement in &data.statements {
+                match statement {
+                    Statement::Assign(_, rvalue) => match rvalue {
+                        Rvalue::Use(operand) | Rvalue::Cast(operand, _) => {
+                            self.visit_operand(instance, operand, chain)?
+                        }
+                        Rvalue::BinOp(_, lhs, rhs) => {
+                            self.visit_operand(instance, lhs, chain)?;
+                            self.visit_operand(instance, rhs, chain)?;
+                        }
+                        Rvalue::Struct(_, fields) => {
+                            for (_, operand) in fields {
+                                self.visit_operand(instance, operand, chain)?;
+                            }
+                        }
+                        Rvalue::Enum(_, _, payload) => {
+                            if let Some(payload) = payload {
+                                self.visit_operand(instance, payload, chain)?;
+                            }
+                        }
+                        Rvalue::Tuple(operands) => {
+                            for operand in operands {
+                                self.visit_operand(instance, operand, chain)?;
+                            }
+                        }
+                        Rvalue::Discriminant(_) => {}
+                    },
+                }
+            }
+
+            match data.terminator() {
+                Terminator::SwitchInt { discr, .. } => {
+                    self.visit_operand(instance, discr, chain)?
+                }
+                Terminator::Call {
+                    callee, args, dest, ..
+                } => {
+                    for arg in args {
+                        self.visit_operand(instance, arg, chain)?;
+                    }
+                    let fn_id = match callee {
+                        Callee::Fn(Operand::Const(Const::Fn(fn_id))) => *fn_id,
+                        Callee::Fn(operand) => {
+                            self.visit_operand(instance, operand, chain)?;
+                            continue;
+                        }
+                        Callee::Method(name) => {
+                            let receiver = args
+                                .first()
+                                .unwrap_or_else(|| ice("method call without a receiver"));
+                            let receiver = self.operand_ty(body, instance, receiver)?;
+                            self.program
+                                .resolve_method(&receiver, name, instance.fn_id)?
+                        }
+                        Callee::Intrinsic(_) => continue,
+                    };
+                    let arg_tys = args
+                        .iter()
+                        .map(|arg| self.operand_ty(body, instance, arg))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let dest_ty = self.place_ty(body, instance, dest)?;
+                    let callee = self.program.instantiate(
+                        fn_id,
+                        &arg_tys,
+                        Some(&dest_ty),
+                        instance.fn_id,
+                    )?;
+                    self.items
+                        .callees
+                        .insert((instance.clone(), block), callee.clone());
+                    self.found(instance, callee, chain)?;
+                }
+                Terminator::Goto(_) | Terminator::Return | Terminator::Unreachable => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Functions referred to without being called are only ever instantiated without generic arguments
+    fn visit_operand(
+        &mut self,
+        instance: &Instance,
+        operand: &Operand,
+        chain: &[InPkg<id::FnDecl>],
+    ) -> Result<(), Diagnostic> {
+        if let Operand::Const(Const::Fn(fn_id)) = operand {
+            let referred = self
+                .program
+                .instantiate(*fn_id, &[], None, instance.fn_id)?;
+            self.found(instance, referred, chain)?;
+        }
+        Ok(())
+    }
+
+    fn found(
+        &mut self,
+        caller: &Instance,
+        instance: Instance,
+        chain: &[InPkg<id::FnDecl>],
+    ) -> Result<(), Diagnostic> {
+        if self.seen.contains(&instance) {
+            return Ok(());
+        }
+        // Only a generic instance can instantiate something it was not written to call, so a chain of calls between
+        // functions that aren't generic, however long, always ends. A chain of generic instances can only go on forever
+        // by coming back to a function it already went through, so it is how often that happens that is limited.
+        let chain: Vec<_> = if caller.args.is_empty() || instance.args.is_empty() {
+            vec![]
+        } else {
+            chain.iter().copied().chain([caller.fn_id]).collect()
+        };
+        let depth = chain
+            .iter()
+            .filter(|fn_id| **fn_id == instance.fn_id)
+            .count();
+        if depth > RECURSION_LIMIT {
+            return Err(CodegenError::InstantiationRecursionLimit {
+                function: (),
+                function_file_span: self.program.fn_span(caller.fn_id),
+                name: self.program.fn_name(caller.fn_id).to_string(),
+                instance: self.program.instance_name(&instance),
+                limit: RECURSION_LIMIT,
+            }
+            .to_diagnostic());
+        }
+        self.seen.insert(instance.clone());
+        self.items.instances.push(instance.clone());
+        self.queue.push_back((instance, chain));
+        Ok(())
+    }
+
+    fn operand_ty(
+        &self,
+        body: &Body,
+        instance: &Instance,
+        operand: &Operand,
+    ) -> Result<TypeKind, Diagnostic> {
+        Ok(match operand {
+            Operand::Copy(place) => self.place_ty(body, instance, place)?,
+            Operand::Const(Const::Int(_, ty)) => self.program.normalize(ty, instance),
+            Operand::Const(Const::Bool(_)) => TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                vec![self.program.interner.get_or_intern_static("bool")],
+                vec![],
+            ))),
+            Operand::Const(Const::Fn(fn_id)) => {
+                let fn_decl = self.program.fn_decl(*fn_id);
+                TypeKind::Concrete(ConcreteKind::Fn(FnSignature::new(
+                    fn_decl.params.iter().map(|param| param.ty.inner.clone()),
+                    fn_decl.return_ty.inner.clone(),
+                )))
+            }
+        })
+    }
+
+    fn place_ty(
+        &self,
+        body: &Body,
+        instance: &Instance,
+        place: &Place,
+    ) -> Result<TypeKind, Diagnostic> {
+        let mut ty = self
+            .program
+            .normalize(&body.locals.get(place.local).ty, instance);
+        for projection in &place.projection {
+            ty = match projection {
+                Projection::Field(name) => self
+                    .program
+                    .layout_in(&ty, instance.fn_id)?
+                    .field(name)
+                    .unwrap_or_else(|| ice("field projection on a type without that field"))
+                    .ty
+                    .clone(),
+                Projection::Index(_) => match ty {
+                    TypeKind::Concrete(ConcreteKind::Ptr(to)) => to.kind,
+                    _ => ice("indexed a value that is not a pointer"),
+                },
+                Projection::Downcast(_, discriminant) => self
+                    .program
+                    .layout_in(&ty, instance.fn_id)?
+                    .payload(*discriminant)
+                    .unwrap_or_else(|| ice("downcast to a variant without a payload"))
+                    .0
+                    .clone(),
+            };
+        }
+        Ok(ty)
+    }
+}
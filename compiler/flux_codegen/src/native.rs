@@ -0,0 +1,1063 @@
+use std::{collections::HashMap, rc::Rc};
+
+use cranelift_codegen::{
+    ir::{
+        condcodes::IntCC, types, AbiParam, Block, FuncRef, InstBuilder, MemFlags, Signature,
+        StackSlotData, StackSlotKind, TrapCode, Type as ClifType, UserFuncName, Value,
+    },
+    settings::{self, Configurable},
+};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Switch, Variable};
+use cranelift_module::{default_libcall_names, FuncId, Linkage, Module};
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use flux_diagnostics::{ice, Diagnostic};
+use flux_hir::def::expr::Op;
+use flux_id::id::{self, InPkg};
+use flux_mir::{
+    mir::{BasicBlock, Callee, Const, Operand, Place, Projection, Rvalue, Statement, Terminator},
+    Body,
+};
+use flux_typesystem::{ConcreteKind, FnSignature, TypeKind};
+use flux_util::{Path, Word};
+
+use crate::{
+    layout::{IntKind, Layout, Repr, Scalar},
+    mono::MonoItems,
+    program::{Instance, Program},
+    OptLevel, Options,
+};
+
+/// Reached when a function that never returns does, or when control reaches an `unreachable` terminator
+const UNREACHABLE: TrapCode = TrapCode::unwrap_user(1);
+
+/// Reached when `malloc` could not allocate the requested memory, where the C backend aborts
+const OUT_OF_MEMORY: TrapCode = TrapCode::unwrap_user(2);
+
+/// `@flux.intrinsics.memcpy` copies the whole source allocation, so allocations remember the size that was requested
+/// in front of the memory handed out to the program, like in the runtime of the C backend
+const ALLOC_HEADER_BYTES: i64 = 8;
+
+/// How a function's parameters and return value are passed
+///
+/// Scalars are passed in registers. Structs are passed as a pointer to a copy owned by the caller, and returned by
+/// writing to a pointer the caller passes as the first parameter. Values with no runtime representation are not
+/// passed at all.
+#[derive(Debug)]
+struct FnAbi {
+    ret: Layout,
+    params: Vec<Layout>,
+    signature: Signature,
+}
+
+/// Compile every function reachable from `main` into a relocatable object file
+pub(crate) fn emit_object(
+    name: &str,
+    main: InPkg<id::FnDecl>,
+    program: Program,
+    mono: &MonoItems,
+    options: &Options,
+) -> Result<Vec<u8>, Diagnostic> {
+    let mut flags = settings::builder();
+    let opt_level = match options.opt_level {
+        OptLevel::None => "none",
+        OptLevel::Speed => "speed",
+        OptLevel::SpeedAndSize => "speed_and_size",
+    };
+    set_flag(&mut flags, "opt_level", opt_level);
+    set_flag(&mut flags, "is_pic", "true");
+    set_flag(
+        &mut flags,
+        "enable_verifier",
+        if options.debug_assertions {
+            "true"
+        } else {
+            "false"
+        },
+    );
+    let isa = cranelift_native::builder()
+        .unwrap_or_else(|err| ice(format!("host machine is not supported by cranelift: {err}")))
+        .finish(settings::Flags::new(flags))
+        .unwrap_or_else(|err| ice(format!("could not build target isa: {err}")));
+    let builder = ObjectBuilder::new(isa, name, default_libcall_names())
+        .unwrap_or_else(|err| ice(format!("could not create object file: {err}")));
+
+    let mut cx = ObjectCtx {
+        program,
+        module: ObjectModule::new(builder),
+        mono,
+        functions: HashMap::new(),
+        libc: HashMap::new(),
+        debug_assertions: options.debug_assertions,
+    };
+    for instance in &mono.instances {
+        cx.declare_fn(instance.clone())?;
+    }
+    for instance in &mono.instances {
+        cx.define_fn(instance.clone())?;
+    }
+    cx.define_entry(main)?;
+
+    Ok(cx
+        .module
+        .finish()
+        .emit()
+        .unwrap_or_else(|err| ice(format!("could not emit object file: {err}"))))
+}
+
+fn set_flag(flags: &mut settings::Builder, name: &str, value: &str) {
+    flags
+        .set(name, value)
+        .unwrap_or_else(|err| ice(format!("invalid cranelift setting `{name}`: {err}")));
+}
+
+struct ObjectCtx<'a> {
+    program: Program<'a>,
+    module: ObjectModule,
+    mono: &'a MonoItems,
+    functions: HashMap<Instance, (FuncId, Rc<FnAbi>)>,
+    libc: HashMap<&'static str, FuncId>,
+    debug_assertions: bool,
+}
+
+impl<'a> ObjectCtx<'a> {
+    fn ptr_ty(&self) -> ClifType {
+        self.module.target_config().pointer_type()
+    }
+
+    fn clif_ty(&self, scalar: Scalar) -> ClifType {
+        match scalar {
+            Scalar::Int(int) => ClifType::int(int.bits as u16)
+                .unwrap_or_else(|| ice("integer type has an invalid width")),
+            Scalar::Bool => types::I8,
+            Scalar::Ptr => self.ptr_ty(),
+        }
+    }
+
+    fn abi(&self, ret: Layout, params: Vec<Layout>) -> FnAbi {
+        let mut signature = self.module.make_signature();
+        if ret.in_memory() {
+            signature.params.push(AbiParam::new(self.ptr_ty()));
+        }
+        for param in &params {
+            match param.repr {
+                Repr::Unit => {}
+                Repr::Scalar(scalar) => signature.params.push(AbiParam::new(self.clif_ty(scalar))),
+                Repr::Aggregate(_) | Repr::Enum(_) => {
+                    signature.params.push(AbiParam::new(self.ptr_ty()))
+                }
+            }
+        }
+        if let Repr::Scalar(scalar) = ret.repr {
+            signature.returns.push(AbiParam::new(self.clif_ty(scalar)));
+        }
+        FnAbi {
+            ret,
+            params,
+            signature,
+        }
+    }
+
+    fn fn_abi(&self, instance: &Instance) -> Result<FnAbi, Diagnostic> {
+        let body = self.program.body(instance.fn_id);
+        let layout = |local: flux_mir::mir::Local| {
+            let ty = self.program.normalize(&body.locals.get(local).ty, instance);
+            self.program.layout_in(&ty, instance.fn_id)
+        };
+        let ret = layout(Body::RETURN_PLACE)?;
+        let params = body.args().map(layout).collect::<Result<_, _>>()?;
+        Ok(self.abi(ret, params))
+    }
+
+    fn declare_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let abi = self.fn_abi(&instance)?;
+        let fn_id = instance.fn_id;
+        let mut symbol = format!(
+            "{}.{}.{}",
+            self.program
+                .interner
+                .resolve(&self.program.packages.get(fn_id.pkg_id).name),
+            self.program.fn_name(fn_id),
+            fn_id.inner.raw()
+        );
+        if !instance.args.is_empty() {
+            let args: Vec<_> = instance
+                .args
+                .iter()
+                .map(|arg| arg.to_string(self.program.interner))
+                .collect();
+            symbol = format!("{symbol}<{}>", args.join(", "));
+        }
+        let func_id = self
+            .module
+            .declare_function(&symbol, Linkage::Local, &abi.signature)
+            .unwrap_or_else(|err| ice(format!("could not declare function `{symbol}`: {err}")));
+        self.functions.insert(instance, (func_id, Rc::new(abi)));
+        Ok(())
+    }
+
+    fn libc_fn(&mut self, name: &'static str, params: &[ClifType], returns: &[ClifType]) -> FuncId {
+        if let Some(func_id) = self.libc.get(name) {
+            return *func_id;
+        }
+
+        let mut signature = self.module.make_signature();
+        signature
+            .params
+            .extend(params.iter().map(|ty| AbiParam::new(*ty)));
+        signature
+            .returns
+            .extend(returns.iter().map(|ty| AbiParam::new(*ty)));
+        let func_id = self
+            .module
+            .declare_function(name, Linkage::Import, &signature)
+            .unwrap_or_else(|err| ice(format!("could not declare `{name}`: {err}")));
+        self.libc.insert(name, func_id);
+        func_id
+    }
+
+    fn define_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let fn_id = instance.fn_id;
+        let (func_id, abi) = self.functions[&instance].clone();
+        let mut ctx = self.module.make_context();
+        ctx.func.signature = abi.signature.clone();
+        ctx.func.name = UserFuncName::user(0, func_id.as_u32());
+
+        let mut fn_builder_ctx = FunctionBuilderContext::new();
+        let builder = FunctionBuilder::new(&mut ctx.func, &mut fn_builder_ctx);
+        FnTranslator::new(self, builder, instance, &abi)?.translate()?;
+
+        self.module
+            .define_function(func_id, &mut ctx)
+            .unwrap_or_else(|err| {
+                ice(format!(
+                    "could not define function `{}`: {err:?}",
+                    self.program.fn_name(fn_id)
+                ))
+            });
+        Ok(())
+    }
+
+    /// Define the C `main` function, which calls the flux `main` and exits with the value it returns
+    fn define_entry(&mut self, main: InPkg<id::FnDecl>) -> Result<(), Diagnostic> {
+        let (main_id, abi) = self.functions[&Instance::new(main, vec![])].clone();
+
+        let mut signature = self.module.make_signature();
+        signature.returns.push(AbiParam::new(types::I32));
+        let func_id = self
+            .module
+            .declare_function("main", Linkage::Export, &signature)
+            .unwrap_or_else(|err| ice(format!("could not declare entry point: {err}")));
+
+        let mut ctx = self.module.make_context();
+        ctx.func.signature = signature;
+        ctx.func.name = UserFuncName::user(0, func_id.as_u32());
+        let mut fn_builder_ctx = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut ctx.func, &mut fn_builder_ctx);
+        let block = builder.create_block();
+        builder.switch_to_block(block);
+
+        let main_ref = self.module.declare_func_in_func(main_id, builder.func);
+        let mut args = vec![];
+        if abi.ret.in_memory() {
+            let slot = builder.create_sized_stack_slot(StackSlotData::new(
+                StackSlotKind::ExplicitSlot,
+                abi.ret.size,
+                abi.ret.align.trailing_zeros() as u8,
+            ));
+            args.push(builder.ins().stack_addr(self.ptr_ty(), slot, 0));
+        }
+        let call = builder.ins().call(main_ref, &args);
+        let status = match abi.ret.repr {
+            Repr::Scalar(scalar) => {
+                let val = builder.inst_results(call)[0];
+                match self.clif_ty(scalar).bits() {
+                    64 => builder.ins().ireduce(types::I32, val),
+                    32 => val,
+                    _ => builder.ins().uextend(types::I32, val),
+                }
+            }
+            _ => builder.ins().iconst(types::I32, 0),
+        };
+        builder.ins().return_(&[status]);
+        builder.seal_all_blocks();
+        builder.finalize();
+
+        self.module
+            .define_function(func_id, &mut ctx)
+            .unwrap_or_else(|err| ice(format!("could not define entry point: {err:?}")));
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Storage {
+    Var(Variable),
+    Stack(cranelift_codegen::ir::StackSlot),
+    /// A struct that lives behind a pointer passed in by the caller
+    Indirect(Value),
+    Unit,
+}
+
+struct LocalInfo {
+    storage: Storage,
+    ty: TypeKind,
+    layout: Layout,
+}
+
+/// A location that can be read from and written to
+#[derive(Clone, Copy)]
+enum PlaceRef {
+    Var(Variable),
+    Addr(Value),
+    Unit,
+}
+
+#[derive(Clone, Copy)]
+enum CValue {
+    Scalar(Value),
+    /// The address of a struct
+    Addr(Value),
+    Unit,
+}
+
+struct Typed {
+    val: CValue,
+    ty: TypeKind,
+    layout: Layout,
+}
+
+struct FnTranslator<'cx, 'a, 'f> {
+    cx: &'cx mut ObjectCtx<'a>,
+    builder: FunctionBuilder<'f>,
+    instance: Instance,
+    body: &'a Body,
+    locals: Vec<LocalInfo>,
+    blocks: Vec<Block>,
+}
+
+impl<'cx, 'a, 'f> FnTranslator<'cx, 'a, 'f> {
+    fn new(
+        cx: &'cx mut ObjectCtx<'a>,
+        mut builder: FunctionBuilder<'f>,
+        instance: Instance,
+        abi: &FnAbi,
+    ) -> Result<Self, Diagnostic> {
+        let body = cx.program.body(instance.fn_id);
+        let blocks: Vec<_> = body.blocks.keys().map(|_| builder.create_block()).collect();
+
+        let entry = blocks[Body::START_BLOCK.raw() as usize];
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        let mut params = builder.block_params(entry).to_vec().into_iter();
+
+        let mut locals = Vec::with_capacity(body.locals.len());
+        for (local, decl) in body.locals.iter() {
+            let is_param = local.raw() as usize <= body.arg_count;
+            let ty = cx.program.normalize(&decl.ty, &instance);
+            let layout = match local.raw() {
+                0 => abi.ret.clone(),
+                n if is_param => abi.params[n as usize - 1].clone(),
+                _ => cx.program.layout_in(&ty, instance.fn_id)?,
+            };
+            let storage = match layout.repr {
+                Repr::Unit => Storage::Unit,
+                Repr::Scalar(scalar) => {
+                    let var = Variable::from_u32(local.raw());
+                    let clif_ty = cx.clif_ty(scalar);
+                    builder.declare_var(var, clif_ty);
+                    let val = match local.raw() {
+                        1.. if is_param => params.next(),
+                        _ => None,
+                    }
+                    .unwrap_or_else(|| builder.ins().iconst(clif_ty, 0));
+                    builder.def_var(var, val);
+                    Storage::Var(var)
+                }
+                Repr::Aggregate(_) | Repr::Enum(_) if is_param => Storage::Indirect(
+                    params
+                        .next()
+                        .unwrap_or_else(|| ice("missing struct parameter")),
+                ),
+                Repr::Aggregate(_) | Repr::Enum(_) => {
+                    Storage::Stack(builder.create_sized_stack_slot(StackSlotData::new(
+                        StackSlotKind::ExplicitSlot,
+                        layout.size,
+                        layout.align.trailing_zeros() as u8,
+                    )))
+                }
+            };
+            locals.push(LocalInfo {
+                storage,
+                ty,
+                layout,
+            });
+        }
+
+        Ok(Self {
+            cx,
+            builder,
+            instance,
+            body,
+            locals,
+            blocks,
+        })
+    }
+
+    fn translate(mut self) -> Result<(), Diagnostic> {
+        for (block, data) in self.body.blocks.iter() {
+            let clif_block = self.blocks[block.raw() as usize];
+            if block != Body::START_BLOCK {
+                self.builder.switch_to_block(clif_block);
+            }
+            for statement in &data.statements {
+                match statement {
+                    Statement::Assign(place, rvalue) => self.assign(place, rvalue)?,
+                }
+            }
+            self.terminator(block, data.terminator())?;
+        }
+        self.builder.seal_all_blocks();
+        self.builder.finalize();
+        Ok(())
+    }
+
+    fn ptr_ty(&self) -> ClifType {
+        self.cx.ptr_ty()
+    }
+
+    fn layout_of(&self, ty: &TypeKind) -> Result<(TypeKind, Layout), Diagnostic> {
+        let ty = self.cx.program.normalize(ty, &self.instance);
+        let layout = self.cx.program.layout_in(&ty, self.instance.fn_id)?;
+        Ok((ty, layout))
+    }
+
+    fn place(&mut self, place: &Place) -> Result<(PlaceRef, TypeKind, Layout), Diagnostic> {
+        let local = &self.locals[place.local.raw() as usize];
+        let mut ty = local.ty.clone();
+        let mut layout = local.layout.clone();
+        let storage = local.storage;
+        let mut place_ref = match storage {
+            Storage::Var(var) => PlaceRef::Var(var),
+            Storage::Stack(slot) => {
+                let ptr_ty = self.ptr_ty();
+                PlaceRef::Addr(self.builder.ins().stack_addr(ptr_ty, slot, 0))
+            }
+            Storage::Indirect(addr) => PlaceRef::Addr(addr),
+            Storage::Unit => PlaceRef::Unit,
+        };
+
+        for projection in &place.projection {
+            match projection {
+                Projection::Field(name) => {
+                    let field = layout
+                        .field(name)
+                        .unwrap_or_else(|| ice("field projection on a type without that field"))
+                        .clone();
+                    let base = match place_ref {
+                        PlaceRef::Addr(base) => base,
+                        _ => ice("field projection on a value that is not in memory"),
+                    };
+                    place_ref =
+                        PlaceRef::Addr(self.builder.ins().iadd_imm(base, field.offset as i64));
+                    ty = field.ty;
+                    layout = field.layout;
+                }
+                Projection::Index(idx) => {
+                    let ptr = self.read_scalar(place_ref, &layout);
+                    let idx = &self.locals[idx.raw() as usize];
+                    let idx_scalar = idx.layout.scalar_kind();
+                    let idx = match idx.storage {
+                        Storage::Var(var) => self.builder.use_var(var),
+                        _ => ice("pointer index is not a scalar"),
+                    };
+                    let idx = self.cast(idx, idx_scalar, Some(Scalar::Ptr));
+                    let (elem_ty, elem_layout) = self.layout_of(pointee(&ty))?;
+                    let offset = self.builder.ins().imul_imm(idx, elem_layout.size as i64);
+                    place_ref = PlaceRef::Addr(self.builder.ins().iadd(ptr, offset));
+                    ty = elem_ty;
+                    layout = elem_layout;
+                }
+                Projection::Downcast(_, discriminant) => {
+                    let (payload_ty, payload_layout) = layout
+                        .payload(*discriminant)
+                        .cloned()
+                        .unwrap_or_else(|| ice("downcast to a variant without a payload"));
+                    let offset = layout
+                        .payload_offset()
+                        .unwrap_or_else(|| ice("downcast of a value that is not an enum"));
+                    let base = match place_ref {
+                        PlaceRef::Addr(base) => base,
+                        _ => ice("downcast of a value that is not in memory"),
+                    };
+                    place_ref = PlaceRef::Addr(self.builder.ins().iadd_imm(base, offset as i64));
+                    ty = payload_ty;
+                    layout = payload_layout;
+                }
+            }
+        }
+        Ok((place_ref, ty, layout))
+    }
+
+    fn read(&mut self, place_ref: PlaceRef, layout: &Layout) -> CValue {
+        match (place_ref, &layout.repr) {
+            (PlaceRef::Var(var), _) => CValue::Scalar(self.builder.use_var(var)),
+            (PlaceRef::Addr(addr), Repr::Scalar(scalar)) => {
+                let ty = self.cx.clif_ty(*scalar);
+                CValue::Scalar(self.builder.ins().load(ty, MemFlags::new(), addr, 0))
+            }
+            (PlaceRef::Addr(addr), Repr::Aggregate(_) | Repr::Enum(_)) => CValue::Addr(addr),
+            (PlaceRef::Addr(_), Repr::Unit) | (PlaceRef::Unit, _) => CValue::Unit,
+        }
+    }
+
+    fn read_scalar(&mut self, place_ref: PlaceRef, layout: &Layout) -> Value {
+        match self.read(place_ref, layout) {
+            CValue::Scalar(val) => val,
+            _ => ice("expected a scalar value"),
+        }
+    }
+
+    fn write(&mut self, place_ref: PlaceRef, layout: &Layout, val: CValue) {
+        match (place_ref, val) {
+            (PlaceRef::Var(var), CValue::Scalar(val)) => self.builder.def_var(var, val),
+            (PlaceRef::Addr(addr), CValue::Scalar(val)) => {
+                self.builder.ins().store(MemFlags::new(), val, addr, 0);
+            }
+            (PlaceRef::Addr(dest), CValue::Addr(src)) => {
+                let config = self.cx.module.target_config();
+                self.builder.emit_small_memory_copy(
+                    config,
+                    dest,
+                    src,
+                    layout.size as u64,
+                    layout.align as u8,
+                    layout.align as u8,
+                    false,
+                    MemFlags::new(),
+                );
+            }
+            (_, CValue::Unit) | (PlaceRef::Unit, _) => {}
+            (PlaceRef::Var(_), CValue::Addr(_)) => ice("cannot write a struct to a scalar"),
+        }
+    }
+
+    fn operand(&mut self, operand: &Operand) -> Result<Typed, Diagnostic> {
+        match operand {
+            Operand::Copy(place) => {
+                let (place_ref, ty, layout) = self.place(place)?;
+                let val = self.read(place_ref, &layout);
+                Ok(Typed { val, ty, layout })
+            }
+            Operand::Const(Const::Int(val, ty)) => {
+                let (ty, layout) = self.layout_of(ty)?;
+                let clif_ty = match layout.repr {
+                    Repr::Scalar(scalar) => self.cx.clif_ty(scalar),
+                    _ => ice("integer constant with a non scalar type"),
+                };
+                let mask = u64::MAX >> (64 - clif_ty.bits());
+                let val = self.builder.ins().iconst(clif_ty, (val & mask) as i64);
+                Ok(Typed {
+                    val: CValue::Scalar(val),
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Bool(val)) => {
+                let bool_ty = TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    vec![self.cx.program.interner.get_or_intern_static("bool")],
+                    vec![],
+                )));
+                let (ty, layout) = self.layout_of(&bool_ty)?;
+                let val = self.builder.ins().iconst(types::I8, *val as i64);
+                Ok(Typed {
+                    val: CValue::Scalar(val),
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Fn(fn_id)) => {
+                let (func_id, _) = self.cx.functions[&Instance::new(*fn_id, vec![])];
+                let func_ref = self.func_ref(func_id);
+                let ptr_ty = self.ptr_ty();
+                let val = self.builder.ins().func_addr(ptr_ty, func_ref);
+                let fn_decl = self.cx.program.fn_decl(*fn_id);
+                let ty = TypeKind::Concrete(ConcreteKind::Fn(FnSignature::new(
+                    fn_decl.params.iter().map(|param| param.ty.inner.clone()),
+                    fn_decl.return_ty.inner.clone(),
+                )));
+                let (ty, layout) = self.layout_of(&ty)?;
+                Ok(Typed {
+                    val: CValue::Scalar(val),
+                    ty,
+                    layout,
+                })
+            }
+        }
+    }
+
+    fn func_ref(&mut self, func_id: FuncId) -> FuncRef {
+        self.cx
+            .module
+            .declare_func_in_func(func_id, self.builder.func)
+    }
+
+    fn assign(&mut self, place: &Place, rvalue: &Rvalue) -> Result<(), Diagnostic> {
+        match rvalue {
+            Rvalue::Use(operand) => {
+                let val = self.operand(operand)?;
+                let (place_ref, _, layout) = self.place(place)?;
+                self.write(place_ref, &layout, val.val);
+            }
+            Rvalue::BinOp(op, lhs, rhs) => {
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                let val = self.bin_op(*op, &lhs, &rhs);
+                let (place_ref, _, layout) = self.place(place)?;
+                self.write(place_ref, &layout, CValue::Scalar(val));
+            }
+            Rvalue::Cast(operand, _) => {
+                let val = self.operand(operand)?;
+                let (place_ref, _, layout) = self.place(place)?;
+                let val = match val.val {
+                    CValue::Scalar(scalar) => CValue::Scalar(self.cast(
+                        scalar,
+                        val.layout.scalar_kind(),
+                        layout.scalar_kind(),
+                    )),
+                    val => val,
+                };
+                self.write(place_ref, &layout, val);
+            }
+            Rvalue::Struct(_, fields) => {
+                let (place_ref, _, layout) = self.place(place)?;
+                let base = match place_ref {
+                    PlaceRef::Addr(base) => base,
+                    _ => ice("struct expression assigned to a place that is not in memory"),
+                };
+                for (name, operand) in fields {
+                    let val = self.operand(operand)?;
+                    let field = layout
+                        .field(name)
+                        .unwrap_or_else(|| ice("struct expression with unknown field"))
+                        .clone();
+                    let addr = self.builder.ins().iadd_imm(base, field.offset as i64);
+                    let val = self.coerce(val, &field.layout);
+                    self.write(PlaceRef::Addr(addr), &field.layout, val);
+                }
+            }
+            Rvalue::Enum(_, discriminant, payload) => {
+                let (place_ref, _, layout) = self.place(place)?;
+                let base = match place_ref {
+                    PlaceRef::Addr(base) => base,
+                    _ => ice("enum variant assigned to a place that is not in memory"),
+                };
+                let tag = self.builder.ins().iconst(
+                    self.cx.clif_ty(Scalar::Int(IntKind::TAG)),
+                    *discriminant as i64,
+                );
+                self.builder.ins().store(MemFlags::new(), tag, base, 0);
+                if let Some(operand) = payload {
+                    let val = self.operand(operand)?;
+                    let (_, payload_layout) = layout
+                        .payload(*discriminant)
+                        .cloned()
+                        .unwrap_or_else(|| ice("payload given to a variant without one"));
+                    let offset = layout
+                        .payload_offset()
+                        .unwrap_or_else(|| ice("enum variant assigned to a non enum"));
+                    let addr = self.builder.ins().iadd_imm(base, offset as i64);
+                    let val = self.coerce(val, &payload_layout);
+                    self.write(PlaceRef::Addr(addr), &payload_layout, val);
+                }
+            }
+            Rvalue::Discriminant(enum_place) => {
+                let (enum_ref, _, _) = self.place(enum_place)?;
+                let addr = match enum_ref {
+                    PlaceRef::Addr(addr) => addr,
+                    _ => ice("discriminant of a value that is not in memory"),
+                };
+                let tag_ty = self.cx.clif_ty(Scalar::Int(IntKind::TAG));
+                let tag = self.builder.ins().load(tag_ty, MemFlags::new(), addr, 0);
+                let (place_ref, _, layout) = self.place(place)?;
+                let tag = self.cast(tag, Some(Scalar::Int(IntKind::TAG)), layout.scalar_kind());
+                self.write(place_ref, &layout, CValue::Scalar(tag));
+            }
+            Rvalue::Tuple(operands) if operands.is_empty() => {}
+            Rvalue::Tuple(operands) => {
+                let (place_ref, _, layout) = self.place(place)?;
+                let (base, fields) = match (place_ref, &layout.repr) {
+                    (PlaceRef::Addr(base), Repr::Aggregate(fields)) => (base, fields),
+                    _ => ice("tuple expression assigned to a place that is not a tuple in memory"),
+                };
+                for (field, operand) in fields.iter().zip(operands) {
+                    let val = self.operand(operand)?;
+                    let addr = self.builder.ins().iadd_imm(base, field.offset as i64);
+                    let val = self.coerce(val, &field.layout);
+                    self.write(PlaceRef::Addr(addr), &field.layout, val);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Convert a scalar to the width of the place it is being stored in
+    fn coerce(&mut self, val: Typed, layout: &Layout) -> CValue {
+        match val.val {
+            CValue::Scalar(scalar) => {
+                CValue::Scalar(self.cast(scalar, val.layout.scalar_kind(), layout.scalar_kind()))
+            }
+            val => val,
+        }
+    }
+
+    fn cast(&mut self, val: Value, from: Option<Scalar>, to: Option<Scalar>) -> Value {
+        let (Some(from), Some(to)) = (from, to) else {
+            return val;
+        };
+        let from_ty = self.cx.clif_ty(from);
+        let to_ty = self.cx.clif_ty(to);
+        if from_ty.bits() == to_ty.bits() {
+            val
+        } else if from_ty.bits() > to_ty.bits() {
+            self.builder.ins().ireduce(to_ty, val)
+        } else if matches!(from, Scalar::Int(IntKind { signed: true, .. })) {
+            self.builder.ins().sextend(to_ty, val)
+        } else {
+            self.builder.ins().uextend(to_ty, val)
+        }
+    }
+
+    fn bin_op(&mut self, op: Op, lhs: &Typed, rhs: &Typed) -> Value {
+        let (CValue::Scalar(l), CValue::Scalar(r)) = (lhs.val, rhs.val) else {
+            ice(format!(
+                "unsupported binary operation `{op}` on non scalar values"
+            ));
+        };
+        let scalar = lhs.layout.scalar_kind();
+        let r = self.cast(r, rhs.layout.scalar_kind(), scalar);
+        let ins = self.builder.ins();
+        match scalar {
+            Some(Scalar::Int(int)) => {
+                let cc = |signed, unsigned| if int.signed { signed } else { unsigned };
+                match op {
+                    Op::Add | Op::Sub | Op::Mul if self.cx.debug_assertions => {
+                        let (val, overflowed) = match (op, int.signed) {
+                            (Op::Add, false) => ins.uadd_overflow(l, r),
+                            (Op::Add, true) => ins.sadd_overflow(l, r),
+                            (Op::Sub, false) => ins.usub_overflow(l, r),
+                            (Op::Sub, true) => ins.ssub_overflow(l, r),
+                            (_, false) => ins.umul_overflow(l, r),
+                            (_, true) => ins.smul_overflow(l, r),
+                        };
+                        self.builder
+                            .ins()
+                            .trapnz(overflowed, TrapCode::INTEGER_OVERFLOW);
+                        val
+                    }
+                    Op::Add => ins.iadd(l, r),
+                    Op::Sub => ins.isub(l, r),
+                    Op::Mul => ins.imul(l, r),
+                    Op::Div if int.signed => ins.sdiv(l, r),
+                    Op::Div => ins.udiv(l, r),
+                    Op::CmpEq => ins.icmp(IntCC::Equal, l, r),
+                    Op::CmpNeq => ins.icmp(IntCC::NotEqual, l, r),
+                    Op::CmpGt => ins.icmp(
+                        cc(IntCC::SignedGreaterThan, IntCC::UnsignedGreaterThan),
+                        l,
+                        r,
+                    ),
+                    Op::CmpGte => ins.icmp(
+                        cc(
+                            IntCC::SignedGreaterThanOrEqual,
+                            IntCC::UnsignedGreaterThanOrEqual,
+                        ),
+                        l,
+                        r,
+                    ),
+                    Op::CmpLt => ins.icmp(cc(IntCC::SignedLessThan, IntCC::UnsignedLessThan), l, r),
+                    Op::CmpLte => ins.icmp(
+                        cc(IntCC::SignedLessThanOrEqual, IntCC::UnsignedLessThanOrEqual),
+                        l,
+                        r,
+                    ),
+                    Op::Eq | Op::CmpAnd | Op::CmpOr => {
+                        ice(format!("unsupported binary operation `{op}` on integers"))
+                    }
+                }
+            }
+            Some(Scalar::Bool) => match op {
+                Op::CmpAnd => ins.band(l, r),
+                Op::CmpOr => ins.bor(l, r),
+                Op::CmpEq => ins.icmp(IntCC::Equal, l, r),
+                Op::CmpNeq => ins.icmp(IntCC::NotEqual, l, r),
+                _ => ice(format!("unsupported binary operation `{op}` on booleans")),
+            },
+            Some(Scalar::Ptr) => match op {
+                Op::CmpEq => ins.icmp(IntCC::Equal, l, r),
+                Op::CmpNeq => ins.icmp(IntCC::NotEqual, l, r),
+                _ => ice(format!("unsupported binary operation `{op}` on pointers")),
+            },
+            None => ice(format!("unsupported binary operation `{op}`")),
+        }
+    }
+
+    fn terminator(&mut self, block: BasicBlock, terminator: &Terminator) -> Result<(), Diagnostic> {
+        match terminator {
+            Terminator::Goto(target) => {
+                let target = self.blocks[target.raw() as usize];
+                self.builder.ins().jump(target, &[]);
+            }
+            Terminator::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                let discr = match self.operand(discr)?.val {
+                    CValue::Scalar(val) => val,
+                    _ => ice("switched on a value that is not a scalar"),
+                };
+                let mut switch = Switch::new();
+                for (val, target) in targets {
+                    switch.set_entry(*val as u128, self.blocks[target.raw() as usize]);
+                }
+                switch.emit(
+                    &mut self.builder,
+                    discr,
+                    self.blocks[otherwise.raw() as usize],
+                );
+            }
+            Terminator::Return => match self.locals[Body::RETURN_PLACE.raw() as usize].storage {
+                Storage::Var(var) => {
+                    let val = self.builder.use_var(var);
+                    self.builder.ins().return_(&[val]);
+                }
+                _ => {
+                    self.builder.ins().return_(&[]);
+                }
+            },
+            Terminator::Call {
+                callee,
+                args,
+                dest,
+                target,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.operand(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match callee {
+                    Callee::Fn(Operand::Const(Const::Fn(_))) | Callee::Method(_) => {
+                        let callee = self.cx.mono.callee(&self.instance, block);
+                        self.direct_call(callee, args, dest)?
+                    }
+                    Callee::Fn(operand) => self.indirect_call(operand, args, dest)?,
+                    Callee::Intrinsic(name) => self.intrinsic(name, args, dest)?,
+                }
+                match target {
+                    Some(target) => {
+                        let target = self.blocks[target.raw() as usize];
+                        self.builder.ins().jump(target, &[]);
+                    }
+                    None => {
+                        self.builder.ins().trap(UNREACHABLE);
+                    }
+                }
+            }
+            Terminator::Unreachable => {
+                self.builder.ins().trap(UNREACHABLE);
+            }
+        }
+        Ok(())
+    }
+
+    fn call_args(&mut self, abi: &FnAbi, args: Vec<Typed>, dest: PlaceRef) -> Vec<Value> {
+        let mut call_args = vec![];
+        if abi.ret.in_memory() {
+            match dest {
+                PlaceRef::Addr(addr) => call_args.push(addr),
+                _ => ice("struct returned into a place that is not in memory"),
+            }
+        }
+        for (arg, param) in args.into_iter().zip(&abi.params) {
+            match self.coerce(arg, param) {
+                CValue::Scalar(val) => call_args.push(val),
+                // The callee is free to modify its parameters, so it gets a copy of its own
+                CValue::Addr(src) => {
+                    let slot = self.builder.create_sized_stack_slot(StackSlotData::new(
+                        StackSlotKind::ExplicitSlot,
+                        param.size,
+                        param.align.trailing_zeros() as u8,
+                    ));
+                    let ptr_ty = self.ptr_ty();
+                    let copy = self.builder.ins().stack_addr(ptr_ty, slot, 0);
+                    self.write(PlaceRef::Addr(copy), param, CValue::Addr(src));
+                    call_args.push(copy);
+                }
+                CValue::Unit => {}
+            }
+        }
+        call_args
+    }
+
+    fn write_call_result(
+        &mut self,
+        call: cranelift_codegen::ir::Inst,
+        dest: PlaceRef,
+        layout: &Layout,
+    ) {
+        if let Some(val) = self.builder.inst_results(call).first().copied() {
+            self.write(dest, layout, CValue::Scalar(val));
+        }
+    }
+
+    fn direct_call(
+        &mut self,
+        callee: &Instance,
+        args: Vec<Typed>,
+        dest: &Place,
+    ) -> Result<(), Diagnostic> {
+        let (dest, _, layout) = self.place(dest)?;
+        let (func_id, abi) = self.cx.functions[callee].clone();
+        let func_ref = self.func_ref(func_id);
+        let call_args = self.call_args(&abi, args, dest);
+        let call = self.builder.ins().call(func_ref, &call_args);
+        self.write_call_result(call, dest, &layout);
+        Ok(())
+    }
+
+    fn indirect_call(
+        &mut self,
+        callee: &Operand,
+        args: Vec<Typed>,
+        dest: &Place,
+    ) -> Result<(), Diagnostic> {
+        let callee = self.operand(callee)?;
+        let signature = match &callee.ty {
+            TypeKind::Concrete(ConcreteKind::Fn(signature)) => signature,
+            _ => ice("called a value that is not a function"),
+        };
+        let ret = self.layout_of(&signature.return_ty().kind)?.1;
+        let params = signature
+            .parameters()
+            .iter()
+            .map(|param| Ok(self.layout_of(&param.kind)?.1))
+            .collect::<Result<_, Diagnostic>>()?;
+        let abi = self.cx.abi(ret, params);
+
+        let callee = match callee.val {
+            CValue::Scalar(val) => val,
+            _ => ice("function pointer is not a scalar"),
+        };
+        let sig_ref = self.builder.import_signature(abi.signature.clone());
+        let (dest, _, layout) = self.place(dest)?;
+        let call_args = self.call_args(&abi, args, dest);
+        let call = self
+            .builder
+            .ins()
+            .call_indirect(sig_ref, callee, &call_args);
+        self.write_call_result(call, dest, &layout);
+        Ok(())
+    }
+
+    fn call_libc(
+        &mut self,
+        name: &'static str,
+        args: &[Value],
+        params: &[ClifType],
+        returns: &[ClifType],
+    ) -> Option<Value> {
+        let func_id = self.cx.libc_fn(name, params, returns);
+        let func_ref = self.func_ref(func_id);
+        let call = self.builder.ins().call(func_ref, args);
+        self.builder.inst_results(call).first().copied()
+    }
+
+    /// Intrinsics map either to a native instruction or to a call into libc
+    fn intrinsic(&mut self, name: &Word, args: Vec<Typed>, dest: &Place) -> Result<(), Diagnostic> {
+        let name = self.cx.program.interner.resolve(name);
+        let name = name
+            .strip_prefix("@flux.intrinsics.")
+            .unwrap_or_else(|| ice(format!("unknown intrinsic `{name}`")));
+        let ptr_ty = self.ptr_ty();
+        let scalar = |arg: &Typed| match arg.val {
+            CValue::Scalar(val) => val,
+            _ => ice("intrinsic argument is not a scalar"),
+        };
+        let (dest, _, layout) = self.place(dest)?;
+
+        match name {
+            "panic" => {
+                self.call_libc("abort", &[], &[], &[]);
+            }
+            "malloc" => {
+                let size = self.cast(
+                    scalar(&args[0]),
+                    args[0].layout.scalar_kind(),
+                    Some(Scalar::Ptr),
+                );
+                // A size that wraps around once the header is added would hand out a tiny allocation
+                let header = self.builder.ins().iconst(ptr_ty, ALLOC_HEADER_BYTES);
+                let (total, overflowed) = self.builder.ins().uadd_overflow(size, header);
+                self.builder.ins().trapnz(overflowed, OUT_OF_MEMORY);
+                let block = self
+                    .call_libc("malloc", &[total], &[ptr_ty], &[ptr_ty])
+                    .unwrap_or_else(|| ice("`malloc` did not return a value"));
+                self.builder.ins().trapz(block, OUT_OF_MEMORY);
+                self.builder
+                    .ins()
+                    .store(MemFlags::trusted(), size, block, 0);
+                let ptr = self.builder.ins().iadd_imm(block, ALLOC_HEADER_BYTES);
+                self.write(dest, &layout, CValue::Scalar(ptr));
+            }
+            // Freeing a null pointer does nothing, so it must not be moved back onto a header
+            "free" => {
+                let ptr = scalar(&args[0]);
+                let header = self.builder.ins().iadd_imm(ptr, -ALLOC_HEADER_BYTES);
+                let block = self.builder.ins().select(ptr, header, ptr);
+                self.call_libc("free", &[block], &[ptr_ty], &[]);
+            }
+            "memcpy" => {
+                let (dst, src) = (scalar(&args[0]), scalar(&args[1]));
+                let size = self.builder.ins().load(
+                    ptr_ty,
+                    MemFlags::trusted(),
+                    src,
+                    -ALLOC_HEADER_BYTES as i32,
+                );
+                let config = self.cx.module.target_config();
+                self.builder.call_memcpy(config, dst, src, size);
+            }
+            "ptr_read" => {
+                let ptr = scalar(&args[0]);
+                let idx = self.cast(
+                    scalar(&args[1]),
+                    args[1].layout.scalar_kind(),
+                    Some(Scalar::Ptr),
+                );
+                let (_, elem_layout) = self.layout_of(pointee(&args[0].ty))?;
+                let offset = self.builder.ins().imul_imm(idx, elem_layout.size as i64);
+                let addr = self.builder.ins().iadd(ptr, offset);
+                let val = self.read(PlaceRef::Addr(addr), &elem_layout);
+                self.write(dest, &layout, val);
+            }
+            _ => {
+                let op = match name.rsplit_once('_') {
+                    Some(("add", _)) => Op::Add,
+                    Some(("mul", _)) => Op::Mul,
+                    Some(("cmp_eq", _)) => Op::CmpEq,
+                    _ => ice(format!("unknown intrinsic `{name}`")),
+                };
+                let val = self.bin_op(op, &args[0], &args[1]);
+                self.write(dest, &layout, CValue::Scalar(val));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn pointee(ty: &TypeKind) -> &TypeKind {
+    match ty {
+        TypeKind::Concrete(ConcreteKind::Ptr(to)) => &to.kind,
+        _ => ice("indexed a value that is not a pointer"),
+    }
+}
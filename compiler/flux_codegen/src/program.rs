@@ -0,0 +1,421 @@
+use std::collections::HashMap;
+
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_hir::{
+    def::item::{ApplyDecl, EnumDecl, FnDecl, StructDecl},
+    Package,
+};
+use flux_id::{
+    id::{self, InPkg, WithPackage},
+    Map,
+};
+use flux_mir::{Body, PackageMir};
+use flux_typesystem::{ConcreteKind, FnSignature, Type, TypeKind};
+use flux_util::{InFile, Interner, Path, Span, WithSpan, Word};
+
+use crate::{
+    diagnostics::CodegenError,
+    layout::{Layout, LayoutCx},
+};
+
+/// A function together with the types its generic parameters are instantiated with
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Instance {
+    pub(crate) fn_id: InPkg<id::FnDecl>,
+    /// Ordered like [`Program::generic_params`]
+    pub(crate) args: Vec<TypeKind>,
+}
+
+impl Instance {
+    pub(crate) fn new(fn_id: InPkg<id::FnDecl>, args: Vec<TypeKind>) -> Self {
+        Self { fn_id, args }
+    }
+}
+
+/// Everything a backend needs to know about the program being compiled
+pub(crate) struct Program<'a> {
+    pub(crate) packages: &'a Map<id::Pkg, Package>,
+    pub(crate) mir: &'a HashMap<id::Pkg, PackageMir>,
+    pub(crate) interner: &'static Interner,
+    pub(crate) layout_cx: LayoutCx,
+}
+
+impl<'a> Program<'a> {
+    pub(crate) fn new(
+        packages: &'a Map<id::Pkg, Package>,
+        mir: &'a HashMap<id::Pkg, PackageMir>,
+        interner: &'static Interner,
+        layout_cx: LayoutCx,
+    ) -> Self {
+        Self {
+            packages,
+            mir,
+            interner,
+            layout_cx,
+        }
+    }
+
+    pub(crate) fn fn_decl(&self, fn_id: InPkg<id::FnDecl>) -> &'a FnDecl {
+        self.packages
+            .get(fn_id.pkg_id)
+            .item_tree()
+            .functions
+            .get(fn_id.inner)
+    }
+
+    pub(crate) fn fn_name(&self, fn_id: InPkg<id::FnDecl>) -> &'static str {
+        self.interner.resolve(&self.fn_decl(fn_id).name)
+    }
+
+    pub(crate) fn body(&self, fn_id: InPkg<id::FnDecl>) -> &'a Body {
+        self.mir
+            .get(&fn_id.pkg_id)
+            .and_then(|mir| mir.get_body(fn_id.inner))
+            .unwrap_or_else(|| ice("function has no mir body"))
+    }
+
+    /// The name of an instance as it would be written in flux, such as `read<u32>`
+    pub(crate) fn instance_name(&self, instance: &Instance) -> String {
+        let name = self.fn_name(instance.fn_id);
+        if instance.args.is_empty() {
+            return name.to_string();
+        }
+        let args: Vec<_> = instance
+            .args
+            .iter()
+            .map(|arg| arg.to_string(self.interner))
+            .collect();
+        format!("{name}<{}>", args.join(", "))
+    }
+
+    /// The span of a function's name, which is where errors about the function as a whole are reported
+    pub(crate) fn fn_span(&self, fn_id: InPkg<id::FnDecl>) -> InFile<Span> {
+        let file_id = self
+            .packages
+            .get(fn_id.pkg_id)
+            .bodies()
+            .get_fn_body(fn_id.inner)
+            .unwrap_or_else(|| ice("function has no lowered body"))
+            .file_id;
+        self.fn_decl(fn_id).name.span.in_file(file_id)
+    }
+
+    /// The application a function is a method of, if any
+    pub(crate) fn apply_of(&self, fn_id: InPkg<id::FnDecl>) -> Option<&'a ApplyDecl> {
+        self.packages
+            .get(fn_id.pkg_id)
+            .item_tree()
+            .applies
+            .values()
+            .find(|apply_decl| apply_decl.methods.contains(&fn_id.inner))
+    }
+
+    /// The names of a function's generic parameters, starting with those of the application it belongs to
+    ///
+    /// Methods already carry the generic parameters of their application, so those are only listed once.
+    pub(crate) fn generic_params(&self, fn_id: InPkg<id::FnDecl>) -> Vec<Word> {
+        let mut generic_params: Vec<Word> = vec![];
+        for name in self
+            .apply_of(fn_id)
+            .into_iter()
+            .flat_map(|apply_decl| apply_decl.generic_params.types.values())
+            .chain(self.fn_decl(fn_id).generic_params.types.values())
+        {
+            if !generic_params.contains(&name.inner) {
+                generic_params.push(name.inner);
+            }
+        }
+        generic_params
+    }
+
+    /// Work out the instance of a function being called from the types of the values passed to and returned from
+    /// it
+    ///
+    /// `ret` is `None` when the function is only referred to, rather than called.
+    pub(crate) fn instantiate(
+        &self,
+        fn_id: InPkg<id::FnDecl>,
+        args: &[TypeKind],
+        ret: Option<&TypeKind>,
+        caller: InPkg<id::FnDecl>,
+    ) -> Result<Instance, Diagnostic> {
+        let generic_params = self.generic_params(fn_id);
+        if generic_params.is_empty() {
+            return Ok(Instance::new(fn_id, vec![]));
+        }
+
+        let body = self.body(fn_id);
+        let apply_decl = self.apply_of(fn_id);
+        let no_generics = HashMap::new();
+        let mut bindings = HashMap::new();
+        let params = body.args().zip(args);
+        for (local, concrete) in ret
+            .map(|ret| (Body::RETURN_PLACE, ret))
+            .into_iter()
+            .chain(params)
+        {
+            let pattern = self.substitute(&body.locals.get(local).ty, apply_decl, &no_generics);
+            bind(&pattern, concrete, &mut bindings);
+        }
+
+        let args = generic_params
+            .iter()
+            .map(|name| {
+                bindings.remove(name).ok_or_else(|| {
+                    CodegenError::UninferredGenericArguments {
+                        function: (),
+                        function_file_span: self.fn_span(caller),
+                        name: self.fn_name(caller).to_string(),
+                        callee: self.fn_name(fn_id).to_string(),
+                        param: self.interner.resolve(name).to_string(),
+                    }
+                    .to_diagnostic()
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Instance::new(fn_id, args))
+    }
+
+    /// Replace `This`, its associated types and generic parameters with the types an instance was created with
+    pub(crate) fn normalize(&self, ty: &TypeKind, instance: &Instance) -> TypeKind {
+        let generics = self
+            .generic_params(instance.fn_id)
+            .into_iter()
+            .zip(instance.args.iter().cloned())
+            .collect();
+        self.substitute(ty, self.apply_of(instance.fn_id), &generics)
+    }
+
+    pub(crate) fn substitute(
+        &self,
+        ty: &TypeKind,
+        apply_decl: Option<&ApplyDecl>,
+        generics: &HashMap<Word, TypeKind>,
+    ) -> TypeKind {
+        let substitute =
+            |ty: &Type| Type::new(self.substitute(&ty.kind, apply_decl, generics), vec![]);
+        match ty {
+            TypeKind::ThisPath(this_path) => {
+                let apply_decl =
+                    apply_decl.unwrap_or_else(|| ice("`This` used outside of an application"));
+                let ty = match this_path.path.segments.as_slice() {
+                    [] => &apply_decl.to_ty.kind,
+                    [name] => {
+                        &apply_decl
+                            .assoc_types
+                            .iter()
+                            .find(|assoc_type| assoc_type.name.inner == *name)
+                            .unwrap_or_else(|| ice("unknown associated type reached codegen"))
+                            .ty
+                            .kind
+                    }
+                    _ => ice("nested associated types reached codegen"),
+                };
+                self.substitute(ty, Some(apply_decl), generics)
+            }
+            TypeKind::Generic(name, _) => generics.get(name).cloned().unwrap_or_else(|| ty.clone()),
+            TypeKind::Concrete(ConcreteKind::Ptr(to)) => {
+                TypeKind::Concrete(ConcreteKind::Ptr(Box::new(substitute(to))))
+            }
+            TypeKind::Concrete(ConcreteKind::Path(path)) => {
+                TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    path.segments.clone(),
+                    path.args.iter().map(substitute).collect(),
+                )))
+            }
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) => {
+                TypeKind::Concrete(ConcreteKind::Tuple(types.iter().map(substitute).collect()))
+            }
+            TypeKind::Concrete(ConcreteKind::Fn(signature)) => {
+                TypeKind::Concrete(ConcreteKind::Fn(FnSignature::new(
+                    signature.parameters().iter().map(substitute),
+                    substitute(signature.return_ty()),
+                )))
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    /// A name for a type that is unique within the program
+    ///
+    /// Structs and enums always take the same number of generic arguments, so their names can simply be followed by
+    /// those of their arguments.
+    pub(crate) fn mangle(&self, ty: &TypeKind) -> String {
+        let mangle_all = |types: &mut dyn Iterator<Item = &TypeKind>| {
+            types
+                .map(|ty| format!("_{}", self.mangle(ty)))
+                .collect::<String>()
+        };
+        match ty {
+            TypeKind::Int => "u64".to_string(),
+            TypeKind::Concrete(ConcreteKind::Path(path)) => {
+                let name = path
+                    .last()
+                    .map(|name| self.interner.resolve(name))
+                    .unwrap_or_default();
+                name.to_string() + &mangle_all(&mut path.args.iter().map(|arg| &arg.kind))
+            }
+            TypeKind::Concrete(ConcreteKind::Ptr(to)) => format!("ptr_{}", self.mangle(&to.kind)),
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) if types.is_empty() => {
+                "unit".to_string()
+            }
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) => {
+                format!(
+                    "tuple{}{}",
+                    types.len(),
+                    mangle_all(&mut types.iter().map(|ty| &ty.kind))
+                )
+            }
+            TypeKind::Concrete(ConcreteKind::Fn(signature)) => format!(
+                "fn{}{}",
+                signature.parameters().len(),
+                mangle_all(
+                    &mut signature
+                        .parameters()
+                        .iter()
+                        .chain(std::iter::once(signature.return_ty()))
+                        .map(|ty| &ty.kind)
+                )
+            ),
+            _ => "never".to_string(),
+        }
+    }
+
+    /// The layout of a type used in a function, reporting an error against the function if it has none
+    pub(crate) fn layout_in(
+        &self,
+        ty: &TypeKind,
+        fn_id: InPkg<id::FnDecl>,
+    ) -> Result<Layout, Diagnostic> {
+        self.layout_cx.layout_of(ty, self).ok_or_else(|| {
+            CodegenError::UnsupportedType {
+                function: (),
+                function_file_span: self.fn_span(fn_id),
+                name: self.fn_name(fn_id).to_string(),
+                ty: ty.to_string(self.interner),
+            }
+            .to_diagnostic()
+        })
+    }
+
+    pub(crate) fn get_enum(&self, name: &Word) -> Option<&'a EnumDecl> {
+        self.packages.values().find_map(|package| {
+            package
+                .item_tree()
+                .enums
+                .values()
+                .find(|enum_decl| enum_decl.name.inner == *name)
+        })
+    }
+
+    pub(crate) fn get_struct(&self, name: &Word) -> Option<&'a StructDecl> {
+        self.packages.values().find_map(|package| {
+            package
+                .item_tree()
+                .structs
+                .values()
+                .find(|struct_decl| struct_decl.name.inner == *name)
+        })
+    }
+
+    /// Find the method called `name` for a receiver of type `receiver`
+    ///
+    /// Applications to the exact type of the receiver are preferred over generic applications, just like in the
+    /// interpreter.
+    pub(crate) fn resolve_method(
+        &self,
+        receiver: &TypeKind,
+        name: &Word,
+        caller: InPkg<id::FnDecl>,
+    ) -> Result<InPkg<id::FnDecl>, Diagnostic> {
+        self.packages
+            .iter()
+            .flat_map(|(pkg_id, package)| {
+                let item_tree = package.item_tree();
+                item_tree.applies.values().filter_map(move |apply_decl| {
+                    let specificity =
+                        self.application_specificity(&apply_decl.to_ty.kind, receiver)?;
+                    apply_decl
+                        .methods
+                        .iter()
+                        .find(|method| item_tree.functions.get(**method).name.inner == *name)
+                        .map(|method| (specificity, (*method).in_pkg(pkg_id)))
+                })
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, method)| method)
+            .ok_or_else(|| {
+                CodegenError::UnresolvedMethod {
+                    function: (),
+                    function_file_span: self.fn_span(caller),
+                    name: self.fn_name(caller).to_string(),
+                    method: self.interner.resolve(name).to_string(),
+                    ty: receiver.to_string(self.interner),
+                }
+                .to_diagnostic()
+            })
+    }
+
+    fn application_specificity(&self, to_ty: &TypeKind, receiver: &TypeKind) -> Option<u8> {
+        match (to_ty, receiver) {
+            (TypeKind::Generic(_, _), _) => Some(0),
+            (
+                TypeKind::Concrete(ConcreteKind::Ptr(_)),
+                TypeKind::Concrete(ConcreteKind::Ptr(_)),
+            ) => Some(1),
+            (
+                TypeKind::Concrete(ConcreteKind::Tuple(to_types)),
+                TypeKind::Concrete(ConcreteKind::Tuple(types)),
+            ) if to_types.len() == types.len() => Some(1),
+            (
+                TypeKind::Concrete(ConcreteKind::Path(to_path)),
+                TypeKind::Concrete(ConcreteKind::Path(path)),
+            ) => (to_path.last()? == path.last()?).then_some(1),
+            _ => None,
+        }
+    }
+}
+
+/// Match a type containing generic parameters against a concrete type, recording what each parameter stands for
+fn bind(pattern: &TypeKind, concrete: &TypeKind, bindings: &mut HashMap<Word, TypeKind>) {
+    match (pattern, concrete) {
+        (_, TypeKind::Generic(..) | TypeKind::Never | TypeKind::Unknown) => {}
+        (TypeKind::Generic(name, _), _) => {
+            bindings.entry(*name).or_insert_with(|| concrete.clone());
+        }
+        (
+            TypeKind::Concrete(ConcreteKind::Ptr(pattern)),
+            TypeKind::Concrete(ConcreteKind::Ptr(concrete)),
+        ) => bind(&pattern.kind, &concrete.kind, bindings),
+        (
+            TypeKind::Concrete(ConcreteKind::Path(pattern)),
+            TypeKind::Concrete(ConcreteKind::Path(concrete)),
+        ) => {
+            for (pattern, concrete) in pattern.args.iter().zip(&concrete.args) {
+                bind(&pattern.kind, &concrete.kind, bindings);
+            }
+        }
+        (
+            TypeKind::Concrete(ConcreteKind::Tuple(patterns)),
+            TypeKind::Concrete(ConcreteKind::Tuple(types)),
+        ) => {
+            for (pattern, concrete) in patterns.iter().zip(types) {
+                bind(&pattern.kind, &concrete.kind, bindings);
+            }
+        }
+        (
+            TypeKind::Concrete(ConcreteKind::Fn(pattern)),
+            TypeKind::Concrete(ConcreteKind::Fn(signature)),
+        ) => {
+            for (pattern, concrete) in pattern.parameters().iter().zip(signature.parameters()) {
+                bind(&pattern.kind, &concrete.kind, bindings);
+            }
+            bind(
+                &pattern.return_ty().kind,
+                &signature.return_ty().kind,
+                bindings,
+            );
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,1131 @@
+use std::{collections::HashMap, rc::Rc};
+
+use flux_diagnostics::{ice, Diagnostic};
+use flux_hir::def::expr::Op;
+use flux_id::id::{self, InPkg};
+use flux_mir::{
+    mir::{BasicBlock, Callee, Const, Operand, Place, Projection, Rvalue, Statement, Terminator},
+    Body,
+};
+use flux_typesystem::{ConcreteKind, FnSignature, TypeKind};
+use flux_util::{Path, Word};
+
+use crate::{
+    layout::{IntKind, Layout, Repr, Scalar},
+    mono::MonoItems,
+    program::{Instance, Program},
+};
+
+const PAGE_SIZE: u32 = 65536;
+
+/// Stands in for the instructions that pop a function's stack frame, whose size is only known once the whole
+/// function has been generated
+const EPILOGUE: &str = ";; epilogue";
+
+/// The stack grows down from the end of its pages, and the heap grows up from there
+const STACK_PAGES: u32 = 16;
+
+/// The allocator behind `@flux.intrinsics.malloc` and `@flux.intrinsics.free`
+///
+/// Every allocation is preceded by an 8 byte header holding its capacity and the size that was requested, which is
+/// what `@flux.intrinsics.memcpy` copies. Freed allocations are pushed onto a free list, whose second header word
+/// links to the next free allocation, and are reused by the first `malloc` they are big enough for. When none are,
+/// memory is bumped off the end of the heap, which grows the linear memory as needed. Sizes that cannot fit in the
+/// 32 bit address space along with their header, or that would bump the heap past its end, trap.
+const RUNTIME: &str = "  (func $__flux_malloc (param $requested i64) (result i32)
+    (local $size i32) (local $prev i32) (local $block i32) (local $end i32)
+    local.get $requested
+    i64.const 4294967280
+    i64.gt_u
+    if
+      unreachable
+    end
+    local.get $requested
+    i32.wrap_i64
+    i32.const 7
+    i32.add
+    i32.const -8
+    i32.and
+    local.set $size
+    global.get $__free_list
+    local.set $block
+    block $exhausted
+      loop $search
+        local.get $block
+        i32.eqz
+        br_if $exhausted
+        local.get $block
+        i32.load
+        local.get $size
+        i32.ge_u
+        if
+          local.get $prev
+          if
+            local.get $prev
+            local.get $block
+            i32.load offset=4
+            i32.store offset=4
+          else
+            local.get $block
+            i32.load offset=4
+            global.set $__free_list
+          end
+          local.get $block
+          local.get $size
+          i32.store offset=4
+          local.get $block
+          i32.const 8
+          i32.add
+          return
+        end
+        local.get $block
+        local.set $prev
+        local.get $block
+        i32.load offset=4
+        local.set $block
+        br $search
+      end
+    end
+    global.get $__heap_end
+    local.tee $block
+    local.get $size
+    i32.const 8
+    i32.add
+    i32.add
+    local.tee $end
+    local.get $block
+    i32.lt_u
+    if
+      unreachable
+    end
+    block $grown
+      loop $grow
+        local.get $end
+        memory.size
+        i32.const 16
+        i32.shl
+        i32.le_u
+        br_if $grown
+        i32.const 1
+        memory.grow
+        i32.const -1
+        i32.eq
+        if
+          unreachable
+        end
+        br $grow
+      end
+    end
+    local.get $end
+    global.set $__heap_end
+    local.get $block
+    local.get $size
+    i32.store
+    local.get $block
+    local.get $size
+    i32.store offset=4
+    local.get $block
+    i32.const 8
+    i32.add
+  )
+
+  (func $__flux_free (param $ptr i32)
+    local.get $ptr
+    i32.eqz
+    if
+      return
+    end
+    local.get $ptr
+    i32.const 8
+    i32.sub
+    local.tee $ptr
+    global.get $__free_list
+    i32.store offset=4
+    local.get $ptr
+    global.set $__free_list
+  )
+
+  (func $__flux_memcpy (param $dst i32) (param $src i32)
+    local.get $dst
+    local.get $src
+    local.get $src
+    i32.const 4
+    i32.sub
+    i32.load
+    memory.copy
+  )
+";
+
+/// Generate a WebAssembly module in the text format, exporting each of `exports` under its name
+///
+/// Values that live in memory, such as structs, are passed to and returned from functions by pointers into the
+/// module's linear memory, which is exported as `memory`.
+pub(crate) fn emit_wat(
+    exports: &[InPkg<id::FnDecl>],
+    program: Program,
+    mono: &MonoItems,
+) -> Result<String, Diagnostic> {
+    let mut cx = WasmCtx {
+        program,
+        mono,
+        functions: HashMap::new(),
+        types: vec![],
+        table: vec![],
+        definitions: String::new(),
+    };
+    for instance in &mono.instances {
+        cx.declare_fn(instance.clone())?;
+    }
+    for instance in &mono.instances {
+        cx.define_fn(instance.clone())?;
+    }
+    let mut export_decls = String::new();
+    for fn_id in exports {
+        let (name, _) = &cx.functions[&Instance::new(*fn_id, vec![])];
+        export_decls.push_str(&format!(
+            "  (export \"{}\" (func {name}))\n",
+            cx.program.fn_name(*fn_id)
+        ));
+    }
+
+    let mut module = String::from("(module\n");
+    for (idx, ty) in cx.types.iter().enumerate() {
+        module.push_str(&format!("  (type $sig{idx} (func{ty}))\n"));
+    }
+    let stack_end = STACK_PAGES * PAGE_SIZE;
+    module.push_str(&format!(
+        "  (memory (export \"memory\") {})\n  (global $__stack_pointer (mut i32) (i32.const {stack_end}))\n  (global $__heap_end (mut i32) (i32.const {stack_end}))\n  (global $__free_list (mut i32) (i32.const 0))\n",
+        STACK_PAGES + 1
+    ));
+    if !cx.table.is_empty() {
+        module.push_str(&format!(
+            "  (table {} funcref)\n  (elem (i32.const 0) func {})\n",
+            cx.table.len(),
+            cx.table.join(" ")
+        ));
+    }
+    module.push_str(&export_decls);
+    module.push('\n');
+    module.push_str(RUNTIME);
+    module.push_str(&cx.definitions);
+    module.push_str(")\n");
+    Ok(module)
+}
+
+/// How a function's parameters and return value are passed
+///
+/// Scalars are passed as wasm values. Values that live in memory are passed as a pointer to a copy owned by the
+/// caller, and returned by writing to a pointer the caller passes as the first parameter. Values with no runtime
+/// representation are not passed at all.
+#[derive(Debug)]
+struct FnAbi {
+    ret: Layout,
+    params: Vec<Layout>,
+    /// The parameter and result declarations of the function's type
+    signature: String,
+}
+
+impl FnAbi {
+    fn new(ret: Layout, params: Vec<Layout>) -> Self {
+        let mut signature = String::new();
+        if ret.in_memory() {
+            signature.push_str(" (param i32)");
+        }
+        for param in &params {
+            match param.repr {
+                Repr::Unit => {}
+                Repr::Scalar(scalar) => {
+                    signature.push_str(&format!(" (param {})", val_type(scalar)))
+                }
+                Repr::Aggregate(_) | Repr::Enum(_) => signature.push_str(" (param i32)"),
+            }
+        }
+        if let Repr::Scalar(scalar) = ret.repr {
+            signature.push_str(&format!(" (result {})", val_type(scalar)));
+        }
+        Self {
+            ret,
+            params,
+            signature,
+        }
+    }
+}
+
+struct WasmCtx<'a> {
+    program: Program<'a>,
+    mono: &'a MonoItems,
+    functions: HashMap<Instance, (String, Rc<FnAbi>)>,
+    /// The signatures of functions that are called indirectly
+    types: Vec<String>,
+    /// Functions whose address is taken, where a function pointer is an index into this table
+    table: Vec<String>,
+    definitions: String,
+}
+
+impl<'a> WasmCtx<'a> {
+    fn fn_abi(&self, instance: &Instance) -> Result<FnAbi, Diagnostic> {
+        let body = self.program.body(instance.fn_id);
+        let layout = |local: flux_mir::mir::Local| {
+            let ty = self.program.normalize(&body.locals.get(local).ty, instance);
+            self.program.layout_in(&ty, instance.fn_id)
+        };
+        let ret = layout(Body::RETURN_PLACE)?;
+        let params = body.args().map(layout).collect::<Result<_, _>>()?;
+        Ok(FnAbi::new(ret, params))
+    }
+
+    fn declare_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let abi = Rc::new(self.fn_abi(&instance)?);
+        let fn_id = instance.fn_id;
+        let mut name = format!(
+            "${}.{}.{}",
+            self.program
+                .interner
+                .resolve(&self.program.packages.get(fn_id.pkg_id).name),
+            self.program.fn_name(fn_id),
+            fn_id.inner.raw()
+        );
+        for arg in &instance.args {
+            name = format!("{name}.{}", self.program.mangle(arg));
+        }
+        self.functions.insert(instance, (name, abi));
+        Ok(())
+    }
+
+    /// The index of the type of functions with a signature, for `call_indirect`
+    fn type_idx(&mut self, signature: &str) -> usize {
+        match self.types.iter().position(|ty| ty == signature) {
+            Some(idx) => idx,
+            None => {
+                self.types.push(signature.to_string());
+                self.types.len() - 1
+            }
+        }
+    }
+
+    fn table_idx(&mut self, name: &str) -> usize {
+        match self.table.iter().position(|entry| entry == name) {
+            Some(idx) => idx,
+            None => {
+                self.table.push(name.to_string());
+                self.table.len() - 1
+            }
+        }
+    }
+
+    fn define_fn(&mut self, instance: Instance) -> Result<(), Diagnostic> {
+        let (name, abi) = self.functions[&instance].clone();
+        let definition = FnEmitter::new(self, instance, &abi)?.emit(&name, &abi)?;
+        self.definitions.push('\n');
+        self.definitions.push_str(&definition);
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+enum Storage {
+    /// A wasm local or parameter
+    Local(String),
+    /// A value that lives in memory at an offset into the function's stack frame
+    Frame(u32),
+    /// A value that lives in memory behind a pointer passed in by the caller
+    Indirect(String),
+    Unit,
+}
+
+struct LocalInfo {
+    storage: Storage,
+    ty: TypeKind,
+    layout: Layout,
+}
+
+/// A location that can be read from and written to
+enum PlaceRef {
+    Local(String),
+    /// Instructions that push the address of the place
+    Addr(Vec<String>),
+    Unit,
+}
+
+struct Typed {
+    /// Instructions that push the value, or its address if it lives in memory
+    code: Vec<String>,
+    ty: TypeKind,
+    layout: Layout,
+}
+
+/// Translates the control flow graph of a function into a single loop that dispatches on the block to run next
+///
+/// Every block is the end of a `block` instruction, so that blocks can be jumped to by branching out of the
+/// `block`s they are nested in. Jumps backwards go through the dispatch loop instead.
+struct FnEmitter<'cx, 'a> {
+    cx: &'cx mut WasmCtx<'a>,
+    instance: Instance,
+    body: &'a Body,
+    locals: Vec<LocalInfo>,
+    wasm_locals: Vec<String>,
+    frame_size: u32,
+    code: Vec<String>,
+}
+
+impl<'cx, 'a> FnEmitter<'cx, 'a> {
+    fn new(cx: &'cx mut WasmCtx<'a>, instance: Instance, abi: &FnAbi) -> Result<Self, Diagnostic> {
+        let body = cx.program.body(instance.fn_id);
+        let mut emitter = Self {
+            cx,
+            instance,
+            body,
+            locals: vec![],
+            wasm_locals: vec![],
+            frame_size: 0,
+            code: vec![],
+        };
+
+        for (local, decl) in body.locals.iter() {
+            let is_param = (1..=body.arg_count).contains(&(local.raw() as usize));
+            let ty = emitter.cx.program.normalize(&decl.ty, &emitter.instance);
+            let layout = match local.raw() {
+                0 => abi.ret.clone(),
+                n if is_param => abi.params[n as usize - 1].clone(),
+                _ => emitter.cx.program.layout_in(&ty, emitter.instance.fn_id)?,
+            };
+            let name = format!("$_{}", local.raw());
+            let storage = match layout.repr {
+                Repr::Unit => Storage::Unit,
+                Repr::Scalar(scalar) => {
+                    if !is_param {
+                        emitter
+                            .wasm_locals
+                            .push(format!("(local {name} {})", val_type(scalar)));
+                    }
+                    Storage::Local(name)
+                }
+                _ if local == Body::RETURN_PLACE => Storage::Indirect("$__ret".to_string()),
+                _ if is_param => Storage::Indirect(name),
+                _ => Storage::Frame(emitter.alloc_slot(&layout)),
+            };
+            emitter.locals.push(LocalInfo {
+                storage,
+                ty,
+                layout,
+            });
+        }
+        Ok(emitter)
+    }
+
+    fn emit(mut self, name: &str, abi: &FnAbi) -> Result<String, Diagnostic> {
+        let block_count = self.body.blocks.len();
+        self.push("loop $dispatch");
+        for block in (0..block_count).rev() {
+            self.push(format!("block $bb{block}"));
+        }
+        self.push("local.get $__bb");
+        let labels: Vec<_> = (0..block_count)
+            .map(|block| format!("$bb{block}"))
+            .collect();
+        self.push(format!("br_table {}", labels.join(" ")));
+        for (block, data) in self.body.blocks.iter() {
+            self.push("end");
+            for statement in &data.statements {
+                match statement {
+                    Statement::Assign(place, rvalue) => self.assign(place, rvalue)?,
+                }
+            }
+            self.terminator(block, data.terminator())?;
+        }
+        self.push("end");
+        self.push("unreachable");
+
+        let mut params = String::new();
+        if abi.ret.in_memory() {
+            params.push_str(" (param $__ret i32)");
+        }
+        for (local, param) in self.body.args().zip(&abi.params) {
+            if let Repr::Scalar(scalar) = param.repr {
+                params.push_str(&format!(" (param $_{} {})", local.raw(), val_type(scalar)));
+            } else if param.in_memory() {
+                params.push_str(&format!(" (param $_{} i32)", local.raw()));
+            }
+        }
+        if let Repr::Scalar(scalar) = abi.ret.repr {
+            params.push_str(&format!(" (result {})", val_type(scalar)));
+        }
+
+        let mut out = format!("  (func {name}{params}\n");
+        self.wasm_locals.extend([
+            "(local $__bb i32)".to_string(),
+            "(local $__frame i32)".to_string(),
+            "(local $__i32 i32)".to_string(),
+            "(local $__i64 i64)".to_string(),
+        ]);
+        out.push_str(&format!("    {}\n", self.wasm_locals.join(" ")));
+        if self.frame_size != 0 {
+            for instr in [
+                "global.get $__stack_pointer".to_string(),
+                format!("i32.const {}", self.frame_size),
+                "i32.sub".to_string(),
+                "local.tee $__frame".to_string(),
+                "global.set $__stack_pointer".to_string(),
+            ] {
+                out.push_str(&format!("    {instr}\n"));
+            }
+        }
+        let mut depth = 0;
+        for instr in &self.code {
+            if instr == EPILOGUE {
+                if self.frame_size != 0 {
+                    for instr in [
+                        "local.get $__frame".to_string(),
+                        format!("i32.const {}", self.frame_size),
+                        "i32.add".to_string(),
+                        "global.set $__stack_pointer".to_string(),
+                    ] {
+                        out.push_str(&"  ".repeat(depth + 2));
+                        out.push_str(&instr);
+                        out.push('\n');
+                    }
+                }
+                continue;
+            }
+            if instr == "end" || instr.starts_with("else") {
+                depth -= 1;
+            }
+            out.push_str(&"  ".repeat(depth + 2));
+            out.push_str(instr);
+            out.push('\n');
+            if instr.starts_with("loop")
+                || instr.starts_with("block")
+                || instr.starts_with("if")
+                || instr.starts_with("else")
+            {
+                depth += 1;
+            }
+        }
+        out.push_str("  )\n");
+        Ok(out)
+    }
+
+    fn push(&mut self, instr: impl Into<String>) {
+        self.code.push(instr.into());
+    }
+
+    /// Reserve space in the stack frame for a value that lives in memory
+    fn alloc_slot(&mut self, layout: &Layout) -> u32 {
+        let offset = self.frame_size.next_multiple_of(layout.align.max(1));
+        // The stack pointer stays aligned to the largest alignment of any type
+        self.frame_size = (offset + layout.size).next_multiple_of(8);
+        offset
+    }
+
+    fn slot_addr(offset: u32) -> Vec<String> {
+        vec![
+            "local.get $__frame".to_string(),
+            format!("i32.const {offset}"),
+            "i32.add".to_string(),
+        ]
+    }
+
+    fn layout_of(&self, ty: &TypeKind) -> Result<(TypeKind, Layout), Diagnostic> {
+        let ty = self.cx.program.normalize(ty, &self.instance);
+        let layout = self.cx.program.layout_in(&ty, self.instance.fn_id)?;
+        Ok((ty, layout))
+    }
+
+    fn place(&mut self, place: &Place) -> Result<(PlaceRef, TypeKind, Layout), Diagnostic> {
+        let local = &self.locals[place.local.raw() as usize];
+        let mut ty = local.ty.clone();
+        let mut layout = local.layout.clone();
+        let mut place_ref = match &local.storage {
+            Storage::Local(name) => PlaceRef::Local(name.clone()),
+            Storage::Frame(offset) => PlaceRef::Addr(Self::slot_addr(*offset)),
+            Storage::Indirect(name) => PlaceRef::Addr(vec![format!("local.get {name}")]),
+            Storage::Unit => PlaceRef::Unit,
+        };
+
+        for projection in &place.projection {
+            match projection {
+                Projection::Field(name) => {
+                    let field = layout
+                        .field(name)
+                        .unwrap_or_else(|| ice("field projection on a type without that field"))
+                        .clone();
+                    let mut addr = match place_ref {
+                        PlaceRef::Addr(addr) => addr,
+                        _ => ice("field projection on a value that is not in memory"),
+                    };
+                    addr.extend([format!("i32.const {}", field.offset), "i32.add".to_string()]);
+                    place_ref = PlaceRef::Addr(addr);
+                    ty = field.ty;
+                    layout = field.layout;
+                }
+                Projection::Index(idx) => {
+                    let ptr = self.read(&place_ref, &layout);
+                    let idx = &self.locals[idx.raw() as usize];
+                    let idx = match &idx.storage {
+                        Storage::Local(name) => Typed {
+                            code: vec![format!("local.get {name}")],
+                            ty: idx.ty.clone(),
+                            layout: idx.layout.clone(),
+                        },
+                        _ => ice("pointer index is not a scalar"),
+                    };
+                    let (elem_ty, elem_layout) = self.layout_of(pointee(&ty))?;
+                    place_ref = PlaceRef::Addr(self.elem_addr(ptr, idx, &elem_layout));
+                    ty = elem_ty;
+                    layout = elem_layout;
+                }
+                Projection::Downcast(_, discriminant) => {
+                    let (payload_ty, payload_layout) = layout
+                        .payload(*discriminant)
+                        .cloned()
+                        .unwrap_or_else(|| ice("downcast to a variant without a payload"));
+                    let offset = layout
+                        .payload_offset()
+                        .unwrap_or_else(|| ice("downcast of a value that is not an enum"));
+                    let mut addr = match place_ref {
+                        PlaceRef::Addr(addr) => addr,
+                        _ => ice("downcast of a value that is not in memory"),
+                    };
+                    addr.extend([format!("i32.const {offset}"), "i32.add".to_string()]);
+                    place_ref = PlaceRef::Addr(addr);
+                    ty = payload_ty;
+                    layout = payload_layout;
+                }
+            }
+        }
+        Ok((place_ref, ty, layout))
+    }
+
+    fn elem_addr(&self, ptr: Vec<String>, idx: Typed, elem_layout: &Layout) -> Vec<String> {
+        let mut addr = ptr;
+        addr.extend(idx.code);
+        addr.extend(cast(idx.layout.scalar_kind(), Some(Scalar::Ptr)));
+        addr.extend([
+            format!("i32.const {}", elem_layout.size),
+            "i32.mul".to_string(),
+            "i32.add".to_string(),
+        ]);
+        addr
+    }
+
+    fn read(&self, place_ref: &PlaceRef, layout: &Layout) -> Vec<String> {
+        match (place_ref, &layout.repr) {
+            (PlaceRef::Local(name), _) => vec![format!("local.get {name}")],
+            (PlaceRef::Addr(addr), Repr::Scalar(scalar)) => {
+                let mut code = addr.clone();
+                code.push(load(*scalar).to_string());
+                code
+            }
+            (PlaceRef::Addr(addr), Repr::Aggregate(_) | Repr::Enum(_)) => addr.clone(),
+            (PlaceRef::Addr(_), Repr::Unit) | (PlaceRef::Unit, _) => vec![],
+        }
+    }
+
+    fn write(&mut self, place_ref: &PlaceRef, layout: &Layout, val: Vec<String>) {
+        match (place_ref, &layout.repr) {
+            (PlaceRef::Unit, _) | (_, Repr::Unit) => {}
+            (PlaceRef::Local(name), _) => {
+                self.code.extend(val);
+                self.push(format!("local.set {name}"));
+            }
+            (PlaceRef::Addr(addr), Repr::Scalar(scalar)) => {
+                self.code.extend(addr.iter().cloned());
+                self.code.extend(val);
+                self.push(store(*scalar));
+            }
+            (PlaceRef::Addr(addr), Repr::Aggregate(_) | Repr::Enum(_)) => {
+                self.code.extend(addr.iter().cloned());
+                self.code.extend(val);
+                self.push(format!("i32.const {}", layout.size));
+                self.push("memory.copy");
+            }
+        }
+    }
+
+    fn operand(&mut self, operand: &Operand) -> Result<Typed, Diagnostic> {
+        match operand {
+            Operand::Copy(place) => {
+                let (place_ref, ty, layout) = self.place(place)?;
+                let code = self.read(&place_ref, &layout);
+                Ok(Typed { code, ty, layout })
+            }
+            Operand::Const(Const::Int(val, ty)) => {
+                let (ty, layout) = self.layout_of(ty)?;
+                let int = match layout.repr {
+                    Repr::Scalar(Scalar::Int(int)) => int,
+                    _ => ice("integer constant with a non integer type"),
+                };
+                let code = if int.bits == 64 {
+                    format!("i64.const {}", *val as i64)
+                } else {
+                    let shift = 64 - int.bits as u32;
+                    let val = if int.signed {
+                        ((*val << shift) as i64 >> shift) as i32
+                    } else {
+                        ((*val << shift) >> shift) as u32 as i32
+                    };
+                    format!("i32.const {val}")
+                };
+                Ok(Typed {
+                    code: vec![code],
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Bool(val)) => {
+                let bool_ty = TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    vec![self.cx.program.interner.get_or_intern_static("bool")],
+                    vec![],
+                )));
+                let (ty, layout) = self.layout_of(&bool_ty)?;
+                Ok(Typed {
+                    code: vec![format!("i32.const {}", *val as i32)],
+                    ty,
+                    layout,
+                })
+            }
+            Operand::Const(Const::Fn(fn_id)) => {
+                let (name, _) = self.cx.functions[&Instance::new(*fn_id, vec![])].clone();
+                let idx = self.cx.table_idx(&name);
+                let fn_decl = self.cx.program.fn_decl(*fn_id);
+                let ty = TypeKind::Concrete(ConcreteKind::Fn(FnSignature::new(
+                    fn_decl.params.iter().map(|param| param.ty.inner.clone()),
+                    fn_decl.return_ty.inner.clone(),
+                )));
+                let (ty, layout) = self.layout_of(&ty)?;
+                Ok(Typed {
+                    code: vec![format!("i32.const {idx}")],
+                    ty,
+                    layout,
+                })
+            }
+        }
+    }
+
+    fn assign(&mut self, place: &Place, rvalue: &Rvalue) -> Result<(), Diagnostic> {
+        let (place_ref, _, layout) = self.place(place)?;
+        let val = match rvalue {
+            Rvalue::Use(operand) => self.operand(operand)?.code,
+            Rvalue::BinOp(op, lhs, rhs) => {
+                let lhs = self.operand(lhs)?;
+                let rhs = self.operand(rhs)?;
+                bin_op(*op, lhs, rhs)
+            }
+            Rvalue::Cast(operand, _) => {
+                let val = self.operand(operand)?;
+                let mut code = val.code;
+                code.extend(cast(val.layout.scalar_kind(), layout.scalar_kind()));
+                code
+            }
+            Rvalue::Struct(_, fields) => {
+                let base = match &place_ref {
+                    PlaceRef::Addr(base) => base.clone(),
+                    _ => ice("struct expression assigned to a place that is not in memory"),
+                };
+                for (name, operand) in fields {
+                    let val = self.operand(operand)?;
+                    let field = layout
+                        .field(name)
+                        .unwrap_or_else(|| ice("struct expression with unknown field"))
+                        .clone();
+                    let mut addr = base.clone();
+                    addr.extend([format!("i32.const {}", field.offset), "i32.add".to_string()]);
+                    let mut code = val.code;
+                    code.extend(cast(val.layout.scalar_kind(), field.layout.scalar_kind()));
+                    self.write(&PlaceRef::Addr(addr), &field.layout, code);
+                }
+                return Ok(());
+            }
+            Rvalue::Enum(_, discriminant, payload) => {
+                let base = match &place_ref {
+                    PlaceRef::Addr(base) => base.clone(),
+                    _ => ice("enum variant assigned to a place that is not in memory"),
+                };
+                self.code.extend(base.iter().cloned());
+                self.push(format!("i32.const {discriminant}"));
+                self.push(store(Scalar::Int(IntKind::TAG)));
+                if let Some(operand) = payload {
+                    let val = self.operand(operand)?;
+                    let (_, payload_layout) = layout
+                        .payload(*discriminant)
+                        .cloned()
+                        .unwrap_or_else(|| ice("payload given to a variant without one"));
+                    let offset = layout
+                        .payload_offset()
+                        .unwrap_or_else(|| ice("enum variant assigned to a non enum"));
+                    let mut addr = base;
+                    addr.extend([format!("i32.const {offset}"), "i32.add".to_string()]);
+                    let mut code = val.code;
+                    code.extend(cast(val.layout.scalar_kind(), payload_layout.scalar_kind()));
+                    self.write(&PlaceRef::Addr(addr), &payload_layout, code);
+                }
+                return Ok(());
+            }
+            Rvalue::Discriminant(enum_place) => {
+                let (enum_ref, _, _) = self.place(enum_place)?;
+                let mut code = match enum_ref {
+                    PlaceRef::Addr(addr) => addr,
+                    _ => ice("discriminant of a value that is not in memory"),
+                };
+                code.push(load(Scalar::Int(IntKind::TAG)).to_string());
+                code.extend(cast(Some(Scalar::Int(IntKind::TAG)), layout.scalar_kind()));
+                code
+            }
+            Rvalue::Tuple(operands) if operands.is_empty() => return Ok(()),
+            Rvalue::Tuple(operands) => {
+                let (base, fields) = match (&place_ref, &layout.repr) {
+                    (PlaceRef::Addr(base), Repr::Aggregate(fields)) => (base.clone(), fields),
+                    _ => ice("tuple expression assigned to a place that is not a tuple in memory"),
+                };
+                for (field, operand) in fields.iter().zip(operands) {
+                    let val = self.operand(operand)?;
+                    let mut addr = base.clone();
+                    addr.extend([format!("i32.const {}", field.offset), "i32.add".to_string()]);
+                    let mut code = val.code;
+                    code.extend(cast(val.layout.scalar_kind(), field.layout.scalar_kind()));
+                    self.write(&PlaceRef::Addr(addr), &field.layout, code);
+                }
+                return Ok(());
+            }
+        };
+        self.write(&place_ref, &layout, val);
+        Ok(())
+    }
+
+    /// Continue at another block, which can be reached directly if it comes after the current one
+    fn jump(&mut self, from: usize, to: usize) {
+        if to > from {
+            self.push(format!("br $bb{to}"));
+        } else {
+            self.push(format!("i32.const {to}"));
+            self.push("local.set $__bb");
+            self.push("br $dispatch");
+        }
+    }
+
+    fn terminator(&mut self, block: BasicBlock, terminator: &Terminator) -> Result<(), Diagnostic> {
+        let from = block.raw() as usize;
+        match terminator {
+            Terminator::Goto(target) => self.jump(from, target.raw() as usize),
+            Terminator::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => {
+                let discr = self.operand(discr)?;
+                let ty = match discr.layout.scalar_kind() {
+                    Some(scalar) => val_type(scalar),
+                    None => ice("switched on a value that is not a scalar"),
+                };
+                for (val, target) in targets {
+                    self.code.extend(discr.code.iter().cloned());
+                    self.push(format!("{ty}.const {val}"));
+                    self.push(format!("{ty}.eq"));
+                    self.push("if");
+                    self.jump(from, target.raw() as usize);
+                    self.push("end");
+                }
+                self.jump(from, otherwise.raw() as usize);
+            }
+            Terminator::Return => {
+                self.push(EPILOGUE);
+                if let Storage::Local(name) =
+                    &self.locals[Body::RETURN_PLACE.raw() as usize].storage
+                {
+                    self.push(format!("local.get {name}"));
+                }
+                self.push("return");
+            }
+            Terminator::Call {
+                callee,
+                args,
+                dest,
+                target,
+            } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.operand(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match callee {
+                    Callee::Fn(Operand::Const(Const::Fn(_))) | Callee::Method(_) => {
+                        let callee = self.cx.mono.callee(&self.instance, block);
+                        self.direct_call(callee, args, dest)?
+                    }
+                    Callee::Fn(operand) => self.indirect_call(operand, args, dest)?,
+                    Callee::Intrinsic(name) => self.intrinsic(name, args, dest)?,
+                }
+                match target {
+                    Some(target) => self.jump(from, target.raw() as usize),
+                    None => self.push("unreachable"),
+                }
+            }
+            Terminator::Unreachable => self.push("unreachable"),
+        }
+        Ok(())
+    }
+
+    /// Push the arguments of a call
+    fn call_args(&mut self, abi: &FnAbi, args: Vec<Typed>, dest: &PlaceRef) {
+        if abi.ret.in_memory() {
+            match dest {
+                PlaceRef::Addr(addr) => self.code.extend(addr.iter().cloned()),
+                _ => ice("struct returned into a place that is not in memory"),
+            }
+        }
+        for (arg, param) in args.into_iter().zip(&abi.params) {
+            match param.repr {
+                Repr::Unit => {}
+                Repr::Scalar(scalar) => {
+                    self.code.extend(arg.code);
+                    self.code
+                        .extend(cast(arg.layout.scalar_kind(), Some(scalar)));
+                }
+                // The callee is free to modify its parameters, so it gets a copy of its own
+                Repr::Aggregate(_) | Repr::Enum(_) => {
+                    let offset = self.alloc_slot(param);
+                    let copy = PlaceRef::Addr(Self::slot_addr(offset));
+                    self.write(&copy, param, arg.code);
+                    self.code.extend(Self::slot_addr(offset));
+                }
+            }
+        }
+    }
+
+    /// Store the result of a call, which has to be moved out of the way while the address of `dest` is pushed
+    fn write_call_result(&mut self, abi: &FnAbi, dest: &PlaceRef, layout: &Layout) {
+        if let Repr::Scalar(scalar) = abi.ret.repr {
+            let tmp = format!("$__{}", val_type(scalar));
+            self.push(format!("local.set {tmp}"));
+            let mut val = vec![format!("local.get {tmp}")];
+            val.extend(cast(Some(scalar), layout.scalar_kind()));
+            self.write(dest, layout, val);
+        }
+    }
+
+    fn direct_call(
+        &mut self,
+        callee: &Instance,
+        args: Vec<Typed>,
+        dest: &Place,
+    ) -> Result<(), Diagnostic> {
+        let (dest, _, layout) = self.place(dest)?;
+        let (name, abi) = self.cx.functions[callee].clone();
+        self.call_args(&abi, args, &dest);
+        self.push(format!("call {name}"));
+        self.write_call_result(&abi, &dest, &layout);
+        Ok(())
+    }
+
+    fn indirect_call(
+        &mut self,
+        callee: &Operand,
+        args: Vec<Typed>,
+        dest: &Place,
+    ) -> Result<(), Diagnostic> {
+        let callee = self.operand(callee)?;
+        let signature = match &callee.ty {
+            TypeKind::Concrete(ConcreteKind::Fn(signature)) => signature,
+            _ => ice("called a value that is not a function"),
+        };
+        let ret = self.layout_of(&signature.return_ty().kind)?.1;
+        let params = signature
+            .parameters()
+            .iter()
+            .map(|param| Ok(self.layout_of(&param.kind)?.1))
+            .collect::<Result<_, Diagnostic>>()?;
+        let abi = FnAbi::new(ret, params);
+        let type_idx = self.cx.type_idx(&abi.signature);
+
+        let (dest, _, layout) = self.place(dest)?;
+        self.call_args(&abi, args, &dest);
+        self.code.extend(callee.code);
+        self.push(format!("call_indirect (type $sig{type_idx})"));
+        self.write_call_result(&abi, &dest, &layout);
+        Ok(())
+    }
+
+    /// Intrinsics map either to wasm instructions or to a call into the runtime
+    fn intrinsic(&mut self, name: &Word, args: Vec<Typed>, dest: &Place) -> Result<(), Diagnostic> {
+        let name = self.cx.program.interner.resolve(name);
+        let name = name
+            .strip_prefix("@flux.intrinsics.")
+            .unwrap_or_else(|| ice(format!("unknown intrinsic `{name}`")));
+        let (dest, _, layout) = self.place(dest)?;
+        let mut args = args.into_iter();
+        let mut arg = || {
+            args.next()
+                .unwrap_or_else(|| ice("missing intrinsic argument"))
+        };
+
+        match name {
+            "panic" => self.push("unreachable"),
+            "malloc" => {
+                let size = arg();
+                let mut val = size.code;
+                val.extend(cast(
+                    size.layout.scalar_kind(),
+                    Some(Scalar::Int(IntKind {
+                        bits: 64,
+                        signed: false,
+                    })),
+                ));
+                val.push("call $__flux_malloc".to_string());
+                self.write(&dest, &layout, val);
+            }
+            "free" => {
+                self.code.extend(arg().code);
+                self.push("call $__flux_free");
+            }
+            "memcpy" => {
+                self.code.extend(arg().code);
+                self.code.extend(arg().code);
+                self.push("call $__flux_memcpy");
+            }
+            "ptr_read" => {
+                let ptr = arg();
+                let (_, elem_layout) = self.layout_of(pointee(&ptr.ty))?;
+                let addr = self.elem_addr(ptr.code, arg(), &elem_layout);
+                let val = self.read(&PlaceRef::Addr(addr), &elem_layout);
+                self.write(&dest, &layout, val);
+            }
+            _ => {
+                let op = match name.rsplit_once('_') {
+                    Some(("add", _)) => Op::Add,
+                    Some(("mul", _)) => Op::Mul,
+                    Some(("cmp_eq", _)) => Op::CmpEq,
+                    _ => ice(format!("unknown intrinsic `{name}`")),
+                };
+                let (lhs, rhs) = (arg(), arg());
+                let val = bin_op(op, lhs, rhs);
+                self.write(&dest, &layout, val);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn val_type(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Int(IntKind { bits: 64, .. }) => "i64",
+        _ => "i32",
+    }
+}
+
+fn load(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Int(IntKind { bits: 64, .. }) => "i64.load",
+        Scalar::Int(IntKind {
+            bits: 16,
+            signed: true,
+        }) => "i32.load16_s",
+        Scalar::Int(IntKind { bits: 16, .. }) => "i32.load16_u",
+        Scalar::Int(IntKind {
+            bits: 8,
+            signed: true,
+        }) => "i32.load8_s",
+        Scalar::Int(IntKind { bits: 8, .. }) | Scalar::Bool => "i32.load8_u",
+        Scalar::Int(_) | Scalar::Ptr => "i32.load",
+    }
+}
+
+fn store(scalar: Scalar) -> &'static str {
+    match scalar {
+        Scalar::Int(IntKind { bits: 64, .. }) => "i64.store",
+        Scalar::Int(IntKind { bits: 16, .. }) => "i32.store16",
+        Scalar::Int(IntKind { bits: 8, .. }) | Scalar::Bool => "i32.store8",
+        Scalar::Int(_) | Scalar::Ptr => "i32.store",
+    }
+}
+
+/// Integers narrower than 32 bits are kept zero or sign extended to fill their `i32`
+fn wrap(int: IntKind) -> Vec<String> {
+    match (int.bits, int.signed) {
+        (8, false) => vec!["i32.const 255".to_string(), "i32.and".to_string()],
+        (16, false) => vec!["i32.const 65535".to_string(), "i32.and".to_string()],
+        (8, true) => vec!["i32.extend8_s".to_string()],
+        (16, true) => vec!["i32.extend16_s".to_string()],
+        _ => vec![],
+    }
+}
+
+/// Convert the value on top of the stack from one scalar to another
+fn cast(from: Option<Scalar>, to: Option<Scalar>) -> Vec<String> {
+    let (Some(from), Some(to)) = (from, to) else {
+        return vec![];
+    };
+    if from == to {
+        return vec![];
+    }
+    let mut code = match (val_type(from), val_type(to)) {
+        ("i64", "i32") => vec!["i32.wrap_i64".to_string()],
+        ("i32", "i64") if matches!(from, Scalar::Int(IntKind { signed: true, .. })) => {
+            vec!["i64.extend_i32_s".to_string()]
+        }
+        ("i32", "i64") => vec!["i64.extend_i32_u".to_string()],
+        _ => vec![],
+    };
+    if let Scalar::Int(int) = to {
+        code.extend(wrap(int));
+    }
+    code
+}
+
+/// Integer arithmetic wraps around, like in release builds of native code
+fn bin_op(op: Op, lhs: Typed, rhs: Typed) -> Vec<String> {
+    let scalar = lhs.layout.scalar_kind();
+    let mut code = lhs.code;
+    code.extend(rhs.code);
+    code.extend(cast(rhs.layout.scalar_kind(), scalar));
+    match scalar {
+        Some(Scalar::Int(int)) => {
+            let ty = val_type(Scalar::Int(int));
+            let sign = if int.signed { "s" } else { "u" };
+            let instr = match op {
+                Op::Add => "add".to_string(),
+                Op::Sub => "sub".to_string(),
+                Op::Mul => "mul".to_string(),
+                Op::Div => format!("div_{sign}"),
+                Op::CmpEq => "eq".to_string(),
+                Op::CmpNeq => "ne".to_string(),
+                Op::CmpGt => format!("gt_{sign}"),
+                Op::CmpGte => format!("ge_{sign}"),
+                Op::CmpLt => format!("lt_{sign}"),
+                Op::CmpLte => format!("le_{sign}"),
+                Op::Eq | Op::CmpAnd | Op::CmpOr => {
+                    ice(format!("unsupported binary operation `{op}` on integers"))
+                }
+            };
+            code.push(format!("{ty}.{instr}"));
+            if let Op::Add | Op::Sub | Op::Mul | Op::Div = op {
+                code.extend(wrap(int));
+            }
+        }
+        Some(Scalar::Bool) => code.push(
+            match op {
+                Op::CmpAnd => "i32.and",
+                Op::CmpOr => "i32.or",
+                Op::CmpEq => "i32.eq",
+                Op::CmpNeq => "i32.ne",
+                _ => ice(format!("unsupported binary operation `{op}` on booleans")),
+            }
+            .to_string(),
+        ),
+        Some(Scalar::Ptr) => code.push(
+            match op {
+                Op::CmpEq => "i32.eq",
+                Op::CmpNeq => "i32.ne",
+                _ => ice(format!("unsupported binary operation `{op}` on pointers")),
+            }
+            .to_string(),
+        ),
+        None => ice(format!("unsupported binary operation `{op}`")),
+    }
+    code
+}
+
+fn pointee(ty: &TypeKind) -> &TypeKind {
+    match ty {
+        TypeKind::Concrete(ConcreteKind::Ptr(to)) => &to.kind,
+        _ => ice("indexed a value that is not a pointer"),
+    }
+}
@@ -0,0 +1,655 @@
+//! A demand-driven query database
+//!
+//! Compilation is split into queries, each a pure function of its key and of the other queries and inputs it reads
+//! through the [`Database`]. Results are memoized along with what they read, so that after an input is changed only
+//! the queries that could have seen the change are run again. When a query runs again but produces the same value as
+//! before, the queries that depend on it are not run again either.
+//!
+//! The database itself is only ever used from one thread. Queries that do most of their work without it can be split
+//! into a [`ParallelQuery`], which reads what it needs from the database first and then computes its value on a thread
+//! pool, so that many of them can be brought up to date at once with [`Database::get_all`].
+
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    fmt::Debug,
+    hash::Hash,
+    ops::Deref,
+    rc::Rc,
+    sync::Arc,
+};
+
+use flux_diagnostics::ice;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// A point in the history of the database, which moves forward every time an input is changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Revision(u64);
+
+/// A value the database is given, rather than computes
+pub trait Input: 'static {
+    type Context: 'static;
+    type Key: Clone + Eq + Hash + Debug + 'static;
+    type Value: Clone + PartialEq + 'static;
+
+    /// The value of an input that has not been set yet
+    fn load(context: &Self::Context, key: &Self::Key) -> Self::Value;
+}
+
+/// A value the database computes from inputs and other queries
+pub trait Query: 'static {
+    type Context: 'static;
+    type Key: Clone + Eq + Hash + Debug + 'static;
+    /// Values are compared with the previous result every time a query is run again, so that nothing depending on it
+    /// has to run again if it didn't change
+    type Value: Clone + PartialEq + 'static;
+
+    fn execute(db: &Database<Self::Context>, key: &Self::Key) -> Self::Value;
+}
+
+/// A query whose value is computed from what it reads without the database, which can be done on another thread
+///
+/// [`Query::execute`] should be [`ParallelQuery::compute`] of [`ParallelQuery::prepare`], so that a value is the same
+/// however it was brought up to date.
+pub trait ParallelQuery: Query<Value: Send> {
+    /// Everything [`ParallelQuery::compute`] needs from the database
+    type Prepared: Send;
+
+    /// Read what the value is computed from, which the query depends on like anything read in [`Query::execute`]
+    fn prepare(db: &Database<Self::Context>, key: &Self::Key) -> Self::Prepared;
+
+    fn compute(prepared: Self::Prepared) -> Self::Value;
+}
+
+/// A value shared between the database and whoever queried it, for values that are too big to compare or copy
+///
+/// Shared values are only ever equal to themselves, so a query producing one always counts as having changed.
+#[derive(Debug, Default)]
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Shared<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+}
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> PartialEq for Shared<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+pub struct Database<C: 'static> {
+    context: C,
+    revision: Revision,
+    storages: RefCell<HashMap<TypeId, Rc<dyn Any>>>,
+    /// The dependencies of every query that is being executed, innermost last
+    active: RefCell<Vec<Vec<Dependency<C>>>>,
+    /// Where parallel queries are computed, or `None` if they are computed on the thread that asked for them
+    pool: Option<rayon::ThreadPool>,
+}
+
+impl<C: 'static> Database<C> {
+    pub fn new(context: C) -> Self {
+        Self {
+            context,
+            revision: Revision(0),
+            storages: RefCell::new(HashMap::new()),
+            active: RefCell::new(vec![]),
+            pool: None,
+        }
+    }
+
+    /// Compute parallel queries on up to `jobs` threads
+    ///
+    /// If a pool of that many threads can't be started, they are computed on the thread that asked for them, which
+    /// only makes them slower.
+    pub fn set_jobs(&mut self, jobs: usize) {
+        self.pool = (jobs > 1)
+            .then(|| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(jobs)
+                    .build()
+                    .ok()
+            })
+            .flatten();
+    }
+
+    /// Whatever the queries share that isn't tracked, like the interner
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+
+    pub fn revision(&self) -> Revision {
+        self.revision
+    }
+
+    /// The value of a query, which the query being executed, if any, will depend on
+    pub fn get<Q: Query<Context = C>>(&self, key: Q::Key) -> Q::Value {
+        let storage = self.storage::<QueryStorage<Q>>();
+        let idx = storage.index(key);
+        let value = storage.fetch(self, idx);
+        self.record(storage, idx);
+        value
+    }
+
+    /// The values of a parallel query for every key, in the same order, which the query being executed, if any, will
+    /// depend on
+    ///
+    /// Every value that isn't up to date is prepared in turn, and then they are all computed at once on the thread pool.
+    /// The values are the same as getting each key in order would give, whatever order they were computed in.
+    pub fn get_all<Q: ParallelQuery<Context = C>>(&self, keys: &[Q::Key]) -> Vec<Q::Value> {
+        let storage = self.storage::<QueryStorage<Q>>();
+        let mut values = Vec::with_capacity(keys.len());
+        let mut stale = vec![];
+        let mut prepared = vec![];
+        for key in keys {
+            let idx = storage.index(key.clone());
+            match storage.verify(self, idx) {
+                Ok(value) => values.push(Some(value)),
+                // The same key can be asked for more than once, but only has to be computed once
+                Err(_) if stale.iter().any(|(stale_idx, _)| *stale_idx == idx) => values.push(None),
+                Err(key) => {
+                    tracing::debug!(query = std::any::type_name::<Q>(), key = ?key, "preparing query");
+                    let (value, dependencies) = storage.track(self, idx, || Q::prepare(self, &key));
+                    stale.push((idx, dependencies));
+                    prepared.push(value);
+                    values.push(None);
+                }
+            }
+            self.record(storage.clone(), idx);
+        }
+
+        let computed: Vec<_> = match &self.pool {
+            Some(pool) => pool.install(|| prepared.into_par_iter().map(Q::compute).collect()),
+            None => prepared.into_iter().map(Q::compute).collect(),
+        };
+        for ((idx, dependencies), value) in stale.into_iter().zip(computed) {
+            storage.store(self, idx, value, dependencies);
+        }
+        keys.iter()
+            .zip(values)
+            .map(|(key, value)| value.unwrap_or_else(|| self.get_untracked::<Q>(key.clone())))
+            .collect()
+    }
+
+    /// The value of a query, without the query being executed depending on it
+    ///
+    /// This is only correct when everything the value is used for is covered by the other dependencies of the query
+    /// being executed.
+    pub fn get_untracked<Q: Query<Context = C>>(&self, key: Q::Key) -> Q::Value {
+        let storage = self.storage::<QueryStorage<Q>>();
+        let idx = storage.index(key);
+        storage.fetch(self, idx)
+    }
+
+    /// The value of an input, which the query being executed, if any, will depend on
+    pub fn input<I: Input<Context = C>>(&self, key: I::Key) -> I::Value {
+        let storage = self.storage::<InputStorage<I>>();
+        let idx = storage.index(&self.context, key, self.revision);
+        let value = storage.slots.borrow()[idx].value.clone();
+        self.record(storage, idx);
+        value
+    }
+
+    /// Change the value of an input, starting a new revision if it is different from what it was
+    pub fn set<I: Input<Context = C>>(&mut self, key: I::Key, value: I::Value) {
+        let storage = self.storage::<InputStorage<I>>();
+        let idx = storage.index(&self.context, key, self.revision);
+        let mut slots = storage.slots.borrow_mut();
+        let slot = &mut slots[idx];
+        if slot.value != value {
+            self.revision = Revision(self.revision.0 + 1);
+            slot.value = value;
+            slot.changed_at = self.revision;
+        }
+    }
+
+    /// The keys of every input of a kind that has been loaded or set
+    pub fn input_keys<I: Input<Context = C>>(&self) -> Vec<I::Key> {
+        self.storage::<InputStorage<I>>()
+            .indices
+            .borrow()
+            .keys()
+            .cloned()
+            .collect()
+    }
+
+    fn storage<S: Default + 'static>(&self) -> Rc<S> {
+        let storage = self
+            .storages
+            .borrow_mut()
+            .entry(TypeId::of::<S>())
+            .or_insert_with(|| Rc::new(S::default()))
+            .clone();
+        storage
+            .downcast()
+            .unwrap_or_else(|_| ice("query storage has the wrong type"))
+    }
+
+    fn record<S: Storage<C> + 'static>(&self, storage: Rc<S>, idx: usize) {
+        if let Some(dependencies) = self.active.borrow_mut().last_mut() {
+            dependencies.push(Dependency { storage, idx });
+        }
+    }
+}
+
+/// Something a query read while it was being executed
+struct Dependency<C: 'static> {
+    storage: Rc<dyn Storage<C>>,
+    idx: usize,
+}
+
+impl<C> Clone for Dependency<C> {
+    fn clone(&self) -> Self {
+        Self {
+            storage: self.storage.clone(),
+            idx: self.idx,
+        }
+    }
+}
+
+impl<C> Dependency<C> {
+    fn changed_after(&self, db: &Database<C>, revision: Revision) -> bool {
+        self.storage.changed_after(db, self.idx, revision)
+    }
+}
+
+trait Storage<C> {
+    /// Whether the value at `idx` has changed since `revision`, bringing it up to date first if it's a query
+    fn changed_after(&self, db: &Database<C>, idx: usize, revision: Revision) -> bool;
+}
+
+struct InputSlot<I: Input> {
+    value: I::Value,
+    changed_at: Revision,
+}
+
+struct InputStorage<I: Input> {
+    indices: RefCell<HashMap<I::Key, usize>>,
+    slots: RefCell<Vec<InputSlot<I>>>,
+}
+
+impl<I: Input> Default for InputStorage<I> {
+    fn default() -> Self {
+        Self {
+            indices: RefCell::new(HashMap::new()),
+            slots: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl<I: Input> InputStorage<I> {
+    fn index(&self, context: &I::Context, key: I::Key, revision: Revision) -> usize {
+        if let Some(idx) = self.indices.borrow().get(&key) {
+            return *idx;
+        }
+        // Nothing can depend on an input before it is loaded, so loading it doesn't need a new revision
+        let value = I::load(context, &key);
+        let mut slots = self.slots.borrow_mut();
+        slots.push(InputSlot {
+            value,
+            changed_at: revision,
+        });
+        self.indices.borrow_mut().insert(key, slots.len() - 1);
+        slots.len() - 1
+    }
+}
+
+impl<I: Input> Storage<I::Context> for InputStorage<I> {
+    fn changed_after(&self, _: &Database<I::Context>, idx: usize, revision: Revision) -> bool {
+        self.slots.borrow()[idx].changed_at > revision
+    }
+}
+
+struct Memo<Q: Query> {
+    value: Q::Value,
+    /// The last revision the value was known to be up to date in
+    verified_at: Revision,
+    /// The revision the value last changed in, which can be older than the last time the query was executed
+    changed_at: Revision,
+    dependencies: Rc<[Dependency<Q::Context>]>,
+}
+
+struct QuerySlot<Q: Query> {
+    key: Q::Key,
+    memo: Option<Memo<Q>>,
+    in_progress: bool,
+}
+
+struct QueryStorage<Q: Query> {
+    indices: RefCell<HashMap<Q::Key, usize>>,
+    slots: RefCell<Vec<QuerySlot<Q>>>,
+}
+
+impl<Q: Query> Default for QueryStorage<Q> {
+    fn default() -> Self {
+        Self {
+            indices: RefCell::new(HashMap::new()),
+            slots: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl<Q: Query> QueryStorage<Q> {
+    fn index(&self, key: Q::Key) -> usize {
+        if let Some(idx) = self.indices.borrow().get(&key) {
+            return *idx;
+        }
+        let mut slots = self.slots.borrow_mut();
+        slots.push(QuerySlot {
+            key: key.clone(),
+            memo: None,
+            in_progress: false,
+        });
+        self.indices.borrow_mut().insert(key, slots.len() - 1);
+        slots.len() - 1
+    }
+
+    /// Bring the value at `idx` up to date, and return it
+    fn fetch(&self, db: &Database<Q::Context>, idx: usize) -> Q::Value {
+        match self.verify(db, idx) {
+            Ok(value) => value,
+            Err(key) => {
+                tracing::debug!(query = std::any::type_name::<Q>(), key = ?key, "executing query");
+                let (value, dependencies) = self.track(db, idx, || Q::execute(db, &key));
+                self.store(db, idx, value, dependencies)
+            }
+        }
+    }
+
+    /// The value at `idx` if it is still up to date, or its key if the query has to be executed again
+    fn verify(&self, db: &Database<Q::Context>, idx: usize) -> Result<Q::Value, Q::Key> {
+        let key = {
+            let mut slots = self.slots.borrow_mut();
+            let slot = &mut slots[idx];
+            if slot.in_progress {
+                ice(format!(
+                    "cycle detected while executing `{}` with {:?}",
+                    std::any::type_name::<Q>(),
+                    slot.key
+                ));
+            }
+            if let Some(memo) = &slot.memo {
+                if memo.verified_at == db.revision {
+                    return Ok(memo.value.clone());
+                }
+            }
+            slot.in_progress = true;
+            slot.key.clone()
+        };
+        let guard = InProgress {
+            slots: &self.slots,
+            idx,
+        };
+
+        // The value is still up to date if nothing it was computed from has changed since it was last verified
+        let old_memo = self.slots.borrow()[idx]
+            .memo
+            .as_ref()
+            .map(|memo| (memo.verified_at, memo.dependencies.clone()));
+        let Some((verified_at, dependencies)) = old_memo else {
+            return Err(key);
+        };
+        if dependencies
+            .iter()
+            .any(|dependency| dependency.changed_after(db, verified_at))
+        {
+            return Err(key);
+        }
+        drop(guard);
+        let mut slots = self.slots.borrow_mut();
+        let memo = slots[idx]
+            .memo
+            .as_mut()
+            .unwrap_or_else(|| ice("memo disappeared while being verified"));
+        memo.verified_at = db.revision;
+        Ok(memo.value.clone())
+    }
+
+    /// Run `f` as the query at `idx`, returning what it produced along with everything it read
+    fn track<T>(
+        &self,
+        db: &Database<Q::Context>,
+        idx: usize,
+        f: impl FnOnce() -> T,
+    ) -> (T, Vec<Dependency<Q::Context>>) {
+        self.slots.borrow_mut()[idx].in_progress = true;
+        let guard = InProgress {
+            slots: &self.slots,
+            idx,
+        };
+        db.active.borrow_mut().push(vec![]);
+        let frame = ActiveFrame { db };
+        let value = f();
+        drop(frame);
+        let dependencies = db
+            .active
+            .borrow_mut()
+            .pop()
+            .unwrap_or_else(|| ice("query stack is empty"));
+        drop(guard);
+        (value, dependencies)
+    }
+
+    /// Memoize the value the query at `idx` was executed to, along with what it read
+    fn store(
+        &self,
+        db: &Database<Q::Context>,
+        idx: usize,
+        value: Q::Value,
+        dependencies: Vec<Dependency<Q::Context>>,
+    ) -> Q::Value {
+        let mut slots = self.slots.borrow_mut();
+        let slot = &mut slots[idx];
+        let changed_at = match &slot.memo {
+            Some(memo) if memo.value == value => memo.changed_at,
+            _ => db.revision,
+        };
+        slot.memo = Some(Memo {
+            value: value.clone(),
+            verified_at: db.revision,
+            changed_at,
+            dependencies: dependencies.into(),
+        });
+        value
+    }
+}
+
+impl<Q: Query> Storage<Q::Context> for QueryStorage<Q> {
+    fn changed_after(&self, db: &Database<Q::Context>, idx: usize, revision: Revision) -> bool {
+        self.fetch(db, idx);
+        let slots = self.slots.borrow();
+        let memo = slots[idx]
+            .memo
+            .as_ref()
+            .unwrap_or_else(|| ice("query has no value after being fetched"));
+        memo.changed_at > revision
+    }
+}
+
+/// Marks a query as no longer being executed, even if it panicked
+struct InProgress<'a, Q: Query> {
+    slots: &'a RefCell<Vec<QuerySlot<Q>>>,
+    idx: usize,
+}
+
+impl<Q: Query> Drop for InProgress<'_, Q> {
+    fn drop(&mut self) {
+        self.slots.borrow_mut()[self.idx].in_progress = false;
+    }
+}
+
+/// Pops the dependencies of a query that panicked, so that the database can still be used afterwards
+struct ActiveFrame<'a, C: 'static> {
+    db: &'a Database<C>,
+}
+
+impl<C> Drop for ActiveFrame<'_, C> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.db.active.borrow_mut().pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Counts the number of times each query is executed
+    #[derive(Default)]
+    struct Executions(RefCell<HashMap<&'static str, usize>>);
+
+    impl Executions {
+        fn record(&self, query: &'static str) {
+            *self.0.borrow_mut().entry(query).or_default() += 1;
+        }
+
+        fn take(&self, query: &'static str) -> usize {
+            self.0.borrow_mut().remove(query).unwrap_or(0)
+        }
+    }
+
+    struct Text;
+
+    impl Input for Text {
+        type Context = Executions;
+        type Key = &'static str;
+        type Value = String;
+
+        fn load(_: &Executions, _: &&'static str) -> String {
+            String::new()
+        }
+    }
+
+    struct Length;
+
+    impl Query for Length {
+        type Context = Executions;
+        type Key = &'static str;
+        type Value = usize;
+
+        fn execute(db: &Database<Executions>, key: &&'static str) -> usize {
+            db.context().record("length");
+            db.input::<Text>(key).len()
+        }
+    }
+
+    struct TotalLength;
+
+    impl Query for TotalLength {
+        type Context = Executions;
+        type Key = ();
+        type Value = usize;
+
+        fn execute(db: &Database<Executions>, _: &()) -> usize {
+            db.context().record("total");
+            db.get::<Length>("a") + db.get::<Length>("b")
+        }
+    }
+
+    struct Shout;
+
+    impl Query for Shout {
+        type Context = Executions;
+        type Key = &'static str;
+        type Value = String;
+
+        fn execute(db: &Database<Executions>, key: &&'static str) -> String {
+            Self::compute(Self::prepare(db, key))
+        }
+    }
+
+    impl ParallelQuery for Shout {
+        type Prepared = String;
+
+        fn prepare(db: &Database<Executions>, key: &&'static str) -> String {
+            db.context().record("shout");
+            db.input::<Text>(key)
+        }
+
+        fn compute(text: String) -> String {
+            text.to_uppercase()
+        }
+    }
+
+    fn db() -> Database<Executions> {
+        let mut db = Database::new(Executions::default());
+        db.set::<Text>("a", "one".to_string());
+        db.set::<Text>("b", "two".to_string());
+        db
+    }
+
+    #[test]
+    fn memoizes_queries() {
+        let db = db();
+        assert_eq!(db.get::<TotalLength>(()), 6);
+        assert_eq!(db.get::<TotalLength>(()), 6);
+        assert_eq!(db.context().take("total"), 1);
+        assert_eq!(db.context().take("length"), 2);
+    }
+
+    #[test]
+    fn reruns_only_what_changed() {
+        let mut db = db();
+        db.get::<TotalLength>(());
+        db.context().take("total");
+        db.context().take("length");
+
+        db.set::<Text>("a", "three".to_string());
+        assert_eq!(db.get::<TotalLength>(()), 8);
+        assert_eq!(db.context().take("total"), 1);
+        assert_eq!(db.context().take("length"), 1);
+    }
+
+    #[test]
+    fn stops_when_a_value_is_unchanged() {
+        let mut db = db();
+        db.get::<TotalLength>(());
+        db.context().take("total");
+        db.context().take("length");
+
+        db.set::<Text>("a", "six".to_string());
+        assert_eq!(db.get::<TotalLength>(()), 6);
+        assert_eq!(db.context().take("length"), 1);
+        assert_eq!(db.context().take("total"), 0);
+    }
+
+    #[test]
+    fn setting_the_same_value_keeps_the_revision() {
+        let mut db = db();
+        let revision = db.revision();
+        db.set::<Text>("a", "one".to_string());
+        assert_eq!(db.revision(), revision);
+    }
+
+    #[test]
+    fn parallel_queries_are_memoized_in_order() {
+        let mut db = db();
+        db.set_jobs(4);
+        assert_eq!(db.get_all::<Shout>(&["b", "a", "b"]), ["TWO", "ONE", "TWO"]);
+        assert_eq!(db.context().take("shout"), 2);
+        assert_eq!(db.get::<Shout>("a"), "ONE");
+        assert_eq!(db.context().take("shout"), 0);
+
+        db.set::<Text>("a", "three".to_string());
+        assert_eq!(db.get_all::<Shout>(&["a", "b"]), ["THREE", "TWO"]);
+        assert_eq!(db.context().take("shout"), 1);
+    }
+}
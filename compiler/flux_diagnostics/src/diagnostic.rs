@@ -1,5 +1,7 @@
 use ariadne::{Color, Label, Report, ReportKind};
-use flux_util::{FileId, FileSpan, FileSpanned};
+use flux_util::{FileId, FileSpan, FileSpanned, Span};
+
+use crate::{Lint, Suggestion};
 
 pub trait ToDiagnostic {
     fn to_diagnostic(&self) -> Diagnostic;
@@ -13,6 +15,7 @@ pub struct Diagnostic {
     msg: String,
     pub labels: Vec<FileSpanned<String>>,
     help: Option<String>,
+    suggestions: Vec<Suggestion>,
 }
 
 impl Diagnostic {
@@ -29,9 +32,32 @@ impl Diagnostic {
             msg,
             labels,
             help: None,
+            suggestions: vec![],
+        }
+    }
+
+    pub fn warning(
+        offset: FileSpan,
+        code: DiagnosticCode,
+        msg: String,
+        labels: Vec<FileSpanned<String>>,
+    ) -> Self {
+        Self {
+            kind: DiagnosticKind::Warning,
+            offset,
+            code,
+            msg,
+            labels,
+            help: None,
+            suggestions: vec![],
         }
     }
 
+    /// Errors stop a package from being compiled, while warnings are only reported
+    pub fn is_error(&self) -> bool {
+        matches!(self.kind, DiagnosticKind::Error)
+    }
+
     // /// Create a diagnostic that does not exist in a file
     // /// For example, an error writing/reading from disk
     // pub fn new_without_file(kind: DiagnosticKind, code: DiagnosticCode, msg: String) -> Self {
@@ -45,6 +71,14 @@ impl Diagnostic {
     //     }
     // }
 
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn help(&self) -> Option<&str> {
+        self.help.as_deref()
+    }
+
     pub fn with_help(mut self, help: String) -> Self {
         self.help = Some(help);
         self
@@ -55,6 +89,32 @@ impl Diagnostic {
         self
     }
 
+    pub fn suggestions(&self) -> &[Suggestion] {
+        &self.suggestions
+    }
+
+    pub fn with_suggestion(mut self, suggestion: Suggestion) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Move every span the diagnostic points at with `relocate`, for when the source it was reported in has moved
+    pub fn relocate(&mut self, relocate: impl Fn(FileId, Span) -> Span) {
+        self.offset.span = relocate(self.offset.file_id, self.offset.span);
+        for label in &mut self.labels {
+            label.inner.span = relocate(label.file_id, label.inner.span);
+        }
+        for suggestion in &mut self.suggestions {
+            let file_span = &mut suggestion.file_span;
+            file_span.span = relocate(file_span.file_id, file_span.span);
+        }
+    }
+
+    pub(crate) fn with_kind(mut self, kind: DiagnosticKind) -> Self {
+        self.kind = kind;
+        self
+    }
+
     pub(crate) fn as_report(&self, config: ariadne::Config) -> Report<ASpan> {
         // let (file_id, offset) = match &self.offset {
         //     Some(offset) => (Some(offset.file_id), offset.span.range.start().into()),
@@ -70,7 +130,7 @@ impl Diagnostic {
         if let Some(primary) = self.labels.get(0) {
             builder.add_label(
                 Label::new(ASpan::new(FileSpan::new(primary.file_id, primary.span)))
-                    .with_color(Color::Red)
+                    .with_color(self.kind.color())
                     .with_message(primary.inner.inner.clone()),
             )
         }
@@ -83,8 +143,21 @@ impl Diagnostic {
             }))
         }
 
-        if let Some(help) = &self.help {
-            builder.set_help(help);
+        // Suggestions are told along with the help, since a report only has room for one
+        let help = self
+            .help
+            .iter()
+            .map(String::as_str)
+            .chain(self.suggestions.iter().map(Suggestion::message))
+            .collect::<Vec<_>>();
+        if !help.is_empty() {
+            builder.set_help(help.join("\n"));
+        }
+
+        if let Some(lint) = Lint::of(self.code) {
+            builder.set_note(format!(
+                "`{lint}` can be set to allow, warn or deny in the `[lints]` table of `flux.toml`"
+            ));
         }
 
         builder.finish()
@@ -104,9 +177,16 @@ impl DiagnosticKind {
             Self::Warning => ReportKind::Warning,
         }
     }
+
+    fn color(&self) -> Color {
+        match self {
+            Self::Error => Color::Red,
+            Self::Warning => Color::Yellow,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DiagnosticCode {
     CouldNotReadConfigFile,
     CouldNotReadEntryFile,
@@ -139,6 +219,155 @@ pub enum DiagnosticCode {
     TypeMismatch,
     CouldNotInfer,
     CouldBeMultipleTypes,
+
+    NoMainFunction,
+    NullPointerDereference,
+    OutOfBoundsAccess,
+    UseAfterFree,
+    InvalidFree,
+    UninitializedRead,
+    IntegerOverflow,
+    DivisionByZero,
+    ExplicitPanic,
+    InvalidAssignment,
+    InvalidCast,
+    StackOverflow,
+
+    UninferredGenericArguments,
+    UnsupportedType,
+    UnresolvedMethod,
+    CouldNotWriteOutputFile,
+    CouldNotLink,
+    InstantiationRecursionLimit,
+    UnknownEnumVariant,
+    IncorrectVariantPayload,
+    NoMatchingArm,
+    NonExhaustiveMatch,
+    UnreachableMatchArm,
+    LoopControlOutsideLoop,
+    UnknownLoopLabel,
+    BreakWithValueInNonLoop,
+    NotAnIterator,
+    UnsupportedExpr,
+    CouldNotReadSourceFile,
+    CouldNotFormat,
+    CouldNotClean,
+    UnusedLocal,
+    UnusedImport,
+    UnusedPrivateFunction,
+    UnreachableCode,
+    UnnecessaryCast,
+    UnknownLint,
+    UnknownDiagnosticCode,
+    InvalidPackageName,
+    CouldNotCreateProject,
+    CouldNotParseConfigFile,
+    MissingConfigKey,
+    ConflictingConfigKeys,
+    InvalidConfigValue,
+    UnknownConfigKey,
+    CouldNotResolveDependencies,
+    DependencyChecksumMismatch,
+    LockfileOutOfDate,
+    CouldNotReadLockfile,
+    CouldNotFetchDependency,
+    MissingGitRevision,
+    DependencyCycle,
+    AllocationTooLarge,
+    NotIndexable,
+}
+
+impl DiagnosticCode {
+    pub const ALL: [DiagnosticCode; 81] = [
+        DiagnosticCode::CouldNotReadConfigFile,
+        DiagnosticCode::CouldNotReadEntryFile,
+        DiagnosticCode::ParserExpected,
+        DiagnosticCode::UnknownGeneric,
+        DiagnosticCode::CouldNotResolveModDecl,
+        DiagnosticCode::DuplicateGenericParams,
+        DiagnosticCode::UnusedGenerics,
+        DiagnosticCode::CannotResolveEmptyPath,
+        DiagnosticCode::UnresolvedPath,
+        DiagnosticCode::PrivateModule,
+        DiagnosticCode::UnexpectedItem,
+        DiagnosticCode::PositiveIntegerOverflow,
+        DiagnosticCode::StmtFollowingTerminatorExpr,
+        DiagnosticCode::ExpectedDifferentItem,
+        DiagnosticCode::MissingFieldsInStructExpr,
+        DiagnosticCode::MissingGenericArguments,
+        DiagnosticCode::UnknownLocal,
+        DiagnosticCode::UnknownIntrinsic,
+        DiagnosticCode::IncorrectNumberOfArgs,
+        DiagnosticCode::IncorrectStructFieldsInInitialization,
+        DiagnosticCode::MemberAccessOnNonStruct,
+        DiagnosticCode::UnknownStructField,
+        DiagnosticCode::CouldNotResolveStruct,
+        DiagnosticCode::CalleeNotFunction,
+        DiagnosticCode::TypeMismatch,
+        DiagnosticCode::CouldNotInfer,
+        DiagnosticCode::CouldBeMultipleTypes,
+        DiagnosticCode::NoMainFunction,
+        DiagnosticCode::NullPointerDereference,
+        DiagnosticCode::OutOfBoundsAccess,
+        DiagnosticCode::UseAfterFree,
+        DiagnosticCode::InvalidFree,
+        DiagnosticCode::UninitializedRead,
+        DiagnosticCode::IntegerOverflow,
+        DiagnosticCode::DivisionByZero,
+        DiagnosticCode::ExplicitPanic,
+        DiagnosticCode::InvalidAssignment,
+        DiagnosticCode::InvalidCast,
+        DiagnosticCode::StackOverflow,
+        DiagnosticCode::UninferredGenericArguments,
+        DiagnosticCode::UnsupportedType,
+        DiagnosticCode::UnresolvedMethod,
+        DiagnosticCode::CouldNotWriteOutputFile,
+        DiagnosticCode::CouldNotLink,
+        DiagnosticCode::InstantiationRecursionLimit,
+        DiagnosticCode::UnknownEnumVariant,
+        DiagnosticCode::IncorrectVariantPayload,
+        DiagnosticCode::NoMatchingArm,
+        DiagnosticCode::NonExhaustiveMatch,
+        DiagnosticCode::UnreachableMatchArm,
+        DiagnosticCode::LoopControlOutsideLoop,
+        DiagnosticCode::UnknownLoopLabel,
+        DiagnosticCode::BreakWithValueInNonLoop,
+        DiagnosticCode::NotAnIterator,
+        DiagnosticCode::UnsupportedExpr,
+        DiagnosticCode::CouldNotReadSourceFile,
+        DiagnosticCode::CouldNotFormat,
+        DiagnosticCode::CouldNotClean,
+        DiagnosticCode::UnusedLocal,
+        DiagnosticCode::UnusedImport,
+        DiagnosticCode::UnusedPrivateFunction,
+        DiagnosticCode::UnreachableCode,
+        DiagnosticCode::UnnecessaryCast,
+        DiagnosticCode::UnknownLint,
+        DiagnosticCode::UnknownDiagnosticCode,
+        DiagnosticCode::InvalidPackageName,
+        DiagnosticCode::CouldNotCreateProject,
+        DiagnosticCode::CouldNotParseConfigFile,
+        DiagnosticCode::MissingConfigKey,
+        DiagnosticCode::ConflictingConfigKeys,
+        DiagnosticCode::InvalidConfigValue,
+        DiagnosticCode::UnknownConfigKey,
+        DiagnosticCode::CouldNotResolveDependencies,
+        DiagnosticCode::DependencyChecksumMismatch,
+        DiagnosticCode::LockfileOutOfDate,
+        DiagnosticCode::CouldNotReadLockfile,
+        DiagnosticCode::CouldNotFetchDependency,
+        DiagnosticCode::MissingGitRevision,
+        DiagnosticCode::DependencyCycle,
+        DiagnosticCode::AllocationTooLarge,
+        DiagnosticCode::NotIndexable,
+    ];
+
+    /// The code a name like `E0016` refers to, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|code| code.to_string().eq_ignore_ascii_case(name))
+    }
 }
 
 impl std::fmt::Display for DiagnosticCode {
@@ -0,0 +1,198 @@
+//! Long-form documentation of every diagnostic code, which `flux explain` prints
+//!
+//! Explanations are markdown, and most show the problem with an example, followed by the example fixed. Examples are
+//! code blocks marked `flux`, along with whatever is expected of them, separated by commas:
+//!
+//! - a diagnostic code, like `flux,E0016`, which building the example reports
+//! - `run`, if the example is interpreted after it is built, in which case the code is reported by running it
+//! - `c`, if the example is translated to C after it is built, in which case the code is reported by doing so
+//!
+//! An example with no code builds without reporting anything, and runs or is translated without a problem if asked
+//! to. Examples are `src/main.flx` of a project that depends on `std`, and a line like `// shapes.flx` starts another
+//! file next to it.
+
+use crate::DiagnosticCode;
+
+/// What an example is built for after it is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExampleStage {
+    Build,
+    Run,
+    EmitC,
+}
+
+#[derive(Debug, Clone)]
+pub struct Example {
+    /// Every file of the example, by its path relative to the `src` directory, starting with `main.flx`
+    pub files: Vec<(String, String)>,
+    pub stage: ExampleStage,
+    /// The code reported for the example, or `None` if it is correct
+    pub code: Option<DiagnosticCode>,
+}
+
+impl DiagnosticCode {
+    /// The explanation of the code, as markdown, under a heading like `# E0016: unknown local referenced`
+    pub fn explanation(&self) -> &'static str {
+        match self {
+            DiagnosticCode::CouldNotReadConfigFile => include_str!("explanations/E0000.md"),
+            DiagnosticCode::CouldNotReadEntryFile => include_str!("explanations/E0001.md"),
+            DiagnosticCode::ParserExpected => include_str!("explanations/E0002.md"),
+            DiagnosticCode::UnknownGeneric => include_str!("explanations/E0003.md"),
+            DiagnosticCode::CouldNotResolveModDecl => include_str!("explanations/E0004.md"),
+            DiagnosticCode::DuplicateGenericParams => include_str!("explanations/E0005.md"),
+            DiagnosticCode::UnusedGenerics => include_str!("explanations/E0006.md"),
+            DiagnosticCode::CannotResolveEmptyPath => include_str!("explanations/E0007.md"),
+            DiagnosticCode::UnresolvedPath => include_str!("explanations/E0008.md"),
+            DiagnosticCode::PrivateModule => include_str!("explanations/E0009.md"),
+            DiagnosticCode::UnexpectedItem => include_str!("explanations/E0010.md"),
+            DiagnosticCode::PositiveIntegerOverflow => include_str!("explanations/E0011.md"),
+            DiagnosticCode::StmtFollowingTerminatorExpr => include_str!("explanations/E0012.md"),
+            DiagnosticCode::ExpectedDifferentItem => include_str!("explanations/E0013.md"),
+            DiagnosticCode::MissingFieldsInStructExpr => include_str!("explanations/E0014.md"),
+            DiagnosticCode::MissingGenericArguments => include_str!("explanations/E0015.md"),
+            DiagnosticCode::UnknownLocal => include_str!("explanations/E0016.md"),
+            DiagnosticCode::UnknownIntrinsic => include_str!("explanations/E0017.md"),
+            DiagnosticCode::IncorrectNumberOfArgs => include_str!("explanations/E0018.md"),
+            DiagnosticCode::IncorrectStructFieldsInInitialization => {
+                include_str!("explanations/E0019.md")
+            }
+            DiagnosticCode::MemberAccessOnNonStruct => include_str!("explanations/E0020.md"),
+            DiagnosticCode::UnknownStructField => include_str!("explanations/E0021.md"),
+            DiagnosticCode::CouldNotResolveStruct => include_str!("explanations/E0022.md"),
+            DiagnosticCode::CalleeNotFunction => include_str!("explanations/E0023.md"),
+            DiagnosticCode::TypeMismatch => include_str!("explanations/E0024.md"),
+            DiagnosticCode::CouldNotInfer => include_str!("explanations/E0025.md"),
+            DiagnosticCode::CouldBeMultipleTypes => include_str!("explanations/E0026.md"),
+            DiagnosticCode::NoMainFunction => include_str!("explanations/E0027.md"),
+            DiagnosticCode::NullPointerDereference => include_str!("explanations/E0028.md"),
+            DiagnosticCode::OutOfBoundsAccess => include_str!("explanations/E0029.md"),
+            DiagnosticCode::UseAfterFree => include_str!("explanations/E0030.md"),
+            DiagnosticCode::InvalidFree => include_str!("explanations/E0031.md"),
+            DiagnosticCode::UninitializedRead => include_str!("explanations/E0032.md"),
+            DiagnosticCode::IntegerOverflow => include_str!("explanations/E0033.md"),
+            DiagnosticCode::DivisionByZero => include_str!("explanations/E0034.md"),
+            DiagnosticCode::ExplicitPanic => include_str!("explanations/E0035.md"),
+            DiagnosticCode::InvalidAssignment => include_str!("explanations/E0036.md"),
+            DiagnosticCode::InvalidCast => include_str!("explanations/E0037.md"),
+            DiagnosticCode::StackOverflow => include_str!("explanations/E0038.md"),
+            DiagnosticCode::UninferredGenericArguments => include_str!("explanations/E0039.md"),
+            DiagnosticCode::UnsupportedType => include_str!("explanations/E0040.md"),
+            DiagnosticCode::UnresolvedMethod => include_str!("explanations/E0041.md"),
+            DiagnosticCode::CouldNotWriteOutputFile => include_str!("explanations/E0042.md"),
+            DiagnosticCode::CouldNotLink => include_str!("explanations/E0043.md"),
+            DiagnosticCode::InstantiationRecursionLimit => include_str!("explanations/E0044.md"),
+            DiagnosticCode::UnknownEnumVariant => include_str!("explanations/E0045.md"),
+            DiagnosticCode::IncorrectVariantPayload => include_str!("explanations/E0046.md"),
+            DiagnosticCode::NoMatchingArm => include_str!("explanations/E0047.md"),
+            DiagnosticCode::NonExhaustiveMatch => include_str!("explanations/E0048.md"),
+            DiagnosticCode::UnreachableMatchArm => include_str!("explanations/E0049.md"),
+            DiagnosticCode::LoopControlOutsideLoop => include_str!("explanations/E0050.md"),
+            DiagnosticCode::UnknownLoopLabel => include_str!("explanations/E0051.md"),
+            DiagnosticCode::BreakWithValueInNonLoop => include_str!("explanations/E0052.md"),
+            DiagnosticCode::NotAnIterator => include_str!("explanations/E0053.md"),
+            DiagnosticCode::UnsupportedExpr => include_str!("explanations/E0054.md"),
+            DiagnosticCode::CouldNotReadSourceFile => include_str!("explanations/E0055.md"),
+            DiagnosticCode::CouldNotFormat => include_str!("explanations/E0056.md"),
+            DiagnosticCode::CouldNotClean => include_str!("explanations/E0057.md"),
+            DiagnosticCode::UnusedLocal => include_str!("explanations/E0058.md"),
+            DiagnosticCode::UnusedImport => include_str!("explanations/E0059.md"),
+            DiagnosticCode::UnusedPrivateFunction => include_str!("explanations/E0060.md"),
+            DiagnosticCode::UnreachableCode => include_str!("explanations/E0061.md"),
+            DiagnosticCode::UnnecessaryCast => include_str!("explanations/E0062.md"),
+            DiagnosticCode::UnknownLint => include_str!("explanations/E0063.md"),
+            DiagnosticCode::UnknownDiagnosticCode => include_str!("explanations/E0064.md"),
+            DiagnosticCode::InvalidPackageName => include_str!("explanations/E0065.md"),
+            DiagnosticCode::CouldNotCreateProject => include_str!("explanations/E0066.md"),
+            DiagnosticCode::CouldNotParseConfigFile => include_str!("explanations/E0067.md"),
+            DiagnosticCode::MissingConfigKey => include_str!("explanations/E0068.md"),
+            DiagnosticCode::ConflictingConfigKeys => include_str!("explanations/E0069.md"),
+            DiagnosticCode::InvalidConfigValue => include_str!("explanations/E0070.md"),
+            DiagnosticCode::UnknownConfigKey => include_str!("explanations/E0071.md"),
+            DiagnosticCode::CouldNotResolveDependencies => include_str!("explanations/E0072.md"),
+            DiagnosticCode::DependencyChecksumMismatch => include_str!("explanations/E0073.md"),
+            DiagnosticCode::LockfileOutOfDate => include_str!("explanations/E0074.md"),
+            DiagnosticCode::CouldNotReadLockfile => include_str!("explanations/E0075.md"),
+            DiagnosticCode::CouldNotFetchDependency => include_str!("explanations/E0076.md"),
+            DiagnosticCode::MissingGitRevision => include_str!("explanations/E0077.md"),
+            DiagnosticCode::DependencyCycle => include_str!("explanations/E0078.md"),
+            DiagnosticCode::AllocationTooLarge => include_str!("explanations/E0079.md"),
+            DiagnosticCode::NotIndexable => include_str!("explanations/E0080.md"),
+        }
+    }
+
+    /// The examples in the explanation of the code
+    ///
+    /// Returns the info string of the first example block that could not be understood as the error.
+    /// The examples in the explanation of the code, or the line of the first one that is marked with something unknown
+    pub fn examples(&self) -> Result<Vec<Example>, String> {
+        let mut examples = vec![];
+        let mut lines = self.explanation().lines();
+        while let Some(line) = lines.next() {
+            let Some(info) = line.strip_prefix("```flux") else {
+                continue;
+            };
+            if !info.is_empty() && !info.starts_with(',') {
+                return Err(line.to_string());
+            }
+            let mut stage = ExampleStage::Build;
+            let mut code = None;
+            for attribute in info.split(',').skip(1) {
+                match attribute {
+                    "run" => stage = ExampleStage::Run,
+                    "c" => stage = ExampleStage::EmitC,
+                    _ => match DiagnosticCode::from_name(attribute) {
+                        Some(attribute_code) if code.is_none() => code = Some(attribute_code),
+                        _ => return Err(line.to_string()),
+                    },
+                }
+            }
+
+            let mut files = vec![("main.flx".to_string(), String::new())];
+            for line in lines.by_ref().take_while(|line| *line != "```") {
+                match line
+                    .strip_prefix("// ")
+                    .filter(|name| name.ends_with(".flx") && !name.contains(' '))
+                {
+                    Some(name) => files.push((name.to_string(), String::new())),
+                    None => {
+                        let (_, content) = files.last_mut().unwrap();
+                        content.push_str(line);
+                        content.push('\n');
+                    }
+                }
+            }
+            examples.push(Example { files, stage, code });
+        }
+        Ok(examples)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DiagnosticCode;
+
+    #[test]
+    fn every_code_is_explained() {
+        for code in DiagnosticCode::ALL {
+            let explanation = code.explanation();
+            assert!(
+                explanation.starts_with(&format!("# {code}: ")),
+                "the explanation of {code} does not start with its title"
+            );
+            let examples = code
+                .examples()
+                .unwrap_or_else(|info| panic!("{code} has an example marked `{info}`"));
+            assert!(
+                examples
+                    .iter()
+                    .all(|example| example.code.is_none() || example.code == Some(code)),
+                "{code} has an example of a different code"
+            );
+        }
+        assert_eq!(
+            DiagnosticCode::from_name("e0016"),
+            Some(DiagnosticCode::UnknownLocal)
+        );
+        assert_eq!(DiagnosticCode::from_name("E9999"), None);
+    }
+}
@@ -16,6 +16,18 @@ impl IOError {
         Self { code, msg, help }
     }
 
+    pub fn code(&self) -> DiagnosticCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+
+    pub fn help(&self) -> &[String] {
+        &self.help
+    }
+
     pub fn to_string(self) -> String {
         format!(
             "{} {}\n\t{}",
@@ -1,7 +1,11 @@
 mod diagnostic;
+mod explain;
 pub mod fmt;
 mod io;
+mod lint;
+mod message_format;
 mod reporting;
+mod suggestion;
 
 use std::{
     backtrace::Backtrace,
@@ -13,8 +17,14 @@ use std::{
 
 use colored::Colorize;
 pub use diagnostic::*;
+pub use explain::{Example, ExampleStage};
 pub use io::IOError;
+pub use lint::{Lint, LintLevel, LintLevels};
+pub use message_format::MessageFormat;
 pub use reporting::*;
+pub use suggestion::{
+    applicable_suggestions, apply_suggestions, closest_name, Applicability, Suggestion,
+};
 
 const TAB_WIDTH: usize = 8;
 const ICE_MSG: &'static str = "internal compiler error:";
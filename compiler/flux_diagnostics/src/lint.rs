@@ -0,0 +1,145 @@
+//! Lints are warnings about code that is legal but probably not what was meant
+//!
+//! Every lint is reported under its own [`DiagnosticCode`], and can be allowed, reported as a warning, or denied, which
+//! reports it as an error. Lints are warnings unless configured otherwise.
+
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{Diagnostic, DiagnosticCode, DiagnosticKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedGenerics,
+    UnusedLocals,
+    UnusedImports,
+    UnusedPrivateFunctions,
+    UnreachableCode,
+    UnnecessaryCasts,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 6] = [
+        Lint::UnusedGenerics,
+        Lint::UnusedLocals,
+        Lint::UnusedImports,
+        Lint::UnusedPrivateFunctions,
+        Lint::UnreachableCode,
+        Lint::UnnecessaryCasts,
+    ];
+
+    /// The name the lint is configured by
+    pub fn name(&self) -> &'static str {
+        match self {
+            Lint::UnusedGenerics => "unused_generics",
+            Lint::UnusedLocals => "unused_locals",
+            Lint::UnusedImports => "unused_imports",
+            Lint::UnusedPrivateFunctions => "unused_private_functions",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::UnnecessaryCasts => "unnecessary_casts",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|lint| lint.name() == name)
+    }
+
+    /// The lint a diagnostic is reported under, if it is one
+    pub fn of(code: DiagnosticCode) -> Option<Self> {
+        match code {
+            DiagnosticCode::UnusedGenerics => Some(Lint::UnusedGenerics),
+            DiagnosticCode::UnusedLocal => Some(Lint::UnusedLocals),
+            DiagnosticCode::UnusedImport => Some(Lint::UnusedImports),
+            DiagnosticCode::UnusedPrivateFunction => Some(Lint::UnusedPrivateFunctions),
+            DiagnosticCode::UnreachableCode => Some(Lint::UnreachableCode),
+            DiagnosticCode::UnnecessaryCast => Some(Lint::UnnecessaryCasts),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl LintLevel {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(LintLevel::Allow),
+            "warn" => Some(LintLevel::Warn),
+            "deny" => Some(LintLevel::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// The level every lint is reported at
+#[derive(Debug, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, LintLevel>,
+    deny_warnings: bool,
+}
+
+impl LintLevels {
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    /// Report every warning as an error, whether it is a lint or not
+    pub fn deny_warnings(&mut self) {
+        self.deny_warnings = true;
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        self.levels.get(&lint).copied().unwrap_or(LintLevel::Warn)
+    }
+
+    /// Give a diagnostic the severity its lint is configured with, or `None` if it is allowed
+    pub fn apply(&self, diagnostic: Diagnostic) -> Option<Diagnostic> {
+        let kind = match Lint::of(diagnostic.code).map(|lint| self.level(lint)) {
+            Some(LintLevel::Allow) => return None,
+            Some(LintLevel::Deny) => DiagnosticKind::Error,
+            Some(LintLevel::Warn) | None if diagnostic.is_error() => DiagnosticKind::Error,
+            Some(LintLevel::Warn) | None if self.deny_warnings => DiagnosticKind::Error,
+            Some(LintLevel::Warn) | None => DiagnosticKind::Warning,
+        };
+        Some(diagnostic.with_kind(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flux_util::{FileId, FileSpan, Span};
+
+    use super::*;
+
+    fn unused_local() -> Diagnostic {
+        Diagnostic::warning(
+            FileSpan::new(FileId::poisoned(), Span::new(0..1)),
+            DiagnosticCode::UnusedLocal,
+            "unused local".into(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn lints_are_reported_at_their_level() {
+        let mut levels = LintLevels::default();
+        assert!(!levels.apply(unused_local()).unwrap().is_error());
+
+        levels.set(Lint::UnusedLocals, LintLevel::Allow);
+        assert!(levels.apply(unused_local()).is_none());
+
+        levels.set(Lint::UnusedLocals, LintLevel::Warn);
+        levels.deny_warnings();
+        assert!(levels.apply(unused_local()).unwrap().is_error());
+    }
+}
@@ -0,0 +1,261 @@
+//! Diagnostics written for other tools to read, such as CI bots and code review tooling
+//!
+//! Positions are 1-based lines and columns, with columns counted in characters. The end of a span is the position of
+//! the character following it, which is what SARIF expects too.
+
+use flux_util::{FileSpan, FileSpanned};
+use serde_json::{json, Value};
+
+use crate::{Diagnostic, IOError, Lint, SourceCache, Suggestion};
+
+/// How diagnostics are reported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Rendered along with the source they point at, on stderr
+    #[default]
+    Human,
+    /// A JSON object per diagnostic, each on its own line of stdout
+    Json,
+    /// A single SARIF 2.1.0 log on stdout, written once everything has been reported
+    Sarif,
+}
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+impl SourceCache {
+    pub(crate) fn diagnostic_to_json(&self, diagnostic: &Diagnostic) -> Value {
+        let labels = diagnostic
+            .labels
+            .iter()
+            .enumerate()
+            .map(|(idx, label)| {
+                let (start, end) = self.label_position(label);
+                json!({
+                    "file": self.get_file_path(&label.file_id),
+                    "start": position_to_json(start),
+                    "end": position_to_json(end),
+                    "message": label.inner.inner.as_str(),
+                    "primary": (idx == 0),
+                })
+            })
+            .collect::<Vec<_>>();
+        json!({
+            "code": diagnostic.code.to_string(),
+            "severity": severity(diagnostic.is_error()),
+            "lint": lint_name(diagnostic),
+            "message": diagnostic.message(),
+            "labels": labels,
+            "help": help_to_json(diagnostic.help()),
+            "suggestions": diagnostic
+                .suggestions()
+                .iter()
+                .map(|suggestion| self.suggestion_to_json(suggestion))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    fn suggestion_to_json(&self, suggestion: &Suggestion) -> Value {
+        let (start, end) = self.span_position(&suggestion.file_span);
+        json!({
+            "file": self.get_file_path(&suggestion.file_span.file_id),
+            "start": position_to_json(start),
+            "end": position_to_json(end),
+            "replacement": suggestion.replacement.as_str(),
+            "message": suggestion.message(),
+            "applicability": suggestion.applicability.name(),
+        })
+    }
+
+    pub(crate) fn io_error_to_json(&self, err: &IOError) -> Value {
+        json!({
+            "code": err.code().to_string(),
+            "severity": severity(true),
+            "lint": null,
+            "message": err.message(),
+            "labels": [],
+            "help": help_to_json(err.help().iter().map(String::as_str)),
+            "suggestions": [],
+        })
+    }
+
+    /// A SARIF result, the first label of which is where the diagnostic is located and the rest related to it
+    pub(crate) fn diagnostic_to_sarif(&self, diagnostic: &Diagnostic) -> Value {
+        let mut locations = diagnostic
+            .labels
+            .iter()
+            .map(|label| self.sarif_location(label));
+        let primary = locations.next();
+        json!({
+            "ruleId": diagnostic.code.to_string(),
+            "level": severity(diagnostic.is_error()),
+            "message": sarif_message(diagnostic.message()),
+            "locations": primary.into_iter().collect::<Vec<_>>(),
+            "relatedLocations": locations.collect::<Vec<_>>(),
+            "fixes": diagnostic
+                .suggestions()
+                .iter()
+                .map(|suggestion| self.suggestion_to_sarif(suggestion))
+                .collect::<Vec<_>>(),
+            "properties": {
+                "help": help_to_json(diagnostic.help()),
+                "lint": lint_name(diagnostic),
+            },
+        })
+    }
+
+    pub(crate) fn io_error_to_sarif(&self, err: &IOError) -> Value {
+        json!({
+            "ruleId": err.code().to_string(),
+            "level": severity(true),
+            "message": sarif_message(err.message()),
+            "locations": [],
+            "properties": { "help": help_to_json(err.help().iter().map(String::as_str)) },
+        })
+    }
+
+    /// A SARIF fix, which replaces the region of the suggestion with its replacement
+    fn suggestion_to_sarif(&self, suggestion: &Suggestion) -> Value {
+        let file_path = self.get_file_path(&suggestion.file_span.file_id);
+        let deleted_region = sarif_region(self.span_position(&suggestion.file_span));
+        json!({
+            "description": sarif_message(suggestion.message()),
+            "artifactChanges": [
+                {
+                    "artifactLocation": { "uri": file_uri(file_path) },
+                    "replacements": [
+                        {
+                            "deletedRegion": deleted_region,
+                            "insertedContent": { "text": suggestion.replacement.as_str() },
+                        },
+                    ],
+                },
+            ],
+            "properties": { "applicability": suggestion.applicability.name() },
+        })
+    }
+
+    fn sarif_location(&self, label: &FileSpanned<String>) -> Value {
+        json!({
+            "physicalLocation": {
+                "artifactLocation": { "uri": file_uri(self.get_file_path(&label.file_id)) },
+                "region": sarif_region(self.label_position(label)),
+            },
+            "message": sarif_message(&label.inner.inner),
+        })
+    }
+
+    fn label_position(&self, label: &FileSpanned<String>) -> ((u32, u32), (u32, u32)) {
+        self.span_position(&FileSpan::new(label.file_id, label.span))
+    }
+
+    fn span_position(&self, file_span: &FileSpan) -> ((u32, u32), (u32, u32)) {
+        let range = file_span.span.range;
+        (
+            self.line_col(&file_span.file_id, range.start().into()),
+            self.line_col(&file_span.file_id, range.end().into()),
+        )
+    }
+}
+
+/// The SARIF log of every result that was reported
+pub(crate) fn sarif_log(results: Vec<Value>) -> Value {
+    json!({
+        "version": "2.1.0",
+        "$schema": SARIF_SCHEMA,
+        "runs": [
+            {
+                "tool": { "driver": { "name": "flux" } },
+                "columnKind": "unicodeCodePoints",
+                "results": results,
+            },
+        ],
+    })
+}
+
+/// Both formats share SARIF's names for how severe a diagnostic is
+fn severity(is_error: bool) -> &'static str {
+    if is_error {
+        "error"
+    } else {
+        "warning"
+    }
+}
+
+fn lint_name(diagnostic: &Diagnostic) -> Option<&'static str> {
+    Lint::of(diagnostic.code).map(|lint| lint.name())
+}
+
+fn sarif_region(
+    ((start_line, start_column), (end_line, end_column)): ((u32, u32), (u32, u32)),
+) -> Value {
+    json!({
+        "startLine": start_line,
+        "startColumn": start_column,
+        "endLine": end_line,
+        "endColumn": end_column,
+    })
+}
+
+fn position_to_json((line, column): (u32, u32)) -> Value {
+    json!({ "line": line, "column": column })
+}
+
+fn help_to_json<'a>(help: impl IntoIterator<Item = &'a str>) -> Value {
+    help.into_iter().map(Value::from).collect::<Vec<_>>().into()
+}
+
+fn sarif_message(text: &str) -> Value {
+    json!({ "text": text })
+}
+
+fn file_uri(path: &str) -> Value {
+    if path.starts_with('/') {
+        format!("file://{path}").into()
+    } else {
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use flux_util::{FileSpan, InFile, Interner, Span, Spanned};
+    use serde_json::{json, Value};
+
+    use crate::{Diagnostic, DiagnosticCode, SourceCache};
+
+    #[test]
+    fn labels_are_resolved_to_lines_and_columns() {
+        let interner = Box::leak(Box::new(Interner::new()));
+        let mut source_cache = SourceCache::new(interner);
+        let file_id =
+            source_cache.add_input_file("main.flx", "fn main() {\n\t\"é\" + x\n}\n".into());
+        // `x` comes after the two byte `é`, so its column is one less than its byte offset into the line
+        let span = Span::new(20..21);
+        let diagnostic = Diagnostic::error(
+            FileSpan::new(file_id, span),
+            DiagnosticCode::UnknownLocal,
+            "unknown local referenced".into(),
+            vec![InFile::new(
+                Spanned::new("unknown local `x` referenced".into(), span),
+                file_id,
+            )],
+        )
+        .with_help("declare `x` first".into());
+
+        let json = source_cache.diagnostic_to_json(&diagnostic);
+        assert_eq!(json.get("code"), Some(&"E0016".into()));
+        assert_eq!(json.get("severity"), Some(&"error".into()));
+        assert_eq!(json.get("help"), Some(&json!(["declare `x` first"])));
+        let label = &json.get("labels").and_then(Value::as_array).unwrap()[0];
+        assert_eq!(label.pointer("/start/line"), Some(&2.into()));
+        assert_eq!(label.pointer("/start/column"), Some(&8.into()));
+        assert_eq!(label.pointer("/end/column"), Some(&9.into()));
+
+        let sarif = source_cache.diagnostic_to_sarif(&diagnostic);
+        let location = &sarif.get("locations").and_then(Value::as_array).unwrap()[0];
+        assert_eq!(
+            location.pointer("/physicalLocation/region/startColumn"),
+            Some(&8.into())
+        );
+    }
+}
@@ -5,16 +5,24 @@ use ariadne::Source;
 use colored::Colorize;
 use flux_util::FileId;
 use flux_util::Interner;
+use serde_json::Value;
+use std::cell::RefCell;
 use std::collections::HashMap;
 use std::path::PathBuf;
 
 use crate::fmt::Plural;
+use crate::message_format::sarif_log;
+use crate::IOError;
+use crate::MessageFormat;
 
 use super::Diagnostic;
 
 pub struct SourceCache {
     interner: &'static Interner,
     map: HashMap<FileId, Source<String>>,
+    message_format: MessageFormat,
+    /// The results of the SARIF log, which can only be written once everything has been reported
+    sarif_results: RefCell<Vec<Value>>,
 }
 
 impl SourceCache {
@@ -22,33 +30,81 @@ impl SourceCache {
         Self {
             interner,
             map: Default::default(),
+            message_format: MessageFormat::default(),
+            sarif_results: Default::default(),
         }
     }
 
+    pub fn with_message_format(mut self, message_format: MessageFormat) -> Self {
+        self.message_format = message_format;
+        self
+    }
+
     pub fn report_diagnostic(&self, diagnostic: &Diagnostic) {
-        diagnostic
-            .as_report(Config::default())
-            .eprint(self)
-            .unwrap()
+        match self.message_format {
+            MessageFormat::Human => diagnostic
+                .as_report(Config::default())
+                .eprint(self)
+                .unwrap(),
+            MessageFormat::Json => println!("{}", self.diagnostic_to_json(diagnostic)),
+            MessageFormat::Sarif => self
+                .sarif_results
+                .borrow_mut()
+                .push(self.diagnostic_to_sarif(diagnostic)),
+        }
     }
 
     pub fn report_diagnostics<'a>(&self, diagnostics: impl Iterator<Item = &'a Diagnostic>) {
+        // Other tools count the diagnostics themselves
+        if self.message_format != MessageFormat::Human {
+            diagnostics.for_each(|diagnostic| self.report_diagnostic(diagnostic));
+            return;
+        }
+
         let diagnostics: Vec<_> = diagnostics.collect();
-        let count = diagnostics.len();
+        let errors = diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.is_error())
+            .count();
+        let warnings = diagnostics.len() - errors;
         for diagnostic in diagnostics {
             diagnostic
                 .as_report(Config::default())
                 .eprint(self)
                 .unwrap()
         }
-        if count > 0 {
+        if warnings > 0 {
+            eprintln!(
+                "{}",
+                format!("{} warning{} generated", warnings, warnings.plural("s")).yellow()
+            );
+        }
+        if errors > 0 {
             eprintln!(
                 "{}",
-                format!("{} error{} generated", count, count.plural("s")).red()
+                format!("{} error{} generated", errors, errors.plural("s")).red()
             );
         }
     }
 
+    pub fn report_io_error(&self, err: IOError) {
+        match self.message_format {
+            MessageFormat::Human => err.report(),
+            MessageFormat::Json => println!("{}", self.io_error_to_json(&err)),
+            MessageFormat::Sarif => self
+                .sarif_results
+                .borrow_mut()
+                .push(self.io_error_to_sarif(&err)),
+        }
+    }
+
+    /// Write out anything that could only be written once everything has been reported
+    pub fn finish_reporting(&self) {
+        if self.message_format == MessageFormat::Sarif {
+            println!("{}", sarif_log(self.sarif_results.take()));
+        }
+    }
+
     pub fn write_diagnostics_to_buffer<W: std::io::Write>(
         &self,
         diagnostics: &[Diagnostic],
@@ -72,6 +128,24 @@ impl SourceCache {
         id
     }
 
+    /// The 1-based line and column of a byte offset into a file, counting columns in characters
+    pub fn line_col(&self, file_id: &FileId, offset: usize) -> (u32, u32) {
+        let (mut line, mut column, mut bytes) = (1, 1, 0);
+        for c in self.map[file_id].chars() {
+            if bytes >= offset {
+                break;
+            }
+            bytes += c.len_utf8();
+            if c == '\n' {
+                line += 1;
+                column = 1;
+            } else {
+                column += 1;
+            }
+        }
+        (line, column)
+    }
+
     #[inline]
     pub fn get_file_path(&self, file_id: &FileId) -> &str {
         self.interner.resolve(&file_id.key())
@@ -0,0 +1,179 @@
+//! Suggested edits that would fix a diagnostic
+//!
+//! A suggestion replaces the text of a span with something else, and says how sure it is to be what was meant.
+//! `flux fix` applies the ones it can trust without asking anybody.
+
+use flux_util::{FileId, FileSpan};
+
+use crate::Diagnostic;
+
+/// How confident a suggestion is in being the right fix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Applicability {
+    /// Applying it does what was meant, without needing to be looked at
+    MachineApplicable,
+    /// It is probably what was meant, like the name closest to one that was misspelled
+    MaybeIncorrect,
+    /// It contains text that has to be replaced before it compiles
+    HasPlaceholders,
+}
+
+impl Applicability {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub file_span: FileSpan,
+    pub replacement: String,
+    msg: String,
+    pub applicability: Applicability,
+}
+
+impl Suggestion {
+    pub fn new(
+        file_span: FileSpan,
+        replacement: String,
+        msg: String,
+        applicability: Applicability,
+    ) -> Self {
+        Self {
+            file_span,
+            replacement,
+            msg,
+            applicability,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.msg
+    }
+}
+
+/// The candidate closest to `name`, if any is close enough to have been what was meant
+///
+/// Like rustc, a candidate may be a third of the length of `name` edits away from it, but always at least one. Ties go
+/// to the candidate that comes first.
+pub fn closest_name<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// The number of characters that have to be inserted, removed, replaced or swapped with the next one to turn `a` into
+/// `b`, so that `cuont` is as close to `count` as `coun` is
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b): (Vec<char>, Vec<char>) = (a.chars().collect(), b.chars().collect());
+    // `distances[i][j]` is the distance between the first `i` characters of `a` and the first `j` of `b`
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, distance) in distances[0].iter_mut().enumerate() {
+        *distance = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = distances[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = substitution
+                .min(distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+            distances[i][j] = distance;
+        }
+    }
+    distances[a.len()][b.len()]
+}
+
+/// The suggestions of `diagnostics` in `file_id` that are at least as confident as `applicability`
+pub fn applicable_suggestions<'a>(
+    diagnostics: impl IntoIterator<Item = &'a Diagnostic>,
+    file_id: FileId,
+    applicability: Applicability,
+) -> Vec<&'a Suggestion> {
+    diagnostics
+        .into_iter()
+        .flat_map(|diagnostic| diagnostic.suggestions())
+        .filter(|suggestion| {
+            suggestion.file_span.file_id == file_id && suggestion.applicability <= applicability
+        })
+        .collect()
+}
+
+/// Apply suggestions to the source of the file they are in
+///
+/// Suggestions that overlap one applied before them are left out, since the text they would replace has changed. The
+/// number of suggestions that were applied is returned along with the new source.
+pub fn apply_suggestions(source: &str, suggestions: &[&Suggestion]) -> (String, usize) {
+    let mut suggestions = suggestions.to_vec();
+    suggestions.sort_by_key(|suggestion| suggestion.file_span.span.range.start());
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut applied = 0;
+    let mut last_end = 0;
+    for suggestion in suggestions {
+        let range = suggestion.file_span.span.range;
+        let (start, end): (usize, usize) = (range.start().into(), range.end().into());
+        if start < last_end || end > source.len() {
+            continue;
+        }
+        fixed.push_str(&source[last_end..start]);
+        fixed.push_str(&suggestion.replacement);
+        last_end = end;
+        applied += 1;
+    }
+    fixed.push_str(&source[last_end..]);
+    (fixed, applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use flux_util::{FileId, FileSpan, Span};
+
+    use super::*;
+
+    fn suggestion(range: std::ops::Range<u32>, replacement: &str) -> Suggestion {
+        Suggestion::new(
+            FileSpan::new(FileId::poisoned(), Span::new(range)),
+            replacement.to_string(),
+            String::new(),
+            Applicability::MachineApplicable,
+        )
+    }
+
+    #[test]
+    fn closest_names_are_suggested() {
+        let candidates = ["count", "total", "x"];
+        assert_eq!(closest_name("cuont", candidates), Some("count"));
+        assert_eq!(closest_name("totl", candidates), Some("total"));
+        assert_eq!(closest_name("y", candidates), Some("x"));
+        assert_eq!(closest_name("length", candidates), None);
+    }
+
+    #[test]
+    fn overlapping_suggestions_are_skipped() {
+        let source = "let y = x as u64;";
+        let rename = suggestion(8..9, "z");
+        let remove_cast = suggestion(8..16, "x");
+        let rename_local = suggestion(4..5, "w");
+        let (fixed, applied) = apply_suggestions(source, &[&rename, &remove_cast, &rename_local]);
+        assert_eq!(fixed, "let w = z as u64;");
+        assert_eq!(applied, 2);
+    }
+}
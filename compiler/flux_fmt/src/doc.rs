@@ -0,0 +1,283 @@
+/// The column past which the printer tries to break lines
+pub(crate) const MAX_WIDTH: usize = 100;
+
+/// The number of columns a tab is counted as when measuring line width
+const TAB_WIDTH: usize = 4;
+
+/// A layout document, in the style of Wadler's "A prettier printer"
+///
+/// Formatting a file first lowers its syntax tree into a [`Doc`], which describes the text along
+/// with the places it may be broken across lines. The [`Printer`] then picks the line breaks,
+/// breaking the outermost [`Doc::Group`]s first until everything fits within [`MAX_WIDTH`].
+#[derive(Debug, Clone)]
+pub(crate) enum Doc {
+    Text(String),
+    /// A single space, unless at the start of a line
+    Space,
+    /// A space when flat, a newline when broken
+    Line,
+    /// Nothing when flat, a newline when broken
+    SoftLine,
+    /// Always a newline, unless already at the start of a line
+    HardLine,
+    /// A newline followed by at most one empty line
+    BlankLine,
+    /// Forces whatever comes next onto a new line, used after line comments
+    BreakAfter,
+    Concat(Vec<Doc>),
+    /// Indents the lines started inside of it by one tab
+    Nest(Box<Doc>),
+    /// Prints its contents flat if they fit on the current line, otherwise broken
+    Group(Box<Doc>),
+    /// Only printed if the enclosing group is broken
+    IfBreak(Box<Doc>),
+}
+
+impl Doc {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    pub fn concat(docs: impl IntoIterator<Item = Doc>) -> Self {
+        Self::Concat(docs.into_iter().collect())
+    }
+
+    pub fn nest(doc: Doc) -> Self {
+        Self::Nest(Box::new(doc))
+    }
+
+    pub fn group(doc: Doc) -> Self {
+        Self::Group(Box::new(doc))
+    }
+
+    pub fn if_break(doc: Doc) -> Self {
+        Self::IfBreak(Box::new(doc))
+    }
+
+    pub fn nil() -> Self {
+        Self::Concat(vec![])
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Flat,
+    Break,
+}
+
+pub(crate) struct Printer {
+    out: String,
+    col: usize,
+    at_line_start: bool,
+    /// The number of empty lines directly before the current position
+    empty_lines: usize,
+    must_break: bool,
+}
+
+impl Printer {
+    pub fn print(doc: &Doc) -> String {
+        let mut printer = Self {
+            out: String::new(),
+            col: 0,
+            at_line_start: true,
+            empty_lines: 0,
+            must_break: false,
+        };
+        printer.run(doc);
+        printer.trim_trailing_whitespace();
+        let trimmed_len = printer.out.trim_end().len();
+        printer.out.truncate(trimmed_len);
+        printer.out.push('\n');
+        printer.out
+    }
+
+    fn run(&mut self, doc: &Doc) {
+        let mut stack = vec![(0, Mode::Break, doc)];
+        while let Some((indent, mode, doc)) = stack.pop() {
+            match doc {
+                Doc::Text(text) => self.text(indent, text),
+                Doc::Space => {
+                    if !self.at_line_start && !self.must_break {
+                        self.out.push(' ');
+                        self.col += 1;
+                    }
+                }
+                Doc::Line | Doc::SoftLine if mode == Mode::Break => self.newline(),
+                Doc::Line => {
+                    if !self.at_line_start && !self.must_break {
+                        self.out.push(' ');
+                        self.col += 1;
+                    }
+                }
+                Doc::SoftLine => {}
+                Doc::HardLine => {
+                    if !self.at_line_start {
+                        self.newline();
+                    }
+                }
+                Doc::BlankLine => {
+                    if !self.at_line_start {
+                        self.newline();
+                    }
+                    if !self.out.is_empty() && self.empty_lines == 0 {
+                        self.newline();
+                    }
+                }
+                Doc::BreakAfter => self.must_break = true,
+                Doc::Concat(docs) => {
+                    stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc)));
+                }
+                Doc::Nest(doc) => stack.push((indent + 1, mode, doc)),
+                Doc::Group(doc) => {
+                    let mode = if self.fits(indent, doc, &stack) {
+                        Mode::Flat
+                    } else {
+                        Mode::Break
+                    };
+                    stack.push((indent, mode, doc));
+                }
+                Doc::IfBreak(doc) => {
+                    if mode == Mode::Break {
+                        stack.push((indent, mode, doc));
+                    }
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, indent: usize, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if self.must_break {
+            self.newline();
+        }
+        if self.at_line_start {
+            self.out.push_str(&"\t".repeat(indent));
+            self.col = indent * TAB_WIDTH;
+            self.at_line_start = false;
+            self.empty_lines = 0;
+        }
+        self.out.push_str(text);
+        match text.rfind('\n') {
+            Some(idx) => self.col = width(&text[idx + 1..]),
+            None => self.col += width(text),
+        }
+    }
+
+    fn newline(&mut self) {
+        self.trim_trailing_whitespace();
+        if self.at_line_start && !self.out.is_empty() {
+            self.empty_lines += 1;
+        }
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.col = 0;
+        self.at_line_start = true;
+        self.must_break = false;
+    }
+
+    fn trim_trailing_whitespace(&mut self) {
+        let trimmed_len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed_len);
+    }
+
+    /// Whether `doc` fits on the current line when printed flat, along with whatever follows it up
+    /// to the next line break
+    fn fits(&self, indent: usize, doc: &Doc, rest: &[(usize, Mode, &Doc)]) -> bool {
+        let mut col = if self.at_line_start || self.must_break {
+            indent * TAB_WIDTH
+        } else {
+            self.col
+        };
+        let mut stack = vec![(Mode::Flat, doc)];
+        let mut rest = rest.iter().rev();
+        loop {
+            let (mode, doc) = match stack.pop() {
+                Some(next) => next,
+                None => match rest.next() {
+                    Some((_, mode, doc)) => (*mode, *doc),
+                    None => return true,
+                },
+            };
+            match doc {
+                Doc::Text(text) => {
+                    if text.contains('\n') {
+                        return mode == Mode::Break;
+                    }
+                    col += width(text);
+                }
+                Doc::Space => col += 1,
+                Doc::Line if mode == Mode::Flat => col += 1,
+                Doc::SoftLine if mode == Mode::Flat => {}
+                Doc::Line | Doc::SoftLine => return true,
+                Doc::HardLine | Doc::BlankLine | Doc::BreakAfter => return mode == Mode::Break,
+                Doc::Concat(docs) => stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+                Doc::Nest(doc) => stack.push((mode, doc)),
+                Doc::Group(doc) => stack.push((mode, doc)),
+                Doc::IfBreak(doc) => {
+                    if mode == Mode::Break {
+                        stack.push((mode, doc));
+                    }
+                }
+            }
+            if col > MAX_WIDTH {
+                return false;
+            }
+        }
+    }
+}
+
+fn width(text: &str) -> usize {
+    text.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(items: &[&str]) -> Doc {
+        let mut inner = vec![Doc::SoftLine];
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                inner.extend([Doc::text(","), Doc::Line]);
+            }
+            inner.push(Doc::text(*item));
+        }
+        inner.push(Doc::if_break(Doc::text(",")));
+        Doc::group(Doc::concat([
+            Doc::text("("),
+            Doc::nest(Doc::concat(inner)),
+            Doc::SoftLine,
+            Doc::text(")"),
+        ]))
+    }
+
+    #[test]
+    fn groups_stay_flat_when_they_fit() {
+        assert_eq!(Printer::print(&list(&["a", "b"])), "(a, b)\n");
+    }
+
+    #[test]
+    fn groups_break_when_too_wide() {
+        let item = "x".repeat(60);
+        assert_eq!(
+            Printer::print(&list(&[&item, &item])),
+            format!("(\n\t{item},\n\t{item},\n)\n")
+        );
+    }
+
+    #[test]
+    fn blank_lines_collapse() {
+        let doc = Doc::concat([
+            Doc::text("a"),
+            Doc::BlankLine,
+            Doc::BlankLine,
+            Doc::text("b"),
+        ]);
+        assert_eq!(Printer::print(&doc), "a\n\nb\n");
+    }
+}
@@ -0,0 +1,543 @@
+use std::collections::{HashMap, VecDeque};
+
+use cstree::{
+    syntax::SyntaxElementRef,
+    text::{TextRange, TextSize},
+    util::NodeOrToken,
+};
+use flux_parser::syntax::{SyntaxKind, SyntaxNode, SyntaxToken};
+
+use crate::doc::Doc;
+
+type Element<'a> = SyntaxElementRef<'a, SyntaxKind>;
+
+/// The newlines around a token, ignoring the whitespace tokens in between
+#[derive(Debug, Clone, Copy, Default)]
+struct Newlines {
+    before: usize,
+    after: usize,
+}
+
+#[derive(Debug)]
+struct Comment {
+    text: String,
+    /// Whether the comment starts its own line, rather than following other code
+    own_line: bool,
+    blank_before: bool,
+    /// Whether whatever comes after the comment has to start on a new line
+    break_after: bool,
+}
+
+/// A child of a node, along with the comments attached to it
+///
+/// Comments on their own line belong to the element after them, and comments following code on
+/// the same line belong to the element before them.
+#[derive(Debug)]
+struct Elem<'a> {
+    element: Element<'a>,
+    leading: Vec<Comment>,
+    trailing: Vec<Comment>,
+    blank_before: bool,
+}
+
+impl<'a> Elem<'a> {
+    fn kind(&self) -> SyntaxKind {
+        self.element.kind()
+    }
+
+    /// Take over the comments of an element that isn't printed, such as a comma that is dropped
+    fn absorb(&mut self, other: Elem<'a>) {
+        self.trailing.extend(other.leading);
+        self.trailing.extend(other.trailing);
+    }
+}
+
+/// The children of a node that haven't been laid out yet
+struct Children<'a> {
+    elems: VecDeque<Elem<'a>>,
+    /// Comments after the last child
+    dangling: Vec<Comment>,
+    last: Option<SyntaxKind>,
+    /// Whether the last child taken had comments after it on the same line
+    last_has_trailing: bool,
+}
+
+impl<'a> Children<'a> {
+    fn peek(&self) -> Option<SyntaxKind> {
+        self.elems.front().map(Elem::kind)
+    }
+
+    fn next(&mut self) -> Option<Elem<'a>> {
+        let elem = self.elems.pop_front()?;
+        self.last = Some(elem.kind());
+        self.last_has_trailing = !elem.trailing.is_empty();
+        Some(elem)
+    }
+
+    fn eat(&mut self, kind: SyntaxKind) -> Option<Elem<'a>> {
+        if self.peek() == Some(kind) {
+            self.next()
+        } else {
+            None
+        }
+    }
+
+    /// Take the elements up to the closing delimiter, along with the commas separating them
+    fn comma_separated(&mut self, close: SyntaxKind) -> Vec<Elem<'a>> {
+        let mut items: Vec<Elem<'a>> = vec![];
+        while let Some(kind) = self.peek() {
+            if kind == close {
+                break;
+            }
+            let mut item = self.next().unwrap();
+            if kind == SyntaxKind::Comma {
+                match items.last_mut() {
+                    Some(prev) => prev.absorb(item),
+                    None => items.push(item),
+                }
+                continue;
+            }
+            if let Some(comma) = self.eat(SyntaxKind::Comma) {
+                item.absorb(comma);
+            }
+            items.push(item);
+        }
+        items
+    }
+}
+
+/// Lays out a syntax tree as a [`Doc`]
+pub(crate) struct Formatter<'src> {
+    src: &'src str,
+    newlines: HashMap<TextSize, Newlines>,
+}
+
+impl<'src> Formatter<'src> {
+    pub fn new(src: &'src str, root: &SyntaxNode) -> Self {
+        let mut tokens: Vec<(TextSize, Newlines)> = vec![];
+        let mut pending = 0;
+        for token in root
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+        {
+            let text = &src[token.text_range()];
+            if token.kind() == SyntaxKind::Whitespace {
+                pending += text.matches('\n').count();
+                continue;
+            }
+            // The first token of a file always starts its own line
+            let before = if tokens.is_empty() {
+                pending.max(1)
+            } else {
+                pending
+            };
+            if let Some((_, prev)) = tokens.last_mut() {
+                prev.after = pending;
+            }
+            tokens.push((token.text_range().start(), Newlines { before, after: 1 }));
+            pending = 0;
+        }
+        Self {
+            src,
+            newlines: tokens.into_iter().collect(),
+        }
+    }
+
+    pub fn root(&self, root: &SyntaxNode) -> Doc {
+        self.node(root, Doc::nil())
+    }
+
+    fn text(&self, range: TextRange) -> &'src str {
+        &self.src[range]
+    }
+
+    fn newlines(&self, token: &SyntaxToken) -> Newlines {
+        self.newlines
+            .get(&token.text_range().start())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn children<'a>(&self, node: &'a SyntaxNode) -> Children<'a> {
+        let mut elems: VecDeque<Elem<'a>> = VecDeque::new();
+        let mut pending = vec![];
+        for element in node.children_with_tokens() {
+            match element.kind() {
+                SyntaxKind::Whitespace => {}
+                SyntaxKind::Comment => {
+                    let comment = self.comment(element.into_token().unwrap());
+                    match elems.back_mut() {
+                        Some(prev) if !comment.own_line && pending.is_empty() => {
+                            prev.trailing.push(comment)
+                        }
+                        _ => pending.push(comment),
+                    }
+                }
+                _ => {
+                    // Nodes like an empty `Visibility` have nothing to print
+                    let Some(first) = first_token(element) else {
+                        continue;
+                    };
+                    elems.push_back(Elem {
+                        element,
+                        leading: std::mem::take(&mut pending),
+                        trailing: vec![],
+                        blank_before: self.newlines(first).before >= 2,
+                    });
+                }
+            }
+        }
+        Children {
+            elems,
+            dangling: pending,
+            last: None,
+            last_has_trailing: false,
+        }
+    }
+
+    fn comment(&self, token: &SyntaxToken) -> Comment {
+        let text = self.text(token.text_range());
+        let newlines = self.newlines(token);
+        Comment {
+            text: text.trim_end().to_string(),
+            own_line: newlines.before > 0,
+            blank_before: newlines.before >= 2,
+            break_after: text.starts_with("//") || newlines.after > 0,
+        }
+    }
+
+    fn comment_doc(&self, comment: &Comment, allow_blank: bool) -> Doc {
+        let before = match (comment.own_line, allow_blank && comment.blank_before) {
+            (true, true) => Doc::BlankLine,
+            (true, false) => Doc::HardLine,
+            (false, _) => Doc::Space,
+        };
+        let after = if comment.break_after {
+            Doc::BreakAfter
+        } else {
+            Doc::nil()
+        };
+        Doc::concat([before, Doc::text(&comment.text), after])
+    }
+
+    fn elem(&self, elem: Elem) -> Doc {
+        self.elem_with(elem, Doc::nil(), false)
+    }
+
+    /// Lay out an element, putting `suffix` right after it but before the comments trailing it
+    ///
+    /// Elements laid out in a sequence, such as statements in a block, keep the blank lines between
+    /// their leading comments.
+    fn elem_with(&self, elem: Elem, suffix: Doc, in_sequence: bool) -> Doc {
+        let mut docs: Vec<_> = elem
+            .leading
+            .iter()
+            .enumerate()
+            .map(|(idx, comment)| self.comment_doc(comment, in_sequence && idx > 0))
+            .collect();
+        if in_sequence && elem.blank_before && !elem.leading.is_empty() {
+            docs.push(Doc::BlankLine);
+        }
+        match elem.element {
+            NodeOrToken::Node(node) => docs.push(self.node(node, suffix)),
+            NodeOrToken::Token(token) => {
+                docs.push(Doc::text(self.text(token.text_range())));
+                docs.push(suffix);
+            }
+        }
+        docs.extend(
+            elem.trailing
+                .iter()
+                .map(|comment| self.comment_doc(comment, false)),
+        );
+        Doc::concat(docs)
+    }
+
+    /// Lay out the comments before an element, leaving the element itself to be laid out separately
+    fn take_leading(&self, elem: &mut Elem, allow_blank: bool) -> Vec<Doc> {
+        std::mem::take(&mut elem.leading)
+            .iter()
+            .enumerate()
+            .map(|(idx, comment)| self.comment_doc(comment, allow_blank && idx > 0))
+            .collect()
+    }
+
+    fn node(&self, node: &SyntaxNode, suffix: Doc) -> Doc {
+        let mut c = self.children(node);
+        let kind = node.kind();
+        let mut docs = vec![];
+        match kind {
+            SyntaxKind::Root => docs.extend(self.lines(&mut c, |_| false)),
+            SyntaxKind::BlockExpr => {
+                docs.extend(c.next().map(|open| self.elem(open)));
+                docs.push(self.braced(&mut c, |_| false));
+            }
+            SyntaxKind::MatchArmList => {
+                docs.extend(c.next().map(|open| self.elem(open)));
+                // Like in Rust, arms whose body is a block don't need a comma after them
+                docs.push(self.braced(&mut c, |arm| {
+                    let body = arm
+                        .element
+                        .as_node()
+                        .and_then(|arm| arm.children().last())
+                        .and_then(|body| first_token(NodeOrToken::Node(body)));
+                    body.is_none_or(|token| token.kind() != SyntaxKind::LBrace)
+                }));
+            }
+            SyntaxKind::StructDeclFieldList => docs.push(self.braced(&mut c, |_| true)),
+            SyntaxKind::EnumDecl => {
+                docs.push(self.header(kind, &mut c));
+                docs.push(self.braced(&mut c, |_| true));
+            }
+            SyntaxKind::TraitDecl | SyntaxKind::ApplyDecl => {
+                docs.push(self.header(kind, &mut c));
+                docs.push(self.braced(&mut c, |_| false));
+            }
+            SyntaxKind::ArgList
+            | SyntaxKind::ParamList
+            | SyntaxKind::GenericParamList
+            | SyntaxKind::GenericArgList
+            | SyntaxKind::TupleExpr
+            | SyntaxKind::TuplePat
+            | SyntaxKind::TupleType
+            | SyntaxKind::StructExprFieldList
+            | SyntaxKind::StructPatFieldList => docs.push(self.delimited(kind, &mut c)),
+            SyntaxKind::VariantPat => {
+                let mut path = vec![];
+                while c.peek().is_some_and(|kind| kind != SyntaxKind::LParen) {
+                    path.push(c.next().unwrap());
+                }
+                docs.push(self.join(kind, path, None, Doc::nil()));
+                if c.peek().is_some() {
+                    docs.push(self.delimited(kind, &mut c));
+                }
+            }
+            SyntaxKind::BinExpr => {
+                let lhs = c.next().map(|lhs| self.elem(lhs));
+                let op = c.next().map(|op| self.elem(op));
+                let rhs = self.join(kind, c.elems.drain(..).collect(), None, Doc::nil());
+                docs.push(Doc::group(Doc::concat([
+                    lhs.unwrap_or_else(Doc::nil),
+                    Doc::nest(Doc::concat([
+                        Doc::Line,
+                        op.unwrap_or_else(Doc::nil),
+                        Doc::Space,
+                        rhs,
+                    ])),
+                ])));
+            }
+            SyntaxKind::EnumDeclVariant => {
+                // The comma after a variant without a payload is part of it, but it is laid out
+                // along with the other commas by the enum
+                let mut elems: Vec<Elem> = vec![];
+                for elem in c.elems.drain(..) {
+                    match elems.last_mut() {
+                        Some(prev) if elem.kind() == SyntaxKind::Comma => prev.absorb(elem),
+                        _ => elems.push(elem),
+                    }
+                }
+                c.elems.extend(elems);
+            }
+            _ => {}
+        }
+        // Whatever wasn't laid out above is joined as it is
+        let prev = c.last;
+        docs.push(self.join(kind, c.elems.drain(..).collect(), prev, suffix));
+        docs.extend(
+            c.dangling
+                .iter()
+                .map(|comment| self.comment_doc(comment, true)),
+        );
+        Doc::concat(docs)
+    }
+
+    /// Lay out elements on a single line, with spaces between them where the language needs or
+    /// conventionally has them
+    fn join(
+        &self,
+        parent: SyntaxKind,
+        elems: Vec<Elem>,
+        mut prev: Option<SyntaxKind>,
+        suffix: Doc,
+    ) -> Doc {
+        let len = elems.len();
+        if len == 0 {
+            return suffix;
+        }
+        let mut docs = vec![];
+        let mut suffix = Some(suffix);
+        for (idx, elem) in elems.into_iter().enumerate() {
+            let kind = elem.kind();
+            if prev.is_some_and(|prev| spaced(parent, prev, kind)) {
+                docs.push(Doc::Space);
+            }
+            let suffix = if idx + 1 == len {
+                suffix.take().unwrap()
+            } else {
+                Doc::nil()
+            };
+            docs.push(self.elem_with(elem, suffix, false));
+            prev = Some(kind);
+        }
+        Doc::concat(docs)
+    }
+
+    /// Lay out the beginning of an item up to and including the `{` opening its body
+    fn header(&self, parent: SyntaxKind, c: &mut Children) -> Doc {
+        let mut elems = vec![];
+        while let Some(elem) = c.next() {
+            let kind = elem.kind();
+            elems.push(elem);
+            if kind == SyntaxKind::LBrace {
+                break;
+            }
+        }
+        self.join(parent, elems, None, Doc::nil())
+    }
+
+    /// Lay out elements one per line, keeping single blank lines between them
+    fn lines(&self, c: &mut Children, wants_comma: impl Fn(&Elem) -> bool) -> Vec<Doc> {
+        let mut docs = vec![];
+        while c.peek().is_some_and(|kind| kind != SyntaxKind::RBrace) {
+            let mut elem = c.next().unwrap();
+            let blank = elem
+                .leading
+                .first()
+                .map_or(elem.blank_before, |comment| comment.blank_before);
+            if !docs.is_empty() && blank {
+                docs.push(Doc::BlankLine);
+            } else {
+                docs.push(Doc::HardLine);
+            }
+            let suffix = if wants_comma(&elem) {
+                Doc::text(",")
+            } else {
+                Doc::nil()
+            };
+            if let Some(comma) = c.eat(SyntaxKind::Comma) {
+                elem.absorb(comma);
+            }
+            docs.push(self.elem_with(elem, suffix, true));
+        }
+        docs
+    }
+
+    /// Lay out the members of a body after its `{` one per line, followed by the closing `}`
+    fn braced(&self, c: &mut Children, wants_comma: impl Fn(&Elem) -> bool) -> Doc {
+        let open_has_trailing = c.last_has_trailing;
+        let mut body = self.lines(c, wants_comma);
+        let Some(mut close) = c.eat(SyntaxKind::RBrace) else {
+            return Doc::nest(Doc::concat(body));
+        };
+        let allow_blank = !body.is_empty();
+        body.extend(self.take_leading(&mut close, allow_blank));
+        if body.is_empty() {
+            // Keeps a comment after an empty `{` from running into the `}`
+            let space = if open_has_trailing {
+                Doc::Space
+            } else {
+                Doc::nil()
+            };
+            return Doc::concat([space, self.elem(close)]);
+        }
+        Doc::concat([
+            Doc::nest(Doc::concat(body)),
+            Doc::HardLine,
+            self.elem(close),
+        ])
+    }
+
+    /// Lay out a delimited, comma separated list on one line if it fits, otherwise with each element
+    /// on its own line
+    fn delimited(&self, parent: SyntaxKind, c: &mut Children) -> Doc {
+        let Some(open) = c.next() else {
+            return Doc::nil();
+        };
+        let close = match open.kind() {
+            SyntaxKind::LParen => SyntaxKind::RParen,
+            SyntaxKind::LBrace => SyntaxKind::RBrace,
+            SyntaxKind::CmpLt => SyntaxKind::CmpGt,
+            _ => return self.elem(open),
+        };
+        // Struct literals are padded with spaces, and stay broken if they were written that way
+        let braced = close == SyntaxKind::RBrace;
+        let preserve_break =
+            braced && first_token(open.element).is_some_and(|token| self.newlines(token).after > 0);
+        let line = if braced { Doc::Line } else { Doc::SoftLine };
+
+        let items = c.comma_separated(close);
+        let mut close = c.eat(close);
+        let close_comments = close
+            .as_mut()
+            .map(|close| self.take_leading(close, false))
+            .unwrap_or_default();
+        let open = self.elem(open);
+        let close = close.map_or_else(Doc::nil, |close| self.elem(close));
+        if items.is_empty() && close_comments.is_empty() {
+            return Doc::concat([open, close]);
+        }
+
+        // A tuple of one element needs its comma to not be mistaken for parentheses
+        let keep_comma =
+            matches!(parent, SyntaxKind::TupleExpr | SyntaxKind::TuplePat) && items.len() == 1;
+        let len = items.len();
+        let mut inner = vec![line.clone()];
+        for (idx, item) in items.into_iter().enumerate() {
+            if idx > 0 {
+                inner.push(Doc::Line);
+            }
+            let suffix = if idx + 1 < len || keep_comma {
+                Doc::text(",")
+            } else if parent == SyntaxKind::ParamList {
+                // Parameter lists don't allow a trailing comma
+                Doc::nil()
+            } else {
+                Doc::if_break(Doc::text(","))
+            };
+            inner.push(self.elem_with(item, suffix, false));
+        }
+        inner.extend(close_comments);
+        let force_break = if preserve_break {
+            Doc::BreakAfter
+        } else {
+            Doc::nil()
+        };
+        Doc::group(Doc::concat([
+            open,
+            force_break,
+            Doc::nest(Doc::concat(inner)),
+            line,
+            close,
+        ]))
+    }
+}
+
+fn first_token(element: Element<'_>) -> Option<&SyntaxToken> {
+    match element {
+        NodeOrToken::Node(node) => node
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+            .find(|token| !matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment)),
+        NodeOrToken::Token(token) => Some(token),
+    }
+}
+
+/// Whether there is a space between two elements next to each other in a node
+fn spaced(parent: SyntaxKind, prev: SyntaxKind, next: SyntaxKind) -> bool {
+    use SyntaxKind::*;
+    match (prev, next) {
+        (
+            _,
+            Comma | SemiColon | Period | DoubleColon | GenericParamList | GenericArgList
+            | ParamList | ArgList | RParen | RSquare,
+        ) => false,
+        (Period | DoubleColon | LParen | LSquare, _) => false,
+        (LBrace, StructDeclFieldList) => false,
+        (_, LSquare) => parent != IdxExpr,
+        (_, Colon) => !matches!(parent, LoopLabel | StructExprField | StructPatField),
+        (_, Star | Ampersand | CmpAnd) if matches!(parent, PtrType | RefType) => false,
+        (Star | Ampersand | CmpAnd, _) if matches!(parent, AddressExpr | DerefExpr) => false,
+        (_, LParen) => !matches!(parent, VariantPat | ParenExpr | ParenPat),
+        _ => true,
+    }
+}
@@ -0,0 +1,262 @@
+use cstree::util::NodeOrToken;
+use doc::Printer;
+use flux_diagnostics::Diagnostic;
+use flux_parser::syntax::{SyntaxKind, SyntaxNode};
+use flux_util::{FileId, Interner};
+use layout::Formatter;
+
+mod doc;
+mod layout;
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// The file doesn't parse, so there is nothing to format
+    Syntax(Vec<Diagnostic>),
+    /// The formatted file doesn't parse to the same tree as the original, which is a bug in the
+    /// formatter rather than the file
+    ChangedMeaning,
+}
+
+/// Format a flux source file in the canonical style
+///
+/// Lines are indented with tabs and wrapped at 100 columns where possible, and comments are kept
+/// where they were written. Formatting is idempotent: formatting the output again changes nothing.
+pub fn format(
+    src: &str,
+    file_id: FileId,
+    interner: &'static Interner,
+) -> Result<String, FormatError> {
+    let parse = flux_parser::parse(src, file_id, interner);
+    if !parse.diagnostics.is_empty() {
+        return Err(FormatError::Syntax(parse.diagnostics));
+    }
+    let root = parse.syntax();
+    let formatted = Printer::print(&Formatter::new(src, &root).root(&root));
+
+    // Only whitespace and commas should ever change, so anything else means the layout is wrong
+    let reparse = flux_parser::parse(&formatted, file_id, interner);
+    if !reparse.diagnostics.is_empty() || shape(&root, src) != shape(&reparse.syntax(), &formatted)
+    {
+        return Err(FormatError::ChangedMeaning);
+    }
+    Ok(formatted)
+}
+
+#[derive(Debug, PartialEq)]
+enum Shape<'a> {
+    Node(SyntaxKind),
+    End,
+    Token(&'a str),
+}
+
+/// Everything about a tree that formatting has to keep the same
+fn shape<'a>(root: &SyntaxNode, src: &'a str) -> (Vec<Shape<'a>>, Vec<&'a str>) {
+    let mut tree = vec![];
+    let mut comments = vec![];
+    for event in root.preorder_with_tokens() {
+        match event {
+            cstree::traversal::WalkEvent::Enter(NodeOrToken::Node(node)) => {
+                tree.push(Shape::Node(node.kind()))
+            }
+            cstree::traversal::WalkEvent::Leave(NodeOrToken::Node(_)) => tree.push(Shape::End),
+            cstree::traversal::WalkEvent::Enter(NodeOrToken::Token(token)) => {
+                let text = &src[token.text_range()];
+                match token.kind() {
+                    SyntaxKind::Whitespace | SyntaxKind::Comma => {}
+                    SyntaxKind::Comment => comments.push(text.trim_end()),
+                    _ => tree.push(Shape::Token(text)),
+                }
+            }
+            cstree::traversal::WalkEvent::Leave(NodeOrToken::Token(_)) => {}
+        }
+    }
+    (tree, comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, sync::OnceLock};
+
+    use flux_util::{FileId, Interner};
+
+    use super::*;
+
+    fn interner() -> &'static Interner {
+        static INTERNER: OnceLock<Interner> = OnceLock::new();
+        INTERNER.get_or_init(Interner::new)
+    }
+
+    fn fmt(src: &str) -> String {
+        let interner = interner();
+        let file_id = FileId::new(interner.get_or_intern("test.flx"));
+        match format(src, file_id, interner) {
+            Ok(formatted) => formatted,
+            Err(err) => panic!("could not format:\n{src}\n{err:?}"),
+        }
+    }
+
+    fn flx_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                flx_files(&path, files);
+            } else if path.extension().is_some_and(|ext| ext == "flx") {
+                files.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn repo_sources_are_formatted() {
+        let root = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../.."));
+        let mut files = vec![];
+        flx_files(&root.join("std"), &mut files);
+        flx_files(&root.join("examples"), &mut files);
+        assert!(!files.is_empty());
+        for path in files {
+            let src = fs::read_to_string(&path).unwrap();
+            assert_eq!(fmt(&src), src, "`{}` is not formatted", path.display());
+        }
+    }
+
+    #[test]
+    fn formats_items() {
+        let src = "pub   fn add<T>(a T,b T)->T where T is Add{a+b}
+struct Foo<T>{x T,y (T,s32)}
+enum Option<T>{Some->T,None}
+trait Iterator{type Item;fn next(this This&)->Option<This::Item>;}
+apply<T> Iterator to Foo<T>{type Item=T;
+fn next(this This&)->Option<T> =>Option::None
+}
+use std::cmp::Eq as Equal;
+mod foo;
+";
+        assert_eq!(
+            fmt(src),
+            "pub fn add<T>(a T, b T) -> T where T is Add {
+	a + b
+}
+struct Foo<T> {
+	x T,
+	y (T, s32),
+}
+enum Option<T> {
+	Some -> T,
+	None,
+}
+trait Iterator {
+	type Item;
+	fn next(this This&) -> Option<This::Item>;
+}
+apply<T> Iterator to Foo<T> {
+	type Item = T;
+	fn next(this This&) -> Option<T> => Option::None
+}
+use std::cmp::Eq as Equal;
+mod foo;
+"
+        );
+    }
+
+    #[test]
+    fn formats_exprs() {
+        let src = "fn main() {
+let p = Point{x:1,y:&*q};
+let t = (1,);
+    if a==b{foo(x,y)}else if c{} else{ bar() }
+  'outer: while i!=10 { i = i+1; break 'outer; }
+match o { Option::Some(x)=>x, Option::None=>{0} _=>1 }
+    arr[0] as u8*
+}
+";
+        assert_eq!(
+            fmt(src),
+            "fn main() {
+	let p = Point { x: 1, y: &*q };
+	let t = (1,);
+	if a == b {
+		foo(x, y)
+	} else if c {} else {
+		bar()
+	}
+	'outer: while i != 10 {
+		i = i + 1;
+		break 'outer;
+	}
+	match o {
+		Option::Some(x) => x,
+		Option::None => {
+			0
+		}
+		_ => 1,
+	}
+	arr[0] as u8*
+}
+"
+        );
+    }
+
+    #[test]
+    fn wraps_long_lines() {
+        let src = "fn main() {
+	some_function(first_argument_value, second_argument_value, third_argument_value, fourth_argument_value);
+}
+";
+        assert_eq!(
+            fmt(src),
+            "fn main() {
+	some_function(
+		first_argument_value,
+		second_argument_value,
+		third_argument_value,
+		fourth_argument_value,
+	);
+}
+"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        let src = "// The entry point
+fn main() {
+	let x = 1; // one
+
+
+	// two
+	let y = 2;
+	/* done */
+}
+
+/*
+ * trailing
+ */
+";
+        assert_eq!(
+            fmt(src),
+            "// The entry point
+fn main() {
+	let x = 1; // one
+
+	// two
+	let y = 2;
+	/* done */
+}
+
+/*
+ * trailing
+ */
+"
+        );
+    }
+
+    #[test]
+    fn refuses_files_with_syntax_errors() {
+        let interner = interner();
+        let file_id = FileId::new(interner.get_or_intern("test.flx"));
+        assert!(matches!(
+            format("fn main( {", file_id, interner),
+            Err(FormatError::Syntax(_))
+        ));
+    }
+}
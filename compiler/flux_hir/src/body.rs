@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use flux_id::{id, Map};
+use flux_typesystem::{TypeKind, Typed};
+use flux_util::{FileId, Span, Spanned};
+use serde::{Deserialize, Serialize};
+
+use crate::def::{expr::Expr, pat::Pat};
+
+/// The type checked expression bodies of every function in a package
+///
+/// The `tid`s stored alongside each expression refer to the type environment of the function they were lowered in,
+/// which does not outlive lowering. The types they resolved to are kept in `types` instead.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PackageBodies {
+    pub exprs: Map<id::Expr, Typed<Expr>>,
+    /// The resolved type of every expression, indexed by the same ids as `exprs`
+    ///
+    /// Expressions whose types could not be resolved are [`TypeKind::Unknown`]
+    pub types: Map<id::Expr, Spanned<TypeKind>>,
+    /// Where each expression was written in the source
+    ///
+    /// The spans of `types` are those of the types the expressions were given, which may have been declared somewhere
+    /// else entirely, as the types of locals are. Expressions introduced during lowering, like the calls `for` loops
+    /// are turned into, have no span of their own.
+    pub expr_spans: HashMap<id::Expr, Span>,
+    pub pats: Map<id::Pat, Typed<Pat>>,
+    /// The resolved type of every pattern, indexed by the same ids as `pats`
+    pub pat_types: Map<id::Pat, Spanned<TypeKind>>,
+    pub fn_bodies: HashMap<id::FnDecl, FnBody>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FnBody {
+    pub mod_id: id::Mod,
+    pub file_id: FileId,
+    pub expr: id::Expr,
+    first_expr: id::Expr,
+}
+
+impl FnBody {
+    pub(crate) fn new(
+        mod_id: id::Mod,
+        file_id: FileId,
+        expr: id::Expr,
+        first_expr: id::Expr,
+    ) -> Self {
+        Self {
+            mod_id,
+            file_id,
+            expr,
+            first_expr,
+        }
+    }
+
+    /// Every expression in the body
+    ///
+    /// The expressions of a body are allocated one after the other while it is lowered, ending with `expr`.
+    pub fn exprs(&self) -> impl Iterator<Item = id::Expr> {
+        (Into::<u32>::into(self.first_expr)..=self.expr.into()).map(id::Expr::from)
+    }
+}
+
+impl PackageBodies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move the bodies lowered into `other` into these ones, after those already here
+    pub fn append(&mut self, other: &PackageBodies) {
+        let expr_offset = self.exprs.len() as u32;
+        let pat_offset = self.pats.len() as u32;
+        let offset_expr = |expr: id::Expr| id::Expr::from(Into::<u32>::into(expr) + expr_offset);
+
+        for (_, expr) in other.exprs.iter() {
+            let mut expr = expr.clone();
+            expr.inner.offset_ids(expr_offset, pat_offset);
+            self.exprs.insert(expr);
+        }
+        for (_, ty) in other.types.iter() {
+            self.types.insert(ty.clone());
+        }
+        self.expr_spans.extend(
+            other
+                .expr_spans
+                .iter()
+                .map(|(expr, span)| (offset_expr(*expr), *span)),
+        );
+        for (_, pat) in other.pats.iter() {
+            let mut pat = pat.clone();
+            pat.inner.offset_ids(pat_offset);
+            self.pats.insert(pat);
+        }
+        for (_, ty) in other.pat_types.iter() {
+            self.pat_types.insert(ty.clone());
+        }
+        self.fn_bodies
+            .extend(other.fn_bodies.iter().map(|(fn_id, fn_body)| {
+                let fn_body = FnBody {
+                    expr: offset_expr(fn_body.expr),
+                    first_expr: offset_expr(fn_body.first_expr),
+                    ..*fn_body
+                };
+                (*fn_id, fn_body)
+            }));
+    }
+
+    /// Move the spans of every expression and pattern with `relocate`, for when the source the bodies were lowered from
+    /// has moved
+    ///
+    /// The spans of `types` and `pat_types` are left alone, since they may not even be in the same file.
+    pub(crate) fn relocate_spans(&mut self, relocate: impl Fn(Span) -> Span) {
+        for expr in self.exprs.as_mut() {
+            expr.inner.relocate_spans(&relocate);
+        }
+        for span in self.expr_spans.values_mut() {
+            *span = relocate(*span);
+        }
+        for pat in self.pats.as_mut() {
+            pat.inner.relocate_spans(&relocate);
+        }
+    }
+
+    pub fn get_fn_body(&self, fn_id: id::FnDecl) -> Option<&FnBody> {
+        self.fn_bodies.get(&fn_id)
+    }
+
+    pub fn get_expr(&self, expr: id::Expr) -> &Typed<Expr> {
+        self.exprs.get(expr)
+    }
+
+    pub fn get_type(&self, expr: id::Expr) -> &Spanned<TypeKind> {
+        self.types.get(expr)
+    }
+
+    pub fn get_pat(&self, pat: id::Pat) -> &Typed<Pat> {
+        self.pats.get(pat)
+    }
+
+    pub fn get_pat_type(&self, pat: id::Pat) -> &Spanned<TypeKind> {
+        self.pat_types.get(pat)
+    }
+}
@@ -3,10 +3,11 @@ use std::{collections::HashMap, sync::OnceLock};
 use flux_diagnostics::ice;
 use flux_typesystem::Type;
 use flux_util::{Interner, Path, Word};
+use serde::{Deserialize, Serialize};
 
 use crate::def::expr::Op;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(super) enum BuiltinType {
     UInt(BuiltinUInt),
     SInt(BuiltinSInt),
@@ -15,7 +16,7 @@ pub(super) enum BuiltinType {
     Bool,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(super) enum BuiltinUInt {
     U64,
     U32,
@@ -23,7 +24,7 @@ pub(super) enum BuiltinUInt {
     U8,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(super) enum BuiltinSInt {
     S64,
     S32,
@@ -31,7 +32,7 @@ pub(super) enum BuiltinSInt {
     S8,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub(super) enum BuiltinFloat {
     F64,
     F32,
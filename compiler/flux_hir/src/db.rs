@@ -0,0 +1,756 @@
+//! The queries packages are built with
+//!
+//! Files are parsed once per change, package definitions are rebuilt from the parsed files, and function bodies are
+//! lowered and type checked one at a time. A body is only lowered again when its own text changes, or when something
+//! it could refer to does: the declarations of any package, which are fingerprinted by [`Signatures`].
+//!
+//! Parsing a file and lowering a body don't need the database once their inputs have been read, so [`ParseFile`] and
+//! [`LowerFnBody`] are parallel queries, and many of them can be computed at once on a thread pool.
+//!
+//! Declarations are fingerprinted by their text alone, so an edit that only moves them, like adding a line to a function
+//! above them, doesn't lower anything but the edited body again. The bodies that were lowered before the edit still
+//! have the spans they had then, so [`PackageBodiesQuery`] moves them to where their text is now, using the
+//! [`Positions`] every body was lowered at.
+
+use std::{
+    cell::RefCell,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
+
+use cstree::{green::GreenNode, util::NodeOrToken};
+use flux_db::{Input, ParallelQuery, Query, Shared};
+use flux_diagnostics::{Diagnostic, SourceCache};
+use flux_id::{
+    id::{self, WithPackage},
+    Map,
+};
+use flux_parser::{
+    ast::{self, AstNode},
+    syntax::{SyntaxKind, SyntaxNode},
+    Parse,
+};
+use flux_typesystem::{MethodResolver, TraitResolver};
+use flux_util::{FileId, Interner, Span, Word};
+
+use crate::{
+    build_resolvers,
+    item::ItemTreeIdx,
+    lower::{lower_function_body, report_unused_private_functions, LoweredFnBody, LoweringCtx},
+    name_res::item::ItemResolver,
+    prelude::PRELUDE_SRC,
+    Config, FileResolver, Package, PackageBodies, PkgBuilder, RelativePath,
+};
+
+/// What every query has access to without depending on it
+///
+/// Changing any of it means starting over with a new database.
+pub struct Context {
+    pub interner: &'static Interner,
+    pub config: Config,
+}
+
+pub type Database = flux_db::Database<Context>;
+
+/// The text of a file, which is read from disk until it is set
+///
+/// Files that don't exist have no text.
+pub struct FileText;
+
+impl Input for FileText {
+    type Context = Context;
+    type Key = FileId;
+    type Value = Option<Arc<str>>;
+
+    fn load(context: &Context, file_id: &FileId) -> Self::Value {
+        if *file_id == FileId::prelude(context.interner) {
+            return Some(PRELUDE_SRC.into());
+        }
+        std::fs::read_to_string(file_id.as_str(context.interner))
+            .ok()
+            .map(Arc::from)
+    }
+}
+
+/// A package that is part of the project, along with the packages it depends on
+#[derive(Debug, Clone, PartialEq, Hash)]
+pub struct PackageSource {
+    pub name: Word,
+    pub entry: FileId,
+    pub dependencies: Vec<id::Pkg>,
+}
+
+/// Every package in the project, where the id of a package is its position in the list
+pub struct Project;
+
+impl Input for Project {
+    type Context = Context;
+    type Key = ();
+    type Value = Arc<[PackageSource]>;
+
+    fn load(_: &Context, _: &()) -> Self::Value {
+        Arc::new([])
+    }
+}
+
+/// A package built by an earlier invocation of the compiler, bodies and all
+#[derive(Debug)]
+pub struct PrebuiltPackage {
+    pub package: Package,
+    /// The files of every module in the package, starting with its entry
+    pub files: Vec<FileId>,
+    /// What the package was built from, which stands in for its declarations in [`Signatures`]
+    pub fingerprint: u64,
+}
+
+/// The packages that don't need to be built, since they already have been
+///
+/// A prebuilt package is never parsed or lowered, so it is only as up to date as whoever set it made sure it is.
+pub struct Prebuilt;
+
+impl Input for Prebuilt {
+    type Context = Context;
+    type Key = id::Pkg;
+    type Value = Option<Shared<PrebuiltPackage>>;
+
+    fn load(_: &Context, _: &id::Pkg) -> Self::Value {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ParsedFile {
+    pub green_node: GreenNode,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParsedFile {
+    pub fn syntax(&self) -> SyntaxNode {
+        SyntaxNode::new_root(self.green_node.clone())
+    }
+}
+
+/// The diagnostics of a parse are determined by its tree, since the tree includes the text it was parsed from
+impl PartialEq for ParsedFile {
+    fn eq(&self, other: &Self) -> bool {
+        self.green_node == other.green_node
+    }
+}
+
+pub struct ParseFile;
+
+impl Query for ParseFile {
+    type Context = Context;
+    type Key = FileId;
+    type Value = ParsedFile;
+
+    fn execute(db: &Database, file_id: &FileId) -> ParsedFile {
+        Self::compute(Self::prepare(db, file_id))
+    }
+}
+
+impl ParallelQuery for ParseFile {
+    type Prepared = (FileId, Arc<str>, &'static Interner);
+
+    fn prepare(db: &Database, file_id: &FileId) -> Self::Prepared {
+        let src = db.input::<FileText>(*file_id).unwrap_or_default();
+        (*file_id, src, db.context().interner)
+    }
+
+    fn compute((file_id, src, interner): Self::Prepared) -> ParsedFile {
+        let parse = flux_parser::parse(&src, file_id, interner);
+        ParsedFile {
+            green_node: parse.green_node,
+            diagnostics: parse.diagnostics,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PackageDefinitions {
+    pub package: Package,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The files of every module in the package, starting with its entry
+    pub files: Vec<FileId>,
+}
+
+/// The item tree and module tree of a package, without any of its bodies
+pub struct DefinePackage;
+
+impl Query for DefinePackage {
+    type Context = Context;
+    type Key = id::Pkg;
+    type Value = Shared<PackageDefinitions>;
+
+    fn execute(db: &Database, package_id: &id::Pkg) -> Self::Value {
+        let project = db.input::<Project>(());
+        let source = &project[Into::<u32>::into(*package_id) as usize];
+        if let Some(prebuilt) = db.input::<Prebuilt>(*package_id) {
+            let mut package = prebuilt.package.clone();
+            package.set_dependencies(source.dependencies.clone());
+            return Shared::new(PackageDefinitions {
+                package,
+                diagnostics: vec![],
+                files: prebuilt.files.clone(),
+            });
+        }
+
+        let interner = db.context().interner;
+        let src = db.input::<FileText>(source.entry).unwrap_or_default();
+
+        let mut diagnostics = vec![];
+        let mut source_cache = SourceCache::new(interner);
+        let resolver = DbFileResolver {
+            db,
+            files: RefCell::new(vec![source.entry]),
+        };
+        let mut builder = PkgBuilder::new(
+            source.name,
+            &mut diagnostics,
+            interner,
+            &mut source_cache,
+            resolver,
+        );
+        builder.seed_with_entry(source.entry, &src);
+        let files = builder.resolver.files.take();
+        let mut package = builder.finish();
+        package.set_dependencies(source.dependencies.clone());
+
+        Shared::new(PackageDefinitions {
+            package,
+            diagnostics,
+            files,
+        })
+    }
+}
+
+/// The definitions of every package in the project
+pub struct Definitions;
+
+impl Query for Definitions {
+    type Context = Context;
+    type Key = ();
+    type Value = Shared<Map<id::Pkg, Package>>;
+
+    fn execute(db: &Database, _: &()) -> Self::Value {
+        let mut packages = Map::new();
+        for idx in 0..db.input::<Project>(()).len() {
+            let definitions = db.get::<DefinePackage>(id::Pkg::from(idx as u32));
+            packages.insert(definitions.package.clone());
+        }
+        Shared::new(packages)
+    }
+}
+
+/// The declarations of a file, apart from where they were written
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileDeclarations {
+    /// A hash of the text of every token outside of a function body, along with the first token of each body
+    pub fingerprint: u64,
+    /// Where each of those tokens starts, in the order they were written
+    pub anchors: Arc<[u32]>,
+}
+
+pub struct DeclareFile;
+
+impl Query for DeclareFile {
+    type Context = Context;
+    type Key = FileId;
+    type Value = FileDeclarations;
+
+    fn execute(db: &Database, file_id: &FileId) -> FileDeclarations {
+        let src = db.input::<FileText>(*file_id).unwrap_or_default();
+        let root = db.get::<ParseFile>(*file_id).syntax();
+        let bodies: Vec<_> = root
+            .descendants()
+            .filter_map(|node| ast::FnDecl::cast(node.clone()))
+            .filter_map(|fn_decl| fn_decl.body())
+            .map(|body| body.syntax().text_range())
+            .collect();
+
+        let mut hasher = DefaultHasher::new();
+        let mut anchors = vec![];
+        for token in root
+            .descendants_with_tokens()
+            .filter_map(NodeOrToken::into_token)
+        {
+            let range = token.text_range();
+            if matches!(token.kind(), SyntaxKind::Whitespace | SyntaxKind::Comment)
+                || bodies
+                    .iter()
+                    .any(|body| body.contains_range(range) && body.start() != range.start())
+            {
+                continue;
+            }
+            src[range].hash(&mut hasher);
+            anchors.push(range.start().into());
+        }
+        FileDeclarations {
+            fingerprint: hasher.finish(),
+            anchors: anchors.into(),
+        }
+    }
+}
+
+/// A fingerprint of everything in the project other than function bodies
+///
+/// This is the text of every token outside of a function body, the files of every package, and the packages each one
+/// depends on. Where the tokens were written is left out, and kept track of by [`Positions`] instead.
+pub struct Signatures;
+
+impl Query for Signatures {
+    type Context = Context;
+    type Key = ();
+    type Value = u64;
+
+    fn execute(db: &Database, _: &()) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        let project = db.input::<Project>(());
+        project.hash(&mut hasher);
+        for idx in 0..project.len() {
+            let package_id = id::Pkg::from(idx as u32);
+            if let Some(prebuilt) = db.input::<Prebuilt>(package_id) {
+                prebuilt.fingerprint.hash(&mut hasher);
+                continue;
+            }
+            for file_id in &db.get::<DefinePackage>(package_id).files {
+                file_id.hash(&mut hasher);
+                db.get::<DeclareFile>(*file_id)
+                    .fingerprint
+                    .hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+}
+
+/// Where the declarations and function bodies of every file that is built were written
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FilePositions(HashMap<FileId, Arc<[u32]>>);
+
+impl FilePositions {
+    /// Move a span from where it was at `old` to where the text it covers is now
+    ///
+    /// Offsets keep their distance to the declaration or body they are in. Spans in files whose declarations have
+    /// changed, rather than only moved, are left where they were, since nothing is lowered with them.
+    fn relocate(&self, old: &FilePositions, file_id: FileId, span: Span) -> Span {
+        let (Some(old), Some(new)) = (old.0.get(&file_id), self.0.get(&file_id)) else {
+            return span;
+        };
+        if old.len() != new.len() {
+            return span;
+        }
+        let relocate = |offset: u32| match old.partition_point(|anchor| *anchor <= offset) {
+            0 => offset,
+            idx => new[idx - 1] + (offset - old[idx - 1]),
+        };
+        let start = u32::from(span.range.start());
+        let end = u32::from(span.range.end());
+        let new_start = relocate(start);
+        let new_end = if end > start {
+            relocate(end - 1) + 1
+        } else {
+            new_start
+        };
+        Span::new(new_start..new_end)
+    }
+}
+
+pub struct Positions;
+
+impl Query for Positions {
+    type Context = Context;
+    type Key = ();
+    type Value = Arc<FilePositions>;
+
+    fn execute(db: &Database, _: &()) -> Self::Value {
+        let mut positions = HashMap::new();
+        for idx in 0..db.input::<Project>(()).len() {
+            let package_id = id::Pkg::from(idx as u32);
+            if db.input::<Prebuilt>(package_id).is_some() {
+                continue;
+            }
+            for file_id in &db.get::<DefinePackage>(package_id).files {
+                positions.insert(*file_id, db.get::<DeclareFile>(*file_id).anchors);
+            }
+        }
+        Arc::new(FilePositions(positions))
+    }
+}
+
+/// A function whose body is lowered on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FnKey {
+    pub package_id: id::Pkg,
+    pub mod_id: id::Mod,
+    pub fn_id: id::FnDecl,
+    /// The apply block the function is a method in, if it is one
+    pub apply_id: Option<id::ApplyDecl>,
+}
+
+/// The text of a function body and the file it was written in
+///
+/// Bodies are found through the function they belong to rather than where they were written, so moving a body
+/// doesn't change its text.
+pub struct FnBodyText;
+
+impl Query for FnBodyText {
+    type Context = Context;
+    type Key = FnKey;
+    type Value = Option<(FileId, Arc<str>)>;
+
+    fn execute(db: &Database, key: &FnKey) -> Self::Value {
+        let packages = db.get::<Definitions>(());
+        let package = packages.get(key.package_id);
+        let file_id = package.module_tree[key.mod_id].file_id;
+        let body = package
+            .item_tree
+            .functions
+            .get(key.fn_id)
+            .ast
+            .as_ref()?
+            .body()?;
+        let range = body.syntax().text_range();
+        let src = db.input::<FileText>(file_id).unwrap_or_default();
+        Some((file_id, src[range].into()))
+    }
+}
+
+/// The resolvers for the trait applications and methods of every package
+pub struct Resolvers;
+
+impl Query for Resolvers {
+    type Context = Context;
+    type Key = ();
+    type Value = Shared<(TraitResolver, MethodResolver)>;
+
+    fn execute(db: &Database, _: &()) -> Self::Value {
+        Shared::new(build_resolvers(
+            &db.get::<Definitions>(()),
+            db.context().interner,
+        ))
+    }
+}
+
+/// A function body, along with where every declaration and body was when it was lowered
+#[derive(Debug)]
+pub struct PositionedFnBody {
+    pub lowered: LoweredFnBody,
+    positions: Arc<FilePositions>,
+}
+
+/// The body of a function, lowered and type checked
+pub struct LowerFnBody;
+
+impl Query for LowerFnBody {
+    type Context = Context;
+    type Key = FnKey;
+    type Value = Shared<PositionedFnBody>;
+
+    fn execute(db: &Database, key: &FnKey) -> Self::Value {
+        Self::compute(Self::prepare(db, key))
+    }
+}
+
+/// What a function body is lowered with
+pub struct FnBodyInputs {
+    key: FnKey,
+    packages: Shared<Map<id::Pkg, Package>>,
+    resolvers: Shared<(TraitResolver, MethodResolver)>,
+    positions: Arc<FilePositions>,
+    config: Config,
+    interner: &'static Interner,
+}
+
+impl ParallelQuery for LowerFnBody {
+    type Prepared = FnBodyInputs;
+
+    fn prepare(db: &Database, key: &FnKey) -> FnBodyInputs {
+        db.get::<Signatures>(());
+        db.get::<FnBodyText>(*key);
+
+        // Everything used from the definitions is either a declaration or the body being lowered, both of which were
+        // depended on above, apart from where they were written, which is remembered so that it can be fixed up
+        FnBodyInputs {
+            key: *key,
+            packages: db.get_untracked::<Definitions>(()),
+            resolvers: db.get_untracked::<Resolvers>(()),
+            positions: db.get_untracked::<Positions>(()),
+            config: db.context().config.clone(),
+            interner: db.context().interner,
+        }
+    }
+
+    fn compute(inputs: FnBodyInputs) -> Self::Value {
+        let FnBodyInputs {
+            key,
+            packages,
+            resolvers,
+            positions,
+            config,
+            interner,
+        } = inputs;
+        let ctx = LoweringCtx::new(key.mod_id.in_pkg(key.package_id), &packages);
+        let item_resolver = ItemResolver::new(key.package_id, &packages, interner);
+        let apply_decl = key
+            .apply_id
+            .map(|apply_id| packages.get(key.package_id).item_tree.applies.get(apply_id));
+        let lowered = lower_function_body(
+            apply_decl,
+            key.fn_id,
+            &ctx,
+            &item_resolver,
+            &resolvers.0,
+            &resolvers.1,
+            &config,
+            interner,
+        );
+        Shared::new(PositionedFnBody { lowered, positions })
+    }
+}
+
+#[derive(Debug)]
+pub struct LoweredPackageBodies {
+    pub bodies: PackageBodies,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The bodies with the types of their expressions, if [`Config::debug_bodies`] is set
+    pub debug: Vec<Diagnostic>,
+}
+
+/// The bodies of every function in a package
+pub struct PackageBodiesQuery;
+
+impl Query for PackageBodiesQuery {
+    type Context = Context;
+    type Key = id::Pkg;
+    type Value = Shared<LoweredPackageBodies>;
+
+    fn execute(db: &Database, package_id: &id::Pkg) -> Self::Value {
+        if let Some(prebuilt) = db.input::<Prebuilt>(*package_id) {
+            return Shared::new(LoweredPackageBodies {
+                bodies: prebuilt.package.bodies.clone(),
+                diagnostics: vec![],
+                debug: vec![],
+            });
+        }
+
+        let packages = db.get::<Definitions>(());
+        let mut lowered = LoweredPackageBodies {
+            bodies: PackageBodies::new(),
+            diagnostics: vec![],
+            debug: vec![],
+        };
+        let positions = db.get::<Positions>(());
+        let keys = fn_keys(*package_id, packages.get(*package_id));
+        for (key, body) in keys.iter().zip(db.get_all::<LowerFnBody>(&keys)) {
+            if body.positions == positions {
+                lowered.bodies.append(&body.lowered.bodies);
+                lowered
+                    .diagnostics
+                    .extend(body.lowered.diagnostics.iter().cloned());
+                lowered.debug.extend(body.lowered.debug.iter().cloned());
+                continue;
+            }
+
+            // The body was lowered before something above it moved
+            let file_id = packages.get(key.package_id).module_tree[key.mod_id].file_id;
+            let relocate = |file_id, span| positions.relocate(&body.positions, file_id, span);
+            let mut bodies = body.lowered.bodies.clone();
+            bodies.relocate_spans(|span| relocate(file_id, span));
+            lowered.bodies.append(&bodies);
+            let relocated = |diagnostic: &Diagnostic| {
+                let mut diagnostic = diagnostic.clone();
+                diagnostic.relocate(relocate);
+                diagnostic
+            };
+            lowered
+                .diagnostics
+                .extend(body.lowered.diagnostics.iter().map(relocated));
+            lowered
+                .debug
+                .extend(body.lowered.debug.iter().map(relocated));
+        }
+        report_unused_private_functions(
+            *package_id,
+            &packages,
+            &lowered.bodies,
+            db.context().interner,
+            &mut lowered.diagnostics,
+        );
+        Shared::new(lowered)
+    }
+}
+
+/// Every function in a package that has a body of its own, in the order they were written in
+pub fn fn_keys(package_id: id::Pkg, package: &Package) -> Vec<FnKey> {
+    let item_tree = &package.item_tree;
+    let mut keys = vec![];
+    for item_id in &item_tree.top_level {
+        let mod_id = item_id.mod_id;
+        match &item_id.inner {
+            ItemTreeIdx::Function(fn_id) => keys.push(FnKey {
+                package_id,
+                mod_id,
+                fn_id: *fn_id,
+                apply_id: None,
+            }),
+            ItemTreeIdx::Apply(apply_id) => keys.extend(
+                item_tree
+                    .applies
+                    .get(*apply_id)
+                    .methods
+                    .iter()
+                    .map(|fn_id| FnKey {
+                        package_id,
+                        mod_id,
+                        fn_id: *fn_id,
+                        apply_id: Some(*apply_id),
+                    }),
+            ),
+            _ => {}
+        }
+    }
+    keys
+}
+
+/// Resolves module files through the database, so that packages depend on the files they are made of
+struct DbFileResolver<'db> {
+    db: &'db Database,
+    files: RefCell<Vec<FileId>>,
+}
+
+impl FileResolver for DbFileResolver<'_> {
+    fn resolve_absolute_path(
+        &self,
+        path: &str,
+        source_cache: &mut SourceCache,
+    ) -> Option<(FileId, String)> {
+        let file_id = FileId::new(self.db.context().interner.get_or_intern(path));
+        let src = self.db.input::<FileText>(file_id)?.to_string();
+        source_cache.add_input_file(path, src.clone());
+        self.files.borrow_mut().push(file_id);
+        Some((file_id, src))
+    }
+
+    fn resolve_relative_path(
+        &self,
+        path: RelativePath,
+        source_cache: &mut SourceCache,
+    ) -> Option<(FileId, String)> {
+        let anchor_path = source_cache.get_file_dir(&path.anchor);
+        self.resolve_absolute_path(&format!("{anchor_path}/{}", path.path), source_cache)
+    }
+
+    fn parse(&self, file_id: FileId, _: &str, _: &'static Interner) -> Parse {
+        let parsed = self.db.get::<ParseFile>(file_id);
+        Parse {
+            green_node: parsed.green_node,
+            diagnostics: parsed.diagnostics,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::OnceLock;
+
+    use super::*;
+
+    fn db(src: &str) -> (Database, FileId) {
+        static INTERNER: OnceLock<Interner> = OnceLock::new();
+        let interner = INTERNER.get_or_init(Interner::new);
+        let mut db = Database::new(Context {
+            interner,
+            config: Config::release(),
+        });
+        let entry = FileId::new(interner.get_or_intern("/incremental/src/main.flx"));
+        db.set::<FileText>(entry, Some(src.into()));
+        let source = PackageSource {
+            name: interner.get_or_intern("incremental"),
+            entry,
+            dependencies: vec![],
+        };
+        db.set::<Project>((), Arc::new([source]));
+        (db, entry)
+    }
+
+    fn fn_key(db: &Database, name: &str) -> FnKey {
+        let packages = db.get::<Definitions>(());
+        let package_id = id::Pkg::from(0);
+        let item_tree = &packages.get(package_id).item_tree;
+        item_tree
+            .top_level
+            .iter()
+            .find_map(|item_id| match item_id.inner {
+                ItemTreeIdx::Function(fn_id)
+                    if db
+                        .context()
+                        .interner
+                        .resolve(&item_tree.functions.get(fn_id).name.inner)
+                        == name =>
+                {
+                    Some(FnKey {
+                        package_id,
+                        mod_id: item_id.mod_id,
+                        fn_id,
+                        apply_id: None,
+                    })
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn lowered_again(src: &str, edited: &str) -> (bool, bool) {
+        let (mut db, entry) = db(src);
+        let (first, second) = (fn_key(&db, "first"), fn_key(&db, "second"));
+        let before = (db.get::<LowerFnBody>(first), db.get::<LowerFnBody>(second));
+        assert!(before.0.lowered.diagnostics.is_empty() && before.1.lowered.diagnostics.is_empty());
+
+        db.set::<FileText>(entry, Some(edited.into()));
+        let after = (db.get::<LowerFnBody>(first), db.get::<LowerFnBody>(second));
+        (before.0 != after.0, before.1 != after.1)
+    }
+
+    #[test]
+    fn editing_a_body_only_lowers_it_again() {
+        let src = "fn first() -> s32 {\n\t1\n}\n\nfn second() -> s32 {\n\t2\n}\n";
+        let edited = "fn first() -> s32 {\n\t1\n}\n\nfn second() -> s32 {\n\t20\n}\n";
+        assert_eq!(lowered_again(src, edited), (false, true));
+    }
+
+    #[test]
+    fn editing_a_body_above_another_only_lowers_it_again() {
+        let src = "fn first() -> s32 {\n\t1\n}\n\nfn second() -> s32 {\n\t2\n}\n";
+        let edited = "fn first() -> s32 {\n\t10\n}\n\nfn second() -> s32 {\n\t2\n}\n";
+        assert_eq!(lowered_again(src, edited), (true, false));
+    }
+
+    #[test]
+    fn bodies_that_moved_point_where_their_text_is_now() {
+        let src =
+            "fn first() -> s32 {\n\t1\n}\n\nfn second() -> s32 {\n\tlet x s32 = missing;\n\tx\n}\n";
+        let edited =
+            "fn first() -> s32 {\n\tlet y = 1;\n\ty\n}\n\nfn second() -> s32 {\n\tlet x s32 = missing;\n\tx\n}\n";
+        let package_id = id::Pkg::from(0);
+        let spans = |db: &Database| {
+            let lowered = db.get::<PackageBodiesQuery>(package_id);
+            let mut expr_spans: Vec<_> = lowered.bodies.expr_spans.iter().collect();
+            expr_spans.sort_by_key(|(expr, _)| Into::<u32>::into(**expr));
+            format!(
+                "{:?} {expr_spans:?} {:?}",
+                lowered.bodies.exprs, lowered.diagnostics
+            )
+        };
+
+        let (mut db, entry) = db(src);
+        let second = db.get::<LowerFnBody>(fn_key(&db, "second"));
+        assert!(!second.lowered.diagnostics.is_empty());
+        spans(&db);
+        db.set::<FileText>(entry, Some(edited.into()));
+        assert!(db.get::<LowerFnBody>(fn_key(&db, "second")) == second);
+
+        assert_eq!(spans(&db), spans(&self::db(edited).0));
+    }
+}
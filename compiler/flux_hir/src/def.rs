@@ -2,15 +2,20 @@ use std::{collections::HashSet, fmt::Debug};
 
 use flux_id::{id, ids, Map};
 use flux_typesystem::{TraitRestriction, Type, Typed};
-use flux_util::{Path, Spanned, WithSpan, Word};
+use flux_util::{words::AsText, Path, Spanned, WithSpan, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 pub mod expr;
 pub mod item;
+pub mod pat;
 
 ids!(GParam);
 
-#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Default, Debug, Serialize, Deserialize)]
 pub struct GenericParams {
+    #[serde_as(as = "Map<GParam, Spanned<AsText>>")]
     pub types: Map<GParam, Spanned<Word>>,
     pub where_predicates: Vec<WherePredicate>,
 }
@@ -115,9 +120,11 @@ impl GenericParams {
     // }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct WherePredicate {
     pub ty: GParam,
+    #[serde_as(as = "AsText")]
     pub name: Word,
     pub bound: Spanned<Path<Word, Type>>,
 }
@@ -128,7 +135,7 @@ impl WherePredicate {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct ParamList(Vec<Param>);
 
 impl ParamList {
@@ -145,8 +152,10 @@ impl ParamList {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Param {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub ty: Spanned<Type>,
 }
@@ -157,7 +166,7 @@ impl Param {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructFieldDeclList(Vec<StructFieldDecl>);
 
 impl StructFieldDeclList {
@@ -182,8 +191,10 @@ impl StructFieldDeclList {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructFieldDecl {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub ty: Spanned<Type>,
 }
@@ -194,17 +205,23 @@ impl StructFieldDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumDeclVariantList(Vec<EnumDeclVariant>);
 
 impl EnumDeclVariantList {
     pub fn new(variants: Vec<EnumDeclVariant>) -> Self {
         Self(variants)
     }
+
+    pub fn iter(&self) -> impl Iterator<Item = &EnumDeclVariant> {
+        self.0.iter()
+    }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumDeclVariant {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub ty: Option<Spanned<Type>>,
 }
@@ -215,8 +232,10 @@ impl EnumDeclVariant {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AssociatedTypeDecl {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub type_bound_list: TypeBoundList,
 }
@@ -230,7 +249,7 @@ impl AssociatedTypeDecl {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct TypeBoundList(Vec<Spanned<TypeBound>>);
 
 impl TypeBoundList {
@@ -247,7 +266,7 @@ impl TypeBoundList {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct TypeBound(Path<Word, Type>);
 
 impl TypeBound {
@@ -260,8 +279,10 @@ impl TypeBound {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct AssociatedTypeDefinition {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub ty: Spanned<Type>,
 }
@@ -272,7 +293,7 @@ impl AssociatedTypeDefinition {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructExprFieldList(Vec<StructExprField>);
 
 impl StructExprFieldList {
@@ -287,10 +308,16 @@ impl StructExprFieldList {
     pub fn iter(&self) -> impl Iterator<Item = &StructExprField> {
         self.0.iter()
     }
+
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut StructExprField> {
+        self.0.iter_mut()
+    }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructExprField {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub val: id::Expr,
 }
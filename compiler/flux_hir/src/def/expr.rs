@@ -3,29 +3,37 @@ use std::{collections::VecDeque, fmt::Display};
 use flux_diagnostics::ice;
 use flux_id::id;
 use flux_typesystem::{Type, Typed, WithType};
-use flux_util::{Path, Spanned, Word};
+use flux_util::{words::AsText, Path, Span, Spanned, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use super::StructExprFieldList;
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Expr {
     Address(id::Expr),
     Assignment(Assignment),
-    // Block(Block),
+    Block(Block),
     BinOp(BinOp),
     Cast(Cast),
-    // Enum(EnumExpr),
+    Enum(EnumExpr),
     Call(Call),
-    // Float(f64),
+    Float(Float),
     Int(u64),
+    Bool(bool),
     Tuple(Vec<id::Expr>),
     Path(Path<Word, id::Ty>),
-    // Let(Let),
+    Let(Let),
     Struct(StructExpr),
     MemberAccess(MemberAccess),
     If(If),
+    Match(Match),
+    Loop(Loop),
+    Break(Break),
+    Continue(Continue),
     Intrinsic(Intrinsic),
-    // Str(Str),
+    Str(#[serde_as(as = "AsText")] Word),
     Poisoned,
 }
 
@@ -35,9 +43,127 @@ impl Expr {
     pub(crate) const fn unit() -> Self {
         Self::Tuple(vec![])
     }
+
+    /// Move every expression and pattern this expression refers to along by `exprs` and `pats`, for when the arenas
+    /// it was lowered into are appended to others
+    pub(crate) fn offset_ids(&mut self, exprs: u32, pats: u32) {
+        let offset = |expr: &mut id::Expr| *expr = id::Expr::from(Into::<u32>::into(*expr) + exprs);
+        match self {
+            Expr::Address(expr) => offset(expr),
+            Expr::Assignment(assignment) => {
+                offset(&mut assignment.lhs);
+                offset(&mut assignment.val);
+            }
+            Expr::Block(block) => block.exprs.iter_mut().for_each(offset),
+            Expr::BinOp(bin_op) => {
+                offset(&mut bin_op.lhs);
+                offset(&mut bin_op.rhs);
+            }
+            Expr::Cast(cast) => offset(&mut cast.val),
+            Expr::Enum(enum_expr) => enum_expr.payload.iter_mut().for_each(offset),
+            Expr::Call(call) => call.exprs.iter_mut().for_each(|expr| offset(expr)),
+            Expr::Tuple(exprs) => exprs.iter_mut().for_each(offset),
+            Expr::Let(let_expr) => offset(&mut let_expr.val),
+            Expr::Struct(struct_expr) => struct_expr
+                .fields
+                .iter_mut()
+                .for_each(|field| offset(&mut field.val)),
+            Expr::MemberAccess(member_access) => offset(&mut member_access.lhs),
+            Expr::If(if_expr) => if_expr.exprs.iter_mut().for_each(offset),
+            Expr::Match(match_expr) => {
+                offset(&mut match_expr.scrutinee);
+                for arm in &mut match_expr.arms {
+                    arm.pat.inner = id::Pat::from(Into::<u32>::into(arm.pat.inner) + pats);
+                    offset(&mut arm.body);
+                }
+            }
+            Expr::Loop(loop_expr) => offset(&mut loop_expr.body),
+            Expr::Break(break_expr) => {
+                offset(&mut break_expr.target);
+                break_expr.val.iter_mut().for_each(offset);
+            }
+            Expr::Continue(continue_expr) => offset(&mut continue_expr.target),
+            Expr::Intrinsic(intrinsic) => intrinsic.args.iter_mut().for_each(|arg| offset(arg)),
+            Expr::Float(_)
+            | Expr::Int(_)
+            | Expr::Bool(_)
+            | Expr::Path(_)
+            | Expr::Str(_)
+            | Expr::Poisoned => {}
+        }
+    }
+
+    /// Move every span in this expression with `relocate`, for when the source it was lowered from has moved
+    pub(crate) fn relocate_spans(&mut self, relocate: &impl Fn(Span) -> Span) {
+        match self {
+            Expr::BinOp(bin_op) => bin_op.op.span = relocate(bin_op.op.span),
+            Expr::Enum(enum_expr) => {
+                enum_expr.path.span = relocate(enum_expr.path.span);
+                enum_expr.variant.span = relocate(enum_expr.variant.span);
+            }
+            Expr::Call(call) => call
+                .exprs
+                .iter_mut()
+                .for_each(|expr| expr.span = relocate(expr.span)),
+            Expr::Let(let_expr) => let_expr.name.span = relocate(let_expr.name.span),
+            Expr::Struct(struct_expr) => {
+                struct_expr.path.span = relocate(struct_expr.path.span);
+                struct_expr
+                    .fields
+                    .iter_mut()
+                    .for_each(|field| field.name.span = relocate(field.name.span));
+            }
+            Expr::MemberAccess(member_access) => {
+                member_access.lhs.span = relocate(member_access.lhs.span);
+                member_access.field.span = relocate(member_access.field.span);
+            }
+            Expr::Match(match_expr) => {
+                match_expr.scrutinee.span = relocate(match_expr.scrutinee.span);
+                for arm in &mut match_expr.arms {
+                    arm.pat.span = relocate(arm.pat.span);
+                }
+            }
+            Expr::Intrinsic(intrinsic) => {
+                intrinsic.args.span = relocate(intrinsic.args.span);
+                intrinsic
+                    .args
+                    .iter_mut()
+                    .for_each(|arg| arg.span = relocate(arg.span));
+            }
+            Expr::Address(_)
+            | Expr::Assignment(_)
+            | Expr::Block(_)
+            | Expr::Cast(_)
+            | Expr::Float(_)
+            | Expr::Int(_)
+            | Expr::Bool(_)
+            | Expr::Tuple(_)
+            | Expr::Path(_)
+            | Expr::If(_)
+            | Expr::Loop(_)
+            | Expr::Break(_)
+            | Expr::Continue(_)
+            | Expr::Str(_)
+            | Expr::Poisoned => {}
+        }
+    }
+}
+
+/// A float literal, stored as the bits of its value so that expressions can be compared for equality
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Float(u64);
+
+impl Float {
+    pub fn new(val: f64) -> Self {
+        Self(val.to_bits())
+    }
+
+    pub fn val(&self) -> f64 {
+        f64::from_bits(self.0)
+    }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Assignment {
     pub lhs: id::Expr,
     pub val: id::Expr,
@@ -49,7 +175,33 @@ impl Assignment {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Block {
+    exprs: Vec<id::Expr>,
+}
+
+impl Block {
+    pub fn new(mut stmts: Vec<id::Expr>, tail: id::Expr) -> Self {
+        stmts.push(tail);
+        Self { exprs: stmts }
+    }
+
+    pub fn stmts(&self) -> &[id::Expr] {
+        self.exprs.get(..self.exprs.len() - 1).unwrap_or(&[])
+    }
+
+    /// The expression the block evaluates to
+    ///
+    /// If the block has no terminator expression, this is a unit expression
+    pub fn tail(&self) -> id::Expr {
+        *self
+            .exprs
+            .last()
+            .unwrap_or_else(|| ice("block expression missing tail expression"))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BinOp {
     pub lhs: id::Expr,
     pub rhs: id::Expr,
@@ -62,7 +214,7 @@ impl BinOp {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum Op {
     Eq,
     Add,
@@ -153,7 +305,7 @@ impl Op {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Cast {
     pub val: id::Expr,
     pub to_ty: id::Ty,
@@ -165,7 +317,36 @@ impl Cast {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+/// The construction of an enum variant, like `Option::Some(x)` or `Option::None`
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct EnumExpr {
+    /// The path to the enum, without the variant
+    pub path: Spanned<Path<Word, Type>>,
+    #[serde_as(as = "Spanned<AsText>")]
+    pub variant: Spanned<Word>,
+    /// The position of the variant in the enum declaration, which is the tag it is stored with
+    pub discriminant: u32,
+    pub payload: Option<id::Expr>,
+}
+
+impl EnumExpr {
+    pub fn new(
+        path: Spanned<Path<Word, Type>>,
+        variant: Spanned<Word>,
+        discriminant: u32,
+        payload: Option<id::Expr>,
+    ) -> Self {
+        Self {
+            path,
+            variant,
+            discriminant,
+            payload,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Call {
     exprs: Vec<Spanned<id::Expr>>,
 }
@@ -185,7 +366,21 @@ impl Call {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Let {
+    #[serde_as(as = "Spanned<AsText>")]
+    pub name: Spanned<Word>,
+    pub val: id::Expr,
+}
+
+impl Let {
+    pub fn new(name: Spanned<Word>, val: id::Expr) -> Self {
+        Self { name, val }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructExpr {
     pub path: Spanned<Path<Word, Type>>,
     pub fields: StructExprFieldList,
@@ -197,8 +392,10 @@ impl StructExpr {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct StructExprField {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub val: Spanned<id::Expr>,
 }
@@ -209,9 +406,11 @@ impl StructExprField {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct MemberAccess {
     pub lhs: Spanned<id::Expr>,
+    #[serde_as(as = "Spanned<AsText>")]
     pub field: Spanned<Word>,
 }
 
@@ -221,7 +420,7 @@ impl MemberAccess {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct If {
     exprs: Vec<id::Expr>,
 }
@@ -282,8 +481,72 @@ impl If {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Match {
+    pub scrutinee: Spanned<id::Expr>,
+    pub arms: Vec<MatchArm>,
+}
+
+impl Match {
+    pub fn new(scrutinee: Spanned<id::Expr>, arms: Vec<MatchArm>) -> Self {
+        Self { scrutinee, arms }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pat: Spanned<id::Pat>,
+    pub body: id::Expr,
+}
+
+impl MatchArm {
+    pub fn new(pat: Spanned<id::Pat>, body: id::Expr) -> Self {
+        Self { pat, body }
+    }
+}
+
+/// A `loop`, which `while` and `for` loops are lowered to as well
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Loop {
+    pub body: id::Expr,
+}
+
+impl Loop {
+    pub fn new(body: id::Expr) -> Self {
+        Self { body }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Break {
+    /// The loop expression being broken out of
+    pub target: id::Expr,
+    /// The value the loop evaluates to, which is unit if there isn't one
+    pub val: Option<id::Expr>,
+}
+
+impl Break {
+    pub fn new(target: id::Expr, val: Option<id::Expr>) -> Self {
+        Self { target, val }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Continue {
+    /// The loop expression whose next iteration is started
+    pub target: id::Expr,
+}
+
+impl Continue {
+    pub fn new(target: id::Expr) -> Self {
+        Self { target }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Intrinsic {
+    #[serde_as(as = "AsText")]
     pub name: Word,
     pub args: Spanned<Vec<Spanned<id::Expr>>>,
 }
@@ -1,7 +1,9 @@
 use flux_id::id;
 use flux_parser::ast;
 use flux_typesystem::{FnSignature, Type};
-use flux_util::{Path, Spanned, Word};
+use flux_util::{words::AsText, Path, Spanned, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::lower::item_tree::ItemTree;
 
@@ -10,19 +12,22 @@ use super::{
     StructFieldDeclList,
 };
 
-#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash, Serialize, Deserialize)]
 pub enum Visibility {
     Private,
     Public,
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FnDecl {
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub visibility: Spanned<Visibility>,
     pub generic_params: Spanned<GenericParams>,
     pub params: Spanned<ParamList>,
     pub return_ty: Spanned<Type>,
+    #[serde(skip)]
     pub ast: Option<ast::FnDecl>,
 }
 
@@ -53,9 +58,11 @@ impl FnDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModDecl {
     pub visibility: Spanned<Visibility>,
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
 }
 
@@ -65,9 +72,11 @@ impl ModDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StructDecl {
     pub visibility: Spanned<Visibility>,
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub generic_params: Spanned<GenericParams>,
     pub fields: StructFieldDeclList,
@@ -89,9 +98,11 @@ impl StructDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EnumDecl {
     pub visibility: Spanned<Visibility>,
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub generic_params: Spanned<GenericParams>,
     pub variants: EnumDeclVariantList,
@@ -113,9 +124,11 @@ impl EnumDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraitDecl {
     pub visibility: Spanned<Visibility>,
+    #[serde_as(as = "Spanned<AsText>")]
     pub name: Spanned<Word>,
     pub generic_params: Spanned<GenericParams>,
     pub assoc_type_decls: Vec<AssociatedTypeDecl>,
@@ -155,7 +168,7 @@ impl TraitDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApplyDecl {
     pub visibility: Spanned<Visibility>,
     pub generic_params: Spanned<GenericParams>,
@@ -185,9 +198,12 @@ impl ApplyDecl {
     }
 }
 
-#[derive(Debug, Clone)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UseDecl {
+    #[serde_as(as = "Spanned<AsText>")]
     pub path: Spanned<Path<Word>>,
+    #[serde_as(as = "Option<Spanned<AsText>>")]
     pub alias: Option<Spanned<Word>>,
     pub all: bool,
 }
@@ -0,0 +1,114 @@
+use flux_id::id;
+use flux_typesystem::{Type, WithType};
+use flux_util::{words::AsText, Path, Span, Spanned, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+/// A pattern in a `match` arm
+///
+/// Patterns are stored alongside expressions so that each one has a type, which is the type of the value it is
+/// matched against.
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Pat {
+    Wildcard,
+    Bind(#[serde_as(as = "Spanned<AsText>")] Spanned<Word>),
+    Int(u64),
+    Bool(bool),
+    Variant(VariantPat),
+    Tuple(Vec<id::Pat>),
+    Struct(StructPat),
+    Poisoned,
+}
+
+impl WithType for Pat {}
+
+impl Pat {
+    /// Move every pattern this pattern refers to along by `pats`, for when the arena it was lowered into is appended
+    /// to another
+    pub(crate) fn offset_ids(&mut self, pats: u32) {
+        let offset = |pat: &mut id::Pat| *pat = id::Pat::from(Into::<u32>::into(*pat) + pats);
+        match self {
+            Pat::Variant(variant) => variant.payload.iter_mut().for_each(offset),
+            Pat::Tuple(pats) => pats.iter_mut().for_each(offset),
+            Pat::Struct(struct_pat) => struct_pat
+                .fields
+                .iter_mut()
+                .for_each(|field| offset(&mut field.pat)),
+            Pat::Wildcard | Pat::Bind(_) | Pat::Int(_) | Pat::Bool(_) | Pat::Poisoned => {}
+        }
+    }
+
+    /// Move every span in this pattern with `relocate`, for when the source it was lowered from has moved
+    pub(crate) fn relocate_spans(&mut self, relocate: &impl Fn(Span) -> Span) {
+        match self {
+            Pat::Bind(name) => name.span = relocate(name.span),
+            Pat::Variant(variant) => {
+                variant.path.span = relocate(variant.path.span);
+                variant.variant.span = relocate(variant.variant.span);
+            }
+            Pat::Struct(struct_pat) => {
+                struct_pat.path.span = relocate(struct_pat.path.span);
+                struct_pat
+                    .fields
+                    .iter_mut()
+                    .for_each(|field| field.name.span = relocate(field.name.span));
+            }
+            Pat::Wildcard | Pat::Int(_) | Pat::Bool(_) | Pat::Tuple(_) | Pat::Poisoned => {}
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct VariantPat {
+    /// The path to the enum, without the variant
+    pub path: Spanned<Path<Word, Type>>,
+    #[serde_as(as = "Spanned<AsText>")]
+    pub variant: Spanned<Word>,
+    pub discriminant: u32,
+    /// The pattern the payload is matched against, which is `None` for unit variants
+    pub payload: Option<id::Pat>,
+}
+
+impl VariantPat {
+    pub fn new(
+        path: Spanned<Path<Word, Type>>,
+        variant: Spanned<Word>,
+        discriminant: u32,
+        payload: Option<id::Pat>,
+    ) -> Self {
+        Self {
+            path,
+            variant,
+            discriminant,
+            payload,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct StructPat {
+    pub path: Spanned<Path<Word, Type>>,
+    pub fields: Vec<StructPatField>,
+}
+
+impl StructPat {
+    pub fn new(path: Spanned<Path<Word, Type>>, fields: Vec<StructPatField>) -> Self {
+        Self { path, fields }
+    }
+}
+
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct StructPatField {
+    #[serde_as(as = "Spanned<AsText>")]
+    pub name: Spanned<Word>,
+    pub pat: id::Pat,
+}
+
+impl StructPatField {
+    pub fn new(name: Spanned<Word>, pat: id::Pat) -> Self {
+        Self { name, pat }
+    }
+}
@@ -80,6 +80,9 @@ pub enum LowerError {
         label at struct_name = "missing fields in `{struct_name}` initialization",
         label at missing_fields = "missing fields {}" with (
             quote_and_listify(missing_fields.iter())
+        ),
+        suggestion(has_placeholders) at insertion => fields = "add the missing field{}" with (
+            missing_fields.plural("s")
         )
     )]
     MissingFieldsInStructExpr {
@@ -87,20 +90,25 @@ pub enum LowerError {
         struct_name: String,
         #[filespanned]
         missing_fields: Vec<String>,
+        #[filespanned]
+        insertion: (),
+        fields: Option<String>,
     },
     #[error(
         location = got_names,
         primary = "missing generic arguments",
         label at got_names = "got {} generic argument{}, {}" with (got_names.len(), got_names.plural("s"), quote_and_listify(got_names.iter())),
-        label at expected_names = "expected {} generic argument{}, {}" with (expected_names.len(), expected_names.plural("s"), quote_and_listify(expected_names.iter()))
+        label at expected_names = "expected {} generic argument{}, {}" with (expected_names.len(), expected_names.plural("s"), quote_and_listify(expected_names.iter())),
+        suggestion(has_placeholders) at got_names => generic_args = "add the missing generic arguments"
     )]
     MissingGenericArguments {
         #[filespanned]
         got_names: Vec<String>,
         #[filespanned]
         expected_names: Vec<String>,
+        generic_args: Option<String>,
     },
-    #[error(
+    #[warning(
         location = item_name,
         primary = "unused generics",
         label at item_name = "unused generics in `{item_name}`",
@@ -117,11 +125,13 @@ pub enum LowerError {
     #[error(
         location = local,
         primary = "unknown local referenced",
-        label at local = "unknown local `{local}` referenced"
+        label at local = "unknown local `{local}` referenced",
+        suggestion(maybe_incorrect) at local => candidate = "did you mean `{candidate}`?"
     )]
     UnknownLocal {
         #[filespanned]
         local: String,
+        candidate: Option<String>,
     },
     #[error(
         location = intrinsic,
@@ -136,13 +146,17 @@ pub enum LowerError {
         location = got_num,
         primary = "incorrect number of arguments in call",
         label at got_num = "got {got_num} argument{}" with (got_num.plural("s")),
-        label at expected_num = "expected {expected_num} argument{}" with (expected_num.plural("s"))
+        label at expected_num = "expected {expected_num} argument{}" with (expected_num.plural("s")),
+        suggestion(maybe_incorrect) at got_num => args = "remove the extra argument{}" with (
+            (got_num - expected_num).plural("s")
+        )
     )]
     IncorrectNumberOfArgs {
         #[filespanned]
         got_num: usize,
         #[filespanned]
         expected_num: usize,
+        args: Option<String>,
     },
     #[error(
         location = got_fields,
@@ -179,13 +193,15 @@ pub enum LowerError {
            location = field,
            primary = "unknown struct field referenced",
            label at field = "unknown struct field `{field}` referenced",
-           label at strukt = "struct `{strukt}` defined here"
+           label at strukt = "struct `{strukt}` defined here",
+           suggestion(maybe_incorrect) at field => candidate = "did you mean `{candidate}`?"
        )]
     UnknownStructField {
         #[filespanned]
         field: String,
         #[filespanned]
         strukt: String,
+        candidate: Option<String>,
     },
     #[error(
         location = strukt,
@@ -207,4 +223,152 @@ pub enum LowerError {
         callee: (),
         ty: String,
     },
+    #[error(
+        location = variant,
+        primary = "unknown enum variant referenced",
+        label at variant = "unknown variant `{variant}` referenced",
+        label at enum_name = "enum `{enum_name}` defined here"
+    )]
+    UnknownEnumVariant {
+        #[filespanned]
+        variant: String,
+        #[filespanned]
+        enum_name: String,
+    },
+    #[error(
+        location = variant,
+        primary = "incorrect number of values in enum variant",
+        label at variant = "`{variant}` holds {expected_num} value{}, got {got_num}" with (expected_num.plural("s"))
+    )]
+    IncorrectVariantPayload {
+        #[filespanned]
+        variant: String,
+        expected_num: usize,
+        got_num: usize,
+    },
+    #[error(
+        location = scrutinee,
+        primary = "non-exhaustive patterns in match expression",
+        label at scrutinee = "pattern{} {}{} not covered" with (
+            missing_count.plural("s"),
+            quote_and_listify(missing.iter()),
+            if *missing_count > missing.len() { format!(" and {} more", missing_count - missing.len()) } else { String::new() }
+        ),
+        help = "add an arm for each missing pattern, or a wildcard pattern `_` to match anything"
+    )]
+    NonExhaustiveMatch {
+        #[filespanned]
+        scrutinee: (),
+        missing: Vec<String>,
+        missing_count: usize,
+    },
+    #[warning(
+        location = arm,
+        primary = "unreachable match arm",
+        label at arm = "this pattern only matches values that are matched by the arms before it",
+    )]
+    UnreachableMatchArm {
+        #[filespanned]
+        arm: (),
+    },
+    #[error(
+        location = keyword,
+        primary = "loop control expression outside of a loop",
+        label at keyword = "`{keyword}` can only be used inside of a loop",
+    )]
+    LoopControlOutsideLoop {
+        #[filespanned]
+        keyword: &'static str,
+    },
+    #[error(
+        location = label,
+        primary = "unknown loop label",
+        label at label = "no enclosing loop is labeled `{label}`",
+    )]
+    UnknownLoopLabel {
+        #[filespanned]
+        label: String,
+    },
+    #[error(
+        location = val,
+        primary = "`break` with a value outside of a `loop`",
+        label at val = "`{kind}` loops always evaluate to `()`, so they cannot be broken out of with a value",
+        help = "use `loop` to break out with a value"
+    )]
+    BreakWithValueInNonLoop {
+        #[filespanned]
+        val: (),
+        kind: &'static str,
+    },
+    #[error(
+        location = iterable,
+        primary = "value is not an iterator",
+        label at iterable = "`{iterable}` does not implement `Iterator`",
+    )]
+    NotAnIterator {
+        #[filespanned]
+        iterable: String,
+    },
+    #[error(
+        location = val,
+        primary = "value cannot be indexed",
+        label at val = "`{val}` does not implement `Index`",
+    )]
+    NotIndexable {
+        #[filespanned]
+        val: String,
+    },
+    #[warning(
+        location = local,
+        primary = "unused local",
+        label at local = "`{local}` is never used"
+    )]
+    UnusedLocal {
+        #[filespanned]
+        local: String,
+    },
+    #[warning(
+        location = import,
+        primary = "unused import",
+        label at import = "`{import}` is never used",
+    )]
+    UnusedImport {
+        #[filespanned]
+        import: String,
+    },
+    #[warning(
+        location = function,
+        primary = "unused private function",
+        label at function = "`{function}` is never called",
+    )]
+    UnusedPrivateFunction {
+        #[filespanned]
+        function: String,
+    },
+    #[warning(
+        location = unreachable,
+        primary = "unreachable code",
+        label at unreachable = "unreachable statement",
+        label at diverging = "any code following this expression is unreachable",
+    )]
+    UnreachableCode {
+        #[filespanned]
+        unreachable: (),
+        #[filespanned]
+        diverging: (),
+    },
+    #[warning(
+        location = cast,
+        primary = "unnecessary cast",
+        label at cast = "casting to `{ty}`, which the value already is, does nothing",
+        suggestion(machine_applicable) at cast_to => replacement = "remove the cast"
+    )]
+    UnnecessaryCast {
+        #[filespanned]
+        cast: (),
+        #[filespanned]
+        cast_to: (),
+        ty: String,
+        replacement: Option<String>,
+    },
 }
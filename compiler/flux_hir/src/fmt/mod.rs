@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use flux_diagnostics::{Diagnostic, DiagnosticCode, SourceCache};
+use flux_diagnostics::{Diagnostic, DiagnosticCode};
 use flux_id::{id, Map};
 use flux_typesystem::{TEnv, TypeKind, Typed};
 use flux_util::{FileId, WithSpan};
@@ -21,23 +21,25 @@ macro_rules! format_tid {
     };
 }
 
+/// The body of a function with the type of every expression in it, as a diagnostic so that it can be printed over the
+/// source
 pub(super) fn format_function_with_types(
     body: id::Expr,
     exprs: &Map<id::Expr, Typed<Expr>>,
+    fn_exprs: &[id::Expr],
     tenv: &mut TEnv,
-    source_cache: &SourceCache,
     file_id: FileId,
-) {
+) -> Diagnostic {
     let mut labels = vec![];
     let mut tids_formatted = HashSet::new();
-    for expr in exprs.values() {
+    for expr in fn_exprs.iter().map(|expr| exprs.get(*expr)) {
         if !tids_formatted.contains(&expr.tid) {
             labels.push(format_tid!(expr.tid, tenv, file_id));
         }
         tids_formatted.insert(expr.tid);
     }
 
-    let diagnostic = Diagnostic::error(
+    Diagnostic::error(
         tenv.get(exprs.get(body).tid)
             .span
             .in_file(file_id)
@@ -45,9 +47,5 @@ pub(super) fn format_function_with_types(
         DiagnosticCode::CouldNotInfer,
         format!(""),
         labels,
-    );
-    let mut buf = Vec::new();
-    source_cache.write_diagnostics_to_buffer(&vec![diagnostic], &mut buf);
-    let s = String::from_utf8(buf).unwrap();
-    println!("{s}");
+    )
 }
@@ -1,11 +1,12 @@
 use std::ops::Deref;
 
 use flux_id::id::{self, InMod};
+use serde::{Deserialize, Serialize};
 
 use crate::builtin::BuiltinType;
 
 #[repr(transparent)]
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ItemId(InMod<ItemTreeIdx>);
 
 impl Deref for ItemId {
@@ -28,7 +29,7 @@ impl ItemId {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ItemTreeIdx {
     Apply(id::ApplyDecl),
     BuiltinType(BuiltinType),
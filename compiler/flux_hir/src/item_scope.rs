@@ -1,7 +1,9 @@
 use std::collections::HashMap;
 
 use flux_id::id::WithMod;
-use flux_util::{Interner, Word};
+use flux_util::{words::AsText, Interner, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::{
     builtin::BuiltinType,
@@ -10,8 +12,10 @@ use crate::{
     module::ModuleTree,
 };
 
-#[derive(Debug, Default)]
+#[serde_as]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ItemScope {
+    #[serde_as(as = "HashMap<AsText, _>")]
     pub items: HashMap<Word, (Visibility, ItemId)>,
 }
 
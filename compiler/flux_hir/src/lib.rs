@@ -1,19 +1,15 @@
 use std::collections::HashMap;
 
-use def::expr::Expr;
-use flux_diagnostics::{Diagnostic, SourceCache};
 use flux_id::{
     id::{self, WithMod, WithPackage},
     Map,
 };
-use flux_typesystem::{MethodResolver, TraitApplication, TraitResolver, Typed};
-use flux_util::{FileId, Interner, Word};
-use item::ItemTreeIdx;
-use lower::lower_item_bodies;
-use name_res::BasicFileResolver;
-use package::PkgBuilder;
+use flux_typesystem::{MethodResolver, TraitApplication, TraitResolver};
+use flux_util::Interner;
 
+mod body;
 mod builtin;
+pub mod db;
 pub mod def;
 mod diagnostics;
 mod fmt;
@@ -26,13 +22,19 @@ mod name_res;
 mod package;
 mod prelude;
 
-pub use name_res::item::ItemResolver;
-pub use package::Package;
+pub use body::{FnBody, PackageBodies};
+pub use item::{ItemId, ItemTreeIdx};
+pub use lower::{item_tree::ItemTree, LoweredFnBody};
+pub use module::{ModuleData, ModuleTree};
+pub use name_res::{item::ItemResolver, BasicFileResolver, FileResolver, RelativePath};
+pub use package::{Package, PkgBuilder};
 
+#[derive(Debug, Clone)]
 pub struct Config {
     pub debug_cst: bool,
     pub debug_item_tree: bool,
     pub debug_bodies: bool,
+    pub debug_mir: bool,
 }
 
 impl Config {
@@ -41,54 +43,12 @@ impl Config {
             debug_cst: false,
             debug_item_tree: false,
             debug_bodies: false,
+            debug_mir: false,
         }
     }
 }
 
-pub fn build_package_definitions(
-    name: Word,
-    file_id: FileId,
-    src: &str,
-    source_cache: &mut SourceCache,
-    interner: &'static Interner,
-    diagnostics: &mut Vec<Diagnostic>,
-) -> Package {
-    let mut pkg_builder =
-        PkgBuilder::new(name, diagnostics, interner, source_cache, BasicFileResolver);
-    pkg_builder.seed_with_entry(file_id, src);
-    pkg_builder.finish()
-}
-
-pub fn build_package_bodies(
-    package_id: id::Pkg,
-    packages: &Map<id::Pkg, Package>,
-    exprs: &mut Map<id::Expr, Typed<Expr>>,
-    interner: &'static Interner,
-    diagnostics: &mut Vec<Diagnostic>,
-    source_cache: &SourceCache,
-) {
-    let item_tree = &packages.get(package_id).item_tree;
-
-    let (trait_resolution, method_resolver) = build_resolvers(packages, interner);
-    for item_id in &item_tree.top_level {
-        lower_item_bodies(
-            item_id.mod_id.in_pkg(package_id),
-            item_id,
-            &trait_resolution,
-            &method_resolver,
-            packages,
-            exprs,
-            interner,
-            diagnostics,
-            source_cache,
-        );
-        // if matches!(item_id.inner, item::ItemTreeIdx::Apply(_)) {
-        //     break;
-        // }
-    }
-}
-
-fn build_resolvers(
+pub(crate) fn build_resolvers(
     packages: &Map<id::Pkg, Package>,
     interner: &'static Interner,
 ) -> (TraitResolver, MethodResolver) {
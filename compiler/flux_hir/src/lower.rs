@@ -1,6 +1,6 @@
-use std::mem;
+use std::collections::HashSet;
 
-use flux_diagnostics::{ice, Diagnostic, SourceCache, ToDiagnostic};
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
 use flux_id::{
     id::{self, InPkg},
     Map,
@@ -10,32 +10,50 @@ use flux_parser::{
     syntax::SyntaxNode,
 };
 use flux_typesystem::{
-    diagnostics::TypeError, MethodResolver, Restriction, TEnv, ThisCtx, TraitResolver, Typed,
+    diagnostics::TypeError, MethodResolver, Restriction, TEnv, ThisCtx, TraitResolver, TypeKind,
 };
-use flux_util::{FileId, Interner, WithSpan};
+use flux_util::{FileId, Interner, Span, WithSpan};
+use tracing::warn;
 
 use crate::{
+    body::{FnBody, PackageBodies},
     def::{expr::Expr, item::ApplyDecl},
+    diagnostics::LowerError,
     fmt::format_function_with_types,
-    item::{ItemId, ItemTreeIdx},
+    item::ItemId,
     name_res::item::ItemResolver,
-    Package,
+    Config, Package,
 };
 
 use self::item_tree::ItemTree;
 
+mod exhaustiveness;
 mod expr;
 pub mod item_tree;
 mod r#type;
+mod unused;
 
-struct LoweringCtx<'a> {
-    package_id: id::Pkg,
+pub(crate) use unused::{report_unused_imports, report_unused_private_functions};
+
+pub(crate) struct LoweringCtx<'a> {
     packages: &'a Map<id::Pkg, Package>,
     item_tree: &'a ItemTree,
     mod_id: id::Mod,
     file_id: FileId,
 }
 
+impl<'a> LoweringCtx<'a> {
+    pub(crate) fn new(mod_id: InPkg<id::Mod>, packages: &'a Map<id::Pkg, Package>) -> Self {
+        let package = packages.get(mod_id.pkg_id);
+        Self {
+            packages,
+            item_tree: &package.item_tree,
+            mod_id: *mod_id,
+            file_id: package.module_tree[*mod_id].file_id,
+        }
+    }
+}
+
 pub(super) fn lower_cst_to_item_tree(
     root: SyntaxNode,
     file_id: FileId,
@@ -49,103 +67,28 @@ pub(super) fn lower_cst_to_item_tree(
         .lower_module_items(&root)
 }
 
-pub(super) fn lower_item_bodies(
-    mod_id: InPkg<id::Mod>,
-    item_id: &ItemId,
-    trait_resolver: &TraitResolver,
-    method_resolver: &MethodResolver,
-    packages: &Map<id::Pkg, Package>,
-    exprs: &mut Map<id::Expr, Typed<Expr>>,
-    interner: &'static Interner,
-    diagnostics: &mut Vec<Diagnostic>,
-    source_cache: &SourceCache,
-) {
-    let item_tree = &packages.get(mod_id.pkg_id).item_tree;
-    let file_id = packages.get(mod_id.pkg_id).module_tree[*mod_id].file_id;
-    let ctx = LoweringCtx {
-        package_id: mod_id.pkg_id,
-        packages,
-        item_tree,
-        mod_id: *mod_id,
-        file_id,
-    };
-
-    let item_resolver = ItemResolver::new(mod_id.pkg_id, packages, interner);
-    match &item_id.inner {
-        ItemTreeIdx::Apply(apply_id) => lower_apply_bodies(
-            *apply_id,
-            &ctx,
-            &item_resolver,
-            trait_resolver,
-            method_resolver,
-            exprs,
-            interner,
-            diagnostics,
-            source_cache,
-        ),
-        // ItemTreeIdx::BuiltinType(_) => todo!(),
-        // ItemTreeIdx::Enum(_) => todo!(),
-        ItemTreeIdx::Function(function_id) => lower_function_body(
-            None,
-            *function_id,
-            &ctx,
-            &item_resolver,
-            trait_resolver,
-            method_resolver,
-            exprs,
-            interner,
-            diagnostics,
-            source_cache,
-        ),
-        // ItemTreeIdx::Module(_) => todo!(),
-        // ItemTreeIdx::Struct(_) => todo!(),
-        // ItemTreeIdx::Trait(_) => todo!(),
-        // ItemTreeIdx::Use(_) => todo!(),
-        _ => {}
-    }
+/// The body of a single function, lowered and type checked on its own
+#[derive(Debug, Clone)]
+pub struct LoweredFnBody {
+    /// Bodies containing only this function's, so its expressions and patterns are numbered from zero
+    pub bodies: PackageBodies,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The body with the type of every expression in it, if [`Config::debug_bodies`] is set
+    pub debug: Option<Diagnostic>,
 }
 
-fn lower_apply_bodies(
-    apply_id: id::ApplyDecl,
-    ctx: &LoweringCtx,
-    item_resolver: &ItemResolver,
-    trait_resolver: &TraitResolver,
-    method_resolver: &MethodResolver,
-    exprs: &mut Map<id::Expr, Typed<Expr>>,
-    interner: &'static Interner,
-    diagnostics: &mut Vec<Diagnostic>,
-    source_cache: &SourceCache,
-) {
-    let apply_decl = ctx.item_tree.applies.get(apply_id);
-
-    apply_decl.methods.iter().for_each(|method_id| {
-        lower_function_body(
-            Some(apply_decl),
-            *method_id,
-            ctx,
-            item_resolver,
-            trait_resolver,
-            method_resolver,
-            exprs,
-            interner,
-            diagnostics,
-            source_cache,
-        );
-    });
-}
-
-fn lower_function_body(
+pub(crate) fn lower_function_body(
     apply_decl: Option<&ApplyDecl>,
     function_id: id::FnDecl,
     ctx: &LoweringCtx,
     item_resolver: &ItemResolver,
     trait_resolver: &TraitResolver,
     method_resolver: &MethodResolver,
-    exprs: &mut Map<id::Expr, Typed<Expr>>,
+    config: &Config,
     interner: &'static Interner,
-    diagnostics: &mut Vec<Diagnostic>,
-    source_cache: &SourceCache,
-) {
+) -> LoweredFnBody {
+    let mut bodies = PackageBodies::new();
+    let mut diagnostics = vec![];
     let fn_decl = ctx.item_tree.functions.get(function_id);
     let mut tenv = TEnv::new(trait_resolver, method_resolver, interner);
 
@@ -178,72 +121,65 @@ fn lower_function_body(
     let mut expr_lowerer = expr::LoweringCtx::new(
         ctx.file_id,
         ctx.mod_id,
-        exprs,
+        &mut bodies.exprs,
+        &mut bodies.expr_spans,
+        &mut bodies.pats,
         ctx.packages,
         &mut tenv,
         this_ctx,
         item_resolver,
         interner,
-        diagnostics,
+        &mut diagnostics,
     );
     let body = expr_lowerer.lower(ast.body(), &fn_decl.generic_params);
+    expr_lowerer.report_unused_locals();
     let return_ty = fn_decl.return_ty.clone();
     let return_ty = expr_lowerer.tenv.insert(return_ty);
 
-    let body_tid = exprs.get(*body).tid;
+    let body_tid = bodies.exprs.get(*body).tid;
     tenv.add_equality(body_tid, return_ty);
 
-    let exprs_copy = exprs.clone();
+    let fn_exprs: Vec<_> = bodies.exprs.keys().collect();
 
-    let unresolved_expressions = loop {
-        let initial_unresolved_expressions = mem::take(exprs.as_mut());
-        let num_initial_unresolved_expressions = initial_unresolved_expressions.len();
-
-        let final_unresolved_expressions: Vec<_> = initial_unresolved_expressions
-            .into_iter()
-            .filter(|unresolved_expr| tenv.resolve(unresolved_expr.tid).is_err())
-            .collect();
+    // Resolving one type can make progress on others, so keep going until nothing changes
+    let mut unresolved_exprs = fn_exprs.clone();
+    loop {
+        let num_initial_unresolved_exprs = unresolved_exprs.len();
+        unresolved_exprs.retain(|expr| tenv.resolve(bodies.exprs.get(*expr).tid).is_err());
 
-        // println!(
-        //     "{} vs {}",
-        //     final_unresolved_expressions.len(),
-        //     num_initial_unresolved_expressions
-        // );
+        if unresolved_exprs.is_empty() || unresolved_exprs.len() == num_initial_unresolved_exprs
 
-        if final_unresolved_expressions.len() == 0
-            || final_unresolved_expressions.len() == num_initial_unresolved_expressions
         {
-            break final_unresolved_expressions;
+            break;
         }
+    }
 
-        *exprs.as_mut() = final_unresolved_expressions;
-    };
-
-    for expr in exprs_copy.values() {
-        // println!("resolving {:?}", expr.inner);
-        match tenv.resolve(expr.tid) {
-            Ok(tkind) => {}
+    let mut tids_reported = HashSet::new();
+    for expr in &fn_exprs {
+        let tid = bodies.exprs.get(*expr).tid;
+        let tkind = match tenv.resolve(tid) {
+            Ok(tkind) => tkind,
+            Err(_) if !tids_reported.insert(tid) => TypeKind::Unknown,
             Err(unresolved_restrictions) => {
                 if unresolved_restrictions.is_empty() {
                     diagnostics.push(
                         TypeError::CouldNotInfer {
                             ty: (),
-                            ty_file_span: tenv.get_span(expr.tid).in_file(ctx.file_id),
+                            ty_file_span: tenv.get_span(tid).in_file(ctx.file_id),
                         }
                         .to_diagnostic(),
                     );
                 } else {
                     for unresolved_restriction in unresolved_restrictions {
-                        // println!("{:#?}", unresolved_restriction);
                         match unresolved_restriction {
                             Restriction::Equals(other) => diagnostics.push(
                                 TypeError::TypeMismatch {
-                                    a: tenv.fmt_tid(expr.tid),
-                                    a_file_span: tenv.get_span(expr.tid).in_file(ctx.file_id),
+                                    a: tenv.fmt_tid(tid),
+                                    a_file_span: tenv.get_span(tid).in_file(ctx.file_id),
                                     b: tenv.fmt_tid(other),
                                     b_file_span: tenv.get_span(other).in_file(ctx.file_id),
                                     span: (),
-                                    span_file_span: tenv.get_span(expr.tid).in_file(ctx.file_id),
+                                    span_file_span: tenv.get_span(tid).in_file(ctx.file_id),
                                 }
                                 .to_diagnostic(),
                             ),
@@ -251,7 +187,7 @@ fn lower_function_body(
                                 diagnostics.push(
                                     TypeError::CouldBeMultipleTypes {
                                         ty: (),
-                                        ty_file_span: tenv.get_span(expr.tid).in_file(ctx.file_id),
+                                        ty_file_span: tenv.get_span(tid).in_file(ctx.file_id),
                                         potential_types: types
                                             .into_iter()
                                             .map(|tkind| tenv.fmt_typekind(&tkind))
@@ -260,24 +196,103 @@ fn lower_function_body(
                                     .to_diagnostic(),
                                 );
                             }
+                            // These are logged rather than printed, since stdout belongs to the language server
+                            // when it is the one lowering bodies
                             Restriction::AssocTypeOf(_, _, _) => {
-                                println!("HELLOOOOOO");
+                                warn!("unresolved associated type restriction");
                             }
                             Restriction::Field(_) => todo!(),
                             Restriction::Trait(trait_restriction) => {
-                                println!(
-                                    "Trait restriction: {}",
+                                warn!(
+                                    "unresolved trait restriction: {}",
                                     tenv.fmt_trait_restriction(&trait_restriction)
                                 )
                             }
                         }
                     }
                 }
+                TypeKind::Unknown
             }
-        }
+        };
+        bodies.types.insert(tkind.at(tenv.get_span(tid)));
     }
 
-    format_function_with_types(*body, &exprs_copy, &mut tenv, source_cache, ctx.file_id);
+    // Patterns are always equated with the expression they match against, so any error in their types has already
+    // been reported for that expression
+    let fn_pats: Vec<_> = bodies.pats.keys().collect();
+    for pat in &fn_pats {
+        let tid = bodies.pats.get(*pat).tid;
+        let tkind = tenv.resolve(tid).unwrap_or(TypeKind::Unknown);
+        bodies.pat_types.insert(tkind.at(tenv.get_span(tid)));
+    }
+
+    // Patterns in a body with errors may not fit the values they are matched against
+    if !diagnostics.iter().any(Diagnostic::is_error) {
+        exhaustiveness::check_matches(
+            &fn_exprs,
+            &bodies,
+            item_resolver,
+            ctx.mod_id,
+            ctx.file_id,
+            interner,
+            &mut diagnostics,
+        );
+        report_unnecessary_casts(&fn_exprs, &bodies, &tenv, ctx.file_id, &mut diagnostics);
+    }
+
+    let debug = config.debug_bodies.then(|| {
+        format_function_with_types(*body, &bodies.exprs, &fn_exprs, &mut tenv, ctx.file_id)
+    });
+
+    bodies.fn_bodies.insert(
+        function_id,
+        FnBody::new(ctx.mod_id, ctx.file_id, *body, id::Expr::from(0)),
+    );
+
+    LoweredFnBody {
+        bodies,
+        diagnostics,
+        debug,
+    }
+}
+
+/// Casts of values to the type they already are
+fn report_unnecessary_casts(
+    exprs: &[id::Expr],
+    bodies: &PackageBodies,
+    tenv: &TEnv,
+    file_id: FileId,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    for expr in exprs {
+        let Expr::Cast(cast) = &bodies.exprs.get(*expr).inner else {
+            continue;
+        };
+        let ty = &bodies.types.get(*expr).inner;
+        if *ty == TypeKind::Unknown || *ty != bodies.types.get(cast.val).inner {
+            continue;
+        }
+        let Some(val_span) = bodies.expr_spans.get(&cast.val) else {
+            continue;
+        };
+        // Everything between the value and the end of the type is the cast, so removing it leaves the value,
+        // parentheses and all
+        let (val_start, val_end) = (val_span.range.start(), val_span.range.end());
+        let cast_end = tenv.get_span(cast.to_ty).range.end();
+        let cast_to = Span::new(u32::from(val_end)..u32::from(cast_end));
+        diagnostics.push(
+            LowerError::UnnecessaryCast {
+                cast: (),
+                cast_file_span: Span::new(u32::from(val_start)..u32::from(cast_end))
+                    .in_file(file_id),
+                cast_to: (),
+                cast_to_file_span: cast_to.in_file(file_id),
+                ty: tenv.fmt_typekind(ty),
+                replacement: Some(String::new()),
+            }
+            .to_diagnostic(),
+        );
+    }
 }
 
 /*
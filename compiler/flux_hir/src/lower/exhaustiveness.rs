@@ -0,0 +1,520 @@
+//! Exhaustiveness and reachability checking for `match` expressions
+//!
+//! This is the usefulness algorithm described in "Warnings for pattern matching" by Luc Maranget. A pattern vector
+//! is useful with respect to a matrix of pattern rows if there is a value it matches that none of the rows match. A
+//! match is exhaustive if a wildcard is not useful with respect to all of its arms, and an arm is reachable if it is
+//! useful with respect to the arms before it. Along the way, the values that make a pattern useful are built up into
+//! witnesses, which are reported as the patterns missing from a match.
+//!
+//! The constructors a column of patterns could have are worked out from the first pattern in the column that is not
+//! a wildcard, so no types have to be resolved. A column of nothing but wildcards never needs to be split.
+
+use std::{fmt::Display, rc::Rc};
+
+use flux_diagnostics::{Diagnostic, ToDiagnostic};
+use flux_id::id::{self, WithMod};
+use flux_typesystem::{ConcreteKind, TypeKind};
+use flux_util::{FileId, Interner, WithSpan};
+
+use crate::{
+    body::PackageBodies,
+    builtin::{BuiltinSInt, BuiltinType, BuiltinUInt},
+    def::{
+        expr::{Expr, Match},
+        pat::Pat,
+    },
+    diagnostics::LowerError,
+    name_res::item::ItemResolver,
+};
+
+/// Listing every missing pattern of a large enum or integer type isn't helpful, so only this many are reported
+const MAX_WITNESSES: usize = 3;
+
+/// Check every `match` expression in `exprs`, which should all belong to a function body that lowered without errors
+pub(super) fn check_matches(
+    exprs: &[id::Expr],
+    bodies: &PackageBodies,
+    item_resolver: &ItemResolver,
+    mod_id: id::Mod,
+    file_id: FileId,
+    interner: &'static Interner,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let checker = Checker {
+        bodies,
+        item_resolver,
+        mod_id,
+        interner,
+    };
+    for expr in exprs {
+        if let Expr::Match(match_expr) = &bodies.get_expr(*expr).inner {
+            checker.check_match(match_expr, file_id, diagnostics);
+        }
+    }
+}
+
+struct Checker<'a> {
+    bodies: &'a PackageBodies,
+    item_resolver: &'a ItemResolver<'a>,
+    mod_id: id::Mod,
+    interner: &'static Interner,
+}
+
+impl Checker<'_> {
+    fn check_match(&self, match_expr: &Match, file_id: FileId, diagnostics: &mut Vec<Diagnostic>) {
+        let rows: Option<Vec<_>> = match_expr
+            .arms
+            .iter()
+            .map(|arm| self.lower_pat(*arm.pat).map(|pat| vec![pat]))
+            .collect();
+        let rows = match rows {
+            Some(rows) => rows,
+            None => return,
+        };
+
+        for (i, row) in rows.iter().enumerate() {
+            if useful(&rows[..i], row).is_empty() {
+                let arm = &match_expr.arms[i];
+                diagnostics.push(
+                    LowerError::UnreachableMatchArm {
+                        arm: (),
+                        arm_file_span: arm.pat.span.in_file(file_id),
+                    }
+                    .to_diagnostic(),
+                );
+            }
+        }
+
+        let witnesses = useful(&rows, &[DeconPat::Wild]);
+        if !witnesses.is_empty() {
+            let missing_count = witnesses.len();
+            let missing = witnesses
+                .into_iter()
+                .take(MAX_WITNESSES)
+                .map(|mut witness| witness.pop().unwrap_or(Witness::Wild).to_string())
+                .collect();
+            diagnostics.push(
+                LowerError::NonExhaustiveMatch {
+                    scrutinee: (),
+                    scrutinee_file_span: match_expr.scrutinee.span.in_file(file_id),
+                    missing,
+                    missing_count,
+                }
+                .to_diagnostic(),
+            );
+        }
+    }
+
+    /// Strip a pattern down to its constructors, or `None` if it contains a pattern that failed to lower
+    fn lower_pat(&self, pat: id::Pat) -> Option<DeconPat> {
+        let ctor = match &self.bodies.get_pat(pat).inner {
+            Pat::Wildcard | Pat::Bind(_) => return Some(DeconPat::Wild),
+            Pat::Int(val) => {
+                let (min, max) = self.int_range(&self.bodies.get_pat_type(pat).inner);
+                let val = *val as i128;
+                DeconCtor {
+                    ctor: Ctor::Range(val, val),
+                    fields: vec![],
+                    shape: Rc::new(Shape::Int { min, max }),
+                }
+            }
+            Pat::Bool(val) => DeconCtor {
+                ctor: Ctor::Bool(*val),
+                fields: vec![],
+                shape: Rc::new(Shape::Bool),
+            },
+            Pat::Variant(variant) => {
+                let (_, enum_decl) = self
+                    .item_resolver
+                    .resolve_enum((&variant.path.inner).in_mod(self.mod_id))
+                    .ok()?;
+                let fields = match variant.payload {
+                    Some(payload) => vec![self.lower_pat(payload)?],
+                    None => vec![],
+                };
+                DeconCtor {
+                    ctor: Ctor::Variant(variant.discriminant),
+                    fields,
+                    shape: Rc::new(Shape::Enum {
+                        name: variant.path.to_string(self.interner),
+                        variants: enum_decl
+                            .variants
+                            .iter()
+                            .map(|decl_variant| {
+                                (
+                                    self.interner.resolve(&decl_variant.name).to_string(),
+                                    decl_variant.ty.is_some() as usize,
+                                )
+                            })
+                            .collect(),
+                    }),
+                }
+            }
+            Pat::Tuple(pats) => DeconCtor {
+                ctor: Ctor::Single,
+                fields: pats
+                    .iter()
+                    .map(|pat| self.lower_pat(*pat))
+                    .collect::<Option<_>>()?,
+                shape: Rc::new(Shape::Tuple(pats.len())),
+            },
+            Pat::Struct(struct_pat) => {
+                let struct_decl = self
+                    .item_resolver
+                    .resolve_struct((&struct_pat.path.inner).in_mod(self.mod_id))
+                    .ok()?;
+                // Fields left out of the pattern match anything
+                let fields = struct_decl
+                    .fields
+                    .iter()
+                    .map(|field_decl| {
+                        match struct_pat
+                            .fields
+                            .iter()
+                            .find(|field| field.name.inner == field_decl.name.inner)
+                        {
+                            Some(field) => self.lower_pat(field.pat),
+                            None => Some(DeconPat::Wild),
+                        }
+                    })
+                    .collect::<Option<_>>()?;
+                DeconCtor {
+                    ctor: Ctor::Single,
+                    fields,
+                    shape: Rc::new(Shape::Struct {
+                        name: struct_pat.path.to_string(self.interner),
+                        fields: struct_decl
+                            .fields
+                            .iter()
+                            .map(|field| self.interner.resolve(&field.name).to_string())
+                            .collect(),
+                    }),
+                }
+            }
+            Pat::Poisoned => return None,
+        };
+        Some(DeconPat::Ctor(ctor))
+    }
+
+    /// The values an integer pattern of type `ty` can match
+    ///
+    /// Integer literals that are never given a concrete type are matched as if they were `u64`s.
+    fn int_range(&self, ty: &TypeKind) -> (i128, i128) {
+        let name = match ty {
+            TypeKind::Concrete(ConcreteKind::Path(path)) if path.len() == 1 => path.get_nth(0),
+            _ => return (0, u64::MAX as i128),
+        };
+        let builtin = BuiltinType::all(self.interner)
+            .iter()
+            .find(|(builtin_name, _)| builtin_name == name)
+            .map(|(_, builtin)| builtin);
+        match builtin {
+            Some(BuiltinType::UInt(BuiltinUInt::U8)) => (0, u8::MAX as i128),
+            Some(BuiltinType::UInt(BuiltinUInt::U16)) => (0, u16::MAX as i128),
+            Some(BuiltinType::UInt(BuiltinUInt::U32)) => (0, u32::MAX as i128),
+            Some(BuiltinType::SInt(BuiltinSInt::S8)) => (i8::MIN as i128, i8::MAX as i128),
+            Some(BuiltinType::SInt(BuiltinSInt::S16)) => (i16::MIN as i128, i16::MAX as i128),
+            Some(BuiltinType::SInt(BuiltinSInt::S32)) => (i32::MIN as i128, i32::MAX as i128),
+            Some(BuiltinType::SInt(BuiltinSInt::S64)) => (i64::MIN as i128, i64::MAX as i128),
+            _ => (0, u64::MAX as i128),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum DeconPat {
+    Wild,
+    Ctor(DeconCtor),
+}
+
+#[derive(Debug, Clone)]
+struct DeconCtor {
+    ctor: Ctor,
+    fields: Vec<DeconPat>,
+    shape: Rc<Shape>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ctor {
+    Variant(u32),
+    /// Structs and tuples only have one constructor
+    Single,
+    /// An inclusive range of integers
+    Range(i128, i128),
+    Bool(bool),
+}
+
+impl Ctor {
+    /// Whether every value built with `other` is also built with `self`
+    ///
+    /// Ranges are always split so that they either contain each other or don't overlap at all.
+    fn covers(&self, other: &Ctor) -> bool {
+        match (self, other) {
+            (Ctor::Range(lo, hi), Ctor::Range(other_lo, other_hi)) => {
+                lo <= other_lo && other_hi <= hi
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// The constructors a column of patterns can have, and how to print them
+#[derive(Debug)]
+enum Shape {
+    Enum {
+        name: String,
+        /// The name of each variant, along with how many values it holds
+        variants: Vec<(String, usize)>,
+    },
+    Struct {
+        name: String,
+        fields: Vec<String>,
+    },
+    Tuple(usize),
+    Int {
+        min: i128,
+        max: i128,
+    },
+    Bool,
+}
+
+impl Shape {
+    fn arity(&self, ctor: &Ctor) -> usize {
+        match (self, ctor) {
+            (Shape::Enum { variants, .. }, Ctor::Variant(discriminant)) => variants
+                .get(*discriminant as usize)
+                .map_or(0, |(_, arity)| *arity),
+            (Shape::Struct { fields, .. }, _) => fields.len(),
+            (Shape::Tuple(len), _) => *len,
+            _ => 0,
+        }
+    }
+
+    /// Every constructor of the shape, with integer ranges split up by the ranges in `column` so that each one is
+    /// either entirely covered by a pattern in the column or not at all
+    fn all_ctors(&self, column: &[&Ctor]) -> Vec<Ctor> {
+        match self {
+            Shape::Enum { variants, .. } => (0..variants.len() as u32).map(Ctor::Variant).collect(),
+            Shape::Struct { .. } | Shape::Tuple(_) => vec![Ctor::Single],
+            Shape::Int { min, max } => split_range(*min, *max, column),
+            Shape::Bool => vec![Ctor::Bool(false), Ctor::Bool(true)],
+        }
+    }
+
+    /// The constructors covered by `ctor`, split up in the same way as [`Shape::all_ctors`]
+    fn split(&self, ctor: &Ctor, column: &[&Ctor]) -> Vec<Ctor> {
+        match (self, ctor) {
+            (Shape::Int { min, max }, Ctor::Range(lo, hi)) => {
+                split_range(*lo.max(min), *hi.min(max), column)
+            }
+            _ => vec![*ctor],
+        }
+    }
+}
+
+/// Split `lo..=hi` at the start and just past the end of every range in `column`
+fn split_range(lo: i128, hi: i128, column: &[&Ctor]) -> Vec<Ctor> {
+    if lo > hi {
+        return vec![];
+    }
+    let mut boundaries: Vec<_> = column
+        .iter()
+        .filter_map(|ctor| match ctor {
+            Ctor::Range(start, end) => Some([*start, end + 1]),
+            _ => None,
+        })
+        .flatten()
+        .filter(|boundary| lo < *boundary && *boundary <= hi)
+        .collect();
+    boundaries.push(lo);
+    boundaries.push(hi + 1);
+    boundaries.sort();
+    boundaries.dedup();
+    boundaries
+        .windows(2)
+        .map(|window| Ctor::Range(window[0], window[1] - 1))
+        .collect()
+}
+
+/// Every vector of values matched by `v` but not by any row in `rows`, written as patterns
+///
+/// The list is empty exactly when `v` is not useful.
+fn useful(rows: &[Vec<DeconPat>], v: &[DeconPat]) -> Vec<Vec<Witness>> {
+    let (head, tail) = match v.split_first() {
+        Some(split) => split,
+        None if rows.is_empty() => return vec![vec![]],
+        None => return vec![],
+    };
+    if rows.is_empty() {
+        return vec![vec![Witness::Wild; v.len()]];
+    }
+
+    let column: Vec<_> = rows
+        .iter()
+        .filter_map(|row| match &row[0] {
+            DeconPat::Ctor(ctor) => Some(ctor),
+            DeconPat::Wild => None,
+        })
+        .collect();
+    let shape = match head {
+        DeconPat::Ctor(ctor) => Some(&ctor.shape),
+        DeconPat::Wild => column.first().map(|ctor| &ctor.shape),
+    };
+    let shape = match shape {
+        Some(shape) => shape.clone(),
+        // Nothing in the column distinguishes between values, so only the rows that start with a wildcard matter
+        None => {
+            let rows: Vec<_> = rows.iter().map(|row| row[1..].to_vec()).collect();
+            return useful(&rows, tail)
+                .into_iter()
+                .map(|witness| [vec![Witness::Wild], witness].concat())
+                .collect();
+        }
+    };
+
+    let column_ctors: Vec<_> = column.iter().map(|ctor| &ctor.ctor).collect();
+    let ctors = match head {
+        DeconPat::Ctor(head) => shape.split(&head.ctor, &column_ctors),
+        DeconPat::Wild => shape.all_ctors(&column_ctors),
+    };
+
+    let mut witnesses = vec![];
+    for ctor in ctors {
+        let arity = shape.arity(&ctor);
+        let rows: Vec<_> = rows
+            .iter()
+            .filter_map(|row| specialize(row, &ctor, arity))
+            .collect();
+        let v = specialize(v, &ctor, arity).unwrap_or_default();
+        for mut witness in useful(&rows, &v) {
+            let rest = witness.split_off(arity);
+            witnesses.push(
+                [
+                    vec![Witness::Ctor {
+                        ctor,
+                        fields: witness,
+                        shape: shape.clone(),
+                    }],
+                    rest,
+                ]
+                .concat(),
+            );
+        }
+    }
+    witnesses
+}
+
+/// Replace the first pattern of `row` with its fields if it could have been built with `ctor`
+fn specialize(row: &[DeconPat], ctor: &Ctor, arity: usize) -> Option<Vec<DeconPat>> {
+    let (head, tail) = row.split_first()?;
+    let fields = match head {
+        DeconPat::Wild => vec![DeconPat::Wild; arity],
+        DeconPat::Ctor(head) if head.ctor.covers(ctor) => head.fields.clone(),
+        DeconPat::Ctor(_) => return None,
+    };
+    Some([fields, tail.to_vec()].concat())
+}
+
+/// A pattern matching values that are not matched by an arm
+#[derive(Debug, Clone)]
+enum Witness {
+    Wild,
+    Ctor {
+        ctor: Ctor,
+        fields: Vec<Witness>,
+        shape: Rc<Shape>,
+    },
+}
+
+impl Display for Witness {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (ctor, fields, shape) = match self {
+            Witness::Wild => return write!(f, "_"),
+            Witness::Ctor {
+                ctor,
+                fields,
+                shape,
+            } => (ctor, fields, shape),
+        };
+        match (shape.as_ref(), ctor) {
+            (Shape::Enum { name, variants }, Ctor::Variant(discriminant)) => {
+                let (variant, _) = &variants[*discriminant as usize];
+                write!(f, "{name}::{variant}")?;
+                if let Some(payload) = fields.first() {
+                    write!(f, "({payload})")?;
+                }
+                Ok(())
+            }
+            (
+                Shape::Struct {
+                    name,
+                    fields: names,
+                },
+                _,
+            ) => {
+                let fields = names
+                    .iter()
+                    .zip(fields)
+                    .map(|(name, field)| format!("{name}: {field}"))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "{name} {{ {fields} }}")
+            }
+            (Shape::Tuple(1), _) => write!(f, "({},)", fields[0]),
+            (Shape::Tuple(_), _) => {
+                let fields = fields
+                    .iter()
+                    .map(|field| field.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                write!(f, "({fields})")
+            }
+            // Any value in the range would do, and the start of it is as good as any
+            (Shape::Int { .. }, Ctor::Range(lo, _)) => write!(f, "{lo}"),
+            (Shape::Bool, Ctor::Bool(val)) => write!(f, "{val}"),
+            _ => write!(f, "_"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use super::{split_range, useful, Ctor, DeconCtor, DeconPat, Shape};
+
+    fn bool_pat(val: bool) -> DeconPat {
+        DeconPat::Ctor(DeconCtor {
+            ctor: Ctor::Bool(val),
+            fields: vec![],
+            shape: Rc::new(Shape::Bool),
+        })
+    }
+
+    #[test]
+    fn ranges_split_at_patterns() {
+        let column = [&Ctor::Range(2, 3), &Ctor::Range(5, 5)];
+        assert_eq!(
+            split_range(0, 9, &column),
+            vec![
+                Ctor::Range(0, 1),
+                Ctor::Range(2, 3),
+                Ctor::Range(4, 4),
+                Ctor::Range(5, 5),
+                Ctor::Range(6, 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_bool_is_witnessed() {
+        let rows = vec![vec![bool_pat(true)]];
+        let witnesses = useful(&rows, &[DeconPat::Wild]);
+        assert_eq!(witnesses.len(), 1);
+        assert_eq!(witnesses[0][0].to_string(), "false");
+
+        let rows = vec![vec![bool_pat(true)], vec![bool_pat(false)]];
+        assert!(useful(&rows, &[DeconPat::Wild]).is_empty());
+        assert!(useful(&rows, &[bool_pat(true)]).is_empty());
+    }
+}
@@ -1,13 +1,13 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
-use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_diagnostics::{closest_name, ice, Diagnostic, ToDiagnostic};
 use flux_id::{
     id::{self, WithMod, WithPackage},
     Map,
 };
 use flux_parser::{
     ast::{self, AstNode},
-    syntax::SyntaxToken,
+    syntax::{SyntaxKind, SyntaxToken},
 };
 use flux_typesystem::{
     ConcreteKind, FnSignature, TEnv, ThisCtx, ThisPath, TraitRestriction, Type, TypeKind, Typed,
@@ -16,33 +16,72 @@ use flux_typesystem::{
 use flux_util::{
     FileId, FileSpanned, InFile, Interner, Path, Span, Spanned, ToSpan, WithSpan, Word,
 };
+use itertools::Itertools;
 
 use crate::{
     builtin,
     def::{
-        expr::{Assignment, BinOp, Call, Cast, Expr, If, Intrinsic, MemberAccess, Op, StructExpr},
-        item::StructDecl,
-        GenericParams, StructExprField, StructExprFieldList,
+        expr::{
+            Assignment, BinOp, Block, Break, Call, Cast, Continue, EnumExpr, Expr, Float, If,
+            Intrinsic, Let, Loop, Match, MatchArm, MemberAccess, Op, StructExpr,
+        },
+        item::{EnumDecl, StructDecl},
+        pat::{Pat, StructPat, StructPatField, VariantPat},
+        GenericParams, StructExprField, StructExprFieldList, StructFieldDeclList,
     },
     diagnostics::LowerError,
     intrinsics,
     item::ItemTreeIdx,
-    name_res::item::ItemResolver,
+    name_res::{diagnostics::ResolutionError, item::ItemResolver},
     Package,
 };
 
-use super::{lower_node_mut, r#type};
+use super::{lower_node_mut, lower_optional_node_mut, r#type};
 
 pub(super) struct LoweringCtx<'a, 'res> {
     type_lowerer: r#type::LoweringCtx,
     file_id: FileId,
     mod_id: id::Mod,
     exprs: &'a mut Map<id::Expr, Typed<Expr>>,
+    expr_spans: &'a mut HashMap<id::Expr, Span>,
+    pats: &'a mut Map<id::Pat, Typed<Pat>>,
     packages: &'a Map<id::Pkg, Package>,
     pub(super) tenv: &'a mut TEnv<'res>,
     item_resolver: &'a ItemResolver<'a>,
     interner: &'static Interner,
     diagnostics: &'a mut Vec<Diagnostic>,
+    /// The loops surrounding the expression being lowered, from outermost to innermost
+    loops: Vec<LoopScope>,
+    /// Every local declared in the body, along with whether it has been referred to
+    declared_locals: Vec<(Spanned<Word>, bool)>,
+    /// The declarations of the locals in scope by name, which are pushed and popped along with the scopes of `tenv`
+    local_scopes: Vec<HashMap<Word, usize>>,
+}
+
+/// A loop that the `break` and `continue` expressions in its body can refer to
+struct LoopScope {
+    expr: id::Expr,
+    label: Option<Word>,
+    tid: id::Ty,
+    kind: LoopKind,
+    has_break: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LoopKind {
+    Loop,
+    While,
+    For,
+}
+
+impl LoopKind {
+    fn keyword(&self) -> &'static str {
+        match self {
+            LoopKind::Loop => "loop",
+            LoopKind::While => "while",
+            LoopKind::For => "for",
+        }
+    }
 }
 
 impl<'a, 'res> LoweringCtx<'a, 'res> {
@@ -50,6 +89,8 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         file_id: FileId,
         mod_id: id::Mod,
         exprs: &'a mut Map<id::Expr, Typed<Expr>>,
+        expr_spans: &'a mut HashMap<id::Expr, Span>,
+        pats: &'a mut Map<id::Pat, Typed<Pat>>,
         packages: &'a Map<id::Pkg, Package>,
         tenv: &'a mut TEnv<'res>,
         this_ctx: ThisCtx,
@@ -62,11 +103,54 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
             file_id,
             mod_id,
             exprs,
+            expr_spans,
+            pats,
             tenv,
             packages,
             item_resolver,
             interner,
             diagnostics,
+            loops: vec![],
+            declared_locals: vec![],
+            local_scopes: vec![HashMap::new()],
+        }
+    }
+
+    /// Declare a local, which is reported by [`LoweringCtx::report_unused_locals`] if nothing refers to it
+    fn declare_local(&mut self, name: &Spanned<Word>, tid: id::Ty) {
+        self.tenv.insert_local(name.inner, tid);
+        self.declared_locals.push((name.clone(), false));
+        let idx = self.declared_locals.len() - 1;
+        self.local_scopes
+            .last_mut()
+            .unwrap_or_else(|| ice("there should always be a local scope"))
+            .insert(name.inner, idx);
+    }
+
+    fn push_scope(&mut self) {
+        self.tenv.push_scope();
+        let scope = self.local_scopes.last().cloned().unwrap_or_default();
+        self.local_scopes.push(scope);
+    }
+
+    fn pop_scope(&mut self) {
+        self.tenv.pop_scope();
+        self.local_scopes.pop();
+    }
+
+    pub(super) fn report_unused_locals(&mut self) {
+        for (name, _) in self.declared_locals.iter().filter(|(_, used)| !used) {
+            if self.type_lowerer.is_poisoned_name(&name.inner) {
+                continue;
+            }
+            let local = self.interner.resolve(&name.inner);
+            self.diagnostics.push(
+                LowerError::UnusedLocal {
+                    local: local.to_string(),
+                    local_file_span: name.span.in_file(self.file_id),
+                }
+                .to_diagnostic(),
+            );
         }
     }
 
@@ -89,13 +173,16 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
             },
             |this, expr| {
                 let span = expr.range().to_span();
-                match expr {
+                let expr = match expr {
                     ast::Expr::PathExpr(path_expr) => {
                         this.lower_path_expr(path_expr, generic_params)
                     }
-                    ast::Expr::ParenExpr(_) => todo!(),
-                    ast::Expr::FloatExpr(_) => todo!(),
+                    ast::Expr::ParenExpr(paren_expr) => {
+                        this.lower_paren_expr(paren_expr, generic_params)
+                    }
+                    ast::Expr::FloatExpr(float_expr) => this.lower_float_expr(float_expr),
                     ast::Expr::IntExpr(int_expr) => this.lower_int_expr(int_expr),
+                    ast::Expr::BoolExpr(bool_expr) => this.lower_bool_expr(bool_expr),
                     ast::Expr::BinExpr(bin_expr) => this.lower_bin_expr(bin_expr, generic_params),
                     ast::Expr::CallExpr(call_expr) => {
                         this.lower_call_expr(call_expr, generic_params)
@@ -106,9 +193,13 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                     ast::Expr::BlockExpr(block_expr) => {
                         this.lower_block_expr(block_expr, generic_params)
                     }
-                    ast::Expr::TupleExpr(_) => todo!(),
-                    ast::Expr::AddressExpr(_) => todo!(),
-                    ast::Expr::IdxExpr(_) => todo!(),
+                    ast::Expr::TupleExpr(tuple_expr) => {
+                        this.lower_tuple_expr(tuple_expr, generic_params)
+                    }
+                    ast::Expr::AddressExpr(address_expr) => {
+                        this.lower_address_expr(address_expr, generic_params)
+                    }
+                    ast::Expr::IdxExpr(idx_expr) => this.lower_idx_expr(idx_expr, generic_params),
                     ast::Expr::MemberAccessExpr(member_access_expr) => {
                         this.lower_member_access_expr(member_access_expr, generic_params)
                     }
@@ -116,12 +207,29 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                     ast::Expr::IntrinsicExpr(intrinsic_expr) => {
                         this.lower_intrinsic_expr(intrinsic_expr, generic_params)
                     }
-                    ast::Expr::StringExpr(_) => todo!(),
+                    ast::Expr::StringExpr(string_expr) => this.lower_string_expr(string_expr),
                     ast::Expr::CastExpr(cast_expr) => {
                         this.lower_cast_expr(cast_expr, generic_params)
                     }
-                }
-                .at(span)
+                    ast::Expr::MatchExpr(match_expr) => {
+                        this.lower_match_expr(match_expr, generic_params)
+                    }
+                    ast::Expr::WhileExpr(while_expr) => {
+                        this.lower_while_expr(while_expr, generic_params)
+                    }
+                    ast::Expr::LoopExpr(loop_expr) => {
+                        this.lower_loop_expr(loop_expr, generic_params)
+                    }
+                    ast::Expr::ForExpr(for_expr) => this.lower_for_expr(for_expr, generic_params),
+                    ast::Expr::BreakExpr(break_expr) => {
+                        this.lower_break_expr(break_expr, generic_params)
+                    }
+                    ast::Expr::ContinueExpr(continue_expr) => {
+                        this.lower_continue_expr(continue_expr)
+                    }
+                };
+                this.expr_spans.insert(expr, span);
+                expr.at(span)
             },
         )
     }
@@ -133,10 +241,11 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
     ) -> (bool, Spanned<id::Expr>) {
         let span = stmt.range().to_span();
         match stmt {
-            ast::Stmt::LetStmt(let_stmt) => (
-                false,
-                self.lower_let_expr(let_stmt, generic_params).at(span),
-            ),
+            ast::Stmt::LetStmt(let_stmt) => {
+                let expr = self.lower_let_expr(let_stmt, generic_params);
+                self.expr_spans.insert(expr, span);
+                (false, expr.at(span))
+            }
             ast::Stmt::ExprStmt(expr_stmt) => (false, self.lower(expr_stmt.expr(), generic_params)),
             ast::Stmt::TerminatorExprStmt(terminator_expr_stmt) => (
                 true,
@@ -167,8 +276,9 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         let tid = self
             .tenv
             .insert(Type::unit().at(let_stmt.range().to_span()));
-        self.tenv.insert_local(name.inner, ty);
-        self.exprs.insert(Expr::unit().with_type(tid))
+        self.declare_local(&name, ty);
+        self.exprs
+            .insert(Expr::Let(Let::new(name, *val)).with_type(tid))
     }
 
     fn lower_path_expr(
@@ -179,18 +289,27 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         let path = self
             .type_lowerer
             .lower_path(path_expr.path(), generic_params);
+        if let Some(expr) = self.try_lower_enum_expr(&path, None, generic_params) {
+            return expr;
+        }
+
         let file_id = self.file_id;
         let span = path.span;
         let path = path.map(|path| path.map_args(|arg| self.tenv.insert(arg.at(span))));
 
         let tid = (path.len() == 1)
-            .then(|| self.tenv.try_get_local(path.get_nth(0)).cloned())
+            .then(|| self.use_local(path.get_nth(0)))
             .flatten()
+            .or_else(|| self.resolve_function_path(&path))
             .unwrap_or_else(|| {
+                let candidate = (path.len() == 1)
+                    .then(|| self.closest_in_scope(path.get_nth(0)))
+                    .flatten();
                 self.diagnostics.push(
                     LowerError::UnknownLocal {
                         local: path.to_string(self.interner),
                         local_file_span: path.span.in_file(file_id),
+                        candidate,
                     }
                     .to_diagnostic(),
                 );
@@ -200,6 +319,69 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         self.exprs.insert(Expr::Path(path.inner).with_type(tid))
     }
 
+    fn use_local(&mut self, name: &Word) -> Option<id::Ty> {
+        let tid = *self.tenv.try_get_local(name)?;
+        if let Some(idx) = self.local_scopes.last().and_then(|scope| scope.get(name)) {
+            self.declared_locals[*idx].1 = true;
+        }
+        Some(tid)
+    }
+
+    /// The local or function in scope whose name is closest to `name`, to suggest when nothing goes by it
+    fn closest_in_scope(&self, name: &Word) -> Option<String> {
+        let mut names: Vec<_> = self
+            .tenv
+            .local_names()
+            .copied()
+            .chain(self.item_resolver.function_names(self.mod_id))
+            .map(|name| self.interner.resolve(&name))
+            .collect();
+        // Ties go to the first candidate, so the order cannot be left to the scopes
+        names.sort();
+        closest_name(self.interner.resolve(name), names).map(str::to_string)
+    }
+
+    /// The field of a struct whose name is closest to `name`, to suggest when the struct has no field by that name
+    fn closest_field(&self, fields: &StructFieldDeclList, name: &Word) -> Option<String> {
+        closest_name(
+            self.interner.resolve(name),
+            fields
+                .iter()
+                .map(|field| self.interner.resolve(&field.name)),
+        )
+        .map(str::to_string)
+    }
+
+    /// The text a node was parsed from, without the whitespace and comments that trail it
+    fn source_text(&self, node: &impl AstNode) -> String {
+        let text = node.syntax().resolve_text(self.interner).to_string();
+        text.trim_end().to_string()
+    }
+
+    /// Paths that are not locals may refer to functions
+    ///
+    /// Only function items are valid in expression position, so anything else is left for the caller to report
+    fn resolve_function_path(&mut self, path: &Spanned<Path<Word, id::Ty>>) -> Option<id::Ty> {
+        let (pkg_id, item_id) = self
+            .item_resolver
+            .resolve_path((&path.inner).in_mod(self.mod_id))
+            .ok()?;
+        match item_id.inner {
+            ItemTreeIdx::Function(fn_id) => {
+                let signature = self
+                    .packages
+                    .get(pkg_id)
+                    .item_tree
+                    .functions
+                    .get(fn_id)
+                    .as_fn_signature();
+                let signature = self.tenv.instantiate_generics(&signature, path.span);
+                Some(self.tenv.insert(Type::function(signature).at(path.span)))
+            }
+            _ => None,
+        }
+    }
+
     fn lower_int_expr(&mut self, int_expr: ast::IntExpr) -> id::Expr {
         let span = int_expr.range().to_span();
         let tid = self.tenv.insert(Type::int().at(span));
@@ -238,6 +420,199 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         self.exprs.insert(Expr::Int(val).with_type(tid))
     }
 
+    fn lower_bool_expr(&mut self, bool_expr: ast::BoolExpr) -> id::Expr {
+        let span = bool_expr.range().to_span();
+        let tid = self.tenv.insert(self.bool_type().at(span));
+        match bool_expr.v() {
+            Some(v) => {
+                let val = v.kind() == SyntaxKind::True;
+                self.exprs.insert(Expr::Bool(val).with_type(tid))
+            }
+            None => self.exprs.insert(Expr::Poisoned.with_type(tid)),
+        }
+    }
+
+    fn lower_float_expr(&mut self, float_expr: ast::FloatExpr) -> id::Expr {
+        let span = float_expr.range().to_span();
+        let tid = self.tenv.insert(Type::float().at(span));
+        let expr = match float_expr.v() {
+            Some(v) => {
+                let val_str = self
+                    .interner
+                    .resolve(
+                        &v.text_key()
+                            .unwrap_or_else(|| ice("float token without text")),
+                    )
+                    .replace("_", "");
+                let val: f64 = val_str
+                    .parse()
+                    .unwrap_or_else(|_| ice("invalid float value reached hir lowering"));
+                Expr::Float(Float::new(val))
+            }
+            None => Expr::Poisoned,
+        };
+        self.exprs.insert(expr.with_type(tid))
+    }
+
+    fn lower_string_expr(&mut self, string_expr: ast::StringExpr) -> id::Expr {
+        let span = string_expr.range().to_span();
+        let str_ty = Type::path(Path::new(
+            vec![self.interner.get_or_intern_static("str")],
+            vec![],
+        ));
+        let tid = self.tenv.insert(str_ty.at(span));
+        let expr = match string_expr.value() {
+            Some(value) => {
+                let text = self.interner.resolve(
+                    &value
+                        .text_key()
+                        .unwrap_or_else(|| ice("string token without text")),
+                );
+                // The lexer only accepts `\\` and `\"` as escape sequences
+                let contents = text[1..text.len() - 1]
+                    .replace("\\\"", "\"")
+                    .replace("\\\\", "\\");
+                Expr::Str(self.interner.get_or_intern(contents))
+            }
+            None => Expr::Poisoned,
+        };
+        self.exprs.insert(expr.with_type(tid))
+    }
+
+    /// `()` is parsed as a parenthesized expression with nothing inside of it, and is the unit value
+    fn lower_paren_expr(
+        &mut self,
+        paren_expr: ast::ParenExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        match paren_expr.expr() {
+            Some(expr) => *self.lower(Some(expr), generic_params),
+            None => {
+                let tid = self
+                    .tenv
+                    .insert(Type::unit().at(paren_expr.range().to_span()));
+                self.exprs.insert(Expr::unit().with_type(tid))
+            }
+        }
+    }
+
+    fn lower_tuple_expr(
+        &mut self,
+        tuple_expr: ast::TupleExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let vals: Vec<_> = tuple_expr
+            .vals()
+            .map(|val| *self.lower(Some(val), generic_params))
+            .collect();
+        let types = vals
+            .iter()
+            .map(|val| Type::r#ref(self.exprs.get(*val).tid))
+            .collect();
+        let tid = self
+            .tenv
+            .insert(Type::tuple(types).at(tuple_expr.range().to_span()));
+        self.exprs.insert(Expr::Tuple(vals).with_type(tid))
+    }
+
+    fn lower_address_expr(
+        &mut self,
+        address_expr: ast::AddressExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let of = self.lower(address_expr.of(), generic_params);
+        let of_tid = self.exprs.get(*of).tid;
+        let tid = self
+            .tenv
+            .insert(Type::address(Type::r#ref(of_tid)).at(address_expr.range().to_span()));
+        self.exprs.insert(Expr::Address(*of).with_type(tid))
+    }
+
+    /// `val[idx]` is lowered to a call to `val.idx(idx)`, the method of the `Index` trait
+    fn lower_idx_expr(
+        &mut self,
+        idx_expr: ast::IdxExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = idx_expr.range().to_span();
+        let val = self.lower(idx_expr.val(), generic_params);
+        let val_tid = self.exprs.get(*val).tid;
+        let idx = self.lower(idx_expr.idx(), generic_params);
+        let idx_tid = self.exprs.get(*idx).tid;
+
+        let index: Path<Word, Type> =
+            Path::new(vec![self.interner.get_or_intern_static("Index")], vec![]);
+        let tid = match self
+            .item_resolver
+            .resolve_trait_ids((&index).in_mod(self.mod_id))
+        {
+            Ok((pkg_id, _, trait_id)) => {
+                let trait_id = trait_id.in_pkg(pkg_id);
+                // Any application of `Index` to the value will do here, since the type of the index is often still
+                // an integer literal of unknown width
+                let val_kind = &self.tenv.get_inner(val_tid).kind;
+                if matches!(val_kind, TypeKind::Concrete(_) | TypeKind::Int)
+                    && !self.tenv.does_type_implement_trait(
+                        val_kind,
+                        &TraitRestriction::new(trait_id, vec![]),
+                    )
+                {
+                    self.diagnostics.push(
+                        LowerError::NotIndexable {
+                            val: self.tenv.fmt_tid(val_tid),
+                            val_file_span: val.span.in_file(self.file_id),
+                        }
+                        .to_diagnostic(),
+                    );
+                    let tid = self.tenv.insert(Type::unknown().at(span));
+                    return self.exprs.insert(Expr::Poisoned.with_type(tid));
+                }
+                let restriction = TraitRestriction::new(trait_id, vec![idx_tid]);
+                self.tenv
+                    .add_trait_restriction(val_tid, restriction.clone());
+                self.assoc_type_of(val_tid, restriction, "Output", span)
+            }
+            Err(err) => {
+                self.diagnostics
+                    .push(err.to_diagnostic(self.file_id, span, self.interner));
+                self.tenv.insert(Type::unknown().at(span))
+            }
+        };
+
+        *self.method_call(val, "idx", vec![idx], tid, span)
+    }
+
+    /// The associated type called `name` of the application of the trait in `restriction` to the type `of`
+    ///
+    /// As with the `Output` of the arithmetic traits, the type is only known once the application has been found.
+    fn assoc_type_of(
+        &mut self,
+        of: id::Ty,
+        restriction: TraitRestriction,
+        name: &'static str,
+        span: Span,
+    ) -> id::Ty {
+        let name = self.interner.get_or_intern_static(name);
+        let tid = self.tenv.insert(Type::unknown().at(span));
+        let assoc_tid = self
+            .tenv
+            .insert(Type::this_path(Path::new(vec![name], vec![]), vec![]).at(span));
+        self.tenv.add_equality(tid, assoc_tid);
+        self.tenv
+            .add_assoc_type_restriction(tid, of, restriction, name);
+        // When the type of `of` is already known, the application is found now, before other types are unified
+        // with a copy of this one that could not tell which application it belongs to
+        let _ = self.tenv.resolve(tid);
+        tid
+    }
+
+    fn bool_type(&self) -> Type {
+        Type::path(Path::new(
+            vec![self.interner.get_or_intern_static("bool")],
+            vec![],
+        ))
+    }
+
     fn lower_bin_expr(
         &mut self,
         bin_expr: ast::BinExpr,
@@ -299,6 +674,10 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                     vec![]
                 },
             );
+            if !include_other_side_as_arg {
+                // Traits like `CmpEq` take `This` for both sides
+                self.tenv.add_equality(lhs_tid, rhs_tid);
+            }
             self.tenv
                 .add_trait_restriction(lhs_tid, lhs_restriction.clone());
             self.tenv
@@ -345,6 +724,8 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                 //     rhs_restriction,
                 //     self.interner.get_or_intern_static("Output"),
                 // );
+                // As in `assoc_type_of`, the application is found now if the types of both sides are already known
+                let _ = self.tenv.resolve(tid);
             }
         }
 
@@ -399,6 +780,15 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         generic_params: &GenericParams,
     ) -> id::Expr {
         let span = call_expr.range().to_span();
+        if let Some(ast::Expr::PathExpr(path_expr)) = call_expr.callee() {
+            let path = self
+                .type_lowerer
+                .lower_path(path_expr.path(), generic_params);
+            if let Some(expr) = self.try_lower_enum_expr(&path, call_expr.args(), generic_params) {
+                return expr;
+            }
+        }
+
         let callee = self.lower(call_expr.callee(), generic_params);
         let callee_tid = self.exprs.get(*callee).tid;
 
@@ -465,14 +855,22 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
             */
             let num_args = path.args.len();
             let num_params = struct_decl.generic_params.types.len();
+            // Leaving out every generic argument leaves them to be inferred, but leaving out only some is a mistake
+            if num_args > 0 && num_args < num_params {
+                self.report_missing_generic_args(&struct_expr, &struct_decl);
+            }
             if num_args < num_params {
                 for _ in num_args..num_params {
                     path.args.push(Type::unknown());
                 }
             }
 
-            let fields =
-                self.lower_struct_fields(struct_expr.field_list(), generic_params, &struct_decl);
+            let fields = self.lower_struct_fields(
+                struct_expr.field_list(),
+                generic_params,
+                &struct_decl,
+                path.span,
+            );
             Expr::Struct(StructExpr::new(path.clone(), fields))
         } else {
             Expr::Poisoned
@@ -487,6 +885,7 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         field_list: Option<ast::StructExprFieldList>,
         generic_params: &GenericParams,
         struct_decl: &InFile<&StructDecl>,
+        path_span: Span,
     ) -> StructExprFieldList {
         lower_node_mut(
             self,
@@ -539,6 +938,8 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                         }
                         .to_diagnostic(),
                     );
+                } else {
+                    this.report_missing_fields(&field_list, struct_decl, &all_fields, path_span);
                 }
 
                 StructExprFieldList::new(fields)
@@ -546,12 +947,100 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         )
     }
 
+    /// Report the fields of a struct that a struct expression leaves out, suggesting to add them after the last one
+    fn report_missing_fields(
+        &mut self,
+        field_list: &ast::StructExprFieldList,
+        struct_decl: &InFile<&StructDecl>,
+        written_fields: &[Word],
+        path_span: Span,
+    ) {
+        let missing_fields: Vec<_> = struct_decl
+            .fields
+            .iter()
+            .filter(|field| !written_fields.contains(&field.name.inner))
+            .map(|field| self.interner.resolve(&field.name).to_string())
+            .collect();
+        if missing_fields.is_empty() {
+            return;
+        }
+
+        // The name of each field stands in for its value
+        let missing = missing_fields
+            .iter()
+            .map(|field| format!("{field}: {field}"))
+            .join(", ");
+        let (insertion, fields) = match field_list.fields().last() {
+            Some(last_field) => (u32::from(last_field.range().end()), format!(", {missing}")),
+            None => (
+                u32::from(field_list.range().start()) + 1,
+                format!(" {missing} "),
+            ),
+        };
+        self.diagnostics.push(
+            LowerError::MissingFieldsInStructExpr {
+                struct_name: self.interner.resolve(&struct_decl.name).to_string(),
+                struct_name_file_span: path_span.in_file(self.file_id),
+                missing_fields,
+                missing_fields_file_span: field_list.range().to_span().in_file(self.file_id),
+                insertion: (),
+                insertion_file_span: Span::new(insertion..insertion).in_file(self.file_id),
+                fields: Some(fields),
+            }
+            .to_diagnostic(),
+        );
+    }
+
+    /// Report a struct expression that gives some of the generic arguments of its struct but not all of them,
+    /// suggesting the names of the generic parameters for the rest
+    fn report_missing_generic_args(
+        &mut self,
+        struct_expr: &ast::StructExpr,
+        struct_decl: &InFile<&StructDecl>,
+    ) {
+        let Some(generic_arg_list) = struct_expr.path().and_then(|path| path.generic_arg_list())
+        else {
+            return;
+        };
+        let got_names: Vec<_> = generic_arg_list
+            .args()
+            .map(|arg| self.source_text(&arg))
+            .collect();
+        let expected_names: Vec<_> = struct_decl
+            .generic_params
+            .types
+            .values()
+            .map(|name| self.interner.resolve(name).to_string())
+            .collect();
+        let generic_args = got_names
+            .iter()
+            .chain(expected_names.iter().skip(got_names.len()))
+            .join(", ");
+        self.diagnostics.push(
+            LowerError::MissingGenericArguments {
+                got_names,
+                got_names_file_span: generic_arg_list.range().to_span().in_file(self.file_id),
+                expected_names,
+                expected_names_file_span: struct_decl
+                    .generic_params
+                    .span
+                    .in_file(struct_decl.file_id),
+                generic_args: Some(format!("<{generic_args}>")),
+            }
+            .to_diagnostic(),
+        );
+    }
+
     fn lower_block_expr(
         &mut self,
         block_expr: ast::BlockExpr,
         generic_params: &GenericParams,
     ) -> id::Expr {
         let mut terminator: Option<id::Expr> = None;
+        let mut stmts = vec![];
+        // The first `break` or `continue` in the block, and whether the code after it has been reported
+        let mut diverging: Option<Span> = None;
+        let mut reported_unreachable = false;
         block_expr.stmts().for_each(|stmt| {
             if let Some(terminator) = &terminator {
                 let span = self.tenv.get_span(self.exprs.get(*terminator).tid);
@@ -566,8 +1055,30 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                 );
             } else {
                 let (was_terminator, expr) = self.lower_stmt(stmt, generic_params);
+                if let (Some(diverging), false) = (diverging, reported_unreachable) {
+                    self.diagnostics.push(
+                        LowerError::UnreachableCode {
+                            unreachable: (),
+                            unreachable_file_span: expr.span.in_file(self.file_id),
+                            diverging: (),
+                            diverging_file_span: diverging.in_file(self.file_id),
+                        }
+                        .to_diagnostic(),
+                    );
+                    reported_unreachable = true;
+                }
+                if diverging.is_none()
+                    && matches!(
+                        self.exprs.get(*expr).inner,
+                        Expr::Break(_) | Expr::Continue(_)
+                    )
+                {
+                    diverging = Some(expr.span);
+                }
                 if was_terminator {
                     terminator = Some(*expr);
+                } else {
+                    stmts.push(*expr);
                 }
             }
         });
@@ -576,10 +1087,13 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
             .map(|rbrace| rbrace.text_range())
             .unwrap_or_else(|| block_expr.range())
             .to_span();
-        terminator.unwrap_or_else(|| {
+        let tail = terminator.unwrap_or_else(|| {
             self.exprs
                 .insert(Expr::unit().with_type(self.tenv.insert(Type::unit().at(span))))
-        })
+        });
+        let tid = self.exprs.get(tail).tid;
+        self.exprs
+            .insert(Expr::Block(Block::new(stmts, tail)).with_type(tid))
     }
 
     fn lower_member_access_expr(
@@ -648,6 +1162,13 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                                     None
                                 }
                             })
+                            .or_else(|| {
+                                self.tenv
+                                    .method_resolver
+                                    .resolve_method(&typekind.inner, field_name, self.tenv)
+                                    .map(|signature| Type::function(signature.clone()))
+                                    .ok()
+                            })
                             .unwrap_or_else(|| {
                                 self.diagnostics.push(
                                     LowerError::UnknownStructField {
@@ -655,6 +1176,7 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                                         field_file_span: field_name.span.in_file(self.file_id),
                                         strukt: self.interner.resolve(&strukt.name).to_string(),
                                         strukt_file_span: strukt.name.span.in_file(file_id),
+                                        candidate: self.closest_field(&strukt.fields, field_name),
                                     }
                                     .to_diagnostic(),
                                 );
@@ -760,6 +1282,7 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
 
         match intrinsics::get_signature(&name, self.interner) {
             Some(signature) => {
+                let signature = self.tenv.instantiate_generics(&signature, span);
                 let args = self.lower_arg_list(
                     intrinsic_expr.arg_list(),
                     generic_params,
@@ -793,6 +1316,7 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         this_id: Option<Spanned<id::Expr>>,
     ) -> Spanned<Vec<Spanned<id::Expr>>> {
         let params = expected_signature.parameters();
+        let has_this = this_id.is_some();
         let result = lower_node_mut(
             self,
             arg_list,
@@ -817,12 +1341,23 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
                 let sig_count = params.len();
 
                 if args_count != sig_count {
+                    // Extra arguments can be removed, but there is no telling what missing ones should be
+                    let args = (args_count > sig_count).then(|| {
+                        let num_written = sig_count.saturating_sub(usize::from(has_this));
+                        let written = arg_list
+                            .args()
+                            .take(num_written)
+                            .map(|arg| this.source_text(&arg))
+                            .join(", ");
+                        format!("({written})")
+                    });
                     this.diagnostics.push(
                         LowerError::IncorrectNumberOfArgs {
                             got_num: args_count,
                             got_num_file_span: arg_list_exprs.span.in_file(this.file_id),
                             expected_num: sig_count,
                             expected_num_file_span: expected_signature.to_file_span(),
+                            args,
                         }
                         .to_diagnostic(),
                     );
@@ -857,4 +1392,830 @@ impl<'a, 'res> LoweringCtx<'a, 'res> {
         let cast = Cast::new(*val, tid);
         self.exprs.insert(Expr::Cast(cast).with_type(tid))
     }
+
+    /// Enum variants are constructed with a path to the variant, which is called like a function if the variant holds
+    /// a value, as in `Option::Some(0)`
+    ///
+    /// Returns `None` if `path` does not lead to an enum, so it can be lowered as any other path.
+    fn try_lower_enum_expr(
+        &mut self,
+        path: &Spanned<Path<Word, Type>>,
+        args: Option<ast::ArgList>,
+        generic_params: &GenericParams,
+    ) -> Option<id::Expr> {
+        let span = args
+            .as_ref()
+            .map(|args| Span::combine(path.span, args.range().to_span()))
+            .unwrap_or(path.span);
+        let variant = match self.resolve_enum_variant(path) {
+            Ok(Some(variant)) => variant,
+            Ok(None) => {
+                let tid = self.tenv.insert(Type::unknown().at(span));
+                return Some(self.exprs.insert(Expr::Poisoned.with_type(tid)));
+            }
+            Err(_) => return None,
+        };
+
+        let (tid, payload_tid) = self.instantiate_enum_variant(&variant, span);
+        let args: Vec<_> = args
+            .map(|args| {
+                args.args()
+                    .map(|arg| self.lower(Some(arg), generic_params))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let payload = payload_tid.zip(args.first()).map(|(payload_tid, arg)| {
+            self.tenv
+                .add_equality(payload_tid, self.exprs.get(**arg).tid);
+            **arg
+        });
+
+        let expected_num = payload_tid.iter().count();
+        if args.len() != expected_num {
+            self.diagnostics.push(
+                LowerError::IncorrectVariantPayload {
+                    variant: self.interner.resolve(&variant.name).to_string(),
+                    variant_file_span: span.in_file(self.file_id),
+                    expected_num,
+                    got_num: args.len(),
+                }
+                .to_diagnostic(),
+            );
+            return Some(self.exprs.insert(Expr::Poisoned.with_type(tid)));
+        }
+
+        let enum_expr = EnumExpr::new(variant.path, variant.name, variant.discriminant, payload);
+        Some(self.exprs.insert(Expr::Enum(enum_expr).with_type(tid)))
+    }
+
+    /// Resolve a path like `Option::Some` to a variant of an enum
+    ///
+    /// Paths that do not lead to an enum are returned as errors for the caller to report if need be, since they may
+    /// refer to something else. Paths that lead to an enum without the variant they name are reported here.
+    fn resolve_enum_variant(
+        &mut self,
+        path: &Spanned<Path<Word, Type>>,
+    ) -> Result<Option<EnumVariant<'a>>, ResolutionError<Type>> {
+        let (variant, segments) = match path.segments.split_last() {
+            Some((variant, segments)) if !segments.is_empty() => (*variant, segments),
+            _ => {
+                return Err(ResolutionError::UnresolvedPath {
+                    path: path.inner.clone(),
+                    segment: 0,
+                })
+            }
+        };
+        let enum_path = Path::new(segments.to_vec(), path.args.clone());
+        let item_resolver: &'a ItemResolver<'a> = self.item_resolver;
+        let (_, decl) = item_resolver.resolve_enum((&enum_path).in_mod(self.mod_id))?;
+
+        match decl
+            .variants
+            .iter()
+            .position(|decl_variant| decl_variant.name.inner == variant)
+        {
+            Some(discriminant) => Ok(Some(EnumVariant {
+                path: enum_path.at(path.span),
+                name: variant.at(path.span),
+                decl,
+                discriminant: discriminant as u32,
+            })),
+            None => {
+                self.diagnostics.push(
+                    LowerError::UnknownEnumVariant {
+                        variant: self.interner.resolve(&variant).to_string(),
+                        variant_file_span: path.span.in_file(self.file_id),
+                        enum_name: self.interner.resolve(&decl.name).to_string(),
+                        enum_name_file_span: decl.name.span.in_file(decl.file_id),
+                    }
+                    .to_diagnostic(),
+                );
+                Ok(None)
+            }
+        }
+    }
+
+    /// The type of a value of `variant`'s enum, with a new unknown type for every generic argument that wasn't
+    /// given, along with the type of the value the variant holds
+    fn instantiate_enum_variant(
+        &mut self,
+        variant: &EnumVariant,
+        span: Span,
+    ) -> (id::Ty, Option<id::Ty>) {
+        let mut instantiated = HashMap::new();
+        let args = variant
+            .decl
+            .generic_params
+            .types
+            .values()
+            .enumerate()
+            .map(|(i, param)| {
+                let arg = variant.path.args.get(i).cloned().unwrap_or(Type::unknown());
+                let tid = self.tenv.insert(arg.at(span));
+                instantiated.insert(param.inner, tid);
+                Type::r#ref(tid)
+            })
+            .collect();
+        let tid = self
+            .tenv
+            .insert(Type::path(Path::new(variant.path.segments.clone(), args)).at(span));
+
+        let payload_tid = variant
+            .decl
+            .variants
+            .iter()
+            .nth(variant.discriminant as usize)
+            .and_then(|decl_variant| decl_variant.ty.as_ref())
+            .map(|ty| {
+                let ty = self.tenv.instantiate_type(ty, span, &mut instantiated);
+                self.tenv.insert(ty.at(span))
+            });
+        (tid, payload_tid)
+    }
+
+    fn lower_match_expr(
+        &mut self,
+        match_expr: ast::MatchExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = match_expr.range().to_span();
+        let scrutinee = self.lower(match_expr.scrutinee(), generic_params);
+        let scrutinee_tid = self.exprs.get(*scrutinee).tid;
+
+        let arms: Vec<_> = match_expr
+            .arm_list()
+            .map(|arm_list| {
+                arm_list
+                    .arms()
+                    .map(|arm| {
+                        // The locals bound by the pattern are only visible in the body of the arm
+                        self.push_scope();
+                        let pat_span = arm
+                            .pat()
+                            .map_or(arm.range().to_span(), |pat| pat.range().to_span());
+                        let pat = self
+                            .lower_pat(arm.pat(), scrutinee_tid, generic_params)
+                            .at(pat_span);
+                        let body = self.lower(arm.body(), generic_params);
+                        self.pop_scope();
+                        MatchArm::new(pat, *body)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let tid = match arms.first() {
+            Some(first) => {
+                let tid = self.exprs.get(first.body).tid;
+                for arm in &arms[1..] {
+                    self.tenv.add_equality(tid, self.exprs.get(arm.body).tid);
+                }
+                tid
+            }
+            None => self.tenv.insert(Type::never().at(span)),
+        };
+
+        self.exprs
+            .insert(Expr::Match(Match::new(scrutinee, arms)).with_type(tid))
+    }
+
+    fn lower_loop_expr(
+        &mut self,
+        loop_expr: ast::LoopExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = loop_expr.range().to_span();
+        self.lower_loop(loop_expr.label(), LoopKind::Loop, span, |this| {
+            this.lower_if_block_expr(loop_expr.block(), generic_params)
+        })
+    }
+
+    /// `while condition { body }` is lowered to `loop { if condition { body } else { break } }`
+    fn lower_while_expr(
+        &mut self,
+        while_expr: ast::WhileExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = while_expr.range().to_span();
+        self.lower_loop(while_expr.label(), LoopKind::While, span, |this| {
+            let condition = this.lower(while_expr.condition(), generic_params);
+            let bool_tid = this.tenv.insert(this.bool_type().at(condition.span));
+            this.tenv
+                .add_equality(this.exprs.get(*condition).tid, bool_tid);
+
+            let block = this.lower_if_block_expr(while_expr.block(), generic_params);
+            let tid = this.exprs.get(block).tid;
+            let exit = this.break_innermost_loop(condition.span);
+            this.tenv.add_equality(tid, this.exprs.get(exit).tid);
+
+            let if_expr = If::new(*condition, block, std::iter::empty(), Some(exit));
+            this.exprs.insert(Expr::If(if_expr).with_type(tid))
+        })
+    }
+
+    /// `for pat in iterable { body }` is lowered to
+    ///
+    /// ```flux
+    /// {
+    ///     let iter = iterable;
+    ///     loop {
+    ///         match iter.current() {
+    ///             Option::Some(pat) => {
+    ///                 iter = iter.advance();
+    ///                 body
+    ///             }
+    ///             Option::None => break,
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// The iterator is advanced before the body is run so that `continue` moves on to the next item.
+    fn lower_for_expr(
+        &mut self,
+        for_expr: ast::ForExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = for_expr.range().to_span();
+        let iterable = self.lower(for_expr.iterable(), generic_params);
+        let iter_tid = self.exprs.get(*iterable).tid;
+        let item_tid = match self.iterator_item_type(iter_tid, iterable.span) {
+            Some(item_tid) => item_tid,
+            None => {
+                // There is nothing to call `current` and `advance` on, but the body can still be checked
+                return self.lower_loop(for_expr.label(), LoopKind::For, span, |this| {
+                    this.push_scope();
+                    let item_tid = this.tenv.insert(Type::unknown().at(iterable.span));
+                    this.lower_pat(for_expr.pat(), item_tid, generic_params);
+                    let body = this.lower_if_block_expr(for_expr.block(), generic_params);
+                    this.pop_scope();
+                    body
+                });
+            }
+        };
+
+        // The iterator is kept in a local that cannot be named in source code
+        let iter = self
+            .interner
+            .get_or_intern_static("<iterator>")
+            .at(iterable.span);
+        self.push_scope();
+        self.tenv.insert_local(iter.inner, iter_tid);
+        let let_tid = self.tenv.insert(Type::unit().at(iterable.span));
+        let let_iter = self
+            .exprs
+            .insert(Expr::Let(Let::new(iter.clone(), *iterable)).with_type(let_tid));
+
+        let loop_expr = self.lower_loop(for_expr.label(), LoopKind::For, span, |this| {
+            let option = this.interner.get_or_intern_static("Option");
+            let option_tid = this.tenv.insert(
+                Type::path(Path::new(vec![option], vec![Type::r#ref(item_tid)])).at(iterable.span),
+            );
+            let current = this.call_iterator_method(iter.clone(), iter_tid, "current", option_tid);
+
+            this.push_scope();
+            let pat_span = for_expr.pat().map_or(span, |pat| pat.range().to_span());
+            let pat = this.lower_pat(for_expr.pat(), item_tid, generic_params);
+            let some_pat = this.option_pat("Some", Some(pat), option_tid, pat_span);
+            let advanced = this.call_iterator_method(iter.clone(), iter_tid, "advance", iter_tid);
+            let iter_path = this
+                .exprs
+                .insert(Expr::Path(Path::new(vec![iter.inner], vec![])).with_type(iter_tid));
+            let assignment_tid = this.tenv.insert(Type::unit().at(iterable.span));
+            let assignment = this.exprs.insert(
+                Expr::Assignment(Assignment::new(iter_path, *advanced)).with_type(assignment_tid),
+            );
+            let body = this.lower_if_block_expr(for_expr.block(), generic_params);
+            let tid = this.exprs.get(body).tid;
+            let some_body = this
+                .exprs
+                .insert(Expr::Block(Block::new(vec![assignment], body)).with_type(tid));
+            this.pop_scope();
+
+            let none_pat = this.option_pat("None", None, option_tid, iterable.span);
+            let none_body = this.break_innermost_loop(iterable.span);
+            this.tenv.add_equality(tid, this.exprs.get(none_body).tid);
+
+            let arms = vec![
+                MatchArm::new(some_pat.at(pat_span), some_body),
+                MatchArm::new(none_pat.at(iterable.span), none_body),
+            ];
+            this.exprs
+                .insert(Expr::Match(Match::new(current, arms)).with_type(tid))
+        });
+        self.pop_scope();
+
+        let tid = self.exprs.get(loop_expr).tid;
+        self.exprs
+            .insert(Expr::Block(Block::new(vec![let_iter], loop_expr)).with_type(tid))
+    }
+
+    /// The type of the items produced by an iterator of type `iter_tid`
+    ///
+    /// Returns `None` if the type is known to not be an iterator, or if there is no `Iterator` trait to be found.
+    fn iterator_item_type(&mut self, iter_tid: id::Ty, span: Span) -> Option<id::Ty> {
+        let iterator: Path<Word, Type> =
+            Path::new(vec![self.interner.get_or_intern_static("Iterator")], vec![]);
+        let (pkg_id, _, trait_id) = self
+            .item_resolver
+            .resolve_trait_ids((&iterator).in_mod(self.mod_id))
+            .map_err(|err| {
+                self.diagnostics
+                    .push(err.to_diagnostic(self.file_id, span, self.interner))
+            })
+            .ok()?;
+        let restriction = TraitRestriction::new(trait_id.in_pkg(pkg_id), vec![]);
+
+        let iter_kind = &self.tenv.get_inner(iter_tid).kind;
+        if matches!(iter_kind, TypeKind::Concrete(_) | TypeKind::Int)
+            && !self.tenv.does_type_implement_trait(iter_kind, &restriction)
+        {
+            self.diagnostics.push(
+                LowerError::NotAnIterator {
+                    iterable: self.tenv.fmt_tid(iter_tid),
+                    iterable_file_span: span.in_file(self.file_id),
+                }
+                .to_diagnostic(),
+            );
+            return None;
+        }
+        self.tenv
+            .add_trait_restriction(iter_tid, restriction.clone());
+        Some(self.assoc_type_of(iter_tid, restriction, "Item", span))
+    }
+
+    /// Call the method of `Iterator` called `name` on the iterator in the local `iter`
+    fn call_iterator_method(
+        &mut self,
+        iter: Spanned<Word>,
+        iter_tid: id::Ty,
+        name: &'static str,
+        return_tid: id::Ty,
+    ) -> Spanned<id::Expr> {
+        let span = iter.span;
+        let receiver = self
+            .exprs
+            .insert(Expr::Path(Path::new(vec![iter.inner], vec![])).with_type(iter_tid))
+            .at(span);
+        self.method_call(receiver, name, vec![], return_tid, span)
+    }
+
+    /// Call the method called `name` on `receiver`, for expressions that are sugar for a method call
+    fn method_call(
+        &mut self,
+        receiver: Spanned<id::Expr>,
+        name: &'static str,
+        args: Vec<Spanned<id::Expr>>,
+        return_tid: id::Ty,
+        span: Span,
+    ) -> Spanned<id::Expr> {
+        // As in any other method call, the receiver is passed as the first argument
+        let args: Vec<_> = std::iter::once(receiver.clone()).chain(args).collect();
+        let signature = FnSignature::new(
+            args.iter()
+                .map(|arg| Type::r#ref(self.exprs.get(**arg).tid)),
+            Type::r#ref(return_tid),
+        );
+        let method_tid = self.tenv.insert(Type::function(signature).at(span));
+        let method = self.interner.get_or_intern_static(name).at(span);
+        let callee = self
+            .exprs
+            .insert(Expr::MemberAccess(MemberAccess::new(receiver, method)).with_type(method_tid))
+            .at(span);
+        self.exprs
+            .insert(Expr::Call(Call::new(callee, args)).with_type(return_tid))
+            .at(span)
+    }
+
+    /// A pattern matching the variant of `Option` called `variant`
+    fn option_pat(
+        &mut self,
+        variant: &'static str,
+        payload: Option<id::Pat>,
+        tid: id::Ty,
+        span: Span,
+    ) -> id::Pat {
+        let path = Path::new(
+            vec![
+                self.interner.get_or_intern_static("Option"),
+                self.interner.get_or_intern_static(variant),
+            ],
+            vec![],
+        )
+        .at(span);
+        let pat = match self.resolve_enum_variant(&path) {
+            Ok(Some(variant)) => Pat::Variant(VariantPat::new(
+                variant.path,
+                variant.name,
+                variant.discriminant,
+                payload,
+            )),
+            Ok(None) => Pat::Poisoned,
+            Err(err) => {
+                self.diagnostics
+                    .push(err.to_diagnostic(self.file_id, span, self.interner));
+                Pat::Poisoned
+            }
+        };
+        self.pats.insert(pat.with_type(tid))
+    }
+
+    /// Lower a loop whose body is lowered by `lower_body`
+    ///
+    /// A `loop` evaluates to the values it is broken out of with, or `Never` if it is never broken out of, whereas
+    /// `while` and `for` loops always evaluate to unit.
+    fn lower_loop(
+        &mut self,
+        label: Option<ast::LoopLabel>,
+        kind: LoopKind,
+        span: Span,
+        lower_body: impl FnOnce(&mut Self) -> id::Expr,
+    ) -> id::Expr {
+        let tid = self.tenv.insert(Type::unknown().at(span));
+        // The loop is inserted before its body is lowered so that the `break` and `continue` expressions in the body
+        // can refer to it
+        let expr = self.exprs.insert(Expr::Poisoned.with_type(tid));
+        let label = label.and_then(|label| label.name().and_then(|name| name.text_key()));
+        self.loops.push(LoopScope {
+            expr,
+            label,
+            tid,
+            kind,
+            has_break: false,
+        });
+        let body = lower_body(self);
+        let scope = self
+            .loops
+            .pop()
+            .unwrap_or_else(|| ice("loop scope was popped while lowering its body"));
+
+        let body_tid = self.exprs.get(body).tid;
+        let unit_tid = self
+            .tenv
+            .insert(Type::unit().at(self.tenv.get_span(body_tid)));
+        self.tenv.add_equality(body_tid, unit_tid);
+
+        match kind {
+            LoopKind::Loop if !scope.has_break => {
+                let never_tid = self.tenv.insert(Type::never().at(span));
+                self.tenv.add_equality(tid, never_tid);
+            }
+            LoopKind::Loop => {}
+            LoopKind::While | LoopKind::For => {
+                let unit_tid = self.tenv.insert(Type::unit().at(span));
+                self.tenv.add_equality(tid, unit_tid);
+            }
+        }
+
+        self.exprs
+            .set(expr, Expr::Loop(Loop::new(body)).with_type(tid));
+        expr
+    }
+
+    fn lower_break_expr(
+        &mut self,
+        break_expr: ast::BreakExpr,
+        generic_params: &GenericParams,
+    ) -> id::Expr {
+        let span = break_expr.range().to_span();
+        let tid = self.tenv.insert(Type::never().at(span));
+        let val = break_expr
+            .value()
+            .map(|val| self.lower(Some(val), generic_params));
+        let scope = match self.find_loop(break_expr.label().cloned(), "break", span) {
+            Some(idx) => &mut self.loops[idx],
+            None => return self.exprs.insert(Expr::Poisoned.with_type(tid)),
+        };
+        scope.has_break = true;
+        let (target, loop_tid, kind) = (scope.expr, scope.tid, scope.kind);
+
+        match (kind, &val) {
+            (LoopKind::Loop, _) => {
+                let val_tid = match &val {
+                    Some(val) => self.exprs.get(val.inner).tid,
+                    None => self.tenv.insert(Type::unit().at(span)),
+                };
+                self.tenv.add_equality(loop_tid, val_tid);
+            }
+            (_, Some(val)) => self.diagnostics.push(
+                LowerError::BreakWithValueInNonLoop {
+                    val: (),
+                    val_file_span: val.span.in_file(self.file_id),
+                    kind: kind.keyword(),
+                }
+                .to_diagnostic(),
+            ),
+            (_, None) => {}
+        }
+
+        self.exprs
+            .insert(Expr::Break(Break::new(target, val.map(|val| *val))).with_type(tid))
+    }
+
+    /// A `break` out of the innermost loop without a value, as `while` and `for` loops end with
+    fn break_innermost_loop(&mut self, span: Span) -> id::Expr {
+        let scope = self
+            .loops
+            .last_mut()
+            .unwrap_or_else(|| ice("tried to break out of a loop outside of any loop"));
+        scope.has_break = true;
+        let target = scope.expr;
+        let tid = self.tenv.insert(Type::never().at(span));
+        self.exprs
+            .insert(Expr::Break(Break::new(target, None)).with_type(tid))
+    }
+
+    fn lower_continue_expr(&mut self, continue_expr: ast::ContinueExpr) -> id::Expr {
+        let span = continue_expr.range().to_span();
+        let tid = self.tenv.insert(Type::never().at(span));
+        let expr = match self.find_loop(continue_expr.label().cloned(), "continue", span) {
+            Some(idx) => Expr::Continue(Continue::new(self.loops[idx].expr)),
+            None => Expr::Poisoned,
+        };
+        self.exprs.insert(expr.with_type(tid))
+    }
+
+    /// Find the loop a `break` or `continue` refers to, which is the innermost loop if it has no label
+    fn find_loop(
+        &mut self,
+        label: Option<SyntaxToken>,
+        keyword: &'static str,
+        span: Span,
+    ) -> Option<usize> {
+        match label {
+            Some(label) => {
+                let name = label
+                    .text_key()
+                    .unwrap_or_else(|| ice("label token without text"));
+                let idx = self
+                    .loops
+                    .iter()
+                    .rposition(|scope| scope.label == Some(name));
+                if idx.is_none() {
+                    self.diagnostics.push(
+                        LowerError::UnknownLoopLabel {
+                            label: self.interner.resolve(&name).to_string(),
+                            label_file_span: label.text_range().to_span().in_file(self.file_id),
+                        }
+                        .to_diagnostic(),
+                    );
+                }
+                idx
+            }
+            None => {
+                let idx = self.loops.len().checked_sub(1);
+                if idx.is_none() {
+                    self.diagnostics.push(
+                        LowerError::LoopControlOutsideLoop {
+                            keyword,
+                            keyword_file_span: span.in_file(self.file_id),
+                        }
+                        .to_diagnostic(),
+                    );
+                }
+                idx
+            }
+        }
+    }
+
+    /// Lower a pattern that is matched against a value of type `tid`, inserting the locals it binds into the current
+    /// scope
+    fn lower_pat(
+        &mut self,
+        pat: Option<ast::Pat>,
+        tid: id::Ty,
+        generic_params: &GenericParams,
+    ) -> id::Pat {
+        lower_optional_node_mut(
+            self,
+            pat,
+            |this| this.pats.insert(Pat::Poisoned.with_type(tid)),
+            |this, pat| {
+                let span = pat.range().to_span();
+                let pat = match pat {
+                    ast::Pat::WildcardPat(_) => Pat::Wildcard,
+                    ast::Pat::BindPat(bind_pat) => {
+                        let name = this.type_lowerer.lower_name(bind_pat.name());
+                        this.declare_local(&name, tid);
+                        Pat::Bind(name)
+                    }
+                    ast::Pat::LiteralPat(literal_pat) => {
+                        this.lower_literal_pat(literal_pat, tid, span)
+                    }
+                    ast::Pat::VariantPat(variant_pat) => {
+                        this.lower_variant_pat(variant_pat, tid, span, generic_params)
+                    }
+                    ast::Pat::TuplePat(tuple_pat) => {
+                        let tids: Vec<_> = tuple_pat
+                            .pats()
+                            .map(|pat| this.tenv.insert(Type::unknown().at(pat.range().to_span())))
+                            .collect();
+                        let tuple_tid = this.tenv.insert(
+                            Type::tuple(tids.iter().map(|tid| Type::r#ref(*tid)).collect())
+                                .at(span),
+                        );
+                        this.tenv.add_equality(tid, tuple_tid);
+                        Pat::Tuple(
+                            tuple_pat
+                                .pats()
+                                .zip(tids)
+                                .map(|(pat, tid)| this.lower_pat(Some(pat), tid, generic_params))
+                                .collect(),
+                        )
+                    }
+                    ast::Pat::ParenPat(paren_pat) => {
+                        return this.lower_pat(paren_pat.pat(), tid, generic_params)
+                    }
+                    ast::Pat::StructPat(struct_pat) => {
+                        this.lower_struct_pat(struct_pat, tid, generic_params)
+                    }
+                };
+                this.pats.insert(pat.with_type(tid))
+            },
+        )
+    }
+
+    fn lower_literal_pat(&mut self, literal_pat: ast::LiteralPat, tid: id::Ty, span: Span) -> Pat {
+        let v = match literal_pat.v() {
+            Some(v) => v,
+            None => return Pat::Poisoned,
+        };
+        if matches!(v.kind(), SyntaxKind::True | SyntaxKind::False) {
+            let bool_tid = self.tenv.insert(self.bool_type().at(span));
+            self.tenv.add_equality(tid, bool_tid);
+            return Pat::Bool(v.kind() == SyntaxKind::True);
+        }
+
+        let int_tid = self.tenv.insert(Type::int().at(span));
+        self.tenv.add_equality(tid, int_tid);
+
+        let val_str = self
+            .interner
+            .resolve(&v.text_key().unwrap_or_else(|| ice("genuinely not sure")))
+            .replace("_", "");
+        match val_str.parse() {
+            Ok(val) => Pat::Int(val),
+            Err(_) => {
+                self.diagnostics.push(
+                    LowerError::PositiveIntegerOverflow {
+                        val: val_str.to_string(),
+                        val_file_span: span.in_file(self.file_id),
+                    }
+                    .to_diagnostic(),
+                );
+                Pat::Poisoned
+            }
+        }
+    }
+
+    fn lower_variant_pat(
+        &mut self,
+        variant_pat: ast::VariantPat,
+        tid: id::Ty,
+        span: Span,
+        generic_params: &GenericParams,
+    ) -> Pat {
+        let path = self
+            .type_lowerer
+            .lower_path(variant_pat.path(), generic_params);
+        let variant = match self.resolve_enum_variant(&path) {
+            Ok(Some(variant)) => Some(variant),
+            Ok(None) => None,
+            Err(err) => {
+                self.diagnostics
+                    .push(err.to_diagnostic(self.file_id, path.span, self.interner));
+                None
+            }
+        };
+        let pats: Vec<_> = variant_pat.pats().collect();
+
+        let (enum_tid, payload_tid) = match &variant {
+            Some(variant) => self.instantiate_enum_variant(variant, path.span),
+            None => (tid, None),
+        };
+        self.tenv.add_equality(tid, enum_tid);
+
+        let expected_num = payload_tid.iter().count();
+        let variant = match variant {
+            Some(variant) if pats.len() == expected_num => variant,
+            variant => {
+                if let Some(variant) = variant {
+                    self.diagnostics.push(
+                        LowerError::IncorrectVariantPayload {
+                            variant: self.interner.resolve(&variant.name).to_string(),
+                            variant_file_span: span.in_file(self.file_id),
+                            expected_num,
+                            got_num: pats.len(),
+                        }
+                        .to_diagnostic(),
+                    );
+                }
+                // The subpatterns are still lowered so that the locals they bind are known in the body of the arm
+                for pat in pats {
+                    let tid = self.tenv.insert(Type::unknown().at(pat.range().to_span()));
+                    self.lower_pat(Some(pat), tid, generic_params);
+                }
+                return Pat::Poisoned;
+            }
+        };
+
+        let payload = payload_tid.map(|payload_tid| {
+            self.lower_pat(pats.into_iter().next(), payload_tid, generic_params)
+        });
+        Pat::Variant(VariantPat::new(
+            variant.path,
+            variant.name,
+            variant.discriminant,
+            payload,
+        ))
+    }
+
+    fn lower_struct_pat(
+        &mut self,
+        struct_pat: ast::StructPat,
+        tid: id::Ty,
+        generic_params: &GenericParams,
+    ) -> Pat {
+        let mut path = self
+            .type_lowerer
+            .lower_path(struct_pat.path(), generic_params);
+        let item_resolver: &'a ItemResolver<'a> = self.item_resolver;
+        let struct_decl = item_resolver
+            .resolve_struct(path.as_ref().inner.in_mod(self.mod_id))
+            .map_err(|err| {
+                self.diagnostics
+                    .push(err.to_diagnostic(self.file_id, path.span, self.interner))
+            })
+            .ok();
+
+        if let Some(struct_decl) = &struct_decl {
+            let num_params = struct_decl.generic_params.types.len();
+            for _ in path.args.len()..num_params {
+                path.args.push(Type::unknown());
+            }
+            let struct_tid = self
+                .tenv
+                .insert(Type::path(path.inner.clone()).at(path.span));
+            self.tenv.add_equality(tid, struct_tid);
+        }
+
+        let field_list = match struct_pat.field_list() {
+            Some(field_list) => field_list,
+            None => return Pat::Poisoned,
+        };
+        let fields = field_list
+            .fields()
+            .map(|field| {
+                let name = self.type_lowerer.lower_name(field.name());
+                let field_decl = struct_decl
+                    .as_ref()
+                    .and_then(|struct_decl| struct_decl.fields.find(*name));
+                let field_tid = match (&struct_decl, field_decl) {
+                    (_, Some(field_decl)) => self.tenv.insert(field_decl.ty.clone()),
+                    (Some(struct_decl), None) => {
+                        self.diagnostics.push(
+                            LowerError::UnknownStructField {
+                                field: self.interner.resolve(&name).to_string(),
+                                field_file_span: name.span.in_file(self.file_id),
+                                strukt: self.interner.resolve(&struct_decl.name).to_string(),
+                                strukt_file_span: struct_decl
+                                    .name
+                                    .span
+                                    .in_file(struct_decl.file_id),
+                                candidate: self.closest_field(&struct_decl.fields, &name),
+                            }
+                            .to_diagnostic(),
+                        );
+                        self.tenv.insert(Type::unknown().at(name.span))
+                    }
+                    (None, None) => self.tenv.insert(Type::unknown().at(name.span)),
+                };
+
+                let pat = match field.pat() {
+                    Some(pat) => self.lower_pat(Some(pat), field_tid, generic_params),
+                    None => {
+                        self.declare_local(&name, field_tid);
+                        self.pats
+                            .insert(Pat::Bind(name.clone()).with_type(field_tid))
+                    }
+                };
+                StructPatField::new(name, pat)
+            })
+            .collect();
+
+        match struct_decl {
+            Some(_) => Pat::Struct(StructPat::new(path, fields)),
+            None => Pat::Poisoned,
+        }
+    }
+}
+
+/// The variant of an enum that a path resolved to
+struct EnumVariant<'a> {
+    /// The path to the enum, without the variant
+    path: Spanned<Path<Word, Type>>,
+    name: Spanned<Word>,
+    decl: InFile<&'a EnumDecl>,
+    discriminant: u32,
 }
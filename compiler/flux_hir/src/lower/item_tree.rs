@@ -8,6 +8,7 @@ use flux_id::{
 use flux_parser::ast::{self, AstNode};
 use flux_typesystem::{ThisCtx, Type};
 use flux_util::{FileId, Interner, Span, Spanned, ToSpan, WithSpan, Word};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     def::{
@@ -22,8 +23,8 @@ use crate::{
 
 use super::{lower_node, lower_optional_node_mut, r#type};
 
-#[derive(Debug, Default, Clone)]
-pub(crate) struct ItemTree {
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ItemTree {
     pub top_level: Vec<ItemId>,
     pub applies: Map<id::ApplyDecl, ApplyDecl>,
     pub enums: Map<id::EnumDecl, EnumDecl>,
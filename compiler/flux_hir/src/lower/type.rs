@@ -54,6 +54,11 @@ impl LoweringCtx {
         )
     }
 
+    /// Whether a name stands in for one that could not be parsed, which has already been reported
+    pub(super) fn is_poisoned_name(&self, name: &Word) -> bool {
+        self.interner.resolve(name) == Self::POISONED_NAME
+    }
+
     pub(super) fn lower_path(
         &self,
         path: Option<ast::Path>,
@@ -0,0 +1,161 @@
+//! Items that are declared but never referred to
+//!
+//! Functions are checked once every body in their package has been lowered, by resolving the paths in those bodies.
+//! Imports are checked syntactically, one module at a time, since the types in declarations refer to them too: an import
+//! is used if any path in its module starts with the name it brings into scope.
+
+use std::collections::HashSet;
+
+use flux_diagnostics::{Diagnostic, ToDiagnostic};
+use flux_id::{
+    id::{self, WithMod},
+    Map,
+};
+use flux_parser::{
+    ast::{self, AstNode},
+    syntax::SyntaxNode,
+};
+use flux_util::{FileId, Interner, Path, ToSpan, WithSpan, Word};
+
+use crate::{
+    def::{expr::Expr, item::Visibility},
+    diagnostics::LowerError,
+    item::ItemTreeIdx,
+    name_res::item::ItemResolver,
+    ModuleTree, Package, PackageBodies,
+};
+
+/// How many imports of imports are followed to find the function a path refers to
+const MAX_IMPORT_DEPTH: usize = 16;
+
+/// Report the private functions of a package that none of its bodies refer to
+///
+/// `main` is the entry point of the package, so it is always used. A function that only calls itself is not reported.
+pub(crate) fn report_unused_private_functions(
+    package_id: id::Pkg,
+    packages: &Map<id::Pkg, Package>,
+    bodies: &PackageBodies,
+    interner: &'static Interner,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let package = packages.get(package_id);
+    let item_resolver = ItemResolver::new(package_id, packages, interner);
+    let referred: HashSet<_> = bodies
+        .fn_bodies
+        .values()
+        .flat_map(|body| body.exprs().map(move |expr| (body.mod_id, expr)))
+        .filter_map(|(mod_id, expr)| match &bodies.exprs.get(expr).inner {
+            Expr::Path(path) => resolve_function(&item_resolver, package_id, package, path, mod_id),
+            _ => None,
+        })
+        .collect();
+
+    let main = package.get_main_fn(interner);
+    for item_id in &package.item_tree.top_level {
+        let ItemTreeIdx::Function(fn_id) = item_id.inner else {
+            continue;
+        };
+        let fn_decl = package.item_tree.functions.get(fn_id);
+        let name = interner.resolve(&fn_decl.name.inner);
+        if item_id.mod_id == ModuleTree::PRELUDE_ID
+            || fn_decl.visibility.inner == Visibility::Public
+            || Some(fn_id) == main
+            || referred.contains(&fn_id)
+        {
+            continue;
+        }
+        let file_id = package.module_tree[item_id.mod_id].file_id;
+        diagnostics.push(
+            LowerError::UnusedPrivateFunction {
+                function: name.to_string(),
+                function_file_span: fn_decl.name.span.in_file(file_id),
+            }
+            .to_diagnostic(),
+        );
+    }
+}
+
+/// The function of `package_id` a path refers to, following any imports along the way
+fn resolve_function<A: Clone>(
+    item_resolver: &ItemResolver,
+    package_id: id::Pkg,
+    package: &Package,
+    path: &Path<Word, A>,
+    mod_id: id::Mod,
+) -> Option<id::FnDecl> {
+    let mut resolved = item_resolver.resolve_path(path.in_mod(mod_id)).ok()?;
+    for _ in 0..MAX_IMPORT_DEPTH {
+        let (resolved_package_id, item_id) = resolved;
+        match item_id.inner {
+            ItemTreeIdx::Function(fn_id) if resolved_package_id == package_id => {
+                return Some(fn_id)
+            }
+            ItemTreeIdx::Use(use_id) if resolved_package_id == package_id => {
+                let use_decl = package.item_tree.uses.get(use_id);
+                resolved = item_resolver
+                    .resolve_path((&use_decl.path.inner).in_mod(item_id.mod_id))
+                    .ok()?;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+/// Report the imports of a module that no path in it starts with
+///
+/// Public imports make items available to other modules, so only private ones are reported.
+pub(crate) fn report_unused_imports(
+    root: &SyntaxNode,
+    file_id: FileId,
+    interner: &'static Interner,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(root) = ast::Root::cast(root.clone()) else {
+        return;
+    };
+    let use_decls: Vec<_> = root
+        .use_decls()
+        .filter(|use_decl| !use_decl.is_poisoned())
+        .collect();
+    let in_use_decl = |path: &ast::Path| {
+        use_decls
+            .iter()
+            .any(|use_decl| use_decl.range().contains_range(path.range()))
+    };
+    let referred: HashSet<Word> = root
+        .syntax()
+        .descendants()
+        .filter_map(|node| ast::Path::cast(node.clone()))
+        .filter(|path| !in_use_decl(path))
+        .filter_map(|path| path.segments().next()?.text_key())
+        .collect();
+
+    for use_decl in &use_decls {
+        if use_decl
+            .visibility()
+            .is_some_and(|visibility| visibility.public().is_some())
+        {
+            continue;
+        }
+        let name = match use_decl.alias() {
+            Some(alias) => alias.ident().and_then(|ident| ident.text_key()),
+            None => use_decl
+                .path()
+                .and_then(|path| path.segments().last()?.text_key()),
+        };
+        let Some(name) = name else {
+            continue;
+        };
+        if referred.contains(&name) {
+            continue;
+        }
+        diagnostics.push(
+            LowerError::UnusedImport {
+                import: interner.resolve(&name).to_string(),
+                import_file_span: use_decl.range().to_span().in_file(file_id),
+            }
+            .to_diagnostic(),
+        );
+    }
+}
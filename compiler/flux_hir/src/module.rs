@@ -2,13 +2,15 @@ use std::ops::Index;
 use std::{collections::HashMap, ops::IndexMut};
 
 use flux_id::{id, Map};
-use flux_util::{FileId, Word};
+use flux_util::{words::AsText, FileId, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::item_scope::ItemScope;
 
 pub(super) mod collect;
 
-#[derive(Debug)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ModuleTree(Map<id::Mod, ModuleData>);
 
 impl ModuleTree {
@@ -46,9 +48,11 @@ impl IndexMut<id::Mod> for ModuleTree {
     }
 }
 
-#[derive(Debug)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ModuleData {
     pub parent: Option<id::Mod>,
+    #[serde_as(as = "HashMap<AsText, _>")]
     pub children: HashMap<Word, id::Mod>,
     pub scope: ItemScope,
     pub file_id: FileId,
@@ -1,5 +1,6 @@
 use flux_diagnostics::{Diagnostic, SourceCache, ToDiagnostic};
-use flux_util::{FileId, FileSpanned};
+use flux_parser::Parse;
+use flux_util::{FileId, FileSpanned, Interner};
 
 use crate::diagnostics::LowerError;
 
@@ -125,7 +126,7 @@ pub struct RelativePath<'a> {
     pub path: &'a str,
 }
 
-pub(super) trait FileResolver {
+pub trait FileResolver {
     fn resolve_absolute_path(
         &self,
         path: &str,
@@ -154,6 +155,11 @@ pub(super) trait FileResolver {
             Err(_) => None,
         }
     }
+
+    /// Parse a file that was resolved, which resolvers that keep syntax trees around can reuse instead
+    fn parse(&self, file_id: FileId, src: &str, interner: &'static Interner) -> Parse {
+        flux_parser::parse(src, file_id, interner)
+    }
 }
 
 pub struct BasicFileResolver;
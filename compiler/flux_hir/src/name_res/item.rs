@@ -6,7 +6,7 @@ use flux_id::{
 use flux_util::{InFile, Interner, Path, WithSpan, Word};
 
 use crate::{
-    def::item::{StructDecl, TraitDecl, Visibility},
+    def::item::{EnumDecl, StructDecl, TraitDecl, Visibility},
     item::{ItemId, ItemTreeIdx},
     item_scope::ItemScope,
     module::ModuleTree,
@@ -57,6 +57,16 @@ impl<'a> ItemResolver<'a> {
         *new_module_id
     }
 
+    /// The functions that can be referred to by name alone in a module of the package being resolved
+    pub fn function_names(&self, mod_id: id::Mod) -> impl Iterator<Item = Word> + '_ {
+        self.module_tree(self.package_id)[mod_id]
+            .scope
+            .items
+            .iter()
+            .filter(|(_, (_, item_id))| matches!(item_id.inner, ItemTreeIdx::Function(_)))
+            .map(|(name, _)| *name)
+    }
+
     pub fn resolve_path<A: Clone>(
         &self,
         path: InMod<&Path<Word, A>>,
@@ -271,4 +281,37 @@ impl<'a> ItemResolver<'a> {
 
         Ok((package_id, item_id.mod_id, struct_id))
     }
+
+    /// Enums are usually brought into scope by `use` items, as `Option` and `Result` are by the prelude, so `use`
+    /// items are followed to the enum they import
+    pub(crate) fn resolve_enum<A: Clone>(
+        &self,
+        path: InMod<&Path<Word, A>>,
+    ) -> Result<(id::Pkg, InFile<&EnumDecl>), ResolutionError<A>> {
+        let (package_id, item_id) = self.resolve_path(path)?;
+        let enum_id = match &item_id.inner {
+            ItemTreeIdx::Enum(id) => *id,
+            ItemTreeIdx::Use(use_id) => {
+                let u = self
+                    .packages
+                    .get(self.package_id)
+                    .item_tree
+                    .uses
+                    .get(*use_id);
+                let use_path = u.path.inner.clone().allow_args();
+                return self.resolve_enum((&use_path).in_mod(path.mod_id));
+            }
+            got => {
+                return Err(ResolutionError::UnexpectedItem {
+                    path: path.inner.clone(),
+                    expected: String::from("enum"),
+                    got: got.to_item_name().to_string(),
+                })
+            }
+        };
+        let pkg = self.packages.get(package_id);
+        let enum_decl = pkg.item_tree.enums.get(enum_id);
+        let file_id = pkg.module_tree[item_id.mod_id].file_id;
+        Ok((package_id, enum_decl.in_file(file_id)))
+    }
 }
@@ -1,30 +1,88 @@
 use flux_diagnostics::{Diagnostic, SourceCache};
 use flux_id::id;
-use flux_util::{FileId, Interner, Word};
+use flux_util::{words::AsText, FileId, Interner, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::{
-    item::ItemId,
-    lower::{item_tree::ItemTree, lower_cst_to_item_tree},
+    body::PackageBodies,
+    def::item::Visibility,
+    item::{ItemId, ItemTreeIdx},
+    lower::{item_tree::ItemTree, lower_cst_to_item_tree, report_unused_imports},
     module::{collect::ModCollector, ModuleData, ModuleTree},
     name_res::{FileResolver, ModDir},
     prelude::PRELUDE_SRC,
 };
 
-#[derive(Debug)]
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Package {
+    #[serde_as(as = "AsText")]
     pub name: Word,
     pub(crate) item_tree: ItemTree,
     pub(crate) module_tree: ModuleTree,
     pub(crate) dependencies: Vec<id::Pkg>,
+    pub(crate) bodies: PackageBodies,
 }
 
 impl Package {
     pub fn set_dependencies(&mut self, dependencies: Vec<id::Pkg>) {
         self.dependencies = dependencies;
     }
+
+    pub fn set_bodies(&mut self, bodies: PackageBodies) {
+        self.bodies = bodies;
+    }
+
+    pub fn item_tree(&self) -> &ItemTree {
+        &self.item_tree
+    }
+
+    pub fn bodies(&self) -> &PackageBodies {
+        &self.bodies
+    }
+
+    pub fn module_tree(&self) -> &ModuleTree {
+        &self.module_tree
+    }
+
+    /// Find the `main` function declared in the root module of the package
+    pub fn get_main_fn(&self, interner: &'static Interner) -> Option<id::FnDecl> {
+        let main = interner.get_or_intern_static("main");
+        self.item_tree
+            .top_level
+            .iter()
+            .filter(|item_id| item_id.mod_id == ModuleTree::ROOT_ID)
+            .find_map(|item_id| match item_id.inner {
+                ItemTreeIdx::Function(fn_id)
+                    if self.item_tree.functions.get(fn_id).name.inner == main =>
+                {
+                    Some(fn_id)
+                }
+                _ => None,
+            })
+    }
+
+    /// The public functions declared in the root module of the package, which make up its interface to the outside
+    /// world
+    pub fn public_fns(&self) -> impl Iterator<Item = id::FnDecl> + '_ {
+        self.item_tree
+            .top_level
+            .iter()
+            .filter(|item_id| item_id.mod_id == ModuleTree::ROOT_ID)
+            .filter_map(|item_id| match item_id.inner {
+                ItemTreeIdx::Function(fn_id)
+                    if self.item_tree.functions.get(fn_id).visibility.inner
+                        == Visibility::Public =>
+                {
+                    Some(fn_id)
+                }
+                _ => None,
+            })
+    }
 }
 
-pub(super) struct PkgBuilder<'a, R: FileResolver> {
+pub struct PkgBuilder<'a, R: FileResolver> {
     name: Word,
     pub item_tree: ItemTree,
     pub module_tree: ModuleTree,
@@ -35,7 +93,7 @@ pub(super) struct PkgBuilder<'a, R: FileResolver> {
 }
 
 impl<'a, R: FileResolver> PkgBuilder<'a, R> {
-    pub(super) fn new(
+    pub fn new(
         name: Word,
         diagnostics: &'a mut Vec<Diagnostic>,
         interner: &'static Interner,
@@ -59,10 +117,11 @@ impl<'a, R: FileResolver> PkgBuilder<'a, R> {
             item_tree: self.item_tree,
             module_tree: self.module_tree,
             dependencies: vec![],
+            bodies: PackageBodies::new(),
         }
     }
 
-    pub(crate) fn seed_with_entry(&mut self, file_id: FileId, src: &str) {
+    pub fn seed_with_entry(&mut self, file_id: FileId, src: &str) {
         let (root, items) = self.new_module(file_id, src, None);
 
         let prelude_file_id = FileId::prelude(self.interner);
@@ -94,7 +153,7 @@ impl<'a, R: FileResolver> PkgBuilder<'a, R> {
         src: &str,
         parent: Option<id::Mod>,
     ) -> (id::Mod, Vec<ItemId>) {
-        let mut cst = flux_parser::parse(src, file_id, self.interner);
+        let mut cst = self.resolver.parse(file_id, src, self.interner);
         let root = cst.syntax();
 
         self.diagnostics.append(&mut cst.diagnostics);
@@ -102,6 +161,10 @@ impl<'a, R: FileResolver> PkgBuilder<'a, R> {
         let module_data = ModuleData::new(parent, file_id);
         let module_id = self.module_tree.insert(module_data);
 
+        // The prelude is part of every package, so its imports aren't the package's to clean up
+        if file_id != FileId::prelude(self.interner) {
+            report_unused_imports(&root, file_id, self.interner, self.diagnostics);
+        }
         let items = lower_cst_to_item_tree(
             root,
             file_id,
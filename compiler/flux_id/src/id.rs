@@ -31,6 +31,8 @@
 
 use std::ops::{Deref, DerefMut};
 
+use serde::{Deserialize, Serialize};
+
 #[macro_export]
 macro_rules! nz_ids {
 	($($name:ident),*) => {
@@ -78,6 +80,18 @@ macro_rules! nz_ids {
 								}
 						}
 				}
+
+				impl $crate::serde::Serialize for $name {
+						fn serialize<S: $crate::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+								$crate::serde::Serialize::serialize(&self.0, serializer)
+						}
+				}
+
+				impl<'de> $crate::serde::Deserialize<'de> for $name {
+						fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+								$crate::serde::Deserialize::deserialize(deserializer).map(Self)
+						}
+				}
 			)*
 		}
 	};
@@ -113,15 +127,27 @@ macro_rules! ids {
 								self.raw()
 						}
 				}
+
+				impl $crate::serde::Serialize for $name {
+						fn serialize<S: $crate::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+								$crate::serde::Serialize::serialize(&self.0, serializer)
+						}
+				}
+
+				impl<'de> $crate::serde::Deserialize<'de> for $name {
+						fn deserialize<D: $crate::serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+								$crate::serde::Deserialize::deserialize(deserializer).map(Self)
+						}
+				}
 			)*
 		}
 	};
 }
 
 nz_ids!(TraitDecl, ApplyDecl);
-nz_ids!(Ty, Mod, Expr, Pkg, EnumDecl, FnDecl, ModDecl, StructDecl, UseDecl);
+nz_ids!(Ty, Mod, Expr, Pat, Pkg, EnumDecl, FnDecl, ModDecl, StructDecl, UseDecl);
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct InPkg<T> {
     pub pkg_id: Pkg,
     pub inner: T,
@@ -161,7 +187,7 @@ where
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct InMod<T> {
     pub mod_id: Mod,
     pub inner: T,
@@ -2,3 +2,5 @@ pub mod id;
 mod map;
 
 pub use map::Map;
+#[doc(hidden)]
+pub use serde;
@@ -2,16 +2,45 @@ use std::marker::PhantomData;
 
 use polonius_the_crab::{polonius, polonius_return};
 use replace_with::replace_with_or_abort;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{de::DeserializeAsWrap, ser::SerializeAsWrap, DeserializeAs, SerializeAs};
 
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct Map<K, V>
 where
     K: From<u32> + Into<u32>,
 {
     data: Vec<V>,
+    #[serde(skip)]
     _idx: PhantomData<K>,
 }
 
+/// Serializes the values of a map the way `U` serializes them, for maps of values that have to be serialized with
+/// `#[serde_as]`
+impl<K, V, U> SerializeAs<Map<K, V>> for Map<K, U>
+where
+    K: From<u32> + Into<u32>,
+    U: SerializeAs<V>,
+{
+    fn serialize_as<S: Serializer>(map: &Map<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializeAsWrap::<_, Vec<U>>::new(&map.data).serialize(serializer)
+    }
+}
+
+impl<'de, K, V, U> DeserializeAs<'de, Map<K, V>> for Map<K, U>
+where
+    K: From<u32> + Into<u32>,
+    U: DeserializeAs<'de, V>,
+{
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Map<K, V>, D::Error> {
+        let data = DeserializeAsWrap::<_, Vec<U>>::deserialize(deserializer)?;
+        Ok(Map {
+            data: data.into_inner(),
+            _idx: PhantomData,
+        })
+    }
+}
+
 impl<K, V> Default for Map<K, V>
 where
     K: From<u32> + Into<u32>,
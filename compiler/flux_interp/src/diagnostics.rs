@@ -0,0 +1,143 @@
+use flux_proc_macros::diagnostic;
+
+#[diagnostic]
+pub enum RuntimeError {
+    #[error(
+        location = ptr,
+        primary = "null pointer dereference",
+        label at ptr = "dereferenced a null pointer",
+    )]
+    NullPointerDereference {
+        #[filespanned]
+        ptr: (),
+    },
+    #[error(
+        location = access,
+        primary = "out of bounds memory access",
+        label at access = "accessed byte {offset} of an allocation of {size} bytes",
+    )]
+    OutOfBoundsAccess {
+        #[filespanned]
+        access: (),
+        offset: u64,
+        size: u64,
+    },
+    #[error(
+        location = access,
+        primary = "use after free",
+        label at access = "accessed memory that has already been freed",
+    )]
+    UseAfterFree {
+        #[filespanned]
+        access: (),
+    },
+    #[error(
+        location = free,
+        primary = "invalid free",
+        label at free = "pointer does not point to the start of a live allocation",
+        help = "only pointers returned by `@flux.intrinsics.malloc` can be freed, and only once",
+    )]
+    InvalidFree {
+        #[filespanned]
+        free: (),
+    },
+    #[error(
+        location = access,
+        primary = "read of uninitialized memory",
+        label at access = "nothing was ever written at byte {offset} of the allocation",
+    )]
+    UninitializedRead {
+        #[filespanned]
+        access: (),
+        offset: u64,
+    },
+    #[error(
+        location = malloc,
+        primary = "allocation too large",
+        label at malloc = "tried to allocate {size} bytes",
+    )]
+    AllocationTooLarge {
+        #[filespanned]
+        malloc: (),
+        size: u64,
+    },
+    #[error(
+        location = expr,
+        primary = "integer overflow",
+        label at expr = "`{op}` overflowed `{ty}`",
+    )]
+    IntegerOverflow {
+        #[filespanned]
+        expr: (),
+        op: &'static str,
+        ty: &'static str,
+    },
+    #[error(
+        location = expr,
+        primary = "division by zero",
+        label at expr = "attempted to divide by zero",
+    )]
+    DivisionByZero {
+        #[filespanned]
+        expr: (),
+    },
+    #[error(
+        location = panic,
+        primary = "program panicked",
+        label at panic = "explicit panic",
+    )]
+    ExplicitPanic {
+        #[filespanned]
+        panic: (),
+    },
+    #[error(
+        location = lhs,
+        primary = "invalid assignment",
+        label at lhs = "cannot assign to this expression",
+    )]
+    InvalidAssignment {
+        #[filespanned]
+        lhs: (),
+    },
+    #[error(
+        location = cast,
+        primary = "invalid cast",
+        label at cast = "cannot cast `{val}` to `{ty}`",
+    )]
+    InvalidCast {
+        #[filespanned]
+        cast: (),
+        val: String,
+        ty: String,
+    },
+    #[error(
+        location = call,
+        primary = "stack overflow",
+        label at call = "exceeded the maximum call depth of {max_depth}",
+    )]
+    StackOverflow {
+        #[filespanned]
+        call: (),
+        max_depth: usize,
+    },
+    #[error(
+        location = scrutinee,
+        primary = "no match arm matched",
+        label at scrutinee = "`{val}` did not match any of the arms",
+    )]
+    NoMatchingArm {
+        #[filespanned]
+        scrutinee: (),
+        val: String,
+    },
+    #[error(
+        location = expr,
+        primary = "unsupported expression",
+        label at expr = "{kind} cannot be interpreted yet",
+    )]
+    UnsupportedExpr {
+        #[filespanned]
+        expr: (),
+        kind: &'static str,
+    },
+}
@@ -0,0 +1,818 @@
+use std::collections::HashMap;
+
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_hir::{
+    def::{
+        expr::{BinOp, Call, Cast, Expr, If, Loop, Match, Op},
+        pat::Pat,
+    },
+    FnBody, ItemResolver, ItemTreeIdx, Package, PackageBodies,
+};
+use flux_id::{
+    id::{self, InPkg, WithMod, WithPackage},
+    Map,
+};
+use flux_typesystem::{ConcreteKind, TypeKind};
+use flux_util::{InFile, Interner, Span, WithSpan, Word};
+
+use crate::{
+    diagnostics::RuntimeError,
+    intrinsics,
+    layout::{bind, LayoutCx},
+    memory::{Heap, MemoryError},
+    value::{Enum, Int, IntTy, Pointer, Struct, Value},
+};
+
+const MAX_CALL_DEPTH: usize = 1024;
+
+pub(crate) struct Interpreter<'a> {
+    packages: &'a Map<id::Pkg, Package>,
+    interner: &'static Interner,
+    pub(crate) heap: Heap,
+    frames: Vec<Frame>,
+    resolved_fns: HashMap<(id::Pkg, id::Expr), InPkg<id::FnDecl>>,
+}
+
+struct Frame {
+    pkg_id: id::Pkg,
+    fn_id: InPkg<id::FnDecl>,
+    /// The types the generic parameters of the function were instantiated with, as far as they could be inferred
+    generics: HashMap<Word, TypeKind>,
+    body: FnBody,
+    scopes: Vec<HashMap<Word, Value>>,
+}
+
+/// Why the evaluation of an expression stopped before producing a value
+pub(crate) enum Unwind {
+    Error(Diagnostic),
+    /// Breaking out of the loop expression with the given value
+    Break(id::Expr, Value),
+    /// Continuing with the next iteration of the loop expression
+    Continue(id::Expr),
+}
+
+impl From<Diagnostic> for Unwind {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Unwind::Error(diagnostic)
+    }
+}
+
+impl Unwind {
+    /// Loops never span function boundaries, so only errors should unwind out of a call
+    fn into_diagnostic(self) -> Diagnostic {
+        match self {
+            Unwind::Error(diagnostic) => diagnostic,
+            Unwind::Break(_, _) | Unwind::Continue(_) => {
+                ice("`break` or `continue` unwound out of a function")
+            }
+        }
+    }
+}
+
+/// Somewhere a value can be assigned to
+///
+/// Fields are stored as a projection from the base so that nested member accesses can be written through.
+enum Place {
+    Local(Word, Vec<Word>),
+    Heap(Pointer, Vec<Word>),
+}
+
+impl<'a> Interpreter<'a> {
+    pub(crate) fn new(packages: &'a Map<id::Pkg, Package>, interner: &'static Interner) -> Self {
+        Self {
+            packages,
+            interner,
+            heap: Heap::new(),
+            frames: vec![],
+            resolved_fns: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn call_fn(
+        &mut self,
+        fn_id: InPkg<id::FnDecl>,
+        args: Vec<Value>,
+        generics: HashMap<Word, TypeKind>,
+        call_span: InFile<Span>,
+    ) -> Result<Value, Diagnostic> {
+        self.push_frame(fn_id, args, generics, call_span)?;
+        let result = self.eval(self.frame().body.expr);
+        self.frames.pop();
+        result.map_err(Unwind::into_diagnostic)
+    }
+
+    fn call_fn_place(
+        &mut self,
+        fn_id: InPkg<id::FnDecl>,
+        args: Vec<Value>,
+        generics: HashMap<Word, TypeKind>,
+        call_span: InFile<Span>,
+    ) -> Result<Place, Diagnostic> {
+        self.push_frame(fn_id, args, generics, call_span)?;
+        let result = self.eval_place(self.frame().body.expr);
+        self.frames.pop();
+        match result.map_err(Unwind::into_diagnostic)? {
+            // Locals die with the frame, so only memory on the heap can be assigned to through a call
+            Place::Local(_, _) => Err(RuntimeError::InvalidAssignment {
+                lhs: (),
+                lhs_file_span: call_span,
+            }
+            .to_diagnostic()),
+            place => Ok(place),
+        }
+    }
+
+    fn push_frame(
+        &mut self,
+        fn_id: InPkg<id::FnDecl>,
+        args: Vec<Value>,
+        generics: HashMap<Word, TypeKind>,
+        call_span: InFile<Span>,
+    ) -> Result<(), Diagnostic> {
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(RuntimeError::StackOverflow {
+                call: (),
+                call_file_span: call_span,
+                max_depth: MAX_CALL_DEPTH,
+            }
+            .to_diagnostic());
+        }
+
+        let package = self.packages.get(fn_id.pkg_id);
+        let fn_decl = package.item_tree().functions.get(fn_id.inner);
+        let body = *package
+            .bodies()
+            .get_fn_body(fn_id.inner)
+            .unwrap_or_else(|| {
+                ice(format!(
+                    "function `{}` has no lowered body",
+                    self.interner.resolve(&fn_decl.name)
+                ))
+            });
+        let params = fn_decl
+            .params
+            .iter()
+            .map(|param| param.name.inner)
+            .zip(args)
+            .collect();
+
+        self.frames.push(Frame {
+            pkg_id: fn_id.pkg_id,
+            fn_id,
+            generics,
+            body,
+            scopes: vec![params],
+        });
+        Ok(())
+    }
+
+    fn frame(&self) -> &Frame {
+        self.frames
+            .last()
+            .unwrap_or_else(|| ice("interpreter has no stack frame"))
+    }
+
+    fn frame_mut(&mut self) -> &mut Frame {
+        self.frames
+            .last_mut()
+            .unwrap_or_else(|| ice("interpreter has no stack frame"))
+    }
+
+    fn bodies(&self) -> &'a PackageBodies {
+        self.packages.get(self.frame().pkg_id).bodies()
+    }
+
+    fn span(&self, expr: id::Expr) -> InFile<Span> {
+        self.bodies()
+            .get_type(expr)
+            .span
+            .in_file(self.frame().body.file_id)
+    }
+
+    fn get_local(&self, name: &Word) -> Option<&Value> {
+        self.frame()
+            .scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+    }
+
+    fn get_local_mut(&mut self, name: &Word) -> Option<&mut Value> {
+        self.frame_mut()
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+    }
+
+    fn eval(&mut self, expr: id::Expr) -> Result<Value, Unwind> {
+        let bodies = self.bodies();
+        match &bodies.get_expr(expr).inner {
+            Expr::Address(_) => self.unsupported(expr, "taking the address of a value"),
+            Expr::Float(_) => self.unsupported(expr, "float literals"),
+            Expr::Str(_) => self.unsupported(expr, "string literals"),
+            Expr::Assignment(assignment) => {
+                let val = self.eval(assignment.val)?;
+                let place = self.eval_place(assignment.lhs)?;
+                self.assign(place, val, self.span(assignment.lhs))?;
+                Ok(Value::unit())
+            }
+            Expr::Block(block) => {
+                self.frame_mut().scopes.push(HashMap::new());
+                let result = block
+                    .stmts()
+                    .iter()
+                    .try_for_each(|stmt| self.eval(*stmt).map(|_| ()))
+                    .and_then(|_| self.eval(block.tail()));
+                self.frame_mut().scopes.pop();
+                result
+            }
+            Expr::BinOp(bin_op) => self.eval_bin_op(expr, bin_op),
+            Expr::Cast(cast) => self.eval_cast(expr, cast),
+            Expr::Call(call) => self.eval_call(expr, call),
+            Expr::Int(val) => {
+                let ty = self
+                    .int_ty(&bodies.get_type(expr).inner)
+                    .unwrap_or(IntTy::DEFAULT);
+                Ok(Value::Int(
+                    Int {
+                        val: *val as i128,
+                        ty,
+                    }
+                    .cast(ty),
+                ))
+            }
+            Expr::Bool(val) => Ok(Value::Bool(*val)),
+            Expr::Tuple(exprs) => exprs
+                .iter()
+                .map(|expr| self.eval(*expr))
+                .collect::<Result<_, _>>()
+                .map(Value::Tuple),
+            Expr::Path(path) => {
+                if path.len() == 1 {
+                    if let Some(val) = self.get_local(path.get_nth(0)) {
+                        return Ok(val.clone());
+                    }
+                }
+                Ok(Value::Fn(self.resolve_fn_path(expr)))
+            }
+            Expr::Let(l) => {
+                let val = self.eval(l.val)?;
+                self.frame_mut()
+                    .scopes
+                    .last_mut()
+                    .unwrap_or_else(|| ice("interpreter frame has no scope"))
+                    .insert(l.name.inner, val);
+                Ok(Value::unit())
+            }
+            Expr::Struct(strukt) => {
+                let name = *strukt
+                    .path
+                    .last()
+                    .unwrap_or_else(|| ice("struct expression with empty path"));
+                let fields = strukt
+                    .fields
+                    .iter()
+                    .map(|field| Ok((field.name.inner, self.eval(field.val)?)))
+                    .collect::<Result<_, Unwind>>()?;
+                Ok(Value::Struct(Struct::new(name, fields)))
+            }
+            Expr::Enum(enum_expr) => {
+                let name = *enum_expr
+                    .path
+                    .last()
+                    .unwrap_or_else(|| ice("enum expression with empty path"));
+                let payload = enum_expr
+                    .payload
+                    .map(|payload| self.eval(payload))
+                    .transpose()?;
+                Ok(Value::Enum(Enum::new(
+                    name,
+                    enum_expr.variant.inner,
+                    enum_expr.discriminant,
+                    payload,
+                )))
+            }
+            Expr::MemberAccess(member_access) => match self.eval(*member_access.lhs)? {
+                Value::Struct(strukt) => Ok(strukt
+                    .get_field(&member_access.field)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        ice(format!(
+                            "struct `{}` has no field `{}`",
+                            self.interner.resolve(&strukt.name),
+                            self.interner.resolve(&member_access.field)
+                        ))
+                    })),
+                val => ice(format!(
+                    "member access on non struct value `{}`",
+                    val.to_string(self.interner)
+                )),
+            },
+            Expr::If(if_expr) => self.eval_if(if_expr),
+            Expr::Match(match_expr) => self.eval_match(match_expr),
+            Expr::Loop(loop_expr) => self.eval_loop(expr, loop_expr),
+            Expr::Break(break_expr) => {
+                let val = match break_expr.val {
+                    Some(val) => self.eval(val)?,
+                    None => Value::unit(),
+                };
+                Err(Unwind::Break(break_expr.target, val))
+            }
+            Expr::Continue(continue_expr) => Err(Unwind::Continue(continue_expr.target)),
+            Expr::Intrinsic(intrinsic) => {
+                let args = intrinsic
+                    .args
+                    .iter()
+                    .map(|arg| Ok((self.eval(**arg)?, self.span(**arg))))
+                    .collect::<Result<Vec<_>, Unwind>>()?;
+                intrinsics::eval(
+                    self,
+                    self.interner.resolve(&intrinsic.name),
+                    args,
+                    self.span(expr),
+                )
+                .map_err(Unwind::from)
+            }
+            Expr::Poisoned => ice("poisoned expression reached the interpreter"),
+        }
+    }
+
+    fn unsupported(&self, expr: id::Expr, kind: &'static str) -> Result<Value, Unwind> {
+        Err(RuntimeError::UnsupportedExpr {
+            expr: (),
+            expr_file_span: self.span(expr),
+            kind,
+        }
+        .to_diagnostic()
+        .into())
+    }
+
+    fn eval_place(&mut self, expr: id::Expr) -> Result<Place, Unwind> {
+        let bodies = self.bodies();
+        match &bodies.get_expr(expr).inner {
+            Expr::Path(path) if path.len() == 1 && self.get_local(path.get_nth(0)).is_some() => {
+                Ok(Place::Local(*path.get_nth(0), vec![]))
+            }
+            Expr::MemberAccess(member_access) => {
+                let mut place = self.eval_place(*member_access.lhs)?;
+                match &mut place {
+                    Place::Local(_, fields) | Place::Heap(_, fields) => {
+                        fields.push(member_access.field.inner)
+                    }
+                }
+                Ok(place)
+            }
+            Expr::Intrinsic(intrinsic)
+                if self.interner.resolve(&intrinsic.name) == intrinsics::PTR_READ =>
+            {
+                let ptr = self.eval(*intrinsic.args[0])?;
+                let idx = self.eval(*intrinsic.args[1])?;
+                match (ptr, idx) {
+                    (Value::Ptr(ptr), Value::Int(idx)) => {
+                        Ok(Place::Heap(ptr.at(idx.val as u64), vec![]))
+                    }
+                    _ => ice("invalid arguments to `ptr_read` reached the interpreter"),
+                }
+            }
+            Expr::Call(call) => {
+                let (fn_id, args) = self.eval_callee_and_args(call)?;
+                let generics = self.instantiate(fn_id, call.args().iter().map(|arg| **arg), expr);
+                Ok(self.call_fn_place(fn_id, args, generics, self.span(expr))?)
+            }
+            Expr::Block(block) => {
+                self.frame_mut().scopes.push(HashMap::new());
+                let result = block
+                    .stmts()
+                    .iter()
+                    .try_for_each(|stmt| self.eval(*stmt).map(|_| ()))
+                    .and_then(|_| self.eval_place(block.tail()));
+                self.frame_mut().scopes.pop();
+                match result? {
+                    Place::Local(_, _) => Err(RuntimeError::InvalidAssignment {
+                        lhs: (),
+                        lhs_file_span: self.span(expr),
+                    }
+                    .to_diagnostic()
+                    .into()),
+                    place => Ok(place),
+                }
+            }
+            _ => Err(RuntimeError::InvalidAssignment {
+                lhs: (),
+                lhs_file_span: self.span(expr),
+            }
+            .to_diagnostic()
+            .into()),
+        }
+    }
+
+    fn assign(&mut self, place: Place, val: Value, span: InFile<Span>) -> Result<(), Diagnostic> {
+        let interner = self.interner;
+        let (base, fields) = match place {
+            Place::Local(name, fields) => {
+                let local = self
+                    .get_local_mut(&name)
+                    .unwrap_or_else(|| ice("assignment to unknown local"));
+                (local, fields)
+            }
+            Place::Heap(ptr, fields) if fields.is_empty() => {
+                return self
+                    .heap
+                    .write(ptr, val)
+                    .map_err(|err| memory_error(err, span));
+            }
+            Place::Heap(ptr, fields) => {
+                let slot = self
+                    .heap
+                    .get_mut(ptr)
+                    .map_err(|err| memory_error(err, span))?;
+                (slot, fields)
+            }
+        };
+
+        let slot = fields.iter().fold(base, |val, field| match val {
+            Value::Struct(strukt) => strukt.get_field_mut(field).unwrap_or_else(|| {
+                ice(format!(
+                    "assignment to unknown struct field `{}`",
+                    interner.resolve(field)
+                ))
+            }),
+            _ => ice("member access on non struct value"),
+        });
+        *slot = val;
+        Ok(())
+    }
+
+    fn eval_if(&mut self, if_expr: &If) -> Result<Value, Unwind> {
+        let else_ifs = if_expr.else_ifs().unwrap_or(&[]);
+        let branches = std::iter::once((if_expr.condition(), if_expr.then())).chain(
+            else_ifs
+                .chunks_exact(2)
+                .map(|else_if| (&else_if[0], &else_if[1])),
+        );
+
+        for (condition, block) in branches {
+            match self.eval(*condition)? {
+                Value::Bool(true) => return self.eval(*block),
+                Value::Bool(false) => {}
+                _ => ice("if condition is not a boolean"),
+            }
+        }
+
+        match if_expr.else_block() {
+            Some(block) => self.eval(*block),
+            None => Ok(Value::unit()),
+        }
+    }
+
+    /// `break` and `continue` unwind up to the loop they target, which catches them
+    fn eval_loop(&mut self, expr: id::Expr, loop_expr: &Loop) -> Result<Value, Unwind> {
+        loop {
+            match self.eval(loop_expr.body) {
+                Ok(_) => {}
+                Err(Unwind::Break(target, val)) if target == expr => return Ok(val),
+                Err(Unwind::Continue(target)) if target == expr => {}
+                Err(unwind) => return Err(unwind),
+            }
+        }
+    }
+
+    fn eval_match(&mut self, match_expr: &Match) -> Result<Value, Unwind> {
+        let val = self.eval(*match_expr.scrutinee)?;
+        for arm in &match_expr.arms {
+            // Each arm gets its own scope for the locals its pattern binds, even if it does not end up matching
+            self.frame_mut().scopes.push(HashMap::new());
+            let result = if self.match_pat(*arm.pat, &val) {
+                Some(self.eval(arm.body))
+            } else {
+                None
+            };
+            self.frame_mut().scopes.pop();
+            if let Some(result) = result {
+                return result;
+            }
+        }
+        Err(RuntimeError::NoMatchingArm {
+            scrutinee: (),
+            scrutinee_file_span: match_expr.scrutinee.span.in_file(self.frame().body.file_id),
+            val: val.to_string(self.interner),
+        }
+        .to_diagnostic()
+        .into())
+    }
+
+    /// Whether `val` matches `pat`, binding the locals in the pattern into the innermost scope
+    fn match_pat(&mut self, pat: id::Pat, val: &Value) -> bool {
+        match (&self.bodies().get_pat(pat).inner, val) {
+            (Pat::Wildcard, _) => true,
+            (Pat::Bind(name), val) => {
+                self.frame_mut()
+                    .scopes
+                    .last_mut()
+                    .unwrap_or_else(|| ice("interpreter frame has no scope"))
+                    .insert(name.inner, val.clone());
+                true
+            }
+            (Pat::Int(n), Value::Int(int)) => {
+                Int {
+                    val: *n as i128,
+                    ty: int.ty,
+                }
+                .cast(int.ty)
+                    == *int
+            }
+            (Pat::Bool(b), Value::Bool(val)) => b == val,
+            (Pat::Variant(variant), Value::Enum(e)) => {
+                variant.discriminant == e.discriminant
+                    && match (variant.payload, &e.payload) {
+                        (Some(pat), Some(payload)) => self.match_pat(pat, payload),
+                        (None, None) => true,
+                        _ => ice("enum payload does not match its variant"),
+                    }
+            }
+            (Pat::Tuple(pats), Value::Tuple(vals)) if pats.len() == vals.len() => pats
+                .iter()
+                .zip(vals)
+                .all(|(pat, val)| self.match_pat(*pat, val)),
+            (Pat::Struct(struct_pat), Value::Struct(strukt)) => {
+                struct_pat.fields.iter().all(|field| {
+                    let val = strukt.get_field(&field.name).unwrap_or_else(|| {
+                        ice(format!(
+                            "struct `{}` has no field `{}`",
+                            self.interner.resolve(&strukt.name),
+                            self.interner.resolve(&field.name)
+                        ))
+                    });
+                    self.match_pat(field.pat, val)
+                })
+            }
+            (Pat::Poisoned, _) => ice("poisoned pattern reached the interpreter"),
+            (_, val) => ice(format!(
+                "pattern matched against value of the wrong type `{}`",
+                val.to_string(self.interner)
+            )),
+        }
+    }
+
+    fn eval_call(&mut self, expr: id::Expr, call: &Call) -> Result<Value, Unwind> {
+        let (fn_id, args) = self.eval_callee_and_args(call)?;
+        let generics = self.instantiate(fn_id, call.args().iter().map(|arg| **arg), expr);
+        Ok(self.call_fn(fn_id, args, generics, self.span(expr))?)
+    }
+
+    fn eval_callee_and_args(
+        &mut self,
+        call: &Call,
+    ) -> Result<(InPkg<id::FnDecl>, Vec<Value>), Unwind> {
+        let bodies = self.bodies();
+        let args = call
+            .args()
+            .iter()
+            .map(|arg| self.eval(**arg))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let fn_id = match &bodies.get_expr(**call.callee()).inner {
+            // The receiver of a method call has already been inserted as the first argument
+            Expr::MemberAccess(member_access) => {
+                let receiver = args
+                    .first()
+                    .unwrap_or_else(|| ice("method call without a receiver"));
+                self.find_method(receiver, &member_access.field)
+                    .unwrap_or_else(|| {
+                        ice(format!(
+                            "could not find method `{}` for `{}`",
+                            self.interner.resolve(&member_access.field),
+                            receiver.to_string(self.interner)
+                        ))
+                    })
+            }
+            _ => match self.eval(**call.callee())? {
+                Value::Fn(fn_id) => fn_id,
+                _ => ice("callee is not a function"),
+            },
+        };
+        Ok((fn_id, args))
+    }
+
+    fn eval_bin_op(&mut self, expr: id::Expr, bin_op: &BinOp) -> Result<Value, Unwind> {
+        let span = self.span(expr);
+        let lhs = self.eval(bin_op.lhs)?;
+
+        match (*bin_op.op, &lhs) {
+            (Op::CmpAnd, Value::Bool(false)) => return Ok(Value::Bool(false)),
+            (Op::CmpOr, Value::Bool(true)) => return Ok(Value::Bool(true)),
+            _ => {}
+        }
+
+        let rhs = self.eval(bin_op.rhs)?;
+        let method_name = self
+            .interner
+            .get_or_intern_static(bin_op.op.as_trait_method_name());
+        match self.find_method(&lhs, &method_name) {
+            Some(fn_id) => {
+                let generics = self.instantiate(fn_id, [bin_op.lhs, bin_op.rhs].into_iter(), expr);
+                Ok(self.call_fn(fn_id, vec![lhs, rhs], generics, span)?)
+            }
+            None => Ok(intrinsics::eval_bin_op(*bin_op.op, lhs, rhs, span)?),
+        }
+    }
+
+    fn eval_cast(&mut self, expr: id::Expr, cast: &Cast) -> Result<Value, Unwind> {
+        let val = self.eval(cast.val)?;
+        let to_ty = &self.bodies().get_type(expr).inner;
+        let invalid_cast = |this: &Self, val: &Value| {
+            Err(RuntimeError::InvalidCast {
+                cast: (),
+                cast_file_span: this.span(expr),
+                val: val.to_string(this.interner),
+                ty: to_ty.to_string(this.interner),
+            }
+            .to_diagnostic()
+            .into())
+        };
+
+        match (to_ty, &val) {
+            (TypeKind::Concrete(ConcreteKind::Ptr(to)), Value::Ptr(ptr)) => {
+                Ok(Value::Ptr(self.with_stride(*ptr, &to.kind)))
+            }
+            (TypeKind::Concrete(ConcreteKind::Ptr(to)), Value::Int(int)) if int.val == 0 => {
+                Ok(Value::Ptr(self.with_stride(Pointer::null(), &to.kind)))
+            }
+            (TypeKind::Concrete(ConcreteKind::Ptr(_)), _) => invalid_cast(self, &val),
+            (to_ty, _) => match self.int_ty(to_ty) {
+                Some(ty) => match val {
+                    Value::Int(int) => Ok(Value::Int(int.cast(ty))),
+                    Value::Bool(b) => Ok(Value::Int(Int { val: b as i128, ty })),
+                    Value::Ptr(ptr) if ptr.alloc.is_none() => Ok(Value::Int(Int { val: 0, ty })),
+                    _ => invalid_cast(self, &val),
+                },
+                None => Ok(val),
+            },
+        }
+    }
+
+    fn resolve_fn_path(&mut self, expr: id::Expr) -> InPkg<id::FnDecl> {
+        let pkg_id = self.frame().pkg_id;
+        if let Some(fn_id) = self.resolved_fns.get(&(pkg_id, expr)) {
+            return *fn_id;
+        }
+
+        let path = match &self.bodies().get_expr(expr).inner {
+            Expr::Path(path) => path,
+            _ => ice("expected path expression"),
+        };
+        let item_resolver = ItemResolver::new(pkg_id, self.packages, self.interner);
+        let fn_id = match item_resolver.resolve_path(path.in_mod(self.frame().body.mod_id)) {
+            Ok((fn_pkg_id, item_id)) => match item_id.inner {
+                ItemTreeIdx::Function(fn_id) => fn_id.in_pkg(fn_pkg_id),
+                _ => ice("path expression did not resolve to a function"),
+            },
+            Err(_) => ice("unresolved path expression reached the interpreter"),
+        };
+        self.resolved_fns.insert((pkg_id, expr), fn_id);
+        fn_id
+    }
+
+    /// Find the method called `name` that should be dispatched to for `receiver`
+    ///
+    /// Applications to the exact type of the receiver are preferred over generic applications.
+    fn find_method(&self, receiver: &Value, name: &Word) -> Option<InPkg<id::FnDecl>> {
+        self.packages
+            .iter()
+            .flat_map(|(pkg_id, package)| {
+                let item_tree = package.item_tree();
+                item_tree.applies.values().filter_map(move |apply_decl| {
+                    let specificity =
+                        self.application_specificity(&apply_decl.to_ty.kind, receiver)?;
+                    apply_decl
+                        .methods
+                        .iter()
+                        .find(|method| item_tree.functions.get(**method).name.inner == *name)
+                        .map(|method| (specificity, (*method).in_pkg(pkg_id)))
+                })
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map(|(_, method)| method)
+    }
+
+    fn application_specificity(&self, to_ty: &TypeKind, receiver: &Value) -> Option<u8> {
+        match (to_ty, receiver) {
+            (TypeKind::Generic(_, _), _) => Some(0),
+            (TypeKind::Concrete(ConcreteKind::Ptr(_)), Value::Ptr(_)) => Some(1),
+            (TypeKind::Concrete(ConcreteKind::Tuple(types)), Value::Tuple(vals))
+                if types.len() == vals.len() =>
+            {
+                Some(1)
+            }
+            (TypeKind::Concrete(ConcreteKind::Path(path)), receiver) => {
+                let name = self.interner.resolve(path.last()?);
+                let matches = match receiver {
+                    Value::Int(int) => int.ty.name() == name,
+                    Value::Bool(_) => name == "bool",
+                    Value::Struct(strukt) => self.interner.resolve(&strukt.name) == name,
+                    Value::Enum(e) => self.interner.resolve(&e.name) == name,
+                    _ => false,
+                };
+                matches.then_some(1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Infer the types the generic parameters of `fn_id` are instantiated with by a call, from the types of its
+    /// arguments and of the call itself, like the native backend does when it monomorphizes the call
+    fn instantiate(
+        &self,
+        fn_id: InPkg<id::FnDecl>,
+        args: impl Iterator<Item = id::Expr>,
+        call: id::Expr,
+    ) -> HashMap<Word, TypeKind> {
+        let layout_cx = LayoutCx::new(self.packages, self.interner);
+        let fn_decl = self
+            .packages
+            .get(fn_id.pkg_id)
+            .item_tree()
+            .functions
+            .get(fn_id.inner);
+        let callee_apply = layout_cx.apply_of(fn_id);
+        let no_generics = HashMap::new();
+
+        let mut generics = HashMap::new();
+        let patterns = std::iter::once(&fn_decl.return_ty.kind)
+            .chain(fn_decl.params.iter().map(|param| &param.ty.kind));
+        for (pattern, expr) in patterns.zip(std::iter::once(call).chain(args)) {
+            let pattern = layout_cx.substitute(pattern, callee_apply, &no_generics);
+            let concrete = self.concrete_ty(&self.bodies().get_type(expr).inner);
+            bind(&pattern, &concrete, &mut generics);
+        }
+        generics
+    }
+
+    /// Substitute the generic arguments and application of the current function into a type from its body
+    fn concrete_ty(&self, ty: &TypeKind) -> TypeKind {
+        let layout_cx = LayoutCx::new(self.packages, self.interner);
+        let frame = self.frame();
+        layout_cx.substitute(ty, layout_cx.apply_of(frame.fn_id), &frame.generics)
+    }
+
+    /// Scale indices into `ptr` by the size of `pointee`, unless it is generic and its size unknown
+    fn with_stride(&self, ptr: Pointer, pointee: &TypeKind) -> Pointer {
+        let pointee = self.concrete_ty(pointee);
+        match LayoutCx::new(self.packages, self.interner).layout_of(&pointee) {
+            Some(layout) => Pointer {
+                stride: layout.size,
+                ..ptr
+            },
+            None => ptr,
+        }
+    }
+
+    fn int_ty(&self, tkind: &TypeKind) -> Option<IntTy> {
+        match tkind {
+            TypeKind::Concrete(ConcreteKind::Path(path)) if path.len() == 1 => {
+                IntTy::from_name(self.interner.resolve(path.get_nth(0)))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub(crate) fn memory_error(err: MemoryError, span: InFile<Span>) -> Diagnostic {
+    match err {
+        MemoryError::NullPointer => RuntimeError::NullPointerDereference {
+            ptr: (),
+            ptr_file_span: span,
+        }
+        .to_diagnostic(),
+        MemoryError::OutOfBounds { offset, size } => RuntimeError::OutOfBoundsAccess {
+            access: (),
+            access_file_span: span,
+            offset,
+            size,
+        }
+        .to_diagnostic(),
+        MemoryError::UseAfterFree => RuntimeError::UseAfterFree {
+            access: (),
+            access_file_span: span,
+        }
+        .to_diagnostic(),
+        MemoryError::InvalidFree => RuntimeError::InvalidFree {
+            free: (),
+            free_file_span: span,
+        }
+        .to_diagnostic(),
+        MemoryError::Uninitialized { offset } => RuntimeError::UninitializedRead {
+            access: (),
+            access_file_span: span,
+            offset,
+        }
+        .to_diagnostic(),
+        MemoryError::AllocationTooLarge { size } => RuntimeError::AllocationTooLarge {
+            malloc: (),
+            malloc_file_span: span,
+            size,
+        }
+        .to_diagnostic(),
+    }
+}
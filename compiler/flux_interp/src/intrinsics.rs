@@ -0,0 +1,160 @@
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_hir::def::expr::Op;
+use flux_util::{InFile, Span};
+
+use crate::{
+    diagnostics::RuntimeError,
+    eval::{memory_error, Interpreter},
+    value::{Int, IntTy, Value},
+};
+
+const PREFIX: &str = "@flux.intrinsics.";
+pub(crate) const PTR_READ: &str = "@flux.intrinsics.ptr_read";
+
+pub(crate) fn eval(
+    interpreter: &mut Interpreter,
+    name: &str,
+    mut args: Vec<(Value, InFile<Span>)>,
+    span: InFile<Span>,
+) -> Result<Value, Diagnostic> {
+    let name = name
+        .strip_prefix(PREFIX)
+        .unwrap_or_else(|| ice(format!("invalid intrinsic name `{name}`")));
+
+    match name {
+        "panic" => Err(RuntimeError::ExplicitPanic {
+            panic: (),
+            panic_file_span: span,
+        }
+        .to_diagnostic()),
+        "malloc" => {
+            let size = expect_int(&args[0].0);
+            interpreter
+                .heap
+                .alloc(size.val as u64)
+                .map(Value::Ptr)
+                .map_err(|err| memory_error(err, span))
+        }
+        "free" => {
+            let (ptr, ptr_span) = args.remove(0);
+            interpreter
+                .heap
+                .free(expect_ptr(&ptr))
+                .map_err(|err| memory_error(err, ptr_span))?;
+            Ok(Value::unit())
+        }
+        "memcpy" => {
+            let dst = expect_ptr(&args[0].0);
+            let src = expect_ptr(&args[1].0);
+            interpreter
+                .heap
+                .copy(dst, src)
+                .map_err(|err| memory_error(err, span))?;
+            Ok(Value::unit())
+        }
+        "ptr_read" => {
+            let ptr = expect_ptr(&args[0].0);
+            let idx = expect_int(&args[1].0);
+            interpreter
+                .heap
+                .read(ptr.at(idx.val as u64))
+                .map_err(|err| memory_error(err, span))
+        }
+        _ => {
+            let (op, ty) = name
+                .rsplit_once('_')
+                .unwrap_or_else(|| ice(format!("unknown intrinsic `{name}`")));
+            let ty = IntTy::from_name(ty).unwrap_or_else(|| ice(format!("unknown intrinsic `{name}`")));
+            let op = match op {
+                "add" => Op::Add,
+                "mul" => Op::Mul,
+                "cmp_eq" => Op::CmpEq,
+                _ => ice(format!("unknown intrinsic `{name}`")),
+            };
+            let lhs = expect_int(&args[0].0).cast(ty);
+            let rhs = expect_int(&args[1].0).cast(ty);
+            eval_int_bin_op(op, lhs, rhs, span)
+        }
+    }
+}
+
+/// Evaluate a binary operation on primitive values that have no trait application to dispatch to
+pub(crate) fn eval_bin_op(
+    op: Op,
+    lhs: Value,
+    rhs: Value,
+    span: InFile<Span>,
+) -> Result<Value, Diagnostic> {
+    match (lhs, rhs) {
+        (Value::Int(lhs), Value::Int(rhs)) => eval_int_bin_op(op, lhs, rhs.cast(lhs.ty), span),
+        (Value::Bool(lhs), Value::Bool(rhs)) => match op {
+            Op::CmpAnd => Ok(Value::Bool(lhs && rhs)),
+            Op::CmpOr => Ok(Value::Bool(lhs || rhs)),
+            Op::CmpEq => Ok(Value::Bool(lhs == rhs)),
+            Op::CmpNeq => Ok(Value::Bool(lhs != rhs)),
+            _ => ice(format!("unsupported binary operation `{op}` on booleans")),
+        },
+        (Value::Ptr(lhs), Value::Ptr(rhs)) => match op {
+            Op::CmpEq => Ok(Value::Bool(lhs == rhs)),
+            Op::CmpNeq => Ok(Value::Bool(lhs != rhs)),
+            _ => ice(format!("unsupported binary operation `{op}` on pointers")),
+        },
+        _ => ice(format!("unsupported binary operation `{op}`")),
+    }
+}
+
+fn eval_int_bin_op(op: Op, lhs: Int, rhs: Int, span: InFile<Span>) -> Result<Value, Diagnostic> {
+    let overflow = |op: &'static str| {
+        RuntimeError::IntegerOverflow {
+            expr: (),
+            expr_file_span: span,
+            op,
+            ty: lhs.ty.name(),
+        }
+        .to_diagnostic()
+    };
+    let checked = |val: Option<i128>, op| {
+        val.and_then(|val| Int::new(val, lhs.ty))
+            .ok_or_else(|| overflow(op))
+    };
+
+    let val = match op {
+        Op::Add => checked(lhs.val.checked_add(rhs.val), "+")?,
+        Op::Sub => checked(lhs.val.checked_sub(rhs.val), "-")?,
+        Op::Mul => checked(lhs.val.checked_mul(rhs.val), "*")?,
+        Op::Div => {
+            if rhs.val == 0 {
+                return Err(RuntimeError::DivisionByZero {
+                    expr: (),
+                    expr_file_span: span,
+                }
+                .to_diagnostic());
+            }
+            checked(lhs.val.checked_div(rhs.val), "/")?
+        }
+        Op::CmpEq => return Ok(Value::Bool(lhs.val == rhs.val)),
+        Op::CmpNeq => return Ok(Value::Bool(lhs.val != rhs.val)),
+        Op::CmpGt => return Ok(Value::Bool(lhs.val > rhs.val)),
+        Op::CmpGte => return Ok(Value::Bool(lhs.val >= rhs.val)),
+        Op::CmpLt => return Ok(Value::Bool(lhs.val < rhs.val)),
+        Op::CmpLte => return Ok(Value::Bool(lhs.val <= rhs.val)),
+        Op::Eq | Op::CmpAnd | Op::CmpOr => {
+            ice(format!("unsupported binary operation `{op}` on integers"))
+        }
+    };
+    Ok(Value::Int(val))
+}
+
+fn expect_int(val: &Value) -> Int {
+    match val {
+        Value::Int(int) => *int,
+        _ => ice("expected integer argument to intrinsic"),
+    }
+}
+
+fn expect_ptr(val: &Value) -> crate::value::Pointer {
+    match val {
+        Value::Ptr(ptr) => *ptr,
+        _ => ice("expected pointer argument to intrinsic"),
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+
+use flux_hir::{
+    def::{item::ApplyDecl, GenericParams},
+    Package,
+};
+use flux_id::{
+    id::{self, InPkg},
+    Map,
+};
+use flux_typesystem::{ConcreteKind, Type, TypeKind};
+use flux_util::{Interner, Path, Word};
+
+/// The size of a pointer on the native targets, whose layouts pointers into the heap are scaled by
+const PTR_BYTES: u64 = 8;
+
+/// The size and alignment of a type in bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Layout {
+    pub(crate) size: u64,
+    pub(crate) align: u64,
+}
+
+impl Layout {
+    const UNIT: Self = Self { size: 0, align: 1 };
+
+    /// The `u32` tag that selects the variant of an enum
+    const TAG: Self = Self::scalar(4);
+
+    const fn scalar(size: u64) -> Self {
+        Self { size, align: size }
+    }
+}
+
+/// Lays out types exactly like the native backend does, so that the interpreter agrees with it on the size of every
+/// allocation a program indexes into
+pub(crate) struct LayoutCx<'a> {
+    packages: &'a Map<id::Pkg, Package>,
+    interner: &'static Interner,
+}
+
+impl<'a> LayoutCx<'a> {
+    pub(crate) fn new(packages: &'a Map<id::Pkg, Package>, interner: &'static Interner) -> Self {
+        Self { packages, interner }
+    }
+
+    /// Lay out a type, returning `None` if its size depends on generic parameters the interpreter knows nothing about
+    pub(crate) fn layout_of(&self, ty: &TypeKind) -> Option<Layout> {
+        self.layout_with(ty, &HashMap::new())
+    }
+
+    /// The application a method belongs to, if the function is one
+    pub(crate) fn apply_of(&self, fn_id: InPkg<id::FnDecl>) -> Option<&'a ApplyDecl> {
+        self.packages
+            .get(fn_id.pkg_id)
+            .item_tree()
+            .applies
+            .values()
+            .find(|apply_decl| apply_decl.methods.contains(&fn_id.inner))
+    }
+
+    /// Replace `This`, its associated types and generic parameters with what they stand for, where known
+    pub(crate) fn substitute(
+        &self,
+        ty: &TypeKind,
+        apply_decl: Option<&ApplyDecl>,
+        generics: &HashMap<Word, TypeKind>,
+    ) -> TypeKind {
+        let substitute =
+            |ty: &Type| Type::new(self.substitute(&ty.kind, apply_decl, generics), vec![]);
+        match ty {
+            TypeKind::ThisPath(this_path) => {
+                let Some(apply_decl) = apply_decl else {
+                    return ty.clone();
+                };
+                let ty = match this_path.path.segments.as_slice() {
+                    [] => &apply_decl.to_ty.kind,
+                    [name] => match apply_decl
+                        .assoc_types
+                        .iter()
+                        .find(|assoc_type| assoc_type.name.inner == *name)
+                    {
+                        Some(assoc_type) => &assoc_type.ty.kind,
+                        None => return ty.clone(),
+                    },
+                    _ => return ty.clone(),
+                };
+                self.substitute(ty, Some(apply_decl), generics)
+            }
+            TypeKind::Generic(name, _) => generics.get(name).cloned().unwrap_or_else(|| ty.clone()),
+            TypeKind::Concrete(ConcreteKind::Ptr(to)) => {
+                TypeKind::Concrete(ConcreteKind::Ptr(Box::new(substitute(to))))
+            }
+            TypeKind::Concrete(ConcreteKind::Path(path)) => {
+                TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    path.segments.clone(),
+                    path.args.iter().map(substitute).collect(),
+                )))
+            }
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) => {
+                TypeKind::Concrete(ConcreteKind::Tuple(types.iter().map(substitute).collect()))
+            }
+            _ => ty.clone(),
+        }
+    }
+
+    fn layout_with(&self, ty: &TypeKind, generics: &HashMap<Word, TypeKind>) -> Option<Layout> {
+        match ty {
+            TypeKind::Int => Some(Layout::scalar(8)),
+            TypeKind::Never => Some(Layout::UNIT),
+            TypeKind::Generic(name, _) => generics.get(name).and_then(|ty| self.layout_of(ty)),
+            TypeKind::Concrete(ConcreteKind::Ptr(_) | ConcreteKind::Fn(_)) => {
+                Some(Layout::scalar(PTR_BYTES))
+            }
+            TypeKind::Concrete(ConcreteKind::Tuple(types)) => {
+                aggregate(types.iter().map(|ty| self.layout_with(&ty.kind, generics)))
+            }
+            TypeKind::Concrete(ConcreteKind::Path(path)) => {
+                let name = path.last()?;
+                match self.interner.resolve(name) {
+                    "bool" => Some(Layout::scalar(1)),
+                    "u64" | "s64" if path.len() == 1 => Some(Layout::scalar(8)),
+                    "u32" | "s32" if path.len() == 1 => Some(Layout::scalar(4)),
+                    "u16" | "s16" if path.len() == 1 => Some(Layout::scalar(2)),
+                    "u8" | "s8" if path.len() == 1 => Some(Layout::scalar(1)),
+                    _ => self.adt_layout(name, path, generics),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn adt_layout(
+        &self,
+        name: &Word,
+        path: &Path<Word, Type>,
+        generics: &HashMap<Word, TypeKind>,
+    ) -> Option<Layout> {
+        let args = |generic_params: &GenericParams| -> HashMap<Word, TypeKind> {
+            generic_params
+                .types
+                .values()
+                .map(|name| name.inner)
+                .zip(
+                    path.args
+                        .iter()
+                        .map(|arg| self.substitute(&arg.kind, None, generics)),
+                )
+                .collect()
+        };
+
+        let item_trees = self.packages.values().map(|package| package.item_tree());
+        for item_tree in item_trees {
+            if let Some(enum_decl) = item_tree
+                .enums
+                .values()
+                .find(|decl| decl.name.inner == *name)
+            {
+                let generics = args(&enum_decl.generic_params);
+                let mut payload = Layout::UNIT;
+                for variant in enum_decl.variants.iter() {
+                    if let Some(ty) = &variant.ty {
+                        let layout = self.layout_with(&ty.kind, &generics)?;
+                        payload.size = payload.size.max(layout.size);
+                        payload.align = payload.align.max(layout.align);
+                    }
+                }
+                let align = Layout::TAG.align.max(payload.align);
+                let payload_offset = Layout::TAG.size.next_multiple_of(payload.align);
+                return Some(Layout {
+                    size: (payload_offset + payload.size).next_multiple_of(align),
+                    align,
+                });
+            }
+            if let Some(struct_decl) = item_tree
+                .structs
+                .values()
+                .find(|decl| decl.name.inner == *name)
+            {
+                let generics = args(&struct_decl.generic_params);
+                return aggregate(
+                    struct_decl
+                        .fields
+                        .iter()
+                        .map(|field| self.layout_with(&field.ty.kind, &generics)),
+                );
+            }
+        }
+        None
+    }
+}
+
+/// Fields are laid out in order, each aligned to its own alignment
+fn aggregate(fields: impl Iterator<Item = Option<Layout>>) -> Option<Layout> {
+    let mut size: u64 = 0;
+    let mut align = 1;
+    for field in fields {
+        let field = field?;
+        size = size.next_multiple_of(field.align) + field.size;
+        align = align.max(field.align);
+    }
+    Some(Layout {
+        size: size.next_multiple_of(align),
+        align,
+    })
+}
+
+/// Bind the generic parameters in `pattern` to the parts of `concrete` they correspond to, keeping the first binding
+/// of each
+pub(crate) fn bind(
+    pattern: &TypeKind,
+    concrete: &TypeKind,
+    bindings: &mut HashMap<Word, TypeKind>,
+) {
+    match (pattern, concrete) {
+        (_, TypeKind::Generic(..) | TypeKind::Never | TypeKind::Unknown) => {}
+        (TypeKind::Generic(name, _), _) => {
+            bindings.entry(*name).or_insert_with(|| concrete.clone());
+        }
+        (
+            TypeKind::Concrete(ConcreteKind::Ptr(pattern)),
+            TypeKind::Concrete(ConcreteKind::Ptr(concrete)),
+        ) => bind(&pattern.kind, &concrete.kind, bindings),
+        (
+            TypeKind::Concrete(ConcreteKind::Path(pattern)),
+            TypeKind::Concrete(ConcreteKind::Path(concrete)),
+        ) => {
+            for (pattern, concrete) in pattern.args.iter().zip(&concrete.args) {
+                bind(&pattern.kind, &concrete.kind, bindings);
+            }
+        }
+        (
+            TypeKind::Concrete(ConcreteKind::Tuple(patterns)),
+            TypeKind::Concrete(ConcreteKind::Tuple(types)),
+        ) => {
+            for (pattern, concrete) in patterns.iter().zip(types) {
+                bind(&pattern.kind, &concrete.kind, bindings);
+            }
+        }
+        _ => {}
+    }
+}
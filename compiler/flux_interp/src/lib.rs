@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use eval::Interpreter;
+use flux_diagnostics::{ice, Diagnostic};
+use flux_hir::Package;
+use flux_id::{
+    id::{self, InPkg},
+    Map,
+};
+use flux_util::{Interner, WithSpan};
+
+mod diagnostics;
+mod eval;
+mod intrinsics;
+mod layout;
+mod memory;
+mod value;
+
+pub use value::{Int, IntTy, Pointer, Struct, Value};
+
+/// Interpreted programs recurse on the host stack, so they get a stack of their own rather than the main thread's
+const INTERPRETER_STACK_SIZE: usize = 512 * 1024 * 1024;
+
+/// Interpret a program starting at `main`, returning the value it evaluates to
+///
+/// Any runtime error (null dereference, out of bounds access, overflow, ...) is reported as a [`Diagnostic`] that
+/// points at the expression that caused it.
+pub fn run(
+    main: InPkg<id::FnDecl>,
+    packages: &Map<id::Pkg, Package>,
+    interner: &'static Interner,
+) -> Result<Value, Diagnostic> {
+    let package = packages.get(main.pkg_id);
+    let call_span = package
+        .item_tree()
+        .functions
+        .get(main.inner)
+        .name
+        .span
+        .in_file(
+            package
+                .bodies()
+                .get_fn_body(main.inner)
+                .unwrap_or_else(|| ice("main function has no lowered body"))
+                .file_id,
+        );
+
+    std::thread::scope(|scope| {
+        std::thread::Builder::new()
+            .name("flux-interp".to_string())
+            .stack_size(INTERPRETER_STACK_SIZE)
+            .spawn_scoped(scope, || {
+                Interpreter::new(packages, interner).call_fn(
+                    main,
+                    vec![],
+                    HashMap::new(),
+                    call_span,
+                )
+            })
+            .unwrap_or_else(|err| ice(format!("could not spawn interpreter thread: {err}")))
+            .join()
+            .unwrap_or_else(|_| ice("interpreter thread panicked"))
+    })
+}
@@ -0,0 +1,159 @@
+use std::collections::BTreeMap;
+
+use flux_id::{ids, Map};
+
+use crate::value::{Pointer, Value};
+
+ids!(AllocId);
+
+/// The largest allocation any target could hand out, beyond which `malloc` fails rather than pretending to succeed
+const MAX_ALLOC_SIZE: u64 = isize::MAX as u64;
+
+/// The heap of an interpreted program
+///
+/// Every call to `malloc` creates a new allocation of the requested number of bytes. Values are never laid out in
+/// memory, so each one is kept at the byte offset it was written to, along with the size of the element it was
+/// written through, which is what makes indices and bounds match the native backend. Freed allocations are kept
+/// around so that dangling pointers can be reported rather than silently aliasing a newer allocation.
+#[derive(Debug, Default)]
+pub(crate) struct Heap {
+    allocs: Map<AllocId, Option<Allocation>>,
+}
+
+#[derive(Debug)]
+struct Allocation {
+    size: u64,
+    /// The values in the allocation by the offset they start at, which never overlap
+    values: BTreeMap<u64, (Value, u64)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MemoryError {
+    NullPointer,
+    OutOfBounds { offset: u64, size: u64 },
+    UseAfterFree,
+    InvalidFree,
+    Uninitialized { offset: u64 },
+    AllocationTooLarge { size: u64 },
+}
+
+impl Allocation {
+    /// Check that the element `ptr` points to lies within the allocation
+    fn check_bounds(&self, ptr: Pointer) -> Result<(), MemoryError> {
+        match ptr.offset.checked_add(ptr.stride) {
+            Some(end) if end <= self.size => Ok(()),
+            _ => Err(MemoryError::OutOfBounds {
+                offset: ptr.offset,
+                size: self.size,
+            }),
+        }
+    }
+
+    /// Remove every value that overlaps the bytes from `start` up to `end`
+    fn clear(&mut self, start: u64, end: u64) {
+        let before = self
+            .values
+            .range(..start)
+            .next_back()
+            .filter(|(offset, (_, size))| **offset + size > start)
+            .map(|(offset, _)| *offset);
+        let overlapping: Vec<_> = before
+            .into_iter()
+            .chain(self.values.range(start..end).map(|(offset, _)| *offset))
+            .collect();
+        for offset in overlapping {
+            self.values.remove(&offset);
+        }
+    }
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn alloc(&mut self, size: u64) -> Result<Pointer, MemoryError> {
+        if size > MAX_ALLOC_SIZE {
+            return Err(MemoryError::AllocationTooLarge { size });
+        }
+        let alloc = self.allocs.insert(Some(Allocation {
+            size,
+            values: BTreeMap::new(),
+        }));
+        Ok(Pointer::new(alloc, 0))
+    }
+
+    pub fn free(&mut self, ptr: Pointer) -> Result<(), MemoryError> {
+        let alloc = ptr.alloc.ok_or(MemoryError::NullPointer)?;
+        let slot = self.allocs.get_mut(alloc);
+        if slot.is_none() || ptr.offset != 0 {
+            return Err(MemoryError::InvalidFree);
+        }
+        *slot = None;
+        Ok(())
+    }
+
+    pub fn read(&self, ptr: Pointer) -> Result<Value, MemoryError> {
+        let allocation = self.allocation(ptr)?;
+        allocation.check_bounds(ptr)?;
+        allocation
+            .values
+            .get(&ptr.offset)
+            .map(|(val, _)| val.clone())
+            .ok_or(MemoryError::Uninitialized { offset: ptr.offset })
+    }
+
+    pub fn write(&mut self, ptr: Pointer, val: Value) -> Result<(), MemoryError> {
+        let allocation = self.allocation_mut(ptr)?;
+        allocation.check_bounds(ptr)?;
+        allocation.clear(ptr.offset, ptr.offset + ptr.stride);
+        allocation.values.insert(ptr.offset, (val, ptr.stride));
+        Ok(())
+    }
+
+    /// Get a mutable reference to an initialized value, so that parts of it can be assigned to
+    pub fn get_mut(&mut self, ptr: Pointer) -> Result<&mut Value, MemoryError> {
+        let allocation = self.allocation_mut(ptr)?;
+        allocation.check_bounds(ptr)?;
+        allocation
+            .values
+            .get_mut(&ptr.offset)
+            .map(|(val, _)| val)
+            .ok_or(MemoryError::Uninitialized { offset: ptr.offset })
+    }
+
+    /// Copy the bytes from `src` to the end of its allocation into `dst`, along with every value they hold
+    pub fn copy(&mut self, dst: Pointer, src: Pointer) -> Result<(), MemoryError> {
+        let allocation = self.allocation(src)?;
+        let len = allocation.size.saturating_sub(src.offset);
+        let values: Vec<_> = allocation
+            .values
+            .range(src.offset..)
+            .map(|(offset, (val, size))| (offset - src.offset, val.clone(), *size))
+            .collect();
+
+        let allocation = self.allocation_mut(dst)?;
+        allocation.check_bounds(Pointer { stride: len, ..dst })?;
+        allocation.clear(dst.offset, dst.offset + len);
+        for (offset, val, size) in values {
+            allocation.values.insert(dst.offset + offset, (val, size));
+        }
+        Ok(())
+    }
+
+    fn allocation(&self, ptr: Pointer) -> Result<&Allocation, MemoryError> {
+        let alloc = ptr.alloc.ok_or(MemoryError::NullPointer)?;
+        self.allocs
+            .get(alloc)
+            .as_ref()
+            .ok_or(MemoryError::UseAfterFree)
+    }
+
+    fn allocation_mut(&mut self, ptr: Pointer) -> Result<&mut Allocation, MemoryError> {
+        let alloc = ptr.alloc.ok_or(MemoryError::NullPointer)?;
+        self.allocs
+            .get_mut(alloc)
+            .as_mut()
+            .ok_or(MemoryError::UseAfterFree)
+    }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Display;
+
+use flux_id::id::{self, InPkg};
+use flux_util::{Interner, Word};
+
+use crate::memory::AllocId;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(Int),
+    Bool(bool),
+    Ptr(Pointer),
+    Struct(Struct),
+    Enum(Enum),
+    Tuple(Vec<Value>),
+    Fn(InPkg<id::FnDecl>),
+}
+
+impl Value {
+    pub const fn unit() -> Self {
+        Self::Tuple(vec![])
+    }
+
+    pub fn to_string(&self, interner: &'static Interner) -> String {
+        match self {
+            Value::Int(int) => format!("{int}{}", int.ty),
+            Value::Bool(b) => b.to_string(),
+            Value::Ptr(ptr) => match ptr.alloc {
+                Some(alloc) => format!("<ptr {}+{}>", alloc.raw(), ptr.offset),
+                None => "<null>".to_string(),
+            },
+            Value::Struct(strukt) => format!(
+                "{} {{ {} }}",
+                interner.resolve(&strukt.name),
+                strukt
+                    .fields
+                    .iter()
+                    .map(|(name, val)| format!(
+                        "{}: {}",
+                        interner.resolve(name),
+                        val.to_string(interner)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Enum(e) => match &e.payload {
+                Some(payload) => format!(
+                    "{}::{}({})",
+                    interner.resolve(&e.name),
+                    interner.resolve(&e.variant),
+                    payload.to_string(interner)
+                ),
+                None => format!(
+                    "{}::{}",
+                    interner.resolve(&e.name),
+                    interner.resolve(&e.variant)
+                ),
+            },
+            Value::Tuple(vals) => format!(
+                "({})",
+                vals.iter()
+                    .map(|val| val.to_string(interner))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Value::Fn(_) => "<fn>".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntTy {
+    S64,
+    S32,
+    S16,
+    S8,
+    U64,
+    U32,
+    U16,
+    U8,
+}
+
+impl IntTy {
+    /// The type integer literals take when the type checker could not narrow them down
+    pub const DEFAULT: Self = Self::U64;
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "s64" => Some(Self::S64),
+            "s32" => Some(Self::S32),
+            "s16" => Some(Self::S16),
+            "s8" => Some(Self::S8),
+            "u64" => Some(Self::U64),
+            "u32" => Some(Self::U32),
+            "u16" => Some(Self::U16),
+            "u8" => Some(Self::U8),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::S64 => "s64",
+            Self::S32 => "s32",
+            Self::S16 => "s16",
+            Self::S8 => "s8",
+            Self::U64 => "u64",
+            Self::U32 => "u32",
+            Self::U16 => "u16",
+            Self::U8 => "u8",
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        match self {
+            Self::S64 | Self::U64 => 64,
+            Self::S32 | Self::U32 => 32,
+            Self::S16 | Self::U16 => 16,
+            Self::S8 | Self::U8 => 8,
+        }
+    }
+
+    pub fn is_signed(&self) -> bool {
+        matches!(self, Self::S64 | Self::S32 | Self::S16 | Self::S8)
+    }
+
+    fn min(&self) -> i128 {
+        if self.is_signed() {
+            -(1i128 << (self.bits() - 1))
+        } else {
+            0
+        }
+    }
+
+    fn max(&self) -> i128 {
+        if self.is_signed() {
+            (1i128 << (self.bits() - 1)) - 1
+        } else {
+            (1i128 << self.bits()) - 1
+        }
+    }
+}
+
+impl Display for IntTy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An integer value along with the type it was computed as
+///
+/// The value is stored widened so that every integer type shares the same arithmetic
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Int {
+    pub val: i128,
+    pub ty: IntTy,
+}
+
+impl Int {
+    pub fn new(val: i128, ty: IntTy) -> Option<Self> {
+        (ty.min()..=ty.max())
+            .contains(&val)
+            .then_some(Self { val, ty })
+    }
+
+    /// Reinterpret the integer as another integer type, truncating or extending as necessary
+    pub fn cast(self, ty: IntTy) -> Self {
+        let truncated = (self.val as u128) & (u128::MAX >> (128 - ty.bits()));
+        let val = if ty.is_signed() && truncated >> (ty.bits() - 1) == 1 {
+            truncated as i128 - (1i128 << ty.bits())
+        } else {
+            truncated as i128
+        };
+        Self { val, ty }
+    }
+}
+
+impl Display for Int {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.val)
+    }
+}
+
+/// A pointer into the interpreter's heap
+///
+/// Offsets count in bytes, like on the native backend. The size of the type a pointer was last cast to point to is
+/// its stride, which indices are scaled by. Generic code does not know the size of what it points to, so a pointer
+/// cast to a generic type keeps the stride it already had, and one fresh out of `malloc` has a stride of one.
+#[derive(Debug, Clone, Copy, Eq)]
+pub struct Pointer {
+    pub alloc: Option<AllocId>,
+    pub offset: u64,
+    pub stride: u64,
+}
+
+impl Pointer {
+    pub const fn null() -> Self {
+        Self {
+            alloc: None,
+            offset: 0,
+            stride: 1,
+        }
+    }
+
+    pub const fn new(alloc: AllocId, offset: u64) -> Self {
+        Self {
+            alloc: Some(alloc),
+            offset,
+            stride: 1,
+        }
+    }
+
+    /// A pointer to the element `idx` elements after the one this one points to
+    pub fn at(self, idx: u64) -> Self {
+        Self {
+            offset: self.offset.saturating_add(idx.saturating_mul(self.stride)),
+            ..self
+        }
+    }
+}
+
+/// Pointers to the same byte are equal, whatever type they point to
+impl PartialEq for Pointer {
+    fn eq(&self, other: &Self) -> bool {
+        self.alloc == other.alloc && self.offset == other.offset
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Struct {
+    pub name: Word,
+    pub fields: Vec<(Word, Value)>,
+}
+
+impl Struct {
+    pub fn new(name: Word, fields: Vec<(Word, Value)>) -> Self {
+        Self { name, fields }
+    }
+
+    pub fn get_field(&self, name: &Word) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find_map(|(field_name, val)| (field_name == name).then_some(val))
+    }
+
+    pub fn get_field_mut(&mut self, name: &Word) -> Option<&mut Value> {
+        self.fields
+            .iter_mut()
+            .find_map(|(field_name, val)| (field_name == name).then_some(val))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Enum {
+    pub name: Word,
+    pub variant: Word,
+    pub discriminant: u32,
+    pub payload: Option<Box<Value>>,
+}
+
+impl Enum {
+    pub fn new(name: Word, variant: Word, discriminant: u32, payload: Option<Value>) -> Self {
+        Self {
+            name,
+            variant,
+            discriminant,
+            payload: payload.map(Box::new),
+        }
+    }
+}
@@ -0,0 +1,510 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use flux::{
+    git::Git,
+    graph::PackageGraph,
+    lockfile::Lockfile,
+    registry::{self, Registry},
+    resolver,
+};
+use flux_diagnostics::{Diagnostic, IOError, SourceCache};
+use flux_hir::{
+    db::{Database, DefinePackage, FileText, PackageBodiesQuery, PackageSource, Project},
+    def::expr::Expr,
+    FnBody, ItemId, ItemResolver, ItemTreeIdx, Package,
+};
+use flux_id::{
+    id::{self, WithMod},
+    Map,
+};
+use flux_typesystem::TypeKind;
+use flux_util::{FileId, InFile, Interner, Span, Spanned, WithSpan, Word};
+
+/// Everything the compiler knows about a project, as of the last time it was built
+///
+/// Projects are built the same way the driver builds them, except that files which are open in the editor are read
+/// from the editor rather than from disk, and nothing past mir lowering is run. Each project keeps the database it was
+/// built with between analyses, so that editing a function only builds what the edit could have changed.
+pub(crate) struct Analysis {
+    interner: &'static Interner,
+    source_cache: SourceCache,
+    packages: Map<id::Pkg, Package>,
+    pub(crate) diagnostics: Vec<Diagnostic>,
+}
+
+/// A symbol declared in a file, in the shape of an lsp `DocumentSymbol`
+pub(crate) struct Symbol {
+    pub(crate) name: String,
+    pub(crate) kind: SymbolKind,
+    pub(crate) span: Span,
+    pub(crate) children: Vec<Symbol>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SymbolKind {
+    Module,
+    Function,
+    Method,
+    Struct,
+    Field,
+    Enum,
+    Variant,
+    Trait,
+    Apply,
+}
+
+/// An expression written in a file, and the function body it is in
+struct ExprAt<'a> {
+    package: &'a Package,
+    package_id: id::Pkg,
+    fn_id: id::FnDecl,
+    fn_body: &'a FnBody,
+    expr: id::Expr,
+    span: Span,
+}
+
+impl Analysis {
+    /// Analyze a project with the database it was last analyzed with, so that only what changed since then is built
+    /// again
+    pub(crate) fn new(
+        project_root: &Path,
+        documents: &HashMap<PathBuf, String>,
+        db: &mut Database,
+    ) -> Result<Self, IOError> {
+        let interner = db.context().interner;
+        let mut analysis = Self {
+            interner,
+            source_cache: SourceCache::new(interner),
+            packages: Map::new(),
+            diagnostics: vec![],
+        };
+
+        // Files are read through the database, which would otherwise keep what they were the first time
+        let prelude = FileId::prelude(interner);
+        for file_id in db.input_keys::<FileText>() {
+            if file_id == prelude {
+                continue;
+            }
+            let path = file_id.as_str(interner);
+            let content = match documents.get(Path::new(path)) {
+                Some(content) => Some(content.as_str().into()),
+                None => std::fs::read_to_string(path).ok().map(Into::into),
+            };
+            db.set::<FileText>(file_id, content);
+        }
+
+        let (config, config_diagnostics) =
+            flux::get_config(project_root, &mut analysis.source_cache)?;
+        analysis.diagnostics.extend(config_diagnostics);
+        let flux_home = registry::flux_home();
+        let (user_config, user_config_diagnostics) =
+            flux::get_user_config(&flux_home, &mut analysis.source_cache)?;
+        analysis.diagnostics.extend(user_config_diagnostics);
+        let registry = Registry::for_project(project_root, &config, &user_config, &flux_home);
+        // The lockfile is left for `flux build` to update, so versions are only picked here
+        let lockfile = Lockfile::read(project_root)?;
+        let resolution = resolver::resolve(
+            project_root,
+            &config,
+            &registry,
+            &Git::new(&flux_home),
+            lockfile.as_ref(),
+            &mut |path| {
+                flux::get_config(path, &mut analysis.source_cache)
+                    .ok()
+                    .map(|(config, _)| config)
+            },
+        )?;
+        for (name, package) in &resolution.packages {
+            registry.fetch(name, &package.version, &package.checksum)?;
+        }
+        let graph = PackageGraph::build(project_root, &config, &resolution, &mut |path| {
+            let (config, config_diagnostics) =
+                flux::get_config(path, &mut analysis.source_cache).ok()?;
+            // Only what stops the dependency from being built is worth bothering the user about
+            analysis
+                .diagnostics
+                .extend(config_diagnostics.into_iter().filter(Diagnostic::is_error));
+            Some(config)
+        })?;
+        let mut sources = vec![];
+        analysis.load_packages(&graph, documents, db, &mut sources)?;
+        let num_packages = sources.len() as u32;
+        db.set::<Project>((), sources.into());
+
+        for package_id in (0..num_packages).map(id::Pkg::from) {
+            let definitions = db.get::<DefinePackage>(package_id);
+            for file_id in &definitions.files {
+                let content = db.input::<FileText>(*file_id).unwrap_or_default();
+                analysis
+                    .source_cache
+                    .add_input_file(file_id.as_str(interner), content.to_string());
+            }
+            analysis
+                .diagnostics
+                .extend(definitions.diagnostics.iter().cloned());
+            analysis.packages.insert(definitions.package.clone());
+        }
+        for package_id in (0..num_packages).map(id::Pkg::from) {
+            let lowered = db.get::<PackageBodiesQuery>(package_id);
+            analysis
+                .diagnostics
+                .extend(lowered.diagnostics.iter().cloned());
+            analysis
+                .packages
+                .get_mut(package_id)
+                .set_bodies(lowered.bodies.clone());
+        }
+
+        // Mir lowering reports errors of its own, but it can't make sense of bodies that failed to type check
+        if !analysis.diagnostics.iter().any(Diagnostic::is_error) {
+            for package_id in (0..num_packages).map(id::Pkg::from) {
+                flux_mir::lower_package(
+                    package_id,
+                    &analysis.packages,
+                    interner,
+                    &mut analysis.diagnostics,
+                );
+            }
+        }
+
+        Ok(analysis)
+    }
+
+    /// Read the entry file of every package in the graph, preferring what is open in the editor to what is on disk
+    fn load_packages(
+        &mut self,
+        graph: &PackageGraph,
+        documents: &HashMap<PathBuf, String>,
+        db: &mut Database,
+        sources: &mut Vec<PackageSource>,
+    ) -> Result<(), IOError> {
+        for package in &graph.packages {
+            let (entry_path, content) =
+                flux::get_package_entry_file_path(&package.root, &package.name)?;
+            let content = documents
+                .get(Path::new(&entry_path))
+                .cloned()
+                .unwrap_or(content);
+            let entry = FileId::new(self.interner.get_or_intern(&entry_path));
+            db.set::<FileText>(entry, Some(content.into()));
+
+            sources.push(PackageSource {
+                name: self.interner.get_or_intern(&package.name),
+                entry,
+                dependencies: package
+                    .dependencies
+                    .iter()
+                    .map(|dependency| id::Pkg::from(*dependency as u32))
+                    .collect(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The id of a file that is part of one of the packages
+    pub(crate) fn file_id(&self, path: &Path) -> Option<FileId> {
+        let key = self.interner.get(path.to_str()?)?;
+        let file_id = FileId::new(key);
+        self.packages
+            .values()
+            .any(|package| {
+                package
+                    .module_tree()
+                    .iter()
+                    .any(|(_, module)| module.file_id == file_id)
+            })
+            .then_some(file_id)
+    }
+
+    pub(crate) fn file_path(&self, file_id: FileId) -> &str {
+        self.source_cache.get_file_path(&file_id)
+    }
+
+    pub(crate) fn file_content(&self, file_id: FileId) -> String {
+        self.source_cache.get_file_content(&file_id)
+    }
+
+    /// The type of the innermost expression at `offset`
+    pub(crate) fn hover(&self, file_id: FileId, offset: u32) -> Option<Spanned<String>> {
+        let ExprAt {
+            package,
+            expr,
+            span,
+            ..
+        } = self.expr_at(file_id, offset)?;
+        let bodies = package.bodies();
+        let (prefix, ty) = match &bodies.get_expr(expr).inner {
+            Expr::Let(let_expr) if contains(let_expr.name.span, offset) => (
+                format!("let {}: ", self.interner.resolve(&let_expr.name.inner)),
+                bodies.get_type(let_expr.val),
+            ),
+            _ => (String::new(), bodies.get_type(expr)),
+        };
+        match &ty.inner {
+            TypeKind::Unknown => None,
+            ty => Some(format!("{prefix}{}", ty.to_string(self.interner)).at(span)),
+        }
+    }
+
+    /// Where whatever is named at `offset` was declared
+    pub(crate) fn definition(&self, file_id: FileId, offset: u32) -> Option<InFile<Span>> {
+        let ExprAt {
+            package,
+            package_id,
+            fn_id,
+            fn_body,
+            expr,
+            ..
+        } = self.expr_at(file_id, offset)?;
+        let (path, variant) = match &package.bodies().get_expr(expr).inner {
+            Expr::Path(path) => {
+                if let Some(span) = self.local_definition(package, fn_id, fn_body, path, offset) {
+                    return Some(InFile::new(span, fn_body.file_id));
+                }
+                let item = self.resolve(package_id, path.in_mod(fn_body.mod_id))?;
+                return self.item_definition(item);
+            }
+            Expr::Struct(struct_expr) => (struct_expr.path.inner.clone().discard_args(), None),
+            Expr::Enum(enum_expr) => (
+                enum_expr.path.inner.clone().discard_args(),
+                Some(enum_expr.variant.inner),
+            ),
+            _ => return None,
+        };
+        let (pkg_id, item_id) = self.resolve(package_id, (&path).in_mod(fn_body.mod_id))?;
+        match (&item_id.inner, variant) {
+            (ItemTreeIdx::Enum(enum_id), Some(variant)) => {
+                let package = self.packages.get(pkg_id);
+                let variant = package
+                    .item_tree()
+                    .enums
+                    .get(*enum_id)
+                    .variants
+                    .iter()
+                    .find(|decl| decl.name.inner == variant)?;
+                Some(InFile::new(
+                    variant.name.span,
+                    package.module_tree()[item_id.mod_id].file_id,
+                ))
+            }
+            _ => self.item_definition((pkg_id, item_id)),
+        }
+    }
+
+    /// The items declared at the top level of a file
+    pub(crate) fn symbols(&self, file_id: FileId) -> Vec<Symbol> {
+        let mut symbols = vec![];
+        for package in self.packages.values() {
+            let item_tree = package.item_tree();
+            let fn_symbol = |fn_id: &id::FnDecl, kind| {
+                let fn_decl = item_tree.functions.get(*fn_id);
+                self.symbol(fn_decl.name.as_ref(), kind, vec![])
+            };
+            for item_id in item_tree
+                .top_level
+                .iter()
+                .filter(|item_id| package.module_tree()[item_id.mod_id].file_id == file_id)
+            {
+                let symbol = match &item_id.inner {
+                    ItemTreeIdx::Function(fn_id) => fn_symbol(fn_id, SymbolKind::Function),
+                    ItemTreeIdx::Module(mod_id) => self.symbol(
+                        item_tree.mods.get(*mod_id).name.as_ref(),
+                        SymbolKind::Module,
+                        vec![],
+                    ),
+                    ItemTreeIdx::Struct(struct_id) => {
+                        let struct_decl = item_tree.structs.get(*struct_id);
+                        let fields = struct_decl
+                            .fields
+                            .iter()
+                            .map(|field| {
+                                self.symbol(field.name.as_ref(), SymbolKind::Field, vec![])
+                            })
+                            .collect();
+                        self.symbol(struct_decl.name.as_ref(), SymbolKind::Struct, fields)
+                    }
+                    ItemTreeIdx::Enum(enum_id) => {
+                        let enum_decl = item_tree.enums.get(*enum_id);
+                        let variants = enum_decl
+                            .variants
+                            .iter()
+                            .map(|variant| {
+                                self.symbol(variant.name.as_ref(), SymbolKind::Variant, vec![])
+                            })
+                            .collect();
+                        self.symbol(enum_decl.name.as_ref(), SymbolKind::Enum, variants)
+                    }
+                    ItemTreeIdx::Trait(trait_id) => {
+                        let trait_decl = item_tree.traits.get(*trait_id);
+                        let methods = trait_decl
+                            .methods
+                            .iter()
+                            .map(|method| fn_symbol(method, SymbolKind::Method))
+                            .collect();
+                        self.symbol(trait_decl.name.as_ref(), SymbolKind::Trait, methods)
+                    }
+                    ItemTreeIdx::Apply(apply_id) => {
+                        let apply_decl = item_tree.applies.get(*apply_id);
+                        let to_ty = apply_decl.to_ty.inner.kind.to_string(self.interner);
+                        let name = match &apply_decl.trt {
+                            Some(trt) => {
+                                format!("apply {} to {to_ty}", trt.to_string(self.interner))
+                            }
+                            None => format!("apply to {to_ty}"),
+                        };
+                        Symbol {
+                            name,
+                            kind: SymbolKind::Apply,
+                            span: apply_decl.to_ty.span,
+                            children: apply_decl
+                                .methods
+                                .iter()
+                                .map(|method| fn_symbol(method, SymbolKind::Method))
+                                .collect(),
+                        }
+                    }
+                    // Builtin types and imports declare nothing new
+                    ItemTreeIdx::BuiltinType(..) | ItemTreeIdx::Use(_) => continue,
+                };
+                symbols.push(symbol);
+            }
+        }
+        symbols
+    }
+
+    fn symbol(&self, name: Spanned<&Word>, kind: SymbolKind, children: Vec<Symbol>) -> Symbol {
+        Symbol {
+            name: self.interner.resolve(name.inner).to_string(),
+            kind,
+            span: name.span,
+            children,
+        }
+    }
+
+    /// The innermost expression written at `offset`, along with the function body it is in
+    fn expr_at(&self, file_id: FileId, offset: u32) -> Option<ExprAt<'_>> {
+        self.packages
+            .iter()
+            .flat_map(|(package_id, package)| {
+                let bodies = package.bodies();
+                bodies
+                    .fn_bodies
+                    .iter()
+                    .filter(move |(_, fn_body)| fn_body.file_id == file_id)
+                    .flat_map(move |(fn_id, fn_body)| {
+                        fn_body.exprs().filter_map(move |expr| {
+                            let span = *bodies.expr_spans.get(&expr)?;
+                            contains(span, offset).then_some(ExprAt {
+                                package,
+                                package_id,
+                                fn_id: *fn_id,
+                                fn_body,
+                                expr,
+                                span,
+                            })
+                        })
+                    })
+            })
+            .min_by_key(|expr_at| expr_at.span.range.len())
+    }
+
+    /// The declaration of the local or parameter a single segment path refers to, if it refers to one
+    ///
+    /// Locals are visible from the end of their `let` until the end of the block it is in, and shadow anything
+    /// declared before them.
+    fn local_definition<A>(
+        &self,
+        package: &Package,
+        fn_id: id::FnDecl,
+        fn_body: &FnBody,
+        path: &flux_util::Path<Word, A>,
+        offset: u32,
+    ) -> Option<Span> {
+        let [name] = path.segments.as_slice() else {
+            return None;
+        };
+        let bodies = package.bodies();
+        let blocks: Vec<Span> = fn_body
+            .exprs()
+            .filter(|expr| matches!(bodies.get_expr(*expr).inner, Expr::Block(_)))
+            .filter_map(|expr| bodies.expr_spans.get(&expr).copied())
+            .collect();
+        let local = fn_body
+            .exprs()
+            .filter_map(|expr| match &bodies.get_expr(expr).inner {
+                Expr::Let(let_expr) if let_expr.name.inner == *name => {
+                    Some((*bodies.expr_spans.get(&expr)?, let_expr.name.span))
+                }
+                _ => None,
+            })
+            .filter(|(let_span, _)| u32::from(let_span.range.end()) <= offset)
+            .filter(|(let_span, _)| {
+                blocks
+                    .iter()
+                    .filter(|block| block.range.contains_range(let_span.range))
+                    .min_by_key(|block| block.range.len())
+                    .is_none_or(|block| contains(*block, offset))
+            })
+            .max_by_key(|(let_span, _)| let_span.range.start())
+            .map(|(_, name_span)| name_span);
+
+        local.or_else(|| {
+            package
+                .item_tree()
+                .functions
+                .get(fn_id)
+                .params
+                .iter()
+                .find(|param| param.name.inner == *name)
+                .map(|param| param.name.span)
+        })
+    }
+
+    fn resolve<A: Clone>(
+        &self,
+        package_id: id::Pkg,
+        path: id::InMod<&flux_util::Path<Word, A>>,
+    ) -> Option<(id::Pkg, ItemId)> {
+        ItemResolver::new(package_id, &self.packages, self.interner)
+            .resolve_path(path)
+            .ok()
+    }
+
+    fn item_definition(
+        &self,
+        (mut pkg_id, mut item_id): (id::Pkg, ItemId),
+    ) -> Option<InFile<Span>> {
+        // Imports are followed to what they import, but they could in principle import each other
+        for _ in 0..16 {
+            let package = self.packages.get(pkg_id);
+            let item_tree = package.item_tree();
+            let span = match &item_id.inner {
+                ItemTreeIdx::Function(fn_id) => item_tree.functions.get(*fn_id).name.span,
+                ItemTreeIdx::Module(mod_id) => item_tree.mods.get(*mod_id).name.span,
+                ItemTreeIdx::Struct(struct_id) => item_tree.structs.get(*struct_id).name.span,
+                ItemTreeIdx::Enum(enum_id) => item_tree.enums.get(*enum_id).name.span,
+                ItemTreeIdx::Trait(trait_id) => item_tree.traits.get(*trait_id).name.span,
+                ItemTreeIdx::Apply(apply_id) => item_tree.applies.get(*apply_id).to_ty.span,
+                ItemTreeIdx::BuiltinType(..) => return None,
+                ItemTreeIdx::Use(use_id) => {
+                    let path = &item_tree.uses.get(*use_id).path.inner;
+                    (pkg_id, item_id) = self.resolve(pkg_id, path.in_mod(item_id.mod_id))?;
+                    continue;
+                }
+            };
+            return Some(InFile::new(
+                span,
+                package.module_tree()[item_id.mod_id].file_id,
+            ));
+        }
+        None
+    }
+}
+
+fn contains(span: Span, offset: u32) -> bool {
+    u32::from(span.range.start()) <= offset && offset <= u32::from(span.range.end())
+}
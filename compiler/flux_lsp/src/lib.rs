@@ -0,0 +1,49 @@
+//! A language server for flux
+//!
+//! The server speaks the language server protocol over any pair of streams, and builds projects with the same
+//! passes the compiler does to publish their diagnostics and answer questions about them.
+
+use std::{
+    io::{BufRead, Write},
+    sync::OnceLock,
+};
+
+use flux_util::Interner;
+use lasso::ThreadedRodeo;
+use server::Server;
+use tracing::warn;
+
+mod analysis;
+mod line_index;
+mod rpc;
+mod server;
+mod uri;
+
+static INTERNER: OnceLock<Interner> = OnceLock::new();
+
+/// Serve a client until it tells the server to exit, returning the code the server should exit with
+///
+/// Exiting without having been asked to shut down first is a failure, as is the client going away without asking
+/// the server to exit at all.
+pub fn run(mut input: impl BufRead, output: impl Write) -> i32 {
+    let interner = INTERNER.get_or_init(|| ThreadedRodeo::from_iter(flux::PRE_INTERNED_VALUES));
+    let mut server = Server::new(output, interner);
+    loop {
+        let message = match rpc::read_message(&mut input) {
+            Ok(Some(message)) => message,
+            Ok(None) => return 1,
+            Err(err) => {
+                warn!("could not read message: {err}");
+                return 1;
+            }
+        };
+        match server.handle(message) {
+            Ok(Some(code)) => return code,
+            Ok(None) => {}
+            Err(err) => {
+                warn!("could not write message: {err}");
+                return 1;
+            }
+        }
+    }
+}
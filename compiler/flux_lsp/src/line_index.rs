@@ -0,0 +1,58 @@
+use flux_util::Span;
+
+use serde_json::{json, Value};
+
+/// Converts between the byte offsets the compiler uses and the positions clients use, which are a line and the
+/// number of UTF-16 code units into it
+pub(crate) struct LineIndex<'a> {
+    src: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub(crate) fn new(src: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { src, line_starts }
+    }
+
+    pub(crate) fn position(&self, offset: u32) -> Value {
+        let offset = (offset as usize).min(self.src.len());
+        let line = self.line_starts.partition_point(|start| *start <= offset) - 1;
+        let character: usize = self.src[self.line_starts[line]..offset]
+            .chars()
+            .map(char::len_utf16)
+            .sum();
+        json!({ "line": line as u32, "character": character as u32 })
+    }
+
+    pub(crate) fn range(&self, span: Span) -> Value {
+        json!({
+            "start": self.position(span.range.start().into()),
+            "end": self.position(span.range.end().into()),
+        })
+    }
+
+    /// The byte offset of a position, which is clamped to the end of its line if it is past it
+    pub(crate) fn offset(&self, position: &Value) -> Option<u32> {
+        let line = position.get("line")?.as_u64()? as usize;
+        let character = position.get("character")?.as_u64()? as usize;
+        let start = *self.line_starts.get(line)?;
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map_or(self.src.len(), |next| next - 1);
+
+        let mut units = 0;
+        let mut offset = start;
+        for c in self.src[start..end].chars() {
+            if units >= character {
+                break;
+            }
+            units += c.len_utf16();
+            offset += c.len_utf8();
+        }
+        Some(offset as u32)
+    }
+}
@@ -0,0 +1,5 @@
+fn main() {
+    // Stdout is the channel to the client, so nothing else can be written to it
+    let code = flux_lsp::run(std::io::stdin().lock(), std::io::stdout().lock());
+    std::process::exit(code);
+}
@@ -0,0 +1,75 @@
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+/// Error codes defined by JSON-RPC and the language server protocol
+pub(crate) mod error_code {
+    pub(crate) const PARSE_ERROR: i32 = -32700;
+    pub(crate) const INVALID_REQUEST: i32 = -32600;
+    pub(crate) const METHOD_NOT_FOUND: i32 = -32601;
+    pub(crate) const INVALID_PARAMS: i32 = -32602;
+    pub(crate) const SERVER_NOT_INITIALIZED: i32 = -32002;
+}
+
+/// Read the next message sent by the client, or `None` if it has closed the stream
+///
+/// Messages are a set of headers followed by a blank line and a JSON body, the length of which is given by the
+/// `Content-Length` header. A message without a valid one is an `InvalidData` error, since there is no telling where
+/// its body ends.
+pub(crate) fn read_message(input: &mut impl BufRead) -> io::Result<Option<Result<Value, String>>> {
+    let mut content_length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>().map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid `Content-Length` header `{}`: {err}", value.trim()),
+                    )
+                })?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let Some(content_length) = content_length else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message has no `Content-Length` header",
+        ));
+    };
+    let mut body = vec![0; content_length];
+    input.read_exact(&mut body)?;
+    Ok(Some(
+        String::from_utf8(body)
+            .map_err(|err| err.to_string())
+            .and_then(|body| serde_json::from_str(&body).map_err(|err| err.to_string())),
+    ))
+}
+
+pub(crate) fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+pub(crate) fn response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub(crate) fn error_response(id: Value, code: i32, msg: impl Into<String>) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": msg.into() } })
+}
+
+pub(crate) fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
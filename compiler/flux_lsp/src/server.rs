@@ -0,0 +1,653 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Write},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::{Path, PathBuf},
+};
+
+use flux::cfg::CFG_FILE_NAME;
+use flux_diagnostics::Diagnostic;
+use flux_hir::db::{Context, Database};
+use flux_util::{FileId, Interner, Span};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::{
+    analysis::{Analysis, Symbol, SymbolKind},
+    line_index::LineIndex,
+    rpc::{self, error_code},
+    uri,
+};
+
+/// The code and message of a request that could not be answered
+type RequestError = (i32, String);
+type RequestResult = Result<Value, RequestError>;
+
+pub(crate) struct Server<W: Write> {
+    output: W,
+    interner: &'static Interner,
+    /// The contents of every document the client has open, which take precedence over what is on disk
+    documents: HashMap<PathBuf, String>,
+    /// The last successful analysis of each project, by the directory its `flux.toml` is in
+    analyses: HashMap<PathBuf, Analysis>,
+    /// The database each project was last analyzed with
+    databases: HashMap<PathBuf, Database>,
+    /// The documents each project last published diagnostics for, which need to be cleared once they have none
+    published: HashMap<PathBuf, HashSet<String>>,
+    initialized: bool,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    pub(crate) fn new(output: W, interner: &'static Interner) -> Self {
+        Self {
+            output,
+            interner,
+            documents: HashMap::new(),
+            analyses: HashMap::new(),
+            databases: HashMap::new(),
+            published: HashMap::new(),
+            initialized: false,
+            shutdown: false,
+        }
+    }
+
+    /// Handle a message from the client, returning the exit code of the server once it has been told to exit
+    pub(crate) fn handle(&mut self, message: Result<Value, String>) -> io::Result<Option<i32>> {
+        let message = match message {
+            Ok(message) => message,
+            Err(err) => {
+                return self
+                    .send(rpc::error_response(
+                        Value::Null,
+                        error_code::PARSE_ERROR,
+                        err,
+                    ))
+                    .map(|_| None)
+            }
+        };
+        let id = message.get("id").cloned();
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let method = match message.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            // The server never sends requests, so there are no responses to expect
+            None if id.is_some() => return Ok(None),
+            None => {
+                return self
+                    .send(rpc::error_response(
+                        Value::Null,
+                        error_code::INVALID_REQUEST,
+                        "message is neither a request nor a notification",
+                    ))
+                    .map(|_| None)
+            }
+        };
+
+        match id {
+            Some(id) => {
+                let response = match self.handle_request(method, &params) {
+                    Ok(result) => rpc::response(id, result),
+                    Err((code, msg)) => rpc::error_response(id, code, msg),
+                };
+                self.send(response)?;
+            }
+            None if method == "exit" => return Ok(Some(if self.shutdown { 0 } else { 1 })),
+            // Notifications other than `exit` are dropped until the server has been initialized
+            None if self.initialized => self.handle_notification(method, &params)?,
+            None => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_request(&mut self, method: &str, params: &Value) -> RequestResult {
+        if method == "initialize" {
+            self.initialized = true;
+            return Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": {
+                        "openClose": true,
+                        // Documents are always sent in full
+                        "change": 1,
+                        "save": { "includeText": false },
+                    },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                },
+                "serverInfo": { "name": "flux_lsp", "version": env!("CARGO_PKG_VERSION") },
+            }));
+        }
+        if !self.initialized {
+            return Err((
+                error_code::SERVER_NOT_INITIALIZED,
+                format!("`{method}` was sent before `initialize`"),
+            ));
+        }
+
+        match method {
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            _ => Err((
+                error_code::METHOD_NOT_FOUND,
+                format!("unsupported method `{method}`"),
+            )),
+        }
+    }
+
+    fn handle_notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let Some(path) = document_path(params) else {
+            return Ok(());
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let Some(text) = params.pointer("/textDocument/text").and_then(Value::as_str)
+                else {
+                    return Ok(());
+                };
+                self.documents.insert(path.clone(), text.to_string());
+            }
+            "textDocument/didChange" => {
+                let Some(text) = params
+                    .get("contentChanges")
+                    .and_then(Value::as_array)
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change.get("text"))
+                    .and_then(Value::as_str)
+                else {
+                    return Ok(());
+                };
+                self.documents.insert(path.clone(), text.to_string());
+            }
+            // What is on disk may have changed for files other than the one that was saved
+            "textDocument/didSave" => {}
+            "textDocument/didClose" => {
+                self.documents.remove(&path);
+            }
+            _ => return Ok(()),
+        }
+        self.analyze(&path)
+    }
+
+    /// Build the project `path` belongs to again, and publish whatever it reports
+    fn analyze(&mut self, path: &Path) -> io::Result<()> {
+        let Some(root) = project_root(path) else {
+            return Ok(());
+        };
+        let interner = self.interner;
+        let db = self.databases.entry(root.clone()).or_insert_with(|| {
+            Database::new(Context {
+                interner,
+                config: flux_hir::Config::release(),
+            })
+        });
+        let analysis = catch_unwind(AssertUnwindSafe(|| {
+            Analysis::new(&root, &self.documents, db)
+        }));
+        let analysis = match analysis {
+            Ok(Ok(analysis)) => analysis,
+            Ok(Err(err)) => {
+                self.analyses.remove(&root);
+                return self.log(err.to_string());
+            }
+            Err(_) => {
+                self.analyses.remove(&root);
+                return self.log(format!(
+                    "the compiler panicked while analyzing `{}`",
+                    root.display()
+                ));
+            }
+        };
+
+        let mut diagnostics: HashMap<String, Vec<Value>> = HashMap::new();
+        for diagnostic in &analysis.diagnostics {
+            let Some(uri) = file_uri(&analysis, diagnostic.offset.file_id) else {
+                continue;
+            };
+            diagnostics
+                .entry(uri)
+                .or_default()
+                .push(diagnostic_to_json(&analysis, diagnostic));
+        }
+
+        let previously_published = self.published.remove(&root).unwrap_or_default();
+        for uri in previously_published
+            .iter()
+            .filter(|uri| !diagnostics.contains_key(*uri))
+        {
+            self.publish(uri, vec![])?;
+        }
+        let published = diagnostics.keys().cloned().collect();
+        for (uri, diagnostics) in diagnostics {
+            self.publish(&uri, diagnostics)?;
+        }
+        self.published.insert(root.clone(), published);
+        self.analyses.insert(root, analysis);
+        Ok(())
+    }
+
+    fn hover(&mut self, params: &Value) -> RequestResult {
+        let (analysis, file_id, offset) = match self.locate(params)? {
+            Some(location) => location,
+            None => return Ok(Value::Null),
+        };
+        let Some(hover) = analysis.hover(file_id, offset) else {
+            return Ok(Value::Null);
+        };
+        let content = analysis.file_content(file_id);
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": format!("```flux\n{}\n```", hover.inner) },
+            "range": LineIndex::new(&content).range(hover.span),
+        }))
+    }
+
+    fn definition(&mut self, params: &Value) -> RequestResult {
+        let (analysis, file_id, offset) = match self.locate(params)? {
+            Some(location) => location,
+            None => return Ok(Value::Null),
+        };
+        Ok(analysis
+            .definition(file_id, offset)
+            .and_then(|definition| location(analysis, definition.file_id, definition.inner))
+            .into())
+    }
+
+    fn document_symbols(&mut self, params: &Value) -> RequestResult {
+        let path = document_path(params).ok_or_else(invalid_params)?;
+        let Some((analysis, file_id)) = self.analysis_of(&path) else {
+            return Ok(Value::Null);
+        };
+        let content = analysis.file_content(file_id);
+        let line_index = LineIndex::new(&content);
+        Ok(analysis
+            .symbols(file_id)
+            .into_iter()
+            .map(|symbol| symbol_to_json(symbol, &line_index))
+            .collect::<Vec<_>>()
+            .into())
+    }
+
+    /// The analysis a request's document is part of, and the offset of the position it was made at
+    fn locate(&mut self, params: &Value) -> Result<Option<(&Analysis, FileId, u32)>, RequestError> {
+        let path = document_path(params).ok_or_else(invalid_params)?;
+        let position = params.get("position").ok_or_else(invalid_params)?;
+        let Some((analysis, file_id)) = self.analysis_of(&path) else {
+            return Ok(None);
+        };
+        // Positions are relative to the document as it was when it was last analyzed
+        let content = analysis.file_content(file_id);
+        let offset = LineIndex::new(&content)
+            .offset(position)
+            .ok_or_else(invalid_params)?;
+        Ok(Some((analysis, file_id, offset)))
+    }
+
+    fn analysis_of(&mut self, path: &Path) -> Option<(&Analysis, FileId)> {
+        let root = project_root(path)?;
+        if !self.analyses.contains_key(&root) {
+            if let Err(err) = self.analyze(path) {
+                warn!("could not publish diagnostics: {err}");
+            }
+        }
+        let analysis = self.analyses.get(&root)?;
+        Some((analysis, analysis.file_id(path)?))
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        self.send(rpc::notification(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        ))
+    }
+
+    fn log(&mut self, message: String) -> io::Result<()> {
+        self.send(rpc::notification(
+            "window/logMessage",
+            json!({ "type": 1, "message": message }),
+        ))
+    }
+
+    fn send(&mut self, message: Value) -> io::Result<()> {
+        rpc::write_message(&mut self.output, &message)
+    }
+}
+
+fn invalid_params() -> RequestError {
+    (
+        error_code::INVALID_PARAMS,
+        String::from("expected a document and position"),
+    )
+}
+
+fn document_path(params: &Value) -> Option<PathBuf> {
+    uri::to_path(params.pointer("/textDocument/uri")?.as_str()?)
+}
+
+/// The closest directory above `path` with a `flux.toml` in it
+fn project_root(path: &Path) -> Option<PathBuf> {
+    path.ancestors()
+        .skip(1)
+        .find(|dir| dir.join(CFG_FILE_NAME).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The uri of a file, if it is one the client can open
+///
+/// The prelude is compiled into every package without ever being on disk.
+fn file_uri(analysis: &Analysis, file_id: FileId) -> Option<String> {
+    let path = Path::new(analysis.file_path(file_id));
+    path.is_absolute().then(|| uri::from_path(path))
+}
+
+fn location(analysis: &Analysis, file_id: FileId, span: Span) -> Option<Value> {
+    let content = analysis.file_content(file_id);
+    Some(json!({
+        "uri": file_uri(analysis, file_id)?,
+        "range": LineIndex::new(&content).range(span),
+    }))
+}
+
+fn diagnostic_to_json(analysis: &Analysis, diagnostic: &Diagnostic) -> Value {
+    let content = analysis.file_content(diagnostic.offset.file_id);
+    let line_index = LineIndex::new(&content);
+
+    let mut message = diagnostic.message().to_string();
+    let primary = diagnostic
+        .labels
+        .first()
+        .filter(|label| label.file_id == diagnostic.offset.file_id);
+    if let Some(label) = primary.filter(|label| label.inner.inner != message) {
+        message.push('\n');
+        message.push_str(&label.inner.inner);
+    }
+    if let Some(help) = diagnostic.help() {
+        message.push_str("\nhelp: ");
+        message.push_str(help);
+    }
+
+    let related_information: Vec<Value> = diagnostic
+        .labels
+        .iter()
+        .skip(1)
+        .filter_map(|label| {
+            Some(json!({
+                "location": location(analysis, label.file_id, label.span)?,
+                "message": label.inner.inner.as_str(),
+            }))
+        })
+        .collect();
+
+    json!({
+        "range": line_index.range(primary.map_or(diagnostic.offset.span, |label| label.span)),
+        "severity": if diagnostic.is_error() { 1 } else { 2 },
+        "code": diagnostic.code.to_string(),
+        "source": "flux",
+        "message": message,
+        "relatedInformation": related_information,
+    })
+}
+
+fn symbol_to_json(symbol: Symbol, line_index: &LineIndex) -> Value {
+    let range = line_index.range(symbol.span);
+    let kind: u32 = match symbol.kind {
+        SymbolKind::Module => 2,
+        SymbolKind::Method => 6,
+        SymbolKind::Field => 8,
+        SymbolKind::Enum => 10,
+        SymbolKind::Trait => 11,
+        SymbolKind::Function => 12,
+        SymbolKind::Apply => 19,
+        SymbolKind::Variant => 22,
+        SymbolKind::Struct => 23,
+    };
+    json!({
+        "name": symbol.name,
+        "kind": kind,
+        "range": range,
+        "selectionRange": range,
+        "children": symbol
+            .children
+            .into_iter()
+            .map(|child| symbol_to_json(child, line_index))
+            .collect::<Vec<_>>(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::Cursor, path::PathBuf};
+
+    use serde_json::{json, Value};
+
+    use crate::{rpc, uri};
+
+    const SRC: &str = r#"struct Point {
+	x s32,
+	y s32,
+}
+
+fn double(n s32) -> s32 {
+	n * 2
+}
+
+fn main() -> s32 {
+	let p = Point { x: 1, y: 2 };
+	let total = double(p.x);
+	total
+}
+"#;
+
+    /// Write a project depending on the standard library to a temporary directory
+    fn new_project(name: &str, src: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("flux-lsp-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("flux.toml"),
+            format!(
+                "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n\n[dependencies]\nstd = {{ path = \"{}\" }}\n",
+                concat!(env!("CARGO_MANIFEST_DIR"), "/../../std")
+            ),
+        )
+        .unwrap();
+        fs::write(root.join("src/main.flx"), src).unwrap();
+        fs::canonicalize(root).unwrap()
+    }
+
+    /// Run the server over a scripted session, returning the exit code and everything it sent back
+    fn session(messages: &[Value]) -> (i32, Vec<Value>) {
+        let mut input = vec![];
+        for message in messages {
+            rpc::write_message(&mut input, message).unwrap();
+        }
+        let mut output = vec![];
+        let code = crate::run(Cursor::new(input), &mut output);
+
+        let mut output = Cursor::new(output);
+        let mut responses = vec![];
+        while let Some(message) = rpc::read_message(&mut output).unwrap() {
+            responses.push(message.unwrap());
+        }
+        (code, responses)
+    }
+
+    fn request(id: u32, method: &str, params: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params })
+    }
+
+    fn initialize() -> [Value; 2] {
+        [
+            request(0, "initialize", json!({})),
+            rpc::notification("initialized", json!({})),
+        ]
+    }
+
+    fn did_open(uri: &str, text: &str) -> Value {
+        rpc::notification(
+            "textDocument/didOpen",
+            json!({
+                "textDocument": { "uri": uri, "languageId": "flux", "version": 1, "text": text },
+            }),
+        )
+    }
+
+    fn did_change(uri: &str, text: &str) -> Value {
+        rpc::notification(
+            "textDocument/didChange",
+            json!({
+                "textDocument": { "uri": uri, "version": 2 },
+                "contentChanges": [{ "text": text }],
+            }),
+        )
+    }
+
+    fn at(uri: &str, line: u32, character: u32) -> Value {
+        json!({
+            "textDocument": { "uri": uri },
+            "position": { "line": line, "character": character },
+        })
+    }
+
+    fn response(responses: &[Value], id: u32) -> &Value {
+        responses
+            .iter()
+            .find(|message| message.get("id") == Some(&id.into()))
+            .unwrap()
+    }
+
+    fn range_start(value: &Value) -> (u64, u64) {
+        let start = value.pointer("/range/start").unwrap();
+        (
+            start.get("line").unwrap().as_u64().unwrap(),
+            start.get("character").unwrap().as_u64().unwrap(),
+        )
+    }
+
+    #[test]
+    fn answers_requests_about_open_documents() {
+        let root = new_project("requests", SRC);
+        let uri = uri::from_path(&root.join("src/main.flx"));
+        let mut messages = initialize().to_vec();
+        messages.extend([
+            did_open(&uri, SRC),
+            // `total` on its last line
+            request(1, "textDocument/hover", at(&uri, 12, 2)),
+            // The call to `double`
+            request(2, "textDocument/definition", at(&uri, 11, 15)),
+            // The use of `p` in the call
+            request(3, "textDocument/definition", at(&uri, 11, 21)),
+            request(
+                4,
+                "textDocument/documentSymbol",
+                json!({ "textDocument": { "uri": uri.as_str() } }),
+            ),
+            request(5, "shutdown", Value::Null),
+            rpc::notification("exit", Value::Null),
+        ]);
+        let (code, responses) = session(&messages);
+        assert_eq!(code, 0);
+
+        let hover = response(&responses, 1).get("result").unwrap();
+        assert_eq!(
+            hover.pointer("/contents/value").unwrap().as_str(),
+            Some("```flux\ns32\n```")
+        );
+
+        let definition = response(&responses, 2).get("result").unwrap();
+        assert_eq!(definition.get("uri").unwrap().as_str(), Some(uri.as_str()));
+        assert_eq!(range_start(definition), (5, 3));
+
+        let definition = response(&responses, 3).get("result").unwrap();
+        assert_eq!(range_start(definition), (10, 5));
+
+        let symbols = response(&responses, 4).get("result").unwrap();
+        let names: Vec<_> = symbols
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| symbol.get("name").unwrap().as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["Point", "double", "main"]);
+        let fields = symbols.as_array().unwrap()[0].get("children").unwrap();
+        assert_eq!(fields.as_array().unwrap().len(), 2);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn publishes_diagnostics_of_unsaved_changes() {
+        let root = new_project("diagnostics", SRC);
+        let uri = uri::from_path(&root.join("src/main.flx"));
+        let mut messages = initialize().to_vec();
+        messages.extend([
+            did_open(&uri, SRC),
+            did_change(&uri, &SRC.replace("double(p.x)", "double(q)")),
+            did_change(&uri, SRC),
+        ]);
+        let (code, responses) = session(&messages);
+        // The client never asked the server to shut down
+        assert_eq!(code, 1);
+
+        let published: Vec<_> = responses
+            .iter()
+            .filter(|message| {
+                message.get("method").and_then(Value::as_str)
+                    == Some("textDocument/publishDiagnostics")
+            })
+            .map(|message| message.get("params").unwrap())
+            .filter(|params| params.get("uri").unwrap().as_str() == Some(uri.as_str()))
+            .collect();
+        assert_eq!(published.len(), 2);
+
+        let diagnostics = published[0].get("diagnostics").unwrap().as_array().unwrap();
+        assert!(!diagnostics.is_empty());
+        assert_eq!(diagnostics[0].get("severity").unwrap().as_u64(), Some(1));
+        assert_eq!(range_start(&diagnostics[0]), (11, 20));
+        // Fixing the error clears it
+        assert_eq!(
+            published[1]
+                .get("diagnostics")
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            0
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn rejects_unknown_and_early_requests() {
+        let (_, responses) = session(&[
+            request(1, "textDocument/hover", json!({})),
+            request(2, "initialize", json!({})),
+            request(3, "workspace/symbol", json!({})),
+        ]);
+        assert_eq!(
+            response(&responses, 1).pointer("/error/code"),
+            Some(&(-32002).into())
+        );
+        assert!(response(&responses, 2).get("result").is_some());
+        assert_eq!(
+            response(&responses, 3).pointer("/error/code"),
+            Some(&(-32601).into())
+        );
+    }
+
+    #[test]
+    fn messages_need_a_valid_content_length() {
+        for input in [
+            "Content-Length: two\r\n\r\n{}",
+            "Content-Type: application/json\r\n\r\n{}",
+        ] {
+            let err = rpc::read_message(&mut Cursor::new(input)).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{input}");
+            assert_eq!(crate::run(Cursor::new(input), vec![]), 1);
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+/// The path of a `file://` uri, which is the only kind of document the server knows how to read
+pub(crate) fn to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' => {
+                let hex = path.get(i + 1..i + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                i += 3;
+            }
+            byte => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let path = PathBuf::from(String::from_utf8(decoded).ok()?);
+    // Files are known to the compiler by their canonical path, but documents that only exist in the editor have none
+    Some(std::fs::canonicalize(&path).unwrap_or(path))
+}
+
+pub(crate) fn from_path(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    uri
+}
@@ -0,0 +1,25 @@
+use flux_proc_macros::diagnostic;
+
+#[diagnostic]
+pub enum MirError {
+    #[error(
+        location = lhs,
+        primary = "invalid assignment",
+        label at lhs = "cannot assign to this expression",
+        help = "only locals, struct fields and pointer reads can be assigned to",
+    )]
+    InvalidAssignment {
+        #[filespanned]
+        lhs: (),
+    },
+    #[error(
+        location = expr,
+        primary = "unsupported expression",
+        label at expr = "{kind} cannot be compiled yet",
+    )]
+    UnsupportedExpr {
+        #[filespanned]
+        expr: (),
+        kind: &'static str,
+    },
+}
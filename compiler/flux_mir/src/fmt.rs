@@ -0,0 +1,212 @@
+use std::fmt::Write;
+
+use flux_hir::Package;
+use flux_id::{
+    id::{self, InPkg},
+    Map,
+};
+use flux_util::{Interner, Word};
+
+use crate::mir::{Body, Callee, Const, Operand, Place, Projection, Rvalue, Statement, Terminator};
+
+pub(crate) struct MirFormatter<'a> {
+    packages: &'a Map<id::Pkg, Package>,
+    interner: &'static Interner,
+}
+
+impl<'a> MirFormatter<'a> {
+    pub(crate) fn new(packages: &'a Map<id::Pkg, Package>, interner: &'static Interner) -> Self {
+        Self { packages, interner }
+    }
+
+    pub(crate) fn fmt_body(&self, name: Word, body: &Body) -> String {
+        let mut s = String::new();
+        let args = body
+            .args()
+            .map(|arg| {
+                format!(
+                    "{}: {}",
+                    self.fmt_local(arg),
+                    body.locals.get(arg).ty.to_string(self.interner)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let _ = writeln!(
+            s,
+            "fn {}({args}) -> {} {{",
+            self.interner.resolve(&name),
+            body.locals
+                .get(Body::RETURN_PLACE)
+                .ty
+                .to_string(self.interner)
+        );
+
+        for (local, decl) in body.locals.iter().skip(body.arg_count + 1) {
+            let _ = write!(
+                s,
+                "    let {}: {};",
+                self.fmt_local(local),
+                decl.ty.to_string(self.interner)
+            );
+            match decl.name {
+                Some(name) => {
+                    let _ = writeln!(s, " // {}", self.interner.resolve(&name));
+                }
+                None => s.push('\n'),
+            }
+        }
+
+        let has_locals = body.locals.len() > body.arg_count + 1;
+        for (block, data) in body.blocks.iter() {
+            if has_locals || block != Body::START_BLOCK {
+                s.push('\n');
+            }
+            let _ = writeln!(s, "    bb{}: {{", block.raw());
+            for statement in &data.statements {
+                let _ = writeln!(s, "        {};", self.fmt_statement(statement));
+            }
+            let _ = writeln!(s, "        {};", self.fmt_terminator(data.terminator()));
+            s.push_str("    }\n");
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    fn fn_name(&self, fn_id: InPkg<id::FnDecl>) -> &'static str {
+        self.interner.resolve(
+            &self
+                .packages
+                .get(fn_id.pkg_id)
+                .item_tree()
+                .functions
+                .get(fn_id.inner)
+                .name,
+        )
+    }
+
+    fn fmt_local(&self, local: impl Into<u32>) -> String {
+        format!("_{}", local.into())
+    }
+
+    fn fmt_place(&self, place: &Place) -> String {
+        place.projection.iter().fold(
+            self.fmt_local(place.local),
+            |s, projection| match projection {
+                Projection::Field(field) => format!("{s}.{}", self.interner.resolve(field)),
+                Projection::Index(idx) => format!("(*{s})[{}]", self.fmt_local(*idx)),
+                Projection::Downcast(variant, _) => {
+                    format!("({s} as {})", self.interner.resolve(variant))
+                }
+            },
+        )
+    }
+
+    fn fmt_operand(&self, operand: &Operand) -> String {
+        match operand {
+            Operand::Copy(place) => format!("copy {}", self.fmt_place(place)),
+            Operand::Const(Const::Int(val, ty)) => {
+                format!("const {val}_{}", ty.to_string(self.interner))
+            }
+            Operand::Const(Const::Bool(val)) => format!("const {val}"),
+            Operand::Const(Const::Fn(fn_id)) => format!("const {}", self.fn_name(*fn_id)),
+        }
+    }
+
+    fn fmt_operands<'b>(&self, operands: impl Iterator<Item = &'b Operand>) -> String {
+        operands
+            .map(|operand| self.fmt_operand(operand))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn fmt_rvalue(&self, rvalue: &Rvalue) -> String {
+        match rvalue {
+            Rvalue::Use(operand) => self.fmt_operand(operand),
+            Rvalue::BinOp(op, lhs, rhs) => {
+                format!("{} {op} {}", self.fmt_operand(lhs), self.fmt_operand(rhs))
+            }
+            Rvalue::Cast(operand, ty) => format!(
+                "{} as {}",
+                self.fmt_operand(operand),
+                ty.to_string(self.interner)
+            ),
+            Rvalue::Struct(name, fields) => format!(
+                "{} {{ {} }}",
+                self.interner.resolve(name),
+                fields
+                    .iter()
+                    .map(|(name, operand)| format!(
+                        "{}: {}",
+                        self.interner.resolve(name),
+                        self.fmt_operand(operand)
+                    ))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Rvalue::Enum(variant, _, payload) => match payload {
+                Some(payload) => format!(
+                    "{}({})",
+                    self.interner.resolve(variant),
+                    self.fmt_operand(payload)
+                ),
+                None => self.interner.resolve(variant).to_string(),
+            },
+            Rvalue::Discriminant(place) => format!("discriminant({})", self.fmt_place(place)),
+            Rvalue::Tuple(operands) => format!("({})", self.fmt_operands(operands.iter())),
+        }
+    }
+
+    fn fmt_statement(&self, statement: &Statement) -> String {
+        match statement {
+            Statement::Assign(place, rvalue) => {
+                format!("{} = {}", self.fmt_place(place), self.fmt_rvalue(rvalue))
+            }
+        }
+    }
+
+    fn fmt_terminator(&self, terminator: &Terminator) -> String {
+        match terminator {
+            Terminator::Goto(target) => format!("goto -> bb{}", target.raw()),
+            Terminator::SwitchInt {
+                discr,
+                targets,
+                otherwise,
+            } => format!(
+                "switchInt({}) -> [{}otherwise: bb{}]",
+                self.fmt_operand(discr),
+                targets
+                    .iter()
+                    .map(|(val, target)| format!("{val}: bb{}, ", target.raw()))
+                    .collect::<String>(),
+                otherwise.raw()
+            ),
+            Terminator::Return => "return".to_string(),
+            Terminator::Call {
+                callee,
+                args,
+                dest,
+                target,
+            } => {
+                let callee = match callee {
+                    Callee::Fn(Operand::Const(Const::Fn(fn_id))) => {
+                        self.fn_name(*fn_id).to_string()
+                    }
+                    Callee::Fn(operand) => format!("({})", self.fmt_operand(operand)),
+                    Callee::Method(name) => format!("<method {}>", self.interner.resolve(name)),
+                    Callee::Intrinsic(name) => self.interner.resolve(name).to_string(),
+                };
+                let target = match target {
+                    Some(target) => format!("[return: bb{}]", target.raw()),
+                    None => "!".to_string(),
+                };
+                format!(
+                    "{} = {callee}({}) -> {target}",
+                    self.fmt_place(dest),
+                    self.fmt_operands(args.iter())
+                )
+            }
+            Terminator::Unreachable => "unreachable".to_string(),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+
+use flux_diagnostics::Diagnostic;
+use flux_hir::Package;
+use flux_id::{id, Map};
+use flux_util::Interner;
+use fmt::MirFormatter;
+use lower::LoweringCtx;
+use serde::{Deserialize, Serialize};
+
+mod diagnostics;
+mod fmt;
+mod lower;
+pub mod mir;
+
+pub use mir::Body;
+
+/// The mir of every function in a package
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PackageMir {
+    pub bodies: HashMap<id::FnDecl, Body>,
+}
+
+impl PackageMir {
+    pub fn get_body(&self, fn_id: id::FnDecl) -> Option<&Body> {
+        self.bodies.get(&fn_id)
+    }
+
+    pub fn to_string(
+        &self,
+        package_id: id::Pkg,
+        packages: &Map<id::Pkg, Package>,
+        interner: &'static Interner,
+    ) -> String {
+        let formatter = MirFormatter::new(packages, interner);
+        let functions = &packages.get(package_id).item_tree().functions;
+        let mut fn_ids: Vec<_> = self.bodies.keys().copied().collect();
+        fn_ids.sort();
+        fn_ids
+            .into_iter()
+            .map(|fn_id| formatter.fmt_body(functions.get(fn_id).name.inner, &self.bodies[&fn_id]))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Lower the type checked bodies of a package into mir
+///
+/// The package's bodies must have been built without errors, since poisoned expressions cannot be lowered.
+pub fn lower_package(
+    package_id: id::Pkg,
+    packages: &Map<id::Pkg, Package>,
+    interner: &'static Interner,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> PackageMir {
+    let bodies = packages
+        .get(package_id)
+        .bodies()
+        .fn_bodies
+        .keys()
+        .map(|fn_id| {
+            let body =
+                LoweringCtx::new(package_id, *fn_id, packages, interner, diagnostics).finish();
+            (*fn_id, body)
+        })
+        .collect();
+    PackageMir { bodies }
+}
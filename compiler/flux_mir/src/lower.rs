@@ -0,0 +1,585 @@
+use std::collections::HashMap;
+
+use flux_diagnostics::{ice, Diagnostic, ToDiagnostic};
+use flux_hir::{
+    def::{
+        expr::{BinOp, Call, Expr, If, Loop, Match, Op},
+        pat::Pat,
+    },
+    FnBody, ItemResolver, ItemTreeIdx, Package, PackageBodies,
+};
+use flux_id::{
+    id::{self, WithMod, WithPackage},
+    Map,
+};
+use flux_typesystem::{ConcreteKind, TypeKind};
+use flux_util::{Interner, Path, WithSpan, Word};
+
+use crate::{
+    diagnostics::MirError,
+    mir::{
+        BasicBlock, BasicBlockData, Body, Callee, Const, Local, LocalDecl, Operand, Place,
+        Projection, Rvalue, Statement, Terminator,
+    },
+};
+
+const PTR_READ: &str = "@flux.intrinsics.ptr_read";
+
+pub(crate) struct LoweringCtx<'a> {
+    fn_body: FnBody,
+    bodies: &'a PackageBodies,
+    item_resolver: ItemResolver<'a>,
+    interner: &'static Interner,
+    diagnostics: &'a mut Vec<Diagnostic>,
+    body: Body,
+    current_block: BasicBlock,
+    scopes: Vec<HashMap<Word, Local>>,
+    /// The loops surrounding the expression being lowered, from outermost to innermost
+    loops: Vec<LoopBlocks>,
+}
+
+/// Where the `break` and `continue` expressions targeting a loop jump to
+struct LoopBlocks {
+    expr: id::Expr,
+    head: BasicBlock,
+    exit: BasicBlock,
+    /// Where the value the loop is broken out of with is stored
+    dest: Place,
+}
+
+impl<'a> LoweringCtx<'a> {
+    pub(crate) fn new(
+        pkg_id: id::Pkg,
+        fn_id: id::FnDecl,
+        packages: &'a Map<id::Pkg, Package>,
+        interner: &'static Interner,
+        diagnostics: &'a mut Vec<Diagnostic>,
+    ) -> Self {
+        let package = packages.get(pkg_id);
+        let fn_decl = package.item_tree().functions.get(fn_id);
+        let fn_body = *package
+            .bodies()
+            .get_fn_body(fn_id)
+            .unwrap_or_else(|| ice("cannot lower function without a body to mir"));
+
+        let mut body = Body::new(fn_decl.return_ty.kind.clone());
+        let params = fn_decl
+            .params
+            .iter()
+            .map(|param| {
+                let local = body.locals.insert(LocalDecl::new(
+                    Some(param.name.inner),
+                    param.ty.kind.clone(),
+                ));
+                (param.name.inner, local)
+            })
+            .collect();
+        body.arg_count = fn_decl.params.iter().count();
+
+        Self {
+            fn_body,
+            bodies: package.bodies(),
+            item_resolver: ItemResolver::new(pkg_id, packages, interner),
+            interner,
+            diagnostics,
+            body,
+            current_block: Body::START_BLOCK,
+            scopes: vec![params],
+            loops: vec![],
+        }
+    }
+
+    pub(crate) fn finish(mut self) -> Body {
+        self.lower_into(self.fn_body.expr, Body::RETURN_PLACE.into());
+        self.terminate(Terminator::Return);
+        self.body
+    }
+
+    fn ty(&self, expr: id::Expr) -> TypeKind {
+        self.bodies.get_type(expr).inner.clone()
+    }
+
+    fn new_temp(&mut self, ty: TypeKind) -> Local {
+        self.body.locals.insert(LocalDecl::new(None, ty))
+    }
+
+    fn new_block(&mut self) -> BasicBlock {
+        self.body.blocks.insert(BasicBlockData::new())
+    }
+
+    fn push_assign(&mut self, place: Place, rvalue: Rvalue) {
+        self.body
+            .blocks
+            .get_mut(self.current_block)
+            .statements
+            .push(Statement::Assign(place, rvalue));
+    }
+
+    fn terminate(&mut self, terminator: Terminator) {
+        self.body
+            .blocks
+            .get_mut(self.current_block)
+            .set_terminator(terminator);
+    }
+
+    fn get_local(&self, name: &Word) -> Option<Local> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .copied()
+    }
+
+    fn lower_into(&mut self, expr: id::Expr, dest: Place) {
+        match &self.bodies.get_expr(expr).inner {
+            Expr::Address(_) => self.unsupported(expr, dest, "taking the address of a value"),
+            Expr::Float(_) => self.unsupported(expr, dest, "float literals"),
+            Expr::Str(_) => self.unsupported(expr, dest, "string literals"),
+            Expr::Assignment(assignment) => {
+                let val = self.lower_operand(assignment.val);
+                match self.lower_place(assignment.lhs) {
+                    Some(place) => self.push_assign(place, Rvalue::Use(val)),
+                    None => self.diagnostics.push(
+                        MirError::InvalidAssignment {
+                            lhs: (),
+                            lhs_file_span: self
+                                .bodies
+                                .get_type(assignment.lhs)
+                                .span
+                                .in_file(self.fn_body.file_id),
+                        }
+                        .to_diagnostic(),
+                    ),
+                }
+                self.push_assign(dest, Rvalue::unit());
+            }
+            Expr::Block(block) => {
+                self.scopes.push(HashMap::new());
+                for stmt in block.stmts() {
+                    self.lower_stmt(*stmt);
+                }
+                self.lower_into(block.tail(), dest);
+                self.scopes.pop();
+            }
+            Expr::BinOp(bin_op) => self.lower_bin_op(bin_op, dest),
+            Expr::Cast(cast) => {
+                let val = self.lower_operand(cast.val);
+                self.push_assign(dest, Rvalue::Cast(val, self.ty(expr)));
+            }
+            Expr::Call(call) => self.lower_call(expr, call, dest),
+            Expr::Int(_) | Expr::Bool(_) | Expr::Path(_) => {
+                let operand = self.lower_operand(expr);
+                self.push_assign(dest, Rvalue::Use(operand));
+            }
+            Expr::Tuple(exprs) => {
+                let operands = exprs.iter().map(|expr| self.lower_operand(*expr)).collect();
+                self.push_assign(dest, Rvalue::Tuple(operands));
+            }
+            Expr::Let(_) => {
+                self.lower_stmt(expr);
+                self.push_assign(dest, Rvalue::unit());
+            }
+            Expr::Struct(strukt) => {
+                let name = *strukt
+                    .path
+                    .last()
+                    .unwrap_or_else(|| ice("struct expression with empty path"));
+                let fields = strukt
+                    .fields
+                    .iter()
+                    .map(|field| (field.name.inner, self.lower_operand(field.val)))
+                    .collect();
+                self.push_assign(dest, Rvalue::Struct(name, fields));
+            }
+            Expr::MemberAccess(_) => {
+                let place = self.lower_place_or_temp(expr);
+                self.push_assign(dest, Rvalue::Use(Operand::Copy(place)));
+            }
+            Expr::Enum(enum_expr) => {
+                let payload = enum_expr.payload.map(|payload| self.lower_operand(payload));
+                self.push_assign(
+                    dest,
+                    Rvalue::Enum(enum_expr.variant.inner, enum_expr.discriminant, payload),
+                );
+            }
+            Expr::If(if_expr) => self.lower_if(if_expr, dest),
+            Expr::Match(match_expr) => self.lower_match(match_expr, dest),
+            Expr::Loop(loop_expr) => self.lower_loop(expr, loop_expr, dest),
+            Expr::Break(break_expr) => {
+                let (exit, loop_dest) = {
+                    let blocks = self.loop_blocks(break_expr.target);
+                    (blocks.exit, blocks.dest.clone())
+                };
+                match break_expr.val {
+                    Some(val) => self.lower_into(val, loop_dest),
+                    None => self.push_assign(loop_dest, Rvalue::unit()),
+                }
+                self.terminate(Terminator::Goto(exit));
+                // Anything after a `break` is unreachable, but still needs a block to be lowered into
+                self.current_block = self.new_block();
+            }
+            Expr::Continue(continue_expr) => {
+                let head = self.loop_blocks(continue_expr.target).head;
+                self.terminate(Terminator::Goto(head));
+                self.current_block = self.new_block();
+            }
+            Expr::Intrinsic(intrinsic) => {
+                let args = intrinsic
+                    .args
+                    .iter()
+                    .map(|arg| self.lower_operand(**arg))
+                    .collect();
+                self.lower_call_terminator(expr, Callee::Intrinsic(intrinsic.name), args, dest);
+            }
+            Expr::Poisoned => ice("poisoned expression cannot be lowered to mir"),
+        }
+    }
+
+    /// Report an expression that mir has no way to represent yet, standing in unit for its value
+    fn unsupported(&mut self, expr: id::Expr, dest: Place, kind: &'static str) {
+        self.diagnostics.push(
+            MirError::UnsupportedExpr {
+                expr: (),
+                expr_file_span: self
+                    .bodies
+                    .get_type(expr)
+                    .span
+                    .in_file(self.fn_body.file_id),
+                kind,
+            }
+            .to_diagnostic(),
+        );
+        self.push_assign(dest, Rvalue::unit());
+    }
+
+    fn lower_stmt(&mut self, stmt: id::Expr) {
+        match &self.bodies.get_expr(stmt).inner {
+            Expr::Let(l) => {
+                let local = self
+                    .body
+                    .locals
+                    .insert(LocalDecl::new(Some(l.name.inner), self.ty(l.val)));
+                self.lower_into(l.val, local.into());
+                // The name is only brought into scope after the value, so that `let x = x;` refers to the outer `x`
+                self.scopes
+                    .last_mut()
+                    .unwrap_or_else(|| ice("mir lowering has no scope"))
+                    .insert(l.name.inner, local);
+            }
+            _ => {
+                let temp = self.new_temp(self.ty(stmt));
+                self.lower_into(stmt, temp.into());
+            }
+        }
+    }
+
+    fn lower_operand(&mut self, expr: id::Expr) -> Operand {
+        match &self.bodies.get_expr(expr).inner {
+            Expr::Int(val) => Operand::Const(Const::Int(*val, self.ty(expr))),
+            Expr::Bool(val) => Operand::Const(Const::Bool(*val)),
+            Expr::Path(path) => match self.lower_local_path(path) {
+                Some(local) => Operand::Copy(local.into()),
+                None => Operand::Const(Const::Fn(self.resolve_fn_path(path))),
+            },
+            Expr::MemberAccess(_) => Operand::Copy(self.lower_place_or_temp(expr)),
+            _ => {
+                let temp = self.new_temp(self.ty(expr));
+                self.lower_into(expr, temp.into());
+                Operand::Copy(temp.into())
+            }
+        }
+    }
+
+    fn lower_local_path(&self, path: &Path<Word, id::Ty>) -> Option<Local> {
+        if path.len() == 1 {
+            self.get_local(path.get_nth(0))
+        } else {
+            None
+        }
+    }
+
+    /// Lower an expression that refers to a location in memory, returning `None` if it does not refer to one
+    fn lower_place(&mut self, expr: id::Expr) -> Option<Place> {
+        match &self.bodies.get_expr(expr).inner {
+            Expr::Path(path) => self.lower_local_path(path).map(Place::from),
+            Expr::MemberAccess(member_access) => Some(
+                self.lower_place_or_temp(*member_access.lhs)
+                    .project(Projection::Field(member_access.field.inner)),
+            ),
+            Expr::Intrinsic(intrinsic) if self.interner.resolve(&intrinsic.name) == PTR_READ => {
+                let ptr = self.lower_into_local(*intrinsic.args[0]);
+                let idx = self.lower_into_local(*intrinsic.args[1]);
+                Some(Place::new(ptr, vec![Projection::Index(idx)]))
+            }
+            // Indexing a pointer goes through `Index::idx`, which only reads from the pointer
+            Expr::Call(call) => match &self.bodies.get_expr(**call.callee()).inner {
+                Expr::MemberAccess(member_access)
+                    if self.interner.resolve(&member_access.field) == "idx"
+                        && matches!(
+                            self.ty(*member_access.lhs),
+                            TypeKind::Concrete(ConcreteKind::Ptr(_))
+                        ) =>
+                {
+                    let ptr = self.lower_into_local(*call.args()[0]);
+                    let idx = self.lower_into_local(*call.args()[1]);
+                    Some(Place::new(ptr, vec![Projection::Index(idx)]))
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn lower_place_or_temp(&mut self, expr: id::Expr) -> Place {
+        self.lower_place(expr)
+            .unwrap_or_else(|| self.lower_into_local(expr).into())
+    }
+
+    fn lower_into_local(&mut self, expr: id::Expr) -> Local {
+        match self.lower_operand(expr) {
+            Operand::Copy(Place { local, projection }) if projection.is_empty() => local,
+            operand => {
+                let temp = self.new_temp(self.ty(expr));
+                self.push_assign(temp.into(), Rvalue::Use(operand));
+                temp
+            }
+        }
+    }
+
+    fn lower_bin_op(&mut self, bin_op: &BinOp, dest: Place) {
+        match *bin_op.op {
+            Op::CmpAnd | Op::CmpOr => {
+                let lhs = self.lower_operand(bin_op.lhs);
+                let rhs_block = self.new_block();
+                let short_circuit_block = self.new_block();
+                let join_block = self.new_block();
+
+                // `&&` only needs the right hand side if the left is true, and `||` only if it is false
+                let (on_false, on_true) = match *bin_op.op {
+                    Op::CmpAnd => (short_circuit_block, rhs_block),
+                    _ => (rhs_block, short_circuit_block),
+                };
+                self.terminate(Terminator::SwitchInt {
+                    discr: lhs,
+                    targets: vec![(0, on_false)],
+                    otherwise: on_true,
+                });
+
+                self.current_block = short_circuit_block;
+                self.push_assign(
+                    dest.clone(),
+                    Rvalue::Use(Operand::Const(Const::Bool(*bin_op.op == Op::CmpOr))),
+                );
+                self.terminate(Terminator::Goto(join_block));
+
+                self.current_block = rhs_block;
+                self.lower_into(bin_op.rhs, dest);
+                self.terminate(Terminator::Goto(join_block));
+
+                self.current_block = join_block;
+            }
+            op => {
+                let lhs = self.lower_operand(bin_op.lhs);
+                let rhs = self.lower_operand(bin_op.rhs);
+                self.push_assign(dest, Rvalue::BinOp(op, lhs, rhs));
+            }
+        }
+    }
+
+    fn lower_if(&mut self, if_expr: &If, dest: Place) {
+        let join_block = self.new_block();
+        let else_ifs = if_expr.else_ifs().unwrap_or(&[]);
+        let branches = std::iter::once((if_expr.condition(), if_expr.then())).chain(
+            else_ifs
+                .chunks_exact(2)
+                .map(|else_if| (&else_if[0], &else_if[1])),
+        );
+
+        for (condition, block) in branches {
+            let condition = self.lower_operand(*condition);
+            let then_block = self.new_block();
+            let else_block = self.new_block();
+            self.terminate(Terminator::SwitchInt {
+                discr: condition,
+                targets: vec![(0, else_block)],
+                otherwise: then_block,
+            });
+
+            self.current_block = then_block;
+            self.lower_into(*block, dest.clone());
+            self.terminate(Terminator::Goto(join_block));
+
+            self.current_block = else_block;
+        }
+
+        match if_expr.else_block() {
+            Some(block) => self.lower_into(*block, dest),
+            None => self.push_assign(dest, Rvalue::unit()),
+        }
+        self.terminate(Terminator::Goto(join_block));
+        self.current_block = join_block;
+    }
+
+    /// The body of a loop is lowered into its own block, which jumps back to itself once it is done
+    fn lower_loop(&mut self, expr: id::Expr, loop_expr: &Loop, dest: Place) {
+        let head = self.new_block();
+        let exit = self.new_block();
+        self.terminate(Terminator::Goto(head));
+
+        self.current_block = head;
+        self.loops.push(LoopBlocks {
+            expr,
+            head,
+            exit,
+            dest,
+        });
+        let body = self.new_temp(self.ty(loop_expr.body));
+        self.lower_into(loop_expr.body, body.into());
+        self.loops.pop();
+        self.terminate(Terminator::Goto(head));
+
+        self.current_block = exit;
+    }
+
+    fn loop_blocks(&self, target: id::Expr) -> &LoopBlocks {
+        self.loops
+            .iter()
+            .rev()
+            .find(|blocks| blocks.expr == target)
+            .unwrap_or_else(|| ice("`break` or `continue` outside of the loop it targets"))
+    }
+
+    /// Arms are tried in order, each one testing its pattern and falling through to the next arm if it does not match
+    fn lower_match(&mut self, match_expr: &Match, dest: Place) {
+        let scrutinee = self.lower_place_or_temp(*match_expr.scrutinee);
+        let join_block = self.new_block();
+        for arm in &match_expr.arms {
+            let next_arm_block = self.new_block();
+            self.scopes.push(HashMap::new());
+            self.lower_pat(*arm.pat, scrutinee.clone(), next_arm_block);
+            self.lower_into(arm.body, dest.clone());
+            self.scopes.pop();
+            self.terminate(Terminator::Goto(join_block));
+            self.current_block = next_arm_block;
+        }
+        self.terminate(Terminator::Unreachable);
+        self.current_block = join_block;
+    }
+
+    /// Test whether the value in `place` matches `pat`, continuing in the current block if it does and jumping to
+    /// `otherwise` if it does not
+    ///
+    /// The locals bound by the pattern are inserted into the innermost scope.
+    fn lower_pat(&mut self, pat: id::Pat, place: Place, otherwise: BasicBlock) {
+        match &self.bodies.get_pat(pat).inner {
+            Pat::Wildcard => {}
+            Pat::Bind(name) => {
+                let ty = self.bodies.get_pat_type(pat).inner.clone();
+                let local = self
+                    .body
+                    .locals
+                    .insert(LocalDecl::new(Some(name.inner), ty));
+                self.push_assign(local.into(), Rvalue::Use(Operand::Copy(place)));
+                self.scopes
+                    .last_mut()
+                    .unwrap_or_else(|| ice("mir lowering has no scope"))
+                    .insert(name.inner, local);
+            }
+            Pat::Int(val) => self.switch_to_matched(Operand::Copy(place), *val, otherwise),
+            Pat::Bool(val) => self.switch_to_matched(Operand::Copy(place), *val as u64, otherwise),
+            Pat::Variant(variant) => {
+                let u32_ty = TypeKind::Concrete(ConcreteKind::Path(Path::new(
+                    vec![self.interner.get_or_intern_static("u32")],
+                    vec![],
+                )));
+                let discriminant = self.new_temp(u32_ty);
+                self.push_assign(discriminant.into(), Rvalue::Discriminant(place.clone()));
+                self.switch_to_matched(
+                    Operand::Copy(discriminant.into()),
+                    variant.discriminant as u64,
+                    otherwise,
+                );
+                if let Some(payload) = variant.payload {
+                    let payload_place = place.project(Projection::Downcast(
+                        variant.variant.inner,
+                        variant.discriminant,
+                    ));
+                    self.lower_pat(payload, payload_place, otherwise);
+                }
+            }
+            Pat::Struct(struct_pat) => {
+                for field in &struct_pat.fields {
+                    let field_place = place.clone().project(Projection::Field(field.name.inner));
+                    self.lower_pat(field.pat, field_place, otherwise);
+                }
+            }
+            Pat::Tuple(pats) => {
+                for (idx, pat) in pats.iter().enumerate() {
+                    let name = self.interner.get_or_intern(idx.to_string());
+                    let element_place = place.clone().project(Projection::Field(name));
+                    self.lower_pat(*pat, element_place, otherwise);
+                }
+            }
+            Pat::Poisoned => ice("poisoned pattern cannot be lowered to mir"),
+        }
+    }
+
+    /// Continue in a new block if `discr` is `val`, otherwise jump to `otherwise`
+    fn switch_to_matched(&mut self, discr: Operand, val: u64, otherwise: BasicBlock) {
+        let matched_block = self.new_block();
+        self.terminate(Terminator::SwitchInt {
+            discr,
+            targets: vec![(val, matched_block)],
+            otherwise,
+        });
+        self.current_block = matched_block;
+    }
+
+    fn lower_call(&mut self, expr: id::Expr, call: &Call, dest: Place) {
+        let args = call
+            .args()
+            .iter()
+            .map(|arg| self.lower_operand(**arg))
+            .collect();
+        let callee = match &self.bodies.get_expr(**call.callee()).inner {
+            // The receiver of a method call has already been inserted as the first argument
+            Expr::MemberAccess(member_access) => Callee::Method(member_access.field.inner),
+            _ => Callee::Fn(self.lower_operand(**call.callee())),
+        };
+        self.lower_call_terminator(expr, callee, args, dest);
+    }
+
+    fn lower_call_terminator(
+        &mut self,
+        expr: id::Expr,
+        callee: Callee,
+        args: Vec<Operand>,
+        dest: Place,
+    ) {
+        let target = match self.ty(expr) {
+            TypeKind::Never => None,
+            _ => Some(self.new_block()),
+        };
+        self.terminate(Terminator::Call {
+            callee,
+            args,
+            dest,
+            target,
+        });
+        // Anything after a diverging call is unreachable, but still needs a block to be lowered into
+        self.current_block = target.unwrap_or_else(|| self.new_block());
+    }
+
+    fn resolve_fn_path(&self, path: &Path<Word, id::Ty>) -> id::InPkg<id::FnDecl> {
+        match self
+            .item_resolver
+            .resolve_path(path.in_mod(self.fn_body.mod_id))
+        {
+            Ok((pkg_id, item_id)) => match item_id.inner {
+                ItemTreeIdx::Function(fn_id) => fn_id.in_pkg(pkg_id),
+                _ => ice("path expression did not resolve to a function"),
+            },
+            Err(_) => ice("unresolved path expression reached mir lowering"),
+        }
+    }
+}
@@ -0,0 +1,210 @@
+use flux_diagnostics::ice;
+use flux_hir::def::expr::Op;
+use flux_id::{
+    id::{self, InPkg},
+    ids, Map,
+};
+use flux_typesystem::TypeKind;
+use flux_util::{words::AsText, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
+
+ids!(Local, BasicBlock);
+
+/// The control flow graph of a single function
+///
+/// Local `_0` is the return place, and is followed by one local for each parameter of the function.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Body {
+    pub locals: Map<Local, LocalDecl>,
+    pub blocks: Map<BasicBlock, BasicBlockData>,
+    pub arg_count: usize,
+}
+
+impl Body {
+    pub const RETURN_PLACE: Local = Local::new(0);
+    pub const START_BLOCK: BasicBlock = BasicBlock::new(0);
+
+    pub(crate) fn new(return_ty: TypeKind) -> Self {
+        let mut locals = Map::new();
+        locals.insert(LocalDecl::new(None, return_ty));
+        let mut blocks = Map::new();
+        blocks.insert(BasicBlockData::new());
+        Self {
+            locals,
+            blocks,
+            arg_count: 0,
+        }
+    }
+
+    pub fn args(&self) -> impl Iterator<Item = Local> {
+        (1..=self.arg_count as u32).map(Local::new)
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalDecl {
+    /// The name of the variable this local was declared for, or `None` for temporaries
+    #[serde_as(as = "Option<AsText>")]
+    pub name: Option<Word>,
+    pub ty: TypeKind,
+}
+
+impl LocalDecl {
+    pub fn new(name: Option<Word>, ty: TypeKind) -> Self {
+        Self { name, ty }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BasicBlockData {
+    pub statements: Vec<Statement>,
+    terminator: Option<Terminator>,
+}
+
+impl BasicBlockData {
+    pub(crate) fn new() -> Self {
+        Self {
+            statements: vec![],
+            terminator: None,
+        }
+    }
+
+    pub fn terminator(&self) -> &Terminator {
+        self.terminator
+            .as_ref()
+            .unwrap_or_else(|| ice("basic block missing terminator"))
+    }
+
+    pub(crate) fn set_terminator(&mut self, terminator: Terminator) {
+        self.terminator = Some(terminator);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Statement {
+    Assign(Place, Rvalue),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Terminator {
+    Goto(BasicBlock),
+    /// Jump to the target whose value matches `discr`, or `otherwise` if none do
+    ///
+    /// Booleans are switched on as `0` for `false` and `1` for `true`
+    SwitchInt {
+        discr: Operand,
+        targets: Vec<(u64, BasicBlock)>,
+        otherwise: BasicBlock,
+    },
+    Return,
+    /// Call `callee`, write the result to `dest` and continue at `target`
+    ///
+    /// `target` is `None` if the callee never returns
+    Call {
+        callee: Callee,
+        args: Vec<Operand>,
+        dest: Place,
+        target: Option<BasicBlock>,
+    },
+    Unreachable,
+}
+
+impl Terminator {
+    pub fn successors(&self) -> Vec<BasicBlock> {
+        match self {
+            Terminator::Goto(target) => vec![*target],
+            Terminator::SwitchInt {
+                targets, otherwise, ..
+            } => targets
+                .iter()
+                .map(|(_, target)| *target)
+                .chain(std::iter::once(*otherwise))
+                .collect(),
+            Terminator::Call { target, .. } => target.iter().copied().collect(),
+            Terminator::Return | Terminator::Unreachable => vec![],
+        }
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Callee {
+    Fn(Operand),
+    /// A method whose receiver is the first argument
+    ///
+    /// Which application the method comes from depends on the type of the receiver.
+    Method(#[serde_as(as = "AsText")] Word),
+    Intrinsic(#[serde_as(as = "AsText")] Word),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Place {
+    pub local: Local,
+    pub projection: Vec<Projection>,
+}
+
+impl Place {
+    pub fn new(local: Local, projection: Vec<Projection>) -> Self {
+        Self { local, projection }
+    }
+
+    pub fn project(mut self, projection: Projection) -> Self {
+        self.projection.push(projection);
+        self
+    }
+}
+
+impl From<Local> for Place {
+    fn from(local: Local) -> Self {
+        Self::new(local, vec![])
+    }
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Projection {
+    /// A field of a struct, or an element of a tuple, which is named by its index
+    Field(#[serde_as(as = "AsText")] Word),
+    /// The element a pointer points to, offset by the value of a local
+    Index(Local),
+    /// The payload of an enum, as the variant with the given name and discriminant
+    Downcast(#[serde_as(as = "AsText")] Word, u32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Operand {
+    Copy(Place),
+    Const(Const),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Const {
+    Int(u64, TypeKind),
+    Bool(bool),
+    Fn(InPkg<id::FnDecl>),
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Rvalue {
+    Use(Operand),
+    BinOp(Op, Operand, Operand),
+    Cast(Operand, TypeKind),
+    Struct(
+        #[serde_as(as = "AsText")] Word,
+        #[serde_as(as = "Vec<(AsText, _)>")] Vec<(Word, Operand)>,
+    ),
+    /// The enum variant with the given name and discriminant, holding a payload if the variant has one
+    Enum(#[serde_as(as = "AsText")] Word, u32, Option<Operand>),
+    /// The discriminant of the enum in a place, as a `u32`
+    Discriminant(Place),
+    Tuple(Vec<Operand>),
+}
+
+impl Rvalue {
+    pub const fn unit() -> Self {
+        Self::Tuple(vec![])
+    }
+}
@@ -6,6 +6,7 @@ mod expr;
 mod generic_args;
 mod generic_params;
 pub(crate) mod item;
+mod pat;
 mod r#type;
 
 fn name(p: &mut Parser, recovery_set: TokenSet, parent: &str) -> Option<TokenKind> {
@@ -7,6 +7,15 @@ use super::{name, r#type::type_};
 
 pub(crate) mod atom;
 
+const BLOCK_LIKE_BEGIN: TokenSet = TokenSet::new(&[
+    TokenKind::If,
+    TokenKind::Match,
+    TokenKind::While,
+    TokenKind::Loop,
+    TokenKind::For,
+    TokenKind::Label,
+]);
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) struct ExprRestrictions {
     pub allow_struct_expressions: bool,
@@ -169,6 +178,7 @@ fn idx_expr(p: &mut Parser, callee: CompletedMarker) -> CompletedMarker {
     let m = callee.precede(p);
     p.bump(TokenKind::LSquare);
     expr(p);
+    p.expect(TokenKind::RSquare, "index expression");
     m.complete(p, SyntaxKind::IdxExpr)
 }
 
@@ -203,8 +213,11 @@ fn stmt(p: &mut Parser) {
         let_stmt(p);
     } else {
         let m = p.start();
+        // Expressions ending in a block, such as loops and `if`s, are often run for their side effects, so they don't
+        // need a semicolon to be followed by another statement
+        let is_block_like = p.at_set(BLOCK_LIKE_BEGIN);
         expr(p);
-        if p.eat(TokenKind::SemiColon) {
+        if p.eat(TokenKind::SemiColon) || (is_block_like && !p.at(TokenKind::RBrace)) {
             m.complete(p, SyntaxKind::ExprStmt);
         } else {
             m.complete(p, SyntaxKind::TerminatorExprStmt);
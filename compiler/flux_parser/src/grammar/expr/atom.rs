@@ -1,5 +1,5 @@
 use crate::{
-    grammar::{expr::arg_list, generic_args::opt_generic_arg_list, name},
+    grammar::{expr::arg_list, generic_args::opt_generic_arg_list, name, pat::pat},
     lexer::TokenKind,
     marker::CompletedMarker,
     parser::Parser,
@@ -9,15 +9,29 @@ use crate::{
 
 use super::{expr, expr_no_structs, stmt, ExprRestrictions};
 
+/// Tokens that end a `break` without a value
+const BREAK_VALUE_FOLLOW_SET: TokenSet = TokenSet::new(&[
+    TokenKind::SemiColon,
+    TokenKind::Comma,
+    TokenKind::RBrace,
+    TokenKind::RParen,
+    TokenKind::EOF,
+]);
+
 pub(super) fn atom(p: &mut Parser, restrictions: ExprRestrictions) -> Option<CompletedMarker> {
     let m = match p.peek() {
         TokenKind::IntLit => int_expr(p),
         TokenKind::FloatLit => float_expr(p),
+        TokenKind::True | TokenKind::False => bool_expr(p),
         TokenKind::StringLit => string_expr(p),
         TokenKind::LParen => paren_or_tuple_expr(p),
         TokenKind::LBrace if restrictions.allow_block_expressions => block_expr(p),
         TokenKind::Ident => path_or_complex_type_expr(p, restrictions),
         TokenKind::If => if_expr(p),
+        TokenKind::Match => match_expr(p),
+        TokenKind::While | TokenKind::Loop | TokenKind::For | TokenKind::Label => loop_expr(p),
+        TokenKind::Break => break_expr(p),
+        TokenKind::Continue => continue_expr(p),
         TokenKind::Intrinsic => intrinsic_expr(p),
         _ => {
             p.err_and_bump("expected expression atom");
@@ -39,6 +53,12 @@ fn float_expr(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::FloatExpr)
 }
 
+fn bool_expr(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump_any();
+    m.complete(p, SyntaxKind::BoolExpr)
+}
+
 fn string_expr(p: &mut Parser) -> CompletedMarker {
     let m = p.start();
     p.bump(TokenKind::StringLit);
@@ -164,6 +184,92 @@ fn if_expr(p: &mut Parser) -> CompletedMarker {
     m.complete(p, SyntaxKind::IfExpr)
 }
 
+fn match_expr(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump(TokenKind::Match);
+    expr_no_structs(p);
+    match_arm_list(p);
+    m.complete(p, SyntaxKind::MatchExpr)
+}
+
+fn match_arm_list(p: &mut Parser) {
+    let m = p.start();
+    p.expect(TokenKind::LBrace, "match expression");
+    while p.loop_safe_not_at(TokenKind::RBrace) {
+        let block_body = match_arm(p);
+        let comma = p.eat(TokenKind::Comma);
+        if p.at(TokenKind::RBrace) {
+            break;
+        }
+        // Like in Rust, arms whose body is a block don't need a comma after them
+        if !comma && !block_body {
+            p.error("expected `,` separating match arms");
+        }
+    }
+    p.expect(TokenKind::RBrace, "match arm list");
+    m.complete(p, SyntaxKind::MatchArmList);
+}
+
+/// Returns whether the body of the arm is a block expression
+fn match_arm(p: &mut Parser) -> bool {
+    let m = p.start();
+    pat(p);
+    p.expect(TokenKind::FatArrow, "match arm");
+    let block_body = p.at(TokenKind::LBrace);
+    expr(p);
+    m.complete(p, SyntaxKind::MatchArm);
+    block_body
+}
+
+/// Parse a `while`, `loop` or `for` expression, along with the label before it if there is one
+fn loop_expr(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    if p.at(TokenKind::Label) {
+        let label = p.start();
+        p.bump(TokenKind::Label);
+        p.expect(TokenKind::Colon, "loop label");
+        label.complete(p, SyntaxKind::LoopLabel);
+    }
+    match p.peek() {
+        TokenKind::While => {
+            p.bump(TokenKind::While);
+            expr_no_structs(p);
+            block_expr(p);
+            m.complete(p, SyntaxKind::WhileExpr)
+        }
+        TokenKind::For => {
+            p.bump(TokenKind::For);
+            pat(p);
+            p.expect(TokenKind::In, "for expression");
+            expr_no_structs(p);
+            block_expr(p);
+            m.complete(p, SyntaxKind::ForExpr)
+        }
+        _ => {
+            p.expect(TokenKind::Loop, "labeled loop");
+            block_expr(p);
+            m.complete(p, SyntaxKind::LoopExpr)
+        }
+    }
+}
+
+fn break_expr(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump(TokenKind::Break);
+    p.eat(TokenKind::Label);
+    if !p.at_set(BREAK_VALUE_FOLLOW_SET) {
+        expr(p);
+    }
+    m.complete(p, SyntaxKind::BreakExpr)
+}
+
+fn continue_expr(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    p.bump(TokenKind::Continue);
+    p.eat(TokenKind::Label);
+    m.complete(p, SyntaxKind::ContinueExpr)
+}
+
 fn intrinsic_expr(p: &mut Parser) -> CompletedMarker {
     let m = p.start();
     p.bump(TokenKind::Intrinsic);
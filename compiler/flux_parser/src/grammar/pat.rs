@@ -0,0 +1,128 @@
+use crate::{
+    lexer::TokenKind, marker::CompletedMarker, parser::Parser, syntax::SyntaxKind,
+    token_set::TokenSet,
+};
+
+use super::name;
+
+const PAT_RECOVERY_SET: TokenSet = TokenSet::new(&[
+    TokenKind::FatArrow,
+    TokenKind::Comma,
+    TokenKind::RParen,
+    TokenKind::RBrace,
+]);
+
+pub(super) fn pat(p: &mut Parser) -> Option<CompletedMarker> {
+    let m = match p.peek() {
+        TokenKind::Underscore => {
+            let m = p.start();
+            p.bump(TokenKind::Underscore);
+            m.complete(p, SyntaxKind::WildcardPat)
+        }
+        TokenKind::IntLit | TokenKind::True | TokenKind::False => {
+            let m = p.start();
+            p.bump_any();
+            m.complete(p, SyntaxKind::LiteralPat)
+        }
+        TokenKind::LParen => paren_or_tuple_pat(p),
+        TokenKind::Ident => path_or_bind_pat(p),
+        _ => {
+            p.err_recover("expected pattern", PAT_RECOVERY_SET);
+            return None;
+        }
+    };
+    Some(m)
+}
+
+fn paren_or_tuple_pat(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    let comma = pat_list(p, "tuple pattern");
+    let kind = match comma {
+        true => SyntaxKind::TuplePat,
+        false => SyntaxKind::ParenPat,
+    };
+    m.complete(p, kind)
+}
+
+/// Parse a parenthesized, comma separated list of patterns, returning whether there was a comma in it
+fn pat_list(p: &mut Parser, parent: &str) -> bool {
+    p.bump(TokenKind::LParen);
+    let mut comma = false;
+    while p.loop_safe_not_at(TokenKind::RParen) {
+        if pat(p).is_none() {
+            break;
+        }
+        if !p.at(TokenKind::RParen) {
+            comma = true;
+            if !p.expect(TokenKind::Comma, parent) {
+                break;
+            }
+        }
+    }
+    p.expect(TokenKind::RParen, parent);
+    comma
+}
+
+/// A lone identifier binds whatever it is matched against, anything longer is the path to an enum variant or struct
+///
+/// Unit variants always have to be written with the enum they belong to, like `Option::None`, so that they can't be
+/// confused with a binding.
+fn path_or_bind_pat(p: &mut Parser) -> CompletedMarker {
+    let m = p.start();
+    let path_marker = p.start();
+    p.bump(TokenKind::Ident);
+    if !p.at(TokenKind::DoubleColon) && !p.at(TokenKind::LParen) && !p.at(TokenKind::LBrace) {
+        path_marker.complete(p, SyntaxKind::Name);
+        return m.complete(p, SyntaxKind::BindPat);
+    }
+
+    while p.at(TokenKind::DoubleColon) {
+        p.bump(TokenKind::DoubleColon);
+        if !p.expect(TokenKind::Ident, "path") {
+            break;
+        }
+    }
+    path_marker.complete(p, SyntaxKind::Path);
+
+    if p.at(TokenKind::LBrace) {
+        struct_pat_field_list(p);
+        m.complete(p, SyntaxKind::StructPat)
+    } else {
+        if p.at(TokenKind::LParen) {
+            pat_list(p, "variant pattern");
+        }
+        m.complete(p, SyntaxKind::VariantPat)
+    }
+}
+
+fn struct_pat_field_list(p: &mut Parser) {
+    let m = p.start();
+    p.bump(TokenKind::LBrace);
+    while p.loop_safe_not_at(TokenKind::RBrace) {
+        struct_pat_field(p);
+        let comma = p.eat(TokenKind::Comma);
+        if p.at(TokenKind::RBrace) {
+            break;
+        }
+        if !comma {
+            p.error("expected `,` separating struct pattern fields");
+            break;
+        }
+    }
+    p.expect(TokenKind::RBrace, "struct pattern field list");
+    m.complete(p, SyntaxKind::StructPatFieldList);
+}
+
+/// Fields without a pattern, like `x` in `Point { x, y: 0 }`, bind the field to a local of the same name
+fn struct_pat_field(p: &mut Parser) {
+    let m = p.start();
+    name(
+        p,
+        TokenSet::new(&[TokenKind::Colon, TokenKind::Comma, TokenKind::RBrace]),
+        "struct pattern field",
+    );
+    if p.eat(TokenKind::Colon) {
+        pat(p);
+    }
+    m.complete(p, SyntaxKind::StructPatField);
+}
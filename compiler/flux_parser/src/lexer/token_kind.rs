@@ -19,6 +19,8 @@ pub enum TokenKind {
     Comment,
     #[regex("[A-Za-z][A-Za-z0-9_]*")]
     Ident,
+    #[regex("'[A-Za-z][A-Za-z0-9_]*")]
+    Label,
     #[regex("0x[0-9a-fA-F]+(_[0-9a-fA-F]+)*")]
     #[regex("0b[0-9]+(_[0-9]+)*")]
     #[regex("[0-9]+(_[0-9]+)*")]
@@ -72,6 +74,20 @@ pub enum TokenKind {
     Enum,
     #[token("as")]
     As,
+    #[token("match")]
+    Match,
+    #[token("true")]
+    True,
+    #[token("false")]
+    False,
+    #[token("while")]
+    While,
+    #[token("loop")]
+    Loop,
+    #[token("break")]
+    Break,
+    #[token("continue")]
+    Continue,
 
     #[token(",")]
     Comma,
@@ -127,6 +143,8 @@ pub enum TokenKind {
     Ampersand,
     #[token(".")]
     Period,
+    #[token("_")]
+    Underscore,
 
     EOF,
 }
@@ -145,14 +163,17 @@ impl Display for TokenKind {
             Self::CmpLte => write!(f, "<="),
             Self::CmpNeq => write!(f, "!="),
             Self::Colon => write!(f, ":"),
+            Self::Break => write!(f, "break"),
             Self::Comma => write!(f, ","),
             Self::Comment => write!(f, "comment"),
+            Self::Continue => write!(f, "continue"),
             Self::DoubleColon => write!(f, "::"),
             Self::Else => write!(f, "else"),
             Self::Enum => write!(f, "enum"),
             Self::Eq => write!(f, "="),
             Self::FatArrow => write!(f, "=>"),
             Self::FloatLit => write!(f, "float"),
+            Self::False => write!(f, "false"),
             Self::Fn => write!(f, "fn"),
             Self::For => write!(f, "for"),
             Self::In => write!(f, "in"),
@@ -161,10 +182,13 @@ impl Display for TokenKind {
             Self::IntLit => write!(f, "int"),
             Self::Intrinsic => write!(f, "intrinsic"),
             Self::Is => write!(f, "is"),
+            Self::Label => write!(f, "label"),
             Self::LBrace => write!(f, "{{"),
             Self::LParen => write!(f, "("),
             Self::LSquare => write!(f, "["),
             Self::Let => write!(f, "let"),
+            Self::Loop => write!(f, "loop"),
+            Self::Match => write!(f, "match"),
             Self::Minus => write!(f, "-"),
             Self::Mod => write!(f, "mod"),
             Self::Mut => write!(f, "mut"),
@@ -182,9 +206,12 @@ impl Display for TokenKind {
             Self::Struct => write!(f, "struct"),
             Self::To => write!(f, "to"),
             Self::Trait => write!(f, "trait"),
+            Self::True => write!(f, "true"),
             Self::Type => write!(f, "type"),
+            Self::Underscore => write!(f, "_"),
             Self::Use => write!(f, "use"),
             Self::Where => write!(f, "where"),
+            Self::While => write!(f, "while"),
             Self::Whitespace => write!(f, "whitespace"),
             Self::This => write!(f, "This"),
             Self::CmpAnd => write!(f, "&&"),
@@ -1,7 +1,7 @@
 use cstree::green::GreenNode;
 use flux_diagnostics::Diagnostic;
 use flux_util::{FileId, Interner};
-use lexer::Lexer;
+use lexer::{Lexer, TokenKind};
 use parser::Parser;
 use sink::Sink;
 use source::Source;
@@ -29,6 +29,15 @@ pub fn parse(src: &str, file: FileId, interner: &'static Interner) -> Parse {
     sink.finish(file)
 }
 
+/// Whether `text` is a single identifier, which is what the names of items and packages have to be
+pub fn is_ident(text: &str) -> bool {
+    let mut tokens = Lexer::new(text);
+    matches!(
+        (tokens.next(), tokens.next()),
+        (Some(token), None) if token.kind == TokenKind::Ident && token.text == text
+    )
+}
+
 #[derive(Debug)]
 pub struct Parse {
     pub green_node: GreenNode,
@@ -202,7 +202,7 @@ macro_rules! enum_node {
                 }
 
 				fn range(&self) -> TextRange {
-					self.syntax().text_range()
+					trim_trailing_whitesapce(self.syntax())
 				}
 
                 fn is_poisoned(&self) -> bool {
@@ -34,6 +34,7 @@ basic_node!(PathExpr);
 basic_node!(ParenExpr);
 basic_node!(FloatExpr);
 basic_node!(IntExpr);
+basic_node!(BoolExpr);
 basic_node!(BinExpr);
 basic_node!(CallExpr);
 basic_node!(StructExpr);
@@ -50,6 +51,15 @@ basic_node!(ElseBlock);
 basic_node!(IntrinsicExpr);
 basic_node!(StringExpr);
 basic_node!(CastExpr);
+basic_node!(MatchExpr);
+basic_node!(MatchArmList);
+basic_node!(MatchArm);
+basic_node!(WhileExpr);
+basic_node!(LoopExpr);
+basic_node!(ForExpr);
+basic_node!(BreakExpr);
+basic_node!(ContinueExpr);
+basic_node!(LoopLabel);
 
 basic_node!(LetStmt);
 basic_node!(ExprStmt);
@@ -60,6 +70,7 @@ enum_node!(
     ParenExpr,
     FloatExpr,
     IntExpr,
+    BoolExpr,
     BinExpr,
     CallExpr,
     StructExpr,
@@ -71,9 +82,34 @@ enum_node!(
     IfExpr,
     IntrinsicExpr,
     StringExpr,
-    CastExpr
+    CastExpr,
+    MatchExpr,
+    WhileExpr,
+    LoopExpr,
+    ForExpr,
+    BreakExpr,
+    ContinueExpr
 );
 
+enum_node!(
+    Pat: WildcardPat,
+    BindPat,
+    LiteralPat,
+    VariantPat,
+    TuplePat,
+    ParenPat,
+    StructPat
+);
+basic_node!(WildcardPat);
+basic_node!(BindPat);
+basic_node!(LiteralPat);
+basic_node!(VariantPat);
+basic_node!(TuplePat);
+basic_node!(ParenPat);
+basic_node!(StructPat);
+basic_node!(StructPatFieldList);
+basic_node!(StructPatField);
+
 enum_node!(Type: PathType, ThisPathType, TupleType, ArrayType, PtrType, RefType);
 basic_node!(PathType);
 basic_node!(ThisPathType);
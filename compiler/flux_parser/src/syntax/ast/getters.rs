@@ -69,6 +69,9 @@ getters! {
     FloatExpr {
         v -> tok(FloatLit);
     }
+    BoolExpr {
+        v -> tok_matches(True, False);
+    }
     PathExpr {
         path -> node(Path);
     }
@@ -87,12 +90,19 @@ getters! {
         lparen -> tok(LParen);
         rparen -> tok(RParen);
     }
+    ParenExpr {
+        expr -> node(Expr);
+    }
     AddressExpr {
         of -> node(Expr);
     }
     TupleExpr {
         vals -> nodes(Expr);
     }
+    IdxExpr {
+        val -> nth_node(Expr, 0);
+        idx -> nth_node(Expr, 1);
+    }
     CastExpr {
         val -> node(Expr);
         to_ty -> node(Type);
@@ -137,6 +147,71 @@ getters! {
         name -> node(Name);
         val -> node(Expr);
     }
+    MatchExpr {
+        scrutinee -> node(Expr);
+        arm_list -> node(MatchArmList);
+    }
+    MatchArmList {
+        arms -> nodes(MatchArm);
+    }
+    MatchArm {
+        pat -> node(Pat);
+        body -> node(Expr);
+    }
+    WhileExpr {
+        label -> node(LoopLabel);
+        condition -> nth_node(Expr, 0);
+        block -> nth_node(BlockExpr, 0);
+    }
+    LoopExpr {
+        label -> node(LoopLabel);
+        block -> node(BlockExpr);
+    }
+    ForExpr {
+        label -> node(LoopLabel);
+        pat -> node(Pat);
+        iterable -> nth_node(Expr, 0);
+        block -> nth_node(BlockExpr, 0);
+    }
+    BreakExpr {
+        label -> tok(Label);
+        value -> node(Expr);
+    }
+    ContinueExpr {
+        label -> tok(Label);
+    }
+    LoopLabel {
+        name -> tok(Label);
+    }
+
+    BindPat {
+        name -> node(Name);
+    }
+    LiteralPat {
+        v -> tok_matches(IntLit, True, False);
+    }
+    VariantPat {
+        path -> node(Path);
+        pats -> nodes(Pat);
+        lparen -> tok(LParen);
+    }
+    TuplePat {
+        pats -> nodes(Pat);
+    }
+    ParenPat {
+        pat -> node(Pat);
+    }
+    StructPat {
+        path -> node(Path);
+        field_list -> node(StructPatFieldList);
+    }
+    StructPatFieldList {
+        fields -> nodes(StructPatField);
+    }
+    StructPatField {
+        name -> node(Name);
+        pat -> node(Pat);
+    }
 
     LetStmt {
         name -> node(Name);
@@ -42,6 +42,7 @@ pub enum SyntaxKind {
     BinExpr,
     IntExpr,
     FloatExpr,
+    BoolExpr,
     StringExpr,
     BlockExpr,
     AddressExpr,
@@ -59,6 +60,25 @@ pub enum SyntaxKind {
     ElseIfBlock,
     ElseBlock,
     IntrinsicExpr,
+    MatchExpr,
+    MatchArmList,
+    MatchArm,
+    WhileExpr,
+    LoopExpr,
+    ForExpr,
+    BreakExpr,
+    ContinueExpr,
+    LoopLabel,
+
+    WildcardPat,
+    BindPat,
+    LiteralPat,
+    VariantPat,
+    TuplePat,
+    ParenPat,
+    StructPat,
+    StructPatFieldList,
+    StructPatField,
 
     ExprStmt,
     TerminatorExprStmt,
@@ -131,6 +151,15 @@ pub enum SyntaxKind {
     In,
     Enum,
     As,
+    Match,
+    True,
+    False,
+    While,
+    Loop,
+    Break,
+    Continue,
+    Label,
+    Underscore,
     Name,
     Poisoned,
     LetStmt,
@@ -202,6 +231,14 @@ impl Syntax for Flux {
             SyntaxKind::In => Some("in"),
             SyntaxKind::Enum => Some("enum"),
             SyntaxKind::As => Some("as"),
+            SyntaxKind::Match => Some("match"),
+            SyntaxKind::True => Some("true"),
+            SyntaxKind::False => Some("false"),
+            SyntaxKind::While => Some("while"),
+            SyntaxKind::Loop => Some("loop"),
+            SyntaxKind::Break => Some("break"),
+            SyntaxKind::Continue => Some("continue"),
+            SyntaxKind::Underscore => Some("_"),
             _ => None,
         }
     }
@@ -234,6 +271,15 @@ impl From<TokenKind> for SyntaxKind {
             TokenKind::Trait => SyntaxKind::Trait,
             TokenKind::Let => SyntaxKind::Let,
             TokenKind::As => SyntaxKind::As,
+            TokenKind::Match => SyntaxKind::Match,
+            TokenKind::True => SyntaxKind::True,
+            TokenKind::False => SyntaxKind::False,
+            TokenKind::While => SyntaxKind::While,
+            TokenKind::Loop => SyntaxKind::Loop,
+            TokenKind::Break => SyntaxKind::Break,
+            TokenKind::Continue => SyntaxKind::Continue,
+            TokenKind::Label => SyntaxKind::Label,
+            TokenKind::Underscore => SyntaxKind::Underscore,
             TokenKind::Return => SyntaxKind::Return,
             TokenKind::Comma => SyntaxKind::Comma,
             TokenKind::CmpEq => SyntaxKind::CmpEq,
@@ -1,7 +1,7 @@
 use proc_macro::TokenStream;
 use quote::{format_ident, quote};
 
-use self::ast::{DiagnosticEnum, ErrorAttribute, FieldAttribute};
+use self::ast::{DiagnosticEnum, ErrorAttribute, FieldAttribute, Severity};
 
 pub(super) mod ast;
 mod parse;
@@ -16,6 +16,8 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
     let mut labels = vec![];
     let mut extra_labels = vec![];
     let mut helps = vec![];
+    let mut suggestions = vec![];
+    let mut constructors = vec![];
 
     let mut i = 0;
     input.variants.iter().for_each(|variant| {
@@ -23,6 +25,10 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
         variants.push(quote! {
             #variant_name
         });
+        constructors.push(match variant.severity {
+            Severity::Error => format_ident!("error"),
+            Severity::Warning => format_ident!("warning"),
+        });
 
         let mut variant_fields = vec![];
         let mut variant_field_names = vec![];
@@ -55,6 +61,7 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
 
         let mut variant_labels = vec![];
         let mut variant_extra_labels = vec![];
+        let mut variant_suggestions = vec![];
 
         helps.push(quote!(None));
 
@@ -107,6 +114,28 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
                     }
                     // let field_file_span = format_ident!("{}_file_span", field);
                 }
+                ErrorAttribute::Suggestion(suggestion) => {
+                    let field_file_span = format_ident!("{}_file_span", suggestion.field);
+                    let replacement = &suggestion.replacement;
+                    let msg = &suggestion.msg;
+                    let applicability = match suggestion.applicability.to_string().as_str() {
+                        "machine_applicable" => format_ident!("MachineApplicable"),
+                        "maybe_incorrect" => format_ident!("MaybeIncorrect"),
+                        _ => format_ident!("HasPlaceholders"),
+                    };
+                    let exprs = suggestion.exprs.iter().flatten();
+                    variant_suggestions.push(quote! {
+                        let diagnostic = match #replacement {
+                            Some(#replacement) => diagnostic.with_suggestion(flux_diagnostics::Suggestion::new(
+                                #field_file_span.to_file_span(),
+                                #replacement.to_string(),
+                                format!(#msg, #(#exprs),*),
+                                flux_diagnostics::Applicability::#applicability,
+                            )),
+                            None => diagnostic,
+                        };
+                    });
+                }
                 ErrorAttribute::Help(help) => {
                     let msg = &help.msg;
                     if let Some(exprs) = &help.exprs {
@@ -124,6 +153,7 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
 
         labels.push(variant_labels);
         extra_labels.push(variant_extra_labels);
+        suggestions.push(variant_suggestions);
         i += 1;
     });
 
@@ -151,12 +181,14 @@ pub(super) fn impl_to_diagnostic_enum(input: &DiagnosticEnum) -> TokenStream {
                                 #(#labels),*
                             ];
                             #(#extra_labels)*
-                            flux_diagnostics::Diagnostic::error(
+                            let diagnostic = flux_diagnostics::Diagnostic::#constructors(
                                 #locations,
                                 flux_diagnostics::DiagnosticCode::#variants,
                                 #primaries.to_string(),
                                 labels,
-                            ).opt_with_help(#helps)
+                            ).opt_with_help(#helps);
+                            #(#suggestions)*
+                            diagnostic
                         }
                     )*
                 }
@@ -24,7 +24,17 @@ pub(super) struct Help {
     pub(super) exprs: Option<Punctuated<Expr, Token![,]>>,
 }
 
+/// A suggested replacement for the text at `field`, made when the `Option<String>` field `replacement` holds one
+pub(super) struct Suggestion {
+    pub(super) applicability: Ident,
+    pub(super) field: Ident,
+    pub(super) replacement: Ident,
+    pub(super) msg: LitStr,
+    pub(super) exprs: Option<Punctuated<Expr, Token![,]>>,
+}
+
 pub(super) struct Variant {
+    pub(super) severity: Severity,
     pub(super) error_attributes: Punctuated<ErrorAttribute, Token![,]>,
     pub(super) name: Ident,
     pub(super) fields: Punctuated<Field, Token![,]>,
@@ -36,6 +46,12 @@ pub(super) struct Field {
     pub(super) ty: Type,
 }
 
+/// Whether a variant was declared with `#[error(...)]` or `#[warning(...)]`
+pub(super) enum Severity {
+    Error,
+    Warning,
+}
+
 pub(super) enum FieldAttribute {
     None,
     FileSpanned,
@@ -47,6 +63,7 @@ pub(super) enum ErrorAttribute {
     Label(Label),
     Labels(Labels),
     Help(Help),
+    Suggestion(Suggestion),
 }
 
 pub(super) struct Location {
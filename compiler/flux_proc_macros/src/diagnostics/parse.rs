@@ -7,18 +7,21 @@ use syn::{
 };
 
 use crate::diagnostics::ast::{
-    DiagnosticEnum, ErrorAttribute, Field, FieldAttribute, Help, Label, Labels, Location, Variant,
+    DiagnosticEnum, ErrorAttribute, Field, FieldAttribute, Help, Label, Labels, Location, Severity,
+    Suggestion, Variant,
 };
 
 mod kw {
     use syn::custom_keyword;
 
     custom_keyword!(error);
+    custom_keyword!(warning);
     custom_keyword!(location);
     custom_keyword!(primary);
     custom_keyword!(label);
     custom_keyword!(labels);
     custom_keyword!(help);
+    custom_keyword!(suggestion);
 
     custom_keyword!(with);
     custom_keyword!(at);
@@ -48,7 +51,13 @@ impl Variant {
         let content1;
         let _hashtag: Token![#] = input.parse()?;
         let _lbracket = bracketed!(content in input);
-        let _error: kw::error = content.parse()?;
+        let severity = if content.peek(kw::warning) {
+            let _warning: kw::warning = content.parse()?;
+            Severity::Warning
+        } else {
+            let _error: kw::error = content.parse()?;
+            Severity::Error
+        };
         let _lparen = parenthesized!(content1 in content);
         let error_attributes: Punctuated<ErrorAttribute, Token![,]> =
             content1.parse_terminated(ErrorAttribute::parse)?;
@@ -57,6 +66,7 @@ impl Variant {
         let _brace = braced!(content in input);
         let fields: Punctuated<Field, Token![,]> = content.parse_terminated(Field::parse)?;
         Ok(Variant {
+            severity,
             error_attributes,
             name,
             fields,
@@ -79,6 +89,8 @@ impl ErrorAttribute {
             input.parse().map(ErrorAttribute::Labels)
         } else if lookahead.peek(kw::help) {
             input.parse().map(ErrorAttribute::Help)
+        } else if lookahead.peek(kw::suggestion) {
+            input.parse().map(ErrorAttribute::Suggestion)
         } else {
             Err(lookahead.error())
         }
@@ -149,6 +161,44 @@ impl Parse for Help {
     }
 }
 
+impl Parse for Suggestion {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let _suggestion: kw::suggestion = input.parse()?;
+        let content;
+        let _paren = parenthesized!(content in input);
+        let applicability: Ident = content.parse()?;
+        if !["machine_applicable", "maybe_incorrect", "has_placeholders"]
+            .contains(&applicability.to_string().as_str())
+        {
+            return Err(syn::Error::new(
+                applicability.span(),
+                "expected `machine_applicable`, `maybe_incorrect` or `has_placeholders`",
+            ));
+        }
+        let _at: kw::at = input.parse()?;
+        let field = input.parse()?;
+        let _arrow: Token![=>] = input.parse()?;
+        let replacement = input.parse()?;
+        let _eq: Token![=] = input.parse()?;
+        let msg: LitStr = input.parse()?;
+        let exprs = if input.peek(kw::with) {
+            let _with: kw::with = input.parse()?;
+            let content;
+            let _paren = parenthesized!(content in input);
+            Some(content.parse_terminated(Expr::parse)?)
+        } else {
+            None
+        };
+        Ok(Suggestion {
+            applicability,
+            field,
+            replacement,
+            msg,
+            exprs,
+        })
+    }
+}
+
 impl Field {
     fn parse(input: ParseStream) -> Result<Self> {
         let attr = if input.peek(Token![#]) {
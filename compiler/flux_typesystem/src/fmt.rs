@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
 use flux_id::id;
+use flux_util::Interner;
 
 use crate::{
     r#type::Restriction, ConcreteKind, TEnv, TraitApplication, TraitRestriction, TypeKind,
@@ -98,66 +99,11 @@ impl<'a> TEnv<'a> {
     // }
 
     pub fn fmt_typekind(&self, ty: &TypeKind) -> String {
-        use crate::TypeKind::*;
-        match ty {
-            ThisPath(this_path) => std::iter::once("This")
-                .chain(this_path.path.iter().map(|key| self.interner.resolve(key)))
-                .collect::<Vec<_>>()
-                .join("::"),
-            Concrete(concrete_kind) => self.fmt_concrete_kind(concrete_kind),
-            Ref(tid) => self.fmt_tid(*tid),
-            Int => format!("int"),
-            Float => format!("float"),
-            Generic(name, _) => format!("{}", self.interner.resolve(&name)),
-            Never => format!("!"),
-            Unknown => format!("unknown"),
-        }
+        fmt_typekind(ty, self.interner, &|tid| self.fmt_tid(tid))
     }
 
     pub fn fmt_concrete_kind(&self, concrete_kind: &ConcreteKind) -> String {
-        use ConcreteKind::*;
-        match concrete_kind {
-            Addr(ty) => format!("{}&", self.fmt_typekind(&ty.kind)),
-            Array(ty, n) => format!("[{}; {n}]", self.fmt_typekind(&ty.kind)),
-            Ptr(ty) => format!("{}*", self.fmt_typekind(&ty.kind)),
-            Path(path) => format!(
-                "{}{}",
-                path.segments
-                    .iter()
-                    .map(|key| self.interner.resolve(key))
-                    .collect::<Vec<_>>()
-                    .join("::"),
-                if path.args.is_empty() {
-                    String::new()
-                } else {
-                    format!(
-                        "<{}>",
-                        path.args
-                            .iter()
-                            .map(|ty| self.fmt_typekind(&ty.kind))
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    )
-                }
-            ),
-            Tuple(tids) => format!(
-                "({})",
-                tids.iter()
-                    .map(|ty| self.fmt_typekind(&ty.kind))
-                    .collect::<Vec<_>>()
-                    .join(", ")
-            ),
-            Fn(signature) => format!(
-                "Fn({}) -> {}",
-                signature
-                    .parameters()
-                    .iter()
-                    .map(|param| self.fmt_typekind(&param.kind))
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                self.fmt_typekind(&signature.return_ty().kind)
-            ),
-        }
+        fmt_concrete_kind(concrete_kind, self.interner, &|tid| self.fmt_tid(tid))
     }
 
     pub fn fmt_trait_restriction(&self, trait_restriction: &TraitRestriction) -> String {
@@ -229,3 +175,83 @@ impl<'a> TEnv<'a> {
         )
     }
 }
+
+impl TypeKind {
+    /// Format a type outside of the type environment it was created in
+    ///
+    /// References to other types can't be followed, so they are printed as their id
+    pub fn to_string(&self, interner: &'static Interner) -> String {
+        fmt_typekind(self, interner, &|tid| format!("'{}", Into::<u32>::into(tid)))
+    }
+}
+
+fn fmt_typekind(
+    ty: &TypeKind,
+    interner: &'static Interner,
+    fmt_ref: &dyn Fn(id::Ty) -> String,
+) -> String {
+    use crate::TypeKind::*;
+    match ty {
+        ThisPath(this_path) => std::iter::once("This")
+            .chain(this_path.path.iter().map(|key| interner.resolve(key)))
+            .collect::<Vec<_>>()
+            .join("::"),
+        Concrete(concrete_kind) => fmt_concrete_kind(concrete_kind, interner, fmt_ref),
+        Ref(tid) => fmt_ref(*tid),
+        Int => format!("int"),
+        Float => format!("float"),
+        Generic(name, _) => format!("{}", interner.resolve(&name)),
+        Never => format!("!"),
+        Unknown => format!("unknown"),
+    }
+}
+
+fn fmt_concrete_kind(
+    concrete_kind: &ConcreteKind,
+    interner: &'static Interner,
+    fmt_ref: &dyn Fn(id::Ty) -> String,
+) -> String {
+    use ConcreteKind::*;
+    match concrete_kind {
+        Addr(ty) => format!("{}&", fmt_typekind(&ty.kind, interner, fmt_ref)),
+        Array(ty, n) => format!("[{}; {n}]", fmt_typekind(&ty.kind, interner, fmt_ref)),
+        Ptr(ty) => format!("{}*", fmt_typekind(&ty.kind, interner, fmt_ref)),
+        Path(path) => format!(
+            "{}{}",
+            path.segments
+                .iter()
+                .map(|key| interner.resolve(key))
+                .collect::<Vec<_>>()
+                .join("::"),
+            if path.args.is_empty() {
+                String::new()
+            } else {
+                format!(
+                    "<{}>",
+                    path.args
+                        .iter()
+                        .map(|ty| fmt_typekind(&ty.kind, interner, fmt_ref))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            }
+        ),
+        Tuple(tids) => format!(
+            "({})",
+            tids.iter()
+                .map(|ty| fmt_typekind(&ty.kind, interner, fmt_ref))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Fn(signature) => format!(
+            "Fn({}) -> {}",
+            signature
+                .parameters()
+                .iter()
+                .map(|param| fmt_typekind(&param.kind, interner, fmt_ref))
+                .collect::<Vec<_>>()
+                .join(", "),
+            fmt_typekind(&signature.return_ty().kind, interner, fmt_ref)
+        ),
+    }
+}
@@ -21,6 +21,7 @@ pub use resolve::TraitResolver;
 pub use tenv::TEnv;
 
 static INT_PATHS: OnceLock<[Path<Word, Type>; 8]> = OnceLock::new();
+static FLOAT_PATHS: OnceLock<[Path<Word, Type>; 2]> = OnceLock::new();
 
 pub fn int_paths(interner: &'static Interner) -> &'static [Path<Word, Type>; 8] {
     INT_PATHS.get_or_init(|| {
@@ -37,3 +38,13 @@ pub fn int_paths(interner: &'static Interner) -> &'static [Path<Word, Type>; 8]
         .map(|name| Path::new(vec![name], vec![]))
     })
 }
+
+pub fn float_paths(interner: &'static Interner) -> &'static [Path<Word, Type>; 2] {
+    FLOAT_PATHS.get_or_init(|| {
+        [
+            interner.get_or_intern_static("f64"),
+            interner.get_or_intern_static("f32"),
+        ]
+        .map(|name| Path::new(vec![name], vec![]))
+    })
+}
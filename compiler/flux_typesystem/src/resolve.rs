@@ -7,7 +7,7 @@ use tracing::{trace, warn};
 use crate::{
     r#trait::TraitApplication,
     r#type::{Restriction, ThisPath},
-    ConcreteKind, TEnv, ThisCtx, Type, TypeKind,
+    ConcreteKind, FnSignature, TEnv, ThisCtx, Type, TypeKind,
 };
 
 impl<'a> TEnv<'a> {
@@ -163,33 +163,30 @@ impl TypeKind {
                 use ConcreteKind::*;
                 match concrete_kind {
                     Array(_, _) => todo!(),
-                    Ptr(ty) => {
-                        ty.kind.resolve(tenv)?;
-                        Ok(self.clone())
-                    }
-                    Addr(ty) => {
-                        ty.kind.resolve(tenv)?;
-                        Ok(self.clone())
-                    }
-                    Path(path) => {
-                        for arg in &path.args {
-                            arg.kind.resolve(tenv)?;
-                        }
-                        Ok(self.clone())
-                    }
-                    Tuple(types) => {
-                        for ty in types {
-                            ty.kind.resolve(tenv)?;
-                        }
-                        Ok(self.clone())
-                    }
-                    Fn(signature) => {
-                        for param in signature.parameters() {
-                            param.kind.resolve(tenv)?;
-                        }
-                        signature.return_ty().kind.resolve(tenv)?;
-                        Ok(self.clone())
-                    }
+                    Ptr(ty) => Ok(Concrete(Ptr(Box::new(ty.resolve(tenv)?)))),
+                    Addr(ty) => Ok(Concrete(Addr(Box::new(ty.resolve(tenv)?)))),
+                    Path(path) => Ok(Concrete(Path(flux_util::Path::new(
+                        path.segments.clone(),
+                        path.args
+                            .iter()
+                            .map(|arg| arg.resolve(tenv))
+                            .collect::<Result<_, _>>()?,
+                    )))),
+                    Tuple(types) => Ok(Concrete(Tuple(
+                        types
+                            .iter()
+                            .map(|ty| ty.resolve(tenv))
+                            .collect::<Result<_, _>>()?,
+                    ))),
+                    Fn(signature) => Ok(Concrete(Fn(FnSignature::from_type_ids(
+                        signature
+                            .parameters()
+                            .iter()
+                            .chain(std::iter::once(signature.return_ty()))
+                            .map(|ty| ty.resolve(tenv))
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into_iter(),
+                    )))),
                 }
             }
             Generic(_, _) => Ok(self.clone()),
@@ -203,13 +200,28 @@ impl TypeKind {
             }
             Ref(tid) => tenv.get(*tid).kind.resolve(tenv),
             Int => Err(()),
-            Float => todo!(),
-            Never => todo!(),
+            // Float literals that nothing else constrains are `f64`s
+            Float => Ok(Concrete(ConcreteKind::Path(flux_util::Path::new(
+                vec![tenv.interner.get_or_intern_static("f64")],
+                vec![],
+            )))),
+            Never => Ok(self.clone()),
             Unknown => Err(()),
         }
     }
 }
 
+impl Type {
+    /// Resolve the kind of this type along with the kinds of any types nested inside of it, so that no references to
+    /// other types are left behind
+    fn resolve(&self, tenv: &TEnv) -> Result<Type, ()> {
+        Ok(Type::new(
+            self.kind.resolve(tenv)?,
+            self.restrictions.clone(),
+        ))
+    }
+}
+
 pub struct TraitResolver {
     pub traits: HashMap<InPkg<id::TraitDecl>, Vec<TraitApplication>>,
     // fields: HashMap<InPkg<id::StructDecl>, Vec<Word>>,
@@ -30,6 +30,10 @@ impl Scope {
             .unwrap_or_else(|| ice("could not get local"))
     }
 
+    pub(super) fn local_names(&self) -> impl Iterator<Item = &Word> {
+        self.map.keys()
+    }
+
     pub(super) fn try_get_local_by_tid(&self, tid: id::Ty) -> Option<Word> {
         self.map
             .iter()
@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use flux_diagnostics::ice;
 use flux_id::{id, Map};
-use flux_util::{Interner, Span, Spanned, WithSpan, Word};
+use flux_util::{Interner, Path, Span, Spanned, WithSpan, Word};
 
 use crate::{
     methods::MethodResolver,
@@ -8,7 +10,7 @@ use crate::{
     r#type::{Restriction, ThisPath, Type},
     resolve::TraitResolver,
     scope::Scope,
-    ConcreteKind, TraitApplication, TraitRestriction, TypeKind,
+    ConcreteKind, FnSignature, TraitApplication, TraitRestriction, TypeKind,
 };
 
 pub struct TEnv<'a> {
@@ -100,6 +102,72 @@ impl<'a> TEnv<'a> {
         ty.push_restriction(Restriction::AssocTypeOf(of, trt, name));
     }
 
+    /// Replace every generic in `signature` with a fresh unknown type
+    ///
+    /// Occurrences of the same generic share the same unknown, so they are inferred together at each use site
+    pub fn instantiate_generics(&mut self, signature: &FnSignature, span: Span) -> FnSignature {
+        let mut instantiated = HashMap::new();
+        let types = signature
+            .parameters()
+            .iter()
+            .chain(std::iter::once(signature.return_ty()))
+            .map(|ty| self.instantiate_type(ty, span, &mut instantiated))
+            .collect::<Vec<_>>();
+        FnSignature::from_type_ids(types.into_iter())
+    }
+
+    /// Replace the generics in `ty` with unknown types, reusing the ones in `instantiated` so that every occurrence of
+    /// a generic is instantiated to the same type
+    pub fn instantiate_type(
+        &mut self,
+        ty: &Type,
+        span: Span,
+        instantiated: &mut HashMap<Word, id::Ty>,
+    ) -> Type {
+        let kind = match &ty.kind {
+            TypeKind::Generic(name, _) => TypeKind::Ref(
+                *instantiated
+                    .entry(*name)
+                    .or_insert_with(|| self.types.insert(Type::unknown().at(span))),
+            ),
+            TypeKind::Concrete(concrete) => TypeKind::Concrete(match concrete {
+                ConcreteKind::Array(ty, n) => {
+                    ConcreteKind::Array(Box::new(self.instantiate_type(ty, span, instantiated)), *n)
+                }
+                ConcreteKind::Ptr(ty) => {
+                    ConcreteKind::Ptr(Box::new(self.instantiate_type(ty, span, instantiated)))
+                }
+                ConcreteKind::Addr(ty) => {
+                    ConcreteKind::Addr(Box::new(self.instantiate_type(ty, span, instantiated)))
+                }
+                ConcreteKind::Path(path) => ConcreteKind::Path(Path::new(
+                    path.segments.clone(),
+                    path.args
+                        .iter()
+                        .map(|arg| self.instantiate_type(arg, span, instantiated))
+                        .collect(),
+                )),
+                ConcreteKind::Tuple(types) => ConcreteKind::Tuple(
+                    types
+                        .iter()
+                        .map(|ty| self.instantiate_type(ty, span, instantiated))
+                        .collect(),
+                ),
+                ConcreteKind::Fn(signature) => ConcreteKind::Fn(FnSignature::from_type_ids(
+                    signature
+                        .parameters()
+                        .iter()
+                        .chain(std::iter::once(signature.return_ty()))
+                        .map(|ty| self.instantiate_type(ty, span, instantiated))
+                        .collect::<Vec<_>>()
+                        .into_iter(),
+                )),
+            }),
+            kind => kind.clone(),
+        };
+        Type::new(kind, ty.restrictions.clone())
+    }
+
     pub fn insert_local(&mut self, name: Word, tid: id::Ty) {
         self.scopes
             .last_mut()
@@ -114,6 +182,14 @@ impl<'a> TEnv<'a> {
             .try_get_local(name)
     }
 
+    /// The names of every local in the current scope
+    pub fn local_names(&self) -> impl Iterator<Item = &Word> {
+        self.scopes
+            .last()
+            .unwrap_or_else(|| ice("there should always be a scope on the stack in `TEnv`"))
+            .local_names()
+    }
+
     pub fn try_get_local_by_tid(&self, tid: id::Ty) -> Option<Word> {
         self.scopes
             .last()
@@ -121,6 +197,24 @@ impl<'a> TEnv<'a> {
             .try_get_local_by_tid(tid)
     }
 
+    /// Enter a scope that starts out with every local in the current one, so that locals inserted while in it are
+    /// forgotten by [`TEnv::pop_scope`]
+    pub fn push_scope(&mut self) {
+        let scope = self
+            .scopes
+            .last()
+            .cloned()
+            .unwrap_or_else(|| ice("there should always be a scope on the stack in `TEnv`"));
+        self.scopes.push(scope);
+    }
+
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() == 1 {
+            ice("popped the last scope in `TEnv`");
+        }
+        self.scopes.pop();
+    }
+
     pub fn get_span(&self, tid: id::Ty) -> Span {
         self.get(tid).span
     }
@@ -188,17 +282,7 @@ impl<'a> TEnv<'a> {
 
             let potential_applications: Vec<_> = applications
                 .iter()
-                .filter(|app| {
-                    let args_match = app
-                        .args
-                        .iter()
-                        .zip(trait_restriction.args.iter())
-                        .filter(|(a, b)| self.types_unify(a, &self.get(**b).kind))
-                        .count()
-                        == trait_restriction.args.len();
-                    self.types_unify(&app.to, to_ty) && args_match
-                })
-                .cloned()
+                .filter_map(|app| self.specialize_application(app, to_ty, &trait_restriction.args))
                 .collect();
             // println!(
             //     "valid apps: {}",
@@ -244,24 +328,135 @@ impl<'a> TEnv<'a> {
         trait_restriction: &TraitRestriction,
     ) -> bool {
         if let Some(applications) = self.trait_resolver.traits.get(&trait_restriction.trait_id) {
-            let potential_applications: Vec<_> = applications
-                .iter()
-                .filter(|app| {
-                    let args_match = app
-                        .args
-                        .iter()
-                        .zip(trait_restriction.args.iter())
-                        .filter(|(a, b)| self.types_unify(a, &self.get(**b).kind))
-                        .count()
-                        == trait_restriction.args.len();
-                    self.types_unify(&app.to, ty) && args_match
-                })
-                .cloned()
-                .collect();
-
-            !potential_applications.is_empty()
+            applications.iter().any(|app| {
+                self.specialize_application(app, ty, &trait_restriction.args)
+                    .is_some()
+            })
         } else {
             false
         }
     }
+
+    /// The application with its generics replaced by the types they stand for when it is applied to `to` with
+    /// `args`, or `None` if it cannot be applied to them
+    ///
+    /// An application like `apply<T> Index<u64> to T*` applies to every pointer, and its associated types refer to the
+    /// type being pointed to.
+    fn specialize_application(
+        &self,
+        app: &TraitApplication,
+        to: &TypeKind,
+        args: &[id::Ty],
+    ) -> Option<TraitApplication> {
+        let mut bindings = HashMap::new();
+        if args.len() > app.args.len() || !self.bind_generics(&app.to, to, &mut bindings) {
+            return None;
+        }
+        for (app_arg, arg) in app.args.iter().zip(args) {
+            if !self.bind_generics(app_arg, &self.get(*arg).kind, &mut bindings) {
+                return None;
+            }
+        }
+
+        if bindings.is_empty() {
+            return Some(app.clone());
+        }
+        Some(TraitApplication::new(
+            substitute_generics(&app.to, &bindings),
+            app.args
+                .iter()
+                .map(|arg| substitute_generics(arg, &bindings))
+                .collect(),
+            app.assoc_types
+                .iter()
+                .map(|(name, ty)| (*name, substitute_generics(ty, &bindings)))
+                .collect(),
+        ))
+    }
+
+    /// Whether `kind` fits `pattern`, binding the generics in `pattern` to the parts of `kind` they line up with
+    fn bind_generics(
+        &self,
+        pattern: &TypeKind,
+        kind: &TypeKind,
+        bindings: &mut HashMap<Word, TypeKind>,
+    ) -> bool {
+        match (pattern, kind) {
+            (_, TypeKind::Ref(tid)) => self.bind_generics(pattern, &self.get(*tid).kind, bindings),
+            (TypeKind::Generic(name, restrictions), kind)
+                if !matches!(kind, TypeKind::Generic(_, _) | TypeKind::Unknown) =>
+            {
+                match bindings.get(name) {
+                    Some(bound) => self.types_unify(bound, kind),
+                    None => {
+                        let implements_restrictions = restrictions
+                            .iter()
+                            .all(|restriction| self.does_type_implement_trait(kind, restriction));
+                        bindings.insert(*name, kind.clone());
+                        implements_restrictions
+                    }
+                }
+            }
+            (TypeKind::Concrete(pattern), TypeKind::Concrete(concrete)) => {
+                match (pattern, concrete) {
+                    (ConcreteKind::Ptr(pattern), ConcreteKind::Ptr(ty))
+                    | (ConcreteKind::Addr(pattern), ConcreteKind::Addr(ty)) => {
+                        self.bind_generics(&pattern.kind, &ty.kind, bindings)
+                    }
+                    (ConcreteKind::Array(pattern, pattern_n), ConcreteKind::Array(ty, n)) => {
+                        pattern_n == n && self.bind_generics(&pattern.kind, &ty.kind, bindings)
+                    }
+                    (ConcreteKind::Path(pattern), ConcreteKind::Path(path)) => {
+                        pattern.segments == path.segments
+                            && pattern.args.iter().zip(&path.args).all(|(pattern, ty)| {
+                                self.bind_generics(&pattern.kind, &ty.kind, bindings)
+                            })
+                    }
+                    (ConcreteKind::Tuple(pattern), ConcreteKind::Tuple(types)) => {
+                        pattern.len() == types.len()
+                            && pattern.iter().zip(types).all(|(pattern, ty)| {
+                                self.bind_generics(&pattern.kind, &ty.kind, bindings)
+                            })
+                    }
+                    _ => self.types_unify(&TypeKind::Concrete(pattern.clone()), kind),
+                }
+            }
+            _ => self.types_unify(pattern, kind),
+        }
+    }
+}
+
+/// Replace the generics in `kind` with the types they are bound to in `bindings`
+fn substitute_generics(kind: &TypeKind, bindings: &HashMap<Word, TypeKind>) -> TypeKind {
+    let substitute = |ty: &Type| {
+        Type::new(
+            substitute_generics(&ty.kind, bindings),
+            ty.restrictions.clone(),
+        )
+    };
+    match kind {
+        TypeKind::Generic(name, _) => bindings.get(name).cloned().unwrap_or_else(|| kind.clone()),
+        TypeKind::Concrete(concrete) => TypeKind::Concrete(match concrete {
+            ConcreteKind::Array(ty, n) => ConcreteKind::Array(Box::new(substitute(ty)), *n),
+            ConcreteKind::Ptr(ty) => ConcreteKind::Ptr(Box::new(substitute(ty))),
+            ConcreteKind::Addr(ty) => ConcreteKind::Addr(Box::new(substitute(ty))),
+            ConcreteKind::Path(path) => ConcreteKind::Path(Path::new(
+                path.segments.clone(),
+                path.args.iter().map(substitute).collect(),
+            )),
+            ConcreteKind::Tuple(types) => {
+                ConcreteKind::Tuple(types.iter().map(substitute).collect())
+            }
+            ConcreteKind::Fn(signature) => ConcreteKind::Fn(FnSignature::from_type_ids(
+                signature
+                    .parameters()
+                    .iter()
+                    .chain(std::iter::once(signature.return_ty()))
+                    .map(substitute)
+                    .collect::<Vec<_>>()
+                    .into_iter(),
+            )),
+        }),
+        kind => kind.clone(),
+    }
 }
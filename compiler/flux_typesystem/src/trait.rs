@@ -1,16 +1,22 @@
 use std::collections::HashMap;
 
 use flux_id::id::{self, InPkg};
-use flux_util::Word;
+use flux_util::{words::AsText, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::TypeKind;
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum ThisCtx {
     Function,
     TraitDecl,
     TypeApplication(Box<TypeKind>),
-    TraitApplication(Box<TypeKind>, Vec<(Word, TypeKind)>),
+    TraitApplication(
+        Box<TypeKind>,
+        #[serde_as(as = "Vec<(AsText, _)>")] Vec<(Word, TypeKind)>,
+    ),
 }
 
 #[derive(Clone, PartialEq, Eq, Debug)]
@@ -1,11 +1,13 @@
 use std::{collections::HashSet, ops::Deref};
 
 use flux_id::id::{self, InPkg};
-use flux_util::{Path, Word};
+use flux_util::{words::AsText, Path, Word};
+use serde::{Deserialize, Serialize};
+use serde_with::serde_as;
 
 use crate::ThisCtx;
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct Type {
     pub kind: TypeKind,
     pub restrictions: Vec<Restriction>,
@@ -104,6 +106,13 @@ impl Type {
         }
     }
 
+    pub const fn float() -> Self {
+        Self {
+            kind: TypeKind::Float,
+            restrictions: vec![],
+        }
+    }
+
     pub fn with_trait_restrictions(self, restrictions: Vec<TraitRestriction>) -> Self {
         Self {
             kind: self.kind,
@@ -160,10 +169,11 @@ impl Type {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum TypeKind {
     Concrete(ConcreteKind),
-    Generic(Word, Vec<TraitRestriction>),
+    Generic(#[serde_as(as = "AsText")] Word, Vec<TraitRestriction>),
     ThisPath(ThisPath), // `This` or associated type like `This::Foo`
     Ref(id::Ty),
     Int,
@@ -172,7 +182,7 @@ pub enum TypeKind {
     Unknown,
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct ThisPath {
     pub path: Path<Word, Type>,
     pub potential_this_ctx: Vec<ThisCtx>,
@@ -187,7 +197,7 @@ impl ThisPath {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum ConcreteKind {
     Array(Box<Type>, u64),
     Ptr(Box<Type>),
@@ -197,17 +207,18 @@ pub enum ConcreteKind {
     Fn(FnSignature),
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[serde_as]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub enum Restriction {
     Equals(id::Ty),
     EqualsOneOf(Vec<TypeKind>),
-    AssocTypeOf(id::Ty, TraitRestriction, Word),
+    AssocTypeOf(id::Ty, TraitRestriction, #[serde_as(as = "AsText")] Word),
     // PossibleAssocTypes(Vec<TypeKind>),
-    Field(Word),
+    Field(#[serde_as(as = "AsText")] Word),
     Trait(TraitRestriction),
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct TraitRestriction {
     pub(super) trait_id: InPkg<id::TraitDecl>,
     pub(super) args: Vec<id::Ty>,
@@ -219,7 +230,7 @@ impl TraitRestriction {
     }
 }
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct Typed<T> {
     pub tid: id::Ty,
     pub inner: T,
@@ -244,7 +255,7 @@ pub trait WithType {
 
 impl WithType for id::Expr {}
 
-#[derive(Clone, PartialEq, Eq, Debug, Hash)]
+#[derive(Clone, PartialEq, Eq, Debug, Hash, Serialize, Deserialize)]
 pub struct FnSignature(Vec<Type>);
 
 impl FnSignature {
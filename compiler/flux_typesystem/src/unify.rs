@@ -4,7 +4,7 @@ use flux_diagnostics::{Diagnostic, ToDiagnostic};
 use flux_id::id;
 use flux_util::{InFile, Span, WithSpan};
 
-use crate::{diagnostics::TypeError, int_paths, ConcreteKind, TEnv, Type, TypeKind};
+use crate::{diagnostics::TypeError, float_paths, int_paths, ConcreteKind, TEnv, Type, TypeKind};
 
 impl<'a> TEnv<'a> {
     pub fn unify(
@@ -29,6 +29,18 @@ impl<'a> TEnv<'a> {
                     .set_with(b, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(a))));
                 Ok(())
             }
+            // Expressions that never produce a value, such as `break`, can stand in for a value of any type
+            (Never, Never) => Ok(()),
+            (Never, _) => {
+                self.types
+                    .set_with(a, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(b))));
+                Ok(())
+            }
+            (_, Never) => {
+                self.types
+                    .set_with(b, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(a))));
+                Ok(())
+            }
             (ThisPath(this_path), _) => {
                 let a_ty = self.get(a);
                 let potential_this = self.resolve_this_path(this_path);
@@ -127,6 +139,21 @@ impl<'a> TEnv<'a> {
                     .set_with(a, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(b))));
                 Ok(())
             }
+            (Float, Float) => Ok(()),
+            (Concrete(ConcreteKind::Path(path)), Float)
+                if path.is_in(float_paths(self.interner)) =>
+            {
+                self.types
+                    .set_with(b, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(a))));
+                Ok(())
+            }
+            (Float, Concrete(ConcreteKind::Path(path)))
+                if path.is_in(float_paths(self.interner)) =>
+            {
+                self.types
+                    .set_with(a, |old_ty| old_ty.map(|ty| ty.set_kind(Ref(b))));
+                Ok(())
+            }
             (Generic(_, a_restrictions), Generic(_, b_restrictions)) => {
                 let b_restrictions = b_restrictions.clone();
                 for a_restriction in a_restrictions.clone() {
@@ -151,6 +178,14 @@ impl<'a> TEnv<'a> {
             (_, Unknown) | (Unknown, _) => false,
             (Ref(a), _) => self.types_unify(&self.get(*a).kind, b),
             (_, Ref(b)) => self.types_unify(a, &self.get(*b).kind),
+            (ThisPath(this_path), _) => match self.resolve_this_path(this_path)[..] {
+                [this] => self.types_unify(this, b),
+                _ => false,
+            },
+            (_, ThisPath(this_path)) => match self.resolve_this_path(this_path)[..] {
+                [this] => self.types_unify(a, this),
+                _ => false,
+            },
             (Concrete(a_concrete), Concrete(b_concrete)) => {
                 self.concretes_unify(a_concrete, b_concrete)
             }
@@ -160,6 +195,12 @@ impl<'a> TEnv<'a> {
             {
                 true
             }
+            (Concrete(ConcreteKind::Path(path)), Float)
+            | (Float, Concrete(ConcreteKind::Path(path)))
+                if path.is_in(float_paths(self.interner)) =>
+            {
+                true
+            }
             (Generic(_, a_restrictions), Generic(_, b_restrictions)) => {
                 let b_restrictions = b_restrictions.clone();
                 for a_restriction in a_restrictions.clone() {
@@ -2,6 +2,7 @@ mod build;
 mod input_file;
 pub mod path;
 mod span;
+pub mod words;
 
 pub use build::*;
 use cstree::interning::TokenKey;
@@ -1,8 +1,9 @@
 use std::ops::{Deref, DerefMut};
 
+use serde::{Deserialize, Serialize};
 use text_size::{TextRange, TextSize};
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct Span {
     pub range: TextRange,
 }
@@ -35,7 +36,7 @@ impl Span {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Spanned<T> {
     pub inner: T,
     pub span: Span,
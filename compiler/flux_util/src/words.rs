@@ -0,0 +1,152 @@
+//! Serializing interned words, for the artifacts kept in the build cache
+//!
+//! A word is only meaningful to the interner it was interned in, so words are written out as text and interned again
+//! in whichever interner they are read with, which is the one set by [`with_interner`]. Fields that hold words are
+//! serialized [`AsText`] with `#[serde_as]`, while paths and file ids always are.
+
+use std::{cell::Cell, convert::Infallible};
+
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_with::{de::DeserializeAsWrap, ser::SerializeAsWrap, DeserializeAs, SerializeAs};
+
+use crate::{FileId, Interner, Path, Spanned, Word};
+
+thread_local! {
+    static INTERNER: Cell<Option<&'static Interner>> = const { Cell::new(None) };
+}
+
+const NO_INTERNER: &str = "words can only be serialized within `with_interner`";
+
+/// Serialize and deserialize words with `interner` while `f` runs
+pub fn with_interner<R>(interner: &'static Interner, f: impl FnOnce() -> R) -> R {
+    let previous = INTERNER.replace(Some(interner));
+    let result = f();
+    INTERNER.set(previous);
+    result
+}
+
+fn interner() -> Option<&'static Interner> {
+    INTERNER.get()
+}
+
+/// Serializes a word as its text
+pub struct AsText;
+
+impl SerializeAs<Word> for AsText {
+    fn serialize_as<S: Serializer>(word: &Word, serializer: S) -> Result<S::Ok, S::Error> {
+        let interner = interner().ok_or_else(|| S::Error::custom(NO_INTERNER))?;
+        serializer.serialize_str(interner.resolve(word))
+    }
+}
+
+impl<'de> DeserializeAs<'de, Word> for AsText {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Word, D::Error> {
+        let interner = interner().ok_or_else(|| D::Error::custom(NO_INTERNER))?;
+        let text = String::deserialize(deserializer)?;
+        Ok(interner.get_or_intern(text))
+    }
+}
+
+/// Paths without generic arguments, like those of `use` declarations, are only their segments
+impl SerializeAs<Path<Word, Infallible>> for AsText {
+    fn serialize_as<S: Serializer>(
+        path: &Path<Word, Infallible>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        SerializeAsWrap::<_, Vec<AsText>>::new(&path.segments).serialize(serializer)
+    }
+}
+
+impl<'de> DeserializeAs<'de, Path<Word, Infallible>> for AsText {
+    fn deserialize_as<D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Path<Word, Infallible>, D::Error> {
+        let segments = DeserializeAsWrap::<_, Vec<AsText>>::deserialize(deserializer)?;
+        Ok(Path::new(segments.into_inner(), vec![]))
+    }
+}
+
+impl<T, U: SerializeAs<T>> SerializeAs<Spanned<T>> for Spanned<U> {
+    fn serialize_as<S: Serializer>(spanned: &Spanned<T>, serializer: S) -> Result<S::Ok, S::Error> {
+        (SerializeAsWrap::<T, U>::new(&spanned.inner), spanned.span).serialize(serializer)
+    }
+}
+
+impl<'de, T, U: DeserializeAs<'de, T>> DeserializeAs<'de, Spanned<T>> for Spanned<U> {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Spanned<T>, D::Error> {
+        let (inner, span) = <(DeserializeAsWrap<T, U>, _)>::deserialize(deserializer)?;
+        Ok(Spanned::new(inner.into_inner(), span))
+    }
+}
+
+impl<A: Serialize> Serialize for Path<Word, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let segments = SerializeAsWrap::<_, Vec<AsText>>::new(&self.segments);
+        (segments, &self.args).serialize(serializer)
+    }
+}
+
+impl<'de, A: Deserialize<'de>> Deserialize<'de> for Path<Word, A> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (segments, args) = <(DeserializeAsWrap<_, Vec<AsText>>, _)>::deserialize(deserializer)?;
+        Ok(Path::new(segments.into_inner(), args))
+    }
+}
+
+impl Serialize for FileId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        AsText::serialize_as(self.key(), serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FileId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        AsText::deserialize_as(deserializer).map(FileId::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_with::serde_as;
+
+    use super::*;
+    use crate::Span;
+
+    #[serde_as]
+    #[derive(Serialize, Deserialize)]
+    struct Words {
+        #[serde_as(as = "Vec<Spanned<AsText>>")]
+        words: Vec<Spanned<Word>>,
+        path: Path<Word, u64>,
+    }
+
+    #[test]
+    fn words_are_interned_again() {
+        let from = Box::leak(Box::new(Interner::new()));
+        let to = Box::leak(Box::new(Interner::new()));
+        to.get_or_intern("padding");
+
+        let word = from.get_or_intern("word");
+        let value = Words {
+            words: vec![
+                Spanned::new(word, Span::new(3..7)),
+                Spanned::new(word, Span::new(9..13)),
+            ],
+            path: Path::new(vec![word], vec![u64::MAX]),
+        };
+        let bytes = with_interner(from, || bincode::serialize(&value)).unwrap();
+
+        let words: Words = with_interner(to, || bincode::deserialize(&bytes)).unwrap();
+        assert_eq!(words.words[1].span, Span::new(9..13));
+        assert_eq!(words.path.args, [u64::MAX]);
+        assert!(words
+            .words
+            .iter()
+            .all(|word| to.resolve(&word.inner) == "word"));
+        assert_eq!(to.resolve(&words.path.segments[0]), "word");
+
+        assert!(bincode::deserialize::<Words>(&bytes).is_err());
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(with_interner(to, || bincode::deserialize::<Words>(truncated)).is_err());
+    }
+}